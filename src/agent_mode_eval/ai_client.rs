@@ -4,7 +4,7 @@ use tokio_stream::{Stream, StreamExt};
 use reqwest::Client;
 use futures::stream::BoxStream;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AiProvider {
     OpenAI,
     Claude,
@@ -14,6 +14,37 @@ pub enum AiProvider {
     Gemini,
 }
 
+impl AiProvider {
+    /// All providers, in the order the settings list them
+    pub const ALL: [AiProvider; 6] = [
+        AiProvider::OpenAI,
+        AiProvider::Claude,
+        AiProvider::Gemini,
+        AiProvider::Groq,
+        AiProvider::Ollama,
+        AiProvider::Local,
+    ];
+
+    /// Whether requests to this provider carry tool definitions, so the
+    /// agent can run commands and read files
+    pub fn supports_tools(&self) -> bool {
+        matches!(self, AiProvider::OpenAI | AiProvider::Claude)
+    }
+}
+
+impl std::fmt::Display for AiProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AiProvider::OpenAI => "OpenAI",
+            AiProvider::Claude => "Anthropic",
+            AiProvider::Gemini => "Gemini",
+            AiProvider::Groq => "Groq",
+            AiProvider::Ollama => "Ollama",
+            AiProvider::Local => "Local",
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AiClient {
     pub config: super::AgentConfig,
@@ -25,6 +56,9 @@ pub struct AiMessage {
     pub role: String,
     pub content: String,
     pub tool_calls: Option<Vec<super::tools::ToolCall>>,
+    /// For `tool` messages, the call they answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,12 +74,19 @@ pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Counted locally because the provider didn't report usage
+    #[serde(default)]
+    pub estimated: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct StreamingResponse {
     pub content: String,
     pub is_complete: bool,
+    /// Usage reported for the whole reply, on the chunk that completes it
+    pub usage: Option<Usage>,
+    /// Tools the model asked to call, on the chunk that completes the reply
+    pub tool_calls: Option<Vec<super::tools::ToolCall>>,
 }
 
 impl AiClient {
@@ -53,9 +94,7 @@ impl AiClient {
         // Validate model for provider
         Self::validate_model_for_provider(&config.provider, &config.model)?;
         
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(60))
-            .build()
+        let client = crate::net::client(Some(std::time::Duration::from_secs(60)))
             .map_err(|e| AiClientError::HttpError(e.to_string()))?;
 
         Ok(Self { config, client })
@@ -76,15 +115,12 @@ impl AiClient {
                 "gemini-2.0-flash-exp", "gemini-2.0-pro-exp",
                 "gemini-1.5-pro", "gemini-1.5-flash"
             ],
-            AiProvider::Ollama => vec![
-                "llama3.2", "llama3.1", "codellama", "mistral", 
-                "phi3", "qwen2.5", "deepseek-coder"
-            ],
             AiProvider::Groq => vec![
                 "llama-3.1-70b-versatile", "llama-3.1-8b-instant",
                 "mixtral-8x7b-32768", "gemma2-9b-it"
             ],
-            AiProvider::Local => return Ok(()), // Local models can be anything
+            // Ollama serves whatever has been pulled, e.g. "llama3:8b"
+            AiProvider::Ollama | AiProvider::Local => return Ok(()), // Local models can be anything
         };
 
         if !valid_models.contains(&model) {
@@ -127,7 +163,7 @@ impl AiClient {
 
         let mut request_body = serde_json::json!({
             "model": self.config.model,
-            "messages": messages,
+            "messages": openai_messages(messages),
             "temperature": self.config.temperature,
             "stream": false
         });
@@ -137,7 +173,7 @@ impl AiClient {
         }
 
         if let Some(tools) = tools {
-            request_body["tools"] = serde_json::to_value(tools)?;
+            request_body["tools"] = serde_json::Value::Array(tools.iter().map(openai_tool).collect());
         }
 
         let response = self.client
@@ -147,7 +183,7 @@ impl AiClient {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| AiClientError::HttpError(e.to_string()))?;
+            .map_err(|e| AiClientError::HttpError(crate::net::describe_error(&e)))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -186,7 +222,7 @@ impl AiClient {
         }
 
         if let Some(tools) = tools {
-            request_body["tools"] = serde_json::to_value(tools)?;
+            request_body["tools"] = serde_json::Value::Array(tools.iter().map(claude_tool).collect());
         }
 
         let response = self.client
@@ -197,7 +233,7 @@ impl AiClient {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| AiClientError::HttpError(e.to_string()))?;
+            .map_err(|e| AiClientError::HttpError(crate::net::describe_error(&e)))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -231,7 +267,7 @@ impl AiClient {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| AiClientError::HttpError(e.to_string()))?;
+            .map_err(|e| AiClientError::HttpError(crate::net::describe_error(&e)))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -261,7 +297,7 @@ impl AiClient {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| AiClientError::HttpError(e.to_string()))?;
+            .map_err(|e| AiClientError::HttpError(crate::net::describe_error(&e)))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -308,7 +344,7 @@ impl AiClient {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| AiClientError::HttpError(e.to_string()))?;
+            .map_err(|e| AiClientError::HttpError(crate::net::describe_error(&e)))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -343,7 +379,7 @@ impl AiClient {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| AiClientError::HttpError(e.to_string()))?;
+            .map_err(|e| AiClientError::HttpError(crate::net::describe_error(&e)))?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -353,11 +389,25 @@ impl AiClient {
         let response_json: serde_json::Value = response.json().await
             .map_err(|e| AiClientError::ParseError(e.to_string()))?;
 
+        // Ollama counts the prompt and the generated tokens as eval counts
+        let usage = match (response_json["prompt_eval_count"].as_u64(), response_json["eval_count"].as_u64()) {
+            (None, None) => None,
+            (prompt, completion) => {
+                let (prompt, completion) = (prompt.unwrap_or(0) as u32, completion.unwrap_or(0) as u32);
+                Some(Usage {
+                    prompt_tokens: prompt,
+                    completion_tokens: completion,
+                    total_tokens: prompt + completion,
+                    estimated: false,
+                })
+            }
+        };
+
         Ok(AiResponse {
             content: response_json["message"]["content"].as_str().unwrap_or("").to_string(),
             tool_calls: None,
             finish_reason: Some("stop".to_string()),
-            usage: None,
+            usage,
         })
     }
 
@@ -368,6 +418,8 @@ impl AiClient {
         let stream = tokio_stream::once(Ok(StreamingResponse {
             content: response.content,
             is_complete: true,
+            usage: response.usage,
+            tool_calls: response.tool_calls,
         }));
         Ok(Box::pin(stream))
     }
@@ -378,6 +430,8 @@ impl AiClient {
         let stream = tokio_stream::once(Ok(StreamingResponse {
             content: response.content,
             is_complete: true,
+            usage: response.usage,
+            tool_calls: response.tool_calls,
         }));
         Ok(Box::pin(stream))
     }
@@ -388,6 +442,8 @@ impl AiClient {
         let stream = tokio_stream::once(Ok(StreamingResponse {
             content: response.content,
             is_complete: true,
+            usage: response.usage,
+            tool_calls: response.tool_calls,
         }));
         Ok(Box::pin(stream))
     }
@@ -398,6 +454,8 @@ impl AiClient {
         let stream = tokio_stream::once(Ok(StreamingResponse {
             content: response.content,
             is_complete: true,
+            usage: response.usage,
+            tool_calls: response.tool_calls,
         }));
         Ok(Box::pin(stream))
     }
@@ -407,6 +465,8 @@ impl AiClient {
         let stream = tokio_stream::once(Ok(StreamingResponse {
             content: response.content,
             is_complete: true,
+            usage: response.usage,
+            tool_calls: response.tool_calls,
         }));
         Ok(Box::pin(stream))
     }
@@ -416,6 +476,8 @@ impl AiClient {
         let stream = tokio_stream::once(Ok(StreamingResponse {
             content: response.content,
             is_complete: true,
+            usage: response.usage,
+            tool_calls: response.tool_calls,
         }));
         Ok(Box::pin(stream))
     }
@@ -435,46 +497,98 @@ impl AiClient {
             prompt_tokens: u["prompt_tokens"].as_u64().unwrap_or(0) as u32,
             completion_tokens: u["completion_tokens"].as_u64().unwrap_or(0) as u32,
             total_tokens: u["total_tokens"].as_u64().unwrap_or(0) as u32,
+            estimated: false,
+        });
+
+        // Arguments arrive as a JSON string
+        let tool_calls = message["tool_calls"].as_array().map(|calls| {
+            calls
+                .iter()
+                .map(|call| super::tools::ToolCall {
+                    id: call["id"].as_str().unwrap_or_default().to_string(),
+                    name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: call["function"]["arguments"]
+                        .as_str()
+                        .and_then(|arguments| serde_json::from_str(arguments).ok())
+                        .unwrap_or_default(),
+                })
+                .collect()
         });
 
         Ok(AiResponse {
             content,
-            tool_calls: None, // TODO: Parse tool calls
+            tool_calls,
             finish_reason,
             usage,
         })
     }
 
     fn parse_claude_response(&self, response: serde_json::Value) -> Result<AiResponse, AiClientError> {
-        let content = response["content"].as_array()
-            .and_then(|arr| arr.first())
-            .and_then(|item| item["text"].as_str())
-            .unwrap_or("")
-            .to_string();
+        let blocks = response["content"].as_array().cloned().unwrap_or_default();
+        let content = blocks
+            .iter()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("");
+        let tool_calls: Vec<super::tools::ToolCall> = blocks
+            .iter()
+            .filter(|block| block["type"] == "tool_use")
+            .map(|block| super::tools::ToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                name: block["name"].as_str().unwrap_or_default().to_string(),
+                arguments: serde_json::from_value(block["input"].clone()).unwrap_or_default(),
+            })
+            .collect();
 
         let usage = response["usage"].as_object().map(|u| Usage {
             prompt_tokens: u["input_tokens"].as_u64().unwrap_or(0) as u32,
             completion_tokens: u["output_tokens"].as_u64().unwrap_or(0) as u32,
             total_tokens: (u["input_tokens"].as_u64().unwrap_or(0) + u["output_tokens"].as_u64().unwrap_or(0)) as u32,
+            estimated: false,
         });
 
         Ok(AiResponse {
             content,
-            tool_calls: None, // TODO: Parse tool calls
-            finish_reason: Some("stop".to_string()),
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            finish_reason: response["stop_reason"].as_str().map(str::to_string),
             usage,
         })
     }
 
-    fn convert_messages_for_claude(&self, messages: Vec<AiMessage>) -> (Option<String>, Vec<AiMessage>) {
+    /// Claude takes the system prompt separately, tool calls as `tool_use`
+    /// content blocks and their results as `tool_result` blocks from the user
+    fn convert_messages_for_claude(&self, messages: Vec<AiMessage>) -> (Option<String>, Vec<serde_json::Value>) {
         let mut system_message = None;
         let mut claude_messages = Vec::new();
 
         for message in messages {
-            if message.role == "system" {
-                system_message = Some(message.content);
-            } else {
-                claude_messages.push(message);
+            match message.role.as_str() {
+                "system" => system_message = Some(message.content),
+                "tool" => claude_messages.push(serde_json::json!({
+                    "role": "user",
+                    "content": [{
+                        "type": "tool_result",
+                        "tool_use_id": message.tool_call_id,
+                        "content": message.content,
+                    }],
+                })),
+                _ => match message.tool_calls {
+                    Some(calls) => {
+                        let mut content = Vec::new();
+                        if !message.content.is_empty() {
+                            content.push(serde_json::json!({ "type": "text", "text": message.content }));
+                        }
+                        content.extend(calls.into_iter().map(|call| serde_json::json!({
+                            "type": "tool_use",
+                            "id": call.id,
+                            "name": call.name,
+                            "input": call.arguments,
+                        })));
+                        claude_messages.push(serde_json::json!({ "role": message.role, "content": content }));
+                    }
+                    None => claude_messages.push(serde_json::json!({ "role": message.role, "content": message.content })),
+                },
             }
         }
 
@@ -503,15 +617,69 @@ impl AiClient {
             .unwrap_or("")
             .to_string();
 
+        let usage = response["usageMetadata"].as_object().map(|u| Usage {
+            prompt_tokens: u["promptTokenCount"].as_u64().unwrap_or(0) as u32,
+            completion_tokens: u["candidatesTokenCount"].as_u64().unwrap_or(0) as u32,
+            total_tokens: u["totalTokenCount"].as_u64().unwrap_or(0) as u32,
+            estimated: false,
+        });
+
         Ok(AiResponse {
             content,
             tool_calls: None,
             finish_reason: Some("stop".to_string()),
-            usage: None,
+            usage,
         })
     }
 }
 
+/// OpenAI's chat format: assistant tool calls carry their arguments as a
+/// JSON string, and results are `tool` messages naming the call
+fn openai_messages(messages: Vec<AiMessage>) -> Vec<serde_json::Value> {
+    messages
+        .into_iter()
+        .map(|message| {
+            let mut value = serde_json::json!({ "role": message.role, "content": message.content });
+            if let Some(calls) = message.tool_calls {
+                value["tool_calls"] = calls
+                    .into_iter()
+                    .map(|call| serde_json::json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": {
+                            "name": call.name,
+                            "arguments": serde_json::to_string(&call.arguments).unwrap_or_default(),
+                        },
+                    }))
+                    .collect();
+            }
+            if let Some(id) = message.tool_call_id {
+                value["tool_call_id"] = serde_json::Value::String(id);
+            }
+            value
+        })
+        .collect()
+}
+
+fn openai_tool(tool: &super::tools::Tool) -> serde_json::Value {
+    serde_json::json!({
+        "type": "function",
+        "function": {
+            "name": tool.name,
+            "description": tool.advertised_description(),
+            "parameters": tool.parameters,
+        },
+    })
+}
+
+fn claude_tool(tool: &super::tools::Tool) -> serde_json::Value {
+    serde_json::json!({
+        "name": tool.name,
+        "description": tool.advertised_description(),
+        "input_schema": tool.parameters,
+    })
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AiClientError {
     #[error("Missing API key")]
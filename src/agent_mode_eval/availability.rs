@@ -0,0 +1,386 @@
+//! Whether AI features can run, and what to tell the user when they can't.
+//!
+//! Without credentials the agent still exists but stays disabled: the first
+//! AI action the user asks for gets one info notice, later ones and any
+//! background AI work quietly do nothing.
+
+use std::time::Duration;
+use super::ai_client::AiProvider;
+use super::AgentConfig;
+
+/// Providers tried, in order, when picking one from the environment
+const PROVIDER_KEYS: &[(&str, AiProvider)] = &[
+    ("OPENAI_API_KEY", AiProvider::OpenAI),
+    ("ANTHROPIC_API_KEY", AiProvider::Claude),
+    ("GEMINI_API_KEY", AiProvider::Gemini),
+    ("GROQ_API_KEY", AiProvider::Groq),
+];
+
+/// Environment variable holding the key for `provider`; `None` if it needs none
+pub fn key_variable(provider: &AiProvider) -> Option<&'static str> {
+    match provider {
+        AiProvider::OpenAI => Some("OPENAI_API_KEY"),
+        AiProvider::Claude => Some("ANTHROPIC_API_KEY"),
+        AiProvider::Gemini => Some("GEMINI_API_KEY"),
+        AiProvider::Groq => Some("GROQ_API_KEY"),
+        AiProvider::Ollama | AiProvider::Local => None,
+    }
+}
+
+impl AgentConfig {
+    /// Configuration for the first provider with a key in the environment,
+    /// or the default provider without credentials
+    pub fn from_env(env: impl Fn(&str) -> Option<String>) -> Self {
+        let found = PROVIDER_KEYS
+            .iter()
+            .find_map(|(var, provider)| env(var).filter(|key| !key.trim().is_empty()).map(|key| (provider.clone(), key)));
+
+        match found {
+            Some((provider, key)) => Self {
+                model: Self::get_default_model(&provider).to_string(),
+                provider,
+                api_key: Some(key),
+                ..Self::default()
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Configuration from the AI preferences. What they leave unset comes
+    /// from the environment: the provider, when none is picked, and the
+    /// provider's API key.
+    pub fn from_preferences(prefs: &crate::config::AiPreferences, env: impl Fn(&str) -> Option<String>) -> Self {
+        let mut config = match &prefs.provider {
+            None => Self::from_env(&env),
+            Some(provider) => Self {
+                provider: provider.clone(),
+                model: Self::get_default_model(provider).to_string(),
+                api_key: key_variable(provider).and_then(|var| env(var)).filter(|key| !key.trim().is_empty()),
+                base_url: match provider {
+                    AiProvider::Ollama | AiProvider::Local => Self::get_default_base_url(provider).map(str::to_string),
+                    _ => None,
+                },
+                ..Self::default()
+            },
+        };
+        if let Some(model) = prefs.model.as_ref().filter(|model| !model.trim().is_empty()) {
+            config.model = model.trim().to_string();
+        }
+        if let Some(base_url) = prefs.base_url.as_ref().filter(|url| !url.trim().is_empty()) {
+            config.base_url = Some(base_url.trim().to_string());
+        }
+        if let Some(api_key) = prefs.api_key.as_ref().filter(|key| !key.trim().is_empty()) {
+            config.api_key = Some(api_key.trim().to_string());
+        }
+        config.temperature = prefs.temperature;
+        config.approval_mode = prefs.tool_approval;
+        config.web_access = prefs.web_access.clone();
+        // Providers that can't take tool definitions get plain prompts
+        config.tools_enabled = config.provider.supports_tools();
+        config
+    }
+
+    /// Configuration for a local Ollama daemon
+    pub fn ollama() -> Self {
+        Self {
+            provider: AiProvider::Ollama,
+            model: Self::get_default_model(&AiProvider::Ollama).to_string(),
+            base_url: Self::get_default_base_url(&AiProvider::Ollama).map(str::to_string),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AiStatus {
+    Ready { provider: String, model: String },
+    /// The provider needs a key and none is set
+    Unconfigured { provider: String, key_variable: &'static str },
+}
+
+impl AiStatus {
+    pub fn of(config: &AgentConfig) -> Self {
+        let provider = format!("{:?}", config.provider);
+        match key_variable(&config.provider) {
+            Some(key_variable) if config.api_key.as_deref().map_or(true, |k| k.trim().is_empty()) => {
+                AiStatus::Unconfigured { provider, key_variable }
+            }
+            _ => AiStatus::Ready { provider, model: config.model.clone() },
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        matches!(self, AiStatus::Ready { .. })
+    }
+}
+
+impl std::fmt::Display for AiStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AiStatus::Ready { provider, model } => write!(f, "ready ({}, {})", provider, model),
+            AiStatus::Unconfigured { provider, key_variable } => {
+                write!(f, "not configured: {} needs {}", provider, key_variable)
+            }
+        }
+    }
+}
+
+/// Kinds of AI use, by whether the user explicitly asked for them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiRequest {
+    ToggleAgent,
+    AgentPrompt,
+    /// A `/ai ...` command
+    AiCommand,
+    /// Automatic fix suggestion after a failed command
+    SuggestFix,
+    Autocomplete,
+    Background,
+}
+
+impl AiRequest {
+    fn is_explicit(&self) -> bool {
+        matches!(self, AiRequest::ToggleAgent | AiRequest::AgentPrompt | AiRequest::AiCommand)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gate {
+    Proceed,
+    /// Don't run; show this once as an info block
+    Notice(String),
+    /// Don't run and say nothing
+    Skip,
+}
+
+/// Tracks whether the "AI isn't set up" notice has been shown this session
+#[derive(Debug, Clone, Default)]
+pub struct AiGate {
+    notice_shown: bool,
+    /// Base URL of a local Ollama daemon that answered
+    ollama: Option<String>,
+}
+
+impl AiGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_ollama(&mut self, base_url: Option<String>) {
+        self.ollama = base_url;
+    }
+
+    pub fn ollama(&self) -> Option<&str> {
+        self.ollama.as_deref()
+    }
+
+    pub fn check(&mut self, status: &AiStatus, request: AiRequest) -> Gate {
+        let AiStatus::Unconfigured { key_variable, .. } = status else {
+            return Gate::Proceed;
+        };
+        if !request.is_explicit() || self.notice_shown {
+            return Gate::Skip;
+        }
+        self.notice_shown = true;
+
+        let mut notice = format!(
+            "AI features are off because no API key is configured. Set {} or add a key in Settings → AI.",
+            key_variable
+        );
+        if let Some(base_url) = &self.ollama {
+            notice.push_str(&format!(
+                " A local Ollama daemon is running at {}; type `/ai use-ollama` to use it.",
+                base_url
+            ));
+        }
+        Gate::Notice(notice)
+    }
+}
+
+/// Whether an Ollama daemon answers at `base_url`
+pub async fn detect_ollama(base_url: &str) -> bool {
+    let Ok(client) = crate::net::client(Some(Duration::from_secs(1))) else {
+        return false;
+    };
+    client
+        .get(format!("{}/api/tags", base_url.trim_end_matches('/')))
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
+
+/// Names of the models an Ollama daemon at `base_url` has pulled
+pub async fn ollama_models(base_url: &str) -> Result<Vec<String>, String> {
+    let client = crate::net::client(Some(Duration::from_secs(3))).map_err(|e| e.to_string())?;
+    let url = format!("{}/api/tags", base_url.trim_end_matches('/'));
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|_| format!("Ollama is not reachable at {}", base_url))?;
+    if !response.status().is_success() {
+        return Err(format!("Ollama at {} answered {}", base_url, response.status()));
+    }
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    parse_ollama_tags(&body)
+}
+
+/// Model names from an `/api/tags` response body
+fn parse_ollama_tags(body: &str) -> Result<Vec<String>, String> {
+    #[derive(serde::Deserialize)]
+    struct Tags {
+        #[serde(default)]
+        models: Vec<Tag>,
+    }
+    #[derive(serde::Deserialize)]
+    struct Tag {
+        name: String,
+    }
+
+    let tags: Tags = serde_json::from_str(body).map_err(|e| format!("Unexpected reply from Ollama: {}", e))?;
+    let mut names: Vec<String> = tags.models.into_iter().map(|tag| tag.name).collect();
+    names.sort();
+    Ok(names)
+}
+
+/// How long a model listing stays fresh
+pub const MODEL_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Recently discovered models, per server, so switching tabs or providers
+/// doesn't query the daemon every time
+#[derive(Debug, Default)]
+pub struct ModelCache {
+    entries: std::collections::HashMap<String, (std::time::Instant, Vec<String>)>,
+}
+
+impl ModelCache {
+    /// Models listed for `base_url` within the last [`MODEL_CACHE_TTL`]
+    pub fn get(&self, base_url: &str, now: std::time::Instant) -> Option<&[String]> {
+        self.entries
+            .get(base_url)
+            .filter(|(fetched, _)| now.duration_since(*fetched) < MODEL_CACHE_TTL)
+            .map(|(_, models)| models.as_slice())
+    }
+
+    pub fn insert(&mut self, base_url: String, models: Vec<String>, now: std::time::Instant) {
+        self.entries.insert(base_url, (now, models));
+    }
+
+    /// Forget `base_url` so the next lookup queries it again
+    pub fn invalidate(&mut self, base_url: &str) {
+        self.entries.remove(base_url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_mode_eval::AgentMode;
+    use crate::block::{Block, BlockContent};
+
+    /// What the app shows for one gated action
+    fn blocks_for(gate: &mut AiGate, status: &AiStatus, request: AiRequest) -> Vec<Block> {
+        match gate.check(status, request) {
+            Gate::Notice(notice) => vec![Block::new_info(notice)],
+            Gate::Proceed | Gate::Skip => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_no_keys_gives_disabled_but_working_agent() {
+        let config = AgentConfig::from_env(|_| None);
+        assert!(config.api_key.is_none());
+        assert!(AgentMode::new(config.clone()).is_ok());
+        assert_eq!(
+            AiStatus::of(&config),
+            AiStatus::Unconfigured { provider: "OpenAI".to_string(), key_variable: "OPENAI_API_KEY" }
+        );
+    }
+
+    #[test]
+    fn test_preferences_override_the_environment() {
+        let env = |name: &str| (name == "ANTHROPIC_API_KEY").then(|| "sk-ant".to_string());
+        let detected = AgentConfig::from_preferences(&crate::config::AiPreferences::default(), env);
+        assert_eq!(detected.provider, AiProvider::Claude);
+        assert_eq!(detected.api_key.as_deref(), Some("sk-ant"));
+
+        let prefs = crate::config::AiPreferences {
+            provider: Some(AiProvider::Ollama),
+            model: Some("mistral".to_string()),
+            temperature: 0.2,
+            ..Default::default()
+        };
+        let ollama = AgentConfig::from_preferences(&prefs, env);
+        assert_eq!(ollama.model, "mistral");
+        assert_eq!(ollama.base_url.as_deref(), Some("http://localhost:11434"));
+        assert_eq!(ollama.temperature, 0.2);
+        assert!(!ollama.tools_enabled);
+        assert!(AiStatus::of(&ollama).is_ready());
+    }
+
+    #[test]
+    fn test_main_flows_without_keys_produce_one_notice_and_no_errors() {
+        let status = AiStatus::of(&AgentConfig::from_env(|_| None));
+        let mut gate = AiGate::new();
+
+        let mut blocks = Vec::new();
+        for request in [
+            AiRequest::SuggestFix,
+            AiRequest::Autocomplete,
+            AiRequest::ToggleAgent,
+            AiRequest::AgentPrompt,
+            AiRequest::AiCommand,
+            AiRequest::ToggleAgent,
+            AiRequest::SuggestFix,
+            AiRequest::Background,
+        ] {
+            blocks.extend(blocks_for(&mut gate, &status, request));
+        }
+
+        assert_eq!(blocks.len(), 1);
+        assert!(blocks.iter().all(|b| !matches!(b.content, BlockContent::Error { .. })));
+        assert!(blocks[0].to_markdown().contains("OPENAI_API_KEY"));
+    }
+
+    #[test]
+    fn test_notice_offers_detected_ollama() {
+        let status = AiStatus::of(&AgentConfig::default());
+        let mut gate = AiGate::new();
+        gate.set_ollama(Some("http://localhost:11434".to_string()));
+
+        let Gate::Notice(notice) = gate.check(&status, AiRequest::ToggleAgent) else {
+            panic!("expected a notice");
+        };
+        assert!(notice.contains("/ai use-ollama"));
+        assert!(AiStatus::of(&AgentConfig::ollama()).is_ready());
+    }
+
+    #[test]
+    fn test_first_key_in_environment_picks_provider() {
+        let config = AgentConfig::from_env(|name| (name == "ANTHROPIC_API_KEY").then(|| "sk-ant-test".to_string()));
+
+        assert!(matches!(config.provider, AiProvider::Claude));
+        assert_eq!(config.model, AgentConfig::get_default_model(&AiProvider::Claude));
+        assert!(AiStatus::of(&config).is_ready());
+
+        let mut gate = AiGate::new();
+        assert_eq!(gate.check(&AiStatus::of(&config), AiRequest::SuggestFix), Gate::Proceed);
+    }
+
+    #[test]
+    fn test_ollama_tags_and_cache() {
+        let body = r#"{"models":[{"name":"llama3:8b","size":1},{"name":"codellama:latest"}]}"#;
+        assert_eq!(parse_ollama_tags(body).unwrap(), vec!["codellama:latest", "llama3:8b"]);
+        assert_eq!(parse_ollama_tags("{}").unwrap(), Vec::<String>::new());
+        assert!(parse_ollama_tags("<html>").is_err());
+
+        let now = std::time::Instant::now();
+        let mut cache = ModelCache::default();
+        cache.insert("http://localhost:11434".into(), vec!["llama3:8b".into()], now);
+        assert_eq!(cache.get("http://localhost:11434", now).unwrap(), ["llama3:8b"]);
+        assert!(cache.get("http://localhost:11434", now + MODEL_CACHE_TTL).is_none());
+        assert!(cache.get("http://other:11434", now).is_none());
+        cache.invalidate("http://localhost:11434");
+        assert!(cache.get("http://localhost:11434", now).is_none());
+    }
+}
@@ -0,0 +1,388 @@
+//! Turns a block's output into prompt context that fits. Long output is cut
+//! down to its start and end plus any stderr and error-looking lines from
+//! the middle, with the gaps marked. When even that is too large, the full
+//! output can be summarized chunk by chunk instead.
+
+use super::ai_client::{AiClient, AiClientError, AiMessage};
+use crate::config::AiContextPreferences;
+use crate::i18n::Locale;
+use crate::timeline::{OutputChunk, OutputStream};
+
+/// Substrings (lowercase) that mark a line worth keeping from the middle
+const ERROR_PATTERNS: &[&str] = &["error:", "error[", "warning:", "panicked", "traceback", "fatal:", "exception"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputLine {
+    pub text: String,
+    pub stderr: bool,
+}
+
+/// Split timestamped chunks into lines, keeping which stream each came from.
+/// A line split across chunks is joined back together.
+pub fn lines_from_chunks(chunks: &[OutputChunk]) -> Vec<OutputLine> {
+    let mut lines = Vec::new();
+    let mut pending = [String::new(), String::new()];
+
+    for chunk in chunks {
+        let stderr = chunk.stream == OutputStream::Stderr;
+        let buffer = &mut pending[usize::from(stderr)];
+        buffer.push_str(&chunk.text);
+        while let Some(end) = buffer.find('\n') {
+            let text = buffer[..end].trim_end_matches('\r').to_string();
+            buffer.drain(..=end);
+            lines.push(OutputLine { text, stderr });
+        }
+    }
+    for (stream, rest) in pending.into_iter().enumerate() {
+        if !rest.is_empty() {
+            lines.push(OutputLine { text: rest, stderr: stream == 1 });
+        }
+    }
+    lines
+}
+
+/// Output with no stream information, treated as stdout
+pub fn lines_from_text(text: &str) -> Vec<OutputLine> {
+    text.lines().map(|line| OutputLine { text: line.to_string(), stderr: false }).collect()
+}
+
+fn is_error_line(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    ERROR_PATTERNS.iter().any(|pattern| lower.contains(pattern))
+}
+
+/// Rough token count, about four characters each
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// `184302` as `184,302`. Prompts are written in English whatever the UI
+/// language, so their numbers are too.
+fn format_count(count: usize) -> String {
+    Locale::En.format_number(count as u64)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Excerpt {
+    /// A kept line and its index in the full output
+    Line(usize, OutputLine),
+    /// This many consecutive lines were left out
+    Omitted(usize),
+}
+
+/// A command and the parts of its output that will be sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockContext {
+    pub command: String,
+    pub excerpt: Vec<Excerpt>,
+    pub total_lines: usize,
+}
+
+impl BlockContext {
+    /// Keep the first and last lines, then stderr and error-looking lines
+    /// from the middle up to their caps. Kept lines stay in output order.
+    pub fn build(command: &str, lines: &[OutputLine], limits: &AiContextPreferences) -> Self {
+        let total = lines.len();
+        let mut keep = vec![false; total];
+
+        let head_end = limits.head_lines.min(total);
+        let tail_start = total.saturating_sub(limits.tail_lines).max(head_end);
+        keep[..head_end].iter_mut().for_each(|k| *k = true);
+        keep[tail_start..].iter_mut().for_each(|k| *k = true);
+
+        let middle = head_end..tail_start;
+        let (mut stderr_left, mut matches_left) = (limits.stderr_lines, limits.match_lines);
+        for i in middle {
+            let line = &lines[i];
+            if line.stderr && stderr_left > 0 {
+                stderr_left -= 1;
+                keep[i] = true;
+            } else if matches_left > 0 && is_error_line(&line.text) {
+                matches_left -= 1;
+                keep[i] = true;
+            }
+        }
+
+        let mut excerpt = Vec::new();
+        let mut omitted = 0;
+        for (i, line) in lines.iter().enumerate() {
+            if keep[i] {
+                if omitted > 0 {
+                    excerpt.push(Excerpt::Omitted(omitted));
+                    omitted = 0;
+                }
+                excerpt.push(Excerpt::Line(i, line.clone()));
+            } else {
+                omitted += 1;
+            }
+        }
+        if omitted > 0 {
+            excerpt.push(Excerpt::Omitted(omitted));
+        }
+
+        Self { command: command.to_string(), excerpt, total_lines: total }
+    }
+
+    pub fn omitted_lines(&self) -> usize {
+        self.excerpt
+            .iter()
+            .map(|part| match part {
+                Excerpt::Omitted(count) => *count,
+                Excerpt::Line(..) => 0,
+            })
+            .sum()
+    }
+
+    /// The command and the kept output, as sent
+    pub fn render(&self) -> String {
+        let mut text = format!("$ {}\n", self.command);
+        for part in &self.excerpt {
+            match part {
+                Excerpt::Line(_, line) => text.push_str(&line.text),
+                Excerpt::Omitted(count) => text.push_str(&format!("… {} lines omitted …", format_count(*count))),
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn estimated_tokens(&self) -> usize {
+        estimate_tokens(&self.render())
+    }
+
+    pub fn is_over(&self, limits: &AiContextPreferences) -> bool {
+        self.estimated_tokens() > limits.max_tokens
+    }
+
+    pub fn prompt(&self) -> String {
+        format!(
+            "Here is a command I ran and its output. Explain what happened and how to fix any errors.\n\n```\n{}```",
+            self.render()
+        )
+    }
+}
+
+/// Pack lines into pieces of at most about `max_tokens` each. A single line
+/// longer than that gets a piece of its own.
+pub fn summary_chunks(lines: &[OutputLine], max_tokens: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    for line in lines {
+        let tokens = estimate_tokens(&line.text) + 1;
+        if current_tokens + tokens > max_tokens && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        if line.stderr {
+            current.push_str("[stderr] ");
+        }
+        current.push_str(&line.text);
+        current.push('\n');
+        current_tokens += tokens;
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn user_message(content: String) -> AiMessage {
+    AiMessage { role: "user".to_string(), content, tool_calls: None, tool_call_id: None }
+}
+
+/// Summarize each piece of the output, then merge the summaries, in rounds
+/// until a single one is left
+pub async fn summarize(
+    client: &AiClient,
+    command: &str,
+    lines: &[OutputLine],
+    limits: &AiContextPreferences,
+) -> Result<String, AiClientError> {
+    let budget = limits.max_tokens.max(1);
+    let mut summaries = Vec::new();
+    let chunks = summary_chunks(lines, budget);
+    let count = chunks.len();
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        let prompt = format!(
+            "This is part {} of {} of the output of `{}`. Summarize it in a few lines, quoting any errors, warnings and failing tests exactly.\n\n```\n{}```",
+            i + 1,
+            count,
+            command,
+            chunk
+        );
+        summaries.push(client.complete(vec![user_message(prompt)], None).await?.content);
+    }
+
+    while summaries.len() > 1 {
+        let parts: Vec<OutputLine> = summaries.iter().map(|summary| OutputLine { text: summary.clone(), stderr: false }).collect();
+        let mut merged = Vec::new();
+        for group in summary_chunks(&parts, budget) {
+            let prompt = format!(
+                "These are summaries of consecutive parts of the output of `{}`. Merge them into one summary, keeping every error quoted exactly.\n\n{}",
+                command, group
+            );
+            merged.push(client.complete(vec![user_message(prompt)], None).await?.content);
+        }
+        // Every group merged into one piece; stop rather than loop forever
+        if merged.len() >= summaries.len() {
+            return Ok(merged.join("\n"));
+        }
+        summaries = merged;
+    }
+    Ok(summaries.pop().unwrap_or_default())
+}
+
+/// The prompt sent in place of the output when it was summarized
+pub fn summary_prompt(command: &str, total_lines: usize, summary: &str) -> String {
+    format!(
+        "Here is a command I ran and a summary of its {} lines of output. Explain what happened and how to fix any errors.\n\n$ {}\n\n{}",
+        format_count(total_lines),
+        command,
+        summary
+    )
+}
+
+/// Lines of output quoted when asking for an explanation
+pub const EXPLAIN_TAIL_LINES: usize = 50;
+
+/// A request for a short explanation of why a command printed what it did,
+/// quoting only the end of stderr (or of all output, if it wrote none)
+pub fn explain_prompt(command: &str, exit_code: Option<i32>, lines: &[OutputLine]) -> String {
+    let stderr: Vec<&str> = lines.iter().filter(|line| line.stderr).map(|line| line.text.as_str()).collect();
+    let (label, quoted) = if stderr.is_empty() {
+        ("output", lines.iter().map(|line| line.text.as_str()).collect())
+    } else {
+        ("stderr", stderr)
+    };
+    let tail = &quoted[quoted.len().saturating_sub(EXPLAIN_TAIL_LINES)..];
+    let exit = exit_code.map_or_else(|| "still running".to_string(), |code| format!("exit code {}", code));
+    format!(
+        "Explain in a few sentences what this output means. Don't suggest a fix.\n\n$ {}\n({})\n\nLast {} lines of {}:\n{}",
+        command,
+        exit,
+        tail.len(),
+        label,
+        tail.join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> AiContextPreferences {
+        AiContextPreferences { head_lines: 3, tail_lines: 2, stderr_lines: 2, match_lines: 2, max_tokens: 100 }
+    }
+
+    /// 1,000 lines of build noise with a few interesting lines in the middle
+    fn build_log() -> Vec<OutputLine> {
+        let mut lines: Vec<OutputLine> = (0..1000)
+            .map(|i| OutputLine { text: format!("   Compiling crate-{} v0.1.0", i), stderr: false })
+            .collect();
+        lines[100].text = "warning: unused variable `x`".to_string();
+        lines[400] = OutputLine { text: "note: linking".to_string(), stderr: true };
+        lines[500].text = "error[E0308]: mismatched types".to_string();
+        lines[600].text = "thread 'main' panicked at src/main.rs:3".to_string();
+        lines[700] = OutputLine { text: "ld: warning".to_string(), stderr: true };
+        lines[800] = OutputLine { text: "one stderr line too many".to_string(), stderr: true };
+        lines
+    }
+
+    fn kept(context: &BlockContext) -> Vec<usize> {
+        context
+            .excerpt
+            .iter()
+            .filter_map(|part| match part {
+                Excerpt::Line(i, _) => Some(*i),
+                Excerpt::Omitted(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_keeps_head_tail_stderr_and_errors_in_order() {
+        let context = BlockContext::build("cargo build", &build_log(), &limits());
+
+        // Two error-ish lines and two stderr lines from the middle; the panic is over the match cap
+        assert_eq!(kept(&context), vec![0, 1, 2, 100, 400, 500, 700, 998, 999]);
+        assert_eq!(context.omitted_lines(), 991);
+        assert_eq!(context.excerpt[3], Excerpt::Omitted(97));
+        assert_eq!(context, BlockContext::build("cargo build", &build_log(), &limits()));
+
+        let rendered = context.render();
+        assert!(rendered.starts_with("$ cargo build\n   Compiling crate-0 v0.1.0\n"));
+        assert!(rendered.contains("… 97 lines omitted …\nwarning: unused variable `x`\n… 299 lines omitted …\nnote: linking\n"));
+        assert!(rendered.ends_with("… 297 lines omitted …\n   Compiling crate-998 v0.1.0\n   Compiling crate-999 v0.1.0\n"));
+    }
+
+    #[test]
+    fn test_short_output_is_sent_whole() {
+        let lines = lines_from_text("a\nb\nc\nd");
+        let context = BlockContext::build("ls", &lines, &limits());
+        assert_eq!(kept(&context), vec![0, 1, 2, 3]);
+        assert_eq!(context.omitted_lines(), 0);
+        assert_eq!(context.render(), "$ ls\na\nb\nc\nd\n");
+        assert!(!context.is_over(&limits()));
+    }
+
+    #[test]
+    fn test_token_estimate_and_over_cap() {
+        let lines: Vec<OutputLine> = (0..50).map(|i| OutputLine { text: format!("error: {}", "x".repeat(40 + i)), stderr: false }).collect();
+        let context = BlockContext::build("make", &lines, &AiContextPreferences { match_lines: 50, ..limits() });
+        assert_eq!(context.estimated_tokens(), estimate_tokens(&context.render()));
+        assert!(context.is_over(&limits()));
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_explain_prompt_quotes_tail_of_stderr() {
+        let mut lines: Vec<OutputLine> = (0..80).map(|i| OutputLine { text: format!("err {}", i), stderr: true }).collect();
+        lines.push(OutputLine { text: "stdout noise".to_string(), stderr: false });
+
+        let prompt = explain_prompt("cargo build", Some(101), &lines);
+        assert!(prompt.contains("$ cargo build\n(exit code 101)"));
+        assert!(prompt.contains("Last 50 lines of stderr:\nerr 30\n"));
+        assert!(prompt.ends_with("err 79"));
+        assert!(!prompt.contains("stdout noise") && !prompt.contains("err 29\n"));
+
+        let quiet = explain_prompt("ls", Some(2), &lines[80..]);
+        assert!(quiet.ends_with("Last 1 lines of output:\nstdout noise"));
+    }
+
+    #[test]
+    fn test_lines_from_chunks_rejoins_split_lines() {
+        let chunk = |stream, text: &str| OutputChunk { offset_ms: 0, stream, text: text.to_string() };
+        let lines = lines_from_chunks(&[
+            chunk(OutputStream::Stdout, "Comp"),
+            chunk(OutputStream::Stderr, "warning: x\n"),
+            chunk(OutputStream::Stdout, "iling\r\ndone"),
+        ]);
+        assert_eq!(lines, vec![
+            OutputLine { text: "warning: x".to_string(), stderr: true },
+            OutputLine { text: "Compiling".to_string(), stderr: false },
+            OutputLine { text: "done".to_string(), stderr: false },
+        ]);
+    }
+
+    #[test]
+    fn test_summary_chunks_respect_budget() {
+        let lines = lines_from_text(&"0123456789abcdef\n".repeat(100));
+        let chunks = summary_chunks(&lines, 50);
+        // Each line is about five tokens, so ten lines per piece
+        assert_eq!(chunks.len(), 10);
+        assert!(chunks.iter().all(|chunk| estimate_tokens(chunk) <= 50));
+        assert_eq!(chunks.concat(), "0123456789abcdef\n".repeat(100));
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1000), "1,000");
+        assert_eq!(format_count(184_302), "184,302");
+        assert_eq!(format_count(1_234_567), "1,234,567");
+    }
+}
@@ -1,23 +1,71 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use super::ai_client::Usage;
+use super::history::HistorySummary;
+use super::usage::TokenTotals;
+
+/// A linear branch of messages. Messages are shared between branches through
+/// `Arc`, so forking copies pointers rather than message contents.
 #[derive(Debug, Clone)]
 pub struct Conversation {
     pub id: Uuid,
     pub system_prompt: String,
-    pub messages: Vec<Message>,
+    pub messages: Vec<Arc<Message>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub metadata: ConversationMetadata,
+    /// Where this branch was forked from; `None` for the original conversation
+    pub parent: Option<BranchPoint>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
     pub role: MessageRole,
     pub content: String,
     pub timestamp: DateTime<Utc>,
     pub tool_calls: Option<Vec<super::tools::ToolCall>>,
+    /// The call a `Tool` message answers
+    #[serde(default)]
+    pub tool_call_id: Option<String>,
+}
+
+impl Message {
+    pub fn new(role: MessageRole, content: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            role,
+            content,
+            timestamp: Utc::now(),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    /// What the model is told a tool call returned
+    pub fn tool_result(result: &super::tools::ToolResult) -> Self {
+        let content = match &result.error {
+            Some(error) => format!("Error: {}", error),
+            None => result.output.clone(),
+        };
+        Self {
+            tool_call_id: Some(result.tool_call_id.clone()),
+            ..Self::new(MessageRole::Tool, content)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchPoint {
+    pub conversation_id: Uuid,
+    /// Last shared message; the branch continues after it. `None` when the
+    /// branch shares no messages with its parent.
+    pub message_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +73,8 @@ pub enum MessageRole {
     System,
     User,
     Assistant,
+    /// Result of a tool call, sent back to the model
+    Tool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +84,20 @@ pub struct ConversationMetadata {
     pub token_count: Option<u32>,
     pub model_used: Option<String>,
     pub provider_used: Option<String>,
+    /// Tokens spent on replies in this branch, not counting those it shares with its parent
+    #[serde(default)]
+    pub usage: TokenTotals,
+    /// Stands in for the oldest messages once the branch outgrew the context
+    #[serde(default)]
+    pub history_summary: Option<HistorySummary>,
+}
+
+/// Message and token counts for one branch
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConversationStats {
+    pub user_messages: usize,
+    pub assistant_messages: usize,
+    pub usage: TokenTotals,
 }
 
 impl Conversation {
@@ -51,26 +115,30 @@ impl Conversation {
                 token_count: None,
                 model_used: None,
                 provider_used: None,
+                usage: TokenTotals::default(),
+                history_summary: None,
             },
+            parent: None,
         }
     }
 
     pub fn add_message(&mut self, message: Message) {
-        self.messages.push(message);
+        self.messages.push(Arc::new(message));
         self.updated_at = Utc::now();
     }
 
-    pub fn get_messages(&self) -> &[Message] {
+    pub fn get_messages(&self) -> &[Arc<Message>] {
         &self.messages
     }
 
     pub fn get_last_message(&self) -> Option<&Message> {
-        self.messages.last()
+        self.messages.last().map(|msg| msg.as_ref())
     }
 
     pub fn get_user_messages(&self) -> Vec<&Message> {
         self.messages
             .iter()
+            .map(|msg| msg.as_ref())
             .filter(|msg| matches!(msg.role, MessageRole::User))
             .collect()
     }
@@ -78,10 +146,66 @@ impl Conversation {
     pub fn get_assistant_messages(&self) -> Vec<&Message> {
         self.messages
             .iter()
+            .map(|msg| msg.as_ref())
             .filter(|msg| matches!(msg.role, MessageRole::Assistant))
             .collect()
     }
 
+    pub fn position_of(&self, message_id: Uuid) -> Option<usize> {
+        self.messages.iter().position(|msg| msg.id == message_id)
+    }
+
+    /// The summary standing in for the oldest messages, and the index of the
+    /// first message after it. A branch forked before the summarized part
+    /// ends has no use for it.
+    pub fn history_summary(&self) -> Option<(&HistorySummary, usize)> {
+        let summary = self.metadata.history_summary.as_ref()?;
+        Some((summary, self.position_of(summary.through)? + 1))
+    }
+
+    /// Start a new branch sharing every message up to and including `message_id`
+    pub fn fork_at(&self, message_id: Uuid) -> Option<Conversation> {
+        let index = self.position_of(message_id)?;
+        Some(self.fork_with_prefix(index + 1))
+    }
+
+    /// Start a new branch sharing every message before `message_id`
+    pub fn fork_before(&self, message_id: Uuid) -> Option<Conversation> {
+        let index = self.position_of(message_id)?;
+        Some(self.fork_with_prefix(index))
+    }
+
+    fn fork_with_prefix(&self, len: usize) -> Conversation {
+        let now = Utc::now();
+        Conversation {
+            id: Uuid::new_v4(),
+            system_prompt: self.system_prompt.clone(),
+            messages: self.messages[..len].to_vec(),
+            created_at: now,
+            updated_at: now,
+            metadata: ConversationMetadata {
+                title: None,
+                usage: TokenTotals::default(),
+                ..self.metadata.clone()
+            },
+            parent: Some(BranchPoint {
+                conversation_id: self.id,
+                message_id: len.checked_sub(1).map(|i| self.messages[i].id),
+            }),
+        }
+    }
+
+    /// Drop `message_id` and everything after it, returning the removed messages
+    pub fn truncate_from(&mut self, message_id: Uuid) -> Vec<Arc<Message>> {
+        match self.position_of(message_id) {
+            Some(index) => {
+                self.updated_at = Utc::now();
+                self.messages.split_off(index)
+            }
+            None => Vec::new(),
+        }
+    }
+
     pub fn clear_messages(&mut self) {
         self.messages.clear();
         self.updated_at = Utc::now();
@@ -113,6 +237,21 @@ impl Conversation {
         self.messages.len()
     }
 
+    /// Add one reply's usage, with its cost if the model has a price
+    pub fn record_usage(&mut self, usage: &Usage, cost: Option<f64>) {
+        self.metadata.usage.record(usage, cost);
+        self.metadata.token_count = Some(self.metadata.usage.total().min(u64::from(u32::MAX)) as u32);
+        self.updated_at = Utc::now();
+    }
+
+    pub fn stats(&self) -> ConversationStats {
+        ConversationStats {
+            user_messages: self.get_user_messages().len(),
+            assistant_messages: self.get_assistant_messages().len(),
+            usage: self.metadata.usage,
+        }
+    }
+
     pub fn get_token_estimate(&self) -> u32 {
         // Simple token estimation (roughly 4 characters per token)
         let total_chars: usize = self.messages
@@ -146,13 +285,15 @@ impl Serialize for Conversation {
         S: serde::Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("Conversation", 6)?;
+        let messages: Vec<&Message> = self.messages.iter().map(|msg| msg.as_ref()).collect();
+        let mut state = serializer.serialize_struct("Conversation", 7)?;
         state.serialize_field("id", &self.id)?;
         state.serialize_field("system_prompt", &self.system_prompt)?;
-        state.serialize_field("messages", &self.messages)?;
+        state.serialize_field("messages", &messages)?;
         state.serialize_field("created_at", &self.created_at)?;
         state.serialize_field("updated_at", &self.updated_at)?;
         state.serialize_field("metadata", &self.metadata)?;
+        state.serialize_field("parent", &self.parent)?;
         state.end()
     }
 }
@@ -170,20 +311,267 @@ impl<'de> Deserialize<'de> for Conversation {
             created_at: DateTime<Utc>,
             updated_at: DateTime<Utc>,
             metadata: ConversationMetadata,
+            #[serde(default)]
+            parent: Option<BranchPoint>,
         }
 
         let data = ConversationData::deserialize(deserializer)?;
         Ok(Conversation {
             id: data.id,
             system_prompt: data.system_prompt,
-            messages: data.messages,
+            messages: data.messages.into_iter().map(Arc::new).collect(),
             created_at: data.created_at,
             updated_at: data.updated_at,
             metadata: data.metadata,
+            parent: data.parent,
         })
     }
 }
 
+/// What to include when exporting a conversation tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportScope {
+    ActiveBranch,
+    Branch(Uuid),
+    FullTree,
+}
+
+/// Entry in the branch switcher
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchSummary {
+    pub id: Uuid,
+    pub label: String,
+    pub depth: usize,
+    pub message_count: usize,
+}
+
+impl std::fmt::Display for BranchSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{} ({})", "  ".repeat(self.depth), self.label, self.message_count)
+    }
+}
+
+/// All branches of one conversation, with the one that is sent to the provider.
+#[derive(Debug, Clone)]
+pub struct ConversationTree {
+    branches: Vec<Conversation>,
+    active: Uuid,
+}
+
+impl ConversationTree {
+    pub fn new(root: Conversation) -> Self {
+        Self {
+            active: root.id,
+            branches: vec![root],
+        }
+    }
+
+    pub fn active_id(&self) -> Uuid {
+        self.active
+    }
+
+    pub fn active(&self) -> &Conversation {
+        self.get(self.active).expect("active branch exists")
+    }
+
+    pub fn active_mut(&mut self) -> &mut Conversation {
+        let active = self.active;
+        self.branches
+            .iter_mut()
+            .find(|branch| branch.id == active)
+            .expect("active branch exists")
+    }
+
+    /// The original conversation every branch descends from; its id names the tree
+    pub fn root(&self) -> &Conversation {
+        &self.branches[0]
+    }
+
+    pub fn root_mut(&mut self) -> &mut Conversation {
+        &mut self.branches[0]
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&Conversation> {
+        self.branches.iter().find(|branch| branch.id == id)
+    }
+
+    pub fn branches(&self) -> &[Conversation] {
+        &self.branches
+    }
+
+    pub fn branch_count(&self) -> usize {
+        self.branches.len()
+    }
+
+    /// Tokens spent across every branch; forks don't repeat their parent's usage
+    pub fn usage(&self) -> TokenTotals {
+        self.branches.iter().map(|branch| branch.metadata.usage).sum()
+    }
+
+    /// Fork the active branch at `message_id` and make the fork active
+    pub fn fork_at(&mut self, message_id: Uuid) -> Option<Uuid> {
+        let branch = self.active().fork_at(message_id)?;
+        Some(self.push_active(branch))
+    }
+
+    /// Fork the active branch just before `message_id` and make the fork active
+    pub fn fork_before(&mut self, message_id: Uuid) -> Option<Uuid> {
+        let branch = self.active().fork_before(message_id)?;
+        Some(self.push_active(branch))
+    }
+
+    fn push_active(&mut self, branch: Conversation) -> Uuid {
+        let id = branch.id;
+        self.branches.push(branch);
+        self.active = id;
+        id
+    }
+
+    pub fn switch_to(&mut self, id: Uuid) -> bool {
+        if self.get(id).is_some() {
+            self.active = id;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Branches in depth-first order, children listed under their parent
+    pub fn summaries(&self) -> Vec<BranchSummary> {
+        let mut summaries = Vec::new();
+        for root in self.branches.iter().filter(|branch| branch.parent.is_none()) {
+            self.collect_summaries(root, 0, &mut summaries);
+        }
+        summaries
+    }
+
+    fn collect_summaries(&self, branch: &Conversation, depth: usize, out: &mut Vec<BranchSummary>) {
+        let label = match branch.parent {
+            None => "main".to_string(),
+            Some(BranchPoint { message_id: None, .. }) => "⎇ from start".to_string(),
+            Some(BranchPoint { conversation_id, message_id: Some(message_id) }) => {
+                let preview = self.get(conversation_id)
+                    .and_then(|parent| parent.position_of(message_id).map(|i| &parent.messages[i]))
+                    .map(|msg| msg.content.chars().take(24).collect::<String>())
+                    .unwrap_or_default();
+                format!("⎇ after \"{}\"", preview)
+            }
+        };
+        out.push(BranchSummary {
+            id: branch.id,
+            label,
+            depth,
+            message_count: branch.messages.len(),
+        });
+
+        for child in self.branches.iter().filter(|b| b.parent.map(|p| p.conversation_id) == Some(branch.id)) {
+            self.collect_summaries(child, depth + 1, out);
+        }
+    }
+
+    pub fn export_to_json(&self, scope: ExportScope) -> Result<String, serde_json::Error> {
+        let branch_id = match scope {
+            ExportScope::FullTree => return serde_json::to_string_pretty(self),
+            ExportScope::ActiveBranch => self.active,
+            ExportScope::Branch(id) => id,
+        };
+        self.get(branch_id)
+            .ok_or_else(|| <serde_json::Error as serde::ser::Error>::custom(format!("unknown branch {}", branch_id)))?
+            .export_to_json()
+    }
+
+    pub fn import_from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// On-disk form of a tree: every message is stored once in `messages` and
+/// branches refer to them by id, so shared prefixes (and large tool outputs
+/// in them) are not duplicated.
+#[derive(Serialize, Deserialize)]
+struct StoredTree {
+    active: Uuid,
+    messages: HashMap<Uuid, Message>,
+    branches: Vec<StoredBranch>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredBranch {
+    id: Uuid,
+    system_prompt: String,
+    message_ids: Vec<Uuid>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    metadata: ConversationMetadata,
+    parent: Option<BranchPoint>,
+}
+
+impl Serialize for ConversationTree {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut messages = HashMap::new();
+        let branches = self.branches
+            .iter()
+            .map(|branch| {
+                for msg in &branch.messages {
+                    messages.entry(msg.id).or_insert_with(|| msg.as_ref().clone());
+                }
+                StoredBranch {
+                    id: branch.id,
+                    system_prompt: branch.system_prompt.clone(),
+                    message_ids: branch.messages.iter().map(|msg| msg.id).collect(),
+                    created_at: branch.created_at,
+                    updated_at: branch.updated_at,
+                    metadata: branch.metadata.clone(),
+                    parent: branch.parent,
+                }
+            })
+            .collect();
+
+        StoredTree { active: self.active, messages, branches }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ConversationTree {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let stored = StoredTree::deserialize(deserializer)?;
+        let pool: HashMap<Uuid, Arc<Message>> = stored.messages
+            .into_iter()
+            .map(|(id, msg)| (id, Arc::new(msg)))
+            .collect();
+
+        let mut branches = Vec::with_capacity(stored.branches.len());
+        for branch in stored.branches {
+            let messages = branch.message_ids
+                .iter()
+                .map(|id| pool.get(id).cloned().ok_or_else(|| D::Error::custom(format!("missing message {}", id))))
+                .collect::<Result<Vec<_>, _>>()?;
+            branches.push(Conversation {
+                id: branch.id,
+                system_prompt: branch.system_prompt,
+                messages,
+                created_at: branch.created_at,
+                updated_at: branch.updated_at,
+                metadata: branch.metadata,
+                parent: branch.parent,
+            });
+        }
+
+        if !branches.iter().any(|branch| branch.id == stored.active) {
+            return Err(D::Error::custom("active branch not found"));
+        }
+
+        Ok(Self { branches, active: stored.active })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -202,10 +590,12 @@ mod tests {
     fn test_add_message() {
         let mut conv = Conversation::new("Test".to_string());
         let message = Message {
+            id: Uuid::new_v4(),
             role: MessageRole::User,
             content: "Hello".to_string(),
             timestamp: Utc::now(),
             tool_calls: None,
+            tool_call_id: None,
         };
 
         conv.add_message(message);
@@ -218,17 +608,21 @@ mod tests {
         let mut conv = Conversation::new("Test".to_string());
         
         conv.add_message(Message {
+            id: Uuid::new_v4(),
             role: MessageRole::User,
             content: "User message".to_string(),
             timestamp: Utc::now(),
             tool_calls: None,
+            tool_call_id: None,
         });
 
         conv.add_message(Message {
+            id: Uuid::new_v4(),
             role: MessageRole::Assistant,
             content: "Assistant message".to_string(),
             timestamp: Utc::now(),
             tool_calls: None,
+            tool_call_id: None,
         });
 
         let user_messages = conv.get_user_messages();
@@ -245,10 +639,12 @@ mod tests {
         let mut conv = Conversation::new("Test".to_string());
         
         conv.add_message(Message {
+            id: Uuid::new_v4(),
             role: MessageRole::User,
             content: "This is a test message with some content".to_string(), // ~40 chars = ~10 tokens
             timestamp: Utc::now(),
             tool_calls: None,
+            tool_call_id: None,
         });
 
         let estimated_tokens = conv.get_token_estimate();
@@ -256,6 +652,25 @@ mod tests {
         assert!(estimated_tokens < 20); // Should be around 10 tokens
     }
 
+    #[test]
+    fn test_usage_accumulates_per_branch() {
+        let (mut tree, ids) = tree_with_history();
+        let usage = Usage { prompt_tokens: 1_000, completion_tokens: 200, total_tokens: 1_200, estimated: false };
+        tree.active_mut().record_usage(&usage, Some(0.01));
+        tree.fork_at(ids[1]);
+        assert!(tree.active().stats().usage.is_empty());
+        tree.active_mut().record_usage(&usage, Some(0.01));
+
+        let stats = tree.get(tree.summaries()[0].id).unwrap().stats();
+        assert_eq!((stats.user_messages, stats.assistant_messages), (2, 2));
+        assert_eq!(stats.usage.total(), 1_200);
+        assert_eq!(tree.usage().total(), 2_400);
+        assert_eq!(tree.usage().cost_usd, Some(0.02));
+
+        let restored = ConversationTree::import_from_json(&tree.export_to_json(ExportScope::FullTree).unwrap()).unwrap();
+        assert_eq!(restored.usage(), tree.usage());
+    }
+
     #[test]
     fn test_conversation_serialization() {
         let conv = Conversation::new("Test system prompt".to_string());
@@ -266,4 +681,91 @@ mod tests {
         assert_eq!(conv.system_prompt, deserialized.system_prompt);
         assert_eq!(conv.messages.len(), deserialized.messages.len());
     }
+
+    fn tree_with_history() -> (ConversationTree, Vec<Uuid>) {
+        let mut conv = Conversation::new("Test".to_string());
+        let mut ids = Vec::new();
+        for (role, content) in [
+            (MessageRole::User, "list large files".to_string()),
+            (MessageRole::Assistant, "x".repeat(10_000)),
+            (MessageRole::User, "delete them".to_string()),
+            (MessageRole::Assistant, "Done".to_string()),
+        ] {
+            let message = Message::new(role, content);
+            ids.push(message.id);
+            conv.add_message(message);
+        }
+        (ConversationTree::new(conv), ids)
+    }
+
+    #[test]
+    fn test_fork_shares_prefix() {
+        let (mut tree, ids) = tree_with_history();
+        let root = tree.active_id();
+
+        let branch = tree.fork_at(ids[1]).unwrap();
+        assert_eq!(tree.active_id(), branch);
+        assert_eq!(tree.active().messages.len(), 2);
+        assert!(Arc::ptr_eq(&tree.active().messages[1], &tree.get(root).unwrap().messages[1]));
+
+        // Diverging on the branch leaves the original untouched
+        tree.active_mut().add_message(Message::new(MessageRole::User, "archive them".to_string()));
+        assert_eq!(tree.get(root).unwrap().messages[2].content, "delete them");
+        assert_eq!(tree.active().messages[2].content, "archive them");
+
+        assert!(tree.fork_at(Uuid::new_v4()).is_none());
+        assert!(tree.switch_to(root));
+        assert_eq!(tree.active().messages.len(), 4);
+    }
+
+    #[test]
+    fn test_truncate_and_fork_before() {
+        let (mut tree, ids) = tree_with_history();
+
+        let branch = tree.fork_before(ids[0]).unwrap();
+        assert!(tree.active().messages.is_empty());
+        assert_eq!(tree.active().parent.unwrap().message_id, None);
+
+        assert!(tree.switch_to(tree.summaries()[0].id));
+        let removed = tree.active_mut().truncate_from(ids[2]);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(tree.active().messages.len(), 2);
+        assert!(tree.active_mut().truncate_from(Uuid::new_v4()).is_empty());
+        assert!(tree.get(branch).is_some());
+    }
+
+    #[test]
+    fn test_summaries_nest_branches_under_parent() {
+        let (mut tree, ids) = tree_with_history();
+        let first = tree.fork_at(ids[1]).unwrap();
+        tree.active_mut().add_message(Message::new(MessageRole::User, "again".to_string()));
+        let nested = tree.fork_at(ids[0]).unwrap();
+
+        let summaries = tree.summaries();
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(summaries[0].label, "main");
+        assert_eq!((summaries[1].id, summaries[1].depth), (first, 1));
+        assert_eq!((summaries[2].id, summaries[2].depth), (nested, 2));
+    }
+
+    #[test]
+    fn test_tree_storage_does_not_duplicate_shared_messages() {
+        let (mut tree, ids) = tree_with_history();
+        tree.fork_at(ids[1]);
+        tree.fork_at(ids[1]);
+
+        let json = tree.export_to_json(ExportScope::FullTree).unwrap();
+        assert_eq!(json.matches(&"x".repeat(10_000)).count(), 1);
+
+        let restored = ConversationTree::import_from_json(&json).unwrap();
+        assert_eq!(restored.branch_count(), 3);
+        assert_eq!(restored.active_id(), tree.active_id());
+        let shared = &restored.branches()[0].messages[1];
+        assert!(restored.branches().iter().all(|b| Arc::ptr_eq(&b.messages[1], shared)));
+
+        let branch_json = tree.export_to_json(ExportScope::ActiveBranch).unwrap();
+        let branch = Conversation::import_from_json(&branch_json).unwrap();
+        assert_eq!(branch.messages.len(), 2);
+        assert!(branch.parent.is_some());
+    }
 }
@@ -0,0 +1,196 @@
+//! Fix suggestions for failed commands. The assistant sees the command and
+//! the end of its output and answers with a corrected command, which is
+//! offered under the failed block: it can be copied to the input to edit,
+//! or, with `ai.one_click_fixes` on, run straight away. Fixes that look
+//! destructive always go through the input.
+//!
+//! Asking on every failure is opt-in (`ai.auto_fix_on_failure`), skips exit
+//! codes that only mean "no" (grep finding nothing), and reuses the answer
+//! for the same command failing again within `REUSE_WINDOW`.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use super::context::OutputLine;
+use crate::config::BenignExit;
+use crate::safety::{self, Verdict};
+
+/// How long a fix is reused for the same command failing again
+pub const REUSE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Lines of output sent along with the failure
+const FIX_TAIL_LINES: usize = 20;
+
+pub fn fix_prompt(command: &str, exit_code: i32, lines: &[OutputLine]) -> String {
+    let tail = &lines[lines.len().saturating_sub(FIX_TAIL_LINES)..];
+    let quoted: Vec<String> = tail
+        .iter()
+        .map(|line| if line.stderr { format!("[stderr] {}", line.text) } else { line.text.clone() })
+        .collect();
+    format!(
+        "This command failed with exit code {}. Reply with only the corrected command, on one line, \
+         or NONE if it can't be fixed by changing the command.\n\n$ {}\n\nLast {} lines of output:\n{}",
+        exit_code,
+        command,
+        quoted.len(),
+        quoted.join("\n")
+    )
+}
+
+/// The command in the assistant's reply, without fences or a `$` prompt.
+/// `None` when there is none or it's the failed command again.
+pub fn parse_fix(reply: &str, failed_command: &str) -> Option<String> {
+    let command = reply
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("```"))?
+        .trim_matches('`')
+        .trim_start_matches("$ ")
+        .trim();
+    (!command.is_empty() && command != "NONE" && command != failed_command.trim()).then(|| command.to_string())
+}
+
+/// Whether `exit_code` is an ordinary answer from the command's program
+/// rather than a failure worth fixing
+pub fn is_benign(command: &str, exit_code: i32, benign: &[BenignExit]) -> bool {
+    let Some(program) = crate::path_inspector::program_of(command) else {
+        return false;
+    };
+    let name = program.rsplit('/').next().unwrap_or(&program);
+    benign.iter().any(|exit| exit.program == name && exit.codes.contains(&exit_code))
+}
+
+/// Fixes recently suggested, by the command they fix
+#[derive(Debug, Clone, Default)]
+pub struct FixCache {
+    recent: HashMap<String, (Instant, String)>,
+}
+
+impl FixCache {
+    /// The fix for `command` if it was suggested within `REUSE_WINDOW`
+    pub fn get(&self, command: &str, now: Instant) -> Option<&str> {
+        self.recent
+            .get(command)
+            .filter(|(at, _)| now.duration_since(*at) < REUSE_WINDOW)
+            .map(|(_, fix)| fix.as_str())
+    }
+
+    pub fn insert(&mut self, command: String, fix: String, now: Instant) {
+        self.recent.retain(|_, (at, _)| now.duration_since(*at) < REUSE_WINDOW);
+        self.recent.insert(command, (now, fix));
+    }
+}
+
+/// Commands that are never run with one click, however harmless the
+/// target looks
+fn manual_rules() -> &'static [(Regex, &'static str)] {
+    static RULES: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        [
+            (r"\brm\s+(?:-\S+\s+)*-[a-zA-Z]*[rRf]", "deletes files recursively or by force"),
+            (r"\bdd\b", "writes raw data with dd"),
+            (r"\bmkfs\b", "formats a filesystem"),
+        ]
+        .into_iter()
+        .map(|(pattern, reason)| (Regex::new(pattern).unwrap(), reason))
+        .collect()
+    })
+}
+
+/// Why `command` has to be reviewed in the input before it runs
+pub fn needs_review(command: &str) -> Option<&'static str> {
+    if let Verdict::Destructive(reason) = safety::classify(command) {
+        return Some(reason);
+    }
+    manual_rules().iter().find(|(pattern, _)| pattern.is_match(command)).map(|(_, reason)| *reason)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Same,
+    Removed,
+    Added,
+}
+
+/// Word diff from the failed command to the suggestion, in reading order
+pub fn word_diff(original: &str, suggested: &str) -> Vec<(DiffKind, String)> {
+    let old: Vec<&str> = original.split_whitespace().collect();
+    let new: Vec<&str> = suggested.split_whitespace().collect();
+    // Longest common subsequence lengths of the suffixes
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push((DiffKind::Same, old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push((DiffKind::Removed, old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push((DiffKind::Added, new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fix_strips_fences_and_rejects_repeats() {
+        assert_eq!(parse_fix("```sh\n$ git push -u origin main\n```", "git push"), Some("git push -u origin main".to_string()));
+        assert_eq!(parse_fix("`ls -la`", "ls -al"), Some("ls -la".to_string()));
+        assert_eq!(parse_fix("NONE", "make"), None);
+        assert_eq!(parse_fix("make\n", "make"), None);
+    }
+
+    #[test]
+    fn test_destructive_fixes_need_review() {
+        for command in ["rm -rf build", "rm -f notes.txt", "dd if=disk.img of=backup.img", "mkfs.ext4 /dev/sdb1", "rm -rf ~"] {
+            assert!(needs_review(command).is_some(), "{}", command);
+        }
+        for command in ["cp -r src dest", "grep -rn TODO .", "rm notes.txt", "git add -A"] {
+            assert_eq!(needs_review(command), None, "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_benign_exits_and_reuse_window() {
+        let benign = [BenignExit { program: "grep".to_string(), codes: vec![1] }];
+        assert!(is_benign("grep -q TODO src/main.rs", 1, &benign));
+        assert!(is_benign("LC_ALL=C /usr/bin/grep TODO", 1, &benign));
+        assert!(!is_benign("grep TODO missing.rs", 2, &benign));
+        assert!(!is_benign("make", 1, &benign));
+
+        let mut cache = FixCache::default();
+        let start = Instant::now();
+        cache.insert("git psuh".to_string(), "git push".to_string(), start);
+        assert_eq!(cache.get("git psuh", start + Duration::from_secs(30)), Some("git push"));
+        assert_eq!(cache.get("git psuh", start + REUSE_WINDOW), None);
+        assert_eq!(cache.get("git pul", start), None);
+    }
+
+    #[test]
+    fn test_word_diff_marks_what_changed() {
+        use DiffKind::*;
+        let diff = word_diff("cp src dest", "cp -r src dest/");
+        assert_eq!(diff, vec![
+            (Same, "cp".to_string()),
+            (Added, "-r".to_string()),
+            (Same, "src".to_string()),
+            (Removed, "dest".to_string()),
+            (Added, "dest/".to_string()),
+        ]);
+    }
+}
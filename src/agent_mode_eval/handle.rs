@@ -0,0 +1,170 @@
+//! Shared access to one agent from several callers, such as servers handling
+//! concurrent requests.
+//!
+//! Locks are only held to read or update the conversation, never across
+//! network I/O: a turn is copied out under the lock and streamed afterwards,
+//! and the finished reply is recorded with a second short write.
+
+use std::sync::Arc;
+use tokio::sync::{mpsc, RwLock};
+use super::conversation::Conversation;
+use super::{AgentConfig, AgentError, AgentMessage, AgentMode};
+
+#[derive(Debug, Clone)]
+pub struct AgentHandle {
+    agent: Arc<RwLock<AgentMode>>,
+}
+
+impl AgentHandle {
+    pub fn new(agent: AgentMode) -> Self {
+        Self { agent: Arc::new(RwLock::new(agent)) }
+    }
+
+    /// Record `content` on the active branch, starting a conversation if
+    /// needed, and stream the reply. The reply is added to the conversation
+    /// once it completes.
+    pub async fn send_message(&self, content: String) -> Result<mpsc::Receiver<AgentMessage>, AgentError> {
+        let summary_job = {
+            let mut agent = self.agent.write().await;
+            if agent.conversations.is_none() {
+                agent.start_conversation()?;
+            }
+            agent.push_user_message(content)?;
+            agent.summary_job()
+        };
+        // Written without holding the lock; if it fails the oldest messages
+        // are simply left out of the request
+        if let Some(job) = summary_job {
+            match job.run().await {
+                Ok(summary) => self.agent.write().await.apply_summary(summary)?,
+                Err(e) => log::warn!("Could not summarize the conversation: {}", e),
+            }
+        }
+        let turn = self.agent.read().await.prepare_turn()?;
+
+        let mut events = turn.stream().await;
+        let (tx, rx) = mpsc::channel(100);
+        let agent = Arc::clone(&self.agent);
+        tokio::spawn(async move {
+            let mut reply = String::new();
+            while let Some(event) = events.recv().await {
+                if let AgentMessage::AssistantDelta(delta) = &event {
+                    reply.push_str(delta);
+                }
+                if matches!(event, AgentMessage::Done) {
+                    let _ = agent.write().await.record_assistant_reply(std::mem::take(&mut reply));
+                }
+                if tx.send(event).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    /// Copy of the active branch
+    pub async fn get_history(&self) -> Option<Conversation> {
+        self.agent.read().await.get_conversation_history().cloned()
+    }
+
+    pub async fn toggle(&self) -> bool {
+        self.agent.write().await.toggle()
+    }
+
+    pub async fn is_enabled(&self) -> bool {
+        self.agent.read().await.enabled
+    }
+
+    pub async fn update_config(&self, config: AgentConfig) -> Result<(), AgentError> {
+        self.agent.write().await.update_config(config)
+    }
+
+    pub async fn clear_conversation(&self) {
+        self.agent.write().await.clear_conversation();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+    use crate::agent_mode_eval::ai_client::AiProvider;
+
+    /// OpenAI-style endpoint that waits `delay` before answering one request
+    fn slow_provider(delay: Duration, reply: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let length = text[..header_end]
+                        .lines()
+                        .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + length || read == 0 {
+                        break;
+                    }
+                }
+            }
+            std::thread::sleep(delay);
+            let body = serde_json::json!({
+                "choices": [{ "message": { "role": "assistant", "content": reply } }]
+            })
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(), body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_history_is_readable_while_a_reply_streams() {
+        let config = AgentConfig {
+            provider: AiProvider::OpenAI,
+            api_key: Some("sk-test".to_string()),
+            base_url: Some(slow_provider(Duration::from_millis(800), "ls -la")),
+            tools_enabled: false,
+            ..AgentConfig::default()
+        };
+        let handle = AgentHandle::new(AgentMode::new(config).unwrap());
+
+        let mut events = handle.send_message("list files".to_string()).await.unwrap();
+        assert!(matches!(events.recv().await, Some(AgentMessage::UserMessage(_))));
+
+        // The provider hasn't answered yet; other callers must not wait for it
+        let started = Instant::now();
+        let history = tokio::time::timeout(Duration::from_millis(50), handle.get_history())
+            .await
+            .expect("get_history blocked behind the streaming reply")
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_millis(50));
+        assert_eq!(history.get_user_messages().len(), 1);
+        tokio::time::timeout(Duration::from_millis(50), handle.toggle()).await.unwrap();
+
+        let mut reply = String::new();
+        while let Some(event) = events.recv().await {
+            match event {
+                AgentMessage::AssistantDelta(delta) => reply.push_str(&delta),
+                AgentMessage::Error(e) => panic!("unexpected error: {}", e),
+                AgentMessage::Done => break,
+                _ => {}
+            }
+        }
+        assert_eq!(reply, "ls -la");
+
+        let history = handle.get_history().await.unwrap();
+        assert_eq!(history.messages.last().unwrap().content, "ls -la");
+    }
+}
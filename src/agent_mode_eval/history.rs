@@ -0,0 +1,227 @@
+//! Keeping a conversation inside the model's context window.
+//!
+//! Tokens are estimated per message. Once a request grows past the
+//! configured share of the model's window, the oldest messages are folded
+//! into one summary written by a cheaper model of the same provider. Recent
+//! messages, and the tool results that answer them, are sent as they are.
+//! Whatever still doesn't fit is dropped from the front, so a request never
+//! goes over budget even if summarizing failed.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::ai_client::{AiClient, AiMessage, AiProvider};
+use super::conversation::{Message, MessageRole};
+use super::usage::estimate_tokens;
+use super::{AgentConfig, AgentError};
+
+/// Role and formatting each message costs on top of its text
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+/// Longest piece of one message quoted to the summarizer
+const SUMMARY_QUOTE_CHARS: usize = 4000;
+
+/// Stands in for every message of the branch up to and including `through`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistorySummary {
+    pub content: String,
+    pub through: Uuid,
+}
+
+/// Tokens the model can take in, request and reply together
+pub fn context_limit(provider: &AiProvider, model: &str) -> u32 {
+    match provider {
+        AiProvider::OpenAI if model == "gpt-4" => 8_192,
+        AiProvider::OpenAI if model == "gpt-3.5-turbo" => 16_385,
+        AiProvider::OpenAI if model.starts_with('o') => 200_000,
+        AiProvider::OpenAI => 128_000,
+        AiProvider::Claude => 200_000,
+        AiProvider::Gemini if model == "gemini-1.5-pro" => 2_000_000,
+        AiProvider::Gemini => 1_000_000,
+        AiProvider::Groq if model.starts_with("mixtral") => 32_768,
+        AiProvider::Groq if model.starts_with("gemma") => 8_192,
+        AiProvider::Groq => 128_000,
+        // Ollama's default context, unless the model file raises it
+        AiProvider::Ollama => 8_192,
+        AiProvider::Local => 4_096,
+    }
+}
+
+/// Most tokens a request may use, leaving room for the reply
+pub fn budget(config: &AgentConfig) -> u32 {
+    let limit = context_limit(&config.provider, &config.model);
+    limit.saturating_sub(config.max_tokens.unwrap_or(0)).max(limit / 4)
+}
+
+/// Past this many tokens the oldest messages get summarized
+pub fn summarize_at(config: &AgentConfig) -> u32 {
+    (budget(config) as f32 * config.summarize_threshold.clamp(0.1, 1.0)) as u32
+}
+
+/// The model summaries are written with: the provider's cheapest
+fn summary_model(config: &AgentConfig) -> &str {
+    match config.provider {
+        AiProvider::OpenAI => "gpt-4-mini",
+        AiProvider::Claude => "claude-3-7-haiku-20241022",
+        AiProvider::Gemini => "gemini-1.5-flash",
+        AiProvider::Groq => "llama-3.1-8b-instant",
+        // Local models cost nothing extra; use the one that's loaded
+        AiProvider::Ollama | AiProvider::Local => &config.model,
+    }
+}
+
+pub fn message_tokens(message: &AiMessage) -> u32 {
+    let calls = message
+        .tool_calls
+        .as_ref()
+        .map(|calls| estimate_tokens(&serde_json::to_string(calls).unwrap_or_default()))
+        .unwrap_or(0);
+    estimate_tokens(&message.content) + calls + MESSAGE_OVERHEAD_TOKENS
+}
+
+pub fn request_tokens(messages: &[AiMessage]) -> u32 {
+    messages.iter().map(message_tokens).sum()
+}
+
+/// Drop the oldest messages, and then shorten the longest, until the
+/// request fits in `budget`. System messages at the start are kept, and a
+/// tool result is never left without the call it answers.
+pub fn fit(mut messages: Vec<AiMessage>, budget: u32) -> Vec<AiMessage> {
+    let pinned = messages.iter().take_while(|m| m.role == "system").count();
+    loop {
+        while messages.len() > pinned + 1 && messages[pinned].role == "tool" {
+            messages.remove(pinned);
+        }
+        if request_tokens(&messages) <= budget || messages.len() <= pinned + 1 {
+            break;
+        }
+        messages.remove(pinned);
+    }
+
+    while request_tokens(&messages) > budget {
+        let over = request_tokens(&messages) - budget;
+        let Some(longest) = messages.iter_mut().filter(|m| !m.content.is_empty()).max_by_key(|m| m.content.len()) else {
+            break;
+        };
+        let keep = longest.content.chars().count().saturating_sub(over as usize * 4 + 1);
+        longest.content = longest.content.chars().take(keep).collect();
+    }
+    messages
+}
+
+/// Where the messages to keep start: the newest that fit in half of
+/// `budget` and number at most `max_messages`, never starting on a tool
+/// result. `None` if nothing before that is left to summarize.
+pub fn summary_split(messages: &[Arc<Message>], start: usize, budget: u32, max_messages: usize) -> Option<usize> {
+    let mut keep_from = messages.len();
+    let mut tokens = 0;
+    while keep_from > start && messages.len() - keep_from < max_messages.max(1) {
+        let cost = estimate_tokens(&messages[keep_from - 1].content) + MESSAGE_OVERHEAD_TOKENS;
+        if tokens + cost > budget / 2 && keep_from < messages.len() {
+            break;
+        }
+        tokens += cost;
+        keep_from -= 1;
+    }
+    // Keep a call's results with the assistant message that made it
+    while keep_from > start && matches!(messages.get(keep_from).map(|m| &m.role), Some(MessageRole::Tool)) {
+        keep_from -= 1;
+    }
+    (keep_from > start).then_some(keep_from)
+}
+
+/// A summary to be written before the next request
+#[derive(Debug, Clone)]
+pub struct SummaryJob {
+    client: AiClient,
+    prompt: String,
+    through: Uuid,
+}
+
+impl SummaryJob {
+    /// Summarize `messages`, building on the summary they follow, if any
+    pub fn new(config: &AgentConfig, previous: Option<&str>, messages: &[Arc<Message>]) -> Option<Self> {
+        let through = messages.last()?.id;
+        let client = AiClient::new(AgentConfig {
+            model: summary_model(config).to_string(),
+            tools_enabled: false,
+            max_tokens: Some(1024),
+            temperature: 0.2,
+            ..config.clone()
+        })
+        .ok()?;
+
+        let mut prompt = String::from(
+            "Summarize this earlier part of a conversation between a user and a terminal assistant. \
+             Keep what was decided, file paths, commands and what they printed, and any tool results \
+             later messages may rely on, quoting exact values. Reply with the summary only.\n",
+        );
+        if let Some(previous) = previous {
+            prompt.push_str(&format!("\nSummary of what came before:\n{}\n", previous));
+        }
+        for message in messages {
+            let speaker = match message.role {
+                MessageRole::User => "User",
+                MessageRole::Assistant => "Assistant",
+                MessageRole::System => "System",
+                MessageRole::Tool => "Tool result",
+            };
+            let content: String = message.content.chars().take(SUMMARY_QUOTE_CHARS).collect();
+            prompt.push_str(&format!("\n{}: {}", speaker, content));
+            for call in message.tool_calls.iter().flatten() {
+                prompt.push_str(&format!("\n(called {})", call.describe()));
+            }
+        }
+        Some(Self { client, prompt, through })
+    }
+
+    pub async fn run(self) -> Result<HistorySummary, AgentError> {
+        let message = AiMessage { role: "user".to_string(), content: self.prompt, tool_calls: None, tool_call_id: None };
+        let response = self.client.complete(vec![message], None).await?;
+        Ok(HistorySummary { content: response.content.trim().to_string(), through: self.through })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ai_message(role: &str, chars: usize) -> AiMessage {
+        AiMessage { role: role.to_string(), content: "x".repeat(chars), tool_calls: None, tool_call_id: None }
+    }
+
+    #[test]
+    fn test_fit_never_exceeds_budget() {
+        for budget in [20, 100, 500, 5_000] {
+            let mut messages = vec![ai_message("system", 200)];
+            for i in 0..40 {
+                messages.push(ai_message(["user", "assistant", "tool"][i % 3], 37 * i));
+            }
+            let fitted = fit(messages, budget);
+            assert!(request_tokens(&fitted) <= budget, "{} tokens over {}", request_tokens(&fitted), budget);
+            assert_eq!(fitted[0].role, "system");
+            assert!(fitted.len() < 3 || fitted[1].role != "tool");
+        }
+
+        // Small requests go out untouched
+        let messages = vec![ai_message("system", 10), ai_message("user", 10)];
+        assert_eq!(fit(messages, 100).len(), 2);
+    }
+
+    #[test]
+    fn test_split_keeps_tool_results_with_their_call() {
+        let message = |role: MessageRole| Arc::new(Message::new(role, "x".repeat(400)));
+        let messages = vec![
+            message(MessageRole::User),
+            message(MessageRole::Assistant),
+            message(MessageRole::User),
+            message(MessageRole::Assistant),
+            message(MessageRole::Tool),
+            message(MessageRole::Tool),
+        ];
+        // Room for two messages: the split would land on a tool result
+        assert_eq!(summary_split(&messages, 0, 420, 10), Some(3));
+        assert_eq!(summary_split(&messages, 0, 100_000, 2), Some(3));
+        assert_eq!(summary_split(&messages, 0, 100_000, 10), None);
+    }
+}
@@ -4,33 +4,126 @@ use tokio::sync::mpsc;
 use uuid::Uuid;
 
 pub mod ai_client;
+pub mod availability;
+pub mod context;
 pub mod conversation;
+pub mod fix;
+pub mod handle;
+pub mod history;
+pub mod store;
+pub mod system_info;
 pub mod tools;
+pub mod usage;
+pub mod web;
 
 use ai_client::{AiClient, AiProvider, AiResponse, StreamingResponse};
-use conversation::{Conversation, Message, MessageRole};
-use tools::{ToolRegistry, ToolCall, ToolResult};
+use conversation::{Conversation, ConversationTree, Message, MessageRole};
+use tools::{Approval, ApprovalMode, ToolRegistry, ToolCall, ToolResult};
 
 #[derive(Debug, Clone)]
 pub struct AgentMode {
     pub enabled: bool,
-    pub current_conversation: Option<Conversation>,
+    /// All branches of the current conversation; the active one is sent to the provider
+    pub conversations: Option<ConversationTree>,
     pub ai_client: AiClient,
     pub tool_registry: ToolRegistry,
     pub auto_execute: bool,
     pub context_window: usize,
 }
 
-#[derive(Debug, Clone)]
+/// Events produced by an agent turn. This is the single message type shared by
+/// the GUI and anything that streams agent output, so it is serializable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum AgentMessage {
-    StartConversation,
-    SendMessage(String),
-    ExecuteCommand(String),
+    /// Echo of the user's prompt as recorded in the conversation
+    UserMessage(String),
+    /// Incremental assistant text
+    AssistantDelta(String),
     ToolCall(ToolCall),
     ToolResult(ToolResult),
-    StreamingResponse(String),
-    ConversationEnded,
+    /// Informational notice from NeoTerm itself (not the model)
+    SystemNotice(String),
     Error(String),
+    Usage(ai_client::Usage),
+    /// The turn has finished; no further messages follow
+    Done,
+}
+
+impl AgentMessage {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, AgentMessage::Done | AgentMessage::Error(_))
+    }
+}
+
+/// Tool-call rounds allowed for one prompt before the agent stops asking
+pub const MAX_TOOL_ROUNDS: usize = 10;
+
+/// Tool calls requested by one reply and the results gathered for them.
+/// The agent only continues once every call has a result.
+#[derive(Debug, Clone, Default)]
+pub struct ToolRound {
+    calls: Vec<ToolCall>,
+    results: Vec<ToolResult>,
+}
+
+impl ToolRound {
+    pub fn push_call(&mut self, call: ToolCall) {
+        self.calls.push(call);
+    }
+
+    pub fn calls(&self) -> &[ToolCall] {
+        &self.calls
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Store `result`; false if it answers no call or one already answered
+    pub fn resolve(&mut self, result: ToolResult) -> bool {
+        let known = self.calls.iter().any(|call| call.id == result.tool_call_id);
+        let answered = self.results.iter().any(|r| r.tool_call_id == result.tool_call_id);
+        if known && !answered {
+            self.results.push(result);
+        }
+        known && !answered
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.calls.iter().all(|call| self.results.iter().any(|r| r.tool_call_id == call.id))
+    }
+
+    /// The calls, and their results in the same order
+    fn into_parts(mut self) -> (Vec<ToolCall>, Vec<ToolResult>) {
+        let calls = self.calls;
+        let results = calls
+            .iter()
+            .filter_map(|call| {
+                let index = self.results.iter().position(|r| r.tool_call_id == call.id)?;
+                Some(self.results.swap_remove(index))
+            })
+            .collect();
+        (calls, results)
+    }
+}
+
+impl From<StreamingResponse> for AgentMessage {
+    fn from(response: StreamingResponse) -> Self {
+        AgentMessage::AssistantDelta(response.content)
+    }
+}
+
+impl From<AgentError> for AgentMessage {
+    fn from(error: AgentError) -> Self {
+        AgentMessage::Error(error.to_string())
+    }
+}
+
+impl From<ai_client::AiClientError> for AgentMessage {
+    fn from(error: ai_client::AiClientError) -> Self {
+        AgentMessage::Error(error.to_string())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +137,30 @@ pub struct AgentConfig {
     pub system_prompt: String,
     pub tools_enabled: bool,
     pub auto_execute_commands: bool,
+    /// Editing the last prompt forks the conversation instead of replacing the turn
+    #[serde(default)]
+    pub fork_on_edit: bool,
+    /// Whether tool calls that run commands or write files wait for the user
+    #[serde(default)]
+    pub approval_mode: ApprovalMode,
+    /// URLs the `fetch_url` tool may read
+    #[serde(default)]
+    pub web_access: web::WebAccess,
+    /// Most messages sent after the summary; older ones are summarized
+    #[serde(default = "default_max_history_length")]
+    pub max_history_length: usize,
+    /// Share of the model's context a request may fill before the oldest
+    /// messages are summarized
+    #[serde(default = "default_summarize_threshold")]
+    pub summarize_threshold: f32,
+}
+
+fn default_max_history_length() -> usize {
+    50
+}
+
+fn default_summarize_threshold() -> f32 {
+    0.75
 }
 
 impl Default for AgentConfig {
@@ -58,6 +175,11 @@ impl Default for AgentConfig {
             system_prompt: "You are a helpful AI assistant integrated into a terminal. You can help users with command-line tasks, explain commands, and execute shell commands when requested. Always be concise and practical in your responses.".to_string(),
             tools_enabled: true,
             auto_execute_commands: false,
+            fork_on_edit: false,
+            approval_mode: ApprovalMode::default(),
+            web_access: web::WebAccess::default(),
+            max_history_length: default_max_history_length(),
+            summarize_threshold: default_summarize_threshold(),
         }
     }
 }
@@ -116,22 +238,47 @@ impl AgentConfig {
 impl AgentMode {
     pub fn new(config: AgentConfig) -> Result<Self, AgentError> {
         let ai_client = AiClient::new(config.clone())?;
-        let tool_registry = ToolRegistry::new();
+        let mut tool_registry = ToolRegistry::new();
+        tool_registry.set_web_access(config.web_access.clone());
         
         Ok(Self {
             enabled: false,
-            current_conversation: None,
+            conversations: None,
             ai_client,
             tool_registry,
             auto_execute: config.auto_execute_commands,
-            context_window: 8192,
+            context_window: config.max_history_length,
         })
     }
 
+    /// Switch provider or model settings, keeping the conversation and tools
+    pub fn set_config(&mut self, config: AgentConfig) -> Result<(), AgentError> {
+        self.ai_client = AiClient::new(config.clone())?;
+        self.auto_execute = config.auto_execute_commands;
+        self.context_window = config.max_history_length;
+        self.tool_registry.set_web_access(config.web_access);
+        Ok(())
+    }
+
+    /// Share the session's read-only switch with the command tool
+    pub fn set_read_only(&mut self, read_only: crate::read_only::ReadOnly) {
+        self.tool_registry.set_read_only(read_only);
+    }
+
+    /// Session details the system info tool reports
+    pub fn set_host(&mut self, host: system_info::HostContext) {
+        self.tool_registry.set_host(host);
+    }
+
+    /// Whether the configured provider can be used
+    pub fn status(&self) -> availability::AiStatus {
+        availability::AiStatus::of(&self.ai_client.config)
+    }
+
     pub fn toggle(&mut self) -> bool {
         self.enabled = !self.enabled;
         if !self.enabled {
-            self.current_conversation = None;
+            self.conversations = None;
         }
         self.enabled
     }
@@ -139,59 +286,198 @@ impl AgentMode {
     pub fn start_conversation(&mut self) -> Result<Uuid, AgentError> {
         let conversation = Conversation::new(self.ai_client.config.system_prompt.clone());
         let id = conversation.id;
-        self.current_conversation = Some(conversation);
+        self.conversations = Some(ConversationTree::new(conversation));
+        Ok(id)
+    }
+
+    /// Continue a saved conversation
+    pub fn load_conversation(&mut self, tree: ConversationTree) {
+        self.conversations = Some(tree);
+    }
+
+    fn active_conversation_mut(&mut self) -> Result<&mut Conversation, AgentError> {
+        self.conversations
+            .as_mut()
+            .map(|tree| tree.active_mut())
+            .ok_or(AgentError::NoActiveConversation)
+    }
+
+    /// Append the user's prompt to the active branch and return its message id
+    pub fn push_user_message(&mut self, content: String) -> Result<Uuid, AgentError> {
+        let message = Message::new(MessageRole::User, content);
+        let id = message.id;
+        self.active_conversation_mut()?.add_message(message);
         Ok(id)
     }
 
-    pub async fn send_message(&mut self, content: String) -> Result<mpsc::Receiver<String>, AgentError> {
-        let conversation = self.current_conversation
+    /// Record a completed streamed reply on the active branch
+    pub fn record_assistant_reply(&mut self, content: String) -> Result<Uuid, AgentError> {
+        let message = Message::new(MessageRole::Assistant, content);
+        let id = message.id;
+        self.active_conversation_mut()?.add_message(message);
+        Ok(id)
+    }
+
+    /// Add a reply's usage to the active branch, priced for the configured
+    /// model, and return what the whole conversation has used
+    pub fn record_usage(&mut self, usage: &ai_client::Usage, prices: &[usage::ModelPrice]) -> Result<usage::TokenTotals, AgentError> {
+        let config = &self.ai_client.config;
+        let cost = usage::price_for(prices, &config.provider, &config.model).map(|price| price.cost(usage));
+        let tree = self.conversations.as_mut().ok_or(AgentError::NoActiveConversation)?;
+        tree.active_mut().record_usage(usage, cost);
+        Ok(tree.usage())
+    }
+
+    /// Record a reply that asked for tools, followed by what each call
+    /// returned; `respond` then lets the model continue from the results
+    pub fn record_tool_round(&mut self, content: String, round: ToolRound) -> Result<Uuid, AgentError> {
+        let (calls, results) = round.into_parts();
+        let mut reply = Message::new(MessageRole::Assistant, content);
+        reply.tool_calls = Some(calls);
+        let id = reply.id;
+        let conversation = self.active_conversation_mut()?;
+        conversation.add_message(reply);
+        for result in &results {
+            conversation.add_message(Message::tool_result(result));
+        }
+        Ok(id)
+    }
+
+    /// Whether `call` may run now, must wait for the user, or is refused
+    pub fn approval(&self, call: &ToolCall) -> Approval {
+        self.tool_registry.approval(call, self.ai_client.config.approval_mode)
+    }
+
+    /// Branch the active conversation after `message_id` and switch to the new branch
+    pub fn fork_at(&mut self, message_id: Uuid) -> Result<Uuid, AgentError> {
+        self.conversations
+            .as_mut()
+            .ok_or(AgentError::NoActiveConversation)?
+            .fork_at(message_id)
+            .ok_or(AgentError::MessageNotFound(message_id))
+    }
+
+    pub fn switch_branch(&mut self, branch_id: Uuid) -> Result<(), AgentError> {
+        let tree = self.conversations
             .as_mut()
             .ok_or(AgentError::NoActiveConversation)?;
+        if tree.switch_to(branch_id) {
+            Ok(())
+        } else {
+            Err(AgentError::BranchNotFound(branch_id))
+        }
+    }
 
-        // Add user message to conversation
-        conversation.add_message(Message {
-            role: MessageRole::User,
-            content: content.clone(),
-            timestamp: chrono::Utc::now(),
-            tool_calls: None,
-        });
+    /// Replace the last user message (and the reply to it) with `content`.
+    /// With `fork_on_edit` the original turn is kept on its own branch.
+    /// Returns the id of the new user message; call `respond` to stream a reply.
+    pub fn edit_last_user_message(&mut self, content: String) -> Result<Uuid, AgentError> {
+        let fork = self.ai_client.config.fork_on_edit;
+        let tree = self.conversations
+            .as_mut()
+            .ok_or(AgentError::NoActiveConversation)?;
+        let last_prompt = tree.active()
+            .get_user_messages()
+            .last()
+            .map(|msg| msg.id)
+            .ok_or(AgentError::NoUserMessage)?;
 
-        // Prepare messages for AI
-        let messages = self.prepare_messages_for_ai(conversation)?;
-        
-        // Get streaming response
-        let (tx, rx) = mpsc::channel(100);
-        let ai_client = self.ai_client.clone();
+        if fork {
+            tree.fork_before(last_prompt);
+        } else {
+            tree.active_mut().truncate_from(last_prompt);
+        }
+
+        self.push_user_message(content)
+    }
+
+    pub async fn send_message(&mut self, content: String) -> Result<mpsc::Receiver<AgentMessage>, AgentError> {
+        self.push_user_message(content)?;
+        self.respond().await
+    }
+
+    /// Stream a reply to the active branch as it currently stands
+    pub async fn respond(&self) -> Result<mpsc::Receiver<AgentMessage>, AgentError> {
+        Ok(self.prepare_turn()?.stream().await)
+    }
+
+    /// Everything one reply needs, copied out of the agent so streaming never
+    /// borrows it: callers sharing the agent only hold a lock for this call
+    pub fn prepare_turn(&self) -> Result<Turn, AgentError> {
+        let conversation = self.get_conversation_history()
+            .ok_or(AgentError::NoActiveConversation)?;
+        let prompt = conversation.get_user_messages()
+            .last()
+            .map(|msg| msg.content.clone())
+            .unwrap_or_default();
         let tools = if self.ai_client.config.tools_enabled {
             Some(self.tool_registry.get_available_tools())
         } else {
             None
         };
 
-        tokio::spawn(async move {
-            match ai_client.stream_completion(messages, tools).await {
-                Ok(mut stream) => {
-                    while let Some(chunk) = stream.next().await {
-                        match chunk {
-                            Ok(response) => {
-                                if let Err(_) = tx.send(response.content).await {
-                                    break;
-                                }
-                            }
-                            Err(e) => {
-                                let _ = tx.send(format!("Error: {}", e)).await;
-                                break;
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    let _ = tx.send(format!("Failed to get AI response: {}", e)).await;
-                }
-            }
-        });
+        Ok(Turn {
+            client: self.ai_client.clone(),
+            messages: self.prepare_messages_for_ai(conversation)?,
+            tools,
+            prompt,
+        })
+    }
 
-        Ok(rx)
+    /// Summary to write before the next request, if the active branch has
+    /// outgrown the configured share of the context or message count
+    pub fn summary_job(&self) -> Option<history::SummaryJob> {
+        let conversation = self.get_conversation_history()?;
+        let config = &self.ai_client.config;
+        let (previous, start) = match conversation.history_summary() {
+            Some((summary, start)) => (Some(summary.content.as_str()), start),
+            None => (None, 0),
+        };
+        let pending = &conversation.messages[start..];
+        let tokens: u32 = pending
+            .iter()
+            .map(|msg| usage::estimate_tokens(&msg.content))
+            .sum::<u32>()
+            + usage::estimate_tokens(&conversation.system_prompt);
+        if tokens <= history::summarize_at(config) && pending.len() <= self.context_window {
+            return None;
+        }
+        let split = history::summary_split(&conversation.messages, start, history::summarize_at(config), self.context_window)?;
+        history::SummaryJob::new(config, previous, &conversation.messages[start..split])
+    }
+
+    /// Store a summary written by a [`history::SummaryJob`] on the active branch
+    pub fn apply_summary(&mut self, summary: history::HistorySummary) -> Result<(), AgentError> {
+        let tree = self.conversations.as_mut().ok_or(AgentError::NoActiveConversation)?;
+        let conversation = tree.active_mut();
+        if conversation.position_of(summary.through).is_none() {
+            return Err(AgentError::MessageNotFound(summary.through));
+        }
+        conversation.metadata.history_summary = Some(summary);
+        Ok(())
+    }
+
+    /// Summarize the oldest messages if the next request needs it; whether
+    /// anything was summarized
+    pub async fn compact(&mut self) -> Result<bool, AgentError> {
+        let Some(job) = self.summary_job() else {
+            return Ok(false);
+        };
+        self.apply_summary(job.run().await?)?;
+        Ok(true)
+    }
+
+    /// A request to turn `request` into one command for `shell`, kept out of
+    /// the conversation
+    pub fn generate_command(&self, request: &str, shell: &str) -> CommandTranslation {
+        let prompt = format!(
+            "Write a single {} command for {} that does the following. Reply with the command only, \
+             without explanation or code fences.\n\n{}",
+            shell,
+            std::env::consts::OS,
+            request
+        );
+        CommandTranslation { client: self.ai_client.clone(), prompt }
     }
 
     pub async fn execute_tool_call(&mut self, tool_call: ToolCall) -> Result<ToolResult, AgentError> {
@@ -207,36 +493,51 @@ impl AgentMode {
             role: "system".to_string(),
             content: conversation.system_prompt.clone(),
             tool_calls: None,
+            tool_call_id: None,
         });
 
-        // Add conversation messages (with context window limit)
-        let recent_messages = if conversation.messages.len() > self.context_window {
-            &conversation.messages[conversation.messages.len() - self.context_window..]
-        } else {
-            &conversation.messages
+        // The summary replaces the messages it covers
+        let start = match conversation.history_summary() {
+            Some((summary, start)) => {
+                messages.push(ai_client::AiMessage {
+                    role: "system".to_string(),
+                    content: format!("Summary of the earlier conversation:\n{}", summary.content),
+                    tool_calls: None,
+                    tool_call_id: None,
+                });
+                start
+            }
+            None => 0,
         };
 
+        // Add conversation messages (with context window limit)
+        let after_summary = &conversation.messages[start..];
+        let recent_messages = &after_summary[after_summary.len().saturating_sub(self.context_window)..];
+
         for msg in recent_messages {
             messages.push(ai_client::AiMessage {
                 role: match msg.role {
                     MessageRole::User => "user".to_string(),
                     MessageRole::Assistant => "assistant".to_string(),
                     MessageRole::System => "system".to_string(),
+                    MessageRole::Tool => "tool".to_string(),
                 },
                 content: msg.content.clone(),
                 tool_calls: msg.tool_calls.clone(),
+                tool_call_id: msg.tool_call_id.clone(),
             });
         }
 
-        Ok(messages)
+        Ok(history::fit(messages, history::budget(&self.ai_client.config)))
     }
 
+    /// The active branch of the current conversation
     pub fn get_conversation_history(&self) -> Option<&Conversation> {
-        self.current_conversation.as_ref()
+        self.conversations.as_ref().map(|tree| tree.active())
     }
 
     pub fn clear_conversation(&mut self) {
-        self.current_conversation = None;
+        self.conversations = None;
     }
 
     pub fn update_config(&mut self, config: AgentConfig) -> Result<(), AgentError> {
@@ -245,16 +546,119 @@ impl AgentMode {
     }
 }
 
+/// One request to the provider, owning its copy of the client and messages
+#[derive(Debug, Clone)]
+pub struct Turn {
+    client: AiClient,
+    messages: Vec<ai_client::AiMessage>,
+    tools: Option<Vec<tools::Tool>>,
+    prompt: String,
+}
+
+impl Turn {
+    /// Start streaming; events arrive on the receiver until `Done` or `Error`
+    pub async fn stream(self) -> mpsc::Receiver<AgentMessage> {
+        use futures::StreamExt;
+
+        let (tx, rx) = mpsc::channel(100);
+        let _ = tx.send(AgentMessage::UserMessage(self.prompt)).await;
+
+        let Turn { client, messages, tools, .. } = self;
+        tokio::spawn(async move {
+            // Kept to estimate usage if the provider doesn't report it
+            let prompt = messages.clone();
+            match client.stream_completion(messages, tools).await {
+                Ok(mut stream) => {
+                    let mut reply = String::new();
+                    let mut usage = None;
+                    while let Some(chunk) = stream.next().await {
+                        match chunk {
+                            Ok(mut response) => {
+                                reply.push_str(&response.content);
+                                usage = response.usage.take().or(usage);
+                                let tool_calls = response.tool_calls.take().unwrap_or_default();
+                                if tx.send(AgentMessage::from(response)).await.is_err() {
+                                    return;
+                                }
+                                for call in tool_calls {
+                                    if tx.send(AgentMessage::ToolCall(call)).await.is_err() {
+                                        return;
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                let _ = tx.send(AgentMessage::from(e)).await;
+                                return;
+                            }
+                        }
+                    }
+                    let usage = usage.unwrap_or_else(|| ai_client::Usage::estimate(&prompt, &reply));
+                    let _ = tx.send(AgentMessage::Usage(usage)).await;
+                    let _ = tx.send(AgentMessage::Done).await;
+                }
+                Err(e) => {
+                    let _ = tx.send(AgentMessage::Error(format!("Failed to get AI response: {}", e))).await;
+                }
+            }
+        });
+
+        rx
+    }
+}
+
+/// A command written from a natural-language request
+#[derive(Debug, Clone)]
+pub struct CommandTranslation {
+    client: AiClient,
+    prompt: String,
+}
+
+impl CommandTranslation {
+    pub async fn run(self) -> Result<String, AgentError> {
+        let message = ai_client::AiMessage { role: "user".to_string(), content: self.prompt, tool_calls: None, tool_call_id: None };
+        let response = self.client.complete(vec![message], None).await?;
+        extract_command(&response.content)
+            .ok_or(AgentError::NoCommand)
+    }
+}
+
+/// The command in a reply, without code fences or a leading prompt sign
+fn extract_command(reply: &str) -> Option<String> {
+    let reply = reply.trim();
+    let body = match reply.strip_prefix("```") {
+        // Skip the language tag on the opening fence
+        Some(fenced) => fenced.split_once('\n').map_or("", |(_, rest)| rest).split("```").next().unwrap_or(""),
+        None => reply,
+    };
+    let lines: Vec<&str> = body
+        .lines()
+        .map(|line| line.trim())
+        .map(|line| line.strip_prefix("$ ").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .collect();
+    let command = lines.join("\n");
+    let command = command.trim_matches('`').trim();
+    (!command.is_empty()).then(|| command.to_string())
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AgentError {
     #[error("No active conversation")]
     NoActiveConversation,
+    #[error("No user message to edit")]
+    NoUserMessage,
+    #[error("Message not found: {0}")]
+    MessageNotFound(Uuid),
+    #[error("Conversation branch not found: {0}")]
+    BranchNotFound(Uuid),
     #[error("AI client error: {0}")]
     AiClientError(#[from] ai_client::AiClientError),
     #[error("Tool error: {0}")]
     ToolError(#[from] tools::ToolError),
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
+    #[error("The reply didn't contain a command")]
+    NoCommand,
     #[error("Configuration error: {0}")]
     ConfigError(String),
 }
@@ -281,10 +685,215 @@ mod tests {
         
         // Start conversation
         let conv_id = agent.start_conversation().unwrap();
-        assert!(agent.current_conversation.is_some());
+        assert!(agent.conversations.is_some());
         
         // Clear conversation
         agent.clear_conversation();
-        assert!(agent.current_conversation.is_none());
+        assert!(agent.conversations.is_none());
+    }
+
+    fn agent_with_two_turns(fork_on_edit: bool) -> AgentMode {
+        let mut agent = AgentMode::new(AgentConfig { fork_on_edit, ..AgentConfig::default() }).unwrap();
+        agent.start_conversation().unwrap();
+        agent.push_user_message("find big files".to_string()).unwrap();
+        agent.record_assistant_reply("du -sh *".to_string()).unwrap();
+        agent.push_user_message("only in /tmp".to_string()).unwrap();
+        agent.record_assistant_reply("du -sh /tmp/*".to_string()).unwrap();
+        agent
+    }
+
+    fn sent_contents(agent: &AgentMode) -> Vec<String> {
+        agent.prepare_messages_for_ai(agent.get_conversation_history().unwrap())
+            .unwrap()
+            .into_iter()
+            .skip(1)
+            .map(|m| m.content)
+            .collect()
+    }
+
+    #[test]
+    fn test_edit_last_prompt_replaces_turn() {
+        let mut agent = agent_with_two_turns(false);
+        agent.edit_last_user_message("only in /var".to_string()).unwrap();
+
+        assert_eq!(sent_contents(&agent), vec!["find big files", "du -sh *", "only in /var"]);
+        assert_eq!(agent.conversations.as_ref().unwrap().branch_count(), 1);
+    }
+
+    #[test]
+    fn test_edit_last_prompt_can_fork() {
+        let mut agent = agent_with_two_turns(true);
+        agent.edit_last_user_message("only in /var".to_string()).unwrap();
+
+        assert_eq!(sent_contents(&agent), vec!["find big files", "du -sh *", "only in /var"]);
+        let tree = agent.conversations.as_ref().unwrap();
+        assert_eq!(tree.branch_count(), 2);
+        assert_eq!(tree.branches()[0].messages.len(), 4);
+    }
+
+    #[test]
+    fn test_edit_without_prompt_is_an_error() {
+        let mut agent = AgentMode::new(AgentConfig::default()).unwrap();
+        agent.start_conversation().unwrap();
+        assert!(matches!(
+            agent.edit_last_user_message("hi".to_string()),
+            Err(AgentError::NoUserMessage)
+        ));
+    }
+
+    #[test]
+    fn test_active_branch_is_sent_to_provider() {
+        let mut agent = AgentMode::new(AgentConfig::default()).unwrap();
+        agent.start_conversation().unwrap();
+
+        let first = agent.push_user_message("how do I list files?".to_string()).unwrap();
+        let reply = agent.record_assistant_reply("Use ls".to_string()).unwrap();
+        agent.push_user_message("and hidden ones?".to_string()).unwrap();
+        agent.record_assistant_reply("ls -a".to_string()).unwrap();
+
+        agent.fork_at(reply).unwrap();
+        agent.push_user_message("and sorted by size?".to_string()).unwrap();
+
+        let sent = agent.prepare_messages_for_ai(agent.get_conversation_history().unwrap()).unwrap();
+        let contents: Vec<&str> = sent.iter().skip(1).map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["how do I list files?", "Use ls", "and sorted by size?"]);
+
+        assert!(matches!(agent.fork_at(Uuid::new_v4()), Err(AgentError::MessageNotFound(_))));
+        let root = agent.conversations.as_ref().unwrap().summaries()[0].id;
+        agent.switch_branch(root).unwrap();
+        assert_eq!(agent.get_conversation_history().unwrap().messages.len(), 4);
+        assert_eq!(agent.get_conversation_history().unwrap().messages[0].id, first);
+    }
+
+    #[test]
+    fn test_usage_is_priced_for_the_configured_model() {
+        let mut agent = agent_with_two_turns(false);
+        let usage = ai_client::Usage { prompt_tokens: 1_000_000, completion_tokens: 0, total_tokens: 1_000_000, estimated: false };
+
+        let totals = agent.record_usage(&usage, &usage::default_prices()).unwrap();
+        assert_eq!(totals.cost_usd, Some(2.5));
+        let totals = agent.record_usage(&usage, &[]).unwrap();
+        assert_eq!((totals.total(), totals.cost_usd), (2_000_000, Some(2.5)));
+        assert_eq!(agent.get_conversation_history().unwrap().stats().usage, totals);
+    }
+
+    #[test]
+    fn test_tool_round_is_recorded_for_the_next_turn() {
+        let mut agent = agent_with_two_turns(false);
+        let call = |id: &str| ToolCall {
+            id: id.to_string(),
+            name: "execute_command".to_string(),
+            arguments: HashMap::from([("command".to_string(), serde_json::json!("ls"))]),
+        };
+        assert_eq!(agent.approval(&call("a")), Approval::Ask);
+
+        let mut round = ToolRound::default();
+        round.push_call(call("a"));
+        round.push_call(call("b"));
+        assert!(round.resolve(ToolResult::error(&call("b"), tools::DENIED_BY_USER)));
+        assert!(!round.is_complete());
+        assert!(!round.resolve(ToolResult::error(&call("c"), "unknown")));
+        assert!(round.resolve(ToolResult { tool_call_id: "a".to_string(), success: true, output: "src".to_string(), error: None }));
+        assert!(round.is_complete());
+
+        agent.record_tool_round("Let me look".to_string(), round).unwrap();
+        let sent = agent.prepare_messages_for_ai(agent.get_conversation_history().unwrap()).unwrap();
+        let roles: Vec<&str> = sent.iter().skip(5).map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["assistant", "tool", "tool"]);
+        assert_eq!(sent[5].tool_calls.as_ref().unwrap().len(), 2);
+        assert_eq!(sent[6].tool_call_id.as_deref(), Some("a"));
+        assert_eq!(sent[6].content, "src");
+        assert!(sent[7].content.starts_with("Error: The user denied"));
+    }
+
+    #[test]
+    fn test_long_history_is_summarized_and_kept_within_budget() {
+        let config = AgentConfig {
+            provider: AiProvider::Local,
+            model: "custom-model".to_string(),
+            max_tokens: Some(1024),
+            max_history_length: 6,
+            ..AgentConfig::default()
+        };
+        let budget = history::budget(&config);
+        let mut agent = AgentMode::new(config).unwrap();
+        agent.start_conversation().unwrap();
+        for turn in 0..30 {
+            agent.push_user_message(format!("question {}: {}", turn, "x".repeat(2000))).unwrap();
+            agent.record_assistant_reply("y".repeat(2000)).unwrap();
+            let sent = agent.prepare_turn().unwrap().messages;
+            assert!(history::request_tokens(&sent) <= budget);
+        }
+        assert!(agent.summary_job().is_some());
+
+        let through = agent.get_conversation_history().unwrap().messages[49].id;
+        agent.apply_summary(history::HistorySummary { content: "asked 25 questions".to_string(), through }).unwrap();
+        let sent = agent.prepare_messages_for_ai(agent.get_conversation_history().unwrap()).unwrap();
+        assert_eq!(sent[1].content, "Summary of the earlier conversation:\nasked 25 questions");
+        assert!(sent.len() <= 2 + 6);
+        assert!(history::request_tokens(&sent) <= budget);
+        assert!(sent.last().unwrap().content.starts_with('y'));
+        assert!(agent.apply_summary(history::HistorySummary { content: String::new(), through: Uuid::new_v4() }).is_err());
+    }
+
+    #[test]
+    fn test_agent_message_conversions() {
+        let delta = AgentMessage::from(StreamingResponse {
+            content: "hello".to_string(),
+            is_complete: false,
+            usage: None,
+            tool_calls: None,
+        });
+        assert!(matches!(delta, AgentMessage::AssistantDelta(ref text) if text == "hello"));
+
+        let error = AgentMessage::from(AgentError::NoActiveConversation);
+        assert!(matches!(error, AgentMessage::Error(ref text) if text == "No active conversation"));
+        assert!(error.is_terminal());
+        assert!(AgentMessage::Done.is_terminal());
+        assert!(!AgentMessage::SystemNotice("hi".to_string()).is_terminal());
+    }
+
+    #[test]
+    fn test_agent_message_serde_round_trip() {
+        let messages = vec![
+            AgentMessage::UserMessage("list files".to_string()),
+            AgentMessage::AssistantDelta("Sure".to_string()),
+            AgentMessage::ToolCall(ToolCall {
+                id: "call_1".to_string(),
+                name: "list_directory".to_string(),
+                arguments: HashMap::new(),
+            }),
+            AgentMessage::ToolResult(ToolResult {
+                tool_call_id: "call_1".to_string(),
+                success: true,
+                output: "src".to_string(),
+                error: None,
+            }),
+            AgentMessage::Usage(ai_client::Usage {
+                prompt_tokens: 10,
+                completion_tokens: 5,
+                total_tokens: 15,
+                estimated: false,
+            }),
+            AgentMessage::Done,
+        ];
+
+        for message in messages {
+            let json = serde_json::to_string(&message).unwrap();
+            let decoded: AgentMessage = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+        }
+
+        let json = serde_json::to_value(AgentMessage::AssistantDelta("x".to_string())).unwrap();
+        assert_eq!(json["type"], "assistant_delta");
+        assert_eq!(json["data"], "x");
+    }
+
+    #[test]
+    fn test_extract_command_strips_formatting() {
+        assert_eq!(extract_command("du -ah . | sort -rh | head -n 5\n").as_deref(), Some("du -ah . | sort -rh | head -n 5"));
+        assert_eq!(extract_command("```bash\n$ ls -S | head -5\n```").as_deref(), Some("ls -S | head -5"));
+        assert_eq!(extract_command("`git log -1`").as_deref(), Some("git log -1"));
+        assert_eq!(extract_command("```\n```"), None);
     }
 }
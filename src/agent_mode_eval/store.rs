@@ -0,0 +1,224 @@
+//! Saved agent conversations, one JSON file per conversation tree in the
+//! `conversations/` config directory.
+//!
+//! Each file starts with a small summary (title, timestamps, message count)
+//! so the sidebar can list conversations without building their trees; a
+//! tree is only loaded when it's opened.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::conversation::{ConversationTree, MessageRole};
+use super::usage::TokenTotals;
+
+/// Characters of the first prompt used as the title of an untitled conversation
+const TITLE_PREVIEW_CHARS: usize = 48;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("IO error: {0}")]
+    IoError(String),
+    #[error("Invalid conversation file {0}: {1}")]
+    ParseError(PathBuf, String),
+    #[error("Conversation not found: {0}")]
+    NotFound(Uuid),
+}
+
+/// Sidebar entry for one saved conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub id: Uuid,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Messages on the active branch
+    pub message_count: usize,
+    /// Tokens spent across all branches
+    #[serde(default)]
+    pub usage: TokenTotals,
+}
+
+impl ConversationSummary {
+    pub fn of(tree: &ConversationTree) -> Self {
+        let root = tree.root();
+        let active = tree.active();
+        let title = root.metadata.title.clone().unwrap_or_else(|| {
+            active
+                .get_user_messages()
+                .first()
+                .map(|message| preview(&message.content))
+                .unwrap_or_else(|| "New conversation".to_string())
+        });
+        Self {
+            id: root.id,
+            title,
+            created_at: root.created_at,
+            updated_at: tree.branches().iter().map(|branch| branch.updated_at).max().unwrap_or(root.updated_at),
+            message_count: active.messages.iter().filter(|m| !matches!(m.role, MessageRole::System)).count(),
+            usage: tree.usage(),
+        }
+    }
+}
+
+fn preview(content: &str) -> String {
+    let line = content.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > TITLE_PREVIEW_CHARS {
+        format!("{}…", line.chars().take(TITLE_PREVIEW_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[derive(Serialize)]
+struct StoredConversation<'a> {
+    summary: ConversationSummary,
+    tree: &'a ConversationTree,
+}
+
+/// Reads just the summary; serde skips the tree without building it
+#[derive(Deserialize)]
+struct SummaryOnly {
+    summary: ConversationSummary,
+}
+
+#[derive(Deserialize)]
+struct TreeOnly {
+    tree: ConversationTree,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConversationManager {
+    dir: PathBuf,
+}
+
+impl ConversationManager {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Saved conversations, most recently updated first. Unreadable files
+    /// are skipped with a warning rather than hiding every other conversation.
+    pub fn list(&self) -> Result<Vec<ConversationSummary>, StoreError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let entries = std::fs::read_dir(&self.dir).map_err(|e| StoreError::IoError(e.to_string()))?;
+        let mut summaries: Vec<ConversationSummary> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| match read::<SummaryOnly>(&path) {
+                Ok(stored) => Some(stored.summary),
+                Err(e) => {
+                    log::warn!("{}", e);
+                    None
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(summaries)
+    }
+
+    pub fn load(&self, id: Uuid) -> Result<ConversationTree, StoreError> {
+        let path = self.path(id);
+        if !path.exists() {
+            return Err(StoreError::NotFound(id));
+        }
+        Ok(read::<TreeOnly>(&path)?.tree)
+    }
+
+    /// Write `tree`, replacing its earlier save
+    pub fn save(&self, tree: &ConversationTree) -> Result<(), StoreError> {
+        let summary = ConversationSummary::of(tree);
+        let path = self.path(summary.id);
+        let json = serde_json::to_string(&StoredConversation { summary, tree })
+            .map_err(|e| StoreError::ParseError(path.clone(), e.to_string()))?;
+        std::fs::create_dir_all(&self.dir).map_err(|e| StoreError::IoError(e.to_string()))?;
+        // Written aside and moved into place, so a crash never leaves half a file
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, json).map_err(|e| StoreError::IoError(e.to_string()))?;
+        std::fs::rename(&partial, &path).map_err(|e| StoreError::IoError(e.to_string()))
+    }
+
+    pub fn rename(&self, id: Uuid, title: &str) -> Result<(), StoreError> {
+        let mut tree = self.load(id)?;
+        let title = title.trim();
+        tree.root_mut().metadata.title = (!title.is_empty()).then(|| title.to_string());
+        self.save(&tree)
+    }
+
+    pub fn delete(&self, id: Uuid) -> Result<(), StoreError> {
+        match std::fs::remove_file(self.path(id)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(StoreError::NotFound(id)),
+            Err(e) => Err(StoreError::IoError(e.to_string())),
+        }
+    }
+}
+
+fn read<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, StoreError> {
+    let file = std::fs::File::open(path).map_err(|e| StoreError::IoError(e.to_string()))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| StoreError::ParseError(path.to_path_buf(), e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_mode_eval::conversation::{Conversation, Message};
+
+    fn tree(prompt: &str) -> ConversationTree {
+        let mut conversation = Conversation::new("You are helpful".to_string());
+        conversation.add_message(Message::new(MessageRole::User, prompt.to_string()));
+        conversation.add_message(Message::new(MessageRole::Assistant, "Sure".to_string()));
+        ConversationTree::new(conversation)
+    }
+
+    #[test]
+    fn test_saved_conversations_are_listed_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ConversationManager::new(dir.path().join("conversations"));
+        assert!(store.list().unwrap().is_empty());
+
+        let older = tree("How do I list open ports?\nOn macOS");
+        store.save(&older).unwrap();
+        let mut newer = tree("Why is my build slow?");
+        newer.active_mut().add_message(Message::new(MessageRole::User, "Still slow".to_string()));
+        store.save(&newer).unwrap();
+        std::fs::write(dir.path().join("conversations").join("broken.json"), "{").unwrap();
+
+        let list = store.list().unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].title, "Why is my build slow?");
+        assert_eq!(list[0].message_count, 3);
+        assert_eq!(list[1].title, "How do I list open ports?");
+
+        let loaded = store.load(older.root().id).unwrap();
+        assert_eq!(loaded.active().messages.len(), 2);
+    }
+
+    #[test]
+    fn test_rename_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ConversationManager::new(dir.path().to_path_buf());
+        let saved = tree("hello");
+        let id = saved.root().id;
+        store.save(&saved).unwrap();
+
+        store.rename(id, "  Greetings ").unwrap();
+        assert_eq!(store.list().unwrap()[0].title, "Greetings");
+        // Saving again keeps the title
+        store.save(&store.load(id).unwrap()).unwrap();
+        assert_eq!(store.list().unwrap()[0].title, "Greetings");
+
+        store.delete(id).unwrap();
+        assert!(store.list().unwrap().is_empty());
+        assert!(matches!(store.load(id), Err(StoreError::NotFound(_))));
+    }
+}
@@ -0,0 +1,205 @@
+//! What `get_system_info` tells the model about the machine.
+//!
+//! The result is a JSON object whose shape is advertised with the tool, so
+//! the model can rely on field names and types. Fields that identify the
+//! user or machine are left out unless the privacy preference allows them.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::tools::{ParameterProperty, ToolParameters};
+
+/// Session state the system info tool reports but can't look up itself
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostContext {
+    /// Name of the active env profile
+    pub env_profile: Option<String>,
+    /// Include the hostname and username
+    pub share_identity: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemInfo {
+    pub os: String,
+    pub os_version: Option<String>,
+    pub arch: String,
+    pub cpu_count: usize,
+    pub total_memory_bytes: Option<u64>,
+    pub available_memory_bytes: Option<u64>,
+    pub current_directory: Option<String>,
+    pub shell: Option<String>,
+    pub env_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
+
+impl SystemInfo {
+    pub fn collect(host: &HostContext) -> Self {
+        let (total_memory_bytes, available_memory_bytes) = memory();
+        Self {
+            os: std::env::consts::OS.to_string(),
+            os_version: os_version(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpu_count: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            total_memory_bytes,
+            available_memory_bytes,
+            current_directory: std::env::current_dir().ok().map(|dir| dir.display().to_string()),
+            shell: std::env::var("SHELL").or_else(|_| std::env::var("COMSPEC")).ok(),
+            env_profile: host.env_profile.clone(),
+            hostname: host.share_identity.then(hostname).flatten(),
+            username: host.share_identity
+                .then(|| std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok())
+                .flatten(),
+        }
+    }
+}
+
+/// The shape of [`SystemInfo`] as JSON; fields not listed as required may be
+/// null or absent
+pub fn schema() -> ToolParameters {
+    let fields = [
+        ("os", "string", "Operating system, as Rust names it (linux, macos, windows)"),
+        ("os_version", "string", "Distribution or release, when known"),
+        ("arch", "string", "CPU architecture"),
+        ("cpu_count", "integer", "Logical CPUs available"),
+        ("total_memory_bytes", "integer", "Installed memory, when known"),
+        ("available_memory_bytes", "integer", "Memory available for new processes, when known"),
+        ("current_directory", "string", "Working directory of the terminal"),
+        ("shell", "string", "Login shell"),
+        ("env_profile", "string", "Active env profile, if any"),
+        ("hostname", "string", "Machine name; only shared if the user allows it"),
+        ("username", "string", "User name; only shared if the user allows it"),
+    ];
+    let properties: HashMap<_, _> = fields
+        .into_iter()
+        .map(|(name, r#type, description)| {
+            (name.to_string(), ParameterProperty {
+                r#type: r#type.to_string(),
+                description: description.to_string(),
+                r#enum: None,
+            })
+        })
+        .collect();
+    ToolParameters {
+        r#type: "object".to_string(),
+        properties,
+        required: ["os", "arch", "cpu_count"].map(str::to_string).to_vec(),
+    }
+}
+
+fn os_version() -> Option<String> {
+    if cfg!(target_os = "linux") {
+        let release = std::fs::read_to_string("/etc/os-release").ok()?;
+        return os_release_name(&release);
+    }
+    if cfg!(target_os = "macos") {
+        let output = std::process::Command::new("sw_vers").arg("-productVersion").output().ok()?;
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return (!version.is_empty()).then(|| format!("macOS {}", version));
+    }
+    None
+}
+
+/// `PRETTY_NAME` from an os-release file
+fn os_release_name(release: &str) -> Option<String> {
+    release
+        .lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+        .map(|value| value.trim().trim_matches('"').to_string())
+}
+
+/// Total and available memory in bytes
+fn memory() -> (Option<u64>, Option<u64>) {
+    if cfg!(target_os = "linux") {
+        return std::fs::read_to_string("/proc/meminfo")
+            .map(|meminfo| parse_meminfo(&meminfo))
+            .unwrap_or_default();
+    }
+    if cfg!(target_os = "macos") {
+        let total = std::process::Command::new("sysctl")
+            .args(["-n", "hw.memsize"])
+            .output()
+            .ok()
+            .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok());
+        return (total, None);
+    }
+    (None, None)
+}
+
+fn parse_meminfo(meminfo: &str) -> (Option<u64>, Option<u64>) {
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+            .map(|kib| kib * 1024)
+    };
+    (field("MemTotal"), field("MemAvailable"))
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    // SAFETY: the buffer is valid for its whole length, which is what we pass
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    if result != 0 {
+        return None;
+    }
+    let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    Some(String::from_utf8_lossy(&buffer[..end]).into_owned())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    /// Every field is advertised with its type, and required ones are present
+    fn assert_matches_schema(value: &Value, schema: &ToolParameters) {
+        let object = value.as_object().expect("system info is a JSON object");
+        for required in &schema.required {
+            assert!(!object.get(required).unwrap_or(&Value::Null).is_null(), "missing {}", required);
+        }
+        for (name, field) in object {
+            let property = schema.properties.get(name).unwrap_or_else(|| panic!("{} is not advertised", name));
+            let matches = match property.r#type.as_str() {
+                _ if field.is_null() => !schema.required.contains(name),
+                "string" => field.is_string(),
+                "integer" => field.is_u64(),
+                other => panic!("unexpected type {}", other),
+            };
+            assert!(matches, "{} is not a {}", name, property.r#type);
+        }
+    }
+
+    #[test]
+    fn test_output_matches_advertised_schema() {
+        let schema = schema();
+        for share_identity in [false, true] {
+            let host = HostContext { env_profile: Some("staging".to_string()), share_identity };
+            let value = serde_json::to_value(SystemInfo::collect(&host)).unwrap();
+            assert_matches_schema(&value, &schema);
+            assert_eq!(value["env_profile"], "staging");
+            if !share_identity {
+                assert!(value.get("hostname").is_none() && value.get("username").is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_parses_linux_sources() {
+        let meminfo = "MemTotal:       16318480 kB\nMemFree:         1204132 kB\nMemAvailable:    9012340 kB\n";
+        assert_eq!(parse_meminfo(meminfo), (Some(16318480 * 1024), Some(9012340 * 1024)));
+        assert_eq!(parse_meminfo(""), (None, None));
+
+        let release = "NAME=\"Ubuntu\"\nVERSION_ID=\"22.04\"\nPRETTY_NAME=\"Ubuntu 22.04.3 LTS\"\n";
+        assert_eq!(os_release_name(release).as_deref(), Some("Ubuntu 22.04.3 LTS"));
+    }
+}
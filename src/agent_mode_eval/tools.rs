@@ -3,10 +3,16 @@ use std::collections::HashMap;
 use std::process::Command;
 use tokio::fs;
 use tokio::process::Command as AsyncCommand;
+use crate::read_only::ReadOnly;
+use super::system_info::{self, HostContext, SystemInfo};
+use super::web::{self, WebAccess};
 
 #[derive(Debug, Clone)]
 pub struct ToolRegistry {
     tools: HashMap<String, Tool>,
+    read_only: ReadOnly,
+    host: HostContext,
+    web: WebAccess,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +20,9 @@ pub struct Tool {
     pub name: String,
     pub description: String,
     pub parameters: ToolParameters,
+    /// Shape of the JSON the tool returns, for tools with structured output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub returns: Option<ToolParameters>,
     pub function: ToolFunction,
 }
 
@@ -28,6 +37,7 @@ pub struct ToolParameters {
 pub struct ParameterProperty {
     pub r#type: String,
     pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub r#enum: Option<Vec<String>>,
 }
 
@@ -39,10 +49,64 @@ pub enum ToolFunction {
     ListDirectory,
     GetSystemInfo,
     SearchFiles,
+    SearchProject,
     GitStatus,
     ProcessList,
+    FetchUrl,
 }
 
+impl Tool {
+    /// The description sent to the model, with the result's shape if it has one
+    pub fn advertised_description(&self) -> String {
+        match &self.returns {
+            Some(returns) => format!(
+                "{} Returns: {}",
+                self.description,
+                serde_json::to_string(returns).unwrap_or_default()
+            ),
+            None => self.description.clone(),
+        }
+    }
+}
+
+impl ToolFunction {
+    /// Runs commands or changes files, so it may need the user's approval
+    pub fn has_side_effects(&self) -> bool {
+        matches!(self, ToolFunction::ExecuteCommand | ToolFunction::WriteFile)
+    }
+}
+
+/// Whether tool calls that run commands or write files need the user's go-ahead
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalMode {
+    /// Run every call
+    Auto,
+    /// Pause and ask before each command or write
+    #[default]
+    Ask,
+    /// Refuse commands and writes; read-only tools still run
+    DenyShell,
+}
+
+impl ApprovalMode {
+    pub const ALL: [ApprovalMode; 3] = [ApprovalMode::Ask, ApprovalMode::Auto, ApprovalMode::DenyShell];
+}
+
+/// What happens to a tool call before it runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Approval {
+    Run,
+    /// Wait for the user to approve or deny it
+    Ask,
+    /// Refused without asking; the reason is sent back to the model
+    Deny(String),
+}
+
+/// Tool result sent when the user turns a call down
+pub const DENIED_BY_USER: &str =
+    "The user denied this tool call. Do not retry it; explain what it was for or suggest an alternative.";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
     pub id: String,
@@ -58,10 +122,58 @@ pub struct ToolResult {
     pub error: Option<String>,
 }
 
+impl ToolCall {
+    fn string_argument(&self, name: &str) -> Option<&str> {
+        self.arguments.get(name).and_then(|v| v.as_str())
+    }
+
+    /// Exactly what the call will do, as shown when asking for approval
+    pub fn describe(&self) -> String {
+        match self.name.as_str() {
+            "execute_command" => {
+                let mut description = format!("$ {}", self.string_argument("command").unwrap_or_default());
+                if let Some(dir) = self.string_argument("working_directory") {
+                    description.push_str(&format!("\n(in {})", dir));
+                }
+                description
+            }
+            "write_file" => {
+                let content = self.string_argument("content").unwrap_or_default();
+                format!(
+                    "Write {} bytes to {}\n{}",
+                    content.len(),
+                    self.string_argument("path").unwrap_or_default(),
+                    content
+                )
+            }
+            _ => format!(
+                "{} {}",
+                self.name,
+                serde_json::to_string(&self.arguments).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+impl ToolResult {
+    /// A call that didn't run, or failed before it could
+    pub fn error(call: &ToolCall, message: &str) -> Self {
+        Self {
+            tool_call_id: call.id.clone(),
+            success: false,
+            output: String::new(),
+            error: Some(message.to_string()),
+        }
+    }
+}
+
 impl ToolRegistry {
     pub fn new() -> Self {
         let mut registry = Self {
             tools: HashMap::new(),
+            read_only: ReadOnly::new(),
+            host: HostContext::default(),
+            web: WebAccess::default(),
         };
         registry.register_default_tools();
         registry
@@ -90,6 +202,7 @@ impl ToolRegistry {
                 },
                 required: vec!["command".to_string()],
             },
+            returns: None,
             function: ToolFunction::ExecuteCommand,
         });
 
@@ -110,6 +223,7 @@ impl ToolRegistry {
                 },
                 required: vec!["path".to_string()],
             },
+            returns: None,
             function: ToolFunction::ReadFile,
         });
 
@@ -135,6 +249,7 @@ impl ToolRegistry {
                 },
                 required: vec!["path".to_string(), "content".to_string()],
             },
+            returns: None,
             function: ToolFunction::WriteFile,
         });
 
@@ -160,18 +275,20 @@ impl ToolRegistry {
                 },
                 required: vec!["path".to_string()],
             },
+            returns: None,
             function: ToolFunction::ListDirectory,
         });
 
         // Get System Info Tool
         self.register_tool(Tool {
             name: "get_system_info".to_string(),
-            description: "Get system information including OS, CPU, memory, etc.".to_string(),
+            description: "Get OS, architecture, CPU count, memory, working directory, shell and env profile as a JSON object".to_string(),
             parameters: ToolParameters {
                 r#type: "object".to_string(),
                 properties: HashMap::new(),
                 required: vec![],
             },
+            returns: Some(system_info::schema()),
             function: ToolFunction::GetSystemInfo,
         });
 
@@ -197,9 +314,46 @@ impl ToolRegistry {
                 },
                 required: vec!["pattern".to_string()],
             },
+            returns: None,
             function: ToolFunction::SearchFiles,
         });
 
+        // Search Project Tool (read-only; shares the find-and-replace engine)
+        self.register_tool(Tool {
+            name: "search_project".to_string(),
+            description: "Search file contents in the project, respecting .gitignore".to_string(),
+            parameters: ToolParameters {
+                r#type: "object".to_string(),
+                properties: {
+                    let mut props = HashMap::new();
+                    props.insert("pattern".to_string(), ParameterProperty {
+                        r#type: "string".to_string(),
+                        description: "Text or regular expression to search for".to_string(),
+                        r#enum: None,
+                    });
+                    props.insert("regex".to_string(), ParameterProperty {
+                        r#type: "boolean".to_string(),
+                        description: "Treat pattern as a regular expression (default: false)".to_string(),
+                        r#enum: None,
+                    });
+                    props.insert("glob".to_string(), ParameterProperty {
+                        r#type: "string".to_string(),
+                        description: "Only search files matching this glob, e.g. *.rs".to_string(),
+                        r#enum: None,
+                    });
+                    props.insert("directory".to_string(), ParameterProperty {
+                        r#type: "string".to_string(),
+                        description: "Directory to search in (default: current)".to_string(),
+                        r#enum: None,
+                    });
+                    props
+                },
+                required: vec!["pattern".to_string()],
+            },
+            returns: None,
+            function: ToolFunction::SearchProject,
+        });
+
         // Git Status Tool
         self.register_tool(Tool {
             name: "git_status".to_string(),
@@ -217,6 +371,7 @@ impl ToolRegistry {
                 },
                 required: vec![],
             },
+            returns: None,
             function: ToolFunction::GitStatus,
         });
 
@@ -237,8 +392,50 @@ impl ToolRegistry {
                 },
                 required: vec![],
             },
+            returns: None,
             function: ToolFunction::ProcessList,
         });
+
+        // Fetch URL Tool
+        self.register_tool(Tool {
+            name: "fetch_url".to_string(),
+            description: "Fetch a web page, such as documentation, as plain text".to_string(),
+            parameters: ToolParameters {
+                r#type: "object".to_string(),
+                properties: {
+                    let mut props = HashMap::new();
+                    props.insert("url".to_string(), ParameterProperty {
+                        r#type: "string".to_string(),
+                        description: "The https URL to fetch".to_string(),
+                        r#enum: None,
+                    });
+                    props
+                },
+                required: vec!["url".to_string()],
+            },
+            returns: Some(web::schema()),
+            function: ToolFunction::FetchUrl,
+        });
+    }
+
+    /// Tools that spawn processes or write files refuse to run while `read_only` is on
+    pub fn with_read_only(mut self, read_only: ReadOnly) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn set_read_only(&mut self, read_only: ReadOnly) {
+        self.read_only = read_only;
+    }
+
+    /// Which URLs `fetch_url` may GET
+    pub fn set_web_access(&mut self, web: WebAccess) {
+        self.web = web;
+    }
+
+    /// Env profile and identity preference reported by `get_system_info`
+    pub fn set_host(&mut self, host: HostContext) {
+        self.host = host;
     }
 
     pub fn register_tool(&mut self, tool: Tool) {
@@ -253,19 +450,44 @@ impl ToolRegistry {
         self.tools.values().cloned().collect()
     }
 
+    /// Whether `call` may run under `mode`. Tools without side effects
+    /// always run; unknown tools run too and fail with `ToolNotFound`.
+    pub fn approval(&self, call: &ToolCall, mode: ApprovalMode) -> Approval {
+        let gated = self.get_tool(&call.name).is_some_and(|tool| tool.function.has_side_effects());
+        match mode {
+            _ if !gated => Approval::Run,
+            ApprovalMode::Auto => Approval::Run,
+            ApprovalMode::Ask => Approval::Ask,
+            ApprovalMode::DenyShell => Approval::Deny(format!(
+                "`{}` is disabled in this session: the user doesn't allow commands or file writes. Suggest what they could run themselves.",
+                call.name
+            )),
+        }
+    }
+
     pub async fn execute_tool(&self, tool_call: ToolCall) -> Result<ToolResult, ToolError> {
         let tool = self.get_tool(&tool_call.name)
             .ok_or_else(|| ToolError::ToolNotFound(tool_call.name.clone()))?;
 
         let result = match &tool.function {
+            // Anything that spawns a process or writes is refused in read-only mode
+            ToolFunction::ExecuteCommand
+            | ToolFunction::WriteFile
+            | ToolFunction::SearchFiles
+            | ToolFunction::GitStatus
+            | ToolFunction::ProcessList if self.read_only.is_enabled() => {
+                self.read_only.check().map(|()| String::new()).map_err(|e| ToolError::ExecutionError(e.to_string()))
+            }
             ToolFunction::ExecuteCommand => self.execute_command_tool(&tool_call).await,
             ToolFunction::ReadFile => self.read_file_tool(&tool_call).await,
             ToolFunction::WriteFile => self.write_file_tool(&tool_call).await,
             ToolFunction::ListDirectory => self.list_directory_tool(&tool_call).await,
             ToolFunction::GetSystemInfo => self.get_system_info_tool(&tool_call).await,
             ToolFunction::SearchFiles => self.search_files_tool(&tool_call).await,
+            ToolFunction::SearchProject => self.search_project_tool(&tool_call).await,
             ToolFunction::GitStatus => self.git_status_tool(&tool_call).await,
             ToolFunction::ProcessList => self.process_list_tool(&tool_call).await,
+            ToolFunction::FetchUrl => self.fetch_url_tool(&tool_call).await,
         };
 
         match result {
@@ -375,29 +597,7 @@ impl ToolRegistry {
     }
 
     async fn get_system_info_tool(&self, _tool_call: &ToolCall) -> Result<String, ToolError> {
-        let mut info = Vec::new();
-        
-        // OS Info
-        info.push(format!("OS: {}", std::env::consts::OS));
-        info.push(format!("Architecture: {}", std::env::consts::ARCH));
-        
-        // Current directory
-        if let Ok(current_dir) = std::env::current_dir() {
-            info.push(format!("Current Directory: {}", current_dir.display()));
-        }
-
-        // Environment variables (selected)
-        if let Ok(user) = std::env::var("USER") {
-            info.push(format!("User: {}", user));
-        }
-        if let Ok(shell) = std::env::var("SHELL") {
-            info.push(format!("Shell: {}", shell));
-        }
-        if let Ok(home) = std::env::var("HOME") {
-            info.push(format!("Home: {}", home));
-        }
-
-        Ok(info.join("\n"))
+        Ok(serde_json::to_string(&SystemInfo::collect(&self.host))?)
     }
 
     async fn search_files_tool(&self, tool_call: &ToolCall) -> Result<String, ToolError> {
@@ -426,6 +626,45 @@ impl ToolRegistry {
         }
     }
 
+    async fn search_project_tool(&self, tool_call: &ToolCall) -> Result<String, ToolError> {
+        const MAX_RESULTS: usize = 200;
+
+        let pattern = tool_call.arguments.get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or(ToolError::MissingArgument("pattern".to_string()))?;
+
+        let mut query = crate::find_replace::SearchQuery::literal(pattern);
+        query.is_regex = tool_call.arguments.get("regex").and_then(|v| v.as_bool()).unwrap_or(false);
+        query.glob = tool_call.arguments.get("glob").and_then(|v| v.as_str()).map(str::to_string);
+        query.context_lines = 0;
+
+        let directory = std::path::PathBuf::from(
+            tool_call.arguments.get("directory").and_then(|v| v.as_str()).unwrap_or(".")
+        );
+
+        let files = tokio::task::spawn_blocking(move || crate::find_replace::search_project(&directory, &query))
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        let lines: Vec<String> = files
+            .iter()
+            .flat_map(|file| file.matches.iter().map(move |m| {
+                format!("{}:{}: {}", file.path.display(), m.line_number, m.line.trim())
+            }))
+            .collect();
+
+        if lines.is_empty() {
+            return Ok("No matches found".to_string());
+        }
+
+        let mut output = lines.iter().take(MAX_RESULTS).cloned().collect::<Vec<_>>().join("\n");
+        if lines.len() > MAX_RESULTS {
+            output.push_str(&format!("\n... {} more matches", lines.len() - MAX_RESULTS));
+        }
+        Ok(output)
+    }
+
     async fn git_status_tool(&self, tool_call: &ToolCall) -> Result<String, ToolError> {
         let repo_path = tool_call.arguments.get("repository_path")
             .and_then(|v| v.as_str())
@@ -451,6 +690,16 @@ impl ToolRegistry {
         }
     }
 
+    async fn fetch_url_tool(&self, tool_call: &ToolCall) -> Result<String, ToolError> {
+        let url = tool_call.arguments.get("url")
+            .and_then(|v| v.as_str())
+            .ok_or(ToolError::MissingArgument("url".to_string()))?;
+
+        let page = self.web.fetch(url).await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        Ok(serde_json::to_string(&page)?)
+    }
+
     async fn process_list_tool(&self, tool_call: &ToolCall) -> Result<String, ToolError> {
         let filter = tool_call.arguments.get("filter")
             .and_then(|v| v.as_str());
@@ -519,6 +768,7 @@ mod tests {
                 properties: HashMap::new(),
                 required: vec![],
             },
+            returns: None,
             function: ToolFunction::GetSystemInfo,
         };
 
@@ -527,6 +777,31 @@ mod tests {
         assert!(registry.get_tool("custom_tool").is_some());
     }
 
+    fn call(name: &str, arguments: &[(&str, &str)]) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            name: name.to_string(),
+            arguments: arguments.iter().map(|(k, v)| (k.to_string(), serde_json::json!(v))).collect(),
+        }
+    }
+
+    #[test]
+    fn test_approval_gates_commands_and_writes() {
+        let registry = ToolRegistry::new();
+        let command = call("execute_command", &[("command", "rm -rf build"), ("working_directory", "/tmp")]);
+        let read = call("read_file", &[("path", "Cargo.toml")]);
+
+        assert_eq!(registry.approval(&command, ApprovalMode::Ask), Approval::Ask);
+        assert_eq!(registry.approval(&command, ApprovalMode::Auto), Approval::Run);
+        assert!(matches!(registry.approval(&call("write_file", &[]), ApprovalMode::DenyShell), Approval::Deny(_)));
+        assert_eq!(registry.approval(&read, ApprovalMode::DenyShell), Approval::Run);
+
+        assert_eq!(command.describe(), "$ rm -rf build\n(in /tmp)");
+        let denied = ToolResult::error(&command, DENIED_BY_USER);
+        assert!(!denied.success);
+        assert_eq!(denied.tool_call_id, "call_1");
+    }
+
     #[tokio::test]
     async fn test_system_info_tool() {
         let registry = ToolRegistry::new();
@@ -538,7 +813,9 @@ mod tests {
 
         let result = registry.execute_tool(tool_call).await.unwrap();
         assert!(result.success);
-        assert!(result.output.contains("OS:"));
-        assert!(result.output.contains("Architecture:"));
+        let info: serde_json::Value = serde_json::from_str(&result.output).unwrap();
+        assert_eq!(info["os"], std::env::consts::OS);
+        assert_eq!(info["arch"], std::env::consts::ARCH);
+        assert!(info.get("hostname").is_none());
     }
 }
@@ -0,0 +1,223 @@
+//! Token counts and what they cost.
+//!
+//! Providers that report usage are taken at their word; for the rest the
+//! counts are estimated from the text sent and received, and marked as such.
+//! Prices are per million tokens and come from the AI preferences, so they
+//! can be corrected without a new release.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::ai_client::{AiMessage, AiProvider, Usage};
+
+/// Rough characters per token for English text and code
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimated tokens in `text` when the provider doesn't say
+pub fn estimate_tokens(text: &str) -> u32 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u32
+}
+
+impl Usage {
+    /// Estimate for a reply the provider didn't count
+    pub fn estimate(prompt: &[AiMessage], completion: &str) -> Self {
+        let prompt_tokens = prompt.iter().map(|message| estimate_tokens(&message.content)).sum();
+        let completion_tokens = estimate_tokens(completion);
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            estimated: true,
+        }
+    }
+}
+
+/// Price of one model, in US dollars per million tokens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub provider: AiProvider,
+    /// Model name, or `*` for every model of the provider
+    pub model: String,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    fn new(provider: AiProvider, model: &str, input_per_million: f64, output_per_million: f64) -> Self {
+        Self { provider, model: model.to_string(), input_per_million, output_per_million }
+    }
+
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input_per_million
+            + usage.completion_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// List prices at the time of writing; local models cost nothing
+pub fn default_prices() -> Vec<ModelPrice> {
+    vec![
+        ModelPrice::new(AiProvider::OpenAI, "gpt-4o", 2.5, 10.0),
+        ModelPrice::new(AiProvider::OpenAI, "gpt-4-turbo", 10.0, 30.0),
+        ModelPrice::new(AiProvider::OpenAI, "gpt-4", 30.0, 60.0),
+        ModelPrice::new(AiProvider::OpenAI, "gpt-4-mini", 0.15, 0.6),
+        ModelPrice::new(AiProvider::OpenAI, "gpt-3.5-turbo", 0.5, 1.5),
+        ModelPrice::new(AiProvider::OpenAI, "o3", 2.0, 8.0),
+        ModelPrice::new(AiProvider::OpenAI, "o3-mini", 1.1, 4.4),
+        ModelPrice::new(AiProvider::Claude, "claude-4-opus-20250514", 15.0, 75.0),
+        ModelPrice::new(AiProvider::Claude, "claude-4-sonnet-20250514", 3.0, 15.0),
+        ModelPrice::new(AiProvider::Claude, "claude-3-7-sonnet-20241022", 3.0, 15.0),
+        ModelPrice::new(AiProvider::Claude, "claude-3-5-sonnet-20241022", 3.0, 15.0),
+        ModelPrice::new(AiProvider::Claude, "claude-3-7-haiku-20241022", 0.8, 4.0),
+        ModelPrice::new(AiProvider::Gemini, "gemini-1.5-pro", 1.25, 5.0),
+        ModelPrice::new(AiProvider::Gemini, "gemini-1.5-flash", 0.075, 0.3),
+        ModelPrice::new(AiProvider::Gemini, "gemini-2.0-flash-exp", 0.1, 0.4),
+        ModelPrice::new(AiProvider::Groq, "llama-3.1-70b-versatile", 0.59, 0.79),
+        ModelPrice::new(AiProvider::Groq, "llama-3.1-8b-instant", 0.05, 0.08),
+        ModelPrice::new(AiProvider::Groq, "mixtral-8x7b-32768", 0.24, 0.24),
+        ModelPrice::new(AiProvider::Groq, "gemma2-9b-it", 0.2, 0.2),
+        ModelPrice::new(AiProvider::Ollama, "*", 0.0, 0.0),
+        ModelPrice::new(AiProvider::Local, "*", 0.0, 0.0),
+    ]
+}
+
+/// The price for `model`, falling back to the provider's `*` entry
+pub fn price_for<'a>(prices: &'a [ModelPrice], provider: &AiProvider, model: &str) -> Option<&'a ModelPrice> {
+    let of_provider = || prices.iter().filter(move |price| &price.provider == provider);
+    of_provider()
+        .find(|price| price.model == model)
+        .or_else(|| of_provider().find(|price| price.model == "*"))
+}
+
+/// Tokens used by a conversation so far, and what they cost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenTotals {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Dollars spent on replies whose model has a price; `None` if none had
+    pub cost_usd: Option<f64>,
+    /// Some of the counts are estimates
+    pub estimated: bool,
+}
+
+impl TokenTotals {
+    pub fn record(&mut self, usage: &Usage, cost: Option<f64>) {
+        self.prompt_tokens += u64::from(usage.prompt_tokens);
+        self.completion_tokens += u64::from(usage.completion_tokens);
+        if let Some(cost) = cost {
+            *self.cost_usd.get_or_insert(0.0) += cost;
+        }
+        self.estimated |= usage.estimated;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// Whether the conversation has used more than `budget` tokens
+    pub fn exceeds(&self, budget: Option<u64>) -> bool {
+        budget.is_some_and(|budget| self.total() > budget)
+    }
+}
+
+impl std::ops::Add for TokenTotals {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            cost_usd: match (self.cost_usd, other.cost_usd) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+            },
+            estimated: self.estimated || other.estimated,
+        }
+    }
+}
+
+impl std::iter::Sum for TokenTotals {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |sum, totals| sum + totals)
+    }
+}
+
+/// "12.3k tokens (~$0.04)"
+impl fmt::Display for TokenTotals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let approx = if self.estimated { "~" } else { "" };
+        write!(f, "{}{} tokens", approx, compact_count(self.total()))?;
+        if let Some(cost) = self.cost_usd {
+            write!(f, " (~${:.2})", cost)?;
+        }
+        Ok(())
+    }
+}
+
+/// 950, 12.3k, 1.2M
+fn compact_count(n: u64) -> String {
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=999_999 => format!("{:.1}k", n as f64 / 1_000.0),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: u32, completion_tokens: u32) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            estimated: false,
+        }
+    }
+
+    #[test]
+    fn test_price_lookup_falls_back_to_wildcard() {
+        let prices = default_prices();
+        let sonnet = price_for(&prices, &AiProvider::Claude, "claude-4-sonnet-20250514").unwrap();
+        assert_eq!(sonnet.cost(&usage(1_000_000, 0)), 3.0);
+        assert_eq!(price_for(&prices, &AiProvider::Ollama, "llama3:8b").unwrap().output_per_million, 0.0);
+        assert!(price_for(&prices, &AiProvider::OpenAI, "gpt-5").is_none());
+    }
+
+    #[test]
+    fn test_totals_accumulate_and_display() {
+        let mut totals = TokenTotals::default();
+        totals.record(&usage(10_000, 2_300), Some(0.04));
+        assert_eq!(totals.to_string(), "12.3k tokens (~$0.04)");
+
+        // An unpriced reply adds tokens but leaves the cost alone
+        totals.record(&Usage { estimated: true, ..usage(500, 0) }, None);
+        assert_eq!(totals.total(), 12_800);
+        assert_eq!(totals.to_string(), "~12.8k tokens (~$0.04)");
+        assert!(totals.exceeds(Some(10_000)));
+        assert!(!totals.exceeds(None));
+
+        assert_eq!(TokenTotals::default().to_string(), "0 tokens");
+        let summed: TokenTotals = [totals, totals].into_iter().sum();
+        assert_eq!(summed.total(), 25_600);
+    }
+
+    #[test]
+    fn test_estimate_counts_prompt_and_reply() {
+        let prompt = vec![AiMessage {
+            role: "user".to_string(),
+            content: "x".repeat(40),
+            tool_calls: None,
+            tool_call_id: None,
+        }];
+        let estimate = Usage::estimate(&prompt, "abcde");
+        assert_eq!((estimate.prompt_tokens, estimate.completion_tokens), (10, 2));
+        assert!(estimate.estimated);
+    }
+}
@@ -0,0 +1,299 @@
+//! Web pages for the `fetch_url` tool.
+//!
+//! Only GET requests are made, each URL and every redirect is checked
+//! against the configured allow and deny lists, and pages come back as
+//! plain text cut to a size the model can take in.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::tools::{ParameterProperty, ToolParameters};
+
+/// Downloads stop here even if the page goes on
+const MAX_DOWNLOAD_BYTES: usize = 2 * 1024 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+pub const TRUNCATED_MARKER: &str = "\n[truncated]";
+
+/// Which URLs the agent may fetch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebAccess {
+    /// Hosts that may be fetched; empty allows any host not denied.
+    /// `example.com` matches the host and its subdomains.
+    pub allow: Vec<String>,
+    /// Hosts that are never fetched, even if allowed
+    pub deny: Vec<String>,
+    /// Fetch plain http:// URLs too
+    pub allow_http: bool,
+    pub max_redirects: usize,
+    /// Most bytes of text handed to the model per page
+    pub max_bytes: usize,
+}
+
+impl Default for WebAccess {
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            allow_http: false,
+            max_redirects: 5,
+            max_bytes: 64 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("Invalid URL '{0}': {1}")]
+    InvalidUrl(String, String),
+    #[error("Not allowed to fetch {0}: {1}")]
+    Blocked(String, String),
+    #[error("{0} is not a text document ({1})")]
+    NotText(String, String),
+    #[error("Request failed: {0}")]
+    Request(String),
+}
+
+/// What the tool returns to the model
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchedPage {
+    /// Where the page was found after redirects
+    pub url: String,
+    pub status: u16,
+    pub content: String,
+    pub truncated: bool,
+}
+
+impl WebAccess {
+    /// Why `url` may not be fetched, if it may not
+    pub fn check(&self, url: &url::Url) -> Result<(), String> {
+        match url.scheme() {
+            "https" => {}
+            "http" if self.allow_http => {}
+            "http" => return Err("only https URLs are allowed".to_string()),
+            scheme => return Err(format!("{} URLs are not supported", scheme)),
+        }
+        let host = url.host_str().ok_or("the URL has no host")?.to_ascii_lowercase();
+        if self.deny.iter().any(|pattern| host_matches(&host, pattern)) {
+            return Err(format!("{} is on the deny list", host));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|pattern| host_matches(&host, pattern)) {
+            return Err(format!("{} is not on the allow list", host));
+        }
+        Ok(())
+    }
+
+    /// GET `url` and reduce it to readable text
+    pub async fn fetch(&self, url: &str) -> Result<FetchedPage, FetchError> {
+        let parsed = url::Url::parse(url).map_err(|e| FetchError::InvalidUrl(url.to_string(), e.to_string()))?;
+        self.check(&parsed).map_err(|reason| FetchError::Blocked(url.to_string(), reason))?;
+
+        // Every hop is held to the same rules as the first URL
+        let access = self.clone();
+        let redirects = reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > access.max_redirects {
+                return attempt.error(format!("more than {} redirects", access.max_redirects));
+            }
+            match access.check(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(reason) => attempt.error(format!("redirect to {} blocked: {}", attempt.url(), reason)),
+            }
+        });
+        let client = crate::net::settings()
+            .client_builder()
+            .map_err(|e| FetchError::Request(e.to_string()))?
+            .redirect(redirects)
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .map_err(|e| FetchError::Request(e.to_string()))?;
+
+        let mut response = client
+            .get(parsed)
+            .send()
+            .await
+            .map_err(|e| FetchError::Request(crate::net::describe_error(&e)))?;
+        let final_url = response.url().to_string();
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("text/plain")
+            .to_ascii_lowercase();
+        if !is_text(&content_type) {
+            return Err(FetchError::NotText(final_url, content_type));
+        }
+
+        let mut body = Vec::new();
+        let mut cut = false;
+        while let Some(chunk) = response.chunk().await.map_err(|e| FetchError::Request(e.to_string()))? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_DOWNLOAD_BYTES {
+                body.truncate(MAX_DOWNLOAD_BYTES);
+                cut = true;
+                break;
+            }
+        }
+        let body = String::from_utf8_lossy(&body);
+        let text = if content_type.contains("html") { html_to_text(&body) } else { body.into_owned() };
+        let (content, truncated) = truncate(&text, self.max_bytes);
+
+        Ok(FetchedPage { url: final_url, status, content, truncated: truncated || cut })
+    }
+}
+
+/// `pattern` is the host itself or one of its parent domains
+fn host_matches(host: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim().trim_start_matches("*.").trim_start_matches('.').to_ascii_lowercase();
+    !pattern.is_empty() && (host == pattern || host.ends_with(&format!(".{}", pattern)))
+}
+
+fn is_text(content_type: &str) -> bool {
+    content_type.starts_with("text/") || ["json", "xml", "javascript"].iter().any(|kind| content_type.contains(kind))
+}
+
+/// At most `max_bytes` of `text`, cut at a character boundary and ending in
+/// [`TRUNCATED_MARKER`] if anything was dropped
+pub fn truncate(text: &str, max_bytes: usize) -> (String, bool) {
+    if text.len() <= max_bytes {
+        return (text.to_string(), false);
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (format!("{}{}", &text[..end], TRUNCATED_MARKER), true)
+}
+
+/// Readable text of an HTML page: scripts and styles dropped, block
+/// elements on their own lines, common entities decoded
+pub fn html_to_text(html: &str) -> String {
+    const SKIPPED: [&str; 4] = ["script", "style", "noscript", "svg"];
+    const BREAKS: [&str; 18] = [
+        "p", "br", "div", "li", "tr", "ul", "ol", "h1", "h2", "h3", "h4", "h5", "h6", "pre", "section", "article",
+        "table", "hr",
+    ];
+    // Items start a line; only the end of other blocks adds a blank one
+    const ITEMS: [&str; 3] = ["li", "tr", "br"];
+
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if !tag.starts_with('/') && SKIPPED.contains(&name.as_str()) {
+            let closing = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&closing) {
+                Some(index) => rest[index..].find('>').map_or("", |end| &rest[index + end + 1..]),
+                None => "",
+            };
+        } else if BREAKS.contains(&name.as_str()) && !(tag.starts_with('/') && ITEMS.contains(&name.as_str())) {
+            text.push('\n');
+        }
+    }
+    text.push_str(rest);
+
+    let text = decode_entities(&text);
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        // Keep paragraph breaks, but only one blank line in a row
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The shape of [`FetchedPage`] as JSON
+pub fn schema() -> ToolParameters {
+    let fields = [
+        ("url", "string", "URL of the page after redirects"),
+        ("status", "integer", "HTTP status code"),
+        ("content", "string", "Page text, ending in [truncated] if cut short"),
+        ("truncated", "boolean", "Whether the text was cut short"),
+    ];
+    let properties: HashMap<_, _> = fields
+        .into_iter()
+        .map(|(name, r#type, description)| {
+            (name.to_string(), ParameterProperty {
+                r#type: r#type.to_string(),
+                description: description.to_string(),
+                r#enum: None,
+            })
+        })
+        .collect();
+    ToolParameters {
+        r#type: "object".to_string(),
+        properties,
+        required: fields.map(|(name, ..)| name.to_string()).to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> url::Url {
+        url::Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_allow_and_deny_lists() {
+        let open = WebAccess::default();
+        assert!(open.check(&url("https://docs.rs/tokio")).is_ok());
+        assert!(open.check(&url("http://docs.rs/tokio")).is_err());
+        assert!(open.check(&url("file:///etc/passwd")).is_err());
+
+        let access = WebAccess {
+            allow: vec!["rust-lang.org".to_string(), "docs.rs".to_string()],
+            deny: vec!["*.internal.rust-lang.org".to_string()],
+            allow_http: true,
+            ..WebAccess::default()
+        };
+        assert!(access.check(&url("https://doc.rust-lang.org/std/")).is_ok());
+        assert!(access.check(&url("http://docs.rs/")).is_ok());
+        assert!(access.check(&url("https://ci.internal.rust-lang.org/")).is_err());
+        assert!(access.check(&url("https://evil-rust-lang.org/")).is_err());
+        assert!(access.check(&url("https://example.com/")).is_err());
+    }
+
+    #[test]
+    fn test_html_is_reduced_to_text() {
+        let html = r#"<html><head><title>Doc</title><style>p { color: red }</style></head>
+            <body><h1>Install</h1><script>alert("x")</script>
+            <p>Run <code>cargo add serde</code> &amp; rebuild.</p><ul><li>one</li><li>two</li></ul></body></html>"#;
+        assert_eq!(html_to_text(html), "Doc\n\nInstall\n\nRun cargo add serde & rebuild.\n\none\ntwo");
+    }
+
+    #[test]
+    fn test_truncation_is_marked() {
+        assert_eq!(truncate("short", 10), ("short".to_string(), false));
+        let (text, truncated) = truncate("héllo world", 2);
+        assert!(truncated);
+        assert_eq!(text, format!("h{}", TRUNCATED_MARKER));
+    }
+}
@@ -0,0 +1,173 @@
+//! Side panel listing saved agent conversations. Picking one continues it in
+//! agent mode; conversations can also be renamed and deleted from here.
+
+use iced::widget::{button, column, row, scrollable, text, text_input};
+use iced::Element;
+use uuid::Uuid;
+
+use crate::agent_mode_eval::store::ConversationSummary;
+
+#[derive(Debug, Clone)]
+pub enum SidebarMessage {
+    Open(Uuid),
+    StartRename(Uuid),
+    RenameChanged(String),
+    SubmitRename,
+    CancelRename,
+    Delete(Uuid),
+    NewConversation,
+    Close,
+}
+
+/// What the application should do after an update
+#[derive(Debug, Clone, PartialEq)]
+pub enum SidebarAction {
+    Open(Uuid),
+    Rename(Uuid, String),
+    Delete(Uuid),
+    NewConversation,
+    Close,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AiSidebar {
+    conversations: Vec<ConversationSummary>,
+    /// Conversation whose title is being edited, with the edited title
+    renaming: Option<(Uuid, String)>,
+    /// Conversation loaded in agent mode
+    active: Option<Uuid>,
+}
+
+impl AiSidebar {
+    pub fn new(conversations: Vec<ConversationSummary>, active: Option<Uuid>) -> Self {
+        Self { conversations, renaming: None, active }
+    }
+
+    /// Replace the list after a save, rename or delete
+    pub fn refresh(&mut self, conversations: Vec<ConversationSummary>, active: Option<Uuid>) {
+        self.conversations = conversations;
+        self.active = active;
+    }
+
+    pub fn update(&mut self, message: SidebarMessage) -> Option<SidebarAction> {
+        match message {
+            SidebarMessage::Open(id) => Some(SidebarAction::Open(id)),
+            SidebarMessage::StartRename(id) => {
+                let title = self.conversations.iter().find(|c| c.id == id).map(|c| c.title.clone())?;
+                self.renaming = Some((id, title));
+                None
+            }
+            SidebarMessage::RenameChanged(title) => {
+                if let Some((_, editing)) = self.renaming.as_mut() {
+                    *editing = title;
+                }
+                None
+            }
+            SidebarMessage::SubmitRename => {
+                let (id, title) = self.renaming.take()?;
+                Some(SidebarAction::Rename(id, title))
+            }
+            SidebarMessage::CancelRename => {
+                self.renaming = None;
+                None
+            }
+            SidebarMessage::Delete(id) => Some(SidebarAction::Delete(id)),
+            SidebarMessage::NewConversation => Some(SidebarAction::NewConversation),
+            SidebarMessage::Close => Some(SidebarAction::Close),
+        }
+    }
+
+    pub fn view(&self) -> Element<SidebarMessage> {
+        let mut list = column![].spacing(6);
+        if self.conversations.is_empty() {
+            list = list.push(text("No saved conversations yet").size(12));
+        }
+        for conversation in &self.conversations {
+            let entry: Element<SidebarMessage> = match &self.renaming {
+                Some((id, title)) if *id == conversation.id => row![
+                    text_input("Title", title)
+                        .on_input(SidebarMessage::RenameChanged)
+                        .on_submit(SidebarMessage::SubmitRename)
+                        .padding(4)
+                        .size(14),
+                    button("✕").on_press(SidebarMessage::CancelRename),
+                ]
+                .spacing(4)
+                .into(),
+                _ => {
+                    let marker = if self.active == Some(conversation.id) { "● " } else { "" };
+                    let when = crate::i18n::format_datetime(&conversation.updated_at.with_timezone(&chrono::Local));
+                    let mut details = format!("{} · {} messages", when, conversation.message_count);
+                    if !conversation.usage.is_empty() {
+                        details.push_str(&format!(" · {}", conversation.usage));
+                    }
+                    column![
+                        button(column![
+                            text(format!("{}{}", marker, conversation.title)).size(14),
+                            text(details).size(12),
+                        ])
+                        .on_press(SidebarMessage::Open(conversation.id))
+                        .width(iced::Length::Fill),
+                        row![
+                            button(text("Rename").size(12)).on_press(SidebarMessage::StartRename(conversation.id)),
+                            button(text("Delete").size(12)).on_press(SidebarMessage::Delete(conversation.id)),
+                        ]
+                        .spacing(4),
+                    ]
+                    .spacing(2)
+                    .into()
+                }
+            };
+            list = list.push(entry);
+        }
+
+        column![
+            row![
+                text("Conversations").size(16).width(iced::Length::Fill),
+                button("＋").on_press(SidebarMessage::NewConversation),
+                button("✕").on_press(SidebarMessage::Close),
+            ]
+            .spacing(4),
+            scrollable(list).height(iced::Length::Fill),
+        ]
+        .spacing(8)
+        .width(iced::Length::Fixed(280.0))
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(title: &str) -> ConversationSummary {
+        ConversationSummary {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            message_count: 2,
+            usage: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_rename_submits_the_edited_title() {
+        let first = summary("ports");
+        let id = first.id;
+        let mut sidebar = AiSidebar::new(vec![first, summary("build")], None);
+
+        assert_eq!(sidebar.update(SidebarMessage::StartRename(id)), None);
+        sidebar.update(SidebarMessage::RenameChanged("Open ports".to_string()));
+        assert_eq!(
+            sidebar.update(SidebarMessage::SubmitRename),
+            Some(SidebarAction::Rename(id, "Open ports".to_string()))
+        );
+        // Nothing left to submit
+        assert_eq!(sidebar.update(SidebarMessage::SubmitRename), None);
+
+        sidebar.update(SidebarMessage::StartRename(id));
+        sidebar.update(SidebarMessage::CancelRename);
+        assert_eq!(sidebar.update(SidebarMessage::SubmitRename), None);
+    }
+}
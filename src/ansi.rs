@@ -0,0 +1,298 @@
+//! Styling in command output.
+//!
+//! SGR sequences (`ESC [ … m`) become styled spans: the 16 standard colors,
+//! the 256-color palette and RGB, for foreground and background, plus bold,
+//! dim, italic, underline and inverse. Every other escape sequence (cursor
+//! movement, erasing, window titles) is dropped, so output never shows raw
+//! escapes. An unfinished sequence at the end, as when output is cut at a
+//! scrub position, is dropped too.
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// Colors of SGR 30–37 and 90–97
+pub const STANDARD_COLORS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00), (0xcd, 0x31, 0x31), (0x0d, 0xbc, 0x79), (0xe5, 0xe5, 0x10),
+    (0x24, 0x72, 0xc8), (0xbc, 0x3f, 0xbc), (0x11, 0xa8, 0xcd), (0xe5, 0xe5, 0xe5),
+    (0x66, 0x66, 0x66), (0xf1, 0x4c, 0x4c), (0x23, 0xd1, 0x8b), (0xf5, 0xf5, 0x43),
+    (0x3b, 0x8e, 0xea), (0xd6, 0x70, 0xd6), (0x29, 0xb8, 0xdb), (0xff, 0xff, 0xff),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// 0–15 are the standard colors, 16–255 the color cube and grays
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Indexed(n @ 0..=15) => STANDARD_COLORS[n as usize],
+            Color::Indexed(n @ 16..=231) => {
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let n = n - 16;
+                (level(n / 36), level(n / 6 % 6), level(n % 6))
+            }
+            Color::Indexed(n) => {
+                let gray = 8 + (n - 232) * 10;
+                (gray, gray, gray)
+            }
+        }
+    }
+
+    pub fn to_css(self) -> String {
+        let (r, g, b) = self.rgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    /// Swap foreground and background
+    pub inverse: bool,
+}
+
+impl Style {
+    /// Apply the parameters of one SGR sequence
+    fn apply(&mut self, params: &str) {
+        let codes: Vec<u32> = params.split([';', ':']).map(|code| code.parse().unwrap_or(0)).collect();
+        let mut codes = codes.into_iter();
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.inverse = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                30..=37 => self.foreground = Some(Color::Indexed((code - 30) as u8)),
+                90..=97 => self.foreground = Some(Color::Indexed((code - 90 + 8) as u8)),
+                39 => self.foreground = None,
+                40..=47 => self.background = Some(Color::Indexed((code - 40) as u8)),
+                100..=107 => self.background = Some(Color::Indexed((code - 100 + 8) as u8)),
+                49 => self.background = None,
+                38 | 48 => {
+                    let color = match codes.next() {
+                        Some(5) => codes.next().map(|n| Color::Indexed(n.min(255) as u8)),
+                        Some(2) => {
+                            let mut channel = || codes.next().unwrap_or(0).min(255) as u8;
+                            Some(Color::Rgb(channel(), channel(), channel()))
+                        }
+                        _ => None,
+                    };
+                    if code == 38 {
+                        self.foreground = color;
+                    } else {
+                        self.background = color;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Foreground and background as drawn, after `inverse`
+    pub fn colors(&self) -> (Option<Color>, Option<Color>) {
+        if self.inverse {
+            (self.background, self.foreground)
+        } else {
+            (self.foreground, self.background)
+        }
+    }
+
+    pub fn to_ratatui(&self) -> ratatui::style::Style {
+        use ratatui::style::Modifier;
+
+        let convert = |color: Color| match color {
+            Color::Indexed(n) => ratatui::style::Color::Indexed(n),
+            Color::Rgb(r, g, b) => ratatui::style::Color::Rgb(r, g, b),
+        };
+        let mut style = ratatui::style::Style::default();
+        if let Some(color) = self.foreground {
+            style = style.fg(convert(color));
+        }
+        if let Some(color) = self.background {
+            style = style.bg(convert(color));
+        }
+        for (on, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.dim, Modifier::DIM),
+            (self.italic, Modifier::ITALIC),
+            (self.underline, Modifier::UNDERLINED),
+            (self.inverse, Modifier::REVERSED),
+        ] {
+            if on {
+                style = style.add_modifier(modifier);
+            }
+        }
+        style
+    }
+
+    /// Inline CSS, or `None` for unstyled text
+    pub fn to_css(&self) -> Option<String> {
+        let (foreground, background) = self.colors();
+        let mut css = Vec::new();
+        if let Some(color) = foreground {
+            css.push(format!("color: {}", color.to_css()));
+        }
+        if let Some(color) = background {
+            css.push(format!("background-color: {}", color.to_css()));
+        }
+        if self.bold {
+            css.push("font-weight: bold".to_string());
+        }
+        if self.dim {
+            css.push("opacity: 0.7".to_string());
+        }
+        if self.italic {
+            css.push("font-style: italic".to_string());
+        }
+        if self.underline {
+            css.push("text-decoration: underline".to_string());
+        }
+        (!css.is_empty()).then(|| css.join("; "))
+    }
+}
+
+/// A run of text in one style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// `text` as styled spans, with escape sequences removed
+pub fn parse(text: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut style = Style::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ESC => match chars.next() {
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut last = None;
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            last = Some(c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                    // Private sequences like `ESC [ ? 25 l` aren't styling
+                    if last == Some('m') && !params.starts_with(['?', '<', '=', '>']) {
+                        style.apply(&params);
+                    }
+                }
+                Some(']') => {
+                    // OSC runs to BEL or ESC \
+                    while let Some(c) = chars.next() {
+                        if c == BEL || (c == ESC && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                Some('(' | ')') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            BEL => {}
+            c => match spans.last_mut() {
+                Some(last) if last.style == style => last.text.push(c),
+                _ => spans.push(Span { text: c.to_string(), style }),
+            },
+        }
+    }
+    spans
+}
+
+/// `text` as styled spans per line; styles carry over line breaks
+pub fn lines(text: &str) -> Vec<Vec<Span>> {
+    let mut lines = vec![Vec::new()];
+    for span in parse(text) {
+        for (i, piece) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Vec::new());
+            }
+            if !piece.is_empty() {
+                lines.last_mut().unwrap().push(Span { text: piece.to_string(), style: span.style });
+            }
+        }
+    }
+    // Like `str::lines`, a final newline doesn't start another line
+    if text.ends_with('\n') && lines.last().is_some_and(Vec::is_empty) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Whether `text` has anything for `parse` to do
+pub fn has_escapes(text: &str) -> bool {
+    text.contains([ESC, BEL])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled(text: &str, style: Style) -> Span {
+        Span { text: text.to_string(), style }
+    }
+
+    #[test]
+    fn test_colors_and_attributes_become_spans() {
+        let green = Style { foreground: Some(Color::Indexed(2)), ..Style::default() };
+        let bold_green = Style { bold: true, ..green };
+        assert_eq!(
+            parse("a\x1b[32mb\x1b[1mc\x1b[0md"),
+            vec![
+                styled("a", Style::default()),
+                styled("b", green),
+                styled("c", bold_green),
+                styled("d", Style::default()),
+            ]
+        );
+
+        let spans = parse("\x1b[4;38;5;208;48;2;1;2;3mx");
+        assert_eq!(spans[0].style.foreground, Some(Color::Indexed(208)));
+        assert_eq!(spans[0].style.background, Some(Color::Rgb(1, 2, 3)));
+        assert!(spans[0].style.underline);
+        assert_eq!(Color::Indexed(208).rgb(), (255, 135, 0));
+        assert_eq!(Color::Indexed(244).rgb(), (128, 128, 128));
+    }
+
+    #[test]
+    fn test_other_sequences_are_dropped() {
+        let spans = parse("\x1b]0;title\x07a\x1b[2K\x1b[1;1Hb\x1b[?25l\x1b(Bc\x07\x1b[3");
+        assert_eq!(spans, vec![styled("abc", Style::default())]);
+    }
+
+    #[test]
+    fn test_ls_color_output_by_line() {
+        // As printed by `ls --color=always`
+        let output = "\x1b[0m\x1b[01;34msrc\x1b[0m\n\x1b[01;32mbuild.sh\x1b[0m\nREADME.md\n";
+        let lines = lines(output);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0][0].text, "src");
+        assert_eq!(lines[0][0].style.foreground, Some(Color::Indexed(4)));
+        assert!(lines[1][0].style.bold);
+        assert_eq!(lines[2], vec![styled("README.md", Style::default())]);
+    }
+}
@@ -0,0 +1,265 @@
+//! Ghost-text suggestions after the cursor, as fish shows them.
+//!
+//! History is looked up on every keystroke: the newest entry that starts
+//! with the input wins. When nothing matches and AI suggestions are on, the
+//! input goes to the provider once typing pauses, no more often than
+//! [`AI_MIN_INTERVAL`]. A request still in flight when the input changes is
+//! aborted, and a reply for older input is ignored.
+
+use futures::future::{AbortHandle, AbortRegistration};
+use std::time::{Duration, Instant};
+
+use crate::agent_mode_eval::ai_client::AiClient;
+use crate::agent_mode_eval::AgentConfig;
+use crate::diagnostics::LatencyTracker;
+
+/// Pause in typing before the AI is asked
+pub const AI_DEBOUNCE: Duration = Duration::from_millis(400);
+/// Least time between two AI requests
+pub const AI_MIN_INTERVAL: Duration = Duration::from_millis(1500);
+/// Shorter input says too little to complete
+const AI_MIN_INPUT_CHARS: usize = 3;
+/// Recent commands quoted to the AI for context
+pub const AI_HISTORY_LINES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestionSource {
+    History,
+    Ai,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// Input the suggestion was made for
+    pub input: String,
+    /// The whole suggested line, starting with `input`
+    pub text: String,
+    pub source: SuggestionSource,
+}
+
+impl Suggestion {
+    /// The part shown dimmed after the cursor
+    pub fn remainder(&self) -> &str {
+        &self.text[self.input.len()..]
+    }
+}
+
+/// What to do once typing has paused
+#[derive(Debug, Clone, PartialEq)]
+pub enum Due {
+    /// The input changed since, or already has a suggestion
+    Ignore,
+    /// Rate limited; check again after this long
+    Wait(Duration),
+    Request,
+}
+
+#[derive(Debug, Default)]
+pub struct Autosuggester {
+    current: Option<Suggestion>,
+    /// Bumped on every input change, so stale timers and replies are ignored
+    generation: u64,
+    in_flight: Option<AbortHandle>,
+    last_request: Option<Instant>,
+    pub history_latency: LatencyTracker,
+    pub ai_latency: LatencyTracker,
+}
+
+impl Autosuggester {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The input changed: drop the old suggestion and any request for it,
+    /// then look in `history`. Returns the generation to ask the AI for
+    /// after [`AI_DEBOUNCE`], if history had nothing and `ai` allows it.
+    pub fn input_changed(&mut self, input: &str, history: &[String], ai: bool) -> Option<u64> {
+        self.clear();
+        if input.trim().is_empty() || input.contains('\n') {
+            return None;
+        }
+
+        let started = Instant::now();
+        let found = from_history(history, input);
+        self.history_latency.record(started.elapsed());
+        if let Some(text) = found {
+            self.current = Some(Suggestion {
+                input: input.to_string(),
+                text: text.to_string(),
+                source: SuggestionSource::History,
+            });
+            return None;
+        }
+        (ai && input.trim().chars().count() >= AI_MIN_INPUT_CHARS).then_some(self.generation)
+    }
+
+    pub fn suggestion(&self) -> Option<&Suggestion> {
+        self.current.as_ref()
+    }
+
+    /// Take the suggested line to put in the input
+    pub fn accept(&mut self) -> Option<String> {
+        let suggestion = self.current.take()?;
+        self.clear();
+        Some(suggestion.text)
+    }
+
+    /// Forget the suggestion and abort any request in flight
+    pub fn clear(&mut self) {
+        if let Some(handle) = self.in_flight.take() {
+            handle.abort();
+        }
+        self.current = None;
+        self.generation += 1;
+    }
+
+    /// Typing paused for `generation`
+    pub fn due(&self, generation: u64, now: Instant) -> Due {
+        if generation != self.generation || self.current.is_some() || self.in_flight.is_some() {
+            return Due::Ignore;
+        }
+        match self.last_request.map(|last| last + AI_MIN_INTERVAL) {
+            Some(next) if now < next => Due::Wait(next - now),
+            _ => Due::Request,
+        }
+    }
+
+    /// A request for the current input is starting; aborting the returned
+    /// registration's future cancels it
+    pub fn start_request(&mut self, now: Instant) -> AbortRegistration {
+        let (handle, registration) = AbortHandle::new_pair();
+        self.in_flight = Some(handle);
+        self.last_request = Some(now);
+        registration
+    }
+
+    /// The AI replied to the request for `generation` after `latency`
+    pub fn ai_reply(&mut self, generation: u64, input: &str, reply: &str, latency: Duration) {
+        self.ai_latency.record(latency);
+        if generation != self.generation {
+            return;
+        }
+        self.in_flight = None;
+        self.current = ai_completion(input, reply).map(|text| Suggestion {
+            input: input.to_string(),
+            text,
+            source: SuggestionSource::Ai,
+        });
+    }
+
+    /// The request for `generation` failed or was aborted
+    pub fn ai_failed(&mut self, generation: u64) {
+        if generation == self.generation {
+            self.in_flight = None;
+        }
+    }
+}
+
+/// The newest single-line entry that starts with `input` and goes further
+pub fn from_history<'a>(entries: &'a [String], input: &str) -> Option<&'a str> {
+    entries
+        .iter()
+        .rev()
+        .find(|entry| entry.len() > input.len() && entry.starts_with(input) && !entry.contains('\n'))
+        .map(String::as_str)
+}
+
+/// A small, fast client for completions: no tools, short replies
+pub fn ai_client(config: &AgentConfig) -> Option<AiClient> {
+    AiClient::new(AgentConfig {
+        tools_enabled: false,
+        max_tokens: Some(64),
+        temperature: 0.2,
+        ..config.clone()
+    })
+    .ok()
+}
+
+pub fn ai_prompt(input: &str, shell: &str, recent: &[String]) -> String {
+    let recent: Vec<&str> = recent
+        .iter()
+        .rev()
+        .filter(|entry| !entry.contains('\n'))
+        .take(AI_HISTORY_LINES)
+        .map(String::as_str)
+        .collect();
+    format!(
+        "Complete this {} command line. Reply with the whole line, starting with exactly what was typed, \
+         on one line and nothing else.\n\nRecent commands, newest first:\n{}\n\nTyped so far: {}",
+        shell,
+        recent.join("\n"),
+        input
+    )
+}
+
+/// The first line of `reply` that continues `input`, ignoring code fences
+/// and a leading prompt sign
+pub fn ai_completion(input: &str, reply: &str) -> Option<String> {
+    reply
+        .lines()
+        .map(|line| line.trim_end().trim_matches('`'))
+        .map(|line| line.strip_prefix("$ ").unwrap_or(line))
+        .find(|line| line.len() > input.len() && line.starts_with(input))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> Vec<String> {
+        ["git status", "git commit -m 'wip'", "cargo test", "git checkout main"].map(str::to_string).to_vec()
+    }
+
+    #[test]
+    fn test_newest_history_match_wins() {
+        let mut suggester = Autosuggester::new();
+        assert_eq!(suggester.input_changed("git c", &history(), true), None);
+        let suggestion = suggester.suggestion().unwrap();
+        assert_eq!(suggestion.remainder(), "heckout main");
+        assert_eq!(suggestion.source, SuggestionSource::History);
+        assert_eq!(suggester.history_latency.len(), 1);
+
+        assert_eq!(suggester.accept().as_deref(), Some("git checkout main"));
+        assert!(suggester.suggestion().is_none());
+
+        // Nothing in history: the AI is asked, if allowed
+        assert!(suggester.input_changed("docker ps", &history(), true).is_some());
+        assert_eq!(suggester.input_changed("docker ps", &history(), false), None);
+        // An exact match leaves nothing to suggest
+        assert!(suggester.input_changed("cargo test", &history(), true).is_some());
+    }
+
+    #[test]
+    fn test_ai_requests_are_debounced_and_rate_limited() {
+        let now = Instant::now();
+        let mut suggester = Autosuggester::new();
+        let first = suggester.input_changed("docker ps", &[], true).unwrap();
+        let second = suggester.input_changed("docker ps -", &[], true).unwrap();
+        assert_eq!(suggester.due(first, now), Due::Ignore);
+        assert_eq!(suggester.due(second, now), Due::Request);
+
+        let _registration = suggester.start_request(now);
+        assert_eq!(suggester.due(second, now), Due::Ignore);
+        // A reply for input that has changed since is dropped
+        let third = suggester.input_changed("docker ps -a", &[], true).unwrap();
+        suggester.ai_reply(second, "docker ps -", "docker ps -a", Duration::from_millis(300));
+        assert!(suggester.suggestion().is_none());
+        assert_eq!(suggester.ai_latency.len(), 1);
+
+        let soon = now + Duration::from_millis(500);
+        assert_eq!(suggester.due(third, soon), Due::Wait(AI_MIN_INTERVAL - Duration::from_millis(500)));
+        assert_eq!(suggester.due(third, now + AI_MIN_INTERVAL), Due::Request);
+        suggester.start_request(now + AI_MIN_INTERVAL);
+        suggester.ai_reply(third, "docker ps -a", "docker ps -a --format '{{.Names}}'", Duration::from_millis(200));
+        assert_eq!(suggester.suggestion().unwrap().remainder(), " --format '{{.Names}}'");
+    }
+
+    #[test]
+    fn test_ai_completion_must_continue_input() {
+        assert_eq!(ai_completion("ls -", "ls -la").as_deref(), Some("ls -la"));
+        assert_eq!(ai_completion("ls -", "```bash\n$ ls -lh\n```").as_deref(), Some("ls -lh"));
+        assert_eq!(ai_completion("ls -", "la"), None);
+        assert_eq!(ai_completion("ls -", "ls -"), None);
+    }
+}
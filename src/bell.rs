@@ -0,0 +1,192 @@
+//! Terminal bell: finds BEL characters in command output and decides what
+//! ringing it does under the `bell_behavior` preference.
+//!
+//! BEL also terminates OSC sequences (`ESC ] ... BEL`, e.g. window titles),
+//! which are not bells. The detector keeps its escape state between chunks
+//! so a sequence split across reads is still recognised.
+
+use std::time::{Duration, Instant};
+use crate::config::BellBehavior;
+
+pub const BEL: u8 = 0x07;
+const ESC: u8 = 0x1b;
+
+/// At most one bell action per this interval, however many bells arrive
+pub const MIN_BELL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a visual bell stays lit
+pub const FLASH_DURATION: Duration = Duration::from_millis(150);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum State {
+    #[default]
+    Ground,
+    /// After ESC
+    Escape,
+    /// Inside `ESC ]`, up to BEL or `ESC \`
+    Osc,
+    /// ESC inside an OSC, possibly the start of `ESC \`
+    OscEscape,
+}
+
+/// Counts bells in a stream of output chunks
+#[derive(Debug, Clone, Default)]
+pub struct BellDetector {
+    state: State,
+}
+
+impl BellDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of bells in `bytes`, continuing from the previous chunk
+    pub fn feed(&mut self, bytes: &[u8]) -> u32 {
+        let mut bells = 0;
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (State::Ground, BEL) => {
+                    bells += 1;
+                    State::Ground
+                }
+                (State::Ground, ESC) => State::Escape,
+                (State::Ground, _) => State::Ground,
+                (State::Escape, b']') => State::Osc,
+                (State::Escape, ESC) => State::Escape,
+                (State::Escape, BEL) => {
+                    bells += 1;
+                    State::Ground
+                }
+                (State::Escape, _) => State::Ground,
+                (State::Osc, BEL) => State::Ground,
+                (State::Osc, ESC) => State::OscEscape,
+                (State::Osc, _) => State::Osc,
+                (State::OscEscape, b'\\') => State::Ground,
+                (State::OscEscape, BEL) => State::Ground,
+                (State::OscEscape, ESC) => State::OscEscape,
+                (State::OscEscape, _) => State::Osc,
+            };
+        }
+        bells
+    }
+}
+
+/// Lets one bell action through per `MIN_BELL_INTERVAL`
+#[derive(Debug, Clone, Default)]
+pub struct BellLimiter {
+    last: Option<Instant>,
+}
+
+impl BellLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(&mut self, now: Instant) -> bool {
+        match self.last {
+            Some(last) if now.duration_since(last) < MIN_BELL_INTERVAL => false,
+            _ => {
+                self.last = Some(now);
+                true
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BellAction {
+    Sound,
+    /// Flash the block border and status line
+    Flash,
+    /// Desktop notification
+    Notify,
+    /// Ask the window manager to mark the window as wanting attention
+    Urgent,
+}
+
+/// What a bell does for `behavior`, given whether the window has focus
+pub fn actions(behavior: &BellBehavior, focused: bool) -> Vec<BellAction> {
+    let mut actions = match behavior {
+        BellBehavior::None => return Vec::new(),
+        BellBehavior::Visual => vec![BellAction::Flash],
+        BellBehavior::Audio => vec![BellAction::Sound],
+        BellBehavior::Both => vec![BellAction::Sound, BellAction::Flash],
+        BellBehavior::Notification if focused => vec![BellAction::Flash],
+        BellBehavior::Notification => vec![BellAction::Notify],
+    };
+    if !focused {
+        actions.push(BellAction::Urgent);
+    }
+    actions
+}
+
+/// Short beep on the default output device, off the UI thread
+pub fn play_sound() {
+    std::thread::spawn(|| {
+        use rodio::Source;
+        let Ok((_stream, handle)) = rodio::OutputStream::try_default() else {
+            return;
+        };
+        let tone = rodio::source::SineWave::new(880.0)
+            .take_duration(Duration::from_millis(120))
+            .amplify(0.2);
+        if let Ok(sink) = rodio::Sink::try_new(&handle) {
+            sink.append(tone);
+            sink.sleep_until_end();
+        }
+    });
+}
+
+pub fn notify(command: &str) {
+    let body = crate::layout::truncate(command, 80);
+    std::thread::spawn(move || {
+        if let Err(e) = notify_rust::Notification::new().summary(crate::i18n::tr("notify.bell")).body(&body).show() {
+            log::debug!("Bell notification failed: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_split(stream: &[u8], split: usize) -> u32 {
+        let mut detector = BellDetector::new();
+        let (first, second) = stream.split_at(split);
+        detector.feed(first) + detector.feed(second)
+    }
+
+    #[test]
+    fn test_counts_bells_across_every_chunk_boundary() {
+        let stream = b"done\x07 \x1b]0;build: ok\x07 \x1b]2;title\x1b\\ \x1b[1mbold\x1b[0m\x07\x07";
+        for split in 0..=stream.len() {
+            assert_eq!(count_split(stream, split), 3, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_osc_terminator_is_not_a_bell() {
+        let mut detector = BellDetector::new();
+        assert_eq!(detector.feed(b"\x1b]0;vim"), 0);
+        assert_eq!(detector.feed(b" main.rs\x07"), 0);
+        assert_eq!(detector.feed(b"\x07"), 1);
+    }
+
+    #[test]
+    fn test_bell_storm_is_rate_limited() {
+        let mut limiter = BellLimiter::new();
+        let start = Instant::now();
+        let allowed = (0..1000)
+            .filter(|i| limiter.allow(start + Duration::from_millis(*i as u64 * 3)))
+            .count();
+        // 3 seconds of bells every 3ms
+        assert_eq!(allowed, 3);
+    }
+
+    #[test]
+    fn test_actions_follow_preference_and_focus() {
+        assert!(actions(&BellBehavior::None, false).is_empty());
+        assert_eq!(actions(&BellBehavior::Both, true), vec![BellAction::Sound, BellAction::Flash]);
+        assert_eq!(actions(&BellBehavior::Notification, true), vec![BellAction::Flash]);
+        assert_eq!(actions(&BellBehavior::Notification, false), vec![BellAction::Notify, BellAction::Urgent]);
+    }
+}
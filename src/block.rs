@@ -1,7 +1,100 @@
-use iced::{Element, widget::{column, row, text, button, container}};
+use iced::{Element, widget::{column, row, text, button, container, scrollable, slider, tooltip}};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::agent_mode_eval::context::{self, OutputLine};
+use crate::ansi;
+use crate::block_search::Highlight;
+use crate::diagnostics::DiagnosticsReport;
+use crate::git_status::{GitAction, GitStatus};
+use crate::dir_listing::{DirListing, DirMessage, EntryKind, SortKey};
+use crate::file_preview::{FilePreview, PreviewContent};
+use crate::find_replace::FindReplaceState;
+use crate::font::OutputFont;
+use crate::i18n::{format_duration, format_number, tr, tr_args};
+use crate::layout::{HeaderLayout, ResponsiveLayout};
+use crate::links::{Link, LinkIndex};
+use crate::markdown_parser::{InlineSpan, ListMarker, MarkdownBlock, StreamingMarkdown};
+use crate::path_inspector::Resolution;
+use crate::plugin_api::PluginBlock;
+use crate::read_only::ReadOnlyReason;
+use crate::renderer::SyntaxHighlighter;
+use crate::scrollback::Scrollback;
+use crate::share::ShareRecord;
+use crate::tee::TeeStatus;
+use crate::timeline::{MarkerKind, OutputChunk, OutputTimeline, TimelineMarker};
+
+/// Command line and its details, laid out by `lines` for the available width
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockHeader {
+    pub title: String,
+    /// Working directory and exit status
+    pub meta: String,
+}
+
+impl BlockHeader {
+    pub fn lines(&self, layout: &ResponsiveLayout) -> Vec<String> {
+        let columns = layout.columns;
+        match layout.block_header() {
+            HeaderLayout::SingleLine => {
+                let meta_width = self.meta.chars().count();
+                let title = crate::status_line::ellipsize_middle(&self.title, columns.saturating_sub(meta_width + 1));
+                let gap = columns.saturating_sub(title.chars().count() + meta_width).max(1);
+                vec![format!("{}{}{}", title, " ".repeat(gap), self.meta)]
+            }
+            HeaderLayout::TwoLine => vec![
+                crate::status_line::ellipsize_middle(&self.title, columns),
+                crate::status_line::ellipsize_middle(&format!("  {}", self.meta), columns),
+            ],
+        }
+    }
+}
+
+/// When, where and how a command ran
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandMetadata {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub exit_code: Option<i32>,
+    pub working_directory: String,
+    pub env_profile: Option<String>,
+}
+
+impl CommandMetadata {
+    /// How long the command ran, or has been running as of `now`
+    pub fn elapsed(&self, now: DateTime<Utc>) -> std::time::Duration {
+        (self.finished_at.unwrap_or(now) - self.started_at).to_std().unwrap_or_default()
+    }
+
+    /// The row under a command's header, e.g. "✓ 0 · 1.2s · ~/projects"
+    pub fn summary(&self, now: DateTime<Utc>) -> String {
+        let status = match self.exit_code {
+            None => BlockStatus::Running.glyph().to_string(),
+            Some(0) => format!("{} 0", BlockStatus::Succeeded.glyph()),
+            Some(code) => format!("{} {}", BlockStatus::Failed(code).glyph(), code),
+        };
+        let mut parts = vec![
+            status,
+            format_duration(self.elapsed(now)),
+            crate::status_line::display_path(std::path::Path::new(&self.working_directory)),
+        ];
+        parts.extend(self.env_profile.clone());
+        parts.join(" · ")
+    }
+}
+
+/// A prompt printed by a shell running inside a block, found through its
+/// OSC 133 marks (see `shell_integration`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptMark {
+    /// Output line the prompt starts on, counting lines no longer in memory
+    pub line: usize,
+    /// What was typed at it, once submitted
+    pub command: Option<String>,
+    pub exit_code: Option<i32>,
+}
 
 #[derive(Debug, Clone)]
 pub struct Block {
@@ -9,6 +102,90 @@ pub struct Block {
     pub content: BlockContent,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Set once the block has been shared
+    pub shared: Option<ShareRecord>,
+    /// Block this one was started from, such as the reply a snippet came from
+    pub source: Option<Uuid>,
+    /// File the running command's output is also being written to
+    pub tee: Option<TeeStatus>,
+    /// Where the command's program was found, kept when it shadows another
+    /// executable of the same name further down PATH
+    pub resolution: Option<Resolution>,
+    /// The assistant's explanation of the output, shown under the block
+    pub annotation: Option<Annotation>,
+    /// Workflow the command was run for, such as by its schedule
+    pub workflow: Option<String>,
+    /// Kept above the other blocks
+    pub pinned: bool,
+    /// Shown as a one-line preview instead of the output or reply
+    pub collapsed: bool,
+    /// The assistant's corrected command, offered under a failed block
+    pub fix: Option<FixSuggestion>,
+    /// Failed block whose suggested fix this command runs
+    pub retry_of: Option<Uuid>,
+    /// Container the command ran in, or whose logs it follows
+    pub container: Option<String>,
+}
+
+/// A corrected command suggested for a failed block
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixSuggestion {
+    Loading,
+    Ready { command: String, run: FixRun },
+    /// No fix, or the request failed
+    Failed(String),
+}
+
+/// How a suggested fix can be run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixRun {
+    /// One-click fixes are off; the fix goes through the input
+    Manual,
+    OneClick,
+    /// Looks destructive, so it goes through the input whatever the preference
+    Review(&'static str),
+}
+
+/// An explanation attached under a command block. It stays out of the
+/// conversation unless pinned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// What was asked, kept so a pinned explanation reads in context
+    pub prompt: String,
+    pub state: AnnotationState,
+    pub collapsed: bool,
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationState {
+    Loading,
+    Ready(String),
+    Failed(String),
+}
+
+impl Annotation {
+    pub fn new(prompt: String) -> Self {
+        Self { prompt, state: AnnotationState::Loading, collapsed: false, pinned: false }
+    }
+}
+
+/// Outcome of a command block, conveyed by glyph as well as color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockStatus {
+    Running,
+    Succeeded,
+    Failed(i32),
+}
+
+impl BlockStatus {
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            BlockStatus::Running => "⏳",
+            BlockStatus::Succeeded => "✓",
+            BlockStatus::Failed(_) => "✗",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -18,21 +195,63 @@ pub enum BlockContent {
         output: Option<String>,
         exit_code: Option<i32>,
         working_directory: String,
+        /// One-off `NAME=value` assignments typed before the command
+        env_overrides: Vec<(String, String)>,
+        /// When each piece of output arrived, for scrubbing back in time
+        timeline: OutputTimeline,
+        markers: Vec<TimelineMarker>,
+        /// Scrubber position; `None` shows the full output
+        scrub_ms: Option<u64>,
+        /// BEL characters the command has printed
+        bells: u32,
+        /// How much output stays in memory, and where the rest went
+        scrollback: Scrollback,
+        started_at: DateTime<Utc>,
+        /// When the command exited; `None` while it runs
+        finished_at: Option<DateTime<Utc>>,
+        /// Env profile that was active when the command started
+        env_profile: Option<String>,
+        /// Prompts of an interactive shell running in the block
+        prompts: Vec<PromptMark>,
+        /// The prompt Ctrl+Up/Down last moved to
+        prompt_cursor: Option<usize>,
+        /// URLs and file paths in the output
+        links: LinkIndex,
     },
     AgentMessage {
         content: String,
+        /// `content` parsed, kept up to date as the reply streams in
+        markdown: StreamingMarkdown,
         role: AgentRole,
+        /// Conversation message shown by this block, once recorded
+        message_id: Option<Uuid>,
+        /// Replaced by an edited turn; kept collapsed instead of deleted
+        superseded: bool,
     },
     UserMessage {
         content: String,
+        message_id: Option<Uuid>,
+        superseded: bool,
     },
     Error {
         message: String,
     },
     Separator,
+    /// Interactive find-and-replace across the workspace
+    FindReplace(FindReplaceState),
+    /// Drawn from a plugin's render tree
+    Plugin(PluginBlock),
+    /// Subsystem health, refreshed while the block is open
+    Diagnostics(DiagnosticsReport),
+    /// Files changed in a git repository, refreshed when it changes
+    GitStatus(GitStatus),
+    /// Entries of a directory, to browse through
+    Directory(DirListing),
+    /// Start of a file, highlighted, or a hex dump of a binary one
+    Preview(FilePreview),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AgentRole {
     Assistant,
     User,
@@ -41,6 +260,10 @@ pub enum AgentRole {
 
 impl Block {
     pub fn new_command(input: String) -> Self {
+        Self::new_command_with_env(input, Vec::new())
+    }
+
+    pub fn new_command_with_env(input: String, env_overrides: Vec<(String, String)>) -> Self {
         let now = Utc::now();
         Self {
             id: Uuid::new_v4(),
@@ -51,22 +274,68 @@ impl Block {
                 working_directory: std::env::current_dir()
                     .map(|p| p.to_string_lossy().to_string())
                     .unwrap_or_else(|_| "~".to_string()),
+                env_overrides,
+                timeline: OutputTimeline::new(),
+                markers: Vec::new(),
+                scrub_ms: None,
+                bells: 0,
+                scrollback: Scrollback::default(),
+                started_at: now,
+                finished_at: None,
+                env_profile: None,
+                prompts: Vec::new(),
+                prompt_cursor: None,
+                links: LinkIndex::default(),
             },
             created_at: now,
             updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
         }
     }
 
+    /// A system notice, for information that isn't an error
+    pub fn new_info(content: String) -> Self {
+        let mut block = Self::new_agent_message(content);
+        if let BlockContent::AgentMessage { ref mut role, .. } = block.content {
+            *role = AgentRole::System;
+        }
+        block
+    }
+
     pub fn new_agent_message(content: String) -> Self {
         let now = Utc::now();
         Self {
             id: Uuid::new_v4(),
             content: BlockContent::AgentMessage {
+                markdown: StreamingMarkdown::new(&content),
                 content,
                 role: AgentRole::Assistant,
+                message_id: None,
+                superseded: false,
             },
             created_at: now,
             updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
         }
     }
 
@@ -74,9 +343,167 @@ impl Block {
         let now = Utc::now();
         Self {
             id: Uuid::new_v4(),
-            content: BlockContent::UserMessage { content },
+            content: BlockContent::UserMessage { content, message_id: None, superseded: false },
+            created_at: now,
+            updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
+        }
+    }
+
+    pub fn new_separator() -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            content: BlockContent::Separator,
+            created_at: now,
+            updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
+        }
+    }
+
+    pub fn new_diagnostics(report: DiagnosticsReport) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            content: BlockContent::Diagnostics(report),
+            created_at: now,
+            updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
+        }
+    }
+
+    pub fn new_git_status(status: GitStatus) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            content: BlockContent::GitStatus(status),
+            created_at: now,
+            updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
+        }
+    }
+
+    pub fn new_directory(listing: DirListing) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            content: BlockContent::Directory(listing),
+            created_at: now,
+            updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
+        }
+    }
+
+    pub fn new_preview(preview: FilePreview) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            content: BlockContent::Preview(preview),
+            created_at: now,
+            updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
+        }
+    }
+
+    pub fn new_find_replace(root: PathBuf) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            content: BlockContent::FindReplace(FindReplaceState::new(root)),
+            created_at: now,
+            updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
+        }
+    }
+
+    pub fn new_plugin(block: PluginBlock) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            content: BlockContent::Plugin(block),
             created_at: now,
             updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
         }
     }
 
@@ -87,26 +514,570 @@ impl Block {
             content: BlockContent::Error { message },
             created_at: now,
             updated_at: now,
+            shared: None,
+            source: None,
+            tee: None,
+            resolution: None,
+            annotation: None,
+            workflow: None,
+            pinned: false,
+            collapsed: false,
+            fix: None,
+            retry_of: None,
+            container: None,
+        }
+    }
+
+    /// Tag a command block with the workflow it was run for
+    pub fn with_workflow(mut self, name: impl Into<String>) -> Self {
+        self.workflow = Some(name.into());
+        self
+    }
+
+    /// Link an agent or user message block to its conversation message
+    pub fn with_message_id(mut self, id: Uuid) -> Self {
+        self.set_message_id(id);
+        self
+    }
+
+    pub fn set_message_id(&mut self, id: Uuid) {
+        match self.content {
+            BlockContent::AgentMessage { ref mut message_id, .. }
+            | BlockContent::UserMessage { ref mut message_id, .. } => *message_id = Some(id),
+            _ => {}
+        }
+    }
+
+    pub fn message_id(&self) -> Option<Uuid> {
+        match self.content {
+            BlockContent::AgentMessage { message_id, .. }
+            | BlockContent::UserMessage { message_id, .. } => message_id,
+            _ => None,
+        }
+    }
+
+    pub fn mark_superseded(&mut self) {
+        match self.content {
+            BlockContent::AgentMessage { ref mut superseded, .. }
+            | BlockContent::UserMessage { ref mut superseded, .. } => {
+                *superseded = true;
+                self.updated_at = Utc::now();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_superseded(&self) -> bool {
+        matches!(
+            self.content,
+            BlockContent::AgentMessage { superseded: true, .. } | BlockContent::UserMessage { superseded: true, .. }
+        )
+    }
+
+    /// Add a streamed piece of an agent reply
+    pub fn append_agent_text(&mut self, chunk: &str) {
+        if let BlockContent::AgentMessage { ref mut content, ref mut markdown, .. } = self.content {
+            content.push_str(chunk);
+            markdown.update(content);
+            self.updated_at = Utc::now();
+        }
+    }
+
+    /// Language tags and contents of the fenced code in an agent reply, in
+    /// order; an unclosed fence counts
+    pub fn code_blocks(&self) -> Vec<(&str, &str)> {
+        match &self.content {
+            BlockContent::AgentMessage { markdown, .. } => markdown.code_blocks(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// An agent reply as styled lines, with its code blocks kept apart
+    pub fn markdown_sections(&self) -> Vec<MarkdownSection> {
+        match &self.content {
+            BlockContent::AgentMessage { markdown, .. } => markdown_sections(markdown),
+            _ => Vec::new(),
         }
     }
 
     pub fn set_output(&mut self, output: String, exit_code: i32) {
-        if let BlockContent::Command { ref mut output: cmd_output, ref mut exit_code: cmd_exit_code, .. } = self.content {
-            *cmd_output = Some(output);
+        if let BlockContent::Command {
+            output: ref mut cmd_output,
+            exit_code: ref mut cmd_exit_code,
+            ref mut scrollback,
+            ref mut finished_at,
+            ref mut links,
+            ref working_directory,
+            ..
+        } = self.content {
+            let output = cmd_output.insert(output);
+            let cut = scrollback.appended(self.id, output, 0);
+            output.drain(..cut);
+            *links = LinkIndex::default();
+            links.update(output, Path::new(working_directory));
             *cmd_exit_code = Some(exit_code);
             self.updated_at = Utc::now();
+            finished_at.get_or_insert(self.updated_at);
+        }
+    }
+
+    /// Append streamed output, recording when it arrived. Beyond the
+    /// scrollback limit the oldest lines are dropped from memory.
+    pub fn append_chunk(&mut self, chunk: OutputChunk) {
+        if let BlockContent::Command { ref mut output, ref mut timeline, ref mut scrollback, ref mut links, ref working_directory, .. } = self.content {
+            let output = output.get_or_insert_with(String::new);
+            let start = output.len();
+            output.push_str(&chunk.text);
+            let cut = scrollback.appended(self.id, output, start);
+            timeline.push(chunk);
+            if cut > 0 {
+                links.drop_front(cut, output[..cut].matches('\n').count());
+                output.drain(..cut);
+                timeline.drop_front(cut);
+            }
+            links.update(output, Path::new(working_directory));
+            self.updated_at = Utc::now();
+        }
+    }
+
+    /// Keep at most `lines` lines of output in memory; 0 keeps everything
+    pub fn set_scrollback_limit(&mut self, lines: usize) {
+        if let BlockContent::Command { ref mut scrollback, .. } = self.content {
+            scrollback.limit = lines;
+        }
+    }
+
+    /// URLs and file paths found in a command block's output
+    pub fn links(&self) -> &[Link] {
+        match &self.content {
+            BlockContent::Command { links, .. } => links.links(),
+            _ => &[],
+        }
+    }
+
+    /// Output limits of a command block; `None` for other block kinds
+    pub fn scrollback(&self) -> Option<&Scrollback> {
+        match &self.content {
+            BlockContent::Command { scrollback, .. } => Some(scrollback),
+            _ => None,
+        }
+    }
+
+    pub fn toggle_collapse(&mut self) {
+        self.collapsed = !self.collapsed;
+    }
+
+    /// Lines of output printed, including those no longer in memory
+    pub fn printed_lines(&self) -> usize {
+        self.scrollback().map_or(0, Scrollback::total_lines)
+    }
+
+    /// How long a finished command ran
+    pub fn duration(&self) -> Option<std::time::Duration> {
+        self.metadata().filter(|metadata| metadata.finished_at.is_some()).map(|metadata| metadata.elapsed(Utc::now()))
+    }
+
+    /// When, where and how a command block ran; `None` for other block kinds
+    pub fn metadata(&self) -> Option<CommandMetadata> {
+        match &self.content {
+            BlockContent::Command { exit_code, working_directory, started_at, finished_at, env_profile, .. } => Some(CommandMetadata {
+                started_at: *started_at,
+                finished_at: *finished_at,
+                exit_code: *exit_code,
+                working_directory: working_directory.clone(),
+                env_profile: env_profile.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Start the clock again, for a block created before its command runs
+    pub fn start(&mut self, env_profile: Option<String>) {
+        if let BlockContent::Command { ref mut started_at, finished_at: None, env_profile: ref mut profile, .. } = self.content {
+            *started_at = Utc::now();
+            *profile = env_profile;
+        }
+    }
+
+    /// What a collapsed block shows of its content: the last line a command
+    /// printed, or the first heading or sentence of a message
+    pub fn collapsed_preview(&self) -> String {
+        const PREVIEW_CHARS: usize = 100;
+        let preview = match &self.content {
+            BlockContent::Command { .. } => ansi::lines(self.output_text())
+                .into_iter()
+                .map(|spans| spans.into_iter().map(|span| span.text).collect::<String>())
+                .rfind(|line| !line.trim().is_empty())
+                .map(|line| line.trim().to_string())
+                .unwrap_or_default(),
+            BlockContent::AgentMessage { content, .. } | BlockContent::UserMessage { content, .. } => first_heading_or_sentence(content),
+            _ => String::new(),
+        };
+        crate::layout::truncate(&preview, PREVIEW_CHARS)
+    }
+
+    /// Count bells rung by a command block
+    pub fn ring_bell(&mut self, count: u32) {
+        if let BlockContent::Command { ref mut bells, .. } = self.content {
+            *bells += count;
+        }
+    }
+
+    /// Mark a streamed command as finished and place its timeline markers
+    pub fn finish_output(&mut self, code: i32, duration_ms: u64, alert_patterns: &[regex::Regex]) {
+        if let BlockContent::Command { ref mut output, ref mut exit_code, ref mut timeline, ref mut markers, started_at, ref mut finished_at, .. } = self.content {
+            output.get_or_insert_with(String::new);
+            *exit_code = Some(code);
+            *finished_at = Some(started_at + chrono::Duration::milliseconds(duration_ms as i64));
+            timeline.duration_ms = timeline.duration_ms.max(duration_ms);
+            *markers = timeline.markers(alert_patterns);
+            self.updated_at = Utc::now();
+        }
+    }
+
+    /// A shell in the block printed a prompt at the end of the output
+    pub fn mark_prompt(&mut self) {
+        if let BlockContent::Command { ref mut prompts, ref scrollback, .. } = self.content {
+            prompts.push(PromptMark { line: scrollback.total_lines(), command: None, exit_code: None });
+        }
+    }
+
+    /// The command submitted at the last prompt
+    pub fn set_prompt_command(&mut self, command: String) {
+        if let BlockContent::Command { ref mut prompts, .. } = self.content {
+            if let Some(prompt) = prompts.last_mut() {
+                prompt.command = Some(command);
+            }
+        }
+    }
+
+    /// How the command submitted at the last prompt exited
+    pub fn set_prompt_exit(&mut self, exit_code: Option<i32>) {
+        if let BlockContent::Command { ref mut prompts, .. } = self.content {
+            if let Some(prompt) = prompts.last_mut() {
+                prompt.exit_code = exit_code;
+            }
+        }
+    }
+
+    pub fn prompts(&self) -> &[PromptMark] {
+        match &self.content {
+            BlockContent::Command { prompts, .. } => prompts,
+            _ => &[],
+        }
+    }
+
+    pub fn prompt_cursor(&self) -> Option<usize> {
+        match &self.content {
+            BlockContent::Command { prompt_cursor, .. } => *prompt_cursor,
+            _ => None,
+        }
+    }
+
+    pub fn set_prompt_cursor(&mut self, cursor: Option<usize>) {
+        if let BlockContent::Command { ref mut prompt_cursor, .. } = self.content {
+            *prompt_cursor = cursor;
+        }
+    }
+
+    /// Show the output as of `offset_ms`, or all of it with `None`
+    pub fn scrub_to(&mut self, offset_ms: Option<u64>) {
+        if let BlockContent::Command { ref mut scrub_ms, .. } = self.content {
+            *scrub_ms = offset_ms;
+        }
+    }
+
+    /// Markdown export used for sharing. Not redacted; callers redact first.
+    pub fn to_markdown(&self) -> String {
+        match &self.content {
+            BlockContent::Command { input, output, exit_code, working_directory, .. } => {
+                let mut markdown = format!("```sh\n$ {}\n", input);
+                if let Some(output) = output {
+                    markdown.push_str(output);
+                    if !output.ends_with('\n') {
+                        markdown.push('\n');
+                    }
+                }
+                markdown.push_str("```\n");
+                markdown.push_str(&format!("\n_in `{}`", working_directory));
+                if let Some(code) = exit_code {
+                    markdown.push_str(&format!(", exit {}", code));
+                }
+                markdown.push_str("_\n");
+                markdown
+            }
+            BlockContent::AgentMessage { content, role: AgentRole::System, .. } => format!("> {}\n", content),
+            BlockContent::AgentMessage { content, .. } => format!("**Assistant:**\n\n{}\n", content),
+            BlockContent::UserMessage { content, .. } => format!("**User:**\n\n{}\n", content),
+            BlockContent::Error { message } => format!("> **Error:** {}\n", message),
+            BlockContent::Plugin(plugin_block) => match &plugin_block.tree {
+                Some(tree) => format!("```\n{}\n```\n", tree.to_plain_text()),
+                None => String::new(),
+            },
+            BlockContent::Diagnostics(report) => format!("```\n{}\n```\n", report.lines().join("\n")),
+            BlockContent::Directory(listing) => {
+                let mut lines = vec![listing.dir.display().to_string()];
+                lines.extend(listing.entries.iter().map(|entry| {
+                    if entry.is_dir { format!("{}/", entry.name) } else { entry.name.clone() }
+                }));
+                format!("```\n{}\n```\n", lines.join("\n"))
+            }
+            BlockContent::Preview(preview) => {
+                let language = match &preview.content {
+                    PreviewContent::Text(_) => preview.path.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default(),
+                    PreviewContent::Binary(_) => String::new(),
+                };
+                format!("`{}`\n\n```{}\n{}\n```\n", preview.path.display(), language, preview.plain_lines().join("\n"))
+            }
+            BlockContent::GitStatus(status) => {
+                let mut lines = vec![status.indicator()];
+                lines.extend(status.staged.iter().map(|entry| format!("staged    {} {}", entry.change.letter(), entry.path)));
+                lines.extend(status.unstaged.iter().map(|entry| format!("unstaged  {} {}", entry.change.letter(), entry.path)));
+                lines.extend(status.untracked.iter().map(|path| format!("untracked ? {}", path)));
+                format!("```\n{}\n```\n", lines.join("\n"))
+            }
+            BlockContent::Separator | BlockContent::FindReplace(_) => String::new(),
+        }
+    }
+
+    /// Short description for share titles
+    pub fn title(&self) -> String {
+        match &self.content {
+            BlockContent::Command { input, .. } => input.clone(),
+            BlockContent::AgentMessage { .. } => "Assistant reply".to_string(),
+            BlockContent::UserMessage { .. } => "Prompt".to_string(),
+            BlockContent::Error { .. } => "Error".to_string(),
+            BlockContent::Plugin(plugin_block) => plugin_block.plugin.clone(),
+            BlockContent::Diagnostics(_) => "Diagnostics".to_string(),
+            BlockContent::GitStatus(_) => "Git status".to_string(),
+            BlockContent::Directory(listing) => listing.dir.display().to_string(),
+            BlockContent::Preview(preview) => preview.path.display().to_string(),
+            BlockContent::Separator | BlockContent::FindReplace(_) => String::new(),
+        }
+    }
+
+    /// Header of a command block; `None` for other block kinds
+    pub fn header(&self, show_status_glyphs: bool) -> Option<BlockHeader> {
+        let BlockContent::Command { input, working_directory, env_overrides, bells, .. } = &self.content else {
+            return None;
+        };
+        let status = self.status().unwrap_or(BlockStatus::Running);
+        let glyph = if show_status_glyphs {
+            format!("{} ", status.glyph())
+        } else {
+            String::new()
+        };
+        let env_prefix: String = env_overrides
+            .iter()
+            .map(|(key, value)| format!("{} ", crate::redaction::display_env_pair(key, value)))
+            .collect();
+        let mut outcome = match status {
+            BlockStatus::Running => tr("block.running").to_string(),
+            BlockStatus::Succeeded => tr_args("block.exit", &[("code", &0)]),
+            BlockStatus::Failed(code) => tr_args("block.exit", &[("code", &code)]),
+        };
+        if *bells > 0 {
+            outcome.push_str(&format!(" · 🔔{}", bells));
+        }
+        if let Some(scrollback) = self.scrollback().filter(|s| s.is_truncated()) {
+            outcome.push_str(" · ");
+            outcome.push_str(&tr_args(
+                "block.truncated",
+                &[
+                    ("total", &format_number(scrollback.total_lines() as u64)),
+                    ("shown", &format_number(scrollback.shown_lines() as u64)),
+                ],
+            ));
+        }
+        if self.source.is_some() {
+            outcome.push_str(" · ");
+            outcome.push_str(tr("block.snippet"));
+        }
+        if self.retry_of.is_some() {
+            outcome.push_str(" · ");
+            outcome.push_str(tr("block.retry"));
+        }
+        if let Some(container) = &self.container {
+            outcome.push_str(" · ");
+            outcome.push_str(&tr_args("block.container", &[("name", container)]));
+        }
+        if let Some(workflow) = &self.workflow {
+            outcome.push_str(" · ");
+            outcome.push_str(&tr_args("block.workflow", &[("name", workflow)]));
+        }
+        if let Some(resolution) = &self.resolution {
+            if let (Some(resolved), Some(shadowed)) = (resolution.resolved(), resolution.shadowed().first()) {
+                outcome.push_str(" · ");
+                outcome.push_str(&tr_args(
+                    "block.shadowing",
+                    &[("path", &resolved.display()), ("shadowed", &shadowed.display())],
+                ));
+            }
+        }
+
+        Some(BlockHeader {
+            title: format!("{}$ {}{}", glyph, env_prefix, input),
+            meta: format!("{} · {}", crate::status_line::display_path(std::path::Path::new(working_directory)), outcome),
+        })
+    }
+
+    /// Output of a command block so far; empty for other block kinds
+    pub fn output_text(&self) -> &str {
+        match &self.content {
+            BlockContent::Command { output: Some(output), .. } => output,
+            _ => "",
+        }
+    }
+
+    /// Output split into lines for AI context, with stderr marked when the timeline has it
+    pub fn output_lines(&self) -> Vec<OutputLine> {
+        match &self.content {
+            BlockContent::Command { timeline, .. } if !timeline.is_empty() => context::lines_from_chunks(timeline.chunks()),
+            _ => context::lines_from_text(self.output_text()),
+        }
+    }
+
+    /// Status of a command block; `None` for other block kinds
+    pub fn status(&self) -> Option<BlockStatus> {
+        match &self.content {
+            BlockContent::Command { exit_code, .. } => Some(match exit_code {
+                None => BlockStatus::Running,
+                Some(0) => BlockStatus::Succeeded,
+                Some(code) => BlockStatus::Failed(*code),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The block's buttons, in the order they are drawn, for keyboard hints
+    pub fn actions(&self) -> Vec<(&'static str, crate::BlockMessage)> {
+        use crate::BlockMessage as M;
+
+        let share = match &self.shared {
+            None => Some((tr("block.action.share"), M::Share)),
+            Some(record) if record.remote_id.is_some() => Some((tr("block.action.unshare"), M::Unshare)),
+            Some(_) => None,
+        };
+        let pin = if self.pinned { tr("block.action.unpin") } else { tr("block.action.pin") };
+        let collapse = if self.collapsed { tr("block.action.expand") } else { tr("block.action.collapse") };
+        let shared_controls = share.into_iter().chain([
+            (collapse, M::ToggleCollapse),
+            (pin, M::TogglePin),
+            (tr("block.action.move_to_top"), M::MoveToTop),
+            (tr("block.action.move_to_bottom"), M::MoveToBottom),
+        ]);
+
+        match &self.content {
+            BlockContent::Command { timeline, .. } => {
+                let mut actions: Vec<_> = [
+                    (tr("block.action.rerun"), M::Rerun),
+                    (tr("block.action.copy"), M::Copy),
+                    (tr("block.action.export"), M::Export),
+                    (tr("block.action.ask_ai"), M::AskAi),
+                    (tr("block.action.delete"), M::Delete),
+                ]
+                    .into_iter()
+                    .chain(shared_controls)
+                    .collect();
+                if self.status() != Some(BlockStatus::Running) && !timeline.is_empty() {
+                    actions.push((tr("block.action.timeline"), M::ToggleScrubber));
+                }
+                if self.scrollback().is_some_and(Scrollback::is_truncated) {
+                    actions.push((tr("block.action.show_full_output"), M::ShowFullOutput));
+                }
+                match &self.annotation {
+                    None if self.status() != Some(BlockStatus::Running) => {
+                        actions.push((tr("block.action.explain"), M::Explain));
+                    }
+                    None => {}
+                    Some(annotation) => {
+                        actions.push((tr("block.action.toggle_annotation"), M::ToggleAnnotation));
+                        if matches!(annotation.state, AnnotationState::Ready(_)) && !annotation.pinned {
+                            actions.push((tr("block.action.pin_annotation"), M::PinAnnotation));
+                        }
+                        actions.push((tr("block.action.dismiss_annotation"), M::DismissAnnotation));
+                    }
+                }
+                if matches!(self.status(), Some(BlockStatus::Failed(_))) && self.fix.is_none() {
+                    actions.push((tr("block.action.suggest_fix"), M::SuggestFix));
+                }
+                if let Some(FixSuggestion::Ready { run, .. }) = &self.fix {
+                    actions.push((tr("block.action.use_fix"), M::UseFix));
+                    if *run == FixRun::OneClick {
+                        actions.push((tr("block.action.apply_fix"), M::ApplyFix));
+                    }
+                }
+                if self.fix.is_some() {
+                    actions.push((tr("block.action.dismiss_fix"), M::DismissFix));
+                }
+                match (self.status(), &self.tee) {
+                    (Some(BlockStatus::Running), None) => actions.push((tr("block.action.tee"), M::StartTee)),
+                    (_, Some(_)) => actions.push((tr("block.action.stop_tee"), M::StopTee)),
+                    _ => {}
+                }
+                actions
+            }
+            BlockContent::AgentMessage { superseded: true, .. } | BlockContent::UserMessage { superseded: true, .. } => {
+                vec![(tr("block.action.copy"), M::Copy)]
+            }
+            BlockContent::AgentMessage { message_id, markdown, .. } => {
+                let mut actions = vec![
+                    (tr("block.action.copy"), M::Copy),
+                    (tr("block.action.export"), M::Export),
+                    (tr("block.action.delete"), M::Delete),
+                ];
+                if message_id.is_some() {
+                    actions.push((tr("block.action.fork"), M::Fork));
+                }
+                actions.extend(shared_controls);
+                for (index, (language, _)) in markdown.code_blocks().into_iter().enumerate() {
+                    actions.push((tr("block.action.copy_code"), M::CopyCode(index)));
+                    if crate::scratch::is_shell_tag(language) {
+                        actions.push((tr("block.action.run_code"), M::RunCode(index)));
+                        actions.push((tr("block.action.insert_code"), M::InsertCode(index)));
+                    }
+                }
+                actions
+            }
+            BlockContent::UserMessage { message_id: Some(_), .. } => vec![(tr("block.action.edit"), M::Edit), (tr("block.action.fork"), M::Fork)],
+            BlockContent::FindReplace(_)
+            | BlockContent::Plugin(_)
+            | BlockContent::Diagnostics(_)
+            | BlockContent::GitStatus(_)
+            | BlockContent::Directory(_)
+            | BlockContent::Preview(_) => {
+                vec![(tr("block.action.delete"), M::Delete)]
+            }
+            BlockContent::UserMessage { .. } | BlockContent::Error { .. } | BlockContent::Separator => Vec::new(),
         }
     }
 
-    pub fn view(&self) -> Element<crate::Message> {
+    /// `highlights` marks search matches in command output; `links` are
+    /// drawn clickable in it
+    pub fn view(
+        &self,
+        show_status_glyphs: bool,
+        layout: &ResponsiveLayout,
+        read_only: Option<ReadOnlyReason>,
+        highlights: &[Highlight],
+        links: &[Link],
+        font: OutputFont,
+    ) -> Element<crate::Message> {
         match &self.content {
-            BlockContent::Command { input, output, exit_code, working_directory } => {
-                self.view_command_block(input, output, exit_code, working_directory)
+            BlockContent::Command { .. } | BlockContent::AgentMessage { superseded: false, .. } if self.collapsed => {
+                self.view_collapsed_block(show_status_glyphs)
+            }
+            BlockContent::Command { output, .. } => {
+                self.view_command_block(output, show_status_glyphs, layout, read_only, highlights, links, font)
+            }
+            BlockContent::AgentMessage { content, superseded: true, .. }
+            | BlockContent::UserMessage { content, superseded: true, .. } => {
+                self.view_superseded_block(content)
             }
-            BlockContent::AgentMessage { content, role } => {
-                self.view_agent_message_block(content, role)
+            BlockContent::AgentMessage { role, .. } => {
+                self.view_agent_message_block(role, read_only)
             }
-            BlockContent::UserMessage { content } => {
+            BlockContent::UserMessage { content, .. } => {
                 self.view_user_message_block(content)
             }
             BlockContent::Error { message } => {
@@ -117,60 +1088,210 @@ impl Block {
                     .padding(8)
                     .into()
             }
+            BlockContent::FindReplace(state) => {
+                self.view_find_replace_block(state)
+            }
+            BlockContent::Plugin(plugin_block) => {
+                self.view_plugin_block(plugin_block)
+            }
+            BlockContent::Diagnostics(report) => {
+                self.view_diagnostics_block(report)
+            }
+            BlockContent::GitStatus(status) => {
+                self.view_git_status_block(status)
+            }
+            BlockContent::Directory(listing) => {
+                self.view_directory_block(listing)
+            }
+            BlockContent::Preview(preview) => {
+                self.view_preview_block(preview)
+            }
         }
     }
 
     fn view_command_block(
         &self,
-        input: &str,
         output: &Option<String>,
-        exit_code: &Option<i32>,
-        working_directory: &str,
+        show_status_glyphs: bool,
+        layout: &ResponsiveLayout,
+        read_only: Option<ReadOnlyReason>,
+        highlights: &[Highlight],
+        links: &[Link],
+        font: OutputFont,
     ) -> Element<crate::Message> {
-        let header = row![
-            text(format!("$ {}", input)).size(14),
-            button("⟲").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Rerun)),
+        let status = self.status().unwrap_or(BlockStatus::Running);
+        let header_lines = self.header(show_status_glyphs).map(|h| h.lines(layout)).unwrap_or_default();
+        let compact = layout.block_header() == HeaderLayout::TwoLine;
+
+        // Compact headers keep the command on its own row and put the actions under it
+        // Read-only sessions keep the button, disabled, with the reason on hover
+        let rerun: Element<crate::Message> = match read_only {
+            Some(reason) => tooltip(button("⟲"), text(reason.explanation()).size(12), tooltip::Position::Bottom).into(),
+            None => button("⟲").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Rerun)).into(),
+        };
+        let mut header = row![
+            text(if compact { header_lines.get(1) } else { header_lines.first() }.cloned().unwrap_or_default()).size(14),
+            rerun,
             button("📋").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Copy)),
+            button("💾").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Export)),
+            button("✨").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::AskAi)),
             button("🗑").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Delete)),
         ]
         .spacing(8);
+        header = header.push(self.view_share_controls()).push(self.view_move_controls());
 
-        let mut content = vec![header.into()];
+        let (timeline, markers, scrub_ms) = match &self.content {
+            BlockContent::Command { timeline, markers, scrub_ms, .. } => (Some(timeline), markers.as_slice(), *scrub_ms),
+            _ => (None, &[][..], None),
+        };
+        // Scrubbing only makes sense once the full timeline is known
+        let timeline = timeline.filter(|t| status != BlockStatus::Running && !t.is_empty());
 
-        if let Some(output_text) = output {
-            let output_style = match exit_code {
-                Some(0) => iced::theme::Text::Color(iced::Color::from_rgb(0.0, 0.8, 0.0)),
-                Some(_) => iced::theme::Text::Color(iced::Color::from_rgb(0.8, 0.0, 0.0)),
-                None => iced::theme::Text::Default,
-            };
+        if timeline.is_some() {
+            header = header.push(
+                button("⏱").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::ToggleScrubber))
+            );
+        }
+        if status != BlockStatus::Running && self.annotation.is_none() {
+            header = header.push(
+                tooltip(
+                    button("💡").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Explain)),
+                    text(tr("block.action.explain")).size(12),
+                    tooltip::Position::Bottom,
+                )
+            );
+        }
+        if matches!(status, BlockStatus::Failed(_)) && self.fix.is_none() {
+            header = header.push(
+                tooltip(
+                    button("🩹").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::SuggestFix)),
+                    text(tr("block.action.suggest_fix")).size(12),
+                    tooltip::Position::Bottom,
+                )
+            );
+        }
+        if status == BlockStatus::Running && self.tee.is_none() {
+            header = header.push(
+                tooltip(
+                    button("⭳").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::StartTee)),
+                    text(tr("block.action.tee")).size(12),
+                    tooltip::Position::Bottom,
+                )
+            );
+        }
 
+        let mut content = Vec::new();
+        if compact {
+            content.push(text(header_lines.first().cloned().unwrap_or_default()).size(14).into());
+        }
+        content.push(header.into());
+        // Running commands count up with each tick
+        if let Some(metadata) = self.metadata() {
             content.push(
-                container(
-                    text(output_text)
-                        .size(12)
-                        .style(output_style)
-                )
-                .padding(8)
-                .style(container::Appearance {
-                    background: Some(iced::Background::Color(iced::Color::from_rgb(0.05, 0.05, 0.05))),
-                    border: iced::Border {
-                        color: iced::Color::from_rgb(0.2, 0.2, 0.2),
-                        width: 1.0,
-                        radius: 4.0.into(),
-                    },
-                    ..Default::default()
-                })
-                .into()
+                text(metadata.summary(Utc::now()))
+                    .size(11)
+                    .style(iced::theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5)))
+                    .into()
             );
         }
 
+        if let (Some(timeline), Some(position)) = (timeline, scrub_ms) {
+            content.push(self.view_scrubber(timeline, markers, position));
+        }
+
+        if let Some(output_text) = output {
+            let output_text = match (timeline, scrub_ms) {
+                (Some(timeline), Some(position)) => timeline.output_at(output_text, position),
+                _ => output_text.as_str(),
+            };
+
+            let output_style = match status {
+                BlockStatus::Succeeded => iced::theme::Text::Color(iced::Color::from_rgb(0.0, 0.8, 0.0)),
+                BlockStatus::Failed(_) => iced::theme::Text::Color(iced::Color::from_rgb(0.8, 0.0, 0.0)),
+                BlockStatus::Running => iced::theme::Text::Default,
+            };
+
+            // Failures are labelled in text too, so they don't rely on red alone
+            if let BlockStatus::Failed(code) = status {
+                content.push(text(tr_args("block.exit", &[("code", &code)])).size(12).into());
+            }
+
+            if let Some(scrollback) = self.scrollback().filter(|s| s.is_truncated()) {
+                content.push(
+                    row![
+                        text(tr_args(
+                            "block.truncated",
+                            &[
+                                ("total", &format_number(scrollback.total_lines() as u64)),
+                                ("shown", &format_number(scrollback.shown_lines() as u64)),
+                            ]
+                        ))
+                        .size(12),
+                        button(text(tr("block.action.show_full_output")).size(12))
+                            .on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::ShowFullOutput)),
+                    ]
+                    .spacing(8)
+                    .into()
+                );
+            }
+
+            // Matches were found in the whole output, not the scrubbed part
+            let highlights = if scrub_ms.is_some() { &[][..] } else { highlights };
+            let links = if scrub_ms.is_some() { &[][..] } else { links };
+            if scrub_ms.is_some() || self.prompts().is_empty() {
+                content.push(output_box(view_output(output_text, output_style, highlights, links, font)));
+            } else {
+                // Each command run at a shell prompt in the block gets its own box
+                let first_line = self.scrollback().map_or(0, |s| s.total_lines() - s.shown_lines());
+                let segments = output_segments(output_text, first_line, self.prompts());
+                for (index, segment) in segments.iter().enumerate() {
+                    let end_line = segments.get(index + 1).map_or(usize::MAX, |next| next.first_line);
+                    if let Some(prompt) = segment.prompt {
+                        content.push(self.view_prompt_mark(prompt));
+                    }
+                    if segment.text.is_empty() {
+                        continue;
+                    }
+                    let segment_highlights: Vec<Highlight> = highlights
+                        .iter()
+                        .filter(|h| (segment.first_line..end_line).contains(&h.line))
+                        .map(|h| Highlight { line: h.line - segment.first_line, ..h.clone() })
+                        .collect();
+                    let segment_links: Vec<Link> = links
+                        .iter()
+                        .filter(|link| (segment.first_line..end_line).contains(&link.line))
+                        .map(|link| Link { line: link.line - segment.first_line, ..link.clone() })
+                        .collect();
+                    content.push(output_box(view_output(segment.text, output_style, &segment_highlights, &segment_links, font)));
+                }
+            }
+        }
+
+        if let Some(tee) = &self.tee {
+            content.push(self.view_tee_footer(tee));
+        }
+
+        if let Some(annotation) = &self.annotation {
+            content.push(self.view_annotation(annotation));
+        }
+
+        if let Some(fix) = &self.fix {
+            content.push(self.view_fix(fix));
+        }
+
+        // Failed blocks get a heavier outline as a shape cue alongside color
+        let (border_color, border_width) = match status {
+            BlockStatus::Failed(_) => (iced::Color::from_rgb(0.8, 0.0, 0.0), 3.0),
+            _ => (iced::Color::from_rgb(0.9, 0.9, 0.9), 1.0),
+        };
+
         container(column(content).spacing(4))
             .padding(8)
             .style(container::Appearance {
                 background: Some(iced::Background::Color(iced::Color::from_rgb(0.98, 0.98, 0.98))),
                 border: iced::Border {
-                    color: iced::Color::from_rgb(0.9, 0.9, 0.9),
-                    width: 1.0,
+                    color: border_color,
+                    width: border_width,
                     radius: 8.0.into(),
                 },
                 ..Default::default()
@@ -178,24 +1299,250 @@ impl Block {
             .into()
     }
 
-    fn view_agent_message_block(&self, content: &str, role: &AgentRole) -> Element<crate::Message> {
+    /// The command run at a shell prompt in the block and how it exited
+    fn view_prompt_mark(&self, index: usize) -> Element<crate::Message> {
+        let prompt = &self.prompts()[index];
+        let status = match prompt.exit_code {
+            None => String::new(),
+            Some(0) => BlockStatus::Succeeded.glyph().to_string(),
+            Some(code) => format!("{} {}", BlockStatus::Failed(code).glyph(), code),
+        };
+        let command = prompt.command.as_deref().unwrap_or("…");
+        let mark = row![
+            text(format!("❯ {}", command)).size(12).font(iced::Font::MONOSPACE),
+            text(status).size(12),
+        ]
+        .spacing(8);
+        let current = self.prompt_cursor() == Some(index);
+        container(mark)
+            .padding([2, 8])
+            .style(container::Appearance {
+                background: current.then_some(iced::Background::Color(iced::Color::from_rgb(0.85, 0.9, 1.0))),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    /// The explanation under a command block, with its own collapse, pin and dismiss buttons
+    fn view_annotation(&self, annotation: &Annotation) -> Element<crate::Message> {
+        let action = |message| crate::Message::BlockAction(self.id, message);
+        let mut header = row![
+            button(text(if annotation.collapsed { "▸" } else { "▾" }).size(12))
+                .on_press(action(crate::BlockMessage::ToggleAnnotation)),
+            text(tr("block.annotation.title")).size(12),
+        ]
+        .spacing(8);
+        if annotation.pinned {
+            header = header.push(text(tr("block.annotation.pinned")).size(12));
+        } else if matches!(annotation.state, AnnotationState::Ready(_)) {
+            header = header.push(
+                button(text(tr("block.action.pin_annotation")).size(12))
+                    .on_press(action(crate::BlockMessage::PinAnnotation)),
+            );
+        }
+        header = header.push(button(text("✕").size(12)).on_press(action(crate::BlockMessage::DismissAnnotation)));
+
+        let mut content = column![header].spacing(4);
+        if !annotation.collapsed {
+            content = content.push(match &annotation.state {
+                AnnotationState::Loading => text(tr("block.annotation.loading")).size(13),
+                AnnotationState::Ready(explanation) => text(explanation).size(13),
+                AnnotationState::Failed(error) => text(tr_args("block.annotation.failed", &[("error", error)])).size(13),
+            });
+        }
+
+        container(content)
+            .padding([4, 8, 4, 16])
+            .style(container::Appearance {
+                background: Some(iced::Background::Color(iced::Color::from_rgb(0.94, 0.96, 1.0))),
+                border: iced::Border {
+                    color: iced::Color::from_rgb(0.6, 0.7, 0.9),
+                    width: 1.0,
+                    radius: 4.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    /// The suggested fix as a word diff against the failed command, with
+    /// buttons to edit it in the input or run it
+    fn view_fix(&self, fix: &FixSuggestion) -> Element<crate::Message> {
+        use crate::agent_mode_eval::fix::{word_diff, DiffKind};
+
+        let action = |message| crate::Message::BlockAction(self.id, message);
+        let mut header = row![text(tr("block.fix.title")).size(12)].spacing(8);
+        let mut content = column![].spacing(4);
+        match fix {
+            FixSuggestion::Loading => content = content.push(text(tr("block.fix.loading")).size(13)),
+            FixSuggestion::Failed(error) => content = content.push(text(tr_args("block.fix.failed", &[("error", error)])).size(13)),
+            FixSuggestion::Ready { command, run } => {
+                header = header.push(
+                    button(text(tr("block.action.use_fix")).size(12)).on_press(action(crate::BlockMessage::UseFix)),
+                );
+                match run {
+                    FixRun::OneClick => {
+                        header = header.push(
+                            button(text(tr("block.action.apply_fix")).size(12))
+                                .style(button::primary)
+                                .on_press(action(crate::BlockMessage::ApplyFix)),
+                        );
+                    }
+                    FixRun::Review(reason) => {
+                        header = header.push(text(tr_args("block.fix.review", &[("reason", reason)])).size(12));
+                    }
+                    FixRun::Manual => {}
+                }
+
+                let original = match &self.content {
+                    BlockContent::Command { input, .. } => input.as_str(),
+                    _ => "",
+                };
+                let diff = word_diff(original, command);
+                let diff_line = |sign: &'static str, hidden: DiffKind, changed: iced::Color| {
+                    diff.iter()
+                        .filter(|(kind, _)| *kind != hidden)
+                        .fold(row![text(sign).size(13).font(iced::Font::MONOSPACE)].spacing(6), |line, (kind, word)| {
+                            let word = text(word.clone()).size(13).font(iced::Font::MONOSPACE);
+                            line.push(if *kind == DiffKind::Same { word } else { word.style(iced::theme::Text::Color(changed)) })
+                        })
+                };
+                content = content
+                    .push(diff_line("−", DiffKind::Added, iced::Color::from_rgb(0.8, 0.0, 0.0)))
+                    .push(diff_line("+", DiffKind::Removed, iced::Color::from_rgb(0.0, 0.6, 0.0)));
+            }
+        }
+        header = header.push(button(text("✕").size(12)).on_press(action(crate::BlockMessage::DismissFix)));
+
+        container(column![header, content].spacing(4))
+            .padding([4, 8, 4, 16])
+            .style(container::Appearance {
+                background: Some(iced::Background::Color(iced::Color::from_rgb(0.94, 1.0, 0.95))),
+                border: iced::Border {
+                    color: iced::Color::from_rgb(0.6, 0.85, 0.65),
+                    width: 1.0,
+                    radius: 4.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    /// Where the output is being mirrored, how much has been written, and a way to stop
+    fn view_tee_footer(&self, tee: &TeeStatus) -> Element<crate::Message> {
+        row![
+            text(tr_args(
+                "block.tee",
+                &[
+                    ("path", &crate::status_line::display_path(&tee.path)),
+                    ("bytes", &format_number(tee.bytes_written)),
+                ]
+            ))
+            .size(12),
+            button(text(tr("block.action.stop_tee")).size(12))
+                .on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::StopTee)),
+        ]
+        .spacing(8)
+        .into()
+    }
+
+    /// "Collapse", "Pin", "Move to top" and "Move to bottom"
+    fn view_move_controls(&self) -> Element<crate::Message> {
+        let pin = if self.pinned { "📍" } else { "📌" };
+        row![
+            button("▾").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::ToggleCollapse)),
+            button(pin).on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::TogglePin)),
+            button("⤒").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::MoveToTop)),
+            button("⤓").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::MoveToBottom)),
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    /// "Share…" before sharing; the link and "Unshare" afterwards
+    fn view_share_controls(&self) -> Element<crate::Message> {
+        match &self.shared {
+            None => button("⤴").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Share)).into(),
+            Some(record) => {
+                let mut controls = row![text(format!("🔗 {}", record.url)).size(12)].spacing(4);
+                if record.remote_id.is_some() {
+                    controls = controls.push(
+                        button(text(tr("block.action.unshare")).size(12))
+                            .on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Unshare))
+                    );
+                }
+                controls.into()
+            }
+        }
+    }
+
+    fn view_scrubber(&self, timeline: &OutputTimeline, markers: &[TimelineMarker], position: u64) -> Element<crate::Message> {
+        const TRACK_WIDTH: usize = 60;
+
+        let duration = timeline.duration_ms.max(1);
+        let id = self.id;
+
+        // One cell per slice of the run: ! for stderr, ▲ for alert matches
+        let mut track = vec!['·'; TRACK_WIDTH];
+        for marker in markers {
+            let cell = ((marker.offset_ms * (TRACK_WIDTH as u64 - 1)) / duration) as usize;
+            track[cell] = match (&marker.kind, track[cell]) {
+                (MarkerKind::Alert(_), _) | (_, '▲') => '▲',
+                (MarkerKind::Stderr, _) => '!',
+            };
+        }
+
+        column![
+            row![
+                slider(0.0..=duration as f64, position as f64, move |ms| {
+                    crate::Message::BlockAction(id, crate::BlockMessage::Scrub(ms as u64))
+                })
+                .width(iced::Length::Fill),
+                text(tr_args(
+                    "block.timeline",
+                    &[
+                        ("position", &format_duration(std::time::Duration::from_millis(position))),
+                        ("duration", &format_duration(std::time::Duration::from_millis(duration))),
+                        ("chunks", &timeline.chunks_at(position)),
+                        ("total", &timeline.chunks().len()),
+                    ]
+                ))
+                .size(12),
+            ]
+            .spacing(8),
+            text(track.into_iter().collect::<String>()).size(12),
+        ]
+        .spacing(2)
+        .into()
+    }
+
+    fn view_agent_message_block(&self, role: &AgentRole, read_only: Option<ReadOnlyReason>) -> Element<crate::Message> {
         let (icon, bg_color) = match role {
             AgentRole::Assistant => ("🤖", iced::Color::from_rgb(0.95, 0.98, 1.0)),
             AgentRole::User => ("👤", iced::Color::from_rgb(0.98, 1.0, 0.95)),
             AgentRole::System => ("⚙️", iced::Color::from_rgb(1.0, 0.98, 0.95)),
         };
 
-        let header = row![
+        let mut header = row![
             text(format!("{} {:?}", icon, role)).size(12),
             button("📋").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Copy)),
+            button("💾").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Export)),
             button("🗑").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Delete)),
         ]
         .spacing(8);
 
-        let message_content = container(
-            text(content).size(14)
-        )
-        .padding(12);
+        if self.message_id().is_some() {
+            header = header.push(
+                button("⎇").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Fork))
+            );
+        }
+        header = header.push(self.view_share_controls()).push(self.view_move_controls());
+
+        let sections = self.markdown_sections().into_iter().map(|section| match section {
+            MarkdownSection::Text(lines) => view_styled_lines(lines, iced::theme::Text::Default, 14.0, iced::Font::DEFAULT),
+            MarkdownSection::Code { index, language, lines } => self.view_code_section(index, language, lines, read_only),
+        });
+        let message_content = container(column(sections).spacing(8)).padding(12);
 
         container(
             column![header, message_content]
@@ -214,19 +1561,190 @@ impl Block {
         .into()
     }
 
+    /// A fenced code block of a reply on a dark background, which the
+    /// highlighting theme expects. Shell snippets can be run or put in the
+    /// input bar; anything else can be copied.
+    fn view_code_section(
+        &self,
+        index: usize,
+        language: String,
+        lines: Vec<Vec<ansi::Span>>,
+        read_only: Option<ReadOnlyReason>,
+    ) -> Element<crate::Message> {
+        let action = |label: &'static str, message: crate::BlockMessage| {
+            button(text(tr(label)).size(11)).on_press(crate::Message::BlockAction(self.id, message))
+        };
+        let mut controls = row![
+            text(language.clone()).size(11).width(iced::Length::Fill),
+            action("block.action.copy_code", crate::BlockMessage::CopyCode(index)),
+        ]
+        .spacing(4);
+        if crate::scratch::is_shell_tag(&language) {
+            let run: Element<crate::Message> = match read_only {
+                Some(reason) => tooltip(
+                    button(text(tr("block.action.run_code")).size(11)),
+                    text(reason.explanation()).size(12),
+                    tooltip::Position::Bottom,
+                )
+                .into(),
+                None => action("block.action.run_code", crate::BlockMessage::RunCode(index)).into(),
+            };
+            controls = controls.push(run).push(action("block.action.insert_code", crate::BlockMessage::InsertCode(index)));
+        }
+        let light = iced::theme::Text::Color(iced::Color::from_rgb8(0xc0, 0xc5, 0xce));
+        column![
+            controls,
+            container(view_styled_lines(lines, light, 12.0, iced::Font::DEFAULT))
+                .padding(8)
+                .width(iced::Length::Fill)
+                .style(container::Appearance {
+                    background: Some(iced::Background::Color(iced::Color::from_rgb8(0x2b, 0x30, 0x3b))),
+                    border: iced::Border { radius: 4.0.into(), ..Default::default() },
+                    ..Default::default()
+                }),
+        ]
+        .spacing(2)
+        .into()
+    }
+
     fn view_user_message_block(&self, content: &str) -> Element<crate::Message> {
+        let mut body = row![
+            text("👤").size(16),
+            text(content).size(14).width(iced::Length::Fill)
+        ]
+        .spacing(8);
+
+        if self.message_id().is_some() {
+            body = body
+                .push(button("✏").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Edit)))
+                .push(button("⎇").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Fork)));
+        }
+
+        container(body)
+        .padding(8)
+        .style(container::Appearance {
+            background: Some(iced::Background::Color(iced::Color::from_rgb(0.98, 1.0, 0.95))),
+            border: iced::Border {
+                color: iced::Color::from_rgb(0.8, 0.9, 0.8),
+                width: 1.0,
+                radius: 8.0.into(),
+            },
+            ..Default::default()
+        })
+        .into()
+    }
+
+    /// One row: the command with its exit code and duration, or the message's
+    /// role, then the preview. Expanding draws the full block again.
+    fn view_collapsed_block(&self, show_status_glyphs: bool) -> Element<crate::Message> {
+        let mut summary = row![
+            button(text("▸").size(12)).on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::ToggleCollapse)),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center);
+
+        let mut failed = false;
+        match &self.content {
+            BlockContent::Command { input, .. } => {
+                let status = self.status().unwrap_or(BlockStatus::Running);
+                let glyph = if show_status_glyphs { format!("{} ", status.glyph()) } else { String::new() };
+                summary = summary.push(text(format!("{}$ {}", glyph, input)).size(13));
+                let (badge, color) = match status {
+                    BlockStatus::Running => (tr("block.running").to_string(), iced::Color::from_rgb(0.5, 0.5, 0.5)),
+                    BlockStatus::Succeeded => (tr_args("block.exit", &[("code", &0)]), iced::Color::from_rgb(0.0, 0.6, 0.0)),
+                    BlockStatus::Failed(code) => {
+                        failed = true;
+                        (tr_args("block.exit", &[("code", &code)]), iced::Color::from_rgb(0.8, 0.0, 0.0))
+                    }
+                };
+                summary = summary.push(
+                    container(text(badge).size(11).style(iced::theme::Text::Color(iced::Color::WHITE)))
+                        .padding([1, 6])
+                        .style(container::Appearance {
+                            background: Some(iced::Background::Color(color)),
+                            border: iced::Border { radius: 8.0.into(), ..Default::default() },
+                            ..Default::default()
+                        }),
+                );
+                if let Some(duration) = self.duration() {
+                    summary = summary.push(text(format_duration(duration)).size(12));
+                }
+            }
+            BlockContent::AgentMessage { role, .. } => {
+                let icon = match role {
+                    AgentRole::Assistant => "🤖",
+                    AgentRole::User => "👤",
+                    AgentRole::System => "⚙️",
+                };
+                summary = summary.push(text(icon).size(12));
+            }
+            _ => {}
+        }
+        summary = summary.push(
+            text(self.collapsed_preview())
+                .size(12)
+                .style(iced::theme::Text::Color(iced::Color::from_rgb(0.45, 0.45, 0.45)))
+                .width(iced::Length::Fill),
+        );
+
+        container(summary)
+            .padding(4)
+            .style(container::Appearance {
+                background: Some(iced::Background::Color(iced::Color::from_rgb(0.97, 0.97, 0.97))),
+                border: iced::Border {
+                    color: if failed { iced::Color::from_rgb(0.8, 0.0, 0.0) } else { iced::Color::from_rgb(0.85, 0.85, 0.85) },
+                    width: if failed { 2.0 } else { 1.0 },
+                    radius: 8.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    fn view_superseded_block(&self, content: &str) -> Element<crate::Message> {
+        let preview: String = content.lines().next().unwrap_or("").chars().take(80).collect();
+
         container(
             row![
-                text("👤").size(16),
-                text(content).size(14)
+                text("↺ superseded").size(12),
+                text(preview).size(12).width(iced::Length::Fill),
+                button("📋").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Copy)),
+            ]
+            .spacing(8)
+        )
+        .padding(4)
+        .style(container::Appearance {
+            background: Some(iced::Background::Color(iced::Color::from_rgb(0.96, 0.96, 0.96))),
+            border: iced::Border {
+                color: iced::Color::from_rgb(0.85, 0.85, 0.85),
+                width: 1.0,
+                radius: 8.0.into(),
+            },
+            ..Default::default()
+        })
+        .into()
+    }
+
+    fn view_find_replace_block<'a>(&'a self, state: &'a FindReplaceState) -> Element<'a, crate::Message> {
+        let id = self.id;
+        let header = row![
+            text("Find and replace").size(12).width(iced::Length::Fill),
+            button("🗑").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Delete)),
+        ]
+        .spacing(8);
+
+        container(
+            column![
+                header,
+                state.view().map(move |message| crate::Message::FindReplace(id, message)),
             ]
             .spacing(8)
         )
         .padding(8)
         .style(container::Appearance {
-            background: Some(iced::Background::Color(iced::Color::from_rgb(0.98, 1.0, 0.95))),
+            background: Some(iced::Background::Color(iced::Color::from_rgb(0.97, 0.97, 1.0))),
             border: iced::Border {
-                color: iced::Color::from_rgb(0.8, 0.9, 0.8),
+                color: iced::Color::from_rgb(0.8, 0.8, 0.9),
                 width: 1.0,
                 radius: 8.0.into(),
             },
@@ -235,6 +1753,317 @@ impl Block {
         .into()
     }
 
+    fn view_diagnostics_block<'a>(&'a self, report: &'a DiagnosticsReport) -> Element<'a, crate::Message> {
+        let header = row![
+            text(format!(
+                "{} Diagnostics · {}",
+                report.status.glyph(),
+                report.generated_at.with_timezone(&chrono::Local).format("%H:%M:%S")
+            ))
+            .size(12)
+            .width(iced::Length::Fill),
+            button("🗑").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Delete)),
+        ]
+        .spacing(8);
+
+        let mut rows = column![header].spacing(4);
+        for diagnostic in &report.rows {
+            let stats: Vec<String> = diagnostic.stats.iter().map(|s| format!("{} {}", s.name, s.value)).collect();
+            let mut line = row![
+                text(format!("{} {}", diagnostic.status.glyph(), diagnostic.subsystem))
+                    .size(12)
+                    .width(iced::Length::Fixed(120.0)),
+                text(&diagnostic.summary).size(12).width(iced::Length::Fixed(200.0)),
+                text(stats.join(" · ")).size(12).width(iced::Length::Fill),
+            ]
+            .spacing(8);
+            if let Some(action) = diagnostic.action {
+                line = line.push(button(text(action.label()).size(12)).on_press(crate::Message::DiagnosticAction(action)));
+            }
+            rows = rows.push(line);
+        }
+
+        container(rows)
+            .padding(8)
+            .style(container::Appearance {
+                background: Some(iced::Background::Color(iced::Color::from_rgb(0.98, 0.98, 0.98))),
+                border: iced::Border {
+                    color: iced::Color::from_rgb(0.85, 0.85, 0.85),
+                    width: 1.0,
+                    radius: 8.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    fn view_git_status_block<'a>(&'a self, status: &'a GitStatus) -> Element<'a, crate::Message> {
+        let header = row![
+            text(format!("⎇ {} · {}", status.indicator(), crate::status_line::display_path(&status.root)))
+                .size(12)
+                .width(iced::Length::Fill),
+            button("🗑").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Delete)),
+        ]
+        .spacing(8);
+
+        let action = |label: &'static str, action: GitAction| {
+            button(text(label).size(12)).padding([2, 8]).on_press(crate::Message::Git(status.root.clone(), action))
+        };
+        let file = |letter: char, path: &str| text(format!("{} {}", letter, path)).size(12).width(iced::Length::Fill);
+        let section = |title: &str| text(title.to_string()).size(12).style(iced::theme::Text::Color(iced::Color::from_rgb(0.45, 0.45, 0.45)));
+
+        let mut rows = column![header].spacing(4);
+        if status.changed() == 0 {
+            rows = rows.push(text("Nothing to commit, working tree clean").size(12));
+        }
+        if !status.staged.is_empty() {
+            rows = rows.push(section("Staged"));
+        }
+        for entry in &status.staged {
+            rows = rows.push(
+                row![
+                    file(entry.change.letter(), &entry.path),
+                    action("Diff", GitAction::Diff { path: entry.path.clone(), staged: true }),
+                    action("Unstage", GitAction::Unstage(entry.path.clone())),
+                ]
+                .spacing(8)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+        if !status.unstaged.is_empty() {
+            rows = rows.push(section("Not staged"));
+        }
+        for entry in &status.unstaged {
+            rows = rows.push(
+                row![
+                    file(entry.change.letter(), &entry.path),
+                    action("Diff", GitAction::Diff { path: entry.path.clone(), staged: false }),
+                    action("Stage", GitAction::Stage(entry.path.clone())),
+                ]
+                .spacing(8)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+        if !status.untracked.is_empty() {
+            rows = rows.push(section("Untracked"));
+        }
+        for path in &status.untracked {
+            rows = rows.push(
+                row![
+                    file('?', path),
+                    action("Diff", GitAction::Diff { path: path.clone(), staged: false }),
+                    action("Stage", GitAction::Stage(path.clone())),
+                ]
+                .spacing(8)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+
+        container(rows)
+            .padding(8)
+            .style(container::Appearance {
+                background: Some(iced::Background::Color(iced::Color::from_rgb(0.98, 0.98, 0.98))),
+                border: iced::Border {
+                    color: iced::Color::from_rgb(0.85, 0.85, 0.85),
+                    width: 1.0,
+                    radius: 8.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    fn view_directory_block<'a>(&'a self, listing: &'a DirListing) -> Element<'a, crate::Message> {
+        let message = |message: DirMessage| crate::Message::Directory(self.id, message);
+        let sort_button = |key: SortKey| {
+            let arrow = match (listing.sort == key, listing.descending) {
+                (true, false) => " ▲",
+                (true, true) => " ▼",
+                (false, _) => "",
+            };
+            button(text(format!("{}{}", key.label(), arrow)).size(12)).padding([2, 8]).on_press(message(DirMessage::SortBy(key)))
+        };
+        let header = row![
+            text(format!("📁 {}", crate::status_line::display_path(&listing.dir))).size(12).width(iced::Length::Fill),
+            button(text("⬆ Up").size(12)).padding([2, 8]).on_press(message(DirMessage::Up)),
+            sort_button(SortKey::Name),
+            sort_button(SortKey::Size),
+            sort_button(SortKey::Modified),
+            button("🗑").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Delete)),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center);
+
+        let mut rows = column![header].spacing(2);
+        if listing.entries.is_empty() {
+            rows = rows.push(text("Empty directory").size(12));
+        }
+        for (index, entry) in listing.page_entries() {
+            let color = match entry.kind {
+                _ if entry.is_hidden() => iced::Color::from_rgb(0.55, 0.55, 0.55),
+                EntryKind::Directory => iced::Color::from_rgb(0.15, 0.4, 0.85),
+                EntryKind::Symlink => iced::Color::from_rgb(0.1, 0.6, 0.65),
+                EntryKind::Executable => iced::Color::from_rgb(0.2, 0.6, 0.2),
+                EntryKind::File => iced::Color::from_rgb(0.15, 0.15, 0.15),
+            };
+            let marker = if listing.selected == Some(index) { "▸ " } else { "  " };
+            let name = format!("{}{} {}{}", marker, entry.kind.icon(), entry.name, if entry.is_dir { "/" } else { "" });
+            let size = if entry.is_dir { String::new() } else { crate::dir_listing::format_size(entry.size) };
+            let modified = entry.modified.map(|time| time.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+
+            let mut line = row![
+                button(text(name).size(12).style(iced::theme::Text::Color(color)))
+                    .style(button::text)
+                    .padding(0)
+                    .on_press(message(DirMessage::Select(index)))
+                    .width(iced::Length::Fill),
+                text(size).size(12).width(iced::Length::Fixed(80.0)),
+                text(modified).size(12).width(iced::Length::Fixed(120.0)),
+            ]
+            .spacing(8)
+            .align_items(iced::Alignment::Center);
+            if listing.pending_delete == Some(index) {
+                let question = if entry.is_dir { "Delete it and everything in it?" } else { "Delete it?" };
+                line = line
+                    .push(text(question).size(12))
+                    .push(button(text("Delete").size(12)).padding([2, 8]).on_press(message(DirMessage::ConfirmDelete)))
+                    .push(button(text("Cancel").size(12)).padding([2, 8]).on_press(message(DirMessage::CancelDelete)));
+            } else {
+                let open = if entry.is_dir { "Enter" } else { "Edit" };
+                line = line.push(button(text(open).size(12)).padding([2, 8]).on_press(message(DirMessage::Open(index))));
+                if !entry.is_dir {
+                    line = line.push(button(text("Preview").size(12)).padding([2, 8]).on_press(message(DirMessage::Preview(index))));
+                }
+                line = line
+                    .push(button(text("Copy path").size(12)).padding([2, 8]).on_press(message(DirMessage::CopyPath(index))))
+                    .push(button(text("Delete").size(12)).padding([2, 8]).on_press(message(DirMessage::RequestDelete(index))));
+            }
+            rows = rows.push(line);
+        }
+        if listing.pages() > 1 {
+            rows = rows.push(
+                row![
+                    button(text("◀").size(12)).padding([2, 8]).on_press(message(DirMessage::Page(-1))),
+                    text(format!("Page {} of {} · {} entries", listing.page + 1, listing.pages(), listing.entries.len())).size(12),
+                    button(text("▶").size(12)).padding([2, 8]).on_press(message(DirMessage::Page(1))),
+                ]
+                .spacing(8)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+
+        container(rows)
+            .padding(8)
+            .style(container::Appearance {
+                background: Some(iced::Background::Color(iced::Color::from_rgb(0.98, 0.98, 0.98))),
+                border: iced::Border {
+                    color: iced::Color::from_rgb(0.85, 0.85, 0.85),
+                    width: 1.0,
+                    radius: 8.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    fn view_preview_block<'a>(&'a self, preview: &'a FilePreview) -> Element<'a, crate::Message> {
+        let mut details = vec![crate::status_line::display_path(&preview.path)];
+        details.extend(preview.language.clone());
+        details.push(crate::dir_listing::format_size(preview.size));
+        if preview.truncated {
+            details.push(match &preview.content {
+                PreviewContent::Text(lines) => format!("first {} lines", lines.len()),
+                PreviewContent::Binary(bytes) => format!("first {} bytes", bytes.len()),
+            });
+        }
+        let header = row![
+            text(format!("📄 {}", details.join(" · "))).size(12).width(iced::Length::Fill),
+            button(text("Edit").size(12)).padding([2, 8]).on_press(crate::Message::EditFile(preview.path.clone())),
+            button("🗑").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Delete)),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center);
+
+        let gutter_style = iced::theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5));
+        let body: Element<crate::Message> = match &preview.content {
+            PreviewContent::Text(lines) if lines.is_empty() => text("Empty file").size(12).style(gutter_style).into(),
+            PreviewContent::Text(lines) => {
+                let width = lines.len().to_string().len();
+                column(lines.iter().enumerate().map(|(index, line)| {
+                    let number = index + 1;
+                    let marked = preview.line == Some(number);
+                    let gutter = text(format!("{:>width$}{} ", number, if marked { "▶" } else { " " }, width = width))
+                        .size(12)
+                        .line_height(iced::widget::text::LineHeight::Absolute(PREVIEW_LINE_HEIGHT.into()))
+                        .style(gutter_style);
+                    let spans = ansi::parse(line).into_iter().map(|span| view_span(span, iced::theme::Text::Default, 12.0, iced::Font::DEFAULT));
+                    let line = row(std::iter::once(gutter.into()).chain(spans));
+                    if !marked {
+                        return line.into();
+                    }
+                    container(line)
+                        .width(iced::Length::Fill)
+                        .style(container::Appearance {
+                            background: Some(iced::Background::Color(iced::Color::from_rgb(0.2, 0.25, 0.35))),
+                            ..Default::default()
+                        })
+                        .into()
+                }))
+                .into()
+            }
+            PreviewContent::Binary(bytes) => text(crate::file_preview::hex_lines(bytes).join("\n"))
+                .size(12)
+                .font(iced::Font::MONOSPACE)
+                .style(iced::theme::Text::Color(iced::Color::from_rgb(0.85, 0.85, 0.85)))
+                .into(),
+        };
+        let body = scrollable(body).id(preview_scrollable_id(self.id)).height(iced::Length::Fixed(PREVIEW_HEIGHT));
+
+        container(column![header, output_box(body.into())].spacing(8))
+            .padding(8)
+            .style(container::Appearance {
+                background: Some(iced::Background::Color(iced::Color::from_rgb(0.98, 0.98, 0.98))),
+                border: iced::Border {
+                    color: iced::Color::from_rgb(0.85, 0.85, 0.85),
+                    width: 1.0,
+                    radius: 8.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    fn view_plugin_block<'a>(&'a self, plugin_block: &'a PluginBlock) -> Element<'a, crate::Message> {
+        let header = row![
+            text(format!("🧩 {}", plugin_block.plugin)).size(12).width(iced::Length::Fill),
+            button("🗑").on_press(crate::Message::BlockAction(self.id, crate::BlockMessage::Delete)),
+        ]
+        .spacing(8);
+
+        let mut content = column![header].spacing(8);
+        if let Some(tree) = &plugin_block.tree {
+            content = content.push(tree.view(self.id));
+        }
+        if let Some(error) = &plugin_block.error {
+            content = content.push(
+                text(error).size(12).style(iced::theme::Text::Color(iced::Color::from_rgb(0.8, 0.0, 0.0)))
+            );
+        }
+
+        container(content)
+            .padding(8)
+            .style(container::Appearance {
+                background: Some(iced::Background::Color(iced::Color::from_rgb(0.98, 0.98, 0.98))),
+                border: iced::Border {
+                    color: iced::Color::from_rgb(0.85, 0.85, 0.85),
+                    width: 1.0,
+                    radius: 8.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
     fn view_error_block(&self, message: &str) -> Element<crate::Message> {
         container(
             row![
@@ -257,10 +2086,610 @@ impl Block {
     }
 }
 
+/// Command output, with its colors and bold text when it has escape
+/// sequences. Spans without a color of their own take `default_style`.
+/// iced text has no underline, so underlined spans are drawn plain.
+/// Output in the dark box it's shown in
+fn output_box(output: Element<crate::Message>) -> Element<crate::Message> {
+    container(output)
+        .padding(8)
+        .style(container::Appearance {
+            background: Some(iced::Background::Color(iced::Color::from_rgb(0.05, 0.05, 0.05))),
+            border: iced::Border {
+                color: iced::Color::from_rgb(0.2, 0.2, 0.2),
+                width: 1.0,
+                radius: 4.0.into(),
+            },
+            ..Default::default()
+        })
+        .into()
+}
+
+/// Height of a row in a file preview, fixed so it can scroll to a line
+pub const PREVIEW_LINE_HEIGHT: f32 = 16.0;
+const PREVIEW_HEIGHT: f32 = 400.0;
+
+pub fn preview_scrollable_id(block_id: Uuid) -> scrollable::Id {
+    scrollable::Id::new(format!("preview-{}", block_id))
+}
+
+/// Output from one prompt to the next
+#[derive(Debug, PartialEq, Eq)]
+struct OutputSegment<'a> {
+    /// Index into the block's prompts; `None` for output before the first
+    prompt: Option<usize>,
+    /// Line of the shown output the segment starts on
+    first_line: usize,
+    text: &'a str,
+}
+
+/// `output` cut where each prompt starts. `first_line` is the line of the
+/// whole output that `output` starts with, as older lines may have been
+/// dropped; prompts on dropped lines start at the top.
+fn output_segments<'a>(output: &'a str, first_line: usize, prompts: &[PromptMark]) -> Vec<OutputSegment<'a>> {
+    let line_start = |line: usize| match line {
+        0 => 0,
+        line => output.match_indices('\n').nth(line - 1).map_or(output.len(), |(index, _)| index + 1),
+    };
+    let mut starts = vec![(None, 0)];
+    starts.extend(prompts.iter().enumerate().map(|(index, prompt)| (Some(index), prompt.line.saturating_sub(first_line))));
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, &(prompt, line))| {
+            let end = starts.get(index + 1).map_or(output.len(), |&(_, next)| line_start(next));
+            OutputSegment { prompt, first_line: line, text: &output[line_start(line)..end] }
+        })
+        .filter(|segment| segment.prompt.is_some() || !segment.text.is_empty())
+        .collect()
+}
+
+fn view_output<'a>(
+    output: &str,
+    default_style: iced::theme::Text,
+    highlights: &[Highlight],
+    links: &[Link],
+    font: OutputFont,
+) -> Element<'a, crate::Message> {
+    if highlights.is_empty() && links.is_empty() {
+        if !ansi::has_escapes(output) {
+            return text(output.to_string()).size(font.size).font(font.font).style(default_style).into();
+        }
+        return view_styled_lines(ansi::lines(output), default_style, font.size, font.font);
+    }
+
+    let mut by_line: HashMap<usize, Vec<&Highlight>> = HashMap::new();
+    for highlight in highlights {
+        by_line.entry(highlight.line).or_default().push(highlight);
+    }
+    let mut links_by_line: HashMap<usize, Vec<&Link>> = HashMap::new();
+    for link in links {
+        links_by_line.entry(link.line).or_default().push(link);
+    }
+    let lines = ansi::lines(output).into_iter().enumerate().map(|(index, spans)| {
+        let (line_highlights, line_links) = (by_line.get(&index), links_by_line.get(&index));
+        if line_highlights.is_none() && line_links.is_none() {
+            return row(spans.into_iter().map(|span| view_span(span, default_style, font.size, font.font))).into();
+        }
+        let pieces = split_highlights(spans, line_highlights.map_or(&[][..], Vec::as_slice));
+        let pieces = split_links(pieces, line_links.map_or(&[][..], Vec::as_slice)).into_iter().map(|(span, current, link)| {
+            let piece = match link {
+                Some(link) => button(
+                    text(span.text)
+                        .size(font.size)
+                        .font(font.font)
+                        .style(iced::theme::Text::Color(iced::Color::from_rgb(0.35, 0.6, 1.0))),
+                )
+                    .style(button::text)
+                    .padding(0)
+                    .on_press(crate::Message::OpenOutputLink(link.target.clone()))
+                    .into(),
+                None => view_span(span, default_style, font.size, font.font),
+            };
+            let Some(current) = current else { return piece };
+            let background = if current { iced::Color::from_rgb(1.0, 0.6, 0.1) } else { iced::Color::from_rgb(0.9, 0.8, 0.2) };
+            container(piece)
+                .style(container::Appearance {
+                    background: Some(iced::Background::Color(background)),
+                    ..Default::default()
+                })
+                .into()
+        });
+        row(pieces).into()
+    });
+    column(lines).into()
+}
+
+/// Cut pieces of a line further where links start and end, pairing each
+/// with the link it's part of
+fn split_links<'l>(pieces: Vec<(ansi::Span, Option<bool>)>, links: &[&'l Link]) -> Vec<(ansi::Span, Option<bool>, Option<&'l Link>)> {
+    let mut split = Vec::new();
+    let mut start = 0;
+    for (span, current) in pieces {
+        let end = start + span.text.len();
+        let mut cuts: Vec<usize> = links
+            .iter()
+            .flat_map(|link| [link.range.start, link.range.end])
+            .filter(|cut| (start..end).contains(cut))
+            .map(|cut| cut - start)
+            .chain([0, span.text.len()])
+            .collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+        for cut in cuts.windows(2) {
+            let Some(piece) = span.text.get(cut[0]..cut[1]) else { continue };
+            let at = start + cut[0];
+            let link = links.iter().find(|link| link.range.contains(&at)).copied();
+            split.push((ansi::Span { text: piece.to_string(), style: span.style }, current, link));
+        }
+        start = end;
+    }
+    split
+}
+
+/// Cut a line's spans where highlights start and end; each piece comes with
+/// whether it's the current match, if it's highlighted at all
+fn split_highlights(spans: Vec<ansi::Span>, highlights: &[&Highlight]) -> Vec<(ansi::Span, Option<bool>)> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for span in spans {
+        let end = start + span.text.len();
+        let mut cuts: Vec<usize> = highlights
+            .iter()
+            .flat_map(|highlight| [highlight.range.start, highlight.range.end])
+            .filter(|cut| (start..end).contains(cut))
+            .map(|cut| cut - start)
+            .chain([0, span.text.len()])
+            .collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+        for cut in cuts.windows(2) {
+            let Some(piece) = span.text.get(cut[0]..cut[1]) else { continue };
+            let at = start + cut[0];
+            let current = highlights.iter().find(|highlight| highlight.range.contains(&at)).map(|highlight| highlight.current);
+            pieces.push((ansi::Span { text: piece.to_string(), style: span.style }, current));
+        }
+        start = end;
+    }
+    pieces
+}
+
+fn view_styled_lines<'a>(
+    lines: Vec<Vec<ansi::Span>>,
+    default_style: iced::theme::Text,
+    size: f32,
+    family: iced::Font,
+) -> Element<'a, crate::Message> {
+    let lines = lines.into_iter().map(|spans| row(spans.into_iter().map(|span| view_span(span, default_style, size, family))).into());
+    column(lines).into()
+}
+
+fn view_span<'a>(span: ansi::Span, default_style: iced::theme::Text, size: f32, family: iced::Font) -> Element<'a, crate::Message> {
+    let (foreground, _) = span.style.colors();
+    let style = match foreground {
+        Some(color) => {
+            let (r, g, b) = color.rgb();
+            iced::theme::Text::Color(iced::Color::from_rgb8(r, g, b))
+        }
+        _ => default_style,
+    };
+    let font = iced::Font {
+        weight: if span.style.bold { iced::font::Weight::Bold } else { iced::font::Weight::Normal },
+        style: if span.style.italic { iced::font::Style::Italic } else { iced::font::Style::Normal },
+        ..family
+    };
+    text(span.text).size(size).style(style).font(font).into()
+}
+
+/// Part of a rendered agent reply
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkdownSection {
+    Text(Vec<Vec<ansi::Span>>),
+    /// Highlighted fenced code; `index` counts code blocks in the reply
+    Code { index: usize, language: String, lines: Vec<Vec<ansi::Span>> },
+}
+
+/// Styled lines for the parsed reply. Markdown markers are dropped; what
+/// they meant is kept as bold, italics and color, which both the window and
+/// the text screen can draw.
+fn markdown_sections(markdown: &StreamingMarkdown) -> Vec<MarkdownSection> {
+    let mut sections = Vec::new();
+    let mut text_lines: Vec<Vec<ansi::Span>> = Vec::new();
+    let mut code_index = 0;
+    let accent = Some(ansi::Color::Indexed(4));
+
+    for block in markdown.blocks() {
+        let line = match block {
+            MarkdownBlock::Heading(_, spans) => {
+                inline_spans(spans, ansi::Style { bold: true, foreground: accent, ..Default::default() })
+            }
+            MarkdownBlock::Paragraph(spans) => inline_spans(spans, ansi::Style::default()),
+            MarkdownBlock::Quote(spans) => {
+                let mut line = vec![ansi::Span { text: "│ ".to_string(), style: ansi::Style { dim: true, ..Default::default() } }];
+                line.extend(inline_spans(spans, ansi::Style { italic: true, ..Default::default() }));
+                line
+            }
+            MarkdownBlock::ListItem(marker, spans) => {
+                let bullet = match marker {
+                    ListMarker::Bullet => "  • ".to_string(),
+                    ListMarker::Number(n) => format!("  {}. ", n),
+                };
+                let mut line = vec![ansi::Span { text: bullet, style: ansi::Style::default() }];
+                line.extend(inline_spans(spans, ansi::Style::default()));
+                line
+            }
+            MarkdownBlock::Code { language, code, .. } => {
+                if !text_lines.is_empty() {
+                    sections.push(MarkdownSection::Text(std::mem::take(&mut text_lines)));
+                }
+                let highlighted = SyntaxHighlighter::shared().highlight(code, language);
+                sections.push(MarkdownSection::Code {
+                    index: code_index,
+                    language: language.clone(),
+                    lines: ansi::lines(&highlighted),
+                });
+                code_index += 1;
+                continue;
+            }
+        };
+        text_lines.push(line);
+    }
+    if !text_lines.is_empty() {
+        sections.push(MarkdownSection::Text(text_lines));
+    }
+    sections
+}
+
+fn inline_spans(spans: &[InlineSpan], base: ansi::Style) -> Vec<ansi::Span> {
+    let mut line = Vec::new();
+    for (i, span) in spans.iter().enumerate() {
+        let mut style = base;
+        style.bold |= span.bold;
+        style.italic |= span.italic;
+        if span.code {
+            style.foreground = Some(ansi::Color::Indexed(1));
+        }
+        if span.link.is_some() {
+            style.foreground = Some(ansi::Color::Indexed(4));
+            style.underline = true;
+        }
+        line.push(ansi::Span { text: span.text.clone(), style });
+        // Keep the address visible after the label, and findable by link hints
+        let label_ends = spans.get(i + 1).map_or(true, |next| next.link != span.link);
+        if let Some(url) = span.link.as_ref().filter(|url| label_ends && **url != span.text) {
+            line.push(ansi::Span { text: format!(" ({})", url), style: ansi::Style { dim: true, ..base } });
+        }
+    }
+    line
+}
+
+/// Where a block is moved within the list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockMove {
+    Up,
+    Down,
+    Top,
+    Bottom,
+}
+
+/// Move block `id` and return its new index
+pub fn move_block(blocks: &mut Vec<Block>, id: Uuid, movement: BlockMove) -> Option<usize> {
+    let from = blocks.iter().position(|b| b.id == id)?;
+    let to = match movement {
+        BlockMove::Up => from.saturating_sub(1),
+        BlockMove::Down => (from + 1).min(blocks.len() - 1),
+        BlockMove::Top => 0,
+        BlockMove::Bottom => blocks.len() - 1,
+    };
+    let block = blocks.remove(from);
+    blocks.insert(to, block);
+    Some(to)
+}
+
+/// Drop block `id` where `target` is: after it when moving down, before it when moving up
+pub fn move_block_to(blocks: &mut Vec<Block>, id: Uuid, target: Uuid) -> Option<usize> {
+    let from = blocks.iter().position(|b| b.id == id)?;
+    let to = blocks.iter().position(|b| b.id == target)?;
+    let block = blocks.remove(from);
+    blocks.insert(to, block);
+    Some(to)
+}
+
+/// Collapse the last command if it succeeded printing more than `max_lines`
+/// lines, as the next one starts
+pub fn auto_collapse(blocks: &mut [Block], max_lines: usize) {
+    let last = blocks.iter_mut().rev().find(|b| matches!(b.content, BlockContent::Command { .. }));
+    if let Some(block) = last.filter(|b| b.status() == Some(BlockStatus::Succeeded) && b.printed_lines() > max_lines) {
+        block.collapsed = true;
+    }
+}
+
+/// The first heading of a Markdown message, or else its first sentence
+fn first_heading_or_sentence(markdown: &str) -> String {
+    let heading = markdown
+        .lines()
+        .find_map(|line| line.trim_start().strip_prefix('#').map(|rest| rest.trim_start_matches('#').trim()))
+        .filter(|heading| !heading.is_empty());
+    if let Some(heading) = heading {
+        return heading.to_string();
+    }
+    let text = markdown.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.find(['.', '!', '?']) {
+        Some(end) => text[..=end].to_string(),
+        None => text,
+    }
+}
+
+/// Which blocks the list shows; the others are only hidden
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlockFilter {
+    #[default]
+    All,
+    Commands,
+    /// Failed commands and error notices
+    Errors,
+    /// Conversation messages
+    Ai,
+    Pinned,
+}
+
+impl BlockFilter {
+    pub const ALL: [BlockFilter; 5] = [Self::All, Self::Commands, Self::Errors, Self::Ai, Self::Pinned];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::All => tr("blocks.filter.all"),
+            Self::Commands => tr("blocks.filter.commands"),
+            Self::Errors => tr("blocks.filter.errors"),
+            Self::Ai => tr("blocks.filter.ai"),
+            Self::Pinned => tr("blocks.filter.pinned"),
+        }
+    }
+
+    pub fn matches(&self, block: &Block) -> bool {
+        match self {
+            Self::All => true,
+            Self::Commands => matches!(block.content, BlockContent::Command { .. }),
+            Self::Errors => {
+                matches!(block.content, BlockContent::Error { .. }) || matches!(block.status(), Some(BlockStatus::Failed(_)))
+            }
+            Self::Ai => matches!(block.content, BlockContent::AgentMessage { .. } | BlockContent::UserMessage { .. }),
+            Self::Pinned => block.pinned,
+        }
+    }
+}
+
+/// The blocks `filter` shows, pinned ones first
+pub fn visible(blocks: &[Block], filter: BlockFilter) -> Vec<&Block> {
+    let (pinned, rest): (Vec<&Block>, Vec<&Block>) = blocks.iter().filter(|b| filter.matches(b)).partition(|b| b.pinned);
+    pinned.into_iter().chain(rest).collect()
+}
+
+/// Restore the order the blocks were created in
+pub fn sort_by_time(blocks: &mut [Block]) {
+    blocks.sort_by_key(|b| b.created_at);
+}
+
+pub fn is_chronological(blocks: &[Block]) -> bool {
+    blocks.windows(2).all(|pair| pair[0].created_at <= pair[1].created_at)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn numbered(count: usize) -> Vec<Block> {
+        let start = Utc::now();
+        (0..count)
+            .map(|i| {
+                let mut block = Block::new_command(format!("echo {}", i));
+                block.created_at = start + chrono::Duration::seconds(i as i64);
+                block
+            })
+            .collect()
+    }
+
+    fn order(blocks: &[Block]) -> Vec<String> {
+        blocks.iter().map(Block::title).collect()
+    }
+
+    #[test]
+    fn test_collapsed_preview_of_commands_and_replies() {
+        let mut block = Block::new_command("cargo build".to_string());
+        block.set_output("   Compiling neoterm\n\x1b[32m    Finished\x1b[0m dev in 3.2s\n\n".to_string(), 0);
+        assert_eq!(block.collapsed_preview(), "Finished dev in 3.2s");
+
+        let reply = Block::new_agent_message("The build failed. Run `cargo clean` first.".to_string());
+        assert_eq!(reply.collapsed_preview(), "The build failed.");
+        let reply = Block::new_agent_message("Some context\n\n## Fixing the linker error\nInstall lld.".to_string());
+        assert_eq!(reply.collapsed_preview(), "Fixing the linker error");
+    }
+
+    #[test]
+    fn test_auto_collapse_keeps_failures_and_short_output() {
+        let mut blocks = numbered(3);
+        blocks[0].set_output("1\n2\n3\n".to_string(), 0);
+        blocks[1].set_output("1\n2\n3\n".to_string(), 1);
+        auto_collapse(&mut blocks[..2], 2);
+        assert!(!blocks[0].collapsed && !blocks[1].collapsed);
+        blocks[2].set_output("1\n2\n".to_string(), 0);
+        auto_collapse(&mut blocks, 2);
+        assert!(!blocks[2].collapsed);
+
+        // A reply after the command doesn't keep it expanded
+        let mut blocks = vec![numbered(1).remove(0), Block::new_agent_message("Done".to_string())];
+        blocks[0].set_output("1\n2\n3\n".to_string(), 0);
+        auto_collapse(&mut blocks, 2);
+        assert!(blocks[0].collapsed);
+    }
+
+    #[test]
+    fn test_filters_hide_blocks_and_pins_go_first() {
+        let mut blocks = numbered(3);
+        blocks[1].set_output("boom".to_string(), 1);
+        blocks[2].pinned = true;
+        blocks.push(Block::new_agent_message("Try again".to_string()));
+        blocks.push(Block::new_error("No such profile".to_string()));
+
+        let titles = |filter| visible(&blocks, filter).into_iter().map(Block::title).collect::<Vec<_>>();
+        assert_eq!(titles(BlockFilter::All)[0], "echo 2");
+        assert_eq!(titles(BlockFilter::Commands), ["echo 2", "echo 0", "echo 1"]);
+        assert_eq!(titles(BlockFilter::Errors).len(), 2);
+        assert_eq!(titles(BlockFilter::Errors)[0], "echo 1");
+        assert_eq!(titles(BlockFilter::Ai).len(), 1);
+        assert_eq!(titles(BlockFilter::Pinned), ["echo 2"]);
+        assert_eq!(blocks.len(), 5);
+    }
+
+    #[test]
+    fn test_highlights_cut_styled_spans() {
+        let bold = ansi::Style { bold: true, ..Default::default() };
+        let spans = vec![
+            ansi::Span { text: "err".to_string(), style: bold },
+            ansi::Span { text: "or: error".to_string(), style: ansi::Style::default() },
+        ];
+        let first = Highlight { line: 0, range: 0..5, current: false };
+        let second = Highlight { line: 0, range: 7..12, current: true };
+        let pieces: Vec<(String, bool, Option<bool>)> = split_highlights(spans, &[&first, &second])
+            .into_iter()
+            .map(|(span, current)| (span.text, span.style.bold, current))
+            .collect();
+        assert_eq!(pieces, vec![
+            ("err".to_string(), true, Some(false)),
+            ("or".to_string(), false, Some(false)),
+            (": ".to_string(), false, None),
+            ("error".to_string(), false, Some(true)),
+        ]);
+    }
+
+    #[test]
+    fn test_links_cut_highlighted_pieces() {
+        let link = Link {
+            line: 0,
+            range: 4..10,
+            target: crate::links::LinkTarget::Url("http://x.io".to_string()),
+        };
+        let pieces = vec![
+            (ansi::Span { text: "see a.rs".to_string(), style: ansi::Style::default() }, None),
+            (ansi::Span { text: ":3 ok".to_string(), style: ansi::Style::default() }, Some(true)),
+        ];
+        let split: Vec<(String, Option<bool>, bool)> = split_links(pieces, &[&link])
+            .into_iter()
+            .map(|(span, current, link)| (span.text, current, link.is_some()))
+            .collect();
+        assert_eq!(split, vec![
+            ("see ".to_string(), None, false),
+            ("a.rs".to_string(), None, true),
+            (":3".to_string(), Some(true), true),
+            (" ok".to_string(), Some(true), false),
+        ]);
+    }
+
+    #[test]
+    fn test_move_block() {
+        let mut blocks = numbered(4);
+        let (first, last) = (blocks[0].id, blocks[3].id);
+
+        assert_eq!(move_block(&mut blocks, first, BlockMove::Down), Some(1));
+        assert_eq!(move_block(&mut blocks, last, BlockMove::Top), Some(0));
+        assert_eq!(order(&blocks), vec!["echo 3", "echo 1", "echo 0", "echo 2"]);
+
+        // Moves stop at the ends
+        assert_eq!(move_block(&mut blocks, last, BlockMove::Up), Some(0));
+        assert_eq!(move_block(&mut blocks, first, BlockMove::Bottom), Some(3));
+        assert_eq!(move_block(&mut blocks, first, BlockMove::Down), Some(3));
+        assert_eq!(move_block(&mut blocks, Uuid::new_v4(), BlockMove::Up), None);
+    }
+
+    #[test]
+    fn test_explanation_controls_follow_annotation_state() {
+        use crate::BlockMessage as M;
+        let has = |block: &Block, wanted: fn(&M) -> bool| block.actions().iter().any(|(_, action)| wanted(action));
+
+        let mut block = Block::new_command("cargo build".to_string());
+        assert!(!has(&block, |a| matches!(a, M::Explain)));
+        block.finish_output(101, 5, &[]);
+        assert!(has(&block, |a| matches!(a, M::Explain)));
+
+        block.annotation = Some(Annotation::new("Explain".to_string()));
+        assert!(!has(&block, |a| matches!(a, M::Explain)));
+        assert!(!has(&block, |a| matches!(a, M::PinAnnotation)));
+        assert!(has(&block, |a| matches!(a, M::DismissAnnotation)));
+
+        block.annotation.as_mut().unwrap().state = AnnotationState::Ready("The linker failed".to_string());
+        assert!(has(&block, |a| matches!(a, M::PinAnnotation)));
+    }
+
+    #[test]
+    fn test_apply_is_only_offered_for_one_click_fixes() {
+        use crate::BlockMessage as M;
+        let has = |block: &Block, wanted: fn(&M) -> bool| block.actions().iter().any(|(_, action)| wanted(action));
+
+        let mut block = Block::new_command("cp src dest".to_string());
+        block.finish_output(1, 5, &[]);
+        assert!(has(&block, |a| matches!(a, M::SuggestFix)));
+        block.fix = Some(FixSuggestion::Loading);
+        assert!(!has(&block, |a| matches!(a, M::SuggestFix)));
+        assert!(!has(&block, |a| matches!(a, M::UseFix)));
+        assert!(has(&block, |a| matches!(a, M::DismissFix)));
+
+        block.fix = Some(FixSuggestion::Ready { command: "rm -rf dest".to_string(), run: FixRun::Review("deletes files") });
+        assert!(has(&block, |a| matches!(a, M::UseFix)));
+        assert!(!has(&block, |a| matches!(a, M::ApplyFix)));
+
+        block.fix = Some(FixSuggestion::Ready { command: "cp -r src dest".to_string(), run: FixRun::OneClick });
+        assert!(has(&block, |a| matches!(a, M::ApplyFix)));
+
+        let mut retry = Block::new_command("cp -r src dest".to_string());
+        retry.retry_of = Some(block.id);
+        assert!(retry.header(false).unwrap().meta.contains(tr("block.retry")));
+    }
+
+    #[test]
+    fn test_long_output_keeps_tail_and_reports_total() {
+        let mut block = Block::new_command("yes | head -n 10".to_string());
+        block.set_scrollback_limit(4);
+        for i in 0..5 {
+            block.append_chunk(OutputChunk {
+                offset_ms: i,
+                stream: crate::timeline::OutputStream::Stdout,
+                text: "y\ny\n".to_string(),
+            });
+        }
+        block.finish_output(0, 5, &[]);
+
+        assert_eq!(block.output_text(), "y\ny\ny\ny\n");
+        assert!(block.header(false).unwrap().meta.contains("10 lines, last 4 shown"));
+        assert!(block.actions().iter().any(|(_, action)| matches!(action, crate::BlockMessage::ShowFullOutput)));
+        let path = block.scrollback().unwrap().full_output().unwrap().clone();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "y\n".repeat(10));
+        block.scrollback().unwrap().discard();
+    }
+
+    #[test]
+    fn test_drop_on_target() {
+        let mut blocks = numbered(4);
+        let ids: Vec<Uuid> = blocks.iter().map(|b| b.id).collect();
+
+        move_block_to(&mut blocks, ids[0], ids[2]);
+        assert_eq!(order(&blocks), vec!["echo 1", "echo 2", "echo 0", "echo 3"]);
+        move_block_to(&mut blocks, ids[3], ids[1]);
+        assert_eq!(order(&blocks), vec!["echo 3", "echo 1", "echo 2", "echo 0"]);
+    }
+
+    #[test]
+    fn test_sort_by_time_recovers_original_order() {
+        let mut blocks = numbered(5);
+        let ids: Vec<Uuid> = blocks.iter().map(|b| b.id).collect();
+        move_block(&mut blocks, ids[4], BlockMove::Top);
+        move_block_to(&mut blocks, ids[1], ids[3]);
+        assert!(!is_chronological(&blocks));
+
+        sort_by_time(&mut blocks);
+        assert!(is_chronological(&blocks));
+        assert_eq!(blocks.iter().map(|b| b.id).collect::<Vec<_>>(), ids);
+    }
+
     #[test]
     fn test_block_creation() {
         let block = Block::new_command("ls -la".to_string());
@@ -282,4 +2711,123 @@ mod tests {
             panic!("Expected command block");
         }
     }
+
+    #[test]
+    fn test_streamed_output_can_be_scrubbed() {
+        use crate::timeline::OutputStream;
+
+        let mut block = Block::new_command("make test".to_string());
+        for (offset_ms, stream, text) in [
+            (0, OutputStream::Stdout, "compiling\n"),
+            (12_000, OutputStream::Stderr, "error: flaky\n"),
+            (45_000, OutputStream::Stdout, "FAILED\n"),
+        ] {
+            block.append_chunk(OutputChunk { offset_ms, stream, text: text.to_string() });
+        }
+        block.finish_output(1, 46_000, &[regex::Regex::new("FAILED").unwrap()]);
+        block.scrub_to(Some(12_000));
+
+        let BlockContent::Command { output, timeline, markers, scrub_ms, .. } = &block.content else {
+            panic!("Expected command block");
+        };
+        assert_eq!(timeline.output_at(output.as_deref().unwrap(), scrub_ms.unwrap()), "compiling\nerror: flaky\n");
+        assert_eq!(timeline.duration_ms, 46_000);
+        assert_eq!(markers.len(), 2);
+        assert_eq!(block.status(), Some(BlockStatus::Failed(1)));
+    }
+
+    #[test]
+    fn test_command_markdown() {
+        let mut block = Block::new_command("echo hi".to_string());
+        block.set_output("hi".to_string(), 0);
+
+        let markdown = block.to_markdown();
+        assert!(markdown.starts_with("```sh\n$ echo hi\nhi\n```\n"));
+        assert!(markdown.ends_with(", exit 0_\n"));
+    }
+
+    #[test]
+    fn test_status_glyphs() {
+        let mut block = Block::new_command("make".to_string());
+        assert_eq!(block.status(), Some(BlockStatus::Running));
+        assert_eq!(block.status().unwrap().glyph(), "⏳");
+
+        block.set_output(String::new(), 2);
+        assert_eq!(block.status(), Some(BlockStatus::Failed(2)));
+        assert_eq!(block.status().unwrap().glyph(), "✗");
+
+        block.set_output(String::new(), 0);
+        assert_eq!(block.status().unwrap().glyph(), "✓");
+
+        assert_eq!(Block::new_agent_message("hi".to_string()).status(), None);
+    }
+
+    #[test]
+    fn test_superseded_blocks_keep_content() {
+        let mut reply = Block::new_agent_message("old answer".to_string());
+        let mut command = Block::new_command("ls".to_string());
+        reply.mark_superseded();
+        command.mark_superseded();
+
+        assert!(reply.is_superseded());
+        assert!(matches!(reply.content, BlockContent::AgentMessage { ref content, .. } if content == "old answer"));
+        assert!(!command.is_superseded());
+    }
+
+    #[test]
+    fn test_command_env_overrides() {
+        let block = Block::new_command_with_env(
+            "cargo test".to_string(),
+            vec![("RUST_LOG".to_string(), "debug".to_string())],
+        );
+
+        if let BlockContent::Command { input, env_overrides, .. } = block.content {
+            assert_eq!(input, "cargo test");
+            assert_eq!(env_overrides, vec![("RUST_LOG".to_string(), "debug".to_string())]);
+        } else {
+            panic!("Expected command block");
+        }
+    }
+
+    #[test]
+    fn test_metadata_summary_counts_up_until_the_command_exits() {
+        let mut block = Block::new_command("cargo build".to_string());
+        block.start(Some("staging".to_string()));
+        if let BlockContent::Command { ref mut working_directory, .. } = block.content {
+            *working_directory = "/srv/app".to_string();
+        }
+        let metadata = block.metadata().unwrap();
+        let later = metadata.started_at + chrono::Duration::milliseconds(3_000);
+        assert_eq!(metadata.summary(later), "⏳ · 3.0s · /srv/app · staging");
+        assert_eq!(block.duration(), None);
+
+        block.finish_output(1, 1_240, &[]);
+        let metadata = block.metadata().unwrap();
+        assert_eq!(metadata.summary(later), "✗ 1 · 1.2s · /srv/app · staging");
+        assert_eq!(block.duration(), Some(std::time::Duration::from_millis(1_240)));
+    }
+
+    #[test]
+    fn test_prompt_marks_split_the_output() {
+        let mut block = Block::new_command("bash".to_string());
+        let chunk = |text: &str| OutputChunk { offset_ms: 0, stream: crate::timeline::OutputStream::Stdout, text: text.to_string() };
+        block.append_chunk(chunk("welcome\n"));
+        block.mark_prompt();
+        block.append_chunk(chunk("$ ls\na.txt\n"));
+        block.set_prompt_command("ls".to_string());
+        block.set_prompt_exit(Some(0));
+        block.mark_prompt();
+        block.append_chunk(chunk("$ "));
+        assert_eq!(block.prompts()[0], PromptMark { line: 1, command: Some("ls".to_string()), exit_code: Some(0) });
+
+        let output = block.output_text();
+        let segments = output_segments(&output, 0, block.prompts());
+        let texts: Vec<(Option<usize>, &str)> = segments.iter().map(|s| (s.prompt, s.text)).collect();
+        assert_eq!(texts, vec![(None, "welcome\n"), (Some(0), "$ ls\na.txt\n"), (Some(1), "$ ")]);
+
+        // With the first two lines dropped, the first prompt starts at the top
+        let segments = output_segments("a.txt\n$ ", 2, block.prompts());
+        let texts: Vec<(Option<usize>, &str)> = segments.iter().map(|s| (s.prompt, s.text)).collect();
+        assert_eq!(texts, vec![(Some(0), "a.txt\n"), (Some(1), "$ ")]);
+    }
 }
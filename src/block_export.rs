@@ -0,0 +1,363 @@
+//! Saving a single block to a file as plain text, Markdown or JSON.
+//!
+//! The JSON form carries everything needed to recreate the block: command
+//! blocks keep their timeline, so stdout and stderr stay apart and the
+//! scrubber still works after an import.
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use uuid::Uuid;
+use crate::block::{AgentRole, Block, BlockContent};
+use crate::input::EnhancedTextInput;
+use crate::tee::AnsiStripper;
+use crate::timeline::{OutputStream, OutputTimeline};
+
+/// Bumped when the JSON layout changes incompatibly
+pub const EXPORT_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("IO error: {0}")]
+    IoError(String),
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Text, ExportFormat::Markdown, ExportFormat::Json];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportFormat::Text => write!(f, "Plain text"),
+            ExportFormat::Markdown => write!(f, "Markdown"),
+            ExportFormat::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// "Export…" being filled in for a block
+#[derive(Debug, Clone)]
+pub struct ExportPrompt {
+    pub block_id: Uuid,
+    pub format: ExportFormat,
+    pub path: EnhancedTextInput,
+}
+
+impl ExportPrompt {
+    pub fn new(block_id: Uuid, dir: &Path, now: DateTime<Local>) -> Self {
+        let format = ExportFormat::Markdown;
+        let mut path = EnhancedTextInput::new();
+        path.set_value(default_path(dir, format, now).to_string_lossy().to_string());
+        Self { block_id, format, path }
+    }
+
+    /// Switch format, keeping the file extension in step unless it was changed by hand
+    pub fn set_format(&mut self, format: ExportFormat) {
+        let path = PathBuf::from(self.path.value());
+        if path.extension().and_then(|ext| ext.to_str()) == Some(self.format.extension()) {
+            self.path.set_value(path.with_extension(format.extension()).to_string_lossy().to_string());
+        }
+        self.format = format;
+    }
+
+    pub fn target(&self) -> PathBuf {
+        expand_home(self.path.value())
+    }
+}
+
+/// A typed path with a leading `~` expanded
+pub fn expand_home(typed: &str) -> PathBuf {
+    let typed = typed.trim();
+    match (typed.strip_prefix("~/"), directories::UserDirs::new()) {
+        (Some(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => PathBuf::from(typed),
+    }
+}
+
+/// The Downloads folder, falling back to the home directory
+pub fn default_dir() -> PathBuf {
+    directories::UserDirs::new()
+        .map(|dirs| dirs.download_dir().map(Path::to_path_buf).unwrap_or_else(|| dirs.home_dir().join("Downloads")))
+        .unwrap_or_default()
+}
+
+/// `neoterm-export-<timestamp>.<ext>` in `dir`
+pub fn default_path(dir: &Path, format: ExportFormat, now: DateTime<Local>) -> PathBuf {
+    dir.join(format!("neoterm-export-{}.{}", now.format("%Y%m%d-%H%M%S"), format.extension()))
+}
+
+/// A block as written to JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedBlock {
+    pub version: u32,
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Older exports and sessions have no pins
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[serde(flatten)]
+    pub content: ExportedContent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExportedContent {
+    Command {
+        command: String,
+        working_directory: String,
+        env_overrides: Vec<(String, String)>,
+        exit_code: Option<i32>,
+        output: String,
+        /// Output chunks with their stream and arrival time
+        timeline: OutputTimeline,
+        /// Older exports only have the block's creation time
+        #[serde(default)]
+        started_at: Option<DateTime<Utc>>,
+        #[serde(default)]
+        finished_at: Option<DateTime<Utc>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        env_profile: Option<String>,
+    },
+    AgentMessage {
+        role: AgentRole,
+        markdown: String,
+        /// Replaced by an edited turn and shown collapsed
+        #[serde(default)]
+        superseded: bool,
+    },
+    UserMessage {
+        markdown: String,
+        #[serde(default)]
+        superseded: bool,
+    },
+    Error {
+        message: String,
+    },
+    /// Plugin, diagnostics and other live blocks, kept as their Markdown
+    Other {
+        title: String,
+        markdown: String,
+    },
+}
+
+impl ExportedBlock {
+    pub fn from_block(block: &Block) -> Self {
+        let content = match &block.content {
+            BlockContent::Command { input, output, exit_code, working_directory, env_overrides, timeline, started_at, finished_at, env_profile, .. } => {
+                ExportedContent::Command {
+                    command: input.clone(),
+                    working_directory: working_directory.clone(),
+                    env_overrides: env_overrides.clone(),
+                    exit_code: *exit_code,
+                    output: output.clone().unwrap_or_default(),
+                    timeline: timeline.clone(),
+                    started_at: Some(*started_at),
+                    finished_at: *finished_at,
+                    env_profile: env_profile.clone(),
+                }
+            }
+            BlockContent::AgentMessage { content, role, superseded, .. } => ExportedContent::AgentMessage {
+                role: role.clone(),
+                markdown: content.clone(),
+                superseded: *superseded,
+            },
+            BlockContent::UserMessage { content, superseded, .. } => {
+                ExportedContent::UserMessage { markdown: content.clone(), superseded: *superseded }
+            }
+            BlockContent::Error { message } => ExportedContent::Error { message: message.clone() },
+            _ => ExportedContent::Other { title: block.title(), markdown: block.to_markdown() },
+        };
+        Self {
+            version: EXPORT_VERSION,
+            id: block.id,
+            created_at: block.created_at,
+            updated_at: block.updated_at,
+            pinned: block.pinned,
+            content,
+        }
+    }
+
+    /// Recreate the block. Live blocks come back as a notice with their Markdown.
+    pub fn into_block(self) -> Block {
+        let mut block = match self.content {
+            ExportedContent::Command { command, working_directory, env_overrides, exit_code, output, timeline, started_at, finished_at, env_profile } => {
+                let mut block = Block::new_command_with_env(command, env_overrides);
+                if let BlockContent::Command { output: ref mut o, exit_code: ref mut e, working_directory: ref mut w, timeline: ref mut t, started_at: ref mut s, finished_at: ref mut f, env_profile: ref mut p, .. } = block.content {
+                    *o = (!output.is_empty() || exit_code.is_some()).then_some(output);
+                    *e = exit_code;
+                    *w = working_directory;
+                    *t = timeline;
+                    *s = started_at.unwrap_or(self.created_at);
+                    *f = finished_at;
+                    *p = env_profile;
+                }
+                block
+            }
+            ExportedContent::AgentMessage { role, markdown, superseded } => {
+                let mut block = Block::new_agent_message(markdown);
+                if let BlockContent::AgentMessage { role: ref mut r, superseded: ref mut s, .. } = block.content {
+                    *r = role;
+                    *s = superseded;
+                }
+                block
+            }
+            ExportedContent::UserMessage { markdown, superseded } => {
+                let mut block = Block::new_user_message(markdown);
+                if let BlockContent::UserMessage { superseded: ref mut s, .. } = block.content {
+                    *s = superseded;
+                }
+                block
+            }
+            ExportedContent::Error { message } => Block::new_error(message),
+            ExportedContent::Other { markdown, .. } => Block::new_info(markdown),
+        };
+        block.id = self.id;
+        block.created_at = self.created_at;
+        block.updated_at = self.updated_at;
+        block.pinned = self.pinned;
+        block
+    }
+}
+
+/// Parse a JSON export back into a block
+pub fn from_json(json: &str) -> Result<Block, ExportError> {
+    serde_json::from_str::<ExportedBlock>(json)
+        .map(ExportedBlock::into_block)
+        .map_err(|e| ExportError::SerializationError(e.to_string()))
+}
+
+/// The block in `format`
+pub fn render(block: &Block, format: ExportFormat) -> Result<String, ExportError> {
+    match format {
+        ExportFormat::Markdown => Ok(block.to_markdown()),
+        ExportFormat::Json => serde_json::to_string_pretty(&ExportedBlock::from_block(block))
+            .map_err(|e| ExportError::SerializationError(e.to_string())),
+        ExportFormat::Text => Ok(plain_text(block)),
+    }
+}
+
+/// Write the block to `path`, creating missing directories
+pub fn export(block: &Block, format: ExportFormat, path: &Path) -> Result<(), ExportError> {
+    let contents = render(block, format)?;
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| ExportError::IoError(e.to_string()))?;
+    }
+    std::fs::write(path, contents).map_err(|e| ExportError::IoError(e.to_string()))
+}
+
+/// Command line, output without escape sequences and stderr marked, then the
+/// outcome; other blocks as their text
+fn plain_text(block: &Block) -> String {
+    let BlockContent::Command { input, output, exit_code, working_directory, timeline, .. } = &block.content else {
+        return match &block.content {
+            BlockContent::AgentMessage { content, .. } | BlockContent::UserMessage { content, .. } => format!("{}\n", content),
+            BlockContent::Error { message } => format!("Error: {}\n", message),
+            _ => block.to_markdown(),
+        };
+    };
+    let mut text = format!("$ {}\n", input);
+    let mut stripper = AnsiStripper::new();
+    if timeline.is_empty() {
+        text.push_str(&stripper.strip(output.as_deref().unwrap_or("")));
+    } else {
+        let mut at_line_start = true;
+        for chunk in timeline.chunks() {
+            for piece in stripper.strip(&chunk.text).split_inclusive('\n') {
+                if at_line_start && chunk.stream == OutputStream::Stderr {
+                    text.push_str("[stderr] ");
+                }
+                text.push_str(piece);
+                at_line_start = piece.ends_with('\n');
+            }
+        }
+    }
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    match exit_code {
+        Some(code) => text.push_str(&format!("\n[in {}, exit {}]\n", working_directory, code)),
+        None => text.push_str(&format!("\n[in {}, still running]\n", working_directory)),
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::OutputChunk;
+    use chrono::TimeZone;
+
+    fn finished_command() -> Block {
+        let mut block = Block::new_command("make".to_string());
+        block.append_chunk(OutputChunk { offset_ms: 5, stream: OutputStream::Stdout, text: "\x1b[32mbuilding\x1b[0m\n".to_string() });
+        block.append_chunk(OutputChunk { offset_ms: 9, stream: OutputStream::Stderr, text: "warning: unused\n".to_string() });
+        block.finish_output(2, 12, &[]);
+        block
+    }
+
+    #[test]
+    fn test_json_round_trips() {
+        let block = finished_command();
+        let json = render(&block, ExportFormat::Json).unwrap();
+        let imported = from_json(&json).unwrap();
+        assert_eq!(ExportedBlock::from_block(&imported), ExportedBlock::from_block(&block));
+        assert_eq!(imported.id, block.id);
+        assert_eq!(imported.output_text(), block.output_text());
+        assert_eq!(imported.metadata(), block.metadata());
+
+        let mut reply = Block::new_agent_message("Use `ls -la`".to_string());
+        reply.pinned = true;
+        let imported = from_json(&render(&reply, ExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(imported.to_markdown(), reply.to_markdown());
+        assert!(imported.pinned);
+    }
+
+    #[test]
+    fn test_plain_text_strips_escapes_and_marks_stderr() {
+        let text = render(&finished_command(), ExportFormat::Text).unwrap();
+        assert!(text.starts_with("$ make\nbuilding\n[stderr] warning: unused\n"));
+        assert!(text.ends_with(", exit 2]\n"));
+    }
+
+    #[test]
+    fn test_changing_format_follows_default_extension() {
+        let now = Local.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+        let mut prompt = ExportPrompt::new(Uuid::new_v4(), Path::new("/tmp"), now);
+        assert_eq!(prompt.target(), PathBuf::from("/tmp/neoterm-export-20240301-093000.md"));
+
+        prompt.set_format(ExportFormat::Json);
+        assert_eq!(prompt.target(), PathBuf::from("/tmp/neoterm-export-20240301-093000.json"));
+
+        prompt.path.set_value("/tmp/notes.log".to_string());
+        prompt.set_format(ExportFormat::Text);
+        assert_eq!(prompt.target(), PathBuf::from("/tmp/notes.log"));
+    }
+
+    #[test]
+    fn test_export_creates_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("block.md");
+        export(&finished_command(), ExportFormat::Markdown, &path).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("```sh\n$ make\n"));
+    }
+}
@@ -0,0 +1,284 @@
+//! Ctrl+F search through the blocks of the focused pane: command input and
+//! output, conversation messages and errors. Plain text matches ignore case;
+//! the regex toggle takes the query as a pattern instead.
+//!
+//! Matches are found a block at a time and kept until that block's text
+//! changes, so streaming output only searches the block it goes to again.
+//! A block stops being searched after `MAX_MATCHES_PER_BLOCK` matches, which
+//! also bounds the highlighting work for giant outputs.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use regex::{Regex, RegexBuilder};
+use uuid::Uuid;
+use crate::ansi;
+use crate::block::{Block, BlockContent};
+
+pub const MAX_MATCHES_PER_BLOCK: usize = 1_000;
+
+/// The part of a block a match is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchField {
+    Input,
+    Output,
+    /// Conversation messages and errors
+    Message,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub block: Uuid,
+    pub field: MatchField,
+    pub line: usize,
+    /// Bytes of the line, with escape sequences removed
+    pub range: Range<usize>,
+}
+
+/// A match to draw in a block's output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    pub line: usize,
+    pub range: Range<usize>,
+    /// The match n/N moved to
+    pub current: bool,
+}
+
+#[derive(Debug, Clone)]
+struct BlockMatches {
+    /// Length of the text that was searched, to notice it changing
+    searched: usize,
+    matches: Vec<SearchMatch>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BlockSearch {
+    pub query: String,
+    pub regex: bool,
+    pattern: Option<Regex>,
+    /// Why the query isn't a valid pattern
+    error: Option<String>,
+    found: HashMap<Uuid, BlockMatches>,
+    matches: Vec<SearchMatch>,
+    current: usize,
+}
+
+impl BlockSearch {
+    pub fn set_query<'a>(&mut self, query: String, blocks: impl IntoIterator<Item = &'a Block>) {
+        self.query = query;
+        self.compile(blocks);
+    }
+
+    pub fn set_regex<'a>(&mut self, regex: bool, blocks: impl IntoIterator<Item = &'a Block>) {
+        self.regex = regex;
+        self.compile(blocks);
+    }
+
+    fn compile<'a>(&mut self, blocks: impl IntoIterator<Item = &'a Block>) {
+        self.found.clear();
+        self.current = 0;
+        self.error = None;
+        self.pattern = None;
+        if !self.query.is_empty() {
+            let pattern = if self.regex { self.query.clone() } else { regex::escape(&self.query) };
+            match RegexBuilder::new(&pattern).case_insensitive(true).build() {
+                Ok(pattern) => self.pattern = Some(pattern),
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        self.refresh(blocks);
+    }
+
+    /// Search blocks that are new or changed since the last call, and drop
+    /// the matches of those that are gone or filtered out
+    pub fn refresh<'a>(&mut self, blocks: impl IntoIterator<Item = &'a Block>) {
+        let current = self.current().cloned();
+        let mut found = HashMap::new();
+        self.matches.clear();
+        if let Some(pattern) = &self.pattern {
+            for block in blocks {
+                let searched = searched_len(block);
+                let matches = match self.found.remove(&block.id) {
+                    Some(previous) if previous.searched == searched => previous,
+                    _ => BlockMatches { searched, matches: search_block(pattern, block) },
+                };
+                self.matches.extend(matches.matches.iter().cloned());
+                found.insert(block.id, matches);
+            }
+        }
+        self.found = found;
+        self.current = current
+            .and_then(|current| self.matches.iter().position(|m| *m == current))
+            .unwrap_or_else(|| self.current.min(self.matches.len().saturating_sub(1)));
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn count(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Position of the current match among all of them, from 0
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
+    pub fn current(&self) -> Option<&SearchMatch> {
+        self.matches.get(self.current)
+    }
+
+    /// Move to the next match, wrapping around after the last
+    pub fn next(&mut self) -> Option<&SearchMatch> {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+        }
+        self.current()
+    }
+
+    /// Move to the previous match, wrapping around before the first
+    pub fn previous(&mut self) -> Option<&SearchMatch> {
+        if !self.matches.is_empty() {
+            self.current = (self.current + self.matches.len() - 1) % self.matches.len();
+        }
+        self.current()
+    }
+
+    /// Whether `block` has a match anywhere
+    pub fn has_matches(&self, block: Uuid) -> bool {
+        self.found.get(&block).is_some_and(|found| !found.matches.is_empty())
+    }
+
+    /// Matches to draw in `block`'s output
+    pub fn highlights(&self, block: Uuid) -> Vec<Highlight> {
+        let Some(found) = self.found.get(&block) else {
+            return Vec::new();
+        };
+        let current = self.current();
+        found.matches
+            .iter()
+            .filter(|m| m.field == MatchField::Output)
+            .map(|m| Highlight { line: m.line, range: m.range.clone(), current: current == Some(m) })
+            .collect()
+    }
+}
+
+fn searched_len(block: &Block) -> usize {
+    match &block.content {
+        BlockContent::Command { input, output, .. } => input.len() + output.as_ref().map_or(0, String::len),
+        BlockContent::AgentMessage { content, .. } | BlockContent::UserMessage { content, .. } => content.len(),
+        BlockContent::Error { message } => message.len(),
+        _ => 0,
+    }
+}
+
+/// Matches in `block`, up to `MAX_MATCHES_PER_BLOCK`
+fn search_block(pattern: &Regex, block: &Block) -> Vec<SearchMatch> {
+    let fields: Vec<(MatchField, std::borrow::Cow<str>)> = match &block.content {
+        BlockContent::Command { input, output, .. } => {
+            let mut fields = vec![(MatchField::Input, input.as_str().into())];
+            if let Some(output) = output {
+                fields.push((MatchField::Output, plain(output)));
+            }
+            fields
+        }
+        BlockContent::AgentMessage { content, .. } | BlockContent::UserMessage { content, .. } => {
+            vec![(MatchField::Message, content.as_str().into())]
+        }
+        BlockContent::Error { message } => vec![(MatchField::Message, message.as_str().into())],
+        _ => Vec::new(),
+    };
+
+    let mut matches = Vec::new();
+    for (field, text) in &fields {
+        // Lines as the output is drawn, split on newlines only
+        for (line, content) in text.split('\n').enumerate() {
+            for found in pattern.find_iter(content).filter(|found| !found.is_empty()) {
+                matches.push(SearchMatch { block: block.id, field: *field, line, range: found.range() });
+                if matches.len() == MAX_MATCHES_PER_BLOCK {
+                    return matches;
+                }
+            }
+        }
+    }
+    matches
+}
+
+/// Output as drawn, without escape sequences
+fn plain(output: &str) -> std::borrow::Cow<str> {
+    if !ansi::has_escapes(output) {
+        return output.into();
+    }
+    ansi::parse(output).into_iter().map(|span| span.text).collect::<String>().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(input: &str, output: &str) -> Block {
+        let mut block = Block::new_command(input.to_string());
+        block.set_output(output.to_string(), 0);
+        block
+    }
+
+    #[test]
+    fn test_plain_search_ignores_case_across_blocks() {
+        let blocks = [
+            command("grep error app.log", "Error: disk full\nok\n\x1b[31mERROR\x1b[0m: retry"),
+            Block::new_agent_message("The error means the disk is full".to_string()),
+        ];
+        let mut search = BlockSearch::default();
+        search.set_query("error".to_string(), &blocks);
+        assert_eq!(search.count(), 4);
+        assert_eq!(search.current().map(|m| m.field), Some(MatchField::Input));
+
+        // Colors don't count towards the highlighted range
+        let highlights = search.highlights(blocks[0].id);
+        assert_eq!(highlights, vec![
+            Highlight { line: 0, range: 0..5, current: false },
+            Highlight { line: 2, range: 0..5, current: false },
+        ]);
+
+        assert_eq!(search.next().map(|m| m.line), Some(0));
+        assert_eq!(search.next().map(|m| m.line), Some(2));
+        assert_eq!(search.next().map(|m| (m.block, m.field)), Some((blocks[1].id, MatchField::Message)));
+        assert_eq!(search.next().map(|m| m.field), Some(MatchField::Input));
+        assert_eq!(search.previous().map(|m| m.block), Some(blocks[1].id));
+    }
+
+    #[test]
+    fn test_regex_toggle_and_invalid_patterns() {
+        let blocks = [command("ls", "a1 b22 c333")];
+        let mut search = BlockSearch::default();
+        search.set_query(r"\d{2,}".to_string(), &blocks);
+        assert_eq!(search.count(), 0);
+
+        search.set_regex(true, &blocks);
+        assert_eq!(search.highlights(blocks[0].id).iter().map(|h| h.range.clone()).collect::<Vec<_>>(), vec![4..6, 8..11]);
+
+        search.set_query("(".to_string(), &blocks);
+        assert!(search.error().is_some());
+        assert_eq!(search.count(), 0);
+    }
+
+    #[test]
+    fn test_only_changed_blocks_are_searched_again() {
+        let mut blocks = vec![command("make warnings", "warning: unused\n")];
+        let mut search = BlockSearch::default();
+        search.set_query("warning".to_string(), &blocks);
+        search.next();
+        assert_eq!(search.position(), 1);
+
+        // More output keeps the current match where it was
+        blocks[0].set_output("warning: unused\nwarning: deprecated\n".to_string(), 0);
+        blocks.push(command("echo warning", ""));
+        search.refresh(&blocks);
+        assert_eq!(search.count(), 4);
+        assert_eq!(search.current().map(|m| (m.field, m.line)), Some((MatchField::Output, 0)));
+
+        blocks.remove(0);
+        search.refresh(&blocks);
+        assert_eq!((search.count(), search.position()), (1, 0));
+    }
+}
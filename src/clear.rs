@@ -0,0 +1,216 @@
+//! Selectively clearing saved state, for `neoterm clear` and the palette.
+//! Each kind of state is cleared through whatever owns it, so in-memory
+//! copies and the files behind them stay consistent. Configuration (themes,
+//! workflows, keybindings, env profiles) is only touched when asked for.
+
+use crate::config::ConfigPaths;
+use crate::history::CommandHistory;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ClearError {
+    #[error("IO error: {0}")]
+    IoError(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ClearTarget {
+    History,
+    Blocks,
+    Conversations,
+    Caches,
+    PluginsData,
+    /// Everything above, plus workflow run records and crash reports
+    All,
+}
+
+impl ClearTarget {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            ClearTarget::History => "command history",
+            ClearTarget::Blocks => "saved blocks",
+            ClearTarget::Conversations => "agent conversations",
+            ClearTarget::Caches => "caches",
+            ClearTarget::PluginsData => "plugin data",
+            ClearTarget::All => "all saved data",
+        }
+    }
+}
+
+/// What was deleted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClearReport {
+    pub removed: Vec<PathBuf>,
+    pub freed_bytes: u64,
+}
+
+impl ClearReport {
+    pub fn summary(&self, target: ClearTarget) -> String {
+        if self.removed.is_empty() {
+            format!("No {} to clear", target.describe())
+        } else {
+            format!("Cleared {} ({} bytes)", target.describe(), self.freed_bytes)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Clearer {
+    paths: ConfigPaths,
+    include_config: bool,
+}
+
+impl Clearer {
+    /// `include_config` lets `All` remove configuration as well
+    pub fn new(paths: ConfigPaths, include_config: bool) -> Self {
+        Self { paths, include_config }
+    }
+
+    pub fn resolve(include_config: bool) -> Result<Self, ClearError> {
+        let paths = ConfigPaths::resolve().map_err(|e| ClearError::IoError(e.to_string()))?;
+        Ok(Self::new(paths, include_config))
+    }
+
+    /// The history store, for a `CommandHistory` that `clear` can empty
+    pub fn history_file(&self) -> PathBuf {
+        self.paths.history_file()
+    }
+
+    fn targets(&self, target: ClearTarget) -> Vec<PathBuf> {
+        let paths = &self.paths;
+        match target {
+            ClearTarget::History => vec![paths.history_file()],
+            ClearTarget::Blocks => vec![paths.sessions_dir()],
+            ClearTarget::Conversations => vec![paths.conversations_dir()],
+            ClearTarget::Caches => vec![paths.cache_dir().to_path_buf()],
+            ClearTarget::PluginsData => vec![paths.plugins_data_dir()],
+            ClearTarget::All => {
+                let mut all: Vec<PathBuf> = [
+                    ClearTarget::History,
+                    ClearTarget::Blocks,
+                    ClearTarget::Conversations,
+                    ClearTarget::Caches,
+                    ClearTarget::PluginsData,
+                ]
+                .into_iter()
+                .flat_map(|target| self.targets(target))
+                .collect();
+                all.extend([paths.workflow_runs_file(), paths.crash_reports_dir()]);
+                if self.include_config {
+                    all.extend([
+                        paths.config_file(),
+                        paths.themes_dir(),
+                        paths.workflows_dir(),
+                        paths.env_profiles_dir(),
+                        paths.profiles_dir(),
+                        paths.templates_dir(),
+                        paths.plugins_dir(),
+                    ]);
+                }
+                all
+            }
+        }
+    }
+
+    /// Files and directories `clear` would delete; only ones that exist
+    pub fn plan(&self, target: ClearTarget) -> Vec<PathBuf> {
+        self.targets(target).into_iter().filter(|path| path.exists()).collect()
+    }
+
+    /// Delete `target`'s files. History goes through `history`, so the
+    /// entries Up-arrow walks through are emptied along with the store.
+    pub fn clear(&self, target: ClearTarget, history: &mut CommandHistory) -> Result<ClearReport, ClearError> {
+        let mut report = ClearReport::default();
+        for path in self.plan(target) {
+            report.freed_bytes += size(&path);
+            remove(&path)?;
+            report.removed.push(path);
+        }
+        if matches!(target, ClearTarget::History | ClearTarget::All) {
+            history.clear().map_err(|e| ClearError::IoError(e.to_string()))?;
+        }
+        Ok(report)
+    }
+}
+
+fn size(path: &Path) -> u64 {
+    if path.is_dir() {
+        crate::workflows::dir_size(path)
+    } else {
+        std::fs::metadata(path).map(|meta| meta.len()).unwrap_or(0)
+    }
+}
+
+fn remove(path: &Path) -> Result<(), ClearError> {
+    let result = if path.is_dir() { std::fs::remove_dir_all(path) } else { std::fs::remove_file(path) };
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(ClearError::IoError(e.to_string())),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn populated() -> (TempDir, ConfigPaths) {
+        let temp_dir = TempDir::new().unwrap();
+        let paths = ConfigPaths::portable(temp_dir.path().to_path_buf());
+        std::fs::write(paths.history_file(), "ls\npwd\n").unwrap();
+        std::fs::write(paths.config_file(), "[preferences]\n").unwrap();
+        for dir in [paths.sessions_dir(), paths.workflow_cache_dir(), paths.themes_dir(), paths.workflows_dir()] {
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("data"), "x").unwrap();
+        }
+        (temp_dir, paths)
+    }
+
+    #[test]
+    fn test_clear_history_empties_store_and_memory() {
+        let (_temp_dir, paths) = populated();
+        let clearer = Clearer::new(paths.clone(), false);
+        let mut history = CommandHistory::new(Some(clearer.history_file()));
+        history.push("ls".to_string());
+        history.push("pwd".to_string());
+
+        let report = clearer.clear(ClearTarget::History, &mut history).unwrap();
+        assert_eq!(report.removed, vec![paths.history_file()]);
+        assert_eq!(report.freed_bytes, 7);
+        assert!(!paths.history_file().exists());
+        assert!(history.entries().is_empty());
+        assert_eq!(history.previous(), None);
+        // Nothing else was touched
+        assert!(paths.sessions_dir().exists());
+        assert!(paths.workflow_cache_dir().exists());
+    }
+
+    #[test]
+    fn test_all_keeps_configuration_unless_included() {
+        let (_temp_dir, paths) = populated();
+        let mut history = CommandHistory::new(Some(paths.history_file()));
+
+        Clearer::new(paths.clone(), false).clear(ClearTarget::All, &mut history).unwrap();
+        assert!(!paths.history_file().exists());
+        assert!(!paths.sessions_dir().exists());
+        assert!(!paths.workflow_cache_dir().exists());
+        assert!(paths.config_file().exists());
+        assert!(paths.themes_dir().join("data").exists());
+        assert!(paths.workflows_dir().join("data").exists());
+
+        let clearer = Clearer::new(paths.clone(), true);
+        assert_eq!(clearer.plan(ClearTarget::All), vec![paths.config_file(), paths.themes_dir(), paths.workflows_dir()]);
+        clearer.clear(ClearTarget::All, &mut history).unwrap();
+        assert!(!paths.config_file().exists());
+        assert!(!paths.themes_dir().exists());
+    }
+
+    #[test]
+    fn test_nothing_to_clear() {
+        let temp_dir = TempDir::new().unwrap();
+        let clearer = Clearer::new(ConfigPaths::portable(temp_dir.path().to_path_buf()), false);
+        let report = clearer.clear(ClearTarget::PluginsData, &mut CommandHistory::default()).unwrap();
+        assert_eq!(report, ClearReport::default());
+        assert_eq!(report.summary(ClearTarget::PluginsData), "No plugin data to clear");
+    }
+}
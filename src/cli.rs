@@ -0,0 +1,1672 @@
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use crate::workflows::{ConflictStrategy, Shell, WorkflowCache, WorkflowExecutor, WorkflowManager, DEFAULT_MAX_CACHE_BYTES};
+use crate::i18n::Locale;
+use crate::agent_mode_eval::availability::AiRequest;
+use crate::workflows::remediation::{self, Remediation, RunHistory, RunOutcome, StepFailure, StepOutcome, WorkflowRun};
+
+/// Command-line interface. Without a subcommand the GUI is started.
+#[derive(Debug, Parser)]
+#[command(name = "neoterm", version, about = "A modern terminal with blocks, workflows and agent mode")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
+    /// Directory to open in (shorthand for --cwd)
+    #[arg(value_name = "PATH", conflicts_with = "cwd")]
+    pub path: Option<PathBuf>,
+
+    /// Working directory to start in
+    #[arg(long, value_name = "DIR")]
+    pub cwd: Option<PathBuf>,
+
+    /// Command to run in the first pane as soon as the window is shown
+    #[arg(long, value_name = "COMMAND")]
+    pub run: Option<String>,
+
+    /// Named layout to open with
+    #[arg(long, value_name = "NAME")]
+    pub layout: Option<String>,
+
+    /// Open without running anything: commands, workflows and agent tools are disabled
+    #[arg(long)]
+    pub read_only: bool,
+
+    /// Keep all configuration, data and caches in DIR (also NEOTERM_CONFIG_DIR)
+    #[arg(long, value_name = "DIR", global = true)]
+    pub config_dir: Option<PathBuf>,
+}
+
+/// Initial GUI state taken from the command line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StartupOptions {
+    pub cwd: Option<PathBuf>,
+    pub run: Option<String>,
+    pub layout: Option<String>,
+    pub read_only: bool,
+}
+
+/// Subcommands whose one-line help is translated, by catalog key
+const SUBCOMMAND_ABOUT: &[(&str, &str)] = &[
+    ("workflow", "cli.workflow"),
+    ("exec", "cli.exec"),
+    ("doctor", "cli.doctor"),
+    ("config", "cli.config"),
+    ("crashes", "cli.crashes"),
+    ("maintenance", "cli.maintenance"),
+    ("clear", "cli.clear"),
+    ("learn", "cli.learn"),
+    ("which", "cli.which"),
+    ("export", "cli.export"),
+    ("ai", "cli.ai"),
+    ("plugin", "cli.plugin"),
+    ("jobs", "cli.jobs"),
+    ("kill", "cli.kill"),
+    ("preview", "cli.preview"),
+];
+
+impl Cli {
+    /// Parse the process arguments, with `--help` in the system language.
+    /// Configuration isn't readable yet (`--config-dir` is one of the
+    /// arguments), so the language comes from the environment.
+    pub fn parse_localized() -> Self {
+        let matches = Self::localized_command(Locale::from_env()).get_matches();
+        Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+    }
+
+    /// The clap command with the program and subcommand descriptions in
+    /// `locale`; argument help stays in English
+    pub fn localized_command(locale: Locale) -> clap::Command {
+        SUBCOMMAND_ABOUT.iter().fold(Self::command().about(locale.tr("cli.about")), |command, (name, key)| {
+            command.mut_subcommand(name, |subcommand| subcommand.about(locale.tr(*key)))
+        })
+    }
+
+    pub fn startup_options(&self) -> StartupOptions {
+        StartupOptions {
+            cwd: self.cwd.clone().or_else(|| self.path.clone()),
+            run: self.run.clone().filter(|command| !command.trim().is_empty()),
+            layout: self.layout.clone(),
+            read_only: self.read_only,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Run and manage workflows
+    Workflow {
+        #[command(subcommand)]
+        command: WorkflowCommand,
+    },
+    /// Run a command and report its output, optionally as structured events
+    Exec {
+        /// Command line, run with the default shell
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExecOutput::Text)]
+        output: ExecOutput,
+        /// With json or ndjson, also copy the raw output to stderr
+        #[arg(long)]
+        echo: bool,
+    },
+    /// Check the installation and configuration
+    Doctor,
+    /// Get, set, export and import configuration, migrate it and switch profiles
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Inspect locally saved crash reports
+    Crashes {
+        #[command(subcommand)]
+        command: CrashesCommand,
+    },
+    /// Prune run history, caches and crash reports to their retention limits
+    Maintenance {
+        #[command(subcommand)]
+        command: MaintenanceCommand,
+    },
+    /// Delete saved history, blocks, conversations, caches or plugin data
+    Clear {
+        #[arg(value_enum)]
+        target: crate::clear::ClearTarget,
+        /// Don't ask for confirmation
+        #[arg(long)]
+        yes: bool,
+        /// With `all`, also delete configuration, themes, workflows, env and config profiles and installed plugins
+        #[arg(long)]
+        include_config: bool,
+    },
+    /// Practise with a multiple-choice quiz on the bundled command templates
+    Learn {
+        /// Number of questions
+        #[arg(long, default_value_t = 5)]
+        count: usize,
+    },
+    /// Show every executable a command name resolves to across PATH
+    Which {
+        name: String,
+    },
+    /// Write a saved session's blocks to one Markdown or HTML document
+    Export {
+        #[arg(long, value_enum, default_value_t = crate::session_export::SessionFormat::Markdown)]
+        format: crate::session_export::SessionFormat,
+        /// File to write; standard output when omitted
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+        /// Saved session to export (defaults to the most recent one)
+        #[arg(long, value_name = "FILE")]
+        session: Option<PathBuf>,
+    },
+    /// Inspect AI providers and their models
+    Ai {
+        #[command(subcommand)]
+        command: AiCommand,
+    },
+    /// Install, list, enable and disable plugins
+    Plugin {
+        #[command(subcommand)]
+        command: PluginCommand,
+    },
+    /// List the commands open windows are running
+    Jobs,
+    /// Signal a running command by its block id
+    Kill {
+        /// Block id, or an unambiguous prefix of it
+        id: String,
+        #[arg(long, value_enum, default_value_t = crate::jobs::JobSignal::Kill)]
+        signal: crate::jobs::JobSignal,
+    },
+    /// Print a file highlighted with line numbers, or a hex dump of a binary one
+    Preview {
+        /// File, optionally with a line to show the lines around, as in src/main.rs:42
+        location: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PluginCommand {
+    /// Show bundled and installed plugins and whether they're enabled
+    List,
+    /// Install a plugin from a directory or .tar.gz archive holding a
+    /// plugin.toml, after agreeing to the permissions it declares
+    Install {
+        source: String,
+        /// Grant the declared permissions without asking
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Load the plugin from the next start
+    Enable {
+        id: String,
+    },
+    /// Stop loading the plugin, keeping it installed
+    Disable {
+        id: String,
+    },
+    /// Download and install newer versions from each plugin's registry index
+    Update {
+        /// Only this plugin
+        id: Option<String>,
+        /// Only report which updates are available
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AiCommand {
+    /// List the models each provider offers; Ollama's are asked from the server
+    Models,
+    /// Ask the assistant, approving its commands on the terminal
+    Chat {
+        #[arg(trailing_var_arg = true, required = true)]
+        prompt: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExecOutput {
+    /// Raw stdout and stderr, as the command printed them
+    Text,
+    /// One JSON object with the collected output once the command exits
+    Json,
+    /// One JSON event per line as output arrives (start, stdout, stderr, exit)
+    Ndjson,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print where configuration, data and caches are kept
+    Paths,
+    /// Copy data from an older location into the current one
+    Migrate {
+        /// Don't ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Convert an iTerm2 (.itermcolors) or Alacritty (.toml, .yml) color
+    /// scheme into a theme
+    ImportTheme {
+        path: PathBuf,
+    },
+    /// Write the whole config to one YAML file, without API keys and
+    /// passwords unless --include-secrets
+    Export {
+        /// File to write; standard output when omitted
+        path: Option<PathBuf>,
+        #[arg(long)]
+        include_secrets: bool,
+    },
+    /// Replace or merge the config with an exported YAML file, backing up
+    /// the current one first
+    Import {
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = crate::config::ImportStrategy::Merge)]
+        strategy: crate::config::ImportStrategy,
+    },
+    /// Print one setting by its dotted path, e.g. terminal.scrollback_lines
+    Get {
+        key: String,
+    },
+    /// Change one setting by its dotted path; a running terminal picks it up
+    Set {
+        key: String,
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+    },
+    /// Print every setting with its dotted path
+    List,
+    /// Save and switch between named sets of preferences and theme
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileCommand {
+    List,
+    /// Save the current preferences and theme under a name
+    Save { name: String },
+    /// Switch to a saved profile; key bindings and plugins stay as they are
+    Use { name: String },
+    Delete { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MaintenanceCommand {
+    /// Run maintenance now, whether or not it is due
+    Run {
+        /// Print what would be deleted without deleting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CrashesCommand {
+    /// List saved reports, newest first
+    List,
+    /// Print a report exactly as it would be sent
+    Show {
+        /// Report id, or an unambiguous prefix of it
+        id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WorkflowCommand {
+    /// Run a workflow by name
+    Run {
+        name: String,
+        /// Argument value as NAME=VALUE, repeatable
+        #[arg(long = "arg", value_parser = parse_key_value)]
+        args: Vec<(String, String)>,
+        /// Always run the command, ignoring and not updating the step cache
+        #[arg(long)]
+        no_cache: bool,
+        /// Let an imported workflow go beyond its declared permissions for this run (asks first)
+        #[arg(long)]
+        allow_undeclared: bool,
+        /// Don't ask for parameters missing from --arg; required ones without a default are an error
+        #[arg(long)]
+        non_interactive: bool,
+    },
+    /// Add a workflow from a file or URL, in our format or Warp's. Downloads
+    /// show the permissions they ask for first.
+    Import {
+        /// Path or http(s) URL
+        source: String,
+        /// Don't ask for confirmation
+        #[arg(long)]
+        yes: bool,
+        /// What to do if a workflow with the same name exists
+        #[arg(long, value_enum, default_value_t = ConflictStrategy::Rename)]
+        on_conflict: ConflictStrategy,
+    },
+    /// Write a workflow to a file in our format
+    Export {
+        name: String,
+        path: PathBuf,
+    },
+    /// Manage the workflow step cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// List, pause or run scheduled workflows
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ScheduleCommand {
+    /// Show when each scheduled workflow runs next
+    List,
+    /// Stop a workflow's scheduled runs until resumed
+    Pause { name: String },
+    Resume { name: String },
+    /// Run a scheduled workflow now, here
+    Run { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// Evict least-recently-used entries until the cache fits its size limit
+    Prune {
+        /// Size limit in megabytes (defaults to the configured limit)
+        #[arg(long)]
+        max_mb: Option<u64>,
+        /// Remove every entry
+        #[arg(long, conflicts_with = "max_mb")]
+        all: bool,
+    },
+}
+
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", s))
+}
+
+/// Run a subcommand and return the process exit code
+pub fn run(command: Commands) -> i32 {
+    let config = crate::config::AppConfig::load().unwrap_or_default();
+    crate::net::configure(&config.preferences.network);
+
+    let result = match command {
+        Commands::Workflow { command } => run_workflow_command(command, &config),
+        Commands::Learn { count } => run_learn(count),
+        Commands::Doctor => run_doctor(&config),
+        Commands::Config { command } => run_config_command(command),
+        Commands::Crashes { command } => run_crashes_command(command),
+        Commands::Maintenance { command } => run_maintenance_command(command, &config),
+        Commands::Clear { target, yes, include_config } => run_clear(target, yes, include_config),
+        Commands::Exec { command, output, echo } => run_exec(&command.join(" "), output, echo),
+        Commands::Which { name } => run_which(&name, &config),
+        Commands::Export { format, out, session } => run_export(format, out, session),
+        Commands::Ai { command } => run_ai_command(command, &config),
+        Commands::Plugin { command } => run_plugin_command(command, config),
+        Commands::Jobs => run_jobs(),
+        Commands::Kill { id, signal } => run_kill(&id, signal),
+        Commands::Preview { location } => run_preview(&location),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("neoterm: {}", e);
+            1
+        }
+    }
+}
+
+fn run_workflow_command(command: WorkflowCommand, config: &crate::config::AppConfig) -> Result<i32, Box<dyn std::error::Error>> {
+    match command {
+        WorkflowCommand::Run { name, args, no_cache, allow_undeclared, non_interactive } => {
+            let manager = WorkflowManager::new()?;
+            let workflow = manager
+                .get_workflow(&name)
+                .ok_or_else(|| crate::workflows::WorkflowError::WorkflowNotFound(name.clone()))?;
+
+            let mut executor = WorkflowExecutor::new(current_shell());
+            if !no_cache {
+                executor = executor.with_cache(WorkflowCache::new()?);
+            }
+            if allow_undeclared && !workflow.trust.is_full() {
+                print_permissions(workflow);
+                if !confirm(&format!("Run '{}' with full access to your account and files?", workflow.name))? {
+                    println!("Not run");
+                    return Ok(1);
+                }
+                executor = executor.allow_undeclared();
+            }
+
+            let mut args = args.into_iter().collect::<HashMap<_, _>>();
+            if !non_interactive && std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+                args = prompt_arguments(workflow, args)?;
+            }
+            let execution = executor.prepare_execution(workflow, args)?;
+            if !execution.steps.is_empty() {
+                let executor = match assistant(config, AiRequest::AgentPrompt) {
+                    Some(client) => executor.with_ai(client),
+                    None => executor,
+                };
+                return run_steps(&executor, &execution);
+            }
+            let mut redactor = crate::redaction::Redactor::new();
+            execution.register_secrets(&mut redactor);
+            let mut run = WorkflowRun::new(execution);
+
+            let runtime = tokio::runtime::Runtime::new()?;
+            // Remediation needs someone to pick an option
+            let interactive = std::io::IsTerminal::is_terminal(&std::io::stdin());
+            let assistant = interactive.then(|| assistant(config, AiRequest::SuggestFix)).flatten();
+
+            let exit_code = loop {
+                let (failure, result) = match runtime.block_on(run.run_step(&executor))? {
+                    StepOutcome::Succeeded(result) => {
+                        print_step_output(&result);
+                        break 0;
+                    }
+                    StepOutcome::Failed(failure, result) => (failure, result),
+                };
+                print_step_output(&result);
+                if !interactive {
+                    break result.output.exit_code;
+                }
+
+                let options = match &assistant {
+                    Some(client) => runtime
+                        .block_on(remediation::suggest(client, &failure, &redactor))
+                        .unwrap_or_else(|e| {
+                            eprintln!("Assistant unavailable ({}); offering the plain options", e);
+                            remediation::fallback_options()
+                        }),
+                    None => remediation::fallback_options(),
+                };
+                let suggested_by_ai = options != remediation::fallback_options();
+                let chosen = prompt_remediation(&failure, &options)?;
+                if !run.choose(chosen, suggested_by_ai) {
+                    break if run.record().outcome == RunOutcome::Skipped { 0 } else { result.output.exit_code };
+                }
+            };
+
+            if let Err(e) = RunHistory::new().and_then(|history| history.append(run.record())) {
+                eprintln!("Failed to record workflow run: {}", e);
+            }
+            Ok(exit_code)
+        }
+        WorkflowCommand::Import { source, yes, on_conflict } => {
+            let mut manager = WorkflowManager::new()?;
+            if !source.starts_with("http://") && !source.starts_with("https://") {
+                println!("{}", manager.import_workflow(std::path::Path::new(&source), on_conflict)?);
+                return Ok(0);
+            }
+
+            let runtime = tokio::runtime::Runtime::new()?;
+            let workflow = runtime.block_on(WorkflowManager::fetch_workflow(&source))?;
+            print_permissions(&workflow);
+            if !yes && !confirm(&format!("Import '{}'?", workflow.name))? {
+                println!("Not imported");
+                return Ok(1);
+            }
+            println!("{}", manager.add_imported(workflow, on_conflict)?);
+            Ok(0)
+        }
+        WorkflowCommand::Export { name, path } => {
+            WorkflowManager::new()?.export_workflow(&name, &path)?;
+            println!("Exported '{}' to {}", name, path.display());
+            Ok(0)
+        }
+        WorkflowCommand::Schedule { command } => run_schedule_command(command),
+        WorkflowCommand::Cache { command: CacheCommand::Prune { max_mb, all } } => {
+            let cache = WorkflowCache::new()?;
+            let limit = if all {
+                0
+            } else {
+                max_mb.map(|mb| mb * 1024 * 1024).unwrap_or(DEFAULT_MAX_CACHE_BYTES)
+            };
+
+            let report = cache.prune(limit)?;
+            println!(
+                "Removed {} cache entries ({:.1} MB freed)",
+                report.removed,
+                report.freed_bytes as f64 / (1024.0 * 1024.0)
+            );
+            Ok(0)
+        }
+    }
+}
+
+fn run_schedule_command(command: ScheduleCommand) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut manager = WorkflowManager::new()?;
+    match command {
+        ScheduleCommand::List => {
+            let runs = manager.upcoming_runs();
+            if runs.is_empty() {
+                println!("No workflows have a schedule");
+            }
+            for run in runs {
+                let next = match (run.paused, run.next_run) {
+                    (true, _) => "paused".to_string(),
+                    (false, Some(next)) => next.format("%Y-%m-%d %H:%M").to_string(),
+                    (false, None) => "never".to_string(),
+                };
+                println!("{:<17} {:<20} {}", next, run.schedule, run.name);
+            }
+            Ok(0)
+        }
+        ScheduleCommand::Pause { name } => {
+            manager.pause_schedule(&name, true)?;
+            println!("Paused '{}'", name);
+            Ok(0)
+        }
+        ScheduleCommand::Resume { name } => {
+            manager.pause_schedule(&name, false)?;
+            println!("Resumed '{}'", name);
+            Ok(0)
+        }
+        ScheduleCommand::Run { name } => {
+            let (_, workflow) = manager.trigger_now(&name)?;
+            let runtime = tokio::runtime::Runtime::new()?;
+            let (output, exit_code) = runtime.block_on(crate::workflows::run_in_background(
+                WorkflowExecutor::new(current_shell()),
+                workflow,
+                HashMap::new(),
+            ))?;
+            print!("{}", output);
+            Ok(exit_code)
+        }
+    }
+}
+
+fn print_permissions(workflow: &crate::workflows::Workflow) {
+    let permissions = workflow.permissions.list();
+    if permissions.is_empty() {
+        println!("'{}' declares no permissions, so it can't run anything until you allow it", workflow.name);
+        return;
+    }
+    println!("'{}' asks to:", workflow.name);
+    for permission in permissions {
+        println!("  {}", permission.describe());
+    }
+}
+
+/// Ask a yes/no question on the terminal; anything but `y` is no
+fn confirm(question: &str) -> std::io::Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::Write::flush(&mut std::io::stdout())?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case("y"))
+}
+
+/// Ask on stdin for each parameter not given with --arg. Enter keeps the
+/// default shown in brackets; a bad value is explained and asked again.
+fn prompt_arguments(
+    workflow: &crate::workflows::Workflow,
+    given: HashMap<String, String>,
+) -> std::io::Result<HashMap<String, String>> {
+    use std::io::Write;
+
+    let mut form = crate::workflows::ParameterForm::new(workflow.clone());
+    for argument in workflow.arguments.iter().filter(|argument| !given.contains_key(&argument.name)) {
+        let mut question = argument.name.clone();
+        if let Some(description) = &argument.description {
+            question = format!("{} ({})", question, description);
+        }
+        match (&argument.options, &argument.arg_type) {
+            (Some(options), _) => question = format!("{} {{{}}}", question, options.join(", ")),
+            (None, crate::workflows::ArgumentType::Boolean) => question = format!("{} {{yes, no}}", question),
+            _ => {}
+        }
+        loop {
+            match form.value(&argument.name) {
+                "" => eprint!("{}: ", question),
+                current => eprint!("{} [{}]: ", question, current),
+            }
+            std::io::stderr().flush()?;
+            let mut answer = String::new();
+            if std::io::stdin().read_line(&mut answer)? == 0 {
+                break;
+            }
+            if !answer.trim().is_empty() {
+                form.set(argument.name.clone(), answer.trim().to_string());
+            }
+            match form.error(argument) {
+                Some(error) => eprintln!("  {}", error),
+                None => break,
+            }
+        }
+    }
+
+    // --arg values win; any that aren't parameters are left for the executor to reject
+    let mut arguments = form.arguments();
+    arguments.extend(given);
+    Ok(arguments)
+}
+
+fn print_step_output(result: &crate::workflows::WorkflowExecutionResult) {
+    print!("{}", result.output.stdout);
+    eprint!("{}", result.output.stderr);
+    if let Some(original) = result.original_duration {
+        eprintln!("[cached] {} (originally took {})", result.workflow_name, crate::i18n::format_duration(original));
+    }
+}
+
+/// Run a workflow's steps, printing each as it starts and finishes
+fn run_steps(
+    executor: &WorkflowExecutor,
+    execution: &crate::workflows::WorkflowExecution,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::workflows::StepEvent;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let (tx, mut rx) = tokio::sync::mpsc::channel(32);
+    let report = runtime.block_on(async {
+        let printer = tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                match event {
+                    StepEvent::Started { name, .. } => eprintln!("▶ {}", name),
+                    StepEvent::Output { text, .. } => print!("{}", text),
+                    StepEvent::Finished { success: true, duration_ms, .. } => eprintln!("✓ done in {}ms", duration_ms),
+                    StepEvent::Finished { error, .. } => eprintln!("✗ {}", error.unwrap_or_default()),
+                    StepEvent::Continuing { .. } => eprintln!("  continuing (continue_on_error)"),
+                }
+            }
+        });
+        let report = executor.run_steps(execution, &tx).await;
+        drop(tx);
+        let _ = printer.await;
+        report
+    })?;
+
+    match report.stopped_at {
+        Some(index) => {
+            eprintln!("Stopped at step {} of {}", index + 1, execution.steps.len());
+            Ok(1)
+        }
+        None => Ok(0),
+    }
+}
+
+/// Client for `request`, if AI is configured and allowed. Like fix
+/// suggestions after a failed command, this never prompts for setup.
+fn assistant(config: &crate::config::AppConfig, request: AiRequest) -> Option<crate::agent_mode_eval::ai_client::AiClient> {
+    use crate::agent_mode_eval::availability::{AiGate, AiStatus, Gate};
+    use crate::agent_mode_eval::AgentConfig;
+
+    if config.preferences.privacy.incognito_mode {
+        return None;
+    }
+    let agent_config = AgentConfig::from_preferences(&config.preferences.ai, |name| std::env::var(name).ok());
+    match AiGate::new().check(&AiStatus::of(&agent_config), request) {
+        Gate::Proceed => crate::agent_mode_eval::ai_client::AiClient::new(agent_config).ok(),
+        Gate::Notice(_) | Gate::Skip => None,
+    }
+}
+
+/// Ask on the terminal until one of `options` is picked
+fn prompt_remediation(failure: &StepFailure, options: &[Remediation]) -> std::io::Result<Remediation> {
+    use std::io::Write;
+
+    eprintln!("\nStep failed with exit code {}: {}", failure.exit_code, failure.command);
+    for (i, option) in options.iter().enumerate() {
+        eprintln!("  [{}] {}", i + 1, option.label());
+        if let Some(diff) = option.diff(&failure.command) {
+            for line in diff.lines() {
+                eprintln!("        {}", line);
+            }
+        }
+    }
+
+    loop {
+        eprint!("Choose 1-{}: ", options.len());
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            return Ok(Remediation::Abort);
+        }
+        if let Some(option) = answer.trim().parse::<usize>().ok().and_then(|n| options.get(n.wrapping_sub(1))) {
+            return Ok(option.clone());
+        }
+    }
+}
+
+/// Exit status mirrors the child's
+fn run_exec(command: &str, output: ExecOutput, echo: bool) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::exec_events::{ExecEvent, ExecEventStream, ExecSummary};
+    use std::io::Write;
+
+    let cwd = std::env::current_dir()?.display().to_string();
+    let events = ExecEventStream::new(uuid::Uuid::new_v4(), chrono::Utc::now());
+
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let mut receiver = crate::shell::ShellManager::new().execute_command_streaming(command.to_string(), HashMap::new());
+        let mut stdout = std::io::stdout().lock();
+        let mut summary = ExecSummary::default();
+        let mut exit_code = 1;
+
+        let mut emit = |event: ExecEvent, stdout: &mut std::io::StdoutLock| -> std::io::Result<()> {
+            match (&event, output) {
+                (ExecEvent::Stdout { data, .. }, ExecOutput::Text) => stdout.write_all(data.as_bytes())?,
+                (ExecEvent::Stderr { data, .. }, ExecOutput::Text) => eprint!("{}", data),
+                (ExecEvent::Stdout { data, .. } | ExecEvent::Stderr { data, .. }, _) if echo => eprint!("{}", data),
+                _ => {}
+            }
+            match output {
+                ExecOutput::Ndjson => {
+                    stdout.write_all(event.to_ndjson().as_bytes())?;
+                    stdout.flush()?;
+                }
+                ExecOutput::Json => summary.record(&event),
+                ExecOutput::Text => {}
+            }
+            if let ExecEvent::Exit { code, .. } = event {
+                exit_code = code;
+            }
+            Ok(())
+        };
+
+        emit(events.start(command, &cwd), &mut stdout)?;
+        while let Some(event) = receiver.recv().await {
+            emit(events.convert(event), &mut stdout)?;
+        }
+
+        if output == ExecOutput::Json {
+            serde_json::to_writer_pretty(&mut stdout, &summary)?;
+            writeln!(stdout)?;
+        }
+        Ok::<_, Box<dyn std::error::Error>>(exit_code)
+    })
+}
+
+fn run_config_command(command: ConfigCommand) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::config::ConfigPaths;
+
+    let paths = ConfigPaths::resolve()?;
+    match command {
+        ConfigCommand::Paths => {
+            println!("config:     {}", paths.config_file().display());
+            println!("themes:     {}", paths.themes_dir().display());
+            println!("workflows:  {}", paths.workflows_dir().display());
+            println!("profiles:   {}", paths.env_profiles_dir().display());
+            println!("templates:  {}", paths.templates_dir().display());
+            println!("cache:      {}", paths.workflow_cache_dir().display());
+            Ok(0)
+        }
+        ConfigCommand::Migrate { yes } => {
+            let legacy = paths.legacy_data(|name| std::env::var(name).ok());
+            let Some(from) = legacy.first() else {
+                println!("Nothing to migrate; using {}", paths.root().display());
+                return Ok(0);
+            };
+
+            if !yes {
+                print!("Copy {} to {}? [y/N] ", from.display(), paths.root().display());
+                std::io::Write::flush(&mut std::io::stdout())?;
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    println!("Not migrated");
+                    return Ok(1);
+                }
+            }
+
+            let report = paths.migrate_from(from)?;
+            println!(
+                "Migrated {} files ({} bytes) to {}; {} was left in place",
+                report.files, report.bytes, paths.root().display(), from.display()
+            );
+            Ok(0)
+        }
+        ConfigCommand::ImportTheme { path } => {
+            let mut manager = crate::config::YamlThemeManager::new()?;
+            let imported = manager.import_scheme_file(&path)?;
+            println!(
+                "Imported \"{}\" into {}",
+                imported.theme.name.unwrap_or_default(),
+                paths.themes_dir().display()
+            );
+            if !imported.defaulted.is_empty() {
+                println!("Kept the default theme's colors for: {}", imported.defaulted.join(", "));
+            }
+            if !imported.skipped.is_empty() {
+                println!("Skipped: {}", imported.skipped.join(", "));
+            }
+            Ok(0)
+        }
+        ConfigCommand::Export { path, include_secrets } => {
+            let exported = crate::config::export_yaml(&crate::config::AppConfig::load()?, include_secrets)?;
+            match path {
+                Some(path) => {
+                    std::fs::write(&path, exported)?;
+                    eprintln!("Exported the config to {}", path.display());
+                }
+                None => print!("{}", exported),
+            }
+            Ok(0)
+        }
+        ConfigCommand::Import { path, strategy } => {
+            let content = std::fs::read_to_string(&path)?;
+            let config = crate::config::import_yaml(&content, &crate::config::AppConfig::load()?, strategy)?;
+            if let Some(backup) = crate::config::backup_config(&paths, chrono::Local::now())? {
+                println!("Backed up the previous config to {}", backup.display());
+            }
+            config.save()?;
+            println!("Imported {} into {}", path.display(), paths.config_file().display());
+            Ok(0)
+        }
+        ConfigCommand::Get { key } => {
+            match crate::config::keys::get(&crate::config::AppConfig::load()?.preferences, &key)? {
+                serde_json::Value::String(text) => println!("{}", text),
+                value => println!("{}", value),
+            }
+            Ok(0)
+        }
+        ConfigCommand::Set { key, value } => {
+            let mut config = crate::config::AppConfig::load()?;
+            config.preferences = crate::config::keys::set(&config.preferences, &key, &value)?;
+            config.save()?;
+            println!("{} = {}", key, crate::config::keys::get(&config.preferences, &key)?);
+            Ok(0)
+        }
+        ConfigCommand::List => {
+            for (key, value) in crate::config::keys::list(&crate::config::AppConfig::load()?.preferences)? {
+                println!("{} = {}", key, value);
+            }
+            Ok(0)
+        }
+        ConfigCommand::Profile { command } => run_profile_command(command),
+    }
+}
+
+fn run_profile_command(command: ProfileCommand) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::config::{AppConfig, ConfigProfile, ConfigProfileManager};
+
+    let manager = ConfigProfileManager::new()?;
+    match command {
+        ProfileCommand::List => {
+            let active = AppConfig::load()?.active_profile;
+            let names = manager.names();
+            if names.is_empty() {
+                println!("No config profiles; save one with `neoterm config profile save <name>`");
+            }
+            for name in names {
+                let marker = if active.as_deref() == Some(name.as_str()) { "*" } else { " " };
+                println!("{} {}", marker, name);
+            }
+            Ok(0)
+        }
+        ProfileCommand::Save { name } => {
+            let mut config = AppConfig::load()?;
+            manager.save(&name, &ConfigProfile::from_config(&config))?;
+            config.active_profile = Some(name.clone());
+            config.save()?;
+            println!("Saved the current preferences and theme as \"{}\"", name);
+            Ok(0)
+        }
+        ProfileCommand::Use { name } => {
+            let config = manager.load(&name)?.apply_to(&AppConfig::load()?, &name);
+            config.save()?;
+            println!("Switched to \"{}\"", name);
+            Ok(0)
+        }
+        ProfileCommand::Delete { name } => {
+            manager.delete(&name)?;
+            println!("Deleted \"{}\"", name);
+            Ok(0)
+        }
+    }
+}
+
+fn run_crashes_command(command: CrashesCommand) -> Result<i32, Box<dyn std::error::Error>> {
+    let dir = crate::config::ConfigPaths::resolve()?.crash_reports_dir();
+    match command {
+        CrashesCommand::List => {
+            let reports = crate::crash_reports::list(&dir);
+            if reports.is_empty() {
+                println!("No crash reports in {}", dir.display());
+            }
+            for report in reports {
+                let when: chrono::DateTime<chrono::Local> = report.event.timestamp.into();
+                println!(
+                    "{}  {}  {}",
+                    &report.id[..report.id.len().min(12)],
+                    when.format("%Y-%m-%d %H:%M"),
+                    report.event.message.as_deref().unwrap_or("(no message)")
+                );
+            }
+            Ok(0)
+        }
+        CrashesCommand::Show { id } => match crate::crash_reports::find(&dir, &id) {
+            Some(report) => {
+                println!("{}", std::fs::read_to_string(&report.path)?);
+                Ok(0)
+            }
+            None => {
+                eprintln!("neoterm: no single crash report matches '{}'", id);
+                Ok(1)
+            }
+        },
+    }
+}
+
+fn run_maintenance_command(
+    command: MaintenanceCommand,
+    config: &crate::config::AppConfig,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::maintenance::{Maintenance, Stores};
+
+    let MaintenanceCommand::Run { dry_run } = command;
+    let maintenance = Maintenance::new(Stores::resolve()?, config.preferences.maintenance.clone());
+    let report = maintenance.run(chrono::Utc::now(), dry_run)?;
+
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    for deletion in &report.deletions {
+        println!("{} {} ({} bytes)", verb, deletion.describe(), deletion.bytes());
+    }
+    if report.deletions.is_empty() {
+        println!("Nothing to remove");
+    } else if dry_run {
+        println!("{} item(s), {} bytes would be reclaimed", report.deletions.len(), report.freed_bytes());
+    } else {
+        println!("{}", report.summary());
+    }
+    Ok(0)
+}
+
+fn run_clear(
+    target: crate::clear::ClearTarget,
+    yes: bool,
+    include_config: bool,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::clear::Clearer;
+    use crate::history::CommandHistory;
+
+    let clearer = Clearer::resolve(include_config)?;
+    let plan = clearer.plan(target);
+    if plan.is_empty() {
+        println!("No {} to clear", target.describe());
+        return Ok(0);
+    }
+
+    if !yes {
+        println!("This deletes:");
+        for path in &plan {
+            println!("  {}", path.display());
+        }
+        if !confirm(&format!("Clear {}?", target.describe()))? {
+            println!("Nothing was cleared");
+            return Ok(1);
+        }
+    }
+
+    let mut history = CommandHistory::new(Some(clearer.history_file()));
+    let report = clearer.clear(target, &mut history)?;
+    println!("{}", report.summary(target));
+    Ok(0)
+}
+
+/// PATH as commands see it: the active env profile's, if it sets one
+fn effective_path(config: &crate::config::AppConfig) -> std::ffi::OsString {
+    config
+        .active_env_profile
+        .as_ref()
+        .and_then(|name| {
+            let manager = crate::config::EnvProfileManager::new().ok()?;
+            manager.get_profile(name)?.variables.get("PATH").cloned()
+        })
+        .map(std::ffi::OsString::from)
+        .or_else(|| std::env::var_os("PATH"))
+        .unwrap_or_default()
+}
+
+fn run_which(name: &str, config: &crate::config::AppConfig) -> Result<i32, Box<dyn std::error::Error>> {
+    let resolution = crate::path_inspector::resolve_all(name, &effective_path(config));
+    let Some(resolved) = resolution.resolved() else {
+        eprintln!("{} not found in PATH", name);
+        return Ok(1);
+    };
+    let shadowed = resolution.shadowed();
+    for path in &resolution.matches {
+        let role = if path == resolved {
+            "runs"
+        } else if shadowed.contains(&path.as_path()) {
+            "shadowed"
+        } else {
+            "same file"
+        };
+        let target = path
+            .canonicalize()
+            .ok()
+            .filter(|target| target != path)
+            .map(|target| format!(" -> {}", target.display()))
+            .unwrap_or_default();
+        println!("{:<9} {}{}", role, path.display(), target);
+    }
+    Ok(0)
+}
+
+fn run_jobs() -> Result<i32, Box<dyn std::error::Error>> {
+    let jobs = crate::jobs::list(&crate::config::ConfigPaths::resolve()?.jobs_dir());
+    if jobs.is_empty() {
+        println!("No running commands");
+    }
+    let now = chrono::Utc::now();
+    for job in jobs {
+        let id = job.block_id.to_string();
+        println!("{}  {}  {}", &id[..8], job.summary(crate::jobs::usage(job.pid), now), job.command);
+    }
+    Ok(0)
+}
+
+fn run_kill(id: &str, signal: crate::jobs::JobSignal) -> Result<i32, Box<dyn std::error::Error>> {
+    let jobs = crate::jobs::list(&crate::config::ConfigPaths::resolve()?.jobs_dir());
+    let Some(job) = crate::jobs::find(&jobs, id) else {
+        eprintln!("neoterm: no single running command matches '{}'", id);
+        return Ok(1);
+    };
+    signal.send(job.pid)?;
+    println!("{} sent to {} (pid {})", signal.verb(), job.command, job.pid);
+    Ok(0)
+}
+
+/// Lines printed before and after the line `neoterm preview` is given
+const PREVIEW_CONTEXT: usize = 10;
+
+fn run_preview(location: &str) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::file_preview::{FilePreview, PreviewContent};
+
+    let (path, line) = crate::file_preview::parse_location(location);
+    let preview = FilePreview::read(std::path::Path::new(path), line).map_err(|e| format!("cannot preview {}: {}", path, e))?;
+    let color = std::io::IsTerminal::is_terminal(&std::io::stdout());
+    let lines = match &preview.content {
+        PreviewContent::Text(lines) if color => lines.clone(),
+        _ => preview.plain_lines(),
+    };
+    if let PreviewContent::Binary(_) = preview.content {
+        println!("{}", lines.join("\n"));
+    } else {
+        let shown = match line {
+            Some(line) => line.saturating_sub(PREVIEW_CONTEXT + 1)..(line + PREVIEW_CONTEXT).min(lines.len()),
+            None => 0..lines.len(),
+        };
+        let width = shown.end.to_string().len();
+        for index in shown {
+            let marker = if line == Some(index + 1) { '▶' } else { ' ' };
+            let reset = if color { "\x1b[0m" } else { "" };
+            println!("{:>width$}{} {}{}", index + 1, marker, lines[index], reset, width = width);
+        }
+    }
+    if preview.truncated {
+        eprintln!("(only the start of the file is shown; it is {})", crate::dir_listing::format_size(preview.size));
+    }
+    Ok(0)
+}
+
+fn run_export(
+    format: crate::session_export::SessionFormat,
+    out: Option<PathBuf>,
+    session: Option<PathBuf>,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::session_export;
+
+    let session = match session {
+        Some(path) => path,
+        None => match session_export::latest(&crate::config::ConfigPaths::resolve()?.sessions_dir()) {
+            Some(path) => path,
+            None => {
+                eprintln!("No saved session to export");
+                return Ok(1);
+            }
+        },
+    };
+    let blocks = session_export::load(&session)?;
+    match out {
+        Some(path) => {
+            session_export::export_to(&blocks, format, &path)?;
+            println!("Exported {} blocks to {}", blocks.len(), path.display());
+        }
+        None => print!("{}", session_export::export(&blocks, format)),
+    }
+    Ok(0)
+}
+
+fn run_plugin_command(command: PluginCommand, mut config: crate::config::AppConfig) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::plugin_api::{self, update, PluginError, PluginManager};
+
+    let manager = PluginManager::new()?;
+    let state = |id: &str, config: &crate::config::AppConfig| {
+        if config.plugins.enabled_plugins.iter().any(|enabled| enabled == id) { "enabled" } else { "disabled" }
+    };
+    match command {
+        PluginCommand::List => {
+            for plugin in plugin_api::builtins() {
+                println!("{:<20} {:<10} {:<8} {}", plugin.name(), "built-in", "", state(plugin.name(), &config));
+            }
+            for plugin in manager.installed()? {
+                match plugin.manifest {
+                    Ok(manifest) => {
+                        println!(
+                            "{:<20} {:<10} {:<8} {}  {}",
+                            manifest.id,
+                            manifest.version,
+                            manifest.plugin_type,
+                            state(&manifest.id, &config),
+                            manifest.name
+                        );
+                        if !manifest.permissions.is_empty() {
+                            let grants = &config.plugins.granted_permissions;
+                            let permissions: Vec<String> = manifest
+                                .permissions
+                                .iter()
+                                .map(|&permission| match grants.is_granted(&manifest.id, permission) {
+                                    true => permission.to_string(),
+                                    false => format!("{} (revoked)", permission),
+                                })
+                                .collect();
+                            println!("{:<20} permissions: {}", "", permissions.join(", "));
+                        }
+                    }
+                    Err(e) => println!("{:<20} {}", plugin.dir.display(), e),
+                }
+            }
+            Ok(0)
+        }
+        PluginCommand::Install { source, yes } => {
+            let manifest = manager.install_plugin(&source, |manifest| {
+                if manifest.permissions.is_empty() {
+                    return true;
+                }
+                println!("{} {} asks to:", manifest.name, manifest.version);
+                for permission in &manifest.permissions {
+                    println!("  • {}", permission.description());
+                }
+                yes || (std::io::IsTerminal::is_terminal(&std::io::stdin())
+                    && confirm("Install it and grant these permissions?").unwrap_or(false))
+            })?;
+            config.plugins.granted_permissions.grant(&manifest.id, manifest.permissions.iter().copied());
+            config.save()?;
+            println!("Installed {} {} ({}); enable it with `neoterm plugin enable {}`", manifest.name, manifest.version, manifest.id, manifest.id);
+            Ok(0)
+        }
+        PluginCommand::Enable { id } => {
+            if !plugin_api::builtins().iter().any(|plugin| plugin.name() == id) {
+                manager.get(&id)?;
+            }
+            if !config.plugins.enabled_plugins.contains(&id) {
+                config.plugins.enabled_plugins.push(id.clone());
+                config.save()?;
+            }
+            println!("Enabled {}", id);
+            Ok(0)
+        }
+        PluginCommand::Disable { id } => {
+            if config.plugins.enabled_plugins.contains(&id) {
+                config.plugins.enabled_plugins.retain(|enabled| enabled != &id);
+                config.save()?;
+            }
+            println!("Disabled {}", id);
+            Ok(0)
+        }
+        PluginCommand::Update { id, check } => {
+            let mut manifests: Vec<_> = manager.installed()?.into_iter().filter_map(|plugin| plugin.manifest.ok()).collect();
+            if let Some(id) = &id {
+                manifests.retain(|manifest| &manifest.id == id);
+                if manifests.is_empty() {
+                    return Err(PluginError::NotInstalled(id.clone()).into());
+                }
+            }
+
+            let runtime = tokio::runtime::Runtime::new()?;
+            let (mut found, mut failed) = (false, false);
+            for (plugin, result) in runtime.block_on(update::check_updates(manifests.clone())) {
+                let update = match result {
+                    Ok(Some(update)) => update,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        eprintln!("{}: {}", plugin, e);
+                        failed = true;
+                        continue;
+                    }
+                };
+                found = true;
+                println!("{} {} → {}", update.id, update.installed, update.latest.version);
+                if check {
+                    continue;
+                }
+                match runtime.block_on(update::download(&update)).and_then(|archive| manager.update_plugin(&update.id, &archive)) {
+                    Ok(manifest) => {
+                        println!("  Updated");
+                        // New permissions aren't granted by updating
+                        let before = manifests.iter().find(|old| old.id == manifest.id).map(|old| old.permissions.clone()).unwrap_or_default();
+                        let added: Vec<_> = manifest.permissions.iter().filter(|permission| !before.contains(permission)).collect();
+                        if !added.is_empty() {
+                            println!("  It now also asks to do the following; allow it in Settings → Plugins:");
+                            for permission in added {
+                                println!("    • {}", permission.description());
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("  {}", e);
+                        failed = true;
+                    }
+                }
+            }
+            if !found && !failed {
+                println!("All plugins are up to date");
+            }
+            Ok(if failed { 1 } else { 0 })
+        }
+    }
+}
+
+fn run_ai_command(command: AiCommand, config: &crate::config::AppConfig) -> Result<i32, Box<dyn std::error::Error>> {
+    match command {
+        AiCommand::Models => run_ai_models(config),
+        AiCommand::Chat { prompt } => run_ai_chat(&prompt.join(" "), config),
+    }
+}
+
+fn run_ai_models(config: &crate::config::AppConfig) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::agent_mode_eval::ai_client::AiProvider;
+    use crate::agent_mode_eval::availability;
+    use crate::agent_mode_eval::AgentConfig;
+
+    let ai = &config.preferences.ai;
+    let mut code = 0;
+    for provider in AiProvider::ALL {
+        println!("{}:", provider);
+        if provider != AiProvider::Ollama {
+            for model in AgentConfig::get_available_models(&provider) {
+                println!("  {}", model);
+            }
+            continue;
+        }
+        // The configured server when Ollama is the chosen provider
+        let base_url = ai
+            .base_url
+            .clone()
+            .filter(|_| ai.provider == Some(AiProvider::Ollama))
+            .or_else(|| AgentConfig::get_default_base_url(&provider).map(str::to_string))
+            .unwrap_or_default();
+        match tokio::runtime::Runtime::new()?.block_on(availability::ollama_models(&base_url)) {
+            Ok(models) if models.is_empty() => println!("  (none pulled; try `ollama pull llama3`)"),
+            Ok(models) => {
+                for model in models {
+                    println!("  {}", model);
+                }
+            }
+            Err(error) => {
+                eprintln!("  {}", error);
+                code = 1;
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// One prompt, answered until the assistant stops calling tools. Each command
+/// or file write is shown and confirmed first unless the approval preference
+/// says otherwise.
+fn run_ai_chat(prompt: &str, config: &crate::config::AppConfig) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::agent_mode_eval::availability::AiStatus;
+    use crate::agent_mode_eval::tools::{Approval, ToolResult, DENIED_BY_USER};
+    use crate::agent_mode_eval::{AgentConfig, AgentMessage, AgentMode, ToolRound, MAX_TOOL_ROUNDS};
+
+    let agent_config = AgentConfig::from_preferences(&config.preferences.ai, |name| std::env::var(name).ok());
+    let status = AiStatus::of(&agent_config);
+    if !status.is_ready() {
+        eprintln!("{}", status);
+        return Ok(1);
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut agent = AgentMode::new(agent_config)?;
+    agent.start_conversation()?;
+    agent.push_user_message(prompt.to_string())?;
+
+    for _ in 0..MAX_TOOL_ROUNDS {
+        if let Err(e) = runtime.block_on(agent.compact()) {
+            eprintln!("Could not summarize earlier messages: {}", e);
+        }
+        let mut events = runtime.block_on(agent.respond())?;
+        let mut reply = String::new();
+        let mut round = ToolRound::default();
+        while let Some(event) = runtime.block_on(events.recv()) {
+            match event {
+                AgentMessage::AssistantDelta(chunk) => {
+                    print!("{}", chunk);
+                    std::io::Write::flush(&mut std::io::stdout())?;
+                    reply.push_str(&chunk);
+                }
+                AgentMessage::ToolCall(call) => round.push_call(call),
+                AgentMessage::Error(error) => {
+                    eprintln!("\nAgent error: {}", error);
+                    return Ok(1);
+                }
+                AgentMessage::Done => break,
+                _ => {}
+            }
+        }
+        println!();
+
+        if round.is_empty() {
+            agent.record_assistant_reply(reply)?;
+            return Ok(0);
+        }
+        for call in round.calls().to_vec() {
+            let result = match agent.approval(&call) {
+                Approval::Run => None,
+                Approval::Ask => {
+                    println!("The assistant wants to use `{}`:\n{}", call.name, call.describe());
+                    if confirm("Allow?")? {
+                        None
+                    } else {
+                        Some(ToolResult::error(&call, DENIED_BY_USER))
+                    }
+                }
+                Approval::Deny(reason) => Some(ToolResult::error(&call, &reason)),
+            };
+            let result = match result {
+                Some(result) => result,
+                None => runtime
+                    .block_on(agent.execute_tool_call(call.clone()))
+                    .unwrap_or_else(|e| ToolResult::error(&call, &e.to_string())),
+            };
+            match &result.error {
+                Some(error) => eprintln!("[{}] {}", call.name, error),
+                None => println!("{}", result.output),
+            }
+            round.resolve(result);
+        }
+        agent.record_tool_round(reply, round)?;
+    }
+    eprintln!("Stopped after {} rounds of tool calls", MAX_TOOL_ROUNDS);
+    Ok(1)
+}
+
+fn run_doctor(config: &crate::config::AppConfig) -> Result<i32, Box<dyn std::error::Error>> {
+    use crate::agent_mode_eval::availability::{self, AiStatus};
+    use crate::agent_mode_eval::AgentConfig;
+
+    let status = AiStatus::of(&AgentConfig::from_preferences(&config.preferences.ai, |name| std::env::var(name).ok()));
+    let ollama_url = AgentConfig::ollama().base_url.unwrap_or_default();
+    let ollama = tokio::runtime::Runtime::new()?.block_on(availability::detect_ollama(&ollama_url));
+
+    let (mark, detail) = match (&status, ollama) {
+        (AiStatus::Ready { .. }, _) => ("ok", status.to_string()),
+        (AiStatus::Unconfigured { .. }, true) => (
+            "warn",
+            format!("{}; a local Ollama daemon is running at {} (use `/ai use-ollama`)", status, ollama_url),
+        ),
+        (AiStatus::Unconfigured { .. }, false) => ("warn", format!("{}; AI features are disabled", status)),
+    };
+    println!("[{:>4}] AI: {}", mark, detail);
+
+    let paths = crate::config::ConfigPaths::resolve()?;
+    println!("[{:>4}] Config: {}", "ok", paths.root().display());
+    for legacy in paths.legacy_data(|name| std::env::var(name).ok()) {
+        println!("[{:>4}] Config: data found in {}; run `neoterm config migrate`", "warn", legacy.display());
+    }
+
+    let path_var = effective_path(config);
+    let issues = crate::path_inspector::hygiene(&path_var);
+    if issues.is_empty() {
+        println!("[{:>4}] PATH: {} entries, no problems found", "ok", std::env::split_paths(&path_var).count());
+    }
+    for issue in issues {
+        println!("[{:>4}] PATH: {}", "warn", issue);
+    }
+
+    // Warnings are informational; only failures would make this non-zero
+    Ok(0)
+}
+
+fn run_learn(count: usize) -> Result<i32, Box<dyn std::error::Error>> {
+    use std::io::BufRead;
+
+    let resources = crate::resources::ResourceManager::load();
+    let questions = crate::mcq::quiz_from_templates(resources.templates(), count, &mut rand::thread_rng());
+    let mut lines = std::io::stdin().lock().lines();
+    let mut score = 0;
+
+    for (number, question) in questions.iter().enumerate() {
+        println!("\n{}. {}", number + 1, question.prompt);
+        for (index, choice) in question.choices.iter().enumerate() {
+            println!("   {}) {}", (b'a' + index as u8) as char, choice);
+        }
+        print!("> ");
+        std::io::Write::flush(&mut std::io::stdout())?;
+
+        let Some(line) = lines.next().transpose()? else { break };
+        let picked = line.trim().chars().next().map(|c| (c.to_ascii_lowercase() as usize).wrapping_sub('a' as usize));
+        if picked.is_some_and(|choice| question.is_correct(choice)) {
+            score += 1;
+            println!("Correct");
+        } else {
+            println!("The answer was: {}", question.choices[question.answer]);
+        }
+    }
+
+    println!("\nScore: {}/{}", score, questions.len());
+    Ok(0)
+}
+
+/// The user's shell, for quoting workflow and template values
+pub(crate) fn current_shell() -> Shell {
+    std::env::var("SHELL")
+        .ok()
+        .and_then(|path| path.rsplit('/').next().map(str::to_string))
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Shell::Bash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_targets_parse() {
+        let cli = Cli::try_parse_from(["neoterm", "clear", "plugins-data", "--yes"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Clear { target: crate::clear::ClearTarget::PluginsData, yes: true, include_config: false })
+        ));
+        assert!(Cli::try_parse_from(["neoterm", "clear", "everything"]).is_err());
+    }
+
+    #[test]
+    fn test_startup_flags() {
+        let cli = Cli::try_parse_from([
+            "neoterm", "--cwd", "/tmp/proj", "--run", "cargo watch -x test", "--layout", "two-pane", "--read-only",
+        ])
+        .unwrap();
+
+        assert!(cli.command.is_none());
+        assert_eq!(cli.startup_options(), StartupOptions {
+            cwd: Some(PathBuf::from("/tmp/proj")),
+            run: Some("cargo watch -x test".to_string()),
+            layout: Some("two-pane".to_string()),
+            read_only: true,
+        });
+    }
+
+    #[test]
+    fn test_positional_path_is_cwd_shorthand() {
+        let cli = Cli::try_parse_from(["neoterm", "."]).unwrap();
+        assert_eq!(cli.startup_options().cwd, Some(PathBuf::from(".")));
+
+        assert!(Cli::try_parse_from(["neoterm", ".", "--cwd", "/tmp"]).is_err());
+    }
+
+    #[test]
+    fn test_subcommands_still_parse() {
+        let cli = Cli::try_parse_from(["neoterm", "workflow", "run", "build", "--arg", "target=x86"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Workflow { command: WorkflowCommand::Run { ref name, .. } }) if name == "build"
+        ));
+        assert_eq!(cli.startup_options(), StartupOptions::default());
+
+        let cli = Cli::try_parse_from(["neoterm", "workflow", "run", "tidy", "--allow-undeclared"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Workflow { command: WorkflowCommand::Run { allow_undeclared: true, non_interactive: false, .. } })
+        ));
+        let cli = Cli::try_parse_from(["neoterm", "workflow", "run", "deploy", "--non-interactive", "--arg", "tag=v1"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Workflow { command: WorkflowCommand::Run { non_interactive: true, .. } })
+        ));
+        let cli = Cli::try_parse_from(["neoterm", "workflow", "import", "https://example.com/tidy.yaml", "--yes"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Workflow { command: WorkflowCommand::Import { yes: true, on_conflict: ConflictStrategy::Rename, .. } })
+        ));
+        let cli = Cli::try_parse_from(["neoterm", "workflow", "import", "warp/kill.yaml", "--on-conflict", "skip"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Workflow { command: WorkflowCommand::Import { on_conflict: ConflictStrategy::Skip, .. } })
+        ));
+    }
+
+    #[test]
+    fn test_exec_takes_trailing_command() {
+        let cli = Cli::try_parse_from(["neoterm", "exec", "--output", "ndjson", "--echo", "ls", "-la", "/tmp"]).unwrap();
+        match cli.command {
+            Some(Commands::Exec { command, output, echo }) => {
+                assert_eq!(command, vec!["ls", "-la", "/tmp"]);
+                assert_eq!(output, ExecOutput::Ndjson);
+                assert!(echo);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_dir_is_global() {
+        let cli = Cli::try_parse_from(["neoterm", "config", "migrate", "--yes", "--config-dir", "/portable"]).unwrap();
+        assert_eq!(cli.config_dir, Some(PathBuf::from("/portable")));
+        assert!(matches!(cli.command, Some(Commands::Config { command: ConfigCommand::Migrate { yes: true } })));
+    }
+
+    #[test]
+    fn test_config_import_theme_parses() {
+        let cli = Cli::try_parse_from(["neoterm", "config", "import-theme", "Solarized Dark.itermcolors"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config { command: ConfigCommand::ImportTheme { ref path } })
+                if *path == PathBuf::from("Solarized Dark.itermcolors")
+        ));
+        assert!(Cli::try_parse_from(["neoterm", "config", "import-theme"]).is_err());
+    }
+
+    #[test]
+    fn test_config_export_and_import_parse() {
+        let cli = Cli::try_parse_from(["neoterm", "config", "export", "--include-secrets"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config { command: ConfigCommand::Export { path: None, include_secrets: true } })
+        ));
+        let cli = Cli::try_parse_from(["neoterm", "config", "import", "neoterm.yaml", "--strategy", "replace"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config {
+                command: ConfigCommand::Import { strategy: crate::config::ImportStrategy::Replace, .. }
+            })
+        ));
+        let cli = Cli::try_parse_from(["neoterm", "config", "profile", "use", "work"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config { command: ConfigCommand::Profile { command: ProfileCommand::Use { ref name } } })
+                if name == "work"
+        ));
+    }
+
+    #[test]
+    fn test_config_set_takes_negative_values() {
+        let cli = Cli::try_parse_from(["neoterm", "config", "set", "ui.transparency", "-1"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config { command: ConfigCommand::Set { ref key, ref value } })
+                if key == "ui.transparency" && value == "-1"
+        ));
+        assert!(Cli::try_parse_from(["neoterm", "config", "set", "ui.transparency"]).is_err());
+    }
+
+    #[test]
+    fn test_blank_run_is_ignored() {
+        let cli = Cli::try_parse_from(["neoterm", "--run", "  "]).unwrap();
+        assert_eq!(cli.startup_options().run, None);
+    }
+
+    #[test]
+    fn test_which_parses() {
+        let cli = Cli::try_parse_from(["neoterm", "which", "python"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Which { ref name }) if name == "python"));
+        assert!(Cli::try_parse_from(["neoterm", "which"]).is_err());
+    }
+
+    #[test]
+    fn test_export_parses() {
+        let cli = Cli::try_parse_from(["neoterm", "export", "--format", "html", "--out", "session.html"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Export { format: crate::session_export::SessionFormat::Html, out: Some(_), session: None })
+        ));
+        let cli = Cli::try_parse_from(["neoterm", "export"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Export { format: crate::session_export::SessionFormat::Markdown, out: None, .. })
+        ));
+    }
+
+    #[test]
+    fn test_job_commands_parse() {
+        let cli = Cli::try_parse_from(["neoterm", "kill", "a1b2"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Kill { ref id, signal: crate::jobs::JobSignal::Kill }) if id == "a1b2"));
+        let cli = Cli::try_parse_from(["neoterm", "kill", "a1b2", "--signal", "stop"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Kill { signal: crate::jobs::JobSignal::Stop, .. })));
+        assert!(matches!(Cli::try_parse_from(["neoterm", "jobs"]).unwrap().command, Some(Commands::Jobs)));
+        let cli = Cli::try_parse_from(["neoterm", "preview", "src/main.rs:42"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Preview { ref location }) if location == "src/main.rs:42"));
+    }
+
+    #[test]
+    fn test_plugin_commands_parse() {
+        let cli = Cli::try_parse_from(["neoterm", "plugin", "install", "./git-graph-0.3.1.tar.gz"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Plugin { command: PluginCommand::Install { ref source, yes: false } }) if source == "./git-graph-0.3.1.tar.gz"
+        ));
+        let cli = Cli::try_parse_from(["neoterm", "plugin", "disable", "git-graph"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Plugin { command: PluginCommand::Disable { .. } })));
+        assert!(Cli::try_parse_from(["neoterm", "plugin", "enable"]).is_err());
+        let cli = Cli::try_parse_from(["neoterm", "plugin", "update", "--check"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Plugin { command: PluginCommand::Update { id: None, check: true } })));
+    }
+
+    #[test]
+    fn test_help_is_localized() {
+        let command = Cli::localized_command(Locale::Es);
+        command.clone().debug_assert();
+        assert_eq!(command.get_about().unwrap().to_string(), Locale::Es.tr("cli.about"));
+        for (name, key) in SUBCOMMAND_ABOUT {
+            let subcommand = command.find_subcommand(name).unwrap();
+            assert_eq!(subcommand.get_about().unwrap().to_string(), Locale::Es.tr(*key));
+        }
+        // Every subcommand has a translated description
+        assert_eq!(command.get_subcommands().count(), SUBCOMMAND_ABOUT.len());
+    }
+}
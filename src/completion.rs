@@ -0,0 +1,268 @@
+//! Tab completion in the input bar: executables from PATH for the first
+//! word of a command, files and directories for the words after it.
+//!
+//! Only the word before the cursor (the end of the input) is completed.
+//! Candidates are ranked by fuzzy score with prefix matches first, and are
+//! inserted backslash-escaped so names with spaces stay one argument.
+//! Directories end in `/`, so the next Tab continues inside them.
+
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Candidates shown under the input
+pub const MAX_CANDIDATES: usize = 10;
+
+/// Characters the shell would otherwise split or interpret
+const SPECIAL: &[char] = &[' ', '\t', '\'', '"', '\\', '$', '`', '&', '|', ';', '<', '>', '(', ')', '*', '?', '[', ']', '#', '!', '{', '}'];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// As shown in the popup
+    pub label: String,
+    /// As inserted, escaped
+    pub replacement: String,
+    pub is_dir: bool,
+}
+
+/// Completions for the word at the end of an input line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completions {
+    /// Byte offset where the completed word starts
+    pub start: usize,
+    pub candidates: Vec<Candidate>,
+    /// Candidate currently in the input; `None` before the first Tab cycles
+    pub selected: Option<usize>,
+    /// The input as typed, before any candidate was put in
+    pub typed: String,
+}
+
+impl Completions {
+    /// The input with `index` in place of the completed word. A lone file
+    /// completion gets a space after it, ready for the next argument.
+    pub fn apply(&self, index: usize) -> String {
+        let candidate = &self.candidates[index];
+        let mut input = format!("{}{}", &self.typed[..self.start], candidate.replacement);
+        if self.candidates.len() == 1 && !candidate.is_dir {
+            input.push(' ');
+        }
+        input
+    }
+
+    /// Move to the next (or previous) candidate, wrapping around
+    pub fn cycle(&mut self, backwards: bool) -> usize {
+        let count = self.candidates.len();
+        let next = match (self.selected, backwards) {
+            (None, false) => 0,
+            (None, true) => count - 1,
+            (Some(i), false) => (i + 1) % count,
+            (Some(i), true) => (i + count - 1) % count,
+        };
+        self.selected = Some(next);
+        next
+    }
+}
+
+/// Where the last word of `input` starts, whether it's the command name,
+/// and its text with quotes and escapes removed
+fn last_word(input: &str) -> (usize, bool, String) {
+    let mut start = 0;
+    let mut first = true;
+    let mut word = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '\\') => {
+                if let Some((_, escaped)) = chars.next() {
+                    word.push(escaped);
+                }
+            }
+            (None, c) if c.is_whitespace() || matches!(c, '|' | ';' | '&' | '(') => {
+                if !word.is_empty() {
+                    first = false;
+                }
+                // A new command starts after a separator
+                if matches!(c, '|' | ';' | '&' | '(') {
+                    first = true;
+                }
+                word.clear();
+                start = i + c.len_utf8();
+            }
+            (None, c) => word.push(c),
+        }
+    }
+    (start, first, word)
+}
+
+/// Completions for the end of `input`, or `None` when nothing matches
+pub fn complete(input: &str, cwd: &Path, path_var: &OsStr) -> Option<Completions> {
+    let (start, first, word) = last_word(input);
+    let candidates = if first && !word.contains('/') && !word.starts_with('~') {
+        rank(&word, executables(path_var), |name| (name.clone(), escape(name), false))
+    } else {
+        paths(&word, cwd)
+    };
+    (!candidates.is_empty()).then(|| Completions {
+        start,
+        candidates,
+        selected: None,
+        typed: input.to_string(),
+    })
+}
+
+/// Every executable name in the directories of `path_var`
+fn executables(path_var: &OsStr) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    for dir in std::env::split_paths(path_var) {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.filter_map(Result::ok) {
+            if crate::path_inspector::is_executable(&entry.path()) {
+                names.insert(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// Entries of the directory `word` points into, matching its last part
+fn paths(word: &str, cwd: &Path) -> Vec<Candidate> {
+    let (dir_part, name) = match word.rfind('/') {
+        Some(i) => (&word[..=i], &word[i + 1..]),
+        None => ("", word),
+    };
+    let dir = expand_dir(dir_part, cwd);
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let entries: Vec<(String, bool)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path().is_dir()))
+        // Hidden entries only when asked for
+        .filter(|(entry_name, _)| !entry_name.starts_with('.') || name.starts_with('.'))
+        .collect();
+    rank(name, entries, |(entry_name, is_dir)| {
+        let slash = if *is_dir { "/" } else { "" };
+        (
+            format!("{}{}", entry_name, slash),
+            format!("{}{}{}", escape(dir_part), escape(entry_name), slash),
+            *is_dir,
+        )
+    })
+}
+
+fn expand_dir(dir_part: &str, cwd: &Path) -> PathBuf {
+    if dir_part.is_empty() {
+        return cwd.to_path_buf();
+    }
+    match dir_part.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => directories::BaseDirs::new()
+            .map(|dirs| dirs.home_dir().join(rest.trim_start_matches('/')))
+            .unwrap_or_else(|| cwd.to_path_buf()),
+        _ => cwd.join(dir_part),
+    }
+}
+
+/// Items matching `query`, prefix matches first, then by fuzzy score and name
+fn rank<T>(query: &str, items: Vec<T>, describe: impl Fn(&T) -> (String, String, bool)) -> Vec<Candidate> {
+    let matcher = SkimMatcherV2::default();
+    let mut scored: Vec<(bool, i64, Candidate)> = items
+        .iter()
+        .filter_map(|item| {
+            let (label, replacement, is_dir) = describe(item);
+            let name = label.trim_end_matches('/');
+            let score = matcher.fuzzy_match(name, query)?;
+            Some((name.starts_with(query), score, Candidate { label, replacement, is_dir }))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then_with(|| a.2.label.cmp(&b.2.label)));
+    scored.into_iter().take(MAX_CANDIDATES).map(|(_, _, candidate)| candidate).collect()
+}
+
+/// `text` with shell-special characters backslash-escaped
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if SPECIAL.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::create_dir(dir.path().join("My Documents")).unwrap();
+        std::fs::write(dir.path().join("My Documents").join("notes.txt"), "").unwrap();
+        std::fs::write(dir.path().join("setup.sh"), "").unwrap();
+        std::fs::write(dir.path().join(".env"), "").unwrap();
+        dir
+    }
+
+    fn labels(completions: &Completions) -> Vec<&str> {
+        completions.candidates.iter().map(|c| c.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_paths_after_the_command() {
+        let dir = fixture();
+        let completions = complete("cat s", dir.path(), OsStr::new("")).unwrap();
+        assert_eq!(completions.start, 4);
+        assert_eq!(labels(&completions), vec!["setup.sh", "src/"]);
+
+        let hidden = complete("source .e", dir.path(), OsStr::new("")).unwrap();
+        assert_eq!(labels(&hidden), vec![".env"]);
+        assert!(complete("cat zzz", dir.path(), OsStr::new("")).is_none());
+    }
+
+    #[test]
+    fn test_spaces_are_escaped_and_directories_continue() {
+        let dir = fixture();
+        let completions = complete("ls My", dir.path(), OsStr::new("")).unwrap();
+        assert_eq!(completions.apply(0), "ls My\\ Documents/");
+
+        // Continuing from the escaped directory, and from a quoted one
+        let inside = complete("ls My\\ Documents/n", dir.path(), OsStr::new("")).unwrap();
+        assert_eq!(inside.apply(0), "ls My\\ Documents/notes.txt ");
+        let quoted = complete("ls \"My Documents/n", dir.path(), OsStr::new("")).unwrap();
+        assert_eq!(quoted.candidates[0].label, "notes.txt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_executables_for_the_first_word() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin = tempfile::tempdir().unwrap();
+        for name in ["cargo", "cargo-clippy", "cat", "notes"] {
+            let path = bin.path().join(name);
+            std::fs::write(&path, "").unwrap();
+            let mode = if name == "notes" { 0o644 } else { 0o755 };
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let cwd = fixture();
+
+        let mut completions = complete("car", cwd.path(), bin.path().as_os_str()).unwrap();
+        assert_eq!(labels(&completions), vec!["cargo", "cargo-clippy"]);
+        assert_eq!(completions.cycle(false), 0);
+        assert_eq!(completions.cycle(false), 1);
+        assert_eq!(completions.cycle(false), 0);
+        assert_eq!(completions.cycle(true), 1);
+
+        // After a pipe the next word is a command again
+        let piped = complete("ls | ca", cwd.path(), bin.path().as_os_str()).unwrap();
+        assert!(labels(&piped).contains(&"cat"));
+        assert!(complete("no", cwd.path(), bin.path().as_os_str()).is_none());
+    }
+}
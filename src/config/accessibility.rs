@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use super::{ColorScheme, ColorValue, ThemeConfig};
+
+/// Minimum contrast for body text against its background (WCAG AA).
+pub const MIN_TEXT_CONTRAST: f32 = 4.5;
+/// Minimum contrast for success/warning/error colors. Status is also carried
+/// by glyphs, so color only has to stand out, not be read.
+pub const MIN_STATUS_CONTRAST: f32 = 2.5;
+/// Minimum sRGB distance between simulated success and error colors for themes
+/// that target a color vision deficiency.
+pub const MIN_SIMULATED_DISTANCE: f32 = 0.6;
+
+/// Which viewers a theme's palette has been checked for.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VisionTarget {
+    #[default]
+    Standard,
+    Deuteranopia,
+    Protanopia,
+    Monochrome,
+}
+
+impl VisionTarget {
+    /// Themes built for a specific audience always show status glyphs
+    pub fn requires_status_glyphs(&self) -> bool {
+        *self != VisionTarget::Standard
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorBlindness {
+    Protanopia,
+    Deuteranopia,
+}
+
+impl ColorBlindness {
+    pub const ALL: [ColorBlindness; 2] = [ColorBlindness::Protanopia, ColorBlindness::Deuteranopia];
+
+    /// Viénot et al. (1999) dichromacy simulation matrices, in linear RGB
+    fn matrix(&self) -> [[f32; 3]; 3] {
+        match self {
+            ColorBlindness::Protanopia => [
+                [0.11238, 0.88762, 0.0],
+                [0.11238, 0.88762, 0.0],
+                [0.00401, -0.00401, 1.0],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.29275, 0.70725, 0.0],
+                [0.29275, 0.70725, 0.0],
+                [-0.02234, 0.02234, 1.0],
+            ],
+        }
+    }
+
+    /// Approximate how `color` appears to a viewer with this deficiency
+    pub fn simulate(&self, color: &ColorValue) -> ColorValue {
+        let linear = [to_linear(color.r), to_linear(color.g), to_linear(color.b)];
+        let m = self.matrix();
+        let channel = |row: [f32; 3]| from_linear(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]);
+
+        ColorValue {
+            r: channel(m[0]),
+            g: channel(m[1]),
+            b: channel(m[2]),
+            a: color.a,
+        }
+    }
+}
+
+fn to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// WCAG relative luminance
+pub fn relative_luminance(color: &ColorValue) -> f32 {
+    0.2126 * to_linear(color.r) + 0.7152 * to_linear(color.g) + 0.0722 * to_linear(color.b)
+}
+
+/// WCAG contrast ratio, from 1.0 (identical) to 21.0 (black on white)
+pub fn contrast_ratio(a: &ColorValue, b: &ColorValue) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    let (lighter, darker) = if la > lb { (la, lb) } else { (lb, la) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+fn distance(a: &ColorValue, b: &ColorValue) -> f32 {
+    ((a.r - b.r).powi(2) + (a.g - b.g).powi(2) + (a.b - b.b).powi(2)).sqrt()
+}
+
+/// Every color of a scheme with its field name, for validation and editors
+pub fn named_colors(scheme: &ColorScheme) -> Vec<(&'static str, &ColorValue)> {
+    let ansi = &scheme.ansi_colors;
+    vec![
+        ("background", &scheme.background),
+        ("surface", &scheme.surface),
+        ("surface_variant", &scheme.surface_variant),
+        ("text", &scheme.text),
+        ("text_secondary", &scheme.text_secondary),
+        ("text_disabled", &scheme.text_disabled),
+        ("terminal_background", &scheme.terminal_background),
+        ("terminal_foreground", &scheme.terminal_foreground),
+        ("terminal_cursor", &scheme.terminal_cursor),
+        ("terminal_selection", &scheme.terminal_selection),
+        ("primary", &scheme.primary),
+        ("secondary", &scheme.secondary),
+        ("accent", &scheme.accent),
+        ("success", &scheme.success),
+        ("warning", &scheme.warning),
+        ("error", &scheme.error),
+        ("hover", &scheme.hover),
+        ("active", &scheme.active),
+        ("focus", &scheme.focus),
+        ("disabled", &scheme.disabled),
+        ("border", &scheme.border),
+        ("divider", &scheme.divider),
+        ("ansi.black", &ansi.black),
+        ("ansi.red", &ansi.red),
+        ("ansi.green", &ansi.green),
+        ("ansi.yellow", &ansi.yellow),
+        ("ansi.blue", &ansi.blue),
+        ("ansi.magenta", &ansi.magenta),
+        ("ansi.cyan", &ansi.cyan),
+        ("ansi.white", &ansi.white),
+        ("ansi.bright_black", &ansi.bright_black),
+        ("ansi.bright_red", &ansi.bright_red),
+        ("ansi.bright_green", &ansi.bright_green),
+        ("ansi.bright_yellow", &ansi.bright_yellow),
+        ("ansi.bright_blue", &ansi.bright_blue),
+        ("ansi.bright_magenta", &ansi.bright_magenta),
+        ("ansi.bright_cyan", &ansi.bright_cyan),
+        ("ansi.bright_white", &ansi.bright_white),
+    ]
+}
+
+/// Check a theme against the contrast and color vision requirements.
+/// Returns one message per problem; empty means the theme passes.
+pub fn validate_theme(theme: &ThemeConfig) -> Vec<String> {
+    let colors = &theme.colors;
+    let mut problems = Vec::new();
+
+    for (name, foreground, background) in [
+        ("text", &colors.text, &colors.background),
+        ("terminal_foreground", &colors.terminal_foreground, &colors.terminal_background),
+    ] {
+        let ratio = contrast_ratio(foreground, background);
+        if ratio < MIN_TEXT_CONTRAST {
+            problems.push(format!("{}: {} contrast {:.2} < {}", theme.name, name, ratio, MIN_TEXT_CONTRAST));
+        }
+    }
+
+    for (name, status) in [("success", &colors.success), ("warning", &colors.warning), ("error", &colors.error)] {
+        let ratio = contrast_ratio(status, &colors.background);
+        if ratio < MIN_STATUS_CONTRAST {
+            problems.push(format!("{}: {} contrast {:.2} < {}", theme.name, name, ratio, MIN_STATUS_CONTRAST));
+        }
+    }
+
+    let deficiencies: &[ColorBlindness] = match theme.vision {
+        VisionTarget::Standard => &[],
+        VisionTarget::Deuteranopia => &[ColorBlindness::Deuteranopia],
+        VisionTarget::Protanopia => &[ColorBlindness::Protanopia],
+        VisionTarget::Monochrome => {
+            for (name, color) in named_colors(colors) {
+                if color.r != color.g || color.g != color.b {
+                    problems.push(format!("{}: {} is not greyscale", theme.name, name));
+                }
+            }
+            &[]
+        }
+    };
+
+    for deficiency in deficiencies {
+        let d = distance(&deficiency.simulate(&colors.success), &deficiency.simulate(&colors.error));
+        if d < MIN_SIMULATED_DISTANCE {
+            problems.push(format!(
+                "{}: success and error are too similar under {:?} ({:.2})",
+                theme.name, deficiency, d
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_themes_pass_validation() {
+        let problems: Vec<String> = ThemeConfig::builtin_themes()
+            .iter()
+            .flat_map(validate_theme)
+            .collect();
+        assert!(problems.is_empty(), "{:#?}", problems);
+    }
+
+    #[test]
+    fn test_contrast_ratio_bounds() {
+        let black = ColorValue { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
+        let white = ColorValue { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
+        assert!((contrast_ratio(&black, &white) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio(&white, &white) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_red_green_collapse_under_deuteranopia() {
+        let red = ColorValue { r: 0.8, g: 0.3, b: 0.2, a: 1.0 };
+        let green = ColorValue { r: 0.4, g: 0.6, b: 0.2, a: 1.0 };
+        let simulated = distance(
+            &ColorBlindness::Deuteranopia.simulate(&red),
+            &ColorBlindness::Deuteranopia.simulate(&green),
+        );
+        assert!(simulated < distance(&red, &green) / 2.0);
+        assert!(simulated < MIN_SIMULATED_DISTANCE);
+    }
+
+    #[test]
+    fn test_low_contrast_theme_is_reported() {
+        let mut theme = ThemeConfig::default();
+        theme.colors.text = theme.colors.background.clone();
+        assert!(!validate_theme(&theme).is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use super::{AppConfig, ConfigError};
+
+/// A named set of environment variables applied to spawned commands.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct EnvProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+impl EnvProfile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            variables: HashMap::new(),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| ConfigError::IoError(e.to_string()))?;
+        let mut profile: EnvProfile = serde_yaml::from_str(&content)
+            .map_err(|e| ConfigError::ParseError(e.to_string()))?;
+
+        if profile.name.trim().is_empty() {
+            profile.name = path.as_ref()
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("unnamed")
+                .to_string();
+        }
+
+        Ok(profile)
+    }
+
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        let content = serde_yaml::to_string(self)
+            .map_err(|e| ConfigError::SerializeError(e.to_string()))?;
+        std::fs::write(path, content)
+            .map_err(|e| ConfigError::IoError(e.to_string()))
+    }
+}
+
+/// Loads and stores env profiles as YAML files in the `env_profiles/` directory.
+#[derive(Debug, Clone)]
+pub struct EnvProfileManager {
+    profiles_dir: PathBuf,
+    profiles: HashMap<String, EnvProfile>,
+}
+
+impl EnvProfileManager {
+    pub fn new() -> Result<Self, ConfigError> {
+        Self::with_dir(AppConfig::env_profiles_dir()?)
+    }
+
+    pub fn with_dir(profiles_dir: PathBuf) -> Result<Self, ConfigError> {
+        let mut manager = Self {
+            profiles_dir,
+            profiles: HashMap::new(),
+        };
+        manager.scan_profiles()?;
+        Ok(manager)
+    }
+
+    /// Reload all profiles from disk
+    pub fn scan_profiles(&mut self) -> Result<(), ConfigError> {
+        self.profiles.clear();
+
+        if !self.profiles_dir.exists() {
+            return Ok(());
+        }
+
+        for entry in std::fs::read_dir(&self.profiles_dir)
+            .map_err(|e| ConfigError::IoError(e.to_string()))?
+        {
+            let entry = entry.map_err(|e| ConfigError::IoError(e.to_string()))?;
+            let path = entry.path();
+
+            if matches!(path.extension().and_then(|s| s.to_str()), Some("yaml") | Some("yml")) {
+                match EnvProfile::from_file(&path) {
+                    Ok(profile) => {
+                        self.profiles.insert(profile.name.clone(), profile);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to load env profile {:?}: {}", path, e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_profile(&self, name: &str) -> Option<&EnvProfile> {
+        self.profiles.get(name)
+    }
+
+    pub fn get_profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn profiles(&self) -> &HashMap<String, EnvProfile> {
+        &self.profiles
+    }
+
+    /// Add or replace a profile and persist it
+    pub fn save_profile(&mut self, profile: EnvProfile) -> Result<(), ConfigError> {
+        std::fs::create_dir_all(&self.profiles_dir)
+            .map_err(|e| ConfigError::IoError(e.to_string()))?;
+        profile.to_file(self.profiles_dir.join(format!("{}.yaml", profile.name)))?;
+        self.profiles.insert(profile.name.clone(), profile);
+        Ok(())
+    }
+
+    pub fn delete_profile(&mut self, name: &str) -> Result<(), ConfigError> {
+        if self.profiles.remove(name).is_some() {
+            for ext in ["yaml", "yml"] {
+                let path = self.profiles_dir.join(format!("{}.{}", name, ext));
+                if path.exists() {
+                    std::fs::remove_file(&path)
+                        .map_err(|e| ConfigError::IoError(e.to_string()))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
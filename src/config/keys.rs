@@ -0,0 +1,196 @@
+//! Single preferences by dotted path, such as `terminal.scrollback_lines`,
+//! for `neoterm config get`, `set` and `list`.
+//!
+//! Paths are resolved over the preferences as serialized, so any nested
+//! field can be reached, and a new value is checked by deserializing the
+//! whole of them again.
+
+use serde_json::{Map, Value};
+use super::{ConfigError, UserPreferences};
+
+/// Accepted in front of a path, as in the config file
+const PREFIX: &str = "preferences.";
+
+/// Every setting that holds a value rather than more settings, in path order
+pub fn list(preferences: &UserPreferences) -> Result<Vec<(String, Value)>, ConfigError> {
+    let mut settings = Vec::new();
+    flatten(String::new(), to_value(preferences)?, &mut settings);
+    settings.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(settings)
+}
+
+pub fn get(preferences: &UserPreferences, key: &str) -> Result<Value, ConfigError> {
+    let value = to_value(preferences)?;
+    let path = split(key);
+    let mut current = &value;
+    for (depth, part) in path.iter().enumerate() {
+        current = match current.as_object() {
+            Some(settings) if settings.contains_key(*part) => &settings[*part],
+            settings => return Err(unknown(&path[..=depth], settings)),
+        };
+    }
+    Ok(current.clone())
+}
+
+/// `preferences` with `key` set from `raw`. Text is taken as it is for
+/// settings that hold text and read as JSON otherwise, so `20000`, `true`
+/// and `["a", "b"]` work unquoted. Names of choices ignore case.
+pub fn set(preferences: &UserPreferences, key: &str, raw: &str) -> Result<UserPreferences, ConfigError> {
+    let mut value = to_value(preferences)?;
+    let path = split(key);
+    let (last, parents) = path.split_last().ok_or_else(|| unknown(&path, value.as_object()))?;
+    let mut parent = &mut value;
+    for (depth, part) in parents.iter().enumerate() {
+        if !parent.as_object().is_some_and(|settings| settings.contains_key(*part)) {
+            return Err(unknown(&path[..=depth], parent.as_object()));
+        }
+        parent = &mut parent[*part];
+    }
+    let Some(settings) = parent.as_object_mut().filter(|settings| settings.contains_key(*last)) else {
+        return Err(unknown(&path, parent.as_object()));
+    };
+
+    let parsed = serde_json::from_str::<Value>(raw).ok();
+    let new = match (&settings[*last], parsed) {
+        (Value::String(_), Some(Value::String(text))) => Value::String(text),
+        (Value::String(_), _) | (_, None) => Value::String(raw.to_string()),
+        (_, Some(parsed)) => parsed,
+    };
+    let invalid = |reason: String| ConfigError::InvalidSetting { key: path.join("."), reason };
+    settings.insert(last.to_string(), new.clone());
+    match serde_json::from_value(value.clone()) {
+        Ok(preferences) => Ok(preferences),
+        Err(e) => {
+            let choices = choices(&e.to_string());
+            if choices.is_empty() {
+                return Err(invalid(e.to_string()));
+            }
+            // `ollama` for `Ollama`
+            let typed = new.as_str().unwrap_or(raw);
+            let Some(choice) = choices.iter().find(|choice| choice.eq_ignore_ascii_case(typed)) else {
+                return Err(invalid(format!("expected one of {}", choices.join(", "))));
+            };
+            let settings = value_at(&mut value, &path[..path.len() - 1]);
+            settings.insert(last.to_string(), Value::String(choice.clone()));
+            serde_json::from_value(value).map_err(|e| invalid(e.to_string()))
+        }
+    }
+}
+
+fn to_value(preferences: &UserPreferences) -> Result<Value, ConfigError> {
+    let mut value = serde_json::to_value(preferences).map_err(|e| ConfigError::SerializeError(e.to_string()))?;
+    shorten_floats(&mut value);
+    Ok(value)
+}
+
+/// `0.9` rather than `0.8999999761581421` for settings held as `f32`
+fn shorten_floats(value: &mut Value) {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            let float = number.as_f64().unwrap_or_default();
+            let short = (float as f32).to_string().parse::<f64>().ok().filter(|_| f64::from(float as f32) == float);
+            if let Some(short) = short.and_then(serde_json::Number::from_f64) {
+                *number = short;
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(shorten_floats),
+        Value::Object(settings) => settings.values_mut().for_each(shorten_floats),
+        _ => {}
+    }
+}
+
+fn split(key: &str) -> Vec<&str> {
+    let key = key.trim();
+    key.strip_prefix(PREFIX).unwrap_or(key).split('.').filter(|part| !part.is_empty()).collect()
+}
+
+/// The settings object at `path`, which `set` has already walked
+fn value_at<'a>(value: &'a mut Value, path: &[&str]) -> &'a mut Map<String, Value> {
+    let mut current = value;
+    for part in path {
+        current = &mut current[*part];
+    }
+    current.as_object_mut().expect("walked by set")
+}
+
+fn unknown(path: &[&str], settings: Option<&Map<String, Value>>) -> ConfigError {
+    ConfigError::UnknownSetting {
+        key: path.join("."),
+        known: settings.map(|settings| settings.keys().cloned().collect()).unwrap_or_default(),
+    }
+}
+
+/// The names serde lists when a choice isn't one of them, as in
+/// "unknown variant `olama`, expected one of `OpenAI`, `Ollama`"
+fn choices(message: &str) -> Vec<String> {
+    let Some((_, expected)) = message.split_once("unknown variant").and_then(|(_, rest)| rest.split_once("expected")) else {
+        return Vec::new();
+    };
+    expected.split('`').skip(1).step_by(2).map(str::to_string).collect()
+}
+
+fn flatten(path: String, value: Value, settings: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                let path = if path.is_empty() { key } else { format!("{}.{}", path, key) };
+                flatten(path, value, settings);
+            }
+        }
+        value => settings.push((path, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_mode_eval::ai_client::AiProvider;
+
+    #[test]
+    fn test_set_checks_types_and_choices() {
+        let preferences = UserPreferences::default();
+
+        let set_lines = set(&preferences, "terminal.scrollback_lines", "20000").unwrap();
+        assert_eq!(set_lines.terminal.scrollback_lines, 20000);
+        assert_eq!(get(&set_lines, "preferences.terminal.scrollback_lines").unwrap(), Value::from(20000));
+
+        let ollama = set(&preferences, "ai.provider", "ollama").unwrap();
+        assert_eq!(ollama.ai.provider, Some(AiProvider::Ollama));
+        let model = set(&preferences, "ai.model", "llama3").unwrap();
+        assert_eq!(model.ai.model.as_deref(), Some("llama3"));
+        let family = set(&preferences, "ui.font_family", "123").unwrap();
+        assert_eq!(family.ui.font_family, "123");
+
+        match set(&preferences, "ai.provider", "olama") {
+            Err(ConfigError::InvalidSetting { key, reason }) => {
+                assert_eq!(key, "ai.provider");
+                assert!(reason.contains("OpenAI") && reason.contains("Ollama"), "{}", reason);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            set(&preferences, "terminal.scrollback_lines", "lots"),
+            Err(ConfigError::InvalidSetting { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_keys_list_what_exists() {
+        let preferences = UserPreferences::default();
+        match set(&preferences, "terminal.scrollbak_lines", "1") {
+            Err(ConfigError::UnknownSetting { key, known }) => {
+                assert_eq!(key, "terminal.scrollbak_lines");
+                assert!(known.contains(&"scrollback_lines".to_string()));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(get(&preferences, "nope.at_all"), Err(ConfigError::UnknownSetting { ref key, .. }) if key == "nope"));
+
+        let listed = list(&preferences).unwrap();
+        assert!(listed.iter().any(|(key, value)| key == "terminal.scrollback_lines" && *value == 10000));
+        assert!(listed.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        let mut translucent = preferences.clone();
+        translucent.ui.transparency = 0.9;
+        assert_eq!(get(&translucent, "ui.transparency").unwrap().to_string(), "0.9");
+    }
+}
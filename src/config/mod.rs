@@ -8,12 +8,27 @@ pub mod preferences;
 pub mod storage;
 pub mod yaml_theme;
 pub mod yaml_theme_manager;
+pub mod env_profile;
+pub mod accessibility;
+pub mod paths;
+pub mod reset;
+pub mod reload;
+pub mod transfer;
+pub mod profiles;
+pub mod keys;
 
 pub use theme::*;
 pub use preferences::*;
 pub use storage::*;
 pub use yaml_theme::*;
 pub use yaml_theme_manager::*;
+pub use env_profile::*;
+pub use accessibility::*;
+pub use paths::*;
+pub use reset::*;
+pub use reload::*;
+pub use transfer::*;
+pub use profiles::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -25,6 +40,14 @@ pub struct AppConfig {
     // YAML theme settings
     pub yaml_themes_enabled: bool,
     pub active_yaml_theme: Option<String>,
+
+    // Env profile applied to every spawned command
+    #[serde(default)]
+    pub active_env_profile: Option<String>,
+
+    // Config profile last switched to
+    #[serde(default)]
+    pub active_profile: Option<String>,
 }
 
 impl Default for AppConfig {
@@ -36,6 +59,8 @@ impl Default for AppConfig {
             plugins: PluginConfig::default(),
             yaml_themes_enabled: true,
             active_yaml_theme: None,
+            active_env_profile: None,
+            active_profile: None,
         }
     }
 }
@@ -47,20 +72,7 @@ impl AppConfig {
         if config_path.exists() {
             let content = std::fs::read_to_string(&config_path)
                 .map_err(|e| ConfigError::IoError(e.to_string()))?;
-            
-            let mut config: AppConfig = toml::from_str(&content)
-                .map_err(|e| ConfigError::ParseError(e.to_string()))?;
-            
-            // Load YAML theme if specified
-            if let Some(yaml_theme_name) = &config.active_yaml_theme {
-                if let Ok(mut theme_manager) = YamlThemeManager::new() {
-                    if let Some(yaml_theme) = theme_manager.get_theme(yaml_theme_name) {
-                        config.theme = yaml_theme;
-                    }
-                }
-            }
-            
-            Ok(config)
+            Self::from_toml(&content)
         } else {
             // Create default config and save it
             let config = Self::default();
@@ -69,6 +81,23 @@ impl AppConfig {
         }
     }
 
+    /// Parse a config file's contents, as `load` does
+    pub fn from_toml(content: &str) -> Result<Self, ConfigError> {
+        let mut config: AppConfig = toml::from_str(content)
+            .map_err(|e| ConfigError::ParseError(e.to_string()))?;
+        
+        // Load YAML theme if specified
+        if let Some(yaml_theme_name) = &config.active_yaml_theme {
+            if let Ok(mut theme_manager) = YamlThemeManager::new() {
+                if let Some(yaml_theme) = theme_manager.get_theme(yaml_theme_name) {
+                    config.theme = yaml_theme;
+                }
+            }
+        }
+        
+        Ok(config)
+    }
+
     pub fn save(&self) -> Result<(), ConfigError> {
         let config_path = Self::config_path()?;
         
@@ -88,20 +117,15 @@ impl AppConfig {
     }
 
     pub fn config_path() -> Result<PathBuf, ConfigError> {
-        let config_dir = dirs::config_dir()
-            .ok_or(ConfigError::ConfigDirNotFound)?
-            .join("neoterm");
-        
-        Ok(config_dir.join("config.toml"))
+        Ok(ConfigPaths::resolve()?.config_file())
     }
 
     pub fn themes_dir() -> Result<PathBuf, ConfigError> {
-        let config_dir = dirs::config_dir()
-            .ok_or(ConfigError::ConfigDirNotFound)?
-            .join("neoterm")
-            .join("themes");
-        
-        Ok(config_dir)
+        Ok(ConfigPaths::resolve()?.themes_dir())
+    }
+
+    pub fn env_profiles_dir() -> Result<PathBuf, ConfigError> {
+        Ok(ConfigPaths::resolve()?.env_profiles_dir())
     }
 
     /// Set active YAML theme
@@ -135,6 +159,18 @@ pub enum ConfigError {
     SerializeError(String),
     #[error("Theme not found: {0}")]
     ThemeNotFound(String),
+    #[error("Refusing to migrate: {} already exists", .0.display())]
+    MigrationConflict(PathBuf),
+    #[error("Migrated copy of {} does not match the original", .0.display())]
+    MigrationVerifyFailed(PathBuf),
+    #[error("Config profile not found: {0}")]
+    ProfileNotFound(String),
+    #[error("Invalid config profile name: {0:?}")]
+    InvalidProfileName(String),
+    #[error("Unknown setting {key}; expected one of: {}", .known.join(", "))]
+    UnknownSetting { key: String, known: Vec<String> },
+    #[error("Invalid value for {key}: {reason}")]
+    InvalidSetting { key: String, reason: String },
     #[error("YAML theme error: {0}")]
     YamlThemeError(#[from] YamlThemeError),
 }
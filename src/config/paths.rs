@@ -0,0 +1,398 @@
+//! Where NeoTerm keeps its files. Every module asks `ConfigPaths` instead of
+//! computing directories itself, so a relocation happens in one place.
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use super::ConfigError;
+
+/// Environment variable that relocates all state, like `--config-dir`
+pub const CONFIG_DIR_ENV: &str = "NEOTERM_CONFIG_DIR";
+/// Left in a legacy directory once its data has been migrated
+const MIGRATED_MARKER: &str = "MIGRATED_TO";
+const APP_DIR: &str = "neoterm";
+
+/// Set from `--config-dir`; wins over the environment
+static DIR_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigPaths {
+    root: PathBuf,
+    cache: PathBuf,
+}
+
+impl ConfigPaths {
+    /// `--config-dir`, then `NEOTERM_CONFIG_DIR`, then the platform location
+    pub fn resolve() -> Result<Self, ConfigError> {
+        let flag = DIR_OVERRIDE.read().unwrap().clone();
+        Self::resolve_with(flag, |name| std::env::var(name).ok())
+    }
+
+    pub fn resolve_with(flag: Option<PathBuf>, env: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let explicit = flag.or_else(|| env(CONFIG_DIR_ENV).filter(|dir| !dir.is_empty()).map(PathBuf::from));
+        if let Some(root) = explicit {
+            return Ok(Self::portable(root));
+        }
+
+        Ok(Self {
+            root: platform_config_dir(&env).ok_or(ConfigError::ConfigDirNotFound)?.join(APP_DIR),
+            cache: platform_cache_dir(&env).ok_or(ConfigError::ConfigDirNotFound)?.join(APP_DIR),
+        })
+    }
+
+    /// Everything, including caches, under one directory
+    pub fn portable(root: PathBuf) -> Self {
+        Self { cache: root.join("cache"), root }
+    }
+
+    /// Used by `--config-dir`; call before anything loads configuration
+    pub fn set_override(dir: Option<PathBuf>) {
+        *DIR_OVERRIDE.write().unwrap() = dir;
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.root.join("config.toml")
+    }
+
+    /// Copies of the config file taken before a reset is saved
+    pub fn config_backups_dir(&self) -> PathBuf {
+        self.root.join("backups")
+    }
+
+    pub fn themes_dir(&self) -> PathBuf {
+        self.root.join("themes")
+    }
+
+    pub fn workflows_dir(&self) -> PathBuf {
+        self.root.join("workflows")
+    }
+
+    pub fn env_profiles_dir(&self) -> PathBuf {
+        self.root.join("env_profiles")
+    }
+
+    /// Named sets of preferences and theme to switch between
+    pub fn profiles_dir(&self) -> PathBuf {
+        self.root.join("profiles")
+    }
+
+    pub fn templates_dir(&self) -> PathBuf {
+        self.root.join("templates")
+    }
+
+    /// Scripts run on terminal events, named after the event
+    pub fn hooks_dir(&self) -> PathBuf {
+        self.root.join("hooks")
+    }
+
+    pub fn crash_reports_dir(&self) -> PathBuf {
+        self.root.join("crash-reports")
+    }
+
+    /// One JSON record per workflow run, appended
+    pub fn workflow_runs_file(&self) -> PathBuf {
+        self.root.join("workflow-runs.jsonl")
+    }
+
+    /// Commands entered at the prompt, one per line
+    pub fn history_file(&self) -> PathBuf {
+        self.root.join("history")
+    }
+
+    /// Saved blocks of previous sessions
+    pub fn sessions_dir(&self) -> PathBuf {
+        self.root.join("sessions")
+    }
+
+    /// Blocks saved on quit, restored on the next start if configured
+    pub fn last_session_file(&self) -> PathBuf {
+        self.sessions_dir().join("last.json")
+    }
+
+    /// Working directory at the last quit, for `working_directory: LastUsed`
+    pub fn last_cwd_file(&self) -> PathBuf {
+        self.sessions_dir().join("last-cwd")
+    }
+
+    /// Words of finished blocks, for palette search; cleared with the sessions
+    pub fn search_index_file(&self) -> PathBuf {
+        self.sessions_dir().join("search-index.jsonl")
+    }
+
+    /// Saved agent conversations
+    pub fn conversations_dir(&self) -> PathBuf {
+        self.root.join("conversations")
+    }
+
+    /// Installed plugins, one directory each named after the plugin's id
+    pub fn plugins_dir(&self) -> PathBuf {
+        self.root.join("plugins")
+    }
+
+    /// Files plugins keep between runs
+    pub fn plugins_data_dir(&self) -> PathBuf {
+        self.root.join("plugin-data")
+    }
+
+    /// Everything here can be deleted and rebuilt
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache
+    }
+
+    pub fn workflow_cache_dir(&self) -> PathBuf {
+        self.cache.join("workflow-cache")
+    }
+
+    /// Snippet files written to run code from assistant replies
+    pub fn scratch_dir(&self) -> PathBuf {
+        self.cache.join("scratch")
+    }
+
+    /// Scripts that make shells run in a block mark their prompts
+    pub fn shell_integration_dir(&self) -> PathBuf {
+        self.cache.join("shell-integration")
+    }
+
+    /// Commands each open window has running, for `neoterm jobs`
+    pub fn jobs_dir(&self) -> PathBuf {
+        self.cache.join("jobs")
+    }
+
+    /// When background maintenance last ran
+    pub fn maintenance_state_file(&self) -> PathBuf {
+        self.cache.join("maintenance.json")
+    }
+
+    /// Older locations that still hold data which hasn't been migrated
+    pub fn legacy_data(&self, env: impl Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+        legacy_locations(&env)
+            .into_iter()
+            .filter(|dir| dir != &self.root && has_data(dir) && !dir.join(MIGRATED_MARKER).exists())
+            .collect()
+    }
+
+    /// Copy `legacy` into this location, verify every file, then switch over
+    /// by moving the verified copy into place. The legacy directory is kept,
+    /// with a marker so it isn't offered again.
+    pub fn migrate_from(&self, legacy: &Path) -> Result<MigrationReport, ConfigError> {
+        if self.config_file().exists() {
+            return Err(ConfigError::MigrationConflict(self.root.clone()));
+        }
+
+        let staging = self.root.with_extension("migrating");
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging).map_err(io_error)?;
+        }
+        let report = copy_tree(legacy, &staging)?;
+        verify_tree(legacy, &staging)?;
+
+        std::fs::create_dir_all(&self.root).map_err(io_error)?;
+        for entry in std::fs::read_dir(&staging).map_err(io_error)? {
+            let entry = entry.map_err(io_error)?;
+            let target = self.root.join(entry.file_name());
+            if target.exists() {
+                // Directories created empty by a first launch give way to the migrated data
+                if target.is_dir() && !has_files(&target) {
+                    std::fs::remove_dir_all(&target).map_err(io_error)?;
+                } else {
+                    return Err(ConfigError::MigrationConflict(target));
+                }
+            }
+            std::fs::rename(entry.path(), &target).map_err(io_error)?;
+        }
+        std::fs::remove_dir_all(&staging).map_err(io_error)?;
+
+        std::fs::write(legacy.join(MIGRATED_MARKER), self.root.display().to_string()).map_err(io_error)?;
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigrationReport {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+fn home(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    env("HOME").or_else(|| env("USERPROFILE")).filter(|h| !h.is_empty()).map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_config_dir(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    home(env).map(|home| home.join("Library").join("Application Support"))
+}
+
+#[cfg(target_os = "macos")]
+fn platform_cache_dir(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    home(env).map(|home| home.join("Library").join("Caches"))
+}
+
+#[cfg(windows)]
+fn platform_config_dir(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    env("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(windows)]
+fn platform_cache_dir(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    env("LOCALAPPDATA").map(PathBuf::from)
+}
+
+/// XDG base directories; relative values are ignored as the spec requires
+#[cfg(not(any(target_os = "macos", windows)))]
+fn platform_config_dir(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    xdg_dir(env, "XDG_CONFIG_HOME", ".config")
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn platform_cache_dir(env: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    xdg_dir(env, "XDG_CACHE_HOME", ".cache")
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn xdg_dir(env: &impl Fn(&str) -> Option<String>, var: &str, fallback: &str) -> Option<PathBuf> {
+    env(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home(env).map(|home| home.join(fallback)))
+}
+
+/// Places earlier versions, or other platforms' conventions, may have used
+fn legacy_locations(env: &impl Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    let Some(home) = home(env) else {
+        return Vec::new();
+    };
+    vec![home.join(".neoterm"), home.join(".config").join(APP_DIR)]
+}
+
+fn has_data(dir: &Path) -> bool {
+    dir.join("config.toml").is_file()
+        || ["themes", "workflows", "env_profiles", "templates"].iter().any(|sub| has_files(&dir.join(sub)))
+}
+
+fn has_files(dir: &Path) -> bool {
+    walkdir::WalkDir::new(dir).into_iter().filter_map(Result::ok).any(|entry| entry.file_type().is_file())
+}
+
+fn io_error(error: std::io::Error) -> ConfigError {
+    ConfigError::IoError(error.to_string())
+}
+
+fn copy_tree(from: &Path, to: &Path) -> Result<MigrationReport, ConfigError> {
+    let mut report = MigrationReport::default();
+    for entry in walkdir::WalkDir::new(from) {
+        let entry = entry.map_err(|e| ConfigError::IoError(e.to_string()))?;
+        let relative = entry.path().strip_prefix(from).expect("walkdir yields paths under its root");
+        if relative.as_os_str() == MIGRATED_MARKER {
+            continue;
+        }
+        let target = to.join(relative);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target).map_err(io_error)?;
+        } else if entry.file_type().is_file() {
+            report.bytes += std::fs::copy(entry.path(), &target).map_err(io_error)?;
+            report.files += 1;
+        }
+    }
+    Ok(report)
+}
+
+/// Byte-for-byte comparison of every file in `original` with its copy
+fn verify_tree(original: &Path, copy: &Path) -> Result<(), ConfigError> {
+    for entry in walkdir::WalkDir::new(original).into_iter().filter_map(Result::ok) {
+        if !entry.file_type().is_file() || entry.file_name() == MIGRATED_MARKER {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(original).expect("walkdir yields paths under its root");
+        let expected = std::fs::read(entry.path()).map_err(io_error)?;
+        let actual = std::fs::read(copy.join(relative)).map_err(io_error)?;
+        if expected != actual {
+            return Err(ConfigError::MigrationVerifyFailed(relative.to_path_buf()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, EnvProfile, EnvProfileManager};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_flag_beats_environment_beats_platform() {
+        let env = |name: &str| match name {
+            CONFIG_DIR_ENV => Some("/from/env".to_string()),
+            "HOME" => Some("/home/ada".to_string()),
+            _ => None,
+        };
+
+        let flagged = ConfigPaths::resolve_with(Some(PathBuf::from("/from/flag")), env).unwrap();
+        assert_eq!(flagged.root(), Path::new("/from/flag"));
+        assert_eq!(flagged.workflow_cache_dir(), PathBuf::from("/from/flag/cache/workflow-cache"));
+
+        let from_env = ConfigPaths::resolve_with(None, env).unwrap();
+        assert_eq!(from_env.root(), Path::new("/from/env"));
+    }
+
+    #[cfg(not(any(target_os = "macos", windows)))]
+    #[test]
+    fn test_xdg_locations() {
+        let env = |name: &str| match name {
+            "HOME" => Some("/home/ada".to_string()),
+            "XDG_CONFIG_HOME" => Some("/xdg/config".to_string()),
+            "XDG_CACHE_HOME" => Some("relative/is/ignored".to_string()),
+            _ => None,
+        };
+        let paths = ConfigPaths::resolve_with(None, env).unwrap();
+
+        assert_eq!(paths.config_file(), PathBuf::from("/xdg/config/neoterm/config.toml"));
+        assert_eq!(paths.workflow_cache_dir(), PathBuf::from("/home/ada/.cache/neoterm/workflow-cache"));
+    }
+
+    #[test]
+    fn test_override_isolates_state() {
+        let temp_dir = TempDir::new().unwrap();
+        ConfigPaths::set_override(Some(temp_dir.path().to_path_buf()));
+
+        AppConfig::default().save().unwrap();
+        EnvProfileManager::new().unwrap().save_profile(EnvProfile::new("isolated")).unwrap();
+        let paths = ConfigPaths::resolve().unwrap();
+        let workflows = crate::workflows::WorkflowManager::get_workflows_dir().unwrap();
+        let cache = crate::workflows::WorkflowCache::get_cache_dir().unwrap();
+        let templates = crate::resources::ResourceManager::user_templates_dir().unwrap();
+
+        ConfigPaths::set_override(None);
+
+        assert!(temp_dir.path().join("config.toml").is_file());
+        assert!(temp_dir.path().join("env_profiles/isolated.yaml").is_file());
+        for dir in [paths.themes_dir(), workflows, cache, templates] {
+            assert!(dir.starts_with(temp_dir.path()), "{} escapes the override", dir.display());
+        }
+    }
+
+    #[test]
+    fn test_migration_copies_verifies_and_marks_legacy() {
+        let home = TempDir::new().unwrap();
+        let legacy = home.path().join(".neoterm");
+        std::fs::create_dir_all(legacy.join("workflows")).unwrap();
+        std::fs::write(legacy.join("config.toml"), "yaml_themes_enabled = true\n").unwrap();
+        std::fs::write(legacy.join("workflows/deploy.yaml"), "name: deploy\ncommand: make deploy\n").unwrap();
+
+        let env = |name: &str| (name == "HOME").then(|| home.path().display().to_string());
+        let paths = ConfigPaths::portable(home.path().join("new"));
+        // An empty directory from a first launch doesn't block migration
+        std::fs::create_dir_all(paths.workflows_dir()).unwrap();
+
+        assert_eq!(paths.legacy_data(env), vec![legacy.clone()]);
+        let report = paths.migrate_from(&legacy).unwrap();
+
+        assert_eq!(report.files, 2);
+        assert_eq!(std::fs::read_to_string(paths.workflows_dir().join("deploy.yaml")).unwrap(), "name: deploy\ncommand: make deploy\n");
+        assert!(legacy.join("config.toml").exists(), "legacy data is kept");
+        assert!(paths.legacy_data(env).is_empty());
+        assert!(matches!(paths.migrate_from(&legacy), Err(ConfigError::MigrationConflict(_))));
+    }
+}
@@ -1,5 +1,13 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use crate::agent_mode_eval::ai_client::AiProvider;
+use crate::agent_mode_eval::tools::ApprovalMode;
+use crate::agent_mode_eval::web::WebAccess;
+use crate::agent_mode_eval::usage::ModelPrice;
+use crate::i18n::Locale;
+use crate::keymap::{Action, KeySequence};
+use crate::plugin_api::PluginGrants;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserPreferences {
@@ -7,8 +15,28 @@ pub struct UserPreferences {
     pub terminal: TerminalPreferences,
     pub editor: EditorPreferences,
     pub ui: UiPreferences,
+    #[serde(default)]
+    pub appearance: AppearancePreferences,
     pub performance: PerformancePreferences,
     pub privacy: PrivacyPreferences,
+    #[serde(default)]
+    pub network: NetworkPreferences,
+    #[serde(default)]
+    pub share: SharePreferences,
+    #[serde(default)]
+    pub crash_reports: CrashReportPreferences,
+    #[serde(default)]
+    pub maintenance: MaintenancePreferences,
+    #[serde(default)]
+    pub scratch: ScratchPreferences,
+    #[serde(default)]
+    pub diagnostics: DiagnosticsPreferences,
+    #[serde(default)]
+    pub ai: AiPreferences,
+    #[serde(default)]
+    pub ai_context: AiContextPreferences,
+    #[serde(default)]
+    pub hooks: HookPreferences,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,7 +46,21 @@ pub struct GeneralPreferences {
     pub working_directory: WorkingDirectoryBehavior,
     pub auto_update: bool,
     pub telemetry_enabled: bool,
-    pub crash_reporting: bool,
+    /// Minutes without input before background work pauses
+    #[serde(default = "default_idle_minutes")]
+    pub idle_timeout_minutes: u64,
+    /// Minutes without input before incognito sessions are locked; `None` never locks
+    #[serde(default)]
+    pub lock_after_idle_minutes: Option<u64>,
+    /// UI language; `None` follows the system locale
+    #[serde(default)]
+    pub language: Option<Locale>,
+}
+
+impl GeneralPreferences {
+    pub fn locale(&self) -> Locale {
+        self.language.unwrap_or_else(Locale::from_env)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +77,9 @@ pub enum WorkingDirectoryBehavior {
     Custom(String),
 }
 
+/// Where the auto-collapse slider starts when the option is turned on
+pub const DEFAULT_AUTO_COLLAPSE_LINES: usize = 50;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerminalPreferences {
     pub scrollback_lines: usize,
@@ -49,6 +94,46 @@ pub struct TerminalPreferences {
     pub word_separators: String,
     pub url_detection: bool,
     pub hyperlink_behavior: HyperlinkBehavior,
+    /// Regexes that mark notable lines on a block's output timeline
+    #[serde(default)]
+    pub alert_patterns: Vec<String>,
+    /// Expand `$VAR` in lines NeoTerm runs itself (`cd`, plugin commands);
+    /// lines passed to the shell are always expanded by the shell
+    #[serde(default = "default_true")]
+    pub expand_variables: bool,
+    /// Collapse a successful command printing more lines than this once the
+    /// next command starts; failures stay expanded
+    #[serde(default)]
+    pub auto_collapse_lines: Option<usize>,
+    /// Desktop notification when a command finishes while the window is
+    /// unfocused
+    #[serde(default = "default_true")]
+    pub notifications_enabled: bool,
+    /// Only for commands that ran at least this long
+    #[serde(default = "default_notify_after_secs")]
+    pub notify_after_secs: u64,
+    /// Run commands in a pseudo-terminal sized to the block list. stdout
+    /// and stderr then arrive together; turn off to keep stderr apart.
+    #[serde(default = "default_true")]
+    pub use_pty: bool,
+    /// Keys go to a running command only after Attach on its block, not
+    /// as soon as the block is focused
+    #[serde(default)]
+    pub require_attach: bool,
+    /// Have bash, zsh and fish started in a block mark their prompts, so
+    /// each command run in them is shown separately
+    #[serde(default = "default_true")]
+    pub shell_integration: bool,
+    /// Show a bare `ls` as a directory block to browse
+    #[serde(default)]
+    pub browse_on_ls: bool,
+    /// What clicking a file path in command output does
+    #[serde(default)]
+    pub open_paths_in: PathLinkAction,
+}
+
+fn default_notify_after_secs() -> u64 {
+    10
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +142,8 @@ pub enum BellBehavior {
     Visual,
     Audio,
     Both,
+    /// Desktop notification while the window is unfocused, visual otherwise
+    Notification,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,17 +153,37 @@ pub enum CursorStyle {
     Bar,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HyperlinkBehavior {
     Click,
     CtrlClick,
     Disabled,
 }
 
+impl HyperlinkBehavior {
+    pub const ALL: [HyperlinkBehavior; 3] = [HyperlinkBehavior::Click, HyperlinkBehavior::CtrlClick, HyperlinkBehavior::Disabled];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PathLinkAction {
+    /// A file preview block at the line
+    #[default]
+    Preview,
+    /// $VISUAL or $EDITOR, at the line where it takes one
+    Editor,
+}
+
+impl PathLinkAction {
+    pub const ALL: [PathLinkAction; 2] = [PathLinkAction::Preview, PathLinkAction::Editor];
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EditorPreferences {
     pub vim_mode: bool,
     pub auto_suggestions: bool,
+    /// Also ask the AI for a suggestion when history has none
+    #[serde(default)]
+    pub ai_suggestions: bool,
     pub syntax_highlighting: bool,
     pub auto_completion: bool,
     pub bracket_matching: bool,
@@ -99,16 +206,76 @@ pub struct UiPreferences {
     pub animations_enabled: bool,
     pub reduce_motion: bool,
     pub high_contrast: bool,
+    /// Scales the output font; Ctrl+= and Ctrl+- step it
     pub zoom_level: f32,
+    /// Family command output is drawn in; empty for the built-in monospace font
+    #[serde(default)]
+    pub font_family: String,
+    /// Output font size before zoom
+    #[serde(default = "default_font_size")]
+    pub font_size: f32,
+    /// Show ✓/✗/⏳ on blocks even when the theme doesn't require them
+    #[serde(default = "default_true")]
+    pub always_show_status_glyphs: bool,
+    #[serde(default)]
+    pub status_line: StatusLinePreferences,
+    /// Key that shows activation hints when the input doesn't have focus
+    #[serde(default = "default_hint_key")]
+    pub hint_key: String,
+    /// Lines a multi-line command shows before the input scrolls
+    #[serde(default = "default_input_max_lines")]
+    pub input_max_lines: usize,
+}
+
+/// Switching between a light and a dark theme with the operating system
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppearancePreferences {
+    pub follow_system: bool,
+    /// Built-in theme used while the system is in light mode
+    pub light_theme: String,
+    /// Built-in theme used while the system is in dark mode
+    pub dark_theme: String,
 }
 
+/// Bottom status bar and which of its segments are shown
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusLinePreferences {
+    pub visible: bool,
+    pub show_mode: bool,
+    /// Working directory and git branch
+    pub show_context: bool,
+    pub show_env_profile: bool,
+    /// Running and queued commands
+    pub show_tasks: bool,
+    /// Sync and AI spinners, idle state
+    pub show_activity: bool,
+    /// Transient notices such as "link copied"
+    pub show_messages: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TabBarVisibility {
     Always,
-    WhenMultiple,
+    /// Only while there's more than one tab
+    #[serde(alias = "WhenMultiple")]
+    Auto,
     Never,
 }
 
+impl TabBarVisibility {
+    pub const ALL: [TabBarVisibility; 3] = [TabBarVisibility::Always, TabBarVisibility::Auto, TabBarVisibility::Never];
+
+    pub fn shows(&self, tabs: usize) -> bool {
+        match self {
+            TabBarVisibility::Always => true,
+            TabBarVisibility::Auto => tabs > 1,
+            TabBarVisibility::Never => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformancePreferences {
     pub gpu_acceleration: bool,
@@ -128,6 +295,227 @@ pub struct PrivacyPreferences {
     pub incognito_mode: bool,
     pub log_level: LogLevel,
     pub share_usage_data: bool,
+    /// Let the agent see the hostname and username
+    #[serde(default)]
+    pub share_host_identity: bool,
+}
+
+/// Outbound HTTP settings. Unset proxies fall back to the standard
+/// HTTP_PROXY / HTTPS_PROXY / NO_PROXY environment variables.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkPreferences {
+    #[serde(default)]
+    pub http_proxy: Option<String>,
+    #[serde(default)]
+    pub https_proxy: Option<String>,
+    #[serde(default)]
+    pub proxy_username: Option<String>,
+    #[serde(default)]
+    pub proxy_password: Option<String>,
+    /// Hosts, domains (`.corp.example`) or IPs/CIDRs that bypass the proxy
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    /// PEM bundle of extra certificate authorities to trust
+    #[serde(default)]
+    pub ca_bundle_path: Option<PathBuf>,
+    /// Disabling verification exposes every request to interception
+    #[serde(default = "default_true")]
+    pub verify_tls: bool,
+}
+
+/// Where "Share block…" uploads to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ShareTarget {
+    /// GitHub Gist; secret (unlisted) unless `public`
+    Gist {
+        #[serde(default)]
+        public: bool,
+    },
+    /// Any endpoint that accepts a markdown POST body and answers with a URL
+    Post { url: String },
+    /// The paste path of a self-hosted NeoTerm server
+    SelfHosted { base_url: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharePreferences {
+    pub target: ShareTarget,
+    /// Environment variable holding the GitHub token for gists
+    #[serde(default = "default_gist_token_env")]
+    pub gist_token_env: String,
+    /// Override for GitHub Enterprise
+    #[serde(default = "default_github_api_url")]
+    pub github_api_url: String,
+}
+
+/// Whether the user agreed to upload crash reports; nothing is sent until they do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashReportConsent {
+    #[default]
+    NotAsked,
+    Granted,
+    Denied,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReportPreferences {
+    #[serde(default)]
+    pub consent: CrashReportConsent,
+    /// Sentry DSN to upload to; without one reports are only kept locally
+    #[serde(default)]
+    pub dsn: Option<String>,
+    /// Identical reports within this many seconds are dropped
+    #[serde(default = "default_duplicate_window_secs")]
+    pub duplicate_window_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiagnosticsPreferences {
+    /// Serve `GET /v1/diagnostics` on this localhost port; off when unset
+    #[serde(default)]
+    pub api_port: Option<u16>,
+}
+
+/// The assistant, and what it's allowed to do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AiPreferences {
+    /// Provider to use; unset picks the first with a key in the environment
+    #[serde(default)]
+    pub provider: Option<AiProvider>,
+    /// Unset uses the provider's default model
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Unset reads the provider's key variable, e.g. `OPENAI_API_KEY`
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default = "default_ai_temperature")]
+    pub temperature: f32,
+    /// Ask before running a generated snippet of more than one command
+    #[serde(default = "default_true")]
+    pub confirm_generated_commands: bool,
+    /// Turn sentences typed at the prompt into a command instead of running
+    /// them; Ctrl+Enter always runs the input as typed
+    #[serde(default = "default_true")]
+    pub natural_language_commands: bool,
+    /// Offer "Apply & run" on a failed command's suggested fix. Destructive
+    /// fixes always go through the input.
+    #[serde(default)]
+    pub one_click_fixes: bool,
+    /// Ask for a fix whenever a command fails; the block's "Suggest fix"
+    /// button works either way
+    #[serde(default)]
+    pub auto_fix_on_failure: bool,
+    /// Exit codes that don't ask for a fix automatically, such as grep's 1
+    /// for "no match"
+    #[serde(default = "default_benign_exits")]
+    pub benign_exits: Vec<BenignExit>,
+    /// Whether the agent's commands and file writes wait for approval
+    #[serde(default)]
+    pub tool_approval: ApprovalMode,
+    /// Dollars per million tokens, by provider and model
+    #[serde(default = "crate::agent_mode_eval::usage::default_prices")]
+    pub prices: Vec<ModelPrice>,
+    /// Warn when one conversation uses more tokens than this; unset never warns
+    #[serde(default = "default_token_budget")]
+    pub token_budget: Option<u64>,
+    /// Hosts the assistant may fetch pages from, and how much of each
+    #[serde(default)]
+    pub web_access: WebAccess,
+}
+
+/// Exit codes that mean "no" rather than "failed" for a program
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenignExit {
+    pub program: String,
+    pub codes: Vec<i32>,
+}
+
+fn default_benign_exits() -> Vec<BenignExit> {
+    ["grep", "egrep", "fgrep", "rg", "diff", "cmp", "test", "["]
+        .into_iter()
+        .map(|program| BenignExit { program: program.to_string(), codes: vec![1] })
+        .collect()
+}
+
+/// How much of a block's output is sent along when asking the AI about it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiContextPreferences {
+    /// Lines always kept from the start of the output
+    #[serde(default = "default_context_head_lines")]
+    pub head_lines: usize,
+    /// Lines always kept from the end of the output
+    #[serde(default = "default_context_tail_lines")]
+    pub tail_lines: usize,
+    /// Most stderr lines kept from the middle
+    #[serde(default = "default_context_stderr_lines")]
+    pub stderr_lines: usize,
+    /// Most error/warning lines kept from the middle
+    #[serde(default = "default_context_match_lines")]
+    pub match_lines: usize,
+    /// Above this estimate, summarizing first is offered
+    #[serde(default = "default_context_max_tokens")]
+    pub max_tokens: usize,
+}
+
+/// Scripts in the hooks directory run on terminal events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookPreferences {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// A hook still running after this is killed
+    #[serde(default = "default_hook_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Commands taking at least this long trigger `on-command-long-running`
+    #[serde(default = "default_long_running_secs")]
+    pub long_running_secs: u64,
+    #[serde(default)]
+    pub run_in_read_only: bool,
+    #[serde(default)]
+    pub run_in_incognito: bool,
+}
+
+impl HookPreferences {
+    /// Whether hooks run in a session with these modes
+    pub fn allows(&self, read_only: bool, incognito: bool) -> bool {
+        self.enabled && (!read_only || self.run_in_read_only) && (!incognito || self.run_in_incognito)
+    }
+}
+
+/// Running code snippets from assistant replies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScratchPreferences {
+    /// Language ids that get a run button, e.g. `python`, `javascript`, `rust`
+    #[serde(default = "default_run_languages")]
+    pub run_languages: Vec<String>,
+    /// Snippet files older than this are deleted
+    #[serde(default = "default_scratch_retention_hours")]
+    pub retention_hours: u64,
+    /// Command lines to use instead of a language's default interpreter, by language id
+    #[serde(default)]
+    pub interpreters: HashMap<String, String>,
+}
+
+/// Retention limits enforced by the background maintenance task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenancePreferences {
+    /// Recorded runs kept per workflow
+    #[serde(default = "default_run_history_keep")]
+    pub run_history_keep: usize,
+    /// Workflow step cache size target
+    #[serde(default = "default_cache_max_mb")]
+    pub cache_max_mb: u64,
+    #[serde(default = "default_crash_reports_keep")]
+    pub crash_reports_keep: usize,
+    /// Crash reports older than this are removed regardless of count
+    #[serde(default = "default_crash_report_max_age_days")]
+    pub crash_report_max_age_days: i64,
+    /// Days between runs
+    #[serde(default = "default_maintenance_interval_days")]
+    pub interval_days: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -139,60 +527,30 @@ pub enum LogLevel {
     Trace,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Keys for the keymap's actions, by action id. Actions left out keep their
+/// default keys, and an empty list unbinds one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct KeyBindings {
-    pub bindings: HashMap<String, KeyBinding>,
+    #[serde(deserialize_with = "crate::keymap::deserialize_bindings")]
+    pub bindings: BTreeMap<String, Vec<KeySequence>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct KeyBinding {
-    pub key: String,
-    pub modifiers: Vec<Modifier>,
-    pub action: Action,
-    pub when: Option<String>, // Context condition
-}
+impl KeyBindings {
+    pub fn keys(&self, action: Action) -> Vec<KeySequence> {
+        self.bindings.get(action.id()).cloned().unwrap_or_else(|| action.default_keys())
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Modifier {
-    Ctrl,
-    Alt,
-    Shift,
-    Super, // Cmd on macOS, Windows key on Windows
-}
+    pub fn set(&mut self, action: Action, keys: Vec<KeySequence>) {
+        self.bindings.insert(action.id().to_string(), keys);
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Action {
-    // Terminal actions
-    NewTab,
-    CloseTab,
-    NextTab,
-    PreviousTab,
-    SplitHorizontal,
-    SplitVertical,
-    CloseSplit,
-    
-    // Edit actions
-    Copy,
-    Paste,
-    Cut,
-    SelectAll,
-    Find,
-    FindNext,
-    FindPrevious,
-    
-    // Navigation
-    ScrollUp,
-    ScrollDown,
-    ScrollToTop,
-    ScrollToBottom,
-    
-    // Application
-    ToggleFullscreen,
-    ToggleSettings,
-    Quit,
-    
-    // Custom command
-    Command(String),
+    pub fn reset(&mut self, action: Action) {
+        self.set(action, action.default_keys());
+    }
+
+    pub fn is_default(&self, action: Action) -> bool {
+        self.keys(action) == action.default_keys()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -201,6 +559,9 @@ pub struct PluginConfig {
     pub plugin_settings: HashMap<String, serde_json::Value>,
     pub auto_update_plugins: bool,
     pub allow_unsigned_plugins: bool,
+    /// Declared permissions the user agreed to at install and hasn't revoked
+    #[serde(default)]
+    pub granted_permissions: PluginGrants,
 }
 
 impl Default for UserPreferences {
@@ -210,8 +571,18 @@ impl Default for UserPreferences {
             terminal: TerminalPreferences::default(),
             editor: EditorPreferences::default(),
             ui: UiPreferences::default(),
+            appearance: AppearancePreferences::default(),
             performance: PerformancePreferences::default(),
             privacy: PrivacyPreferences::default(),
+            network: NetworkPreferences::default(),
+            share: SharePreferences::default(),
+            crash_reports: CrashReportPreferences::default(),
+            maintenance: MaintenancePreferences::default(),
+            scratch: ScratchPreferences::default(),
+            diagnostics: DiagnosticsPreferences::default(),
+            ai: AiPreferences::default(),
+            ai_context: AiContextPreferences::default(),
+            hooks: HookPreferences::default(),
         }
     }
 }
@@ -224,7 +595,9 @@ impl Default for GeneralPreferences {
             working_directory: WorkingDirectoryBehavior::Home,
             auto_update: true,
             telemetry_enabled: false,
-            crash_reporting: true,
+            idle_timeout_minutes: default_idle_minutes(),
+            lock_after_idle_minutes: None,
+            language: None,
         }
     }
 }
@@ -244,6 +617,16 @@ impl Default for TerminalPreferences {
             word_separators: " \t\n\"'`()[]{}".to_string(),
             url_detection: true,
             hyperlink_behavior: HyperlinkBehavior::CtrlClick,
+            alert_patterns: Vec::new(),
+            expand_variables: true,
+            auto_collapse_lines: None,
+            notifications_enabled: true,
+            notify_after_secs: default_notify_after_secs(),
+            use_pty: true,
+            require_attach: false,
+            shell_integration: true,
+            browse_on_ls: false,
+            open_paths_in: PathLinkAction::Preview,
         }
     }
 }
@@ -253,6 +636,7 @@ impl Default for EditorPreferences {
         Self {
             vim_mode: false,
             auto_suggestions: true,
+            ai_suggestions: false,
             syntax_highlighting: true,
             auto_completion: true,
             bracket_matching: true,
@@ -269,7 +653,7 @@ impl Default for EditorPreferences {
 impl Default for UiPreferences {
     fn default() -> Self {
         Self {
-            show_tab_bar: TabBarVisibility::WhenMultiple,
+            show_tab_bar: TabBarVisibility::Auto,
             show_title_bar: true,
             show_menu_bar: false,
             compact_mode: false,
@@ -279,6 +663,26 @@ impl Default for UiPreferences {
             reduce_motion: false,
             high_contrast: false,
             zoom_level: 1.0,
+            font_family: String::new(),
+            font_size: default_font_size(),
+            always_show_status_glyphs: true,
+            status_line: StatusLinePreferences::default(),
+            hint_key: default_hint_key(),
+            input_max_lines: default_input_max_lines(),
+        }
+    }
+}
+
+impl Default for StatusLinePreferences {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            show_mode: true,
+            show_context: true,
+            show_env_profile: true,
+            show_tasks: true,
+            show_activity: true,
+            show_messages: true,
         }
     }
 }
@@ -297,6 +701,185 @@ impl Default for PerformancePreferences {
     }
 }
 
+impl Default for NetworkPreferences {
+    fn default() -> Self {
+        Self {
+            http_proxy: None,
+            https_proxy: None,
+            proxy_username: None,
+            proxy_password: None,
+            no_proxy: Vec::new(),
+            ca_bundle_path: None,
+            verify_tls: true,
+        }
+    }
+}
+
+impl Default for SharePreferences {
+    fn default() -> Self {
+        Self {
+            target: ShareTarget::Gist { public: false },
+            gist_token_env: default_gist_token_env(),
+            github_api_url: default_github_api_url(),
+        }
+    }
+}
+
+impl Default for CrashReportPreferences {
+    fn default() -> Self {
+        Self {
+            consent: CrashReportConsent::NotAsked,
+            dsn: None,
+            duplicate_window_secs: default_duplicate_window_secs(),
+        }
+    }
+}
+
+fn default_duplicate_window_secs() -> u64 {
+    300
+}
+
+impl Default for AiContextPreferences {
+    fn default() -> Self {
+        Self {
+            head_lines: default_context_head_lines(),
+            tail_lines: default_context_tail_lines(),
+            stderr_lines: default_context_stderr_lines(),
+            match_lines: default_context_match_lines(),
+            max_tokens: default_context_max_tokens(),
+        }
+    }
+}
+
+fn default_context_head_lines() -> usize {
+    40
+}
+
+fn default_context_tail_lines() -> usize {
+    120
+}
+
+fn default_context_stderr_lines() -> usize {
+    200
+}
+
+fn default_context_match_lines() -> usize {
+    200
+}
+
+fn default_context_max_tokens() -> usize {
+    12_000
+}
+
+impl Default for HookPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout_ms: default_hook_timeout_ms(),
+            long_running_secs: default_long_running_secs(),
+            run_in_read_only: false,
+            run_in_incognito: false,
+        }
+    }
+}
+
+fn default_hook_timeout_ms() -> u64 {
+    2_000
+}
+
+fn default_long_running_secs() -> u64 {
+    30
+}
+
+impl Default for AiPreferences {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            model: None,
+            base_url: None,
+            api_key: None,
+            temperature: default_ai_temperature(),
+            confirm_generated_commands: true,
+            natural_language_commands: true,
+            one_click_fixes: false,
+            auto_fix_on_failure: false,
+            benign_exits: default_benign_exits(),
+            tool_approval: ApprovalMode::default(),
+            prices: crate::agent_mode_eval::usage::default_prices(),
+            token_budget: default_token_budget(),
+            web_access: WebAccess::default(),
+        }
+    }
+}
+
+fn default_ai_temperature() -> f32 {
+    0.7
+}
+
+fn default_token_budget() -> Option<u64> {
+    Some(200_000)
+}
+
+impl Default for AppearancePreferences {
+    fn default() -> Self {
+        Self {
+            follow_system: false,
+            light_theme: "Default Light".to_string(),
+            dark_theme: "Default Dark".to_string(),
+        }
+    }
+}
+
+impl Default for ScratchPreferences {
+    fn default() -> Self {
+        Self {
+            run_languages: default_run_languages(),
+            retention_hours: default_scratch_retention_hours(),
+            interpreters: HashMap::new(),
+        }
+    }
+}
+
+fn default_run_languages() -> Vec<String> {
+    vec!["python".to_string(), "javascript".to_string()]
+}
+
+fn default_scratch_retention_hours() -> u64 {
+    24
+}
+
+impl Default for MaintenancePreferences {
+    fn default() -> Self {
+        Self {
+            run_history_keep: default_run_history_keep(),
+            cache_max_mb: default_cache_max_mb(),
+            crash_reports_keep: default_crash_reports_keep(),
+            crash_report_max_age_days: default_crash_report_max_age_days(),
+            interval_days: default_maintenance_interval_days(),
+        }
+    }
+}
+
+fn default_run_history_keep() -> usize {
+    50
+}
+
+fn default_cache_max_mb() -> u64 {
+    1024
+}
+
+fn default_crash_reports_keep() -> usize {
+    20
+}
+
+fn default_crash_report_max_age_days() -> i64 {
+    90
+}
+
+fn default_maintenance_interval_days() -> u64 {
+    7
+}
+
 impl Default for PrivacyPreferences {
     fn default() -> Self {
         Self {
@@ -306,80 +889,14 @@ impl Default for PrivacyPreferences {
             incognito_mode: false,
             log_level: LogLevel::Info,
             share_usage_data: false,
+            share_host_identity: false,
         }
     }
 }
 
 impl Default for KeyBindings {
     fn default() -> Self {
-        let mut bindings = HashMap::new();
-        
-        // Terminal shortcuts
-        bindings.insert("new_tab".to_string(), KeyBinding {
-            key: "t".to_string(),
-            modifiers: vec![Modifier::Ctrl],
-            action: Action::NewTab,
-            when: None,
-        });
-        
-        bindings.insert("close_tab".to_string(), KeyBinding {
-            key: "w".to_string(),
-            modifiers: vec![Modifier::Ctrl],
-            action: Action::CloseTab,
-            when: None,
-        });
-        
-        bindings.insert("next_tab".to_string(), KeyBinding {
-            key: "Tab".to_string(),
-            modifiers: vec![Modifier::Ctrl],
-            action: Action::NextTab,
-            when: None,
-        });
-        
-        bindings.insert("previous_tab".to_string(), KeyBinding {
-            key: "Tab".to_string(),
-            modifiers: vec![Modifier::Ctrl, Modifier::Shift],
-            action: Action::PreviousTab,
-            when: None,
-        });
-        
-        // Edit shortcuts
-        bindings.insert("copy".to_string(), KeyBinding {
-            key: "c".to_string(),
-            modifiers: vec![Modifier::Ctrl],
-            action: Action::Copy,
-            when: None,
-        });
-        
-        bindings.insert("paste".to_string(), KeyBinding {
-            key: "v".to_string(),
-            modifiers: vec![Modifier::Ctrl],
-            action: Action::Paste,
-            when: None,
-        });
-        
-        bindings.insert("find".to_string(), KeyBinding {
-            key: "f".to_string(),
-            modifiers: vec![Modifier::Ctrl],
-            action: Action::Find,
-            when: None,
-        });
-        
-        // Application shortcuts
-        bindings.insert("fullscreen".to_string(), KeyBinding {
-            key: "F11".to_string(),
-            modifiers: vec![],
-            action: Action::ToggleFullscreen,
-            when: None,
-        });
-        
-        bindings.insert("settings".to_string(), KeyBinding {
-            key: "comma".to_string(),
-            modifiers: vec![Modifier::Ctrl],
-            action: Action::ToggleSettings,
-            when: None,
-        });
-        
+        let bindings = Action::all().map(|action| (action.id().to_string(), action.default_keys())).collect();
         Self { bindings }
     }
 }
@@ -391,6 +908,35 @@ impl Default for PluginConfig {
             plugin_settings: HashMap::new(),
             auto_update_plugins: true,
             allow_unsigned_plugins: false,
+            granted_permissions: PluginGrants::default(),
         }
     }
 }
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_hint_key() -> String {
+    "f".to_string()
+}
+
+fn default_input_max_lines() -> usize {
+    8
+}
+
+fn default_font_size() -> f32 {
+    crate::font::DEFAULT_SIZE
+}
+
+fn default_gist_token_env() -> String {
+    "GITHUB_TOKEN".to_string()
+}
+
+fn default_github_api_url() -> String {
+    "https://api.github.com".to_string()
+}
+
+fn default_idle_minutes() -> u64 {
+    crate::idle::DEFAULT_IDLE_MINUTES
+}
@@ -0,0 +1,131 @@
+//! Named config profiles, such as "work" and "personal". A profile holds
+//! preferences and a theme; switching to one leaves key bindings, plugins
+//! and everything else alone.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use super::{AppConfig, ConfigError, ConfigPaths, ThemeConfig, UserPreferences};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    pub theme: ThemeConfig,
+    pub preferences: UserPreferences,
+}
+
+impl ConfigProfile {
+    /// The preferences and theme in use
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            theme: config.theme.clone(),
+            preferences: config.preferences.clone(),
+        }
+    }
+
+    /// `config` with this profile's preferences and theme, recorded as `name`
+    pub fn apply_to(&self, config: &AppConfig, name: &str) -> AppConfig {
+        AppConfig {
+            theme: self.theme.clone(),
+            preferences: self.preferences.clone(),
+            active_profile: Some(name.to_string()),
+            ..config.clone()
+        }
+    }
+}
+
+/// Profiles stored as YAML files in the `profiles/` directory
+#[derive(Debug, Clone)]
+pub struct ConfigProfileManager {
+    dir: PathBuf,
+}
+
+impl ConfigProfileManager {
+    pub fn new() -> Result<Self, ConfigError> {
+        Ok(Self::with_dir(ConfigPaths::resolve()?.profiles_dir()))
+    }
+
+    pub fn with_dir(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Sorted; empty when the directory doesn't exist
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                matches!(path.extension()?.to_str()?, "yaml" | "yml")
+                    .then(|| path.file_stem()?.to_str().map(str::to_string))?
+            })
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn load(&self, name: &str) -> Result<ConfigProfile, ConfigError> {
+        let path = self.existing_path(name)?.ok_or_else(|| ConfigError::ProfileNotFound(name.to_string()))?;
+        let content = std::fs::read_to_string(&path).map_err(|e| ConfigError::IoError(e.to_string()))?;
+        serde_yaml::from_str(&content).map_err(|e| ConfigError::ParseError(e.to_string()))
+    }
+
+    /// Add or replace a profile
+    pub fn save(&self, name: &str, profile: &ConfigProfile) -> Result<(), ConfigError> {
+        let path = self.existing_path(name)?.unwrap_or_else(|| self.dir.join(format!("{}.yaml", name)));
+        let content = serde_yaml::to_string(profile).map_err(|e| ConfigError::SerializeError(e.to_string()))?;
+        std::fs::create_dir_all(&self.dir).map_err(|e| ConfigError::IoError(e.to_string()))?;
+        std::fs::write(path, content).map_err(|e| ConfigError::IoError(e.to_string()))
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), ConfigError> {
+        let path = self.existing_path(name)?.ok_or_else(|| ConfigError::ProfileNotFound(name.to_string()))?;
+        std::fs::remove_file(path).map_err(|e| ConfigError::IoError(e.to_string()))
+    }
+
+    /// The profile's file, if it has one. Names become file names, so
+    /// ones that would leave the directory are refused.
+    fn existing_path(&self, name: &str) -> Result<Option<PathBuf>, ConfigError> {
+        let name = name.trim();
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(ConfigError::InvalidProfileName(name.to_string()));
+        }
+        Ok(["yaml", "yml"]
+            .into_iter()
+            .map(|extension| self.dir.join(format!("{}.{}", name, extension)))
+            .find(|path| path.exists()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{Action, KeySequence};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_profiles_swap_preferences_and_theme_only() {
+        let dir = TempDir::new().unwrap();
+        let manager = ConfigProfileManager::with_dir(dir.path().join("profiles"));
+        assert!(manager.names().is_empty());
+
+        let mut work = AppConfig::default();
+        work.preferences.ui.font_size = 15.0;
+        work.theme.name = "Work".to_string();
+        manager.save("work", &ConfigProfile::from_config(&work)).unwrap();
+        manager.save("personal", &ConfigProfile::from_config(&AppConfig::default())).unwrap();
+        assert_eq!(manager.names(), vec!["personal", "work"]);
+
+        let mut current = AppConfig::default();
+        current.keybindings.set(Action::Palette, vec!["Ctrl+K".parse::<KeySequence>().unwrap()]);
+        let switched = manager.load("work").unwrap().apply_to(&current, "work");
+        assert_eq!(switched.preferences.ui.font_size, 15.0);
+        assert_eq!(switched.theme.name, "Work");
+        assert_eq!(switched.keybindings, current.keybindings);
+        assert_eq!(switched.active_profile.as_deref(), Some("work"));
+
+        manager.delete("personal").unwrap();
+        assert_eq!(manager.names(), vec!["work"]);
+        assert!(matches!(manager.load("personal"), Err(ConfigError::ProfileNotFound(_))));
+        assert!(matches!(manager.load("../config"), Err(ConfigError::InvalidProfileName(_))));
+    }
+}
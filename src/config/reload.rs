@@ -0,0 +1,152 @@
+//! Reloading the config file after it's edited outside the app.
+//!
+//! `ConfigManager::reload` reads the file again, compares it with the
+//! config in use and sends what changed to everyone subscribed. A file that
+//! doesn't parse is reported and leaves the config in use alone.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde_json::Value;
+use tokio::sync::broadcast;
+use super::{AppConfig, ConfigError};
+
+/// Updates held for a subscriber that hasn't read them yet
+const UPDATES_BUFFERED: usize = 8;
+/// Longer values are cut short in the diff
+const MAX_VALUE_CHARS: usize = 80;
+
+/// One setting that differs, by its dotted path in the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingChange {
+    pub path: String,
+    /// `None` where the setting wasn't there
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// The config read from the edited file, and how it differs from before
+#[derive(Debug, Clone)]
+pub struct ConfigUpdate {
+    pub config: AppConfig,
+    pub changes: Vec<SettingChange>,
+}
+
+impl ConfigUpdate {
+    /// `-` and `+` lines for each changed setting
+    pub fn diff(&self) -> String {
+        let mut lines = Vec::new();
+        for change in &self.changes {
+            if let Some(old) = &change.old {
+                lines.push(format!("- {} = {}", change.path, show(old)));
+            }
+            if let Some(new) = &change.new {
+                lines.push(format!("+ {} = {}", change.path, show(new)));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+fn show(value: &Value) -> String {
+    let shown = value.to_string();
+    if shown.chars().count() <= MAX_VALUE_CHARS {
+        return shown;
+    }
+    format!("{}…", shown.chars().take(MAX_VALUE_CHARS).collect::<String>())
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigManager {
+    path: PathBuf,
+    updates: broadcast::Sender<Arc<ConfigUpdate>>,
+}
+
+impl ConfigManager {
+    pub fn new(path: PathBuf) -> Self {
+        let (updates, _) = broadcast::channel(UPDATES_BUFFERED);
+        Self { path, updates }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Updates from edits to the file, from now on
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<Arc<ConfigUpdate>> {
+        self.updates.subscribe()
+    }
+
+    /// Read the file again and compare it with `current`. Nothing is sent
+    /// when they match, as after the app saves the file itself.
+    pub fn reload(&self, current: &AppConfig) -> Result<Option<Arc<ConfigUpdate>>, ConfigError> {
+        let content = std::fs::read_to_string(&self.path).map_err(|e| ConfigError::IoError(e.to_string()))?;
+        let config = AppConfig::from_toml(&content)?;
+        let changes = diff(current, &config);
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        let update = Arc::new(ConfigUpdate { config, changes });
+        // Having no subscribers isn't an error
+        let _ = self.updates.send(update.clone());
+        Ok(Some(update))
+    }
+}
+
+/// Settings that differ between two configs, in path order
+pub fn diff(old: &AppConfig, new: &AppConfig) -> Vec<SettingChange> {
+    let (old, new) = (serde_json::to_value(old).ok(), serde_json::to_value(new).ok());
+    let mut changes = Vec::new();
+    compare(String::new(), old.as_ref(), new.as_ref(), &mut changes);
+    changes
+}
+
+fn compare(path: String, old: Option<&Value>, new: Option<&Value>, changes: &mut Vec<SettingChange>) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                compare(path, old.get(key), new.get(key), changes);
+            }
+        }
+        (old, new) if old != new => changes.push(SettingChange { path, old: old.cloned(), new: new.cloned() }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reload_reports_changes_and_keeps_bad_files_out() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        let current = AppConfig::default();
+        let manager = ConfigManager::new(path.clone());
+        let mut updates = manager.subscribe_changes();
+
+        std::fs::write(&path, toml::to_string_pretty(&current).unwrap()).unwrap();
+        assert!(manager.reload(&current).unwrap().is_none());
+
+        let mut edited = current.clone();
+        edited.preferences.ui.font_size = 15.0;
+        edited.preferences.terminal.scrollback_lines = 500;
+        std::fs::write(&path, toml::to_string_pretty(&edited).unwrap()).unwrap();
+        let update = manager.reload(&current).unwrap().unwrap();
+        let paths: Vec<&str> = update.changes.iter().map(|change| change.path.as_str()).collect();
+        assert_eq!(paths, vec!["preferences.terminal.scrollback_lines", "preferences.ui.font_size"]);
+        assert_eq!(
+            update.diff(),
+            "- preferences.terminal.scrollback_lines = 10000\n+ preferences.terminal.scrollback_lines = 500\n\
+             - preferences.ui.font_size = 12.0\n+ preferences.ui.font_size = 15.0"
+        );
+        assert_eq!(updates.try_recv().unwrap().changes, update.changes);
+
+        std::fs::write(&path, "[preferences\nui = ").unwrap();
+        assert!(matches!(manager.reload(&current), Err(ConfigError::ParseError(_))));
+        assert!(updates.try_recv().is_err());
+    }
+}
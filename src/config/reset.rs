@@ -0,0 +1,298 @@
+//! Resetting configuration to defaults, section by section, and the
+//! timestamped backups taken before a reset is saved.
+
+use super::{AppConfig, ConfigError, ConfigPaths};
+use crate::i18n::tr;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use std::path::{Path, PathBuf};
+
+const BACKUP_PREFIX: &str = "config-";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// A part of the configuration that can be reset on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigSection {
+    Theme,
+    KeyBindings,
+    EnvProfile,
+    Plugins,
+    General,
+    Terminal,
+    Editor,
+    Ui,
+    Appearance,
+    Performance,
+    Privacy,
+    Network,
+    Share,
+    CrashReports,
+    Maintenance,
+    Scratch,
+    Diagnostics,
+    Ai,
+    AiContext,
+    Hooks,
+}
+
+impl ConfigSection {
+    pub const ALL: &'static [ConfigSection] = &[
+        ConfigSection::Theme,
+        ConfigSection::KeyBindings,
+        ConfigSection::EnvProfile,
+        ConfigSection::Plugins,
+        ConfigSection::General,
+        ConfigSection::Terminal,
+        ConfigSection::Editor,
+        ConfigSection::Ui,
+        ConfigSection::Appearance,
+        ConfigSection::Performance,
+        ConfigSection::Privacy,
+        ConfigSection::Network,
+        ConfigSection::Share,
+        ConfigSection::CrashReports,
+        ConfigSection::Maintenance,
+        ConfigSection::Scratch,
+        ConfigSection::Diagnostics,
+        ConfigSection::Ai,
+        ConfigSection::AiContext,
+        ConfigSection::Hooks,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ConfigSection::Theme => tr("config.section.theme"),
+            ConfigSection::KeyBindings => tr("config.section.keybindings"),
+            ConfigSection::EnvProfile => tr("config.section.env_profile"),
+            ConfigSection::Plugins => tr("config.section.plugins"),
+            ConfigSection::General => tr("config.section.general"),
+            ConfigSection::Terminal => tr("config.section.terminal"),
+            ConfigSection::Editor => tr("config.section.editor"),
+            ConfigSection::Ui => tr("config.section.ui"),
+            ConfigSection::Appearance => tr("config.section.appearance"),
+            ConfigSection::Performance => tr("config.section.performance"),
+            ConfigSection::Privacy => tr("config.section.privacy"),
+            ConfigSection::Network => tr("config.section.network"),
+            ConfigSection::Share => tr("config.section.share"),
+            ConfigSection::CrashReports => tr("config.section.crash_reports"),
+            ConfigSection::Maintenance => tr("config.section.maintenance"),
+            ConfigSection::Scratch => tr("config.section.scratch"),
+            ConfigSection::Diagnostics => tr("config.section.diagnostics"),
+            ConfigSection::Ai => tr("config.section.ai"),
+            ConfigSection::AiContext => tr("config.section.ai_context"),
+            ConfigSection::Hooks => tr("config.section.hooks"),
+        }
+    }
+
+    /// The section's settings, for comparing
+    fn value(&self, config: &AppConfig) -> serde_json::Value {
+        let prefs = &config.preferences;
+        let value = match self {
+            ConfigSection::Theme => serde_json::to_value((&config.theme, config.yaml_themes_enabled, &config.active_yaml_theme)),
+            ConfigSection::KeyBindings => serde_json::to_value(&config.keybindings),
+            ConfigSection::EnvProfile => serde_json::to_value(&config.active_env_profile),
+            ConfigSection::Plugins => serde_json::to_value(&config.plugins),
+            ConfigSection::General => serde_json::to_value(&prefs.general),
+            ConfigSection::Terminal => serde_json::to_value(&prefs.terminal),
+            ConfigSection::Editor => serde_json::to_value(&prefs.editor),
+            ConfigSection::Ui => serde_json::to_value(&prefs.ui),
+            ConfigSection::Appearance => serde_json::to_value(&prefs.appearance),
+            ConfigSection::Performance => serde_json::to_value(&prefs.performance),
+            ConfigSection::Privacy => serde_json::to_value(&prefs.privacy),
+            ConfigSection::Network => serde_json::to_value(&prefs.network),
+            ConfigSection::Share => serde_json::to_value(&prefs.share),
+            ConfigSection::CrashReports => serde_json::to_value(&prefs.crash_reports),
+            ConfigSection::Maintenance => serde_json::to_value(&prefs.maintenance),
+            ConfigSection::Scratch => serde_json::to_value(&prefs.scratch),
+            ConfigSection::Diagnostics => serde_json::to_value(&prefs.diagnostics),
+            ConfigSection::Ai => serde_json::to_value(&prefs.ai),
+            ConfigSection::AiContext => serde_json::to_value(&prefs.ai_context),
+            ConfigSection::Hooks => serde_json::to_value(&prefs.hooks),
+        };
+        value.unwrap_or(serde_json::Value::Null)
+    }
+
+    /// Copy this section from `defaults` into `config`
+    fn reset(&self, config: &mut AppConfig, defaults: &AppConfig) {
+        let (prefs, default_prefs) = (&mut config.preferences, &defaults.preferences);
+        match self {
+            ConfigSection::Theme => {
+                config.theme = defaults.theme.clone();
+                config.yaml_themes_enabled = defaults.yaml_themes_enabled;
+                config.active_yaml_theme = defaults.active_yaml_theme.clone();
+            }
+            ConfigSection::KeyBindings => config.keybindings = defaults.keybindings.clone(),
+            ConfigSection::EnvProfile => config.active_env_profile = defaults.active_env_profile.clone(),
+            ConfigSection::Plugins => config.plugins = defaults.plugins.clone(),
+            ConfigSection::General => prefs.general = default_prefs.general.clone(),
+            ConfigSection::Terminal => prefs.terminal = default_prefs.terminal.clone(),
+            ConfigSection::Editor => prefs.editor = default_prefs.editor.clone(),
+            ConfigSection::Ui => prefs.ui = default_prefs.ui.clone(),
+            ConfigSection::Appearance => prefs.appearance = default_prefs.appearance.clone(),
+            ConfigSection::Performance => prefs.performance = default_prefs.performance.clone(),
+            ConfigSection::Privacy => prefs.privacy = default_prefs.privacy.clone(),
+            ConfigSection::Network => prefs.network = default_prefs.network.clone(),
+            ConfigSection::Share => prefs.share = default_prefs.share.clone(),
+            ConfigSection::CrashReports => prefs.crash_reports = default_prefs.crash_reports.clone(),
+            ConfigSection::Maintenance => prefs.maintenance = default_prefs.maintenance.clone(),
+            ConfigSection::Scratch => prefs.scratch = default_prefs.scratch.clone(),
+            ConfigSection::Diagnostics => prefs.diagnostics = default_prefs.diagnostics.clone(),
+            ConfigSection::Ai => prefs.ai = default_prefs.ai.clone(),
+            ConfigSection::AiContext => prefs.ai_context = default_prefs.ai_context.clone(),
+            ConfigSection::Hooks => prefs.hooks = default_prefs.hooks.clone(),
+        }
+    }
+}
+
+/// Sections of `config` that differ from the defaults
+pub fn changed_sections(config: &AppConfig) -> Vec<ConfigSection> {
+    let defaults = AppConfig::default();
+    ConfigSection::ALL
+        .iter()
+        .copied()
+        .filter(|section| section.value(config) != section.value(&defaults))
+        .collect()
+}
+
+/// `config` with only `sections` put back to their defaults
+pub fn reset_sections(config: &AppConfig, sections: &[ConfigSection]) -> AppConfig {
+    let defaults = AppConfig::default();
+    let mut reset = config.clone();
+    for section in sections {
+        section.reset(&mut reset, &defaults);
+    }
+    reset
+}
+
+/// A saved copy of the config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigBackup {
+    pub path: PathBuf,
+    pub created: DateTime<Local>,
+}
+
+impl ConfigBackup {
+    pub fn label(&self) -> String {
+        crate::i18n::format_datetime(&self.created)
+    }
+}
+
+/// Copy the config file into the backups directory. `None` when there is no
+/// config file yet.
+pub fn backup_config(paths: &ConfigPaths, now: DateTime<Local>) -> Result<Option<PathBuf>, ConfigError> {
+    let config_file = paths.config_file();
+    if !config_file.exists() {
+        return Ok(None);
+    }
+    let dir = paths.config_backups_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| ConfigError::IoError(e.to_string()))?;
+
+    let stamp = now.format(BACKUP_TIME_FORMAT);
+    let mut backup = dir.join(format!("{}{}.toml", BACKUP_PREFIX, stamp));
+    // Two resets within a second keep both copies
+    let mut n = 1;
+    while backup.exists() {
+        backup = dir.join(format!("{}{}-{}.toml", BACKUP_PREFIX, stamp, n));
+        n += 1;
+    }
+    std::fs::copy(&config_file, &backup).map_err(|e| ConfigError::IoError(e.to_string()))?;
+    Ok(Some(backup))
+}
+
+/// Backups, newest first
+pub fn list_backups(paths: &ConfigPaths) -> Vec<ConfigBackup> {
+    let Ok(entries) = std::fs::read_dir(paths.config_backups_dir()) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<ConfigBackup> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            let created = backup_time(&path)?;
+            Some(ConfigBackup { path, created })
+        })
+        .collect();
+    backups.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| b.path.cmp(&a.path)));
+    backups
+}
+
+fn backup_time(path: &Path) -> Option<DateTime<Local>> {
+    let name = path.file_name()?.to_str()?.strip_suffix(".toml")?.strip_prefix(BACKUP_PREFIX)?;
+    let stamp = name.get(..15)?;
+    let naive = NaiveDateTime::parse_from_str(stamp, BACKUP_TIME_FORMAT).ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+/// Load a backup the same way the config file is loaded, then make it the config file
+pub fn restore_backup(paths: &ConfigPaths, backup: &Path) -> Result<AppConfig, ConfigError> {
+    let content = std::fs::read_to_string(backup).map_err(|e| ConfigError::IoError(e.to_string()))?;
+    let config = AppConfig::from_toml(&content)?;
+    std::fs::write(paths.config_file(), content).map_err(|e| ConfigError::IoError(e.to_string()))?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn customized() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.keybindings.bindings.clear();
+        config.preferences.terminal.scrollback_lines = 123;
+        config.preferences.privacy.incognito_mode = !config.preferences.privacy.incognito_mode;
+        config.active_env_profile = Some("staging".to_string());
+        config
+    }
+
+    #[test]
+    fn test_changed_sections_are_listed() {
+        assert!(changed_sections(&AppConfig::default()).is_empty());
+        assert_eq!(
+            changed_sections(&customized()),
+            vec![ConfigSection::KeyBindings, ConfigSection::EnvProfile, ConfigSection::Terminal, ConfigSection::Privacy]
+        );
+    }
+
+    #[test]
+    fn test_partial_reset_leaves_other_sections() {
+        let config = customized();
+        let reset = reset_sections(&config, &[ConfigSection::KeyBindings]);
+
+        assert_eq!(
+            serde_json::to_value(&reset.keybindings).unwrap(),
+            serde_json::to_value(&AppConfig::default().keybindings).unwrap()
+        );
+        assert_eq!(reset.preferences.terminal.scrollback_lines, 123);
+        assert_eq!(reset.preferences.privacy.incognito_mode, config.preferences.privacy.incognito_mode);
+        assert_eq!(reset.active_env_profile.as_deref(), Some("staging"));
+        assert_eq!(changed_sections(&reset), vec![ConfigSection::EnvProfile, ConfigSection::Terminal, ConfigSection::Privacy]);
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let paths = ConfigPaths::portable(temp_dir.path().to_path_buf());
+        let now = Local.with_ymd_and_hms(2026, 3, 14, 15, 9, 26).unwrap();
+        assert_eq!(backup_config(&paths, now).unwrap(), None);
+
+        let saved = toml::to_string_pretty(&customized()).unwrap();
+        std::fs::write(paths.config_file(), &saved).unwrap();
+        let first = backup_config(&paths, now).unwrap().unwrap();
+        let second = backup_config(&paths, now).unwrap().unwrap();
+        assert_eq!(first.file_name().unwrap(), "config-20260314-150926.toml");
+        assert_ne!(first, second);
+
+        let backups = list_backups(&paths);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].created, now);
+        assert_eq!(backups[0].label(), "2026-03-14 15:09:26");
+
+        std::fs::write(paths.config_file(), toml::to_string_pretty(&AppConfig::default()).unwrap()).unwrap();
+        let restored = restore_backup(&paths, &first).unwrap();
+        assert_eq!(restored.preferences.terminal.scrollback_lines, 123);
+        assert_eq!(std::fs::read_to_string(paths.config_file()).unwrap(), saved);
+
+        std::fs::write(&second, "not = [valid").unwrap();
+        assert!(restore_backup(&paths, &second).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use iced::{Color, Font};
 use std::collections::HashMap;
+use super::accessibility::VisionTarget;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThemeConfig {
@@ -10,6 +11,9 @@ pub struct ThemeConfig {
     pub spacing: Spacing,
     pub effects: Effects,
     pub custom_themes: HashMap<String, CustomTheme>,
+    /// Audience the palette was validated for. Optional.
+    #[serde(default)]
+    pub vision: VisionTarget,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -172,6 +176,7 @@ impl Default for ThemeConfig {
             spacing: Spacing::default(),
             effects: Effects::default(),
             custom_themes: HashMap::new(),
+            vision: VisionTarget::Standard,
         }
     }
 }
@@ -271,6 +276,29 @@ impl Default for AnsiColors {
 }
 
 impl AnsiColors {
+    pub fn monochrome() -> Self {
+        let grey = |v: f32| ColorValue { r: v, g: v, b: v, a: 1.0 };
+        Self {
+            black: grey(0.0),
+            red: grey(0.6),
+            green: grey(0.7),
+            yellow: grey(0.8),
+            blue: grey(0.5),
+            magenta: grey(0.55),
+            cyan: grey(0.75),
+            white: grey(0.85),
+
+            bright_black: grey(0.4),
+            bright_red: grey(0.75),
+            bright_green: grey(0.85),
+            bright_yellow: grey(0.95),
+            bright_blue: grey(0.65),
+            bright_magenta: grey(0.7),
+            bright_cyan: grey(0.9),
+            bright_white: grey(1.0),
+        }
+    }
+
     pub fn default_light() -> Self {
         Self {
             black: ColorValue { r: 0.0, g: 0.0, b: 0.0, a: 1.0 },
@@ -362,6 +390,9 @@ impl ThemeConfig {
             Self::monokai(),
             Self::solarized_dark(),
             Self::solarized_light(),
+            Self::deuteranopia_safe(),
+            Self::protanopia_safe(),
+            Self::monochrome(),
         ]
     }
 
@@ -446,7 +477,8 @@ impl ThemeConfig {
                 surface: ColorValue { r: 0.93, g: 0.91, b: 0.84, a: 1.0 },
                 surface_variant: ColorValue { r: 0.87, g: 0.85, b: 0.78, a: 1.0 },
                 
-                text: ColorValue { r: 0.40, g: 0.48, b: 0.51, a: 1.0 },
+                // base01 rather than base00 to reach AA contrast on base3
+                text: ColorValue { r: 0.35, g: 0.43, b: 0.46, a: 1.0 },
                 text_secondary: ColorValue { r: 0.51, g: 0.58, b: 0.59, a: 1.0 },
                 
                 primary: ColorValue { r: 0.15, g: 0.55, b: 0.82, a: 1.0 },
@@ -460,4 +492,103 @@ impl ThemeConfig {
             ..Self::default()
         }
     }
+
+    /// Blue/orange palette that stays distinguishable with deuteranopia
+    pub fn deuteranopia_safe() -> Self {
+        Self {
+            name: "Colorblind Safe (Deuteranopia)".to_string(),
+            colors: ColorScheme {
+                background: ColorValue { r: 0.09, g: 0.10, b: 0.12, a: 1.0 },
+                surface: ColorValue { r: 0.13, g: 0.14, b: 0.17, a: 1.0 },
+                surface_variant: ColorValue { r: 0.18, g: 0.19, b: 0.22, a: 1.0 },
+
+                text: ColorValue { r: 0.92, g: 0.92, b: 0.90, a: 1.0 },
+                text_secondary: ColorValue { r: 0.72, g: 0.72, b: 0.70, a: 1.0 },
+
+                terminal_background: ColorValue { r: 0.06, g: 0.07, b: 0.08, a: 1.0 },
+                terminal_foreground: ColorValue { r: 0.92, g: 0.92, b: 0.90, a: 1.0 },
+
+                primary: ColorValue { r: 0.34, g: 0.71, b: 0.91, a: 1.0 },
+                accent: ColorValue { r: 0.80, g: 0.47, b: 0.65, a: 1.0 },
+                success: ColorValue { r: 0.34, g: 0.71, b: 0.91, a: 1.0 },
+                warning: ColorValue { r: 0.94, g: 0.89, b: 0.26, a: 1.0 },
+                error: ColorValue { r: 0.90, g: 0.62, b: 0.0, a: 1.0 },
+
+                ..ColorScheme::default_dark()
+            },
+            vision: VisionTarget::Deuteranopia,
+            ..Self::default()
+        }
+    }
+
+    /// Light blue/vermillion palette that stays distinguishable with protanopia
+    pub fn protanopia_safe() -> Self {
+        Self {
+            name: "Colorblind Safe (Protanopia)".to_string(),
+            colors: ColorScheme {
+                background: ColorValue { r: 0.98, g: 0.98, b: 0.97, a: 1.0 },
+                surface: ColorValue { r: 0.95, g: 0.95, b: 0.94, a: 1.0 },
+                surface_variant: ColorValue { r: 0.91, g: 0.91, b: 0.90, a: 1.0 },
+
+                text: ColorValue { r: 0.10, g: 0.10, b: 0.12, a: 1.0 },
+                text_secondary: ColorValue { r: 0.35, g: 0.35, b: 0.38, a: 1.0 },
+
+                terminal_background: ColorValue { r: 0.98, g: 0.98, b: 0.97, a: 1.0 },
+                terminal_foreground: ColorValue { r: 0.10, g: 0.10, b: 0.12, a: 1.0 },
+
+                primary: ColorValue { r: 0.0, g: 0.45, b: 0.70, a: 1.0 },
+                accent: ColorValue { r: 0.80, g: 0.47, b: 0.65, a: 1.0 },
+                success: ColorValue { r: 0.0, g: 0.45, b: 0.70, a: 1.0 },
+                warning: ColorValue { r: 0.60, g: 0.45, b: 0.0, a: 1.0 },
+                error: ColorValue { r: 0.84, g: 0.37, b: 0.0, a: 1.0 },
+
+                ..ColorScheme::default_light()
+            },
+            vision: VisionTarget::Protanopia,
+            ..Self::default()
+        }
+    }
+
+    /// Greyscale only; status is conveyed by glyphs and borders
+    pub fn monochrome() -> Self {
+        let grey = |v: f32| ColorValue { r: v, g: v, b: v, a: 1.0 };
+        let overlay = |a: f32| ColorValue { r: 1.0, g: 1.0, b: 1.0, a };
+
+        Self {
+            name: "Monochrome".to_string(),
+            colors: ColorScheme {
+                background: grey(0.0),
+                surface: grey(0.08),
+                surface_variant: grey(0.14),
+
+                text: grey(0.92),
+                text_secondary: grey(0.70),
+                text_disabled: grey(0.50),
+
+                terminal_background: grey(0.0),
+                terminal_foreground: grey(0.92),
+                terminal_cursor: grey(1.0),
+                terminal_selection: overlay(0.3),
+
+                ansi_colors: AnsiColors::monochrome(),
+
+                primary: grey(1.0),
+                secondary: grey(0.6),
+                accent: grey(0.85),
+                success: grey(0.75),
+                warning: grey(0.85),
+                error: grey(1.0),
+
+                hover: overlay(0.1),
+                active: overlay(0.2),
+                focus: overlay(0.5),
+                disabled: ColorValue { r: 0.5, g: 0.5, b: 0.5, a: 0.5 },
+
+                border: grey(0.3),
+                divider: grey(0.25),
+            },
+            vision: VisionTarget::Monochrome,
+            ..Self::default()
+        }
+    }
 }
@@ -0,0 +1,151 @@
+//! Exporting the whole config to one YAML file, and importing one back.
+//!
+//! Exports leave out secrets unless asked for them, so an import keeps the
+//! secrets already here when the file has none.
+
+use serde_yaml::{Mapping, Value};
+use super::{AppConfig, ConfigError};
+
+/// Settings left out of exports by default, by their path in the file
+const SECRETS: &[&[&str]] = &[
+    &["preferences", "ai", "api_key"],
+    &["preferences", "network", "proxy_password"],
+];
+
+/// How an imported file combines with the config in use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportStrategy {
+    /// The file's settings, with defaults for any it leaves out
+    Replace,
+    /// The file's settings over the ones in use, keeping local key bindings
+    #[default]
+    Merge,
+}
+
+/// The config as YAML, without secrets unless `include_secrets`
+pub fn export_yaml(config: &AppConfig, include_secrets: bool) -> Result<String, ConfigError> {
+    let mut value = serde_yaml::to_value(config).map_err(|e| ConfigError::SerializeError(e.to_string()))?;
+    if !include_secrets {
+        for path in SECRETS {
+            take(&mut value, path);
+        }
+    }
+    serde_yaml::to_string(&value).map_err(|e| ConfigError::SerializeError(e.to_string()))
+}
+
+/// The config an exported file describes, combined with `local` as
+/// `strategy` says. Nothing is saved.
+pub fn import_yaml(content: &str, local: &AppConfig, strategy: ImportStrategy) -> Result<AppConfig, ConfigError> {
+    let imported: Value = serde_yaml::from_str(content).map_err(|e| ConfigError::ParseError(e.to_string()))?;
+    if !imported.is_mapping() {
+        return Err(ConfigError::ParseError("expected a mapping of settings".to_string()));
+    }
+    let to_value = |config: &AppConfig| serde_yaml::to_value(config).map_err(|e| ConfigError::SerializeError(e.to_string()));
+    let mut local_value = to_value(local)?;
+    let mut combined = match strategy {
+        ImportStrategy::Replace => {
+            let mut defaults = to_value(&AppConfig::default())?;
+            for path in SECRETS {
+                if let Some(secret) = take(&mut local_value, path) {
+                    put(&mut defaults, path, secret);
+                }
+            }
+            defaults
+        }
+        ImportStrategy::Merge => local_value,
+    };
+    merge(&mut combined, imported);
+    let mut config: AppConfig = serde_yaml::from_value(combined).map_err(|e| ConfigError::ParseError(e.to_string()))?;
+    if strategy == ImportStrategy::Merge {
+        config.keybindings = local.keybindings.clone();
+    }
+    Ok(config)
+}
+
+/// Mappings are combined key by key; anything else in `from` wins
+fn merge(into: &mut Value, from: Value) {
+    match (into, from) {
+        (Value::Mapping(into), Value::Mapping(from)) => {
+            for (key, value) in from {
+                match into.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        into.insert(key, value);
+                    }
+                }
+            }
+        }
+        (into, from) => *into = from,
+    }
+}
+
+fn take(value: &mut Value, path: &[&str]) -> Option<Value> {
+    let (last, parents) = path.split_last()?;
+    let mut current = value;
+    for key in parents {
+        current = current.get_mut(*key)?;
+    }
+    current.as_mapping_mut()?.remove(*last)
+}
+
+fn put(value: &mut Value, path: &[&str], secret: Value) {
+    let Some((last, parents)) = path.split_last() else { return };
+    let mut current = value;
+    for key in parents {
+        let Some(mapping) = current.as_mapping_mut() else { return };
+        current = mapping.entry(Value::from(*key)).or_insert_with(|| Value::Mapping(Mapping::new()));
+    }
+    if let Some(mapping) = current.as_mapping_mut() {
+        mapping.insert(Value::from(*last), secret);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{Action, KeySequence};
+
+    fn local() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.preferences.ai.api_key = Some("sk-local".to_string());
+        config.preferences.terminal.scrollback_lines = 500;
+        config.keybindings.set(Action::Palette, vec!["Ctrl+K".parse::<KeySequence>().unwrap()]);
+        config
+    }
+
+    #[test]
+    fn test_export_redacts_secrets_unless_asked() {
+        let config = local();
+        let redacted = export_yaml(&config, false).unwrap();
+        assert!(!redacted.contains("sk-local"));
+        assert!(!redacted.contains("api_key"));
+        assert!(export_yaml(&config, true).unwrap().contains("sk-local"));
+    }
+
+    #[test]
+    fn test_import_strategies() {
+        let mut exported = AppConfig::default();
+        exported.preferences.ui.font_size = 16.0;
+        exported.keybindings.set(Action::Palette, Vec::new());
+        let file = export_yaml(&exported, false).unwrap();
+        let local = local();
+
+        let merged = import_yaml(&file, &local, ImportStrategy::Merge).unwrap();
+        assert_eq!(merged.preferences.ui.font_size, 16.0);
+        assert_eq!(merged.keybindings, local.keybindings);
+        assert_eq!(merged.preferences.ai.api_key.as_deref(), Some("sk-local"));
+
+        let replaced = import_yaml(&file, &local, ImportStrategy::Replace).unwrap();
+        assert_eq!(replaced.keybindings, exported.keybindings);
+        assert_eq!(replaced.preferences.terminal.scrollback_lines, 10000);
+        assert_eq!(replaced.preferences.ai.api_key.as_deref(), Some("sk-local"));
+
+        // A partial file only changes what it names
+        let partial = "preferences:\n  ui:\n    font_size: 9.0\n";
+        let merged = import_yaml(partial, &local, ImportStrategy::Merge).unwrap();
+        assert_eq!(merged.preferences.ui.font_size, 9.0);
+        assert_eq!(merged.preferences.terminal.scrollback_lines, 500);
+
+        assert!(matches!(import_yaml("- not settings", &local, ImportStrategy::Merge), Err(ConfigError::ParseError(_))));
+    }
+}
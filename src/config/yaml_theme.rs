@@ -226,6 +226,7 @@ impl YamlTheme {
             spacing: Spacing::default(),
             effects,
             custom_themes: HashMap::new(),
+            vision: Default::default(),
         })
     }
 
@@ -454,6 +455,203 @@ impl YamlTheme {
     }
 }
 
+/// A color scheme from another terminal, converted to a theme
+#[derive(Debug, Clone)]
+pub struct ImportedScheme {
+    pub theme: YamlTheme,
+    /// Colors the scheme lacked or had in a form we can't read; the
+    /// default theme's are used
+    pub defaulted: Vec<String>,
+    /// Entries of the scheme with no counterpart in a theme
+    pub skipped: Vec<String>,
+}
+
+/// A color of a theme that schemes from other terminals set
+#[derive(Debug, Clone, Copy)]
+enum SchemeSlot {
+    Background,
+    Foreground,
+    Cursor,
+    Selection,
+    Normal(usize),
+    Bright(usize),
+}
+
+const ANSI_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+impl ImportedScheme {
+    /// Start from the default theme; UI colors, font and effects are left
+    /// to be derived from the scheme's colors
+    fn new(name: &str) -> Self {
+        let mut theme = YamlTheme::from_theme_config(&ThemeConfig::default());
+        theme.name = Some(name.to_string());
+        theme.ui_colors = None;
+        theme.font = None;
+        theme.effects = None;
+        Self { theme, defaulted: Vec::new(), skipped: Vec::new() }
+    }
+
+    fn set(&mut self, slot: SchemeSlot, source: &str, color: Option<ColorValue>) {
+        let Some(color) = color else {
+            self.defaulted.push(source.to_string());
+            return;
+        };
+        let target = match slot {
+            SchemeSlot::Background => &mut self.theme.background,
+            SchemeSlot::Foreground => &mut self.theme.foreground,
+            SchemeSlot::Cursor => self.theme.cursor.get_or_insert_with(String::new),
+            SchemeSlot::Selection => self.theme.selection.get_or_insert_with(String::new),
+            SchemeSlot::Normal(index) => ansi_color_mut(&mut self.theme.terminal_colors.normal, index),
+            SchemeSlot::Bright(index) => ansi_color_mut(&mut self.theme.terminal_colors.bright, index),
+        };
+        *target = color_to_hex(&color);
+    }
+}
+
+fn ansi_color_mut(set: &mut AnsiColorSet, index: usize) -> &mut String {
+    match index {
+        0 => &mut set.black,
+        1 => &mut set.red,
+        2 => &mut set.green,
+        3 => &mut set.yellow,
+        4 => &mut set.blue,
+        5 => &mut set.magenta,
+        6 => &mut set.cyan,
+        _ => &mut set.white,
+    }
+}
+
+impl YamlTheme {
+    /// Convert an iTerm2 `.itermcolors` file or an Alacritty YAML or TOML
+    /// scheme. The theme is named after the file.
+    pub fn import_scheme<P: AsRef<std::path::Path>>(path: P) -> Result<ImportedScheme, YamlThemeError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| YamlThemeError::IoError(e.to_string()))?;
+        let name = path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Imported Theme".to_string());
+        let extension = path.extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "itermcolors" => Self::from_itermcolors(&content, &name),
+            "toml" => {
+                let scheme = toml::from_str(&content)
+                    .map_err(|e| YamlThemeError::ParseError(e.to_string()))?;
+                Self::from_alacritty(scheme, &name)
+            }
+            "yml" | "yaml" => {
+                let scheme = serde_yaml::from_str(&content)
+                    .map_err(|e| YamlThemeError::ParseError(e.to_string()))?;
+                Self::from_alacritty(scheme, &name)
+            }
+            _ => Err(YamlThemeError::InvalidFormat(format!(
+                "{}: expected an .itermcolors, .toml or .yml scheme",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Convert an iTerm2 color preset, an XML property list of colors
+    /// given as red, green and blue components between 0 and 1
+    pub fn from_itermcolors(plist: &str, name: &str) -> Result<ImportedScheme, YamlThemeError> {
+        let entry = regex::Regex::new(r"(?s)<key>([^<]*)</key>\s*<dict>(.*?)</dict>").unwrap();
+        let component = regex::Regex::new(
+            r"<key>(Red|Green|Blue|Alpha) Component</key>\s*<(?:real|integer)>([^<]*)</"
+        ).unwrap();
+
+        let mut entries: HashMap<String, Option<ColorValue>> = HashMap::new();
+        for captures in entry.captures_iter(plist) {
+            let mut color = ColorValue { r: -1.0, g: -1.0, b: -1.0, a: 1.0 };
+            for parts in component.captures_iter(&captures[2]) {
+                let Ok(value) = parts[2].trim().parse::<f32>() else { continue };
+                let value = value.clamp(0.0, 1.0);
+                match &parts[1] {
+                    "Red" => color.r = value,
+                    "Green" => color.g = value,
+                    "Blue" => color.b = value,
+                    _ => color.a = value,
+                }
+            }
+            let complete = color.r >= 0.0 && color.g >= 0.0 && color.b >= 0.0;
+            entries.insert(captures[1].trim().to_string(), complete.then_some(color));
+        }
+        if entries.is_empty() {
+            return Err(YamlThemeError::InvalidFormat("no colors found in the property list".to_string()));
+        }
+
+        let mut imported = ImportedScheme::new(name);
+        let mut take = |slot, key: String| {
+            let color = entries.remove(&key).flatten();
+            imported.set(slot, &key, color);
+        };
+        take(SchemeSlot::Background, "Background Color".to_string());
+        take(SchemeSlot::Foreground, "Foreground Color".to_string());
+        take(SchemeSlot::Cursor, "Cursor Color".to_string());
+        take(SchemeSlot::Selection, "Selection Color".to_string());
+        for index in 0..8 {
+            take(SchemeSlot::Normal(index), format!("Ansi {} Color", index));
+            take(SchemeSlot::Bright(index), format!("Ansi {} Color", index + 8));
+        }
+
+        imported.skipped = entries.into_keys().collect();
+        imported.skipped.sort();
+        Ok(imported)
+    }
+
+    /// Convert the `colors` section of an Alacritty configuration, parsed
+    /// from YAML or TOML
+    pub fn from_alacritty(scheme: serde_json::Value, name: &str) -> Result<ImportedScheme, YamlThemeError> {
+        let serde_json::Value::Object(mut root) = scheme else {
+            return Err(YamlThemeError::InvalidFormat("expected a table of settings".to_string()));
+        };
+        let Some(serde_json::Value::Object(mut colors)) = root.remove("colors") else {
+            return Err(YamlThemeError::MissingField("colors".to_string()));
+        };
+
+        let mut imported = ImportedScheme::new(name);
+        let mut take = |slot, section: &str, key: &str| {
+            let color = colors
+                .get_mut(section)
+                .and_then(|section| section.as_object_mut())
+                .and_then(|section| section.remove(key))
+                .and_then(|value| alacritty_color(&value));
+            imported.set(slot, &format!("colors.{}.{}", section, key), color);
+        };
+        take(SchemeSlot::Background, "primary", "background");
+        take(SchemeSlot::Foreground, "primary", "foreground");
+        take(SchemeSlot::Cursor, "cursor", "cursor");
+        take(SchemeSlot::Selection, "selection", "background");
+        for (index, color) in ANSI_NAMES.iter().enumerate() {
+            take(SchemeSlot::Normal(index), "normal", color);
+            take(SchemeSlot::Bright(index), "bright", color);
+        }
+
+        let mut skipped: Vec<String> = root.keys().cloned().collect();
+        for (section, value) in colors {
+            match value {
+                serde_json::Value::Object(rest) => {
+                    skipped.extend(rest.keys().map(|key| format!("colors.{}.{}", section, key)));
+                }
+                _ => skipped.push(format!("colors.{}", section)),
+            }
+        }
+        skipped.sort();
+        imported.skipped = skipped;
+        Ok(imported)
+    }
+}
+
+/// Alacritty writes colors as `#rrggbb` or `0xrrggbb`; `CellForeground`
+/// and the like aren't colors of their own
+fn alacritty_color(value: &serde_json::Value) -> Option<ColorValue> {
+    let color = value.as_str()?.trim();
+    let hex = color.strip_prefix("0x").or_else(|| color.strip_prefix('#'))?;
+    parse_hex_color(hex).ok()
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum YamlThemeError {
     #[error("Parse error: {0}")]
@@ -667,7 +865,7 @@ mod tests {
 
     #[test]
     fn test_yaml_theme_conversion() {
-        let yaml_str = r#"
+        let yaml_str = r##"
 name: "Test Theme"
 accent: "#009688"
 background: "#2f343f"
@@ -691,7 +889,7 @@ terminal_colors:
     magenta: "#9e5e83"
     cyan: "#37c3d6"
     white: "#f9f9f9"
-"#;
+"##;
 
         let theme = YamlTheme::from_yaml(yaml_str).unwrap();
         assert_eq!(theme.name.as_ref().unwrap(), "Test Theme");
@@ -700,4 +898,84 @@ terminal_colors:
         let theme_config = theme.to_theme_config().unwrap();
         assert_eq!(theme_config.name, "Test Theme");
     }
+
+    #[test]
+    fn test_import_itermcolors() {
+        let component = |name: &str, value: f32| format!("<key>{} Component</key><real>{}</real>", name, value);
+        let color = |key: &str, r: f32, g: f32, b: f32| {
+            format!(
+                "<key>{}</key>\n<dict>{}{}{}<key>Color Space</key><string>sRGB</string></dict>\n",
+                key, component("Red", r), component("Green", g), component("Blue", b)
+            )
+        };
+        let mut plist = String::from("<?xml version=\"1.0\"?>\n<plist version=\"1.0\">\n<dict>\n");
+        for index in 0..16 {
+            plist.push_str(&color(&format!("Ansi {} Color", index), 0.0, 0.0, index as f32 / 15.0));
+        }
+        plist.push_str(&color("Background Color", 0.0, 0.0, 0.0));
+        plist.push_str(&color("Foreground Color", 1.0, 1.0, 1.0));
+        plist.push_str(&color("Bold Color", 1.0, 0.0, 0.0));
+        plist.push_str("</dict>\n</plist>\n");
+
+        let imported = YamlTheme::from_itermcolors(&plist, "Night").unwrap();
+        assert_eq!(imported.theme.name.as_deref(), Some("Night"));
+        assert_eq!(imported.theme.background, "#000000");
+        assert_eq!(imported.theme.foreground, "#ffffff");
+        assert_eq!(imported.theme.terminal_colors.normal.black, "#000000");
+        assert_eq!(imported.theme.terminal_colors.bright.white, "#0000ff");
+        assert_eq!(imported.defaulted, vec!["Cursor Color", "Selection Color"]);
+        assert_eq!(imported.skipped, vec!["Bold Color"]);
+        assert!(imported.theme.validate().is_ok());
+    }
+
+    #[test]
+    fn test_import_alacritty_toml_and_yaml() {
+        let toml_scheme = r##"
+[colors.primary]
+background = "0x1d1f21"
+foreground = "#c5c8c6"
+dim_foreground = "#828482"
+
+[colors.cursor]
+text = "CellBackground"
+cursor = "CellForeground"
+
+[colors.normal]
+black = "#1d1f21"
+red = "#cc6666"
+green = "#b5bd68"
+yellow = "#f0c674"
+blue = "#81a2be"
+magenta = "#b294bb"
+cyan = "#8abeb7"
+white = "#c5c8c6"
+
+[colors.bright]
+black = "#666666"
+red = "#d54e53"
+green = "#b9ca4a"
+yellow = "#e7c547"
+blue = "#7aa6da"
+magenta = "#c397d8"
+cyan = "#70c0b1"
+white = "#eaeaea"
+
+[font]
+size = 12
+"##;
+        let scheme: serde_json::Value = toml::from_str(toml_scheme).unwrap();
+        let imported = YamlTheme::from_alacritty(scheme, "tomorrow-night").unwrap();
+        assert_eq!(imported.theme.background, "#1d1f21");
+        assert_eq!(imported.theme.terminal_colors.bright.white, "#eaeaea");
+        assert_eq!(imported.defaulted, vec!["colors.cursor.cursor", "colors.selection.background"]);
+        assert_eq!(imported.skipped, vec!["colors.cursor.text", "colors.primary.dim_foreground", "font"]);
+        assert!(imported.theme.to_theme_config().is_ok());
+
+        let yaml_scheme = "colors:\n  primary:\n    background: '0x282a36'\n  normal:\n    red: '0xff5555'\n";
+        let scheme: serde_json::Value = serde_yaml::from_str(yaml_scheme).unwrap();
+        let imported = YamlTheme::from_alacritty(scheme, "dracula").unwrap();
+        assert_eq!(imported.theme.background, "#282a36");
+        assert_eq!(imported.theme.terminal_colors.normal.red, "#ff5555");
+        assert_eq!(imported.defaulted.len(), 18);
+    }
 }
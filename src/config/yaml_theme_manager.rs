@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use crate::config::{AppConfig, ThemeConfig, ConfigError};
-use super::yaml_theme::{YamlTheme, YamlThemeError};
+use super::yaml_theme::{ImportedScheme, YamlTheme, YamlThemeError};
 
 pub struct YamlThemeManager {
     themes_dir: PathBuf,
@@ -132,6 +132,21 @@ impl YamlThemeManager {
         self.import_theme_from_string(&content, None)
     }
 
+    /// Import an iTerm2 or Alacritty color scheme, saved under the scheme
+    /// file's name
+    pub fn import_scheme_file<P: AsRef<Path>>(&mut self, path: P) -> Result<ImportedScheme, YamlThemeError> {
+        let imported = YamlTheme::import_scheme(path)?;
+        let theme_name = imported.theme.name.clone().unwrap_or_default();
+
+        let file_path = self.themes_dir.join(format!("{}.yaml", sanitize_filename(&theme_name)));
+        imported.theme.to_file(&file_path)?;
+
+        self.loaded_themes.insert(theme_name.clone(), imported.theme.clone());
+        self.theme_cache.remove(&theme_name);
+
+        Ok(imported)
+    }
+
     /// Export theme to YAML string
     pub fn export_theme_to_string(&self, theme_config: &ThemeConfig) -> Result<String, YamlThemeError> {
         let yaml_theme = YamlTheme::from_theme_config(theme_config);
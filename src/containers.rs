@@ -0,0 +1,286 @@
+//! Running Docker and Podman containers, to run commands in with `exec`.
+//!
+//! Containers are listed through the runtime's API socket when one answers
+//! (`DOCKER_HOST`, `/var/run/docker.sock`, or Podman's socket under
+//! `XDG_RUNTIME_DIR`), and through `docker ps` / `podman ps` otherwise.
+//! Exec, logs, stop and restart always go through the CLI of the runtime the
+//! container was found with.
+//!
+//! Each command runs in an `exec` of its own, so `cd` and exported
+//! variables don't carry over to the next one.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use serde::Deserialize;
+use thiserror::Error;
+
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(2);
+/// Log lines shown before following new ones
+const LOG_TAIL: usize = 200;
+const PS_FORMAT: &str = "{{.ID}}\t{{.Names}}\t{{.Image}}\t{{.Status}}";
+
+#[derive(Error, Debug)]
+pub enum ContainerError {
+    #[error("No container runtime found: neither Docker nor Podman is installed or running")]
+    NoRuntime,
+    #[error("{runtime}: {message}")]
+    Runtime { runtime: &'static str, message: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runtime {
+    Docker,
+    Podman,
+}
+
+impl Runtime {
+    pub fn program(self) -> &'static str {
+        match self {
+            Runtime::Docker => "docker",
+            Runtime::Podman => "podman",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub status: String,
+    pub runtime: Runtime,
+}
+
+impl Container {
+    /// `command` as a host command line running it in this container.
+    /// `env` names the variables passed through from the host.
+    pub fn exec_line(&self, command: &str, env: &[String], tty: bool) -> String {
+        let mut argv = vec![self.runtime.program().to_string(), "exec".to_string()];
+        argv.push(if tty { "-it" } else { "-i" }.to_string());
+        for name in env {
+            argv.push("-e".to_string());
+            argv.push(name.clone());
+        }
+        argv.extend([self.id.clone(), "sh".to_string(), "-c".to_string(), command.to_string()]);
+        crate::scratch::command_line(&argv)
+    }
+
+    /// Host command line following the container's logs
+    pub fn logs_line(&self) -> String {
+        format!("{} logs --follow --tail {} {}", self.runtime.program(), LOG_TAIL, self.id)
+    }
+
+    /// `name (image) · status`, for lists
+    pub fn summary(&self) -> String {
+        format!("{} ({}) · {}", self.name, self.image, self.status)
+    }
+}
+
+/// Stopping and restarting ask for confirmation first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerAction {
+    Stop,
+    Restart,
+}
+
+impl ContainerAction {
+    pub fn verb(self) -> &'static str {
+        match self {
+            ContainerAction::Stop => "Stop",
+            ContainerAction::Restart => "Restart",
+        }
+    }
+
+    pub fn done(self) -> &'static str {
+        match self {
+            ContainerAction::Stop => "Stopped",
+            ContainerAction::Restart => "Restarted",
+        }
+    }
+
+    fn subcommand(self) -> &'static str {
+        match self {
+            ContainerAction::Stop => "stop",
+            ContainerAction::Restart => "restart",
+        }
+    }
+
+    pub fn run(self, container: &Container) -> Result<(), ContainerError> {
+        cli(container.runtime, &[self.subcommand(), &container.id]).map(|_| ())
+    }
+}
+
+/// Running containers of whichever runtimes answer, Docker's first
+pub fn list() -> Result<Vec<Container>, ContainerError> {
+    let env: HashMap<String, String> = std::env::vars().collect();
+    for (runtime, socket) in sockets(&env) {
+        match list_via_socket(runtime, &socket) {
+            Ok(containers) => return Ok(containers),
+            Err(e) => log::debug!("Cannot list containers through {}: {}", socket.display(), e),
+        }
+    }
+
+    let mut failure = None;
+    for runtime in [Runtime::Docker, Runtime::Podman] {
+        match cli(runtime, &["ps", "--format", PS_FORMAT]) {
+            Ok(output) => return Ok(parse_ps(&output, runtime)),
+            Err(ContainerError::NoRuntime) => {}
+            Err(e) => failure = failure.or(Some(e)),
+        }
+    }
+    Err(failure.unwrap_or(ContainerError::NoRuntime))
+}
+
+/// API sockets to try, with the runtime each belongs to
+fn sockets(env: &HashMap<String, String>) -> Vec<(Runtime, PathBuf)> {
+    let mut sockets = Vec::new();
+    match env.get("DOCKER_HOST") {
+        Some(host) => {
+            if let Some(path) = host.strip_prefix("unix://") {
+                sockets.push((Runtime::Docker, PathBuf::from(path)));
+            }
+        }
+        None => sockets.push((Runtime::Docker, PathBuf::from("/var/run/docker.sock"))),
+    }
+    if let Some(runtime_dir) = env.get("XDG_RUNTIME_DIR") {
+        sockets.push((Runtime::Podman, PathBuf::from(runtime_dir).join("podman/podman.sock")));
+    }
+    sockets.push((Runtime::Podman, PathBuf::from("/run/podman/podman.sock")));
+    sockets
+}
+
+#[cfg(unix)]
+fn list_via_socket(runtime: Runtime, socket: &std::path::Path) -> std::io::Result<Vec<Container>> {
+    let mut stream = std::os::unix::net::UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+    stream.set_write_timeout(Some(SOCKET_TIMEOUT))?;
+    // HTTP/1.0, so the reply isn't chunked and ends when the socket closes
+    stream.write_all(b"GET /containers/json HTTP/1.0\r\nHost: localhost\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    parse_api_response(&response, runtime)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "unexpected reply"))
+}
+
+#[cfg(not(unix))]
+fn list_via_socket(_runtime: Runtime, _socket: &std::path::Path) -> std::io::Result<Vec<Container>> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "no unix sockets"))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiContainer {
+    id: String,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    status: String,
+}
+
+fn parse_api_response(response: &str, runtime: Runtime) -> Option<Vec<Container>> {
+    let (head, body) = response.split_once("\r\n\r\n")?;
+    if head.split_whitespace().nth(1) != Some("200") {
+        return None;
+    }
+    let listed: Vec<ApiContainer> = serde_json::from_str(body).ok()?;
+    Some(
+        listed
+            .into_iter()
+            .map(|container| Container {
+                name: container.names.first().map(|name| name.trim_start_matches('/').to_string()).unwrap_or_default(),
+                id: container.id.chars().take(12).collect(),
+                image: container.image,
+                status: container.status,
+                runtime,
+            })
+            .collect(),
+    )
+}
+
+/// `ps` output in `PS_FORMAT`, one container per line
+fn parse_ps(output: &str, runtime: Runtime) -> Vec<Container> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some(Container {
+                id: fields.next()?.to_string(),
+                name: fields.next()?.to_string(),
+                image: fields.next()?.to_string(),
+                status: fields.next().unwrap_or_default().to_string(),
+                runtime,
+            })
+        })
+        .collect()
+}
+
+/// Stdout of the runtime's CLI, or its stderr as the error
+fn cli(runtime: Runtime, args: &[&str]) -> Result<String, ContainerError> {
+    let output = std::process::Command::new(runtime.program()).args(args).output().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            ContainerError::NoRuntime
+        } else {
+            ContainerError::Runtime { runtime: runtime.program(), message: e.to_string() }
+        }
+    })?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(ContainerError::Runtime {
+            runtime: runtime.program(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn web() -> Container {
+        Container {
+            id: "3f2a9c1b7d4e".to_string(),
+            name: "web".to_string(),
+            image: "nginx:1.25".to_string(),
+            status: "Up 2 hours".to_string(),
+            runtime: Runtime::Docker,
+        }
+    }
+
+    #[test]
+    fn test_parse_listings() {
+        let ps = parse_ps("3f2a9c1b7d4e\tweb\tnginx:1.25\tUp 2 hours\n\n", Runtime::Docker);
+        assert_eq!(ps, vec![web()]);
+
+        let response = "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n\
+            [{\"Id\":\"3f2a9c1b7d4e0123456789\",\"Names\":[\"/web\"],\"Image\":\"nginx:1.25\",\"Status\":\"Up 2 hours\",\"State\":\"running\"}]";
+        assert_eq!(parse_api_response(response, Runtime::Docker), Some(vec![web()]));
+        assert_eq!(parse_api_response("HTTP/1.0 500 Internal Server Error\r\n\r\n{}", Runtime::Docker), None);
+    }
+
+    #[test]
+    fn test_exec_line_quotes_the_command() {
+        let line = web().exec_line("echo 'hi' && ls", &["TOKEN".to_string()], true);
+        assert_eq!(line, r"docker exec -it -e TOKEN 3f2a9c1b7d4e sh -c 'echo '\''hi'\'' && ls'");
+        assert_eq!(web().logs_line(), "docker logs --follow --tail 200 3f2a9c1b7d4e");
+    }
+
+    #[test]
+    fn test_sockets_follow_docker_host() {
+        let env = HashMap::from([
+            ("DOCKER_HOST".to_string(), "unix:///home/me/.docker/run/docker.sock".to_string()),
+            ("XDG_RUNTIME_DIR".to_string(), "/run/user/1000".to_string()),
+        ]);
+        assert_eq!(sockets(&env), vec![
+            (Runtime::Docker, PathBuf::from("/home/me/.docker/run/docker.sock")),
+            (Runtime::Podman, PathBuf::from("/run/user/1000/podman/podman.sock")),
+            (Runtime::Podman, PathBuf::from("/run/podman/podman.sock")),
+        ]);
+        let remote = HashMap::from([("DOCKER_HOST".to_string(), "tcp://10.0.0.2:2376".to_string())]);
+        assert_eq!(sockets(&remote).len(), 1);
+    }
+}
@@ -0,0 +1,317 @@
+//! Crash reporting. Every report is scrubbed and written to the local
+//! crash-reports directory so it can be inspected with `neoterm crashes`;
+//! it is only uploaded to Sentry once the user has agreed and a DSN is set.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sentry::protocol::{Event, Exception, Value};
+use crate::config::{CrashReportConsent, CrashReportPreferences};
+use crate::redaction::{Redactor, REDACTED};
+
+/// Extra and tag keys whose values are command lines or environment values
+const SENSITIVE_KEYS: &[&str] = &["command", "input", "args", "argv", "cwd", "env", "environment"];
+
+pub struct CrashReporter {
+    dir: PathBuf,
+    redactor: Redactor,
+    upload: bool,
+    duplicate_window: Duration,
+    /// Fingerprint of each recent report and when it was last kept
+    recent: Mutex<HashMap<String, Instant>>,
+}
+
+impl CrashReporter {
+    pub fn new(dir: PathBuf, redactor: Redactor, prefs: &CrashReportPreferences) -> Self {
+        Self {
+            dir,
+            redactor,
+            upload: prefs.consent == CrashReportConsent::Granted && prefs.dsn.is_some(),
+            duplicate_window: Duration::from_secs(prefs.duplicate_window_secs),
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Scrub, drop duplicates, keep a local copy, and return the event only if
+    /// it may be uploaded. Used as Sentry's `before_send`.
+    pub fn process(&self, event: Event<'static>) -> Option<Event<'static>> {
+        let event = scrub_event(event, &self.redactor);
+        if !self.first_in_window(&fingerprint(&event), Instant::now()) {
+            return None;
+        }
+        if let Err(e) = self.write(&event) {
+            log::warn!("Could not save crash report: {}", e);
+        }
+        self.upload.then_some(event)
+    }
+
+    fn first_in_window(&self, fingerprint: &str, now: Instant) -> bool {
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|_, seen| now.duration_since(*seen) < self.duplicate_window);
+        if recent.contains_key(fingerprint) {
+            return false;
+        }
+        recent.insert(fingerprint.to_string(), now);
+        true
+    }
+
+    fn write(&self, event: &Event<'static>) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("{}.json", event.event_id.simple()));
+        std::fs::write(&path, serde_json::to_string_pretty(event)?)?;
+        Ok(path)
+    }
+}
+
+/// Remove anything that could identify the user or reveal what they ran
+pub fn scrub_event(mut event: Event<'static>, redactor: &Redactor) -> Event<'static> {
+    event.message = event.message.map(|message| redactor.redact(&message));
+    event.user = None;
+    event.request = None;
+    event.server_name = None;
+
+    for exception in event.exception.values.iter_mut() {
+        exception.value = exception.value.as_deref().map(|value| redactor.redact(value));
+        if let Some(stacktrace) = exception.stacktrace.as_mut() {
+            for frame in stacktrace.frames.iter_mut() {
+                frame.vars.clear();
+            }
+        }
+    }
+
+    event.breadcrumbs.values.retain(|crumb| crumb.category.as_deref() != Some("command"));
+    for crumb in event.breadcrumbs.values.iter_mut() {
+        crumb.message = crumb.message.as_deref().map(|message| redactor.redact(message));
+        crumb.data.clear();
+    }
+
+    event.extra.retain(|key, _| !is_sensitive_key(key));
+    for value in event.extra.values_mut() {
+        scrub_value(value, redactor);
+    }
+    event.tags.retain(|key, _| !is_sensitive_key(key));
+    for value in event.tags.values_mut() {
+        *value = redactor.redact(value);
+    }
+
+    event
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SENSITIVE_KEYS.contains(&key.as_str()) || crate::redaction::is_secret_key(&key)
+}
+
+fn scrub_value(value: &mut Value, redactor: &Redactor) {
+    match value {
+        Value::String(text) => *text = redactor.redact(text),
+        Value::Array(items) => items.iter_mut().for_each(|item| scrub_value(item, redactor)),
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if is_sensitive_key(key) {
+                    *item = Value::String(REDACTED.to_string());
+                } else {
+                    scrub_value(item, redactor);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reports with the same message and exception are duplicates
+fn fingerprint(event: &Event<'static>) -> String {
+    let exceptions: Vec<String> = event.exception.values
+        .iter()
+        .map(|e| format!("{}: {}", e.ty, e.value.as_deref().unwrap_or("")))
+        .collect();
+    format!("{}|{}", event.message.as_deref().unwrap_or(""), exceptions.join("|"))
+}
+
+fn event_from_panic(info: &std::panic::PanicHookInfo<'_>) -> Event<'static> {
+    let message = info.payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string());
+    let location = info.location().map(|l| format!(" at {}:{}", l.file(), l.line())).unwrap_or_default();
+
+    Event {
+        level: sentry::Level::Fatal,
+        message: Some(format!("{}{}", message, location)),
+        exception: vec![Exception {
+            ty: "panic".to_string(),
+            value: Some(message),
+            stacktrace: sentry::integrations::backtrace::current_stacktrace(),
+            ..Default::default()
+        }]
+        .into(),
+        release: sentry::release_name!(),
+        ..Default::default()
+    }
+}
+
+/// Keep local reports of panics and, with consent and a DSN, upload them.
+/// The returned guard flushes pending uploads when dropped.
+pub fn install(prefs: &CrashReportPreferences, dir: PathBuf) -> Option<sentry::ClientInitGuard> {
+    let mut redactor = Redactor::new();
+    let env: HashMap<String, String> = std::env::vars().collect();
+    redactor.register_env(&env);
+    let reporter = Arc::new(CrashReporter::new(dir, redactor, prefs));
+
+    let dsn = prefs.dsn.as_deref().and_then(|dsn| match dsn.parse::<sentry::types::Dsn>() {
+        Ok(dsn) => Some(dsn),
+        Err(e) => {
+            log::warn!("Ignoring invalid crash report DSN: {}", e);
+            None
+        }
+    });
+    let guard = match dsn {
+        Some(dsn) if reporter.upload => {
+            let before_send = Arc::clone(&reporter);
+            Some(sentry::init(sentry::ClientOptions {
+                dsn: Some(dsn),
+                release: sentry::release_name!(),
+                // Panics are captured by the hook below, after scrubbing
+                default_integrations: false,
+                send_default_pii: false,
+                before_send: Some(Arc::new(move |event| before_send.process(event))),
+                ..Default::default()
+            }))
+        }
+        _ => None,
+    };
+    let uploading = guard.is_some();
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let event = event_from_panic(info);
+        if uploading {
+            // before_send scrubs, deduplicates and saves it locally
+            sentry::capture_event(event);
+            if let Some(client) = sentry::Hub::current().client() {
+                client.flush(Some(Duration::from_secs(2)));
+            }
+        } else {
+            reporter.process(event);
+        }
+        previous(info);
+    }));
+
+    guard
+}
+
+/// A saved report, for `neoterm crashes`
+#[derive(Debug, Clone)]
+pub struct SavedReport {
+    pub id: String,
+    pub path: PathBuf,
+    pub event: Event<'static>,
+}
+
+/// Saved reports, newest first
+pub fn list(dir: &Path) -> Vec<SavedReport> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<SavedReport> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let event = serde_json::from_str(&std::fs::read_to_string(&path).ok()?).ok()?;
+            let id = path.file_stem()?.to_string_lossy().to_string();
+            Some(SavedReport { id, path, event })
+        })
+        .collect();
+    reports.sort_by(|a, b| b.event.timestamp.cmp(&a.event.timestamp));
+    reports
+}
+
+/// The report whose id starts with `prefix`, if exactly one does
+pub fn find(dir: &Path, prefix: &str) -> Option<SavedReport> {
+    let mut matches = list(dir).into_iter().filter(|report| report.id.starts_with(prefix));
+    let found = matches.next()?;
+    matches.next().is_none().then_some(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sentry::protocol::{Breadcrumb, Map};
+    use tempfile::TempDir;
+
+    const FAKE_KEY: &str = "sk-test1234567890abcdefghij";
+
+    fn leaky_event() -> Event<'static> {
+        let mut extra = Map::new();
+        extra.insert("command".to_string(), Value::from(format!("curl -H 'x-api-key: {}' api.example.com", FAKE_KEY)));
+        extra.insert("detail".to_string(), serde_json::json!({ "note": format!("key was {}", FAKE_KEY), "OPENAI_API_KEY": "plain" }));
+        let mut crumb_data = Map::new();
+        crumb_data.insert("env".to_string(), Value::from("AWS_SECRET_ACCESS_KEY=hunter2hunter2"));
+
+        Event {
+            message: Some(format!("request failed with {}", FAKE_KEY)),
+            exception: vec![Exception {
+                ty: "panic".to_string(),
+                value: Some(format!("bad key {}", FAKE_KEY)),
+                ..Default::default()
+            }]
+            .into(),
+            breadcrumbs: vec![
+                Breadcrumb { category: Some("command".to_string()), message: Some("git push".to_string()), ..Default::default() },
+                Breadcrumb { category: Some("ui".to_string()), message: Some(FAKE_KEY.to_string()), data: crumb_data, ..Default::default() },
+            ]
+            .into(),
+            extra,
+            server_name: Some("ada-laptop".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_scrubber_removes_api_key_and_commands() {
+        let event = scrub_event(leaky_event(), &Redactor::new());
+        let json = serde_json::to_string(&event).unwrap();
+
+        assert!(!json.contains(FAKE_KEY), "{}", json);
+        assert!(!json.contains("curl"));
+        assert!(!json.contains("git push"));
+        assert!(!json.contains("hunter2"));
+        assert!(!json.contains("ada-laptop"));
+        assert!(!event.extra.contains_key("command"));
+        assert_eq!(event.message.as_deref(), Some("request failed with [REDACTED]"));
+        assert_eq!(event.extra["detail"]["OPENAI_API_KEY"], REDACTED);
+    }
+
+    #[test]
+    fn test_reports_are_saved_locally_without_consent() {
+        let temp_dir = TempDir::new().unwrap();
+        let reporter = CrashReporter::new(temp_dir.path().to_path_buf(), Redactor::new(), &CrashReportPreferences::default());
+
+        assert!(reporter.process(leaky_event()).is_none(), "nothing is uploaded without consent");
+
+        let reports = list(temp_dir.path());
+        assert_eq!(reports.len(), 1);
+        assert!(!std::fs::read_to_string(&reports[0].path).unwrap().contains(FAKE_KEY));
+        assert!(find(temp_dir.path(), &reports[0].id[..8]).is_some());
+    }
+
+    #[test]
+    fn test_duplicates_are_rate_limited() {
+        let temp_dir = TempDir::new().unwrap();
+        let prefs = CrashReportPreferences {
+            consent: CrashReportConsent::Granted,
+            dsn: Some("https://public@sentry.example.com/1".to_string()),
+            ..Default::default()
+        };
+        let reporter = CrashReporter::new(temp_dir.path().to_path_buf(), Redactor::new(), &prefs);
+
+        assert!(reporter.process(leaky_event()).is_some());
+        assert!(reporter.process(leaky_event()).is_none());
+        let different = Event { message: Some("other failure".to_string()), ..Default::default() };
+        assert!(reporter.process(different).is_some());
+        assert_eq!(list(temp_dir.path()).len(), 2);
+    }
+}
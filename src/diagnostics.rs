@@ -0,0 +1,424 @@
+//! Health of NeoTerm's subsystems in one place: each subsystem reports a
+//! row with its status, a few live numbers and, where it helps, an action
+//! that fixes the usual problem. The same report backs the Diagnostics
+//! block and `GET /v1/diagnostics`.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// How often an open Diagnostics block refreshes
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(3);
+/// AI requests averaged for the latency figure
+const LATENCY_WINDOW: usize = 20;
+/// Average AI latency above this is reported as degraded
+const SLOW_AI: Duration = Duration::from_secs(20);
+/// Average AI suggestion latency above this is reported as degraded
+const SLOW_SUGGESTION: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+    Down,
+}
+
+impl HealthStatus {
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            HealthStatus::Ok => "●",
+            HealthStatus::Degraded => "◐",
+            HealthStatus::Down => "○",
+        }
+    }
+}
+
+/// Fixes offered next to a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticAction {
+    /// Stop waiting for the AI reply in progress
+    ClearAiQueue,
+    /// Run retention and cleanup now instead of waiting for the schedule
+    RunMaintenance,
+    ReloadPlugins,
+}
+
+impl DiagnosticAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DiagnosticAction::ClearAiQueue => "Clear AI queue",
+            DiagnosticAction::RunMaintenance => "Run maintenance",
+            DiagnosticAction::ReloadPlugins => "Reload plugins",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stat {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiagnosticRow {
+    pub subsystem: String,
+    pub status: HealthStatus,
+    pub summary: String,
+    pub stats: Vec<Stat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<DiagnosticAction>,
+}
+
+impl DiagnosticRow {
+    pub fn new(subsystem: impl Into<String>, status: HealthStatus, summary: impl Into<String>) -> Self {
+        Self {
+            subsystem: subsystem.into(),
+            status,
+            summary: summary.into(),
+            stats: Vec::new(),
+            action: None,
+        }
+    }
+
+    pub fn stat(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.stats.push(Stat { name: name.into(), value: value.to_string() });
+        self
+    }
+
+    pub fn action(mut self, action: DiagnosticAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+}
+
+/// Anything that can report on its own health
+pub trait HealthProvider {
+    fn check(&self) -> DiagnosticRow;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiagnosticsReport {
+    pub generated_at: DateTime<Utc>,
+    /// The worst status of any row
+    pub status: HealthStatus,
+    pub rows: Vec<DiagnosticRow>,
+}
+
+impl DiagnosticsReport {
+    pub fn collect(providers: &[&dyn HealthProvider], now: DateTime<Utc>) -> Self {
+        let rows: Vec<DiagnosticRow> = providers.iter().map(|provider| provider.check()).collect();
+        let status = rows.iter().map(|row| row.status).max().unwrap_or(HealthStatus::Ok);
+        Self { generated_at: now, status, rows }
+    }
+
+    /// Plain-text form, one line per row
+    pub fn lines(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let stats: Vec<String> = row.stats.iter().map(|s| format!("{}={}", s.name, s.value)).collect();
+                format!("{} {:<10} {}  {}", row.status.glyph(), row.subsystem, row.summary, stats.join(" "))
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+}
+
+/// Rolling average of the last few AI response times
+#[derive(Debug, Clone, Default)]
+pub struct LatencyTracker {
+    samples: VecDeque<Duration>,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        if self.samples.len() == LATENCY_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        let count = self.samples.len() as u32;
+        (count > 0).then(|| self.samples.iter().sum::<Duration>() / count)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Commands with output still streaming in
+pub struct CommandsHealth {
+    pub running: usize,
+    /// Output channels with a bell detector still attached
+    pub open_streams: usize,
+}
+
+impl HealthProvider for CommandsHealth {
+    fn check(&self) -> DiagnosticRow {
+        DiagnosticRow::new("commands", HealthStatus::Ok, format!("{} running", self.running))
+            .stat("running", self.running)
+            .stat("open_streams", self.open_streams)
+    }
+}
+
+pub struct AiHealth<'a> {
+    pub status: Option<&'a crate::agent_mode_eval::availability::AiStatus>,
+    pub streaming: bool,
+    pub latency: &'a LatencyTracker,
+}
+
+impl HealthProvider for AiHealth<'_> {
+    fn check(&self) -> DiagnosticRow {
+        use crate::agent_mode_eval::availability::AiStatus;
+
+        let average = self.latency.average();
+        let mut row = match self.status {
+            None => DiagnosticRow::new("ai", HealthStatus::Down, "not available"),
+            Some(AiStatus::Unconfigured { provider, key_variable }) => {
+                DiagnosticRow::new("ai", HealthStatus::Down, format!("{}: set {}", provider, key_variable))
+            }
+            Some(AiStatus::Ready { provider, model }) => {
+                let status = if average.is_some_and(|avg| avg > SLOW_AI) {
+                    HealthStatus::Degraded
+                } else {
+                    HealthStatus::Ok
+                };
+                DiagnosticRow::new("ai", status, format!("{} {}", provider, model))
+            }
+        };
+        row = row
+            .stat("in_flight", u8::from(self.streaming))
+            .stat("avg_latency_ms", average.map_or("-".to_string(), |avg| avg.as_millis().to_string()))
+            .stat("samples", self.latency.len());
+        if self.streaming {
+            row = row.action(DiagnosticAction::ClearAiQueue);
+        }
+        row
+    }
+}
+
+/// Ghost-text suggestions in the input
+pub struct SuggestionsHealth<'a> {
+    pub enabled: bool,
+    pub ai_enabled: bool,
+    pub history_latency: &'a LatencyTracker,
+    pub ai_latency: &'a LatencyTracker,
+}
+
+impl HealthProvider for SuggestionsHealth<'_> {
+    fn check(&self) -> DiagnosticRow {
+        let summary = match (self.enabled, self.ai_enabled) {
+            (false, _) => "off",
+            (true, false) => "history",
+            (true, true) => "history and AI",
+        };
+        let micros = |latency: &LatencyTracker| latency.average().map_or("-".to_string(), |avg| avg.as_micros().to_string());
+        let ai_average = self.ai_latency.average();
+        // A suggestion that arrives after the next keystroke is no use
+        let status = if ai_average.is_some_and(|avg| avg > SLOW_SUGGESTION) {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Ok
+        };
+        DiagnosticRow::new("suggestions", status, summary)
+            .stat("history_avg_us", micros(self.history_latency))
+            .stat("ai_avg_ms", ai_average.map_or("-".to_string(), |avg| avg.as_millis().to_string()))
+            .stat("ai_samples", self.ai_latency.len())
+    }
+}
+
+pub struct CacheHealth {
+    pub entries: usize,
+    pub bytes: u64,
+    pub limit_bytes: u64,
+}
+
+impl HealthProvider for CacheHealth {
+    fn check(&self) -> DiagnosticRow {
+        let over = self.bytes > self.limit_bytes;
+        let status = if over { HealthStatus::Degraded } else { HealthStatus::Ok };
+        let summary = format!("{:.1} of {:.0} MB", mib(self.bytes), mib(self.limit_bytes));
+        let row = DiagnosticRow::new("cache", status, summary).stat("entries", self.entries);
+        if over {
+            row.action(DiagnosticAction::RunMaintenance)
+        } else {
+            row
+        }
+    }
+}
+
+pub struct PluginsHealth {
+    pub loaded: usize,
+    /// Plugins enabled in the config that failed to load
+    pub failed: Vec<String>,
+}
+
+impl HealthProvider for PluginsHealth {
+    fn check(&self) -> DiagnosticRow {
+        if self.failed.is_empty() {
+            DiagnosticRow::new("plugins", HealthStatus::Ok, format!("{} loaded", self.loaded))
+                .stat("loaded", self.loaded)
+        } else {
+            DiagnosticRow::new("plugins", HealthStatus::Degraded, format!("failed: {}", self.failed.join(", ")))
+                .stat("loaded", self.loaded)
+                .stat("failed", self.failed.len())
+                .action(DiagnosticAction::ReloadPlugins)
+        }
+    }
+}
+
+pub struct MaintenanceHealth {
+    pub due: bool,
+}
+
+impl HealthProvider for MaintenanceHealth {
+    fn check(&self) -> DiagnosticRow {
+        if self.due {
+            DiagnosticRow::new("maintenance", HealthStatus::Ok, "due").action(DiagnosticAction::RunMaintenance)
+        } else {
+            DiagnosticRow::new("maintenance", HealthStatus::Ok, "up to date")
+        }
+    }
+}
+
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// Latest report, shared with the HTTP endpoint
+pub type SharedReport = Arc<RwLock<Option<DiagnosticsReport>>>;
+
+/// `GET /v1/diagnostics`: the latest report as JSON, 503 before the first one
+pub fn routes(shared: SharedReport) -> impl warp::Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    use warp::Filter;
+
+    warp::path!("v1" / "diagnostics").and(warp::get()).map(move || {
+        match shared.read().ok().and_then(|report| report.clone()) {
+            Some(report) => warp::reply::with_status(warp::reply::json(&report), warp::http::StatusCode::OK),
+            None => warp::reply::with_status(
+                warp::reply::json(&serde_json::json!({ "error": "no report yet" })),
+                warp::http::StatusCode::SERVICE_UNAVAILABLE,
+            ),
+        }
+    })
+}
+
+/// Serve the endpoint on localhost only, until the application exits
+pub async fn serve(port: u16, shared: SharedReport) {
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    match warp::serve(routes(shared)).try_bind_ephemeral(address) {
+        Ok((bound, server)) => {
+            log::info!("Diagnostics available at http://{}/v1/diagnostics", bound);
+            server.await;
+        }
+        Err(e) => log::warn!("Cannot serve diagnostics on {}: {}", address, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_mode_eval::availability::AiStatus;
+
+    struct Fixed(DiagnosticRow);
+
+    impl HealthProvider for Fixed {
+        fn check(&self) -> DiagnosticRow {
+            self.0.clone()
+        }
+    }
+
+    fn stat<'a>(row: &'a DiagnosticRow, name: &str) -> &'a str {
+        &row.stats.iter().find(|s| s.name == name).unwrap().value
+    }
+
+    #[test]
+    fn test_report_aggregates_every_provider() {
+        let mut latency = LatencyTracker::new();
+        for ms in [100, 200, 300] {
+            latency.record(Duration::from_millis(ms));
+        }
+        let ready = AiStatus::Ready { provider: "OpenAI".to_string(), model: "gpt-4o".to_string() };
+
+        let commands = CommandsHealth { running: 2, open_streams: 2 };
+        let ai = AiHealth { status: Some(&ready), streaming: true, latency: &latency };
+        let cache = CacheHealth { entries: 3, bytes: 5 * 1024 * 1024, limit_bytes: 1024 * 1024 };
+        let plugins = PluginsHealth { loaded: 1, failed: Vec::new() };
+        let maintenance = MaintenanceHealth { due: false };
+        let report = DiagnosticsReport::collect(&[&commands, &ai, &cache, &plugins, &maintenance], Utc::now());
+
+        let subsystems: Vec<&str> = report.rows.iter().map(|r| r.subsystem.as_str()).collect();
+        assert_eq!(subsystems, vec!["commands", "ai", "cache", "plugins", "maintenance"]);
+        assert_eq!(stat(&report.rows[0], "running"), "2");
+        assert_eq!(stat(&report.rows[1], "avg_latency_ms"), "200");
+        assert_eq!(report.rows[1].action, Some(DiagnosticAction::ClearAiQueue));
+        assert_eq!(report.rows[2].status, HealthStatus::Degraded);
+        assert_eq!(report.rows[2].action, Some(DiagnosticAction::RunMaintenance));
+        assert_eq!(report.status, HealthStatus::Degraded);
+    }
+
+    #[test]
+    fn test_worst_row_sets_overall_status() {
+        let ok = Fixed(DiagnosticRow::new("a", HealthStatus::Ok, ""));
+        let down = Fixed(DiagnosticRow::new("b", HealthStatus::Down, "gone"));
+        assert_eq!(DiagnosticsReport::collect(&[&ok], Utc::now()).status, HealthStatus::Ok);
+        assert_eq!(DiagnosticsReport::collect(&[&ok, &down], Utc::now()).status, HealthStatus::Down);
+        assert_eq!(DiagnosticsReport::collect(&[], Utc::now()).status, HealthStatus::Ok);
+    }
+
+    #[test]
+    fn test_slow_ai_suggestions_degrade() {
+        let history = LatencyTracker::new();
+        let mut ai = LatencyTracker::new();
+        ai.record(Duration::from_millis(2500));
+        let row = SuggestionsHealth { enabled: true, ai_enabled: true, history_latency: &history, ai_latency: &ai }.check();
+        assert_eq!(row.status, HealthStatus::Degraded);
+        assert_eq!(stat(&row, "ai_avg_ms"), "2500");
+        assert_eq!(stat(&row, "history_avg_us"), "-");
+    }
+
+    #[test]
+    fn test_latency_average_is_rolling() {
+        let mut latency = LatencyTracker::new();
+        assert_eq!(latency.average(), None);
+        for _ in 0..LATENCY_WINDOW {
+            latency.record(Duration::from_secs(60));
+        }
+        latency.record(Duration::from_secs(60 - LATENCY_WINDOW as u64));
+        assert_eq!(latency.len(), LATENCY_WINDOW);
+        assert_eq!(latency.average(), Some(Duration::from_secs(59)));
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_serves_latest_report() {
+        let shared: SharedReport = Arc::new(RwLock::new(None));
+        let filter = routes(shared.clone());
+
+        let response = warp::test::request().path("/v1/diagnostics").reply(&filter).await;
+        assert_eq!(response.status(), 503);
+
+        let row = Fixed(DiagnosticRow::new("ai", HealthStatus::Ok, "OpenAI").stat("samples", 4));
+        *shared.write().unwrap() = Some(DiagnosticsReport::collect(&[&row], Utc::now()));
+        let response = warp::test::request().path("/v1/diagnostics").reply(&filter).await;
+        assert_eq!(response.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["rows"][0]["stats"][0]["value"], "4");
+    }
+}
@@ -0,0 +1,344 @@
+//! Directory blocks: a structured `ls` with entries that can be opened,
+//! sorted and paged through. The block keeps its own selection, sort and
+//! page; moving into another directory, opening files and deleting them are
+//! left to the app as `DirEffect`s, since they touch the tracked cwd, the
+//! editor and the disk.
+
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+
+/// Entries shown per page
+pub const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Directory,
+    Symlink,
+    Executable,
+    File,
+}
+
+impl EntryKind {
+    pub fn icon(self) -> &'static str {
+        match self {
+            EntryKind::Directory => "📁",
+            EntryKind::Symlink => "🔗",
+            EntryKind::Executable => "⚙",
+            EntryKind::File => "📄",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    /// Whether it leads to a directory, following symlinks
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<DateTime<Local>>,
+}
+
+impl Entry {
+    fn read(entry: &std::fs::DirEntry) -> Self {
+        let path = entry.path();
+        let link = entry.file_type().map(|kind| kind.is_symlink()).unwrap_or(false);
+        // Broken links still list, as links
+        let metadata = std::fs::metadata(&path).or_else(|_| entry.metadata()).ok();
+        let is_dir = metadata.as_ref().is_some_and(|metadata| metadata.is_dir());
+        let kind = if link {
+            EntryKind::Symlink
+        } else if is_dir {
+            EntryKind::Directory
+        } else if metadata.as_ref().is_some_and(is_executable) {
+            EntryKind::Executable
+        } else {
+            EntryKind::File
+        };
+        Self {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            path,
+            kind,
+            is_dir,
+            size: metadata.as_ref().filter(|_| !is_dir).map_or(0, |metadata| metadata.len()),
+            modified: metadata.and_then(|metadata| metadata.modified().ok()).map(DateTime::<Local>::from),
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.name.starts_with('.')
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Modified,
+}
+
+impl SortKey {
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "Name",
+            SortKey::Size => "Size",
+            SortKey::Modified => "Modified",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DirMessage {
+    Select(usize),
+    /// Enter the directory at this index, or open the file in the editor
+    Open(usize),
+    /// Enter the selected entry
+    OpenSelected,
+    MoveSelection(isize),
+    Up,
+    /// Show the file at this index in a preview block
+    Preview(usize),
+    /// Sort by this key; the current key again reverses the order
+    SortBy(SortKey),
+    Page(isize),
+    CopyPath(usize),
+    RequestDelete(usize),
+    ConfirmDelete,
+    CancelDelete,
+}
+
+/// What a directory block asks the app to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum DirEffect {
+    /// Make this the working directory and list it
+    Enter(PathBuf),
+    Edit(PathBuf),
+    Preview(PathBuf),
+    CopyPath(PathBuf),
+    Delete(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirListing {
+    pub dir: PathBuf,
+    /// Sorted; directories come first
+    pub entries: Vec<Entry>,
+    pub sort: SortKey,
+    pub descending: bool,
+    pub page: usize,
+    /// Index into `entries`
+    pub selected: Option<usize>,
+    /// Entry awaiting confirmation to be deleted
+    pub pending_delete: Option<usize>,
+}
+
+impl DirListing {
+    pub fn read(dir: &Path) -> std::io::Result<Self> {
+        let mut listing = Self {
+            dir: dir.to_path_buf(),
+            entries: Vec::new(),
+            sort: SortKey::Name,
+            descending: false,
+            page: 0,
+            selected: None,
+            pending_delete: None,
+        };
+        listing.reload()?;
+        Ok(listing)
+    }
+
+    /// Read the directory again, keeping the sort and, where it still
+    /// exists, the selected entry
+    pub fn reload(&mut self) -> std::io::Result<()> {
+        let selected = self.selected_entry().map(|entry| entry.name.clone());
+        self.entries = std::fs::read_dir(&self.dir)?.flatten().map(|entry| Entry::read(&entry)).collect();
+        self.pending_delete = None;
+        self.sort_entries();
+        self.selected = selected.and_then(|name| self.entries.iter().position(|entry| entry.name == name));
+        self.page = self.page.min(self.pages() - 1);
+        Ok(())
+    }
+
+    fn sort_entries(&mut self) {
+        let (sort, descending) = (self.sort, self.descending);
+        self.entries.sort_by(|a, b| {
+            let order = match sort {
+                SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortKey::Size => a.size.cmp(&b.size),
+                SortKey::Modified => a.modified.cmp(&b.modified),
+            };
+            let order = if descending { order.reverse() } else { order };
+            b.is_dir.cmp(&a.is_dir).then(order)
+        });
+    }
+
+    pub fn pages(&self) -> usize {
+        self.entries.len().div_ceil(PAGE_SIZE).max(1)
+    }
+
+    /// The current page's entries, with their indices
+    pub fn page_entries(&self) -> impl Iterator<Item = (usize, &Entry)> {
+        self.entries.iter().enumerate().skip(self.page * PAGE_SIZE).take(PAGE_SIZE)
+    }
+
+    pub fn selected_entry(&self) -> Option<&Entry> {
+        self.selected.and_then(|index| self.entries.get(index))
+    }
+
+    pub fn update(&mut self, message: DirMessage) -> Option<DirEffect> {
+        match message {
+            DirMessage::Select(index) => {
+                self.selected = Some(index).filter(|&index| index < self.entries.len());
+                None
+            }
+            DirMessage::Open(index) => {
+                let entry = self.entries.get(index)?;
+                Some(if entry.is_dir { DirEffect::Enter(entry.path.clone()) } else { DirEffect::Edit(entry.path.clone()) })
+            }
+            DirMessage::OpenSelected => self.selected.and_then(|index| self.update(DirMessage::Open(index))),
+            DirMessage::MoveSelection(step) => {
+                if self.entries.is_empty() {
+                    return None;
+                }
+                let index = match self.selected {
+                    Some(index) => (index as isize + step).clamp(0, self.entries.len() as isize - 1) as usize,
+                    None => self.page * PAGE_SIZE,
+                };
+                self.selected = Some(index);
+                self.page = index / PAGE_SIZE;
+                None
+            }
+            DirMessage::Up => self.dir.parent().map(|parent| DirEffect::Enter(parent.to_path_buf())),
+            DirMessage::Preview(index) => {
+                self.entries.get(index).filter(|entry| !entry.is_dir).map(|entry| DirEffect::Preview(entry.path.clone()))
+            }
+            DirMessage::SortBy(key) => {
+                self.descending = key == self.sort && !self.descending;
+                self.sort = key;
+                let selected = self.selected_entry().map(|entry| entry.path.clone());
+                self.sort_entries();
+                self.selected = selected.and_then(|path| self.entries.iter().position(|entry| entry.path == path));
+                self.page = 0;
+                None
+            }
+            DirMessage::Page(step) => {
+                self.page = (self.page as isize + step).clamp(0, self.pages() as isize - 1) as usize;
+                None
+            }
+            DirMessage::CopyPath(index) => self.entries.get(index).map(|entry| DirEffect::CopyPath(entry.path.clone())),
+            DirMessage::RequestDelete(index) => {
+                self.pending_delete = Some(index).filter(|&index| index < self.entries.len());
+                None
+            }
+            DirMessage::ConfirmDelete => {
+                let index = self.pending_delete.take()?;
+                self.entries.get(index).map(|entry| DirEffect::Delete(entry.path.clone()))
+            }
+            DirMessage::CancelDelete => {
+                self.pending_delete = None;
+                None
+            }
+        }
+    }
+}
+
+/// `4.2 KB`, with one decimal from KB up
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Delete a file or link, or a directory with everything in it
+pub fn delete(path: &Path) -> std::io::Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn names(listing: &DirListing) -> Vec<&str> {
+        listing.entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sorting_keeps_directories_first() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("b.txt"), "1").unwrap();
+        std::fs::write(dir.path().join("A.md"), "12345").unwrap();
+
+        let mut listing = DirListing::read(dir.path()).unwrap();
+        assert_eq!(names(&listing), vec!["src", "A.md", "b.txt"]);
+        assert_eq!(listing.entries[0].kind, EntryKind::Directory);
+
+        listing.update(DirMessage::SortBy(SortKey::Size));
+        assert_eq!(names(&listing), vec!["src", "b.txt", "A.md"]);
+        listing.update(DirMessage::SortBy(SortKey::Size));
+        assert!(listing.descending);
+        assert_eq!(names(&listing), vec!["src", "A.md", "b.txt"]);
+    }
+
+    #[test]
+    fn test_navigation_and_paging() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        for i in 0..PAGE_SIZE + 5 {
+            std::fs::write(dir.path().join(format!("f{:03}", i)), "").unwrap();
+        }
+        let mut listing = DirListing::read(dir.path()).unwrap();
+        assert_eq!(listing.pages(), 2);
+        assert_eq!(listing.page_entries().count(), PAGE_SIZE);
+
+        assert_eq!(listing.update(DirMessage::Open(0)), Some(DirEffect::Enter(dir.path().join("sub"))));
+        assert_eq!(listing.update(DirMessage::Open(1)), Some(DirEffect::Edit(dir.path().join("f000"))));
+        assert_eq!(listing.update(DirMessage::Preview(0)), None);
+        assert_eq!(listing.update(DirMessage::Preview(1)), Some(DirEffect::Preview(dir.path().join("f000"))));
+        assert_eq!(listing.update(DirMessage::Up), dir.path().parent().map(|parent| DirEffect::Enter(parent.to_path_buf())));
+
+        listing.update(DirMessage::Select(PAGE_SIZE - 1));
+        listing.update(DirMessage::MoveSelection(1));
+        assert_eq!(listing.page, 1);
+        listing.update(DirMessage::Page(5));
+        assert_eq!(listing.page, 1);
+
+        listing.update(DirMessage::RequestDelete(1));
+        assert_eq!(listing.update(DirMessage::ConfirmDelete), Some(DirEffect::Delete(dir.path().join("f000"))));
+        assert_eq!(listing.pending_delete, None);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(4300), "4.2 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GB");
+    }
+}
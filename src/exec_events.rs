@@ -0,0 +1,231 @@
+//! Event schema for streamed command output.
+//!
+//! `neoterm exec --output ndjson` writes one of these per line, and
+//! `to_sse` gives the server-sent-event framing of the same object, so
+//! consumers only learn one format.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::shell::CommandEvent;
+use crate::timeline::OutputStream;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExecEvent {
+    Start {
+        id: Uuid,
+        timestamp: DateTime<Utc>,
+        command: String,
+        cwd: String,
+    },
+    Stdout {
+        id: Uuid,
+        timestamp: DateTime<Utc>,
+        /// Milliseconds since `start`
+        offset_ms: u64,
+        data: String,
+    },
+    Stderr {
+        id: Uuid,
+        timestamp: DateTime<Utc>,
+        offset_ms: u64,
+        data: String,
+    },
+    Exit {
+        id: Uuid,
+        timestamp: DateTime<Utc>,
+        offset_ms: u64,
+        code: i32,
+    },
+}
+
+impl ExecEvent {
+    /// Value of the `event` tag
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExecEvent::Start { .. } => "start",
+            ExecEvent::Stdout { .. } => "stdout",
+            ExecEvent::Stderr { .. } => "stderr",
+            ExecEvent::Exit { .. } => "exit",
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        match self {
+            ExecEvent::Start { id, .. }
+            | ExecEvent::Stdout { id, .. }
+            | ExecEvent::Stderr { id, .. }
+            | ExecEvent::Exit { id, .. } => *id,
+        }
+    }
+
+    /// One line of newline-delimited JSON
+    pub fn to_ndjson(&self) -> String {
+        let mut line = serde_json::to_string(self).expect("exec events always serialize");
+        line.push('\n');
+        line
+    }
+
+    /// Server-sent-event frame carrying the same JSON object
+    pub fn to_sse(&self) -> String {
+        format!("event: {}\nid: {}\ndata: {}\n\n", self.name(), self.id(), self.to_ndjson().trim_end())
+    }
+}
+
+/// Turns the shell's output events for one command into `ExecEvent`s
+#[derive(Debug, Clone)]
+pub struct ExecEventStream {
+    id: Uuid,
+    started_at: DateTime<Utc>,
+    started: std::time::Instant,
+}
+
+impl ExecEventStream {
+    pub fn new(id: Uuid, started_at: DateTime<Utc>) -> Self {
+        Self { id, started_at, started: std::time::Instant::now() }
+    }
+
+    pub fn start(&self, command: &str, cwd: &str) -> ExecEvent {
+        ExecEvent::Start {
+            id: self.id,
+            timestamp: self.started_at,
+            command: command.to_string(),
+            cwd: cwd.to_string(),
+        }
+    }
+
+    pub fn convert(&self, event: CommandEvent) -> ExecEvent {
+        match event {
+            CommandEvent::Chunk(chunk) => {
+                let timestamp = self.at(chunk.offset_ms);
+                match chunk.stream {
+                    OutputStream::Stdout => ExecEvent::Stdout { id: self.id, timestamp, offset_ms: chunk.offset_ms, data: chunk.text },
+                    OutputStream::Stderr => ExecEvent::Stderr { id: self.id, timestamp, offset_ms: chunk.offset_ms, data: chunk.text },
+                }
+            }
+            CommandEvent::Exited(code) => {
+                let offset_ms = self.started.elapsed().as_millis() as u64;
+                ExecEvent::Exit { id: self.id, timestamp: self.at(offset_ms), offset_ms, code }
+            }
+        }
+    }
+
+    fn at(&self, offset_ms: u64) -> DateTime<Utc> {
+        self.started_at + Duration::milliseconds(offset_ms as i64)
+    }
+}
+
+/// Whole-run result for `--output json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExecSummary {
+    pub id: Uuid,
+    pub command: String,
+    pub exit_code: i32,
+    pub duration_ms: u64,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl ExecSummary {
+    pub fn record(&mut self, event: &ExecEvent) {
+        match event {
+            ExecEvent::Start { id, command, .. } => {
+                self.id = *id;
+                self.command = command.clone();
+            }
+            ExecEvent::Stdout { data, .. } => self.stdout.push_str(data),
+            ExecEvent::Stderr { data, .. } => self.stderr.push_str(data),
+            ExecEvent::Exit { code, offset_ms, .. } => {
+                self.exit_code = *code;
+                self.duration_ms = *offset_ms;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::OutputChunk;
+    use serde_json::{json, Value};
+
+    fn stream() -> ExecEventStream {
+        let id = Uuid::parse_str("6f1c2d3e-4a5b-4c6d-8e7f-001122334455").unwrap();
+        let started_at = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        ExecEventStream::new(id, started_at)
+    }
+
+    #[test]
+    fn test_event_schema() {
+        let stream = stream();
+        let events = [
+            stream.start("make test", "/work"),
+            stream.convert(CommandEvent::Chunk(OutputChunk {
+                offset_ms: 1500,
+                stream: OutputStream::Stderr,
+                text: "warning: unused\n".to_string(),
+            })),
+        ];
+        let json: Vec<Value> = events.iter().map(|e| serde_json::from_str(&e.to_ndjson()).unwrap()).collect();
+
+        assert_eq!(json, vec![
+            json!({
+                "event": "start",
+                "id": "6f1c2d3e-4a5b-4c6d-8e7f-001122334455",
+                "timestamp": "2024-05-01T12:00:00Z",
+                "command": "make test",
+                "cwd": "/work",
+            }),
+            json!({
+                "event": "stderr",
+                "id": "6f1c2d3e-4a5b-4c6d-8e7f-001122334455",
+                "timestamp": "2024-05-01T12:00:01.500Z",
+                "offset_ms": 1500,
+                "data": "warning: unused\n",
+            }),
+        ]);
+    }
+
+    #[test]
+    fn test_exit_event_and_round_trip() {
+        let stream = stream();
+        let exit = stream.convert(CommandEvent::Exited(3));
+
+        let value: Value = serde_json::from_str(&exit.to_ndjson()).unwrap();
+        assert_eq!(value["event"], "exit");
+        assert_eq!(value["code"], 3);
+        assert!(value["offset_ms"].is_u64());
+
+        let parsed: ExecEvent = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, exit);
+    }
+
+    #[test]
+    fn test_ndjson_and_sse_carry_the_same_object() {
+        let event = stream().start("ls", "/");
+        let line = event.to_ndjson();
+        assert!(line.ends_with('\n') && !line.trim_end().contains('\n'));
+
+        let sse = event.to_sse();
+        assert!(sse.starts_with("event: start\nid: 6f1c2d3e-4a5b-4c6d-8e7f-001122334455\n"));
+        assert!(sse.contains(&format!("data: {}\n\n", line.trim_end())));
+    }
+
+    #[test]
+    fn test_summary_collects_events() {
+        let stream = stream();
+        let mut summary = ExecSummary::default();
+        for event in [
+            stream.start("echo hi", "/"),
+            stream.convert(CommandEvent::Chunk(OutputChunk { offset_ms: 5, stream: OutputStream::Stdout, text: "hi\n".to_string() })),
+            stream.convert(CommandEvent::Exited(0)),
+        ] {
+            summary.record(&event);
+        }
+
+        assert_eq!(summary.command, "echo hi");
+        assert_eq!(summary.stdout, "hi\n");
+        assert_eq!(summary.exit_code, 0);
+    }
+}
@@ -0,0 +1,195 @@
+//! File preview blocks: the start of a file, highlighted and with line
+//! numbers, or a hex dump of the first bytes when the file is binary.
+//!
+//! Other features open a preview at a line with a location such as
+//! `src/main.rs:42`, which `parse_location` splits.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::languages::LanguageManager;
+use crate::renderer::SyntaxHighlighter;
+
+/// Bytes of a file read for a preview; the rest is left out
+pub const MAX_BYTES: u64 = 256 * 1024;
+/// Lines shown of a text file
+pub const MAX_LINES: usize = 5000;
+/// Bytes shown of a binary file
+pub const HEX_BYTES: usize = 512;
+/// Bytes looked through for a NUL, which marks a file as binary
+const SNIFF_BYTES: usize = 8000;
+const HEX_ROW: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreviewContent {
+    /// Lines with color escapes from the highlighter
+    Text(Vec<String>),
+    Binary(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePreview {
+    pub path: PathBuf,
+    /// Detected language's name
+    pub language: Option<String>,
+    pub content: PreviewContent,
+    /// Size on disk
+    pub size: u64,
+    /// Whether only the start of the file is shown
+    pub truncated: bool,
+    /// 1-based line the preview was opened at
+    pub line: Option<usize>,
+}
+
+impl FilePreview {
+    pub fn read(path: &Path, line: Option<usize>) -> std::io::Result<Self> {
+        let size = std::fs::metadata(path)?.len();
+        let mut bytes = Vec::new();
+        std::fs::File::open(path)?.take(MAX_BYTES).read_to_end(&mut bytes)?;
+        let cut = size > bytes.len() as u64;
+
+        if is_binary(&bytes, cut) {
+            bytes.truncate(HEX_BYTES);
+            return Ok(Self {
+                path: path.to_path_buf(),
+                language: None,
+                truncated: size > bytes.len() as u64,
+                content: PreviewContent::Binary(bytes),
+                size,
+                line: None,
+            });
+        }
+
+        // A read cut short can end partway through a character
+        let valid = std::str::from_utf8(&bytes).map_or_else(|e| e.valid_up_to(), str::len);
+        let text = std::str::from_utf8(&bytes[..valid]).unwrap_or_default();
+        let mut lines: Vec<&str> = text.lines().collect();
+        let truncated = cut || lines.len() > MAX_LINES;
+        lines.truncate(MAX_LINES);
+
+        let token = language_token(path);
+        let highlighter = SyntaxHighlighter::shared();
+        let language = token.as_deref().and_then(|token| {
+            LanguageManager::new(HashMap::new())
+                .by_extension(token)
+                .map(|language| language.name.to_string())
+                .or_else(|| highlighter.syntax_name(token).map(str::to_string))
+        });
+        let highlighted = if lines.is_empty() {
+            Vec::new()
+        } else {
+            highlighter
+                .highlight(&lines.join("\n"), token.as_deref().unwrap_or("txt"))
+                .lines()
+                .map(str::to_string)
+                .collect()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            language,
+            content: PreviewContent::Text(highlighted),
+            size,
+            truncated,
+            line,
+        })
+    }
+
+    /// The lines without color, or the hex dump
+    pub fn plain_lines(&self) -> Vec<String> {
+        match &self.content {
+            PreviewContent::Text(lines) => lines
+                .iter()
+                .map(|line| crate::ansi::parse(line).into_iter().map(|span| span.text).collect())
+                .collect(),
+            PreviewContent::Binary(bytes) => hex_lines(bytes),
+        }
+    }
+}
+
+/// What to ask the highlighter for: the extension, or the file name for
+/// files like `Makefile` that have none
+fn language_token(path: &Path) -> Option<String> {
+    path.extension()
+        .or_else(|| path.file_name())
+        .map(|token| token.to_string_lossy().into_owned())
+}
+
+fn is_binary(bytes: &[u8], cut: bool) -> bool {
+    if bytes[..bytes.len().min(SNIFF_BYTES)].contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(_) => false,
+        Err(e) => !(cut && e.error_len().is_none()),
+    }
+}
+
+/// `xxd`-style rows: offset, 16 bytes in hex, and the printable ones
+pub fn hex_lines(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(HEX_ROW)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let (left, right) = hex.split_at(hex.len().min(HEX_ROW / 2));
+            let printable: String = chunk
+                .iter()
+                .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+                .collect();
+            format!("{:08x}  {:<23}  {:<23}  |{}|", row * HEX_ROW, left.join(" "), right.join(" "), printable)
+        })
+        .collect()
+}
+
+/// Split `src/main.rs:42` or `src/main.rs:42:7` into the path and line.
+/// Column numbers are dropped.
+pub fn parse_location(location: &str) -> (&str, Option<usize>) {
+    let numbered = |text: &str| {
+        text.rsplit_once(':')
+            .and_then(|(rest, number)| number.parse::<usize>().ok().filter(|&number| number > 0).map(|number| (rest, number)))
+    };
+    match numbered(location) {
+        Some((rest, last)) => match numbered(rest) {
+            Some((path, line)) => (path, Some(line)),
+            None => (rest, Some(last)),
+        },
+        None => (location, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(parse_location("src/main.rs:42"), ("src/main.rs", Some(42)));
+        assert_eq!(parse_location("src/main.rs:42:7"), ("src/main.rs", Some(42)));
+        assert_eq!(parse_location("src/main.rs"), ("src/main.rs", None));
+        assert_eq!(parse_location("notes:todo"), ("notes:todo", None));
+    }
+
+    #[test]
+    fn test_text_and_binary_previews() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("main.rs");
+        std::fs::write(&source, "fn main() {\n    println!(\"hi\");\n}\n").unwrap();
+        let preview = FilePreview::read(&source, Some(2)).unwrap();
+        assert_eq!(preview.language.as_deref(), Some("Rust"));
+        assert!(!preview.truncated);
+        assert_eq!(preview.plain_lines(), vec!["fn main() {", "    println!(\"hi\");", "}"]);
+
+        let binary = dir.path().join("a.out");
+        let mut bytes = b"\x7fELF\x02\x01\x01\x00".to_vec();
+        bytes.resize(HEX_BYTES * 2, 0);
+        std::fs::write(&binary, &bytes).unwrap();
+        let preview = FilePreview::read(&binary, None).unwrap();
+        assert!(preview.truncated);
+        assert_eq!(preview.content, PreviewContent::Binary(bytes[..HEX_BYTES].to_vec()));
+        assert_eq!(
+            preview.plain_lines()[0],
+            "00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|"
+        );
+    }
+}
@@ -0,0 +1,513 @@
+use iced::{Element, widget::{button, checkbox, column, row, scrollable, text, text_input}};
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::virtual_fs::{VfsError, VirtualFileSystem};
+
+/// Files with a NUL byte in their first 8 KiB are treated as binary and skipped
+const BINARY_SNIFF_LEN: usize = 8192;
+pub const DEFAULT_CONTEXT_LINES: usize = 2;
+
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub pattern: String,
+    pub is_regex: bool,
+    pub case_sensitive: bool,
+    /// Only search files matching this glob, e.g. `*.rs` or `src/**/*.toml`
+    pub glob: Option<String>,
+    pub context_lines: usize,
+}
+
+impl SearchQuery {
+    pub fn literal(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            is_regex: false,
+            case_sensitive: true,
+            glob: None,
+            context_lines: DEFAULT_CONTEXT_LINES,
+        }
+    }
+
+    pub fn regex(pattern: impl Into<String>) -> Self {
+        Self { is_regex: true, ..Self::literal(pattern) }
+    }
+
+    pub fn build_regex(&self) -> Result<Regex, SearchError> {
+        let pattern = if self.is_regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|e| SearchError::InvalidPattern(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FindMatch {
+    /// 1-based line number
+    pub line_number: usize,
+    pub line: String,
+    /// Byte range of the match within `line`
+    pub start: usize,
+    pub end: usize,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    pub matches: Vec<FindMatch>,
+}
+
+impl FileMatches {
+    pub fn selected_count(&self) -> usize {
+        self.matches.iter().filter(|m| m.selected).count()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+    #[error("Invalid glob: {0}")]
+    InvalidGlob(String),
+    #[error(transparent)]
+    Vfs(#[from] VfsError),
+}
+
+/// Search every text file under `root`, honouring .gitignore and friends.
+/// Results are sorted by path so repeated searches render in a stable order.
+pub fn search_project(root: &Path, query: &SearchQuery) -> Result<Vec<FileMatches>, SearchError> {
+    let regex = query.build_regex()?;
+
+    let mut builder = ignore::WalkBuilder::new(root);
+    if let Some(glob) = query.glob.as_deref().filter(|g| !g.trim().is_empty()) {
+        let mut overrides = ignore::overrides::OverrideBuilder::new(root);
+        overrides.add(glob).map_err(|e| SearchError::InvalidGlob(e.to_string()))?;
+        builder.overrides(overrides.build().map_err(|e| SearchError::InvalidGlob(e.to_string()))?);
+    }
+
+    let results = Arc::new(Mutex::new(Vec::new()));
+    builder.build_parallel().run(|| {
+        let regex = regex.clone();
+        let results = Arc::clone(&results);
+        let context_lines = query.context_lines;
+        Box::new(move |entry| {
+            let Ok(entry) = entry else { return ignore::WalkState::Continue };
+            if entry.file_type().map_or(false, |ft| ft.is_file()) {
+                if let Some(file) = search_file(entry.path(), &regex, context_lines) {
+                    results.lock().unwrap().push(file);
+                }
+            }
+            ignore::WalkState::Continue
+        })
+    });
+
+    let mut files = std::mem::take(&mut *results.lock().unwrap());
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn search_file(path: &Path, regex: &Regex, context_lines: usize) -> Option<FileMatches> {
+    let bytes = std::fs::read(path).ok()?;
+    if is_binary(&bytes) {
+        return None;
+    }
+    let contents = String::from_utf8(bytes).ok()?;
+    let lines: Vec<&str> = contents.lines().collect();
+
+    let mut matches = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        for found in regex.find_iter(line) {
+            matches.push(FindMatch {
+                line_number: index + 1,
+                line: line.to_string(),
+                start: found.start(),
+                end: found.end(),
+                context_before: lines[index.saturating_sub(context_lines)..index]
+                    .iter().map(|l| l.to_string()).collect(),
+                context_after: lines[(index + 1).min(lines.len())..(index + 1 + context_lines).min(lines.len())]
+                    .iter().map(|l| l.to_string()).collect(),
+                selected: true,
+            });
+        }
+    }
+
+    if matches.is_empty() {
+        None
+    } else {
+        Some(FileMatches { path: path.to_path_buf(), matches })
+    }
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
+/// Replace the selected matches of one file. Regex searches expand `$1`/`${name}`
+/// in the replacement; literal searches insert it verbatim.
+pub fn replace_selected(
+    contents: &str,
+    file: &FileMatches,
+    regex: &Regex,
+    replacement: &str,
+    expand_captures: bool,
+) -> (String, usize) {
+    let selected: HashSet<(usize, usize)> = file.matches
+        .iter()
+        .filter(|m| m.selected)
+        .map(|m| (m.line_number, m.start))
+        .collect();
+
+    let mut output = String::with_capacity(contents.len());
+    let mut replaced = 0;
+
+    // split_inclusive keeps each line's terminator so endings survive untouched
+    for (index, raw_line) in contents.split_inclusive('\n').enumerate() {
+        let body_len = raw_line.trim_end_matches(['\n', '\r']).len();
+        let (line, ending) = raw_line.split_at(body_len);
+
+        let mut last = 0;
+        for captures in regex.captures_iter(line) {
+            let found = captures.get(0).unwrap();
+            if !selected.contains(&(index + 1, found.start())) {
+                continue;
+            }
+            output.push_str(&line[last..found.start()]);
+            if expand_captures {
+                captures.expand(replacement, &mut output);
+            } else {
+                output.push_str(replacement);
+            }
+            last = found.end();
+            replaced += 1;
+        }
+        output.push_str(&line[last..]);
+        output.push_str(ending);
+    }
+
+    (output, replaced)
+}
+
+/// Stage the selected replacements in `vfs` and return how many were made.
+/// Nothing is written until the overlay is committed.
+pub fn stage_replacements(
+    vfs: &mut VirtualFileSystem,
+    files: &[FileMatches],
+    query: &SearchQuery,
+    replacement: &str,
+) -> Result<usize, SearchError> {
+    let regex = query.build_regex()?;
+    let mut total = 0;
+
+    for file in files.iter().filter(|f| f.selected_count() > 0) {
+        let contents = vfs.read(&file.path)?;
+        let (updated, replaced) = replace_selected(&contents, file, &regex, replacement, query.is_regex);
+        if replaced > 0 {
+            vfs.write(&file.path, updated)?;
+            total += replaced;
+        }
+    }
+
+    Ok(total)
+}
+
+/// State of a find-and-replace tool block
+#[derive(Debug, Clone)]
+pub struct FindReplaceState {
+    pub root: PathBuf,
+    pub pattern: String,
+    pub replacement: String,
+    pub glob: String,
+    pub is_regex: bool,
+    pub case_sensitive: bool,
+    pub results: Vec<FileMatches>,
+    pub searching: bool,
+    pub status: Option<String>,
+    /// Consolidated diff of the staged replacements, shown before applying
+    pub preview: Option<String>,
+    vfs: VirtualFileSystem,
+}
+
+#[derive(Debug, Clone)]
+pub enum FindReplaceMessage {
+    PatternChanged(String),
+    ReplacementChanged(String),
+    GlobChanged(String),
+    ToggleRegex(bool),
+    ToggleCaseSensitive(bool),
+    Search,
+    SearchFinished(Result<Vec<FileMatches>, String>),
+    ToggleMatch(usize, usize),
+    ToggleFile(usize),
+    /// Show a match in a file preview, which the app opens
+    OpenMatch(usize, usize),
+    Preview,
+    Apply,
+    CancelPreview,
+    Undo,
+}
+
+impl FindReplaceState {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            pattern: String::new(),
+            replacement: String::new(),
+            glob: String::new(),
+            is_regex: false,
+            case_sensitive: true,
+            results: Vec::new(),
+            searching: false,
+            status: None,
+            preview: None,
+            vfs: VirtualFileSystem::new(),
+        }
+    }
+
+    pub fn query(&self) -> SearchQuery {
+        SearchQuery {
+            pattern: self.pattern.clone(),
+            is_regex: self.is_regex,
+            case_sensitive: self.case_sensitive,
+            glob: Some(self.glob.clone()).filter(|g| !g.trim().is_empty()),
+            context_lines: DEFAULT_CONTEXT_LINES,
+        }
+    }
+
+    /// Handle a message. `Search` is run by the caller off the UI thread;
+    /// everything else is applied here.
+    /// File and 1-based line of a match
+    pub fn location(&self, file: usize, index: usize) -> Option<(PathBuf, usize)> {
+        let file = self.results.get(file)?;
+        file.matches.get(index).map(|found| (file.path.clone(), found.line_number))
+    }
+
+    pub fn update(&mut self, message: FindReplaceMessage) {
+        match message {
+            FindReplaceMessage::PatternChanged(pattern) => self.pattern = pattern,
+            FindReplaceMessage::ReplacementChanged(replacement) => self.replacement = replacement,
+            FindReplaceMessage::GlobChanged(glob) => self.glob = glob,
+            FindReplaceMessage::ToggleRegex(value) => self.is_regex = value,
+            FindReplaceMessage::ToggleCaseSensitive(value) => self.case_sensitive = value,
+            FindReplaceMessage::Search => {
+                self.searching = true;
+                self.preview = None;
+                self.vfs.discard();
+            }
+            FindReplaceMessage::SearchFinished(result) => {
+                self.searching = false;
+                match result {
+                    Ok(results) => {
+                        let total: usize = results.iter().map(|f| f.matches.len()).sum();
+                        self.status = Some(format!("{} matches in {} files", total, results.len()));
+                        self.results = results;
+                    }
+                    Err(e) => self.status = Some(e),
+                }
+            }
+            FindReplaceMessage::ToggleMatch(file, index) => {
+                if let Some(found) = self.results.get_mut(file).and_then(|f| f.matches.get_mut(index)) {
+                    found.selected = !found.selected;
+                }
+            }
+            FindReplaceMessage::ToggleFile(file) => {
+                if let Some(file) = self.results.get_mut(file) {
+                    let select = file.selected_count() < file.matches.len();
+                    file.matches.iter_mut().for_each(|m| m.selected = select);
+                }
+            }
+            FindReplaceMessage::OpenMatch(..) => {}
+            FindReplaceMessage::Preview => {
+                self.vfs.discard();
+                let query = self.query();
+                match stage_replacements(&mut self.vfs, &self.results, &query, &self.replacement) {
+                    Ok(0) => self.status = Some("No matches selected".to_string()),
+                    Ok(count) => {
+                        self.status = Some(format!("{} replacements staged", count));
+                        self.preview = Some(self.vfs.diff());
+                    }
+                    Err(e) => self.status = Some(e.to_string()),
+                }
+            }
+            FindReplaceMessage::Apply => {
+                self.preview = None;
+                match self.vfs.commit() {
+                    Ok(count) => {
+                        self.status = Some(format!("Updated {} files", count));
+                        self.results.clear();
+                    }
+                    Err(e) => {
+                        self.vfs.discard();
+                        self.status = Some(e.to_string());
+                    }
+                }
+            }
+            FindReplaceMessage::CancelPreview => {
+                self.preview = None;
+                self.vfs.discard();
+            }
+            FindReplaceMessage::Undo => {
+                self.status = Some(match self.vfs.undo() {
+                    Ok(count) => format!("Restored {} files", count),
+                    Err(e) => e.to_string(),
+                });
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<FindReplaceMessage> {
+        let search_row = row![
+            text_input("Find", &self.pattern)
+                .on_input(FindReplaceMessage::PatternChanged)
+                .on_submit(FindReplaceMessage::Search),
+            text_input("Files (glob)", &self.glob)
+                .on_input(FindReplaceMessage::GlobChanged)
+                .on_submit(FindReplaceMessage::Search)
+                .width(iced::Length::Fixed(160.0)),
+            checkbox(".*", self.is_regex).on_toggle(FindReplaceMessage::ToggleRegex),
+            checkbox("Aa", self.case_sensitive).on_toggle(FindReplaceMessage::ToggleCaseSensitive),
+            button(if self.searching { "Searching…" } else { "Search" })
+                .on_press_maybe((!self.searching).then_some(FindReplaceMessage::Search)),
+        ]
+        .spacing(8);
+
+        let mut replace_row = row![
+            text_input("Replace", &self.replacement)
+                .on_input(FindReplaceMessage::ReplacementChanged),
+            button("Preview").on_press_maybe((!self.results.is_empty()).then_some(FindReplaceMessage::Preview)),
+        ]
+        .spacing(8);
+        if self.vfs.can_undo() {
+            replace_row = replace_row.push(button("Undo").on_press(FindReplaceMessage::Undo));
+        }
+
+        let mut content = column![text("🔎 Find and replace").size(14), search_row, replace_row].spacing(8);
+
+        if let Some(status) = &self.status {
+            content = content.push(text(status).size(12));
+        }
+
+        if let Some(diff) = &self.preview {
+            content = content
+                .push(scrollable(text(diff).size(12)).height(iced::Length::Fixed(240.0)))
+                .push(row![
+                    button("Apply").on_press(FindReplaceMessage::Apply),
+                    button("Cancel").on_press(FindReplaceMessage::CancelPreview),
+                ].spacing(8));
+            return content.into();
+        }
+
+        let mut results = column![].spacing(6);
+        for (file_index, file) in self.results.iter().enumerate() {
+            let display = file.path.strip_prefix(&self.root).unwrap_or(&file.path);
+            results = results.push(
+                checkbox(
+                    format!("{} ({}/{})", display.display(), file.selected_count(), file.matches.len()),
+                    file.selected_count() == file.matches.len(),
+                )
+                .on_toggle(move |_| FindReplaceMessage::ToggleFile(file_index)),
+            );
+
+            for (match_index, found) in file.matches.iter().enumerate() {
+                let mut lines = column![].spacing(0);
+                for (offset, line) in found.context_before.iter().enumerate() {
+                    let number = found.line_number - found.context_before.len() + offset;
+                    lines = lines.push(text(format!("{:>5}  {}", number, line)).size(12));
+                }
+                lines = lines.push(text(format!("{:>5}▶ {}", found.line_number, found.line)).size(12));
+                for (offset, line) in found.context_after.iter().enumerate() {
+                    lines = lines.push(text(format!("{:>5}  {}", found.line_number + 1 + offset, line)).size(12));
+                }
+
+                results = results.push(
+                    row![
+                        checkbox("", found.selected)
+                            .on_toggle(move |_| FindReplaceMessage::ToggleMatch(file_index, match_index)),
+                        lines,
+                        button(text("Open").size(12)).on_press(FindReplaceMessage::OpenMatch(file_index, match_index)),
+                    ]
+                    .spacing(8)
+                    .padding([0, 0, 0, 16]),
+                );
+            }
+        }
+
+        content.push(scrollable(results).height(iced::Length::Shrink)).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_regex_replacement_expands_capture_groups() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("lib.rs");
+        std::fs::write(&path, "let a = foo(1);\nlet b = foo(22);\n").unwrap();
+
+        let query = SearchQuery::regex(r"foo\((\d+)\)");
+        let results = search_project(temp_dir.path(), &query).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].matches.len(), 2);
+
+        let mut vfs = VirtualFileSystem::new();
+        let count = stage_replacements(&mut vfs, &results, &query, "bar($1, 0)").unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(vfs.read(&path).unwrap(), "let a = bar(1, 0);\nlet b = bar(22, 0);\n");
+
+        vfs.commit().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "let a = bar(1, 0);\nlet b = bar(22, 0);\n");
+    }
+
+    #[test]
+    fn test_binary_files_are_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("text.txt"), "needle\n").unwrap();
+        std::fs::write(temp_dir.path().join("blob.bin"), b"needle\0\x01\x02").unwrap();
+
+        let results = search_project(temp_dir.path(), &SearchQuery::literal("needle")).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].path.ends_with("text.txt"));
+    }
+
+    #[test]
+    fn test_unchecked_matches_are_left_alone() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.md");
+        std::fs::write(&path, "a.b a.b\r\naxb\r\n").unwrap();
+
+        // Literal search must not treat `.` as a wildcard or `$` as a capture
+        let query = SearchQuery::literal("a.b");
+        let mut results = search_project(temp_dir.path(), &query).unwrap();
+        assert_eq!(results[0].matches.len(), 2);
+        results[0].matches[0].selected = false;
+
+        let mut vfs = VirtualFileSystem::new();
+        stage_replacements(&mut vfs, &results, &query, "$0").unwrap();
+        assert_eq!(vfs.read(&path).unwrap(), "a.b $0\r\naxb\r\n");
+    }
+
+    #[test]
+    fn test_glob_limits_files() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.rs"), "todo\n").unwrap();
+        std::fs::write(temp_dir.path().join("b.md"), "todo\n").unwrap();
+
+        let mut query = SearchQuery::literal("todo");
+        query.glob = Some("*.rs".to_string());
+        let results = search_project(temp_dir.path(), &query).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].path.ends_with("a.rs"));
+    }
+}
@@ -0,0 +1,140 @@
+//! The font command output is drawn in: `ui.font_family` at `ui.font_size`,
+//! scaled by `ui.zoom_level`. The PTY's rows and columns and the layout
+//! rules' cell width follow the effective size.
+
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+use cosmic_text::fontdb;
+use crate::config::UiPreferences;
+
+pub const DEFAULT_SIZE: f32 = 12.0;
+pub const MIN_SIZE: f32 = 8.0;
+pub const MAX_SIZE: f32 = 24.0;
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 3.0;
+const ZOOM_STEP: f32 = 0.1;
+/// Advance of a monospace cell at the default size
+const CELL_WIDTH: f32 = 7.2;
+/// Line height at the default size
+const LINE_HEIGHT: f32 = 16.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zoom {
+    /// Ctrl+=
+    In,
+    /// Ctrl+-
+    Out,
+    /// Ctrl+0
+    Reset,
+}
+
+impl Zoom {
+    /// The zoom level after this step from `level`
+    pub fn apply(self, level: f32) -> f32 {
+        let level = match self {
+            Zoom::In => level + ZOOM_STEP,
+            Zoom::Out => level - ZOOM_STEP,
+            Zoom::Reset => 1.0,
+        };
+        // Steps of a tenth, without float drift building up
+        ((level * 10.0).round() / 10.0).clamp(MIN_ZOOM, MAX_ZOOM)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputFont {
+    pub font: iced::Font,
+    /// Effective size, zoom included
+    pub size: f32,
+}
+
+impl Default for OutputFont {
+    fn default() -> Self {
+        Self { font: iced::Font::MONOSPACE, size: DEFAULT_SIZE }
+    }
+}
+
+impl OutputFont {
+    /// The font `ui` asks for. A family that isn't installed falls back to
+    /// the built-in monospace font, with a warning to show.
+    pub fn resolve(ui: &UiPreferences) -> (Self, Option<String>) {
+        let size = ui.font_size.clamp(MIN_SIZE, MAX_SIZE) * ui.zoom_level.clamp(MIN_ZOOM, MAX_ZOOM);
+        let family = ui.font_family.trim();
+        if family.is_empty() {
+            return (Self { size, ..Self::default() }, None);
+        }
+        if !is_installed(family) {
+            let warning = format!("Font \"{}\" isn't installed; using the built-in monospace font", family);
+            return (Self { size, ..Self::default() }, Some(warning));
+        }
+        (Self { font: iced::Font::with_name(static_name(family)), size }, None)
+    }
+
+    pub fn cell_width(&self) -> f32 {
+        CELL_WIDTH * self.scale()
+    }
+
+    pub fn line_height(&self) -> f32 {
+        LINE_HEIGHT * self.scale()
+    }
+
+    /// Size relative to the default, for metrics measured at the default
+    pub fn scale(&self) -> f32 {
+        self.size / DEFAULT_SIZE
+    }
+}
+
+/// Whether a font family is installed, matched without regard to case
+pub fn is_installed(family: &str) -> bool {
+    static FONTS: OnceLock<fontdb::Database> = OnceLock::new();
+    let fonts = FONTS.get_or_init(|| {
+        let mut fonts = fontdb::Database::new();
+        fonts.load_system_fonts();
+        fonts
+    });
+    fonts
+        .faces()
+        .any(|face| face.families.iter().any(|(name, _)| name.eq_ignore_ascii_case(family)))
+}
+
+/// `iced::Font` names a family with a `&'static str`; each family is kept
+/// once, however often it's picked
+fn static_name(family: &str) -> &'static str {
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut names = NAMES.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(name) = names.get(family) {
+        return name;
+    }
+    let name: &'static str = Box::leak(family.to_string().into_boxed_str());
+    names.insert(name);
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_steps_and_limits() {
+        assert_eq!(Zoom::In.apply(1.0), 1.1);
+        assert_eq!(Zoom::Out.apply(Zoom::Out.apply(1.0)), 0.8);
+        assert_eq!(Zoom::In.apply(MAX_ZOOM), MAX_ZOOM);
+        assert_eq!(Zoom::Out.apply(MIN_ZOOM), MIN_ZOOM);
+        assert_eq!(Zoom::Reset.apply(2.3), 1.0);
+    }
+
+    #[test]
+    fn test_resolve_scales_and_falls_back() {
+        let mut ui = UiPreferences::default();
+        ui.zoom_level = 1.5;
+        let (font, warning) = OutputFont::resolve(&ui);
+        assert_eq!(font, OutputFont { font: iced::Font::MONOSPACE, size: 18.0 });
+        assert_eq!(warning, None);
+        assert!((font.cell_width() - 10.8).abs() < 1e-4);
+
+        ui.font_family = "No Such Font Family 0451".to_string();
+        let (font, warning) = OutputFont::resolve(&ui);
+        assert_eq!(font.font, iced::Font::MONOSPACE);
+        assert!(warning.is_some());
+    }
+}
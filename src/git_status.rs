@@ -0,0 +1,390 @@
+//! Status of the git repository the working directory is in: the branch
+//! with its dirty and ahead/behind counts shown by the prompt, and the
+//! staged, unstaged and untracked files listed by the git panel block.
+//!
+//! Repositories are read with libgit2, so none of this needs `git` on PATH.
+//! Repositories libgit2 can't open (newer formats or extensions) fall back
+//! to `git status --porcelain=v2` and friends.
+
+use std::path::{Path, PathBuf};
+use git2::{DiffFormat, DiffOptions, ErrorCode, ObjectType, Repository, Status, StatusOptions};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum GitError {
+    #[error("Not a git repository: {0}")]
+    NotARepository(PathBuf),
+    #[error("Git error: {0}")]
+    Git(String),
+}
+
+impl From<git2::Error> for GitError {
+    fn from(e: git2::Error) -> Self {
+        GitError::Git(e.message().to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    TypeChanged,
+    Conflicted,
+}
+
+impl Change {
+    pub fn letter(self) -> char {
+        match self {
+            Change::Added => 'A',
+            Change::Modified => 'M',
+            Change::Deleted => 'D',
+            Change::Renamed => 'R',
+            Change::TypeChanged => 'T',
+            Change::Conflicted => 'U',
+        }
+    }
+
+    /// A column of porcelain status; `.` is unchanged
+    fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'A' | 'C' => Some(Change::Added),
+            'M' => Some(Change::Modified),
+            'D' => Some(Change::Deleted),
+            'R' => Some(Change::Renamed),
+            'T' => Some(Change::TypeChanged),
+            'U' => Some(Change::Conflicted),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileEntry {
+    pub path: String,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GitStatus {
+    /// Top of the working tree
+    pub root: PathBuf,
+    /// Branch name, or the short commit id when detached
+    pub branch: String,
+    pub ahead: usize,
+    pub behind: usize,
+    pub staged: Vec<FileEntry>,
+    pub unstaged: Vec<FileEntry>,
+    pub untracked: Vec<String>,
+}
+
+impl GitStatus {
+    /// Files with any change, counting a file staged and modified again once
+    pub fn changed(&self) -> usize {
+        let mut paths: Vec<&str> = self.staged.iter().chain(&self.unstaged).map(|entry| entry.path.as_str()).collect();
+        paths.sort_unstable();
+        paths.dedup();
+        paths.len() + self.untracked.len()
+    }
+
+    /// `main ±3 ↑1 ↓2`, for next to the prompt
+    pub fn indicator(&self) -> String {
+        let mut indicator = self.branch.clone();
+        let changed = self.changed();
+        if changed > 0 {
+            indicator.push_str(&format!(" ±{}", changed));
+        }
+        if self.ahead > 0 {
+            indicator.push_str(&format!(" ↑{}", self.ahead));
+        }
+        if self.behind > 0 {
+            indicator.push_str(&format!(" ↓{}", self.behind));
+        }
+        indicator
+    }
+}
+
+/// What the git panel's per-file buttons do
+#[derive(Debug, Clone, PartialEq)]
+pub enum GitAction {
+    Stage(String),
+    Unstage(String),
+    /// The staged or the unstaged changes to one file
+    Diff { path: String, staged: bool },
+}
+
+/// Status of the repository containing `dir`
+pub fn read(dir: &Path) -> Result<GitStatus, GitError> {
+    let repo = match Repository::discover(dir) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => return Err(GitError::NotARepository(dir.to_path_buf())),
+        Err(e) => {
+            log::debug!("libgit2 cannot open the repository at {}, using git: {}", dir.display(), e);
+            return read_with_cli(dir);
+        }
+    };
+    read_with_git2(&repo).or_else(|e| {
+        log::debug!("libgit2 cannot read the status of {}, using git: {}", dir.display(), e);
+        read_with_cli(dir)
+    })
+}
+
+fn read_with_git2(repo: &Repository) -> Result<GitStatus, GitError> {
+    let root = repo.workdir().ok_or_else(|| GitError::Git("bare repository".to_string()))?.to_path_buf();
+    let mut status = GitStatus { root, ..Default::default() };
+
+    match repo.head() {
+        Ok(head) => {
+            status.branch = match head.shorthand().filter(|_| head.is_branch()) {
+                Some(name) => name.to_string(),
+                None => head.target().map(|oid| oid.to_string().chars().take(7).collect()).unwrap_or_default(),
+            };
+            if let (true, Some(local)) = (head.is_branch(), head.target()) {
+                let upstream = git2::Branch::wrap(head).upstream().ok().and_then(|upstream| upstream.get().target());
+                if let Some(upstream) = upstream {
+                    (status.ahead, status.behind) = repo.graph_ahead_behind(local, upstream)?;
+                }
+            }
+        }
+        // A new repository's branch has no commits yet
+        Err(e) if e.code() == ErrorCode::UnbornBranch => {
+            let head = repo.find_reference("HEAD")?;
+            let target = head.symbolic_target().unwrap_or_default();
+            status.branch = target.strip_prefix("refs/heads/").unwrap_or(target).to_string();
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true).exclude_submodules(true).renames_head_to_index(true);
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let Some(path) = entry.path().map(str::to_string) else {
+            continue;
+        };
+        let flags = entry.status();
+        if flags.contains(Status::CONFLICTED) {
+            status.unstaged.push(FileEntry { path, change: Change::Conflicted });
+            continue;
+        }
+        if flags.contains(Status::WT_NEW) {
+            status.untracked.push(path);
+            continue;
+        }
+        let staged = [
+            (Status::INDEX_NEW, Change::Added),
+            (Status::INDEX_MODIFIED, Change::Modified),
+            (Status::INDEX_DELETED, Change::Deleted),
+            (Status::INDEX_RENAMED, Change::Renamed),
+            (Status::INDEX_TYPECHANGE, Change::TypeChanged),
+        ];
+        if let Some((_, change)) = staged.iter().find(|(flag, _)| flags.contains(*flag)) {
+            status.staged.push(FileEntry { path: path.clone(), change: *change });
+        }
+        let unstaged = [
+            (Status::WT_MODIFIED, Change::Modified),
+            (Status::WT_DELETED, Change::Deleted),
+            (Status::WT_RENAMED, Change::Renamed),
+            (Status::WT_TYPECHANGE, Change::TypeChanged),
+        ];
+        if let Some((_, change)) = unstaged.iter().find(|(flag, _)| flags.contains(*flag)) {
+            status.unstaged.push(FileEntry { path, change: *change });
+        }
+    }
+    Ok(status)
+}
+
+fn read_with_cli(dir: &Path) -> Result<GitStatus, GitError> {
+    let root = git(dir, &["rev-parse", "--show-toplevel"])?;
+    let output = git(dir, &["status", "--porcelain=v2", "--branch", "-z"])?;
+    let mut status = parse_porcelain(&output);
+    status.root = PathBuf::from(root.trim());
+    Ok(status)
+}
+
+/// `git status --porcelain=v2 --branch -z` output
+fn parse_porcelain(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        if let Some(head) = record.strip_prefix("# branch.head ") {
+            status.branch = head.to_string();
+        } else if let Some(oid) = record.strip_prefix("# branch.oid ") {
+            if status.branch == "(detached)" {
+                status.branch = oid.chars().take(7).collect();
+            }
+        } else if let Some(counts) = record.strip_prefix("# branch.ab ") {
+            for count in counts.split_whitespace() {
+                if let Some(ahead) = count.strip_prefix('+') {
+                    status.ahead = ahead.parse().unwrap_or(0);
+                } else if let Some(behind) = count.strip_prefix('-') {
+                    status.behind = behind.parse().unwrap_or(0);
+                }
+            }
+        } else if let Some(path) = record.strip_prefix("? ") {
+            status.untracked.push(path.to_string());
+        } else if let Some(kind @ ("1" | "2" | "u")) = record.get(..1) {
+            // Ordinary, renamed (whose original path is the next record) and unmerged entries
+            let fields = match kind {
+                "1" => 9,
+                "2" => 10,
+                _ => 11,
+            };
+            let mut parts = record.splitn(fields, ' ');
+            let xy: Vec<char> = parts.nth(1).unwrap_or_default().chars().collect();
+            let Some(path) = parts.last().map(str::to_string) else {
+                continue;
+            };
+            if kind == "2" {
+                records.next();
+            }
+            if kind == "u" {
+                status.unstaged.push(FileEntry { path, change: Change::Conflicted });
+                continue;
+            }
+            if let Some(change) = xy.first().copied().and_then(Change::from_letter) {
+                status.staged.push(FileEntry { path: path.clone(), change });
+            }
+            if let Some(change) = xy.get(1).copied().and_then(Change::from_letter) {
+                status.unstaged.push(FileEntry { path, change });
+            }
+        }
+    }
+    status
+}
+
+/// Do `action` in the repository at `root`. Diffs come back as patch text.
+pub fn apply(root: &Path, action: &GitAction) -> Result<Option<String>, GitError> {
+    let repo = Repository::open(root);
+    let result = repo.map_err(GitError::from).and_then(|repo| apply_with_git2(&repo, action));
+    result.or_else(|e| {
+        log::debug!("libgit2 cannot {:?} in {}, using git: {}", action, root.display(), e);
+        match action {
+            GitAction::Stage(path) => git(root, &["add", "--", path]).map(|_| None),
+            GitAction::Unstage(path) => git(root, &["reset", "-q", "--", path]).map(|_| None),
+            GitAction::Diff { path, staged: true } => git(root, &["diff", "--cached", "--", path]).map(Some),
+            GitAction::Diff { path, staged: false } => git(root, &["diff", "--", path]).map(Some),
+        }
+    })
+}
+
+fn apply_with_git2(repo: &Repository, action: &GitAction) -> Result<Option<String>, GitError> {
+    let workdir = repo.workdir().ok_or_else(|| GitError::Git("bare repository".to_string()))?;
+    match action {
+        GitAction::Stage(path) => {
+            let mut index = repo.index()?;
+            if workdir.join(path).symlink_metadata().is_ok() {
+                index.add_path(Path::new(path))?;
+            } else {
+                index.remove_path(Path::new(path))?;
+            }
+            index.write()?;
+            Ok(None)
+        }
+        GitAction::Unstage(path) => {
+            match repo.head() {
+                Ok(head) => repo.reset_default(Some(&head.peel(ObjectType::Commit)?), [path.as_str()])?,
+                // Nothing committed yet: unstaging takes the file out of the index
+                Err(e) if e.code() == ErrorCode::UnbornBranch => {
+                    let mut index = repo.index()?;
+                    index.remove_path(Path::new(path))?;
+                    index.write()?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+            Ok(None)
+        }
+        GitAction::Diff { path, staged } => {
+            let mut options = DiffOptions::new();
+            options.pathspec(path.as_str());
+            let diff = if *staged {
+                let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+                repo.diff_tree_to_index(head.as_ref(), None, Some(&mut options))?
+            } else {
+                options.include_untracked(true).show_untracked_content(true).recurse_untracked_dirs(true);
+                repo.diff_index_to_workdir(None, Some(&mut options))?
+            };
+            let mut patch = String::new();
+            diff.print(DiffFormat::Patch, |_, _, line| {
+                if matches!(line.origin(), '+' | '-' | ' ') {
+                    patch.push(line.origin());
+                }
+                patch.push_str(&String::from_utf8_lossy(line.content()));
+                true
+            })?;
+            Ok(Some(patch))
+        }
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, GitError> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| GitError::Git(format!("cannot run git: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if stderr.contains("not a git repository") {
+            return Err(GitError::NotARepository(dir.to_path_buf()));
+        }
+        return Err(GitError::Git(stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_porcelain() {
+        let output = [
+            "# branch.oid 0123456789abcdef",
+            "# branch.head main",
+            "# branch.upstream origin/main",
+            "# branch.ab +1 -2",
+            "1 M. N... 100644 100644 100644 aaaa bbbb src/lib.rs",
+            "1 .D N... 100644 100644 000000 aaaa aaaa old notes.txt",
+            "2 R. N... 100644 100644 100644 aaaa aaaa R100 src/new.rs",
+            "src/old.rs",
+            "u UU N... 100644 100644 100644 100644 aaaa bbbb cccc Cargo.lock",
+            "? scratch/",
+            "",
+        ]
+        .join("\0");
+        let status = parse_porcelain(&output);
+        assert_eq!(status.branch, "main");
+        assert_eq!((status.ahead, status.behind), (1, 2));
+        assert_eq!(status.staged, vec![
+            FileEntry { path: "src/lib.rs".to_string(), change: Change::Modified },
+            FileEntry { path: "src/new.rs".to_string(), change: Change::Renamed },
+        ]);
+        assert_eq!(status.unstaged, vec![
+            FileEntry { path: "old notes.txt".to_string(), change: Change::Deleted },
+            FileEntry { path: "Cargo.lock".to_string(), change: Change::Conflicted },
+        ]);
+        assert_eq!(status.untracked, vec!["scratch/".to_string()]);
+        assert_eq!(status.indicator(), "main ±5 ↑1 ↓2");
+    }
+
+    #[test]
+    fn test_stage_and_unstage_with_git2() {
+        let dir = TempDir::new().unwrap();
+        Repository::init(dir.path()).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+
+        let status = read(dir.path()).unwrap();
+        assert_eq!(status.untracked, vec!["a.txt".to_string()]);
+
+        apply(dir.path(), &GitAction::Stage("a.txt".to_string())).unwrap();
+        let status = read(dir.path()).unwrap();
+        assert_eq!(status.staged, vec![FileEntry { path: "a.txt".to_string(), change: Change::Added }]);
+        let patch = apply(dir.path(), &GitAction::Diff { path: "a.txt".to_string(), staged: true }).unwrap().unwrap();
+        assert!(patch.contains("+one"));
+
+        apply(dir.path(), &GitAction::Unstage("a.txt".to_string())).unwrap();
+        assert!(read(dir.path()).unwrap().staged.is_empty());
+    }
+}
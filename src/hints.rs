@@ -0,0 +1,181 @@
+//! Keyboard hint mode: every interactive element on screen gets a two-letter
+//! label, and typing a label activates the element.
+//!
+//! Renderers register what they draw in an `InteractableRegistry` while
+//! rendering, so the labels always match what is visible. Labels all have
+//! the same length, so no label is a prefix of another and a match is never
+//! ambiguous.
+
+/// Home row first, so the most common labels are the easiest to type
+pub const HINT_ALPHABET: &[u8] = b"asdfghjklqwertyuiopzxcvbnm";
+const LABEL_LEN: usize = 2;
+
+/// Labels available on one screen
+pub fn capacity() -> usize {
+    HINT_ALPHABET.len().pow(LABEL_LEN as u32)
+}
+
+/// The label for the `index`th interactable, `None` past `capacity()`
+pub fn label(index: usize) -> Option<String> {
+    if index >= capacity() {
+        return None;
+    }
+    let n = HINT_ALPHABET.len();
+    let first = HINT_ALPHABET[index / n] as char;
+    let second = HINT_ALPHABET[index % n] as char;
+    Some(format!("{}{}", first, second))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractableKind {
+    Link,
+    BlockButton,
+    Suggestion,
+    PaletteRow,
+    ToolbarButton,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interactable<A> {
+    pub label: String,
+    pub kind: InteractableKind,
+    /// Shown next to the label where the element itself can't carry it
+    pub description: String,
+    pub action: A,
+}
+
+/// What is on screen right now, rebuilt on every render
+#[derive(Debug, Clone)]
+pub struct InteractableRegistry<A> {
+    entries: Vec<Interactable<A>>,
+}
+
+impl<A> Default for InteractableRegistry<A> {
+    fn default() -> Self {
+        Self { entries: Vec::new() }
+    }
+}
+
+impl<A: Clone> InteractableRegistry<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Add an element and return its label; `None` once labels run out
+    pub fn register(&mut self, kind: InteractableKind, description: impl Into<String>, action: A) -> Option<String> {
+        let label = label(self.entries.len())?;
+        self.entries.push(Interactable { label: label.clone(), kind, description: description.into(), action });
+        Some(label)
+    }
+
+    pub fn entries(&self) -> &[Interactable<A>] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn find(&self, label: &str) -> Option<&Interactable<A>> {
+        self.entries.iter().find(|entry| entry.label == label)
+    }
+
+    fn has_prefix(&self, typed: &str) -> bool {
+        self.entries.iter().any(|entry| entry.label.starts_with(typed))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HintInput<A> {
+    /// Part of a label; keep waiting
+    Pending,
+    Activate(A),
+    /// Matches nothing; hint mode ends
+    NoMatch,
+}
+
+/// Letters typed so far while hints are shown
+#[derive(Debug, Clone, Default)]
+pub struct HintMode {
+    typed: String,
+}
+
+impl HintMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+
+    pub fn key<A: Clone>(&mut self, key: char, registry: &InteractableRegistry<A>) -> HintInput<A> {
+        self.typed.push(key.to_ascii_lowercase());
+        if let Some(entry) = registry.find(&self.typed) {
+            return HintInput::Activate(entry.action.clone());
+        }
+        if registry.has_prefix(&self.typed) {
+            HintInput::Pending
+        } else {
+            HintInput::NoMatch
+        }
+    }
+}
+
+/// URLs in `text`, with their character column
+pub fn find_links(text: &str) -> Vec<(usize, String)> {
+    crate::links::url_pattern()
+        .find_iter(text)
+        .map(|m| (text[..m.start()].chars().count(), m.as_str().to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_are_unique_and_prefix_free() {
+        let labels: Vec<String> = (0..capacity()).map(|i| label(i).unwrap()).collect();
+        for (i, a) in labels.iter().enumerate() {
+            assert_eq!(a.len(), 2);
+            for b in &labels[i + 1..] {
+                assert!(!b.starts_with(a.as_str()) && !a.starts_with(b.as_str()), "{} / {}", a, b);
+            }
+        }
+        assert_eq!(label(capacity()), None);
+        assert_eq!(label(0).as_deref(), Some("aa"));
+        assert_eq!(label(27).as_deref(), Some("ss"));
+    }
+
+    #[test]
+    fn test_typing_a_label_dispatches_its_action() {
+        let mut registry = InteractableRegistry::new();
+        for i in 0..40 {
+            registry.register(InteractableKind::BlockButton, format!("button {}", i), i);
+        }
+
+        let mut mode = HintMode::new();
+        assert_eq!(mode.key('s', &registry), HintInput::Pending);
+        assert_eq!(mode.key('D', &registry), HintInput::Activate(28));
+
+        let mut mode = HintMode::new();
+        assert_eq!(mode.key('f', &registry), HintInput::NoMatch);
+    }
+
+    #[test]
+    fn test_find_links() {
+        assert_eq!(
+            find_links("see https://example.com/a?b=1, or (http://x.io/docs)."),
+            vec![(4, "https://example.com/a?b=1".to_string()), (35, "http://x.io/docs".to_string())]
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! Commands entered at the prompt, Up/Down navigation through them and
+//! Ctrl+R search. The history owns its store file too, so clearing it
+//! empties both.
+//!
+//! The store holds one JSON string per line, so multi-line commands survive,
+//! and is appended to as commands run. Loading keeps the newest entries up
+//! to the limit and rewrites the file when it had more.
+
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Candidates shown under the input during Ctrl+R
+pub const SEARCH_CANDIDATES: usize = 5;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum HistoryError {
+    #[error("IO error: {0}")]
+    IoError(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommandHistory {
+    entries: Vec<String>,
+    /// Entry shown while navigating with Up/Down
+    index: Option<usize>,
+    store: Option<PathBuf>,
+    /// Whether new entries are written to `store`
+    persistent: bool,
+    /// Entries kept; 0 keeps everything
+    limit: usize,
+}
+
+impl CommandHistory {
+    pub fn new(store: Option<PathBuf>) -> Self {
+        Self { entries: Vec::new(), index: None, store, persistent: false, limit: 0 }
+    }
+
+    /// The history saved in `store`, keeping the newest `limit` entries
+    pub fn load(store: Option<PathBuf>, limit: usize) -> Self {
+        let mut history = Self { persistent: true, limit, ..Self::new(store) };
+        let Some(path) = history.store.clone() else {
+            return history;
+        };
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return history;
+        };
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let command = serde_json::from_str::<String>(line).unwrap_or_else(|_| line.to_string());
+            if history.entries.last() != Some(&command) {
+                history.entries.push(command);
+            }
+        }
+        if history.trim() {
+            if let Err(e) = history.rewrite() {
+                log::warn!("Failed to compact command history: {}", e);
+            }
+        }
+        history
+    }
+
+    /// Stop or resume writing new entries to disk, e.g. for incognito mode
+    pub fn set_persistent(&mut self, persistent: bool) {
+        self.persistent = persistent;
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.trim();
+    }
+
+    /// Drop the oldest entries beyond the limit; whether any were dropped
+    fn trim(&mut self) -> bool {
+        let excess = self.entries.len().saturating_sub(self.limit);
+        if self.limit == 0 || excess == 0 {
+            return false;
+        }
+        self.entries.drain(..excess);
+        true
+    }
+
+    fn rewrite(&self) -> Result<(), HistoryError> {
+        let Some(path) = &self.store else { return Ok(()) };
+        let mut contents = String::new();
+        for entry in &self.entries {
+            contents.push_str(&serde_json::to_string(entry).map_err(|e| HistoryError::IoError(e.to_string()))?);
+            contents.push('\n');
+        }
+        std::fs::write(path, contents).map_err(|e| HistoryError::IoError(e.to_string()))
+    }
+
+    fn append(&self, command: &str) -> Result<(), HistoryError> {
+        let Some(path) = &self.store else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| HistoryError::IoError(e.to_string()))?;
+        }
+        let line = serde_json::to_string(command).map_err(|e| HistoryError::IoError(e.to_string()))?;
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| HistoryError::IoError(e.to_string()))
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Record a submitted command and stop navigating. Running the same
+    /// command twice in a row records it once.
+    pub fn push(&mut self, command: String) {
+        self.index = None;
+        if self.entries.last() == Some(&command) {
+            return;
+        }
+        if self.persistent {
+            if let Err(e) = self.append(&command) {
+                log::warn!("Failed to save command history: {}", e);
+            }
+        }
+        self.entries.push(command);
+        self.trim();
+    }
+
+    /// Distinct entries fuzzy-matching `query`, best first; newer entries
+    /// win ties, so an empty query lists the most recent commands
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&str> {
+        let matcher = SkimMatcherV2::default();
+        let mut seen = HashSet::new();
+        let mut hits: Vec<(i64, usize, &str)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, entry)| seen.insert(entry.as_str()))
+            .filter_map(|(i, entry)| matcher.fuzzy_match(entry, query).map(|score| (score, i, entry.as_str())))
+            .collect();
+        hits.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+        hits.into_iter().take(limit).map(|(_, _, entry)| entry).collect()
+    }
+
+    /// One entry further back, staying on the oldest
+    pub fn previous(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let index = match self.index {
+            None => self.entries.len() - 1,
+            Some(i) => i.saturating_sub(1),
+        };
+        self.index = Some(index);
+        Some(&self.entries[index])
+    }
+
+    /// One entry forward; past the newest, an empty input. `None` when not navigating.
+    pub fn next(&mut self) -> Option<&str> {
+        let i = self.index?;
+        if i + 1 < self.entries.len() {
+            self.index = Some(i + 1);
+            Some(&self.entries[i + 1])
+        } else {
+            self.index = None;
+            Some("")
+        }
+    }
+
+    /// Forget every entry, in memory and on disk
+    pub fn clear(&mut self) -> Result<(), HistoryError> {
+        self.entries.clear();
+        self.index = None;
+        match &self.store {
+            Some(path) => match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(HistoryError::IoError(e.to_string())),
+                _ => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+/// Ctrl+R in progress: the query typed in place of the input, and which
+/// candidate Enter would pick
+#[derive(Debug, Clone, Default)]
+pub struct HistorySearch {
+    pub query: String,
+    pub selected: usize,
+    /// Input from before the search, put back on Esc
+    pub saved_input: String,
+}
+
+impl HistorySearch {
+    pub fn new(saved_input: String) -> Self {
+        Self { saved_input, ..Self::default() }
+    }
+
+    /// Move the selection by `step` among `count` candidates, wrapping around
+    pub fn move_selection(&mut self, step: isize, count: usize) {
+        if count > 0 {
+            self.selected = (self.selected as isize + step).rem_euclid(count as isize) as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navigates_up_and_down() {
+        let mut history = CommandHistory::new(None);
+        assert_eq!(history.previous(), None);
+        history.push("ls".to_string());
+        history.push("pwd".to_string());
+
+        assert_eq!(history.next(), None);
+        assert_eq!(history.previous(), Some("pwd"));
+        assert_eq!(history.previous(), Some("ls"));
+        assert_eq!(history.previous(), Some("ls"));
+        assert_eq!(history.next(), Some("pwd"));
+        assert_eq!(history.next(), Some(""));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn test_persists_collapses_repeats_and_keeps_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        let mut history = CommandHistory::load(Some(path.clone()), 3);
+        for command in ["ls", "ls", "git status", "printf 'a\nb'", "make", "make"] {
+            history.push(command.to_string());
+        }
+        assert_eq!(history.entries(), ["git status", "printf 'a\nb'", "make"]);
+
+        // The file has every run, the reload only the newest up to the limit
+        let reloaded = CommandHistory::load(Some(path.clone()), 3);
+        assert_eq!(reloaded.entries(), history.entries());
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        let mut incognito = CommandHistory::load(Some(path.clone()), 3);
+        incognito.set_persistent(false);
+        incognito.push("secret".to_string());
+        assert!(!CommandHistory::load(Some(path), 3).entries().contains(&"secret".to_string()));
+    }
+
+    #[test]
+    fn test_search_ranks_matches_and_skips_duplicates() {
+        let mut history = CommandHistory::new(None);
+        for command in ["cargo build", "git checkout main", "cargo test", "ls", "cargo test"] {
+            history.push(command.to_string());
+        }
+        history.push("ls".to_string());
+
+        assert_eq!(history.search("", 3), vec!["ls", "cargo test", "git checkout main"]);
+        assert_eq!(history.search("ctest", 5), vec!["cargo test"]);
+        let cargo = history.search("cargo", 5);
+        assert_eq!(cargo.len(), 2);
+        assert!(cargo.contains(&"cargo build"));
+        assert!(history.search("zzz", 5).is_empty());
+
+        let mut search = HistorySearch::new("draft".to_string());
+        search.move_selection(-1, 3);
+        assert_eq!(search.selected, 2);
+        search.move_selection(1, 3);
+        assert_eq!(search.selected, 0);
+    }
+}
@@ -0,0 +1,306 @@
+//! User hook scripts: executables in `<config>/hooks/` named after an event
+//! (`on-command-failed`, …) that are run with a JSON payload on stdin.
+//!
+//! Hooks get a short timeout and are never waited on by the UI thread. Only
+//! `on-command-submit` can change what happens: a non-zero exit cancels the
+//! command and its stderr is shown instead. Other hooks are fire-and-forget;
+//! their failures are logged, at most once a minute per event.
+
+use crate::config::HookPreferences;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+
+/// Failures of one event's hook are logged at most this often
+pub const FAILURE_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    /// A command line is about to run; a non-zero exit cancels it
+    CommandSubmit,
+    CommandFailed,
+    /// A command finished after running longer than the configured threshold
+    CommandLongRunning,
+    SessionStart,
+    SyncConflict,
+}
+
+impl HookEvent {
+    pub const ALL: &'static [HookEvent] = &[
+        HookEvent::CommandSubmit,
+        HookEvent::CommandFailed,
+        HookEvent::CommandLongRunning,
+        HookEvent::SessionStart,
+        HookEvent::SyncConflict,
+    ];
+
+    /// File name of the event's script in the hooks directory
+    pub fn script_name(&self) -> &'static str {
+        match self {
+            HookEvent::CommandSubmit => "on-command-submit",
+            HookEvent::CommandFailed => "on-command-failed",
+            HookEvent::CommandLongRunning => "on-command-long-running",
+            HookEvent::SessionStart => "on-session-start",
+            HookEvent::SyncConflict => "on-sync-conflict",
+        }
+    }
+
+    /// Whether the hook's exit code can stop what triggered it
+    pub fn can_cancel(&self) -> bool {
+        matches!(self, HookEvent::CommandSubmit)
+    }
+}
+
+/// How a hook run ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookOutcome {
+    pub event: HookEvent,
+    /// `None` when the script was killed or timed out
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+    pub timed_out: bool,
+}
+
+impl HookOutcome {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// A submit hook said no. Timeouts and crashes don't cancel, so a broken
+    /// hook can't stop every command.
+    pub fn cancels(&self) -> bool {
+        self.event.can_cancel() && matches!(self.exit_code, Some(code) if code != 0)
+    }
+
+    /// Why the hook failed, for the log
+    pub fn describe_failure(&self) -> String {
+        let script = self.event.script_name();
+        let stderr = self.stderr.trim();
+        match (self.timed_out, self.exit_code) {
+            (true, _) => format!("{} hook timed out", script),
+            (false, Some(code)) if stderr.is_empty() => format!("{} hook exited with {}", script, code),
+            (false, Some(code)) => format!("{} hook exited with {}: {}", script, code, stderr),
+            (false, None) => format!("{} hook was killed", script),
+        }
+    }
+}
+
+/// Lets one failure per event through per `FAILURE_LOG_INTERVAL`, counting
+/// the ones held back
+#[derive(Debug, Default)]
+struct FailureLimiter {
+    last: HashMap<HookEvent, (Instant, u32)>,
+}
+
+impl FailureLimiter {
+    /// `Some(suppressed)` when this failure should be logged, with the
+    /// number held back since the last one that was
+    fn allow(&mut self, event: HookEvent, now: Instant) -> Option<u32> {
+        match self.last.get_mut(&event) {
+            Some((last, suppressed)) if now.duration_since(*last) < FAILURE_LOG_INTERVAL => {
+                *suppressed += 1;
+                None
+            }
+            _ => {
+                let suppressed = self.last.insert(event, (now, 0)).map_or(0, |(_, suppressed)| suppressed);
+                Some(suppressed)
+            }
+        }
+    }
+}
+
+/// Finds and runs hook scripts. Cheap to clone; clones share the failure log.
+#[derive(Debug, Clone)]
+pub struct HookRunner {
+    dir: PathBuf,
+    timeout: Duration,
+    failures: Arc<Mutex<FailureLimiter>>,
+}
+
+impl HookRunner {
+    pub fn new(dir: PathBuf, timeout: Duration) -> Self {
+        Self { dir, timeout, failures: Arc::new(Mutex::new(FailureLimiter::default())) }
+    }
+
+    pub fn from_prefs(dir: PathBuf, prefs: &HookPreferences) -> Self {
+        Self::new(dir, Duration::from_millis(prefs.timeout_ms))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The event's script, if one is installed and executable
+    pub fn script(&self, event: HookEvent) -> Option<PathBuf> {
+        let path = self.dir.join(event.script_name());
+        is_executable(&path).then_some(path)
+    }
+
+    /// Run the event's hook with `payload` on stdin. `None` when there is
+    /// no hook for the event or it couldn't be started.
+    pub async fn run(&self, event: HookEvent, payload: &serde_json::Value) -> Option<HookOutcome> {
+        let script = self.script(event)?;
+        let outcome = match self.spawn(event, &script, payload).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                self.log_failure(event, &format!("{} hook couldn't run: {}", event.script_name(), e));
+                return None;
+            }
+        };
+        if !outcome.succeeded() && !outcome.cancels() {
+            self.log_failure(event, &outcome.describe_failure());
+        }
+        Some(outcome)
+    }
+
+    async fn spawn(&self, event: HookEvent, script: &Path, payload: &serde_json::Value) -> std::io::Result<HookOutcome> {
+        let mut child = tokio::process::Command::new(script)
+            .env("NEOTERM_HOOK_EVENT", event.script_name())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook that doesn't read its payload closes the pipe early; that's fine
+            let _ = stdin.write_all(payload.to_string().as_bytes()).await;
+        }
+
+        match tokio::time::timeout(self.timeout, child.wait_with_output()).await {
+            Ok(output) => {
+                let output = output?;
+                Ok(HookOutcome {
+                    event,
+                    exit_code: output.status.code(),
+                    stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                    timed_out: false,
+                })
+            }
+            // Dropping the child kills it
+            Err(_) => Ok(HookOutcome { event, exit_code: None, stderr: String::new(), timed_out: true }),
+        }
+    }
+
+    fn log_failure(&self, event: HookEvent, message: &str) {
+        let Some(suppressed) = self.failures.lock().unwrap().allow(event, Instant::now()) else {
+            return;
+        };
+        if suppressed > 0 {
+            log::warn!("{} ({} more failures not shown)", message, suppressed);
+        } else {
+            log::warn!("{}", message);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn install(dir: &Path, event: HookEvent, script: &str) {
+        let path = dir.join(event.script_name());
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hook_receives_payload() {
+        let temp_dir = TempDir::new().unwrap();
+        let received = temp_dir.path().join("received.json");
+        install(
+            temp_dir.path(),
+            HookEvent::CommandFailed,
+            &format!("#!/bin/sh\ncat > '{}'\necho \"$NEOTERM_HOOK_EVENT\" >&2\n", received.display()),
+        );
+        let runner = HookRunner::new(temp_dir.path().to_path_buf(), Duration::from_secs(5));
+        let payload = serde_json::json!({ "event": "on-command-failed", "command": "make test", "exit_code": 2 });
+
+        let outcome = runner.run(HookEvent::CommandFailed, &payload).await.unwrap();
+        assert!(outcome.succeeded());
+        assert!(!outcome.cancels());
+        assert_eq!(outcome.stderr.trim(), "on-command-failed");
+        let received: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&received).unwrap()).unwrap();
+        assert_eq!(received, payload);
+    }
+
+    #[tokio::test]
+    async fn test_submit_hook_cancels_with_stderr() {
+        let temp_dir = TempDir::new().unwrap();
+        install(
+            temp_dir.path(),
+            HookEvent::CommandSubmit,
+            "#!/bin/sh\nif grep -q 'rm -rf' ; then echo 'not on a Friday' >&2; exit 3; fi\n",
+        );
+        let runner = HookRunner::new(temp_dir.path().to_path_buf(), Duration::from_secs(5));
+
+        let outcome = runner
+            .run(HookEvent::CommandSubmit, &serde_json::json!({ "command": "rm -rf build" }))
+            .await
+            .unwrap();
+        assert!(outcome.cancels());
+        assert_eq!(outcome.exit_code, Some(3));
+        assert_eq!(outcome.stderr.trim(), "not on a Friday");
+
+        let outcome = runner
+            .run(HookEvent::CommandSubmit, &serde_json::json!({ "command": "ls" }))
+            .await
+            .unwrap();
+        assert!(!outcome.cancels());
+    }
+
+    #[tokio::test]
+    async fn test_slow_hook_times_out_without_cancelling() {
+        let temp_dir = TempDir::new().unwrap();
+        install(temp_dir.path(), HookEvent::CommandSubmit, "#!/bin/sh\nsleep 5\n");
+        let runner = HookRunner::new(temp_dir.path().to_path_buf(), Duration::from_millis(100));
+
+        let started = Instant::now();
+        let outcome = runner.run(HookEvent::CommandSubmit, &serde_json::json!({})).await.unwrap();
+        assert!(outcome.timed_out);
+        assert!(!outcome.cancels());
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn test_missing_or_non_executable_hook_is_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("on-session-start"), "#!/bin/sh\nexit 1\n").unwrap();
+        let runner = HookRunner::new(temp_dir.path().to_path_buf(), Duration::from_secs(5));
+
+        assert!(runner.script(HookEvent::SessionStart).is_none());
+        assert!(runner.run(HookEvent::SessionStart, &serde_json::json!({})).await.is_none());
+        assert!(runner.run(HookEvent::SyncConflict, &serde_json::json!({})).await.is_none());
+    }
+
+    #[test]
+    fn test_failures_are_rate_limited() {
+        let mut limiter = FailureLimiter::default();
+        let start = Instant::now();
+
+        assert_eq!(limiter.allow(HookEvent::CommandFailed, start), Some(0));
+        assert_eq!(limiter.allow(HookEvent::CommandFailed, start + Duration::from_secs(1)), None);
+        assert_eq!(limiter.allow(HookEvent::CommandFailed, start + Duration::from_secs(2)), None);
+        // Other events have their own allowance
+        assert_eq!(limiter.allow(HookEvent::SessionStart, start + Duration::from_secs(2)), Some(0));
+        assert_eq!(limiter.allow(HookEvent::CommandFailed, start + FAILURE_LOG_INTERVAL), Some(2));
+    }
+}
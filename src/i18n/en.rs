@@ -0,0 +1,276 @@
+//! English strings. Every key used in the UI must be here; other catalogs
+//! fall back to these.
+
+pub const STRINGS: &[(&str, &str)] = &[
+    // Settings tabs
+    ("settings.tab.general", "General"),
+    ("settings.tab.appearance", "Appearance"),
+    ("settings.tab.terminal", "Terminal"),
+    ("settings.tab.editor", "Editor"),
+    ("settings.tab.keybindings", "Key Bindings"),
+    ("settings.tab.performance", "Performance"),
+    ("settings.tab.privacy", "Privacy"),
+    ("settings.tab.ai", "AI"),
+    ("settings.tab.plugins", "Plugins"),
+    // General
+    ("settings.general.title", "General Settings"),
+    ("settings.general.startup", "Startup Behavior:"),
+    ("settings.general.shell", "Default Shell:"),
+    ("settings.general.shell_placeholder", "Shell path..."),
+    ("settings.general.language", "Language:"),
+    ("settings.general.language_system", "System default"),
+    ("settings.general.auto_update", "Auto Update"),
+    ("settings.general.auto_update_help", "Automatically check for and install updates"),
+    ("settings.general.telemetry", "Telemetry"),
+    ("settings.general.telemetry_help", "Help improve NeoTerm by sharing anonymous usage data"),
+    ("settings.backups.open", "Restore from backup…"),
+    ("settings.backups.title", "Restore from backup"),
+    ("settings.backups.none", "No config backups yet"),
+    ("settings.backups.restore", "Restore"),
+    // Reset confirmation
+    ("settings.reset.title", "Reset to Defaults"),
+    ("settings.reset.nothing", "Nothing differs from the defaults"),
+    ("settings.reset.close", "Close"),
+    ("settings.reset.explanation", "These sections differ from the defaults. Ticked ones will be reset; the current config file is backed up when you save."),
+    ("settings.reset.confirm", "Reset Selected"),
+    // Import confirmation
+    ("settings.import.title", "Import Config"),
+    ("settings.import.explanation", "Merging keeps your key bindings and any setting the file leaves out. Replacing uses the file's settings and defaults for the rest. Secrets left out of the file stay as they are; the current config file is backed up when you save."),
+    ("settings.import.merge", "Merge"),
+    ("settings.import.replace", "Replace"),
+    // Appearance
+    ("settings.appearance.title", "Appearance Settings"),
+    ("settings.appearance.theme", "Theme:"),
+    ("settings.appearance.follow_system", "Switch between a light and a dark theme with the system"),
+    ("settings.appearance.light_theme", "Light theme:"),
+    ("settings.appearance.dark_theme", "Dark theme:"),
+    ("settings.appearance.font_family", "Font Family:"),
+    ("settings.appearance.font_placeholder", "Font name..."),
+    ("settings.appearance.font_size", "Font Size:"),
+    ("settings.appearance.font_sample", "$ ls -la ~/src  # 0O 1lI {}[] => != ->"),
+    ("settings.appearance.transparency", "Transparency:"),
+    ("settings.appearance.blur", "Blur Background"),
+    ("settings.appearance.animations", "Enable Animations"),
+    ("settings.appearance.status_glyphs", "Always show status glyphs (✓ ✗ ⏳)"),
+    ("settings.appearance.tab_bar", "Tab bar:"),
+    ("settings.appearance.tab_bar.always", "Always"),
+    ("settings.appearance.tab_bar.auto", "With more than one tab"),
+    ("settings.appearance.tab_bar.never", "Never"),
+    ("settings.appearance.theme_editor", "Custom Theme Editor"),
+    // Terminal
+    ("settings.terminal.title", "Terminal Settings"),
+    ("settings.terminal.scrollback", "Scrollback Lines:"),
+    ("settings.terminal.scroll_sensitivity", "Scroll Sensitivity:"),
+    ("settings.terminal.copy_on_select", "Copy on Select"),
+    ("settings.terminal.paste_on_right_click", "Paste on Right Click"),
+    ("settings.terminal.confirm_close", "Confirm Before Closing"),
+    ("settings.terminal.auto_collapse", "Collapse long output of successful commands"),
+    ("settings.terminal.auto_collapse_lines", "Lines before collapsing:"),
+    ("settings.terminal.notifications", "Notify when a long command finishes in the background"),
+    ("settings.terminal.notify_after", "Seconds before notifying:"),
+    ("settings.terminal.use_pty", "Run commands in a terminal sized to the window (merges stderr into stdout)"),
+    ("settings.terminal.require_attach", "Only type into a running command after Attach"),
+    ("settings.terminal.shell_integration", "Mark prompts of shells started in a block (takes effect on restart)"),
+    ("settings.terminal.browse_on_ls", "Show a bare `ls` as a directory to browse"),
+    ("settings.terminal.hyperlinks", "Links in output:"),
+    ("settings.terminal.hyperlinks.click", "Open on click"),
+    ("settings.terminal.hyperlinks.ctrl_click", "Open on Ctrl+click"),
+    ("settings.terminal.hyperlinks.disabled", "Not clickable"),
+    ("settings.terminal.open_paths_in", "Open file paths in:"),
+    ("settings.terminal.open_paths_in.preview", "File preview"),
+    ("settings.terminal.open_paths_in.editor", "$EDITOR"),
+    ("settings.terminal.expand_variables", "Expand $VARIABLES in cd and plugin commands"),
+    ("settings.terminal.cursor_style", "Cursor Style:"),
+    ("settings.terminal.cursor_blink", "Cursor Blink"),
+    // Editor
+    ("settings.editor.title", "Editor Settings"),
+    ("settings.editor.vim_mode", "Vim Mode"),
+    ("settings.editor.auto_suggestions", "Auto Suggestions"),
+    ("settings.editor.ai_suggestions", "AI Suggestions When History Has None"),
+    ("settings.editor.syntax_highlighting", "Syntax Highlighting"),
+    ("settings.editor.auto_completion", "Auto Completion"),
+    ("settings.editor.indent_size", "Indent Size:"),
+    ("settings.editor.tab_width", "Tab Width:"),
+    ("settings.editor.insert_spaces", "Insert Spaces"),
+    // Key bindings
+    ("settings.keybindings.title", "Key Bindings"),
+    // Performance
+    ("settings.performance.title", "Performance Settings"),
+    ("settings.performance.gpu", "GPU Acceleration"),
+    ("settings.performance.vsync", "VSync"),
+    ("settings.performance.max_fps", "Max FPS:"),
+    ("settings.performance.memory_limit", "Memory Limit (MB):"),
+    // Privacy
+    ("settings.privacy.title", "Privacy Settings"),
+    ("settings.privacy.history", "Enable History"),
+    ("settings.privacy.history_limit", "History Limit:"),
+    ("settings.privacy.clear_on_exit", "Clear History on Exit"),
+    ("settings.privacy.incognito", "Incognito Mode"),
+    ("settings.privacy.share_host_identity", "Let the assistant see the hostname and username"),
+    ("settings.clear.title", "Clear Data"),
+    ("settings.clear.history", "History"),
+    ("settings.clear.blocks", "Blocks"),
+    ("settings.clear.conversations", "Conversations"),
+    ("settings.clear.caches", "Caches"),
+    ("settings.clear.plugins_data", "Plugin data"),
+    ("settings.clear.all", "All"),
+    ("settings.clear.help", "Themes, workflows and keybindings are kept. You're asked before anything is deleted."),
+    ("settings.crash_reports.title", "Crash Reports"),
+    ("settings.crash_reports.send", "Send crash reports"),
+    ("settings.crash_reports.dsn", "Report DSN:"),
+    ("settings.crash_reports.help", "Reports are scrubbed of commands, environment values and keys, and always kept locally (`neoterm crashes list`). Changes apply on the next launch."),
+    ("settings.network.title", "Network"),
+    ("settings.network.help", "Empty proxy fields use HTTP_PROXY, HTTPS_PROXY and NO_PROXY from the environment."),
+    ("settings.network.http_proxy", "HTTP Proxy:"),
+    ("settings.network.https_proxy", "HTTPS Proxy:"),
+    ("settings.network.no_proxy", "No Proxy:"),
+    ("settings.network.ca_bundle", "CA Bundle:"),
+    ("settings.network.verify_tls", "Verify TLS Certificates"),
+    ("settings.network.tls_off", "⚠ Certificate verification is OFF. Anyone on the network path can read and alter your AI, sync and drive traffic."),
+    // Plugins
+    ("settings.plugins.title", "Plugin Settings"),
+    ("settings.plugins.none", "No plugins installed. Install one with `neoterm plugin install <directory or .tar.gz>`."),
+    ("settings.plugins.revoke", "Revoke"),
+    ("settings.plugins.allow", "Allow"),
+    ("settings.plugins.revoked", "revoked"),
+    ("settings.plugins.update_available", "Update available:"),
+    ("plugin.permission.filesystem", "Read and write files anywhere your account can"),
+    ("plugin.permission.network", "Send and receive data over the network"),
+    ("plugin.permission.run_commands", "Run commands in your shell"),
+    ("plugin.permission.clipboard", "Read your clipboard"),
+    ("plugin.permission.environment", "Read your environment variables, which may hold secrets"),
+    ("settings.ai.title", "AI Settings"),
+    ("settings.ai.provider", "Provider"),
+    ("settings.ai.provider_auto", "From environment"),
+    ("settings.ai.model", "Model"),
+    ("settings.ai.refresh_models", "Refresh models"),
+    ("settings.ai.base_url", "Base URL"),
+    ("settings.ai.api_key", "API key"),
+    ("settings.ai.api_key_help", "Left empty, the key is read from the environment variable shown. Keys entered here are saved in the config file."),
+    ("settings.ai.temperature", "Temperature"),
+    ("settings.ai.confirm_generated_commands", "Confirm before running snippets of several commands"),
+    ("settings.ai.natural_language_commands", "Turn sentences typed at the prompt into commands (Ctrl+Enter runs as typed)"),
+    ("settings.ai.one_click_fixes", "Offer \"Apply & run\" on suggested fixes (destructive fixes always need review)"),
+    ("settings.ai.auto_fix_on_failure", "Suggest a fix whenever a command fails"),
+    ("settings.ai.tool_approval", "Agent commands"),
+    ("settings.ai.tool_approval.ask", "Ask before running"),
+    ("settings.ai.tool_approval.auto", "Run without asking"),
+    ("settings.ai.tool_approval.deny_shell", "Never run commands or write files"),
+    ("settings.ai.no_tools", "This provider can't run tools: the agent answers, but can't run commands or read files for you."),
+    // Actions
+    ("settings.actions.reset", "Reset to Defaults"),
+    ("settings.actions.import", "Import Config"),
+    ("settings.actions.export", "Export Config"),
+    ("settings.actions.reset_short", "Reset"),
+    ("settings.actions.import_short", "Import"),
+    ("settings.actions.export_short", "Export"),
+    ("settings.actions.cancel", "Cancel"),
+    ("settings.actions.save", "Save"),
+    // Config sections, in the reset confirmation
+    ("config.section.theme", "Theme"),
+    ("config.section.keybindings", "Key bindings"),
+    ("config.section.env_profile", "Active env profile"),
+    ("config.section.plugins", "Plugins"),
+    ("config.section.general", "General"),
+    ("config.section.terminal", "Terminal"),
+    ("config.section.editor", "Editor"),
+    ("config.section.ui", "Interface"),
+    ("config.section.appearance", "Light and dark mode"),
+    ("config.section.performance", "Performance"),
+    ("config.section.privacy", "Privacy"),
+    ("config.section.network", "Network"),
+    ("config.section.share", "Sharing"),
+    ("config.section.crash_reports", "Crash reports"),
+    ("config.section.maintenance", "Maintenance"),
+    ("config.section.scratch", "Snippet runner"),
+    ("config.section.diagnostics", "Diagnostics"),
+    ("config.section.ai", "AI provider"),
+    ("config.section.ai_context", "AI context"),
+    ("config.section.hooks", "Hooks"),
+    // Block headers and buttons
+    ("block.running", "running"),
+    ("block.exit", "exit {code}"),
+    ("block.snippet", "↳ snippet"),
+    ("block.retry", "↳ retry with fix"),
+    ("block.workflow", "⚙ {name}"),
+    ("block.container", "🐳 {name}"),
+    ("block.shadowing", "runs {path}, not {shadowed}"),
+    ("block.tee", "→ {path} · {bytes} bytes"),
+    ("block.truncated", "{total} lines, last {shown} shown"),
+    ("block.timeline", "{position} / {duration} · {chunks} of {total} chunks"),
+    ("block.action.rerun", "Rerun"),
+    ("block.action.copy", "Copy"),
+    ("block.action.export", "Export…"),
+    ("block.action.ask_ai", "Ask AI"),
+    ("block.action.explain", "Explain output"),
+    ("block.action.toggle_annotation", "Show or hide explanation"),
+    ("block.action.pin_annotation", "Pin to conversation"),
+    ("block.action.dismiss_annotation", "Dismiss explanation"),
+    ("block.action.suggest_fix", "Suggest a fix"),
+    ("block.annotation.title", "Explanation"),
+    ("block.annotation.loading", "Explaining…"),
+    ("block.annotation.failed", "Could not explain the output: {error}"),
+    ("block.annotation.pinned", "Pinned to the conversation"),
+    ("block.action.use_fix", "Edit fix"),
+    ("block.action.apply_fix", "Apply & run"),
+    ("block.action.dismiss_fix", "Dismiss fix"),
+    ("block.fix.title", "Suggested fix"),
+    ("block.fix.loading", "Looking for a fix…"),
+    ("block.fix.failed", "No fix suggested: {error}"),
+    ("block.fix.review", "Review before running: {reason}"),
+    ("block.action.delete", "Delete"),
+    ("block.action.share", "Share"),
+    ("block.action.unshare", "Unshare"),
+    ("blocks.filter.all", "All"),
+    ("blocks.filter.commands", "Commands"),
+    ("blocks.filter.errors", "Errors"),
+    ("blocks.filter.ai", "AI"),
+    ("blocks.filter.pinned", "Pinned"),
+    ("block.action.collapse", "Collapse"),
+    ("block.action.expand", "Expand"),
+    ("block.action.pin", "Pin"),
+    ("block.action.unpin", "Unpin"),
+    ("block.action.move_to_top", "Move to top"),
+    ("block.action.move_to_bottom", "Move to bottom"),
+    ("block.action.timeline", "Timeline"),
+    ("block.action.fork", "Fork"),
+    ("block.action.edit", "Edit"),
+    ("block.action.tee", "Also write to file…"),
+    ("block.action.stop_tee", "Stop writing to file"),
+    ("block.action.show_full_output", "Show full output"),
+    ("block.action.copy_code", "Copy code"),
+    ("block.action.run_code", "Run"),
+    ("block.action.insert_code", "Insert"),
+    // Status line
+    ("status.mode.normal", "NORMAL"),
+    ("status.mode.agent", "AGENT"),
+    ("status.mode.incognito", "INCOGNITO"),
+    ("status.mode.broadcast", "BROADCAST"),
+    ("status.read_only", "🔒 read-only"),
+    ("status.sync", "{spinner} sync"),
+    ("status.ai", "{spinner} AI"),
+    ("status.idle", "idle"),
+    ("status.running", "{count} running"),
+    ("status.queued", ", {count} queued"),
+    // Desktop notifications
+    ("notify.bell", "NeoTerm: bell"),
+    ("notify.command_succeeded", "NeoTerm: command finished"),
+    ("notify.command_failed", "NeoTerm: command failed (exit {code})"),
+    // Command-line help
+    ("cli.about", "A modern terminal with blocks, workflows and agent mode"),
+    ("cli.workflow", "Run and manage workflows"),
+    ("cli.exec", "Run a command and report its output, optionally as structured events"),
+    ("cli.doctor", "Check the installation and configuration"),
+    ("cli.config", "Get, set, export and import settings, migrate configuration locations, import themes and switch profiles"),
+    ("cli.crashes", "Inspect locally saved crash reports"),
+    ("cli.maintenance", "Prune run history, caches and crash reports to their retention limits"),
+    ("cli.clear", "Delete saved history, blocks, conversations, caches or plugin data"),
+    ("cli.which", "Show every executable a command name resolves to across PATH"),
+    ("cli.export", "Write a saved session's blocks to one Markdown or HTML document"),
+    ("cli.learn", "Practise with a multiple-choice quiz on the bundled command templates"),
+    ("cli.ai", "Inspect AI providers and their models"),
+    ("cli.plugin", "Install, list, enable and disable plugins"),
+    ("cli.jobs", "List the commands open windows are running"),
+    ("cli.kill", "Signal a running command by its block id"),
+    ("cli.preview", "Print a file highlighted with line numbers, or a hex dump of a binary one"),
+];
@@ -0,0 +1,275 @@
+//! Spanish strings.
+
+pub const STRINGS: &[(&str, &str)] = &[
+    // Settings tabs
+    ("settings.tab.general", "General"),
+    ("settings.tab.appearance", "Apariencia"),
+    ("settings.tab.terminal", "Terminal"),
+    ("settings.tab.editor", "Editor"),
+    ("settings.tab.keybindings", "Atajos de teclado"),
+    ("settings.tab.performance", "Rendimiento"),
+    ("settings.tab.privacy", "Privacidad"),
+    ("settings.tab.ai", "IA"),
+    ("settings.tab.plugins", "Complementos"),
+    // General
+    ("settings.general.title", "Ajustes generales"),
+    ("settings.general.startup", "Al iniciar:"),
+    ("settings.general.shell", "Shell predeterminada:"),
+    ("settings.general.shell_placeholder", "Ruta de la shell..."),
+    ("settings.general.language", "Idioma:"),
+    ("settings.general.language_system", "Predeterminado del sistema"),
+    ("settings.general.auto_update", "Actualización automática"),
+    ("settings.general.auto_update_help", "Buscar e instalar actualizaciones automáticamente"),
+    ("settings.general.telemetry", "Telemetría"),
+    ("settings.general.telemetry_help", "Ayuda a mejorar NeoTerm compartiendo datos de uso anónimos"),
+    ("settings.backups.open", "Restaurar desde una copia…"),
+    ("settings.backups.title", "Restaurar desde una copia"),
+    ("settings.backups.none", "Todavía no hay copias de la configuración"),
+    ("settings.backups.restore", "Restaurar"),
+    // Reset confirmation
+    ("settings.reset.title", "Restablecer valores predeterminados"),
+    ("settings.reset.nothing", "Nada difiere de los valores predeterminados"),
+    ("settings.reset.close", "Cerrar"),
+    ("settings.reset.explanation", "Estas secciones difieren de los valores predeterminados. Las marcadas se restablecerán; al guardar se hace una copia del archivo de configuración actual."),
+    ("settings.reset.confirm", "Restablecer selección"),
+    // Import confirmation
+    ("settings.import.title", "Importar configuración"),
+    ("settings.import.explanation", "Combinar conserva tus atajos de teclado y los ajustes que el archivo no incluye. Reemplazar usa los ajustes del archivo y los valores predeterminados para el resto. Los secretos que falten en el archivo se mantienen; al guardar se hace una copia del archivo de configuración actual."),
+    ("settings.import.merge", "Combinar"),
+    ("settings.import.replace", "Reemplazar"),
+    // Appearance
+    ("settings.appearance.title", "Ajustes de apariencia"),
+    ("settings.appearance.theme", "Tema:"),
+    ("settings.appearance.follow_system", "Cambiar entre un tema claro y uno oscuro según el sistema"),
+    ("settings.appearance.light_theme", "Tema claro:"),
+    ("settings.appearance.dark_theme", "Tema oscuro:"),
+    ("settings.appearance.font_family", "Tipo de letra:"),
+    ("settings.appearance.font_placeholder", "Nombre de la fuente..."),
+    ("settings.appearance.font_size", "Tamaño de letra:"),
+    ("settings.appearance.font_sample", "$ ls -la ~/src  # 0O 1lI {}[] => != ->"),
+    ("settings.appearance.transparency", "Transparencia:"),
+    ("settings.appearance.blur", "Desenfocar el fondo"),
+    ("settings.appearance.animations", "Activar animaciones"),
+    ("settings.appearance.status_glyphs", "Mostrar siempre los iconos de estado (✓ ✗ ⏳)"),
+    ("settings.appearance.tab_bar", "Barra de pestañas:"),
+    ("settings.appearance.tab_bar.always", "Siempre"),
+    ("settings.appearance.tab_bar.auto", "Con más de una pestaña"),
+    ("settings.appearance.tab_bar.never", "Nunca"),
+    ("settings.appearance.theme_editor", "Editor de temas personalizados"),
+    // Terminal
+    ("settings.terminal.title", "Ajustes del terminal"),
+    ("settings.terminal.scrollback", "Líneas de historial:"),
+    ("settings.terminal.scroll_sensitivity", "Sensibilidad del desplazamiento:"),
+    ("settings.terminal.copy_on_select", "Copiar al seleccionar"),
+    ("settings.terminal.paste_on_right_click", "Pegar con clic derecho"),
+    ("settings.terminal.confirm_close", "Confirmar antes de cerrar"),
+    ("settings.terminal.auto_collapse", "Plegar la salida larga de los comandos correctos"),
+    ("settings.terminal.auto_collapse_lines", "Líneas antes de plegar:"),
+    ("settings.terminal.notifications", "Avisar cuando termine un comando largo en segundo plano"),
+    ("settings.terminal.notify_after", "Segundos antes de avisar:"),
+    ("settings.terminal.use_pty", "Ejecutar comandos en un terminal del tamaño de la ventana (une stderr con stdout)"),
+    ("settings.terminal.require_attach", "Escribir en un comando en ejecución solo tras Conectar"),
+    ("settings.terminal.shell_integration", "Marcar los prompts de los shells iniciados en un bloque (se aplica al reiniciar)"),
+    ("settings.terminal.browse_on_ls", "Mostrar un `ls` sin argumentos como un directorio navegable"),
+    ("settings.terminal.hyperlinks", "Enlaces en la salida:"),
+    ("settings.terminal.hyperlinks.click", "Abrir al hacer clic"),
+    ("settings.terminal.hyperlinks.ctrl_click", "Abrir con Ctrl+clic"),
+    ("settings.terminal.hyperlinks.disabled", "No se pueden pulsar"),
+    ("settings.terminal.open_paths_in", "Abrir rutas de archivo en:"),
+    ("settings.terminal.open_paths_in.preview", "Vista previa"),
+    ("settings.terminal.open_paths_in.editor", "$EDITOR"),
+    ("settings.terminal.expand_variables", "Expandir $VARIABLES en cd y en comandos de plugins"),
+    ("settings.terminal.cursor_style", "Estilo del cursor:"),
+    ("settings.terminal.cursor_blink", "Cursor parpadeante"),
+    // Editor
+    ("settings.editor.title", "Ajustes del editor"),
+    ("settings.editor.vim_mode", "Modo Vim"),
+    ("settings.editor.auto_suggestions", "Sugerencias automáticas"),
+    ("settings.editor.ai_suggestions", "Sugerencias de IA cuando el historial no tiene ninguna"),
+    ("settings.editor.syntax_highlighting", "Resaltado de sintaxis"),
+    ("settings.editor.auto_completion", "Autocompletado"),
+    ("settings.editor.indent_size", "Tamaño de sangría:"),
+    ("settings.editor.tab_width", "Ancho de tabulación:"),
+    ("settings.editor.insert_spaces", "Insertar espacios"),
+    // Key bindings
+    ("settings.keybindings.title", "Atajos de teclado"),
+    // Performance
+    ("settings.performance.title", "Ajustes de rendimiento"),
+    ("settings.performance.gpu", "Aceleración por GPU"),
+    ("settings.performance.vsync", "Sincronización vertical"),
+    ("settings.performance.max_fps", "FPS máximos:"),
+    ("settings.performance.memory_limit", "Límite de memoria (MB):"),
+    // Privacy
+    ("settings.privacy.title", "Ajustes de privacidad"),
+    ("settings.privacy.history", "Guardar historial"),
+    ("settings.privacy.history_limit", "Límite del historial:"),
+    ("settings.privacy.clear_on_exit", "Borrar el historial al salir"),
+    ("settings.privacy.incognito", "Modo incógnito"),
+    ("settings.privacy.share_host_identity", "Permitir que el asistente vea el nombre del equipo y del usuario"),
+    ("settings.clear.title", "Borrar datos"),
+    ("settings.clear.history", "Historial"),
+    ("settings.clear.blocks", "Bloques"),
+    ("settings.clear.conversations", "Conversaciones"),
+    ("settings.clear.caches", "Cachés"),
+    ("settings.clear.plugins_data", "Datos de complementos"),
+    ("settings.clear.all", "Todo"),
+    ("settings.clear.help", "Se conservan los temas, flujos de trabajo y atajos. Se pide confirmación antes de borrar nada."),
+    ("settings.crash_reports.title", "Informes de fallos"),
+    ("settings.crash_reports.send", "Enviar informes de fallos"),
+    ("settings.crash_reports.dsn", "DSN de informes:"),
+    ("settings.crash_reports.help", "Los informes se limpian de comandos, valores de entorno y claves, y siempre se guardan localmente (`neoterm crashes list`). Los cambios se aplican en el próximo inicio."),
+    ("settings.network.title", "Red"),
+    ("settings.network.help", "Los campos de proxy vacíos usan HTTP_PROXY, HTTPS_PROXY y NO_PROXY del entorno."),
+    ("settings.network.http_proxy", "Proxy HTTP:"),
+    ("settings.network.https_proxy", "Proxy HTTPS:"),
+    ("settings.network.no_proxy", "Sin proxy:"),
+    ("settings.network.ca_bundle", "Certificados de CA:"),
+    ("settings.network.verify_tls", "Verificar certificados TLS"),
+    ("settings.network.tls_off", "⚠ La verificación de certificados está DESACTIVADA. Cualquiera en la ruta de red puede leer y alterar tu tráfico de IA, sincronización y unidad."),
+    // Plugins
+    ("settings.plugins.title", "Ajustes de complementos"),
+    ("settings.plugins.none", "No hay complementos instalados. Instala uno con `neoterm plugin install <directorio o .tar.gz>`."),
+    ("settings.plugins.revoke", "Revocar"),
+    ("settings.plugins.allow", "Permitir"),
+    ("settings.plugins.revoked", "revocado"),
+    ("settings.plugins.update_available", "Actualización disponible:"),
+    ("plugin.permission.filesystem", "Leer y escribir archivos en cualquier lugar al que tenga acceso tu cuenta"),
+    ("plugin.permission.network", "Enviar y recibir datos por la red"),
+    ("plugin.permission.run_commands", "Ejecutar comandos en tu shell"),
+    ("plugin.permission.clipboard", "Leer tu portapapeles"),
+    ("plugin.permission.environment", "Leer tus variables de entorno, que pueden contener secretos"),
+    ("settings.ai.title", "Ajustes de IA"),
+    ("settings.ai.provider", "Proveedor"),
+    ("settings.ai.provider_auto", "Según el entorno"),
+    ("settings.ai.model", "Modelo"),
+    ("settings.ai.refresh_models", "Actualizar modelos"),
+    ("settings.ai.base_url", "URL base"),
+    ("settings.ai.api_key", "Clave de API"),
+    ("settings.ai.api_key_help", "Si se deja vacía, la clave se lee de la variable de entorno indicada. Las claves escritas aquí se guardan en el archivo de configuración."),
+    ("settings.ai.temperature", "Temperatura"),
+    ("settings.ai.confirm_generated_commands", "Confirmar antes de ejecutar fragmentos de varios comandos"),
+    ("settings.ai.natural_language_commands", "Convertir frases escritas en el prompt en comandos (Ctrl+Enter ejecuta tal cual)"),
+    ("settings.ai.one_click_fixes", "Ofrecer \"Aplicar y ejecutar\" en las correcciones sugeridas (las destructivas siempre se revisan)"),
+    ("settings.ai.auto_fix_on_failure", "Sugerir una corrección cada vez que falle un comando"),
+    ("settings.ai.tool_approval", "Comandos del agente"),
+    ("settings.ai.tool_approval.ask", "Preguntar antes de ejecutar"),
+    ("settings.ai.tool_approval.auto", "Ejecutar sin preguntar"),
+    ("settings.ai.tool_approval.deny_shell", "Nunca ejecutar comandos ni escribir archivos"),
+    ("settings.ai.no_tools", "Este proveedor no admite herramientas: el agente responde, pero no puede ejecutar comandos ni leer archivos por ti."),
+    // Actions
+    ("settings.actions.reset", "Restablecer valores predeterminados"),
+    ("settings.actions.import", "Importar configuración"),
+    ("settings.actions.export", "Exportar configuración"),
+    ("settings.actions.reset_short", "Restablecer"),
+    ("settings.actions.import_short", "Importar"),
+    ("settings.actions.export_short", "Exportar"),
+    ("settings.actions.cancel", "Cancelar"),
+    ("settings.actions.save", "Guardar"),
+    // Config sections, in the reset confirmation
+    ("config.section.theme", "Tema"),
+    ("config.section.keybindings", "Atajos de teclado"),
+    ("config.section.env_profile", "Perfil de entorno activo"),
+    ("config.section.plugins", "Complementos"),
+    ("config.section.general", "General"),
+    ("config.section.terminal", "Terminal"),
+    ("config.section.editor", "Editor"),
+    ("config.section.ui", "Interfaz"),
+    ("config.section.appearance", "Modo claro y oscuro"),
+    ("config.section.performance", "Rendimiento"),
+    ("config.section.privacy", "Privacidad"),
+    ("config.section.network", "Red"),
+    ("config.section.share", "Compartir"),
+    ("config.section.crash_reports", "Informes de fallos"),
+    ("config.section.maintenance", "Mantenimiento"),
+    ("config.section.scratch", "Ejecución de fragmentos"),
+    ("config.section.diagnostics", "Diagnóstico"),
+    ("config.section.ai", "Proveedor de IA"),
+    ("config.section.ai_context", "Contexto para la IA"),
+    ("config.section.hooks", "Scripts de eventos"),
+    // Block headers and buttons
+    ("block.running", "en curso"),
+    ("block.exit", "salida {code}"),
+    ("block.snippet", "↳ fragmento"),
+    ("block.retry", "↳ reintento con corrección"),
+    ("block.workflow", "⚙ {name}"),
+    ("block.container", "🐳 {name}"),
+    ("block.shadowing", "ejecuta {path}, no {shadowed}"),
+    ("block.tee", "→ {path} · {bytes} bytes"),
+    ("block.truncated", "{total} líneas, se muestran las últimas {shown}"),
+    ("block.timeline", "{position} / {duration} · {chunks} de {total} fragmentos"),
+    ("block.action.rerun", "Repetir"),
+    ("block.action.copy", "Copiar"),
+    ("block.action.export", "Exportar…"),
+    ("block.action.ask_ai", "Preguntar a la IA"),
+    ("block.action.explain", "Explicar la salida"),
+    ("block.action.toggle_annotation", "Mostrar u ocultar la explicación"),
+    ("block.action.pin_annotation", "Fijar en la conversación"),
+    ("block.action.dismiss_annotation", "Descartar la explicación"),
+    ("block.action.suggest_fix", "Sugerir una corrección"),
+    ("block.annotation.title", "Explicación"),
+    ("block.annotation.loading", "Explicando…"),
+    ("block.annotation.failed", "No se pudo explicar la salida: {error}"),
+    ("block.annotation.pinned", "Fijada en la conversación"),
+    ("block.action.use_fix", "Editar la corrección"),
+    ("block.action.apply_fix", "Aplicar y ejecutar"),
+    ("block.action.dismiss_fix", "Descartar la corrección"),
+    ("block.fix.title", "Corrección sugerida"),
+    ("block.fix.loading", "Buscando una corrección…"),
+    ("block.fix.failed", "Sin corrección sugerida: {error}"),
+    ("block.fix.review", "Revisar antes de ejecutar: {reason}"),
+    ("block.action.delete", "Eliminar"),
+    ("block.action.share", "Compartir"),
+    ("block.action.unshare", "Dejar de compartir"),
+    ("blocks.filter.all", "Todos"),
+    ("blocks.filter.commands", "Comandos"),
+    ("blocks.filter.errors", "Errores"),
+    ("blocks.filter.ai", "IA"),
+    ("blocks.filter.pinned", "Fijados"),
+    ("block.action.collapse", "Plegar"),
+    ("block.action.expand", "Desplegar"),
+    ("block.action.pin", "Fijar"),
+    ("block.action.unpin", "Desfijar"),
+    ("block.action.move_to_top", "Mover arriba del todo"),
+    ("block.action.move_to_bottom", "Mover abajo del todo"),
+    ("block.action.timeline", "Línea de tiempo"),
+    ("block.action.fork", "Bifurcar"),
+    ("block.action.edit", "Editar"),
+    ("block.action.tee", "Escribir también en un archivo…"),
+    ("block.action.stop_tee", "Dejar de escribir en el archivo"),
+    ("block.action.show_full_output", "Ver la salida completa"),
+    ("block.action.copy_code", "Copiar código"),
+    ("block.action.run_code", "Ejecutar"),
+    ("block.action.insert_code", "Insertar"),
+    // Status line
+    ("status.mode.normal", "NORMAL"),
+    ("status.mode.agent", "AGENTE"),
+    ("status.mode.incognito", "INCÓGNITO"),
+    ("status.mode.broadcast", "DIFUSIÓN"),
+    ("status.read_only", "🔒 solo lectura"),
+    ("status.sync", "{spinner} sincronizando"),
+    ("status.ai", "{spinner} IA"),
+    ("status.idle", "inactivo"),
+    ("status.running", "{count} en curso"),
+    ("status.queued", ", {count} en cola"),
+    // Desktop notifications
+    ("notify.bell", "NeoTerm: campana"),
+    ("notify.command_succeeded", "NeoTerm: comando terminado"),
+    ("notify.command_failed", "NeoTerm: el comando falló (salida {code})"),
+    // Command-line help
+    ("cli.about", "Un terminal moderno con bloques, flujos de trabajo y modo agente"),
+    ("cli.workflow", "Ejecutar y gestionar flujos de trabajo"),
+    ("cli.exec", "Ejecutar un comando e informar de su salida, opcionalmente como eventos estructurados"),
+    ("cli.doctor", "Comprobar la instalación y la configuración"),
+    ("cli.config", "Consultar, cambiar, exportar e importar ajustes, migrar las ubicaciones de la configuración, importar temas y cambiar de perfil"),
+    ("cli.crashes", "Consultar los informes de fallos guardados localmente"),
+    ("cli.maintenance", "Recortar el historial de ejecuciones, las cachés y los informes de fallos a sus límites de retención"),
+    ("cli.clear", "Borrar el historial, los bloques, las conversaciones, las cachés o los datos de complementos guardados"),
+    ("cli.which", "Mostrar todos los ejecutables a los que se resuelve un nombre de comando en el PATH"),
+    ("cli.export", "Escribir los bloques de una sesión guardada en un único documento Markdown o HTML"),
+    ("cli.learn", "Practicar con un cuestionario de opción múltiple sobre las plantillas de comandos incluidas"),
+    ("cli.ai", "Consultar los proveedores de IA y sus modelos"),
+    ("cli.plugin", "Instalar, listar, activar y desactivar complementos"),
+    ("cli.jobs", "Listar los comandos que ejecutan las ventanas abiertas"),
+    ("cli.kill", "Enviar una señal a un comando en ejecución por el id de su bloque"),
+    ("cli.preview", "Mostrar un archivo resaltado con números de línea, o un volcado hexadecimal si es binario"),
+];
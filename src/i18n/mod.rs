@@ -0,0 +1,254 @@
+//! Translatable UI strings and locale-aware number, duration and date
+//! formatting. Strings are looked up by key in the current locale's catalog,
+//! then in English, then fall back to the key itself, so a missing
+//! translation shows up as English rather than as nothing.
+
+mod en;
+mod es;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::RwLock;
+use std::time::Duration;
+
+static CURRENT: RwLock<Locale> = RwLock::new(Locale::En);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+impl Locale {
+    pub const ALL: &'static [Locale] = &[Locale::En, Locale::Es];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Es => "es",
+        }
+    }
+
+    /// The language's name in itself, for pickers
+    pub fn native_name(&self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::Es => "Español",
+        }
+    }
+
+    /// From a language tag or POSIX locale: `es`, `es-419`, `es_MX.UTF-8`
+    pub fn from_code(code: &str) -> Option<Locale> {
+        let language = code.split(['_', '-', '.', '@']).next()?.to_ascii_lowercase();
+        Self::ALL.iter().copied().find(|locale| locale.code() == language)
+    }
+
+    /// From LC_ALL, LC_MESSAGES or LANG, whichever is set first; English
+    /// when none names a supported language
+    pub fn from_env() -> Locale {
+        Self::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    pub fn from_env_with(env: impl Fn(&str) -> Option<String>) -> Locale {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env(name))
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::from_code(&value))
+            .unwrap_or_default()
+    }
+
+    fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => en::STRINGS,
+            Locale::Es => es::STRINGS,
+        }
+    }
+
+    fn lookup(&self, key: &str) -> Option<&'static str> {
+        self.catalog().iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+    }
+
+    pub fn tr(&self, key: &'static str) -> &'static str {
+        self.lookup(key).or_else(|| Locale::En.lookup(key)).unwrap_or(key)
+    }
+
+    /// `tr` with `{name}` placeholders filled in
+    pub fn tr_args(&self, key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+        let mut message = self.tr(key).to_string();
+        for (name, value) in args {
+            message = message.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+        message
+    }
+
+    /// Thousands and decimal separators
+    fn separators(&self) -> (char, char) {
+        match self {
+            Locale::En => (',', '.'),
+            Locale::Es => ('.', ','),
+        }
+    }
+
+    /// `184302` as `184,302` or `184.302`
+    pub fn format_number(&self, n: u64) -> String {
+        let (thousands, _) = self.separators();
+        let digits = n.to_string();
+        let mut formatted = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                formatted.push(thousands);
+            }
+            formatted.push(digit);
+        }
+        formatted
+    }
+
+    fn format_decimal(&self, value: f64, places: usize) -> String {
+        let (_, decimal) = self.separators();
+        let fixed = format!("{:.*}", places, value.max(0.0));
+        match fixed.split_once('.') {
+            Some((whole, fraction)) => {
+                format!("{}{}{}", self.format_number(whole.parse().unwrap_or(0)), decimal, fraction)
+            }
+            None => self.format_number(fixed.parse().unwrap_or(0)),
+        }
+    }
+
+    /// `1.5s` under a minute, then `2m 05s`, then `1h 02m`
+    pub fn format_duration(&self, duration: Duration) -> String {
+        let secs = duration.as_secs();
+        if secs < 60 {
+            format!("{}s", self.format_decimal(duration.as_secs_f64(), 1))
+        } else if secs < 3600 {
+            format!("{}m {:02}s", secs / 60, secs % 60)
+        } else {
+            format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+        }
+    }
+
+    pub fn format_datetime(&self, time: &DateTime<Local>) -> String {
+        let format = match self {
+            Locale::En => "%Y-%m-%d %H:%M:%S",
+            Locale::Es => "%d/%m/%Y %H:%M:%S",
+        };
+        time.format(format).to_string()
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.native_name())
+    }
+}
+
+/// Use `locale` for everything shown from now on
+pub fn set_locale(locale: Locale) {
+    *CURRENT.write().unwrap() = locale;
+}
+
+pub fn locale() -> Locale {
+    *CURRENT.read().unwrap()
+}
+
+/// The current locale's string for `key`
+pub fn tr(key: &'static str) -> &'static str {
+    locale().tr(key)
+}
+
+pub fn tr_args(key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    locale().tr_args(key, args)
+}
+
+pub fn format_number(n: u64) -> String {
+    locale().format_number(n)
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    locale().format_duration(duration)
+}
+
+pub fn format_datetime(time: &DateTime<Local>) -> String {
+    locale().format_datetime(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::BTreeSet;
+
+    fn placeholders(template: &str) -> BTreeSet<&str> {
+        template
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn test_every_locale_is_complete() {
+        let english: BTreeSet<&str> = en::STRINGS.iter().map(|(key, _)| *key).collect();
+        assert_eq!(english.len(), en::STRINGS.len(), "duplicate English keys");
+
+        for locale in Locale::ALL {
+            let keys: BTreeSet<&str> = locale.catalog().iter().map(|(key, _)| *key).collect();
+            assert_eq!(keys.len(), locale.catalog().len(), "duplicate {} keys", locale.code());
+            assert_eq!(keys, english, "{} keys differ from English", locale.code());
+            for (key, template) in locale.catalog() {
+                assert!(!template.is_empty(), "{} {} is empty", locale.code(), key);
+                assert_eq!(
+                    placeholders(template),
+                    placeholders(Locale::En.tr(key)),
+                    "{} {} has different placeholders",
+                    locale.code(),
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_and_fallback() {
+        assert_eq!(Locale::Es.tr("settings.actions.save"), "Guardar");
+        assert_eq!(Locale::En.tr("settings.actions.save"), "Save");
+        assert_eq!(Locale::Es.tr("no.such.key"), "no.such.key");
+        assert_eq!(Locale::Es.tr_args("block.exit", &[("code", &127)]), "salida 127");
+    }
+
+    #[test]
+    fn test_locale_from_environment() {
+        assert_eq!(Locale::from_code("es_MX.UTF-8"), Some(Locale::Es));
+        assert_eq!(Locale::from_code("es-419"), Some(Locale::Es));
+        assert_eq!(Locale::from_code("C"), None);
+
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+        };
+        assert_eq!(Locale::from_env_with(env(&[("LANG", "es_ES.UTF-8")])), Locale::Es);
+        assert_eq!(Locale::from_env_with(env(&[("LC_ALL", "en_US.UTF-8"), ("LANG", "es_ES.UTF-8")])), Locale::En);
+        assert_eq!(Locale::from_env_with(env(&[("LC_ALL", ""), ("LANG", "es_ES.UTF-8")])), Locale::Es);
+        assert_eq!(Locale::from_env_with(env(&[("LANG", "fr_FR.UTF-8")])), Locale::En);
+    }
+
+    #[test]
+    fn test_numbers_durations_and_dates() {
+        assert_eq!(Locale::En.format_number(0), "0");
+        assert_eq!(Locale::En.format_number(999), "999");
+        assert_eq!(Locale::En.format_number(1000), "1,000");
+        assert_eq!(Locale::En.format_number(184_302), "184,302");
+        assert_eq!(Locale::Es.format_number(1_234_567), "1.234.567");
+
+        assert_eq!(Locale::En.format_duration(Duration::from_millis(1540)), "1.5s");
+        assert_eq!(Locale::Es.format_duration(Duration::from_millis(1540)), "1,5s");
+        assert_eq!(Locale::En.format_duration(Duration::from_secs(125)), "2m 05s");
+        assert_eq!(Locale::Es.format_duration(Duration::from_secs(3720)), "1h 02m");
+
+        let time = Local.with_ymd_and_hms(2026, 3, 14, 15, 9, 26).unwrap();
+        assert_eq!(Locale::En.format_datetime(&time), "2026-03-14 15:09:26");
+        assert_eq!(Locale::Es.format_datetime(&time), "14/03/2026 15:09:26");
+    }
+}
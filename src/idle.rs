@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_IDLE_MINUTES: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleState {
+    Active,
+    Idle,
+    /// Idle past the lock threshold
+    LongIdle,
+}
+
+impl IdleState {
+    pub fn is_idle(&self) -> bool {
+        *self != IdleState::Active
+    }
+}
+
+/// Background work that should stop while nobody is at the keyboard
+pub trait IdleAware {
+    fn pause(&mut self);
+    fn resume(&mut self);
+}
+
+/// Tracks the time since the last input event and reports each change of
+/// state exactly once. Time is passed in so tests can drive it.
+#[derive(Debug, Clone)]
+pub struct IdleDetector {
+    idle_after: Duration,
+    /// `None` disables the long-idle state
+    long_idle_after: Option<Duration>,
+    last_activity: Instant,
+    state: IdleState,
+}
+
+impl IdleDetector {
+    pub fn new(idle_after: Duration, long_idle_after: Option<Duration>, now: Instant) -> Self {
+        Self {
+            idle_after,
+            long_idle_after,
+            last_activity: now,
+            state: IdleState::Active,
+        }
+    }
+
+    pub fn state(&self) -> IdleState {
+        self.state
+    }
+
+    /// An input event happened. Returns the new state if this ends idleness.
+    pub fn record_activity(&mut self, now: Instant) -> Option<IdleState> {
+        self.last_activity = now;
+        self.transition(IdleState::Active)
+    }
+
+    /// Check the clock. Returns the new state if a threshold was crossed.
+    pub fn poll(&mut self, now: Instant) -> Option<IdleState> {
+        let quiet = now.saturating_duration_since(self.last_activity);
+        let state = match self.long_idle_after {
+            Some(long) if quiet >= long => IdleState::LongIdle,
+            _ if quiet >= self.idle_after => IdleState::Idle,
+            _ => IdleState::Active,
+        };
+        self.transition(state)
+    }
+
+    fn transition(&mut self, state: IdleState) -> Option<IdleState> {
+        if state == self.state {
+            return None;
+        }
+        self.state = state;
+        Some(state)
+    }
+}
+
+/// Pause or resume subscribers for a reported change. Only the edges between
+/// active and idle reach them; deepening into long idle does not.
+pub fn notify(previous: IdleState, current: IdleState, subscribers: &mut [&mut dyn IdleAware]) {
+    match (previous.is_idle(), current.is_idle()) {
+        (false, true) => subscribers.iter_mut().for_each(|s| s.pause()),
+        (true, false) => subscribers.iter_mut().for_each(|s| s.resume()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter {
+        paused: usize,
+        resumed: usize,
+    }
+
+    impl IdleAware for Counter {
+        fn pause(&mut self) {
+            self.paused += 1;
+        }
+
+        fn resume(&mut self) {
+            self.resumed += 1;
+        }
+    }
+
+    fn minutes(n: u64) -> Duration {
+        Duration::from_secs(n * 60)
+    }
+
+    #[test]
+    fn test_pause_and_resume_fire_once_per_transition() {
+        let start = Instant::now();
+        let mut detector = IdleDetector::new(minutes(5), Some(minutes(30)), start);
+        let mut counter = Counter::default();
+
+        let mut changes = Vec::new();
+        for offset in [1, 4, 6, 7, 10, 31, 45] {
+            let previous = detector.state();
+            if let Some(current) = detector.poll(start + minutes(offset)) {
+                changes.push(current);
+                notify(previous, current, &mut [&mut counter]);
+            }
+        }
+        assert_eq!(changes, vec![IdleState::Idle, IdleState::LongIdle]);
+        assert_eq!((counter.paused, counter.resumed), (1, 0));
+
+        let previous = detector.state();
+        let change = detector.record_activity(start + minutes(46));
+        assert_eq!(change, Some(IdleState::Active));
+        notify(previous, change.unwrap(), &mut [&mut counter]);
+
+        // More activity while already active changes nothing
+        assert_eq!(detector.record_activity(start + minutes(47)), None);
+        assert_eq!(detector.poll(start + minutes(50)), None);
+        assert_eq!((counter.paused, counter.resumed), (1, 1));
+    }
+
+    #[test]
+    fn test_long_idle_can_be_disabled() {
+        let start = Instant::now();
+        let mut detector = IdleDetector::new(minutes(5), None, start);
+
+        assert_eq!(detector.poll(start + minutes(5)), Some(IdleState::Idle));
+        assert_eq!(detector.poll(start + minutes(600)), None);
+        assert_eq!(detector.state(), IdleState::Idle);
+    }
+}
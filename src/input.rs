@@ -84,6 +84,19 @@ impl EnhancedTextInput {
         self.update_suggestions();
     }
 
+    /// Replace the text without treating it as a command, for prompts that
+    /// ask for something else such as a path
+    pub fn set_value(&mut self, value: String) {
+        self.value = value;
+        self.syntax_tree = None;
+        self.suggestions.clear();
+        self.active_suggestion = None;
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
     pub fn add_to_history(&mut self, command: String) {
         if !command.trim().is_empty() && self.history.front() != Some(&command) {
             self.history.push_front(command);
@@ -271,9 +284,19 @@ impl EnhancedTextInput {
     }
 
     pub fn view(&self) -> Element<Message> {
-        let input = text_input("Enter command...", &self.value)
-            .on_input(Message::InputChanged)
-            .on_submit(Message::ExecuteCommand)
+        self.view_with("Enter command...", Message::InputChanged, Message::ExecuteCommand)
+    }
+
+    /// The input with its own placeholder and messages, for prompts other than the command line
+    pub fn view_with<'a>(
+        &'a self,
+        placeholder: &str,
+        on_input: impl Fn(String) -> Message + 'a,
+        on_submit: Message,
+    ) -> Element<'a, Message> {
+        let input = text_input(placeholder, &self.value)
+            .on_input(on_input)
+            .on_submit(on_submit)
             .padding(12)
             .size(16);
 
@@ -339,3 +362,89 @@ pub enum HistoryDirection {
     Up,
     Down,
 }
+
+/// Whether `text` is an unfinished command that Enter should continue on a
+/// new line rather than submit: an unclosed quote, a trailing backslash, or
+/// a heredoc whose delimiter line hasn't been typed yet
+pub fn needs_continuation(text: &str) -> bool {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    // Delimiters of open heredocs, and whether leading tabs are stripped
+    let mut heredocs: Vec<(String, bool)> = Vec::new();
+
+    for line in text.split('\n') {
+        if let Some((delimiter, strip_tabs)) = heredocs.first() {
+            let line = if *strip_tabs { line.trim_start_matches('\t') } else { line };
+            if line == delimiter.as_str() {
+                heredocs.remove(0);
+            }
+            continue;
+        }
+
+        let mut chars = line.char_indices().peekable();
+        escaped = false;
+        while let Some((i, c)) = chars.next() {
+            if escaped {
+                escaped = false;
+                continue;
+            }
+            match (quote, c) {
+                (Some('\''), '\'') => quote = None,
+                (Some('\''), _) => {}
+                (_, '\\') => escaped = true,
+                (Some('"'), '"') => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(c),
+                (None, '#') if i == 0 || line[..i].ends_with(char::is_whitespace) => break,
+                // A here-string, not a heredoc
+                (None, '<') if line[i..].starts_with("<<<") => {
+                    chars.next();
+                    chars.next();
+                }
+                (None, '<') if line[i..].starts_with("<<") => {
+                    chars.next();
+                    let rest = &line[i + 2..];
+                    let strip_tabs = rest.starts_with('-');
+                    let word: String = rest
+                        .trim_start_matches('-')
+                        .trim_start()
+                        .chars()
+                        .take_while(|c| !c.is_whitespace() && !matches!(c, ';' | '|' | '&' | '<' | '>'))
+                        .filter(|c| !matches!(c, '\'' | '"' | '\\'))
+                        .collect();
+                    if !word.is_empty() {
+                        heredocs.push((word, strip_tabs));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    quote.is_some() || escaped || !heredocs.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unfinished_commands_continue() {
+        assert!(!needs_continuation("echo hi"));
+        assert!(needs_continuation("echo 'it"));
+        assert!(needs_continuation("echo \"one\ntwo"));
+        assert!(!needs_continuation("echo \"one\ntwo\""));
+        assert!(!needs_continuation("echo \"it's\""));
+        assert!(needs_continuation("ls \\"));
+        assert!(!needs_continuation("echo \\\\"));
+        assert!(!needs_continuation("echo 'a' # it's fine"));
+    }
+
+    #[test]
+    fn test_heredoc_continues_until_its_delimiter() {
+        assert!(needs_continuation("cat <<EOF"));
+        assert!(needs_continuation("cat <<'EOF' > out.txt\nline"));
+        assert!(!needs_continuation("cat <<'EOF' > out.txt\nline\nEOF"));
+        assert!(!needs_continuation("cat <<-END\n\tline\n\tEND"));
+        assert!(!needs_continuation("grep x <<< \"$text\""));
+    }
+}
@@ -0,0 +1,245 @@
+//! Commands NeoTerm has running, for `jobs`-style control from the palette
+//! and from `neoterm jobs` / `neoterm kill`. Each window records its jobs
+//! in a file of its own under the cache directory, named after its process
+//! id, so the CLI can find them; files of windows that are gone are
+//! removed when listed.
+//!
+//! Commands run in their own session, so signals go to the job's process
+//! group and reach everything it started, not just the shell.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::i18n::format_duration;
+
+/// A running command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub block_id: Uuid,
+    pub pid: u32,
+    pub command: String,
+    pub started_at: DateTime<Utc>,
+}
+
+impl Job {
+    pub fn elapsed(&self, now: DateTime<Utc>) -> Duration {
+        (now - self.started_at).to_std().unwrap_or_default()
+    }
+
+    /// `pid · elapsed · cpu · memory`, for listing next to the command
+    pub fn summary(&self, usage: Option<Usage>, now: DateTime<Utc>) -> String {
+        let usage = usage
+            .map(|usage| format!("{:.1}% CPU · {:.1} MB", usage.cpu_percent, usage.memory_bytes as f64 / (1024.0 * 1024.0)))
+            .unwrap_or_else(|| "usage unknown".to_string());
+        format!("pid {} · {} · {}", self.pid, format_duration(self.elapsed(now)), usage)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Usage {
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+}
+
+/// CPU and resident memory of `pid`, as `ps` reports them
+pub fn usage(pid: u32) -> Option<Usage> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "%cpu=,rss=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    parse_ps(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_ps(output: &str) -> Option<Usage> {
+    let mut fields = output.split_whitespace();
+    let cpu_percent = fields.next()?.replace(',', ".").parse().ok()?;
+    let rss_kib: u64 = fields.next()?.parse().ok()?;
+    Some(Usage { cpu_percent, memory_bytes: rss_kib * 1024 })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum JobSignal {
+    /// SIGTERM
+    Kill,
+    /// SIGSTOP
+    Stop,
+    /// SIGCONT
+    Continue,
+}
+
+impl JobSignal {
+    pub fn verb(self) -> &'static str {
+        match self {
+            JobSignal::Kill => "Kill",
+            JobSignal::Stop => "Stop",
+            JobSignal::Continue => "Continue",
+        }
+    }
+
+    /// Send the signal to the process group `pid` leads
+    #[cfg(unix)]
+    pub fn send(self, pid: u32) -> std::io::Result<()> {
+        let signal = match self {
+            JobSignal::Kill => libc::SIGTERM,
+            JobSignal::Stop => libc::SIGSTOP,
+            JobSignal::Continue => libc::SIGCONT,
+        };
+        // SAFETY: kill only takes plain integers
+        if unsafe { libc::kill(-(pid as libc::pid_t), signal) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn send(self, _pid: u32) -> std::io::Result<()> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "job control needs a Unix system"))
+    }
+}
+
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks that the process exists
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+        || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+/// This window's running commands, by block, kept in its jobs file
+#[derive(Debug, Clone, Default)]
+pub struct JobRegistry {
+    running: Arc<Mutex<HashMap<Uuid, Job>>>,
+    file: Option<PathBuf>,
+}
+
+impl JobRegistry {
+    /// Record the jobs in `dir` for `neoterm jobs`
+    pub fn recorded_in(dir: PathBuf) -> Self {
+        Self { file: Some(dir.join(format!("{}.json", std::process::id()))), ..Default::default() }
+    }
+
+    pub fn insert(&self, job: Job) {
+        let mut running = self.running.lock().unwrap();
+        running.insert(job.block_id, job);
+        self.save(&running);
+    }
+
+    /// Forget `block_id`'s job once its command has exited, however it ended
+    pub fn remove(&self, block_id: Uuid) {
+        let mut running = self.running.lock().unwrap();
+        if running.remove(&block_id).is_some() {
+            self.save(&running);
+        }
+    }
+
+    pub fn get(&self, block_id: Uuid) -> Option<Job> {
+        self.running.lock().unwrap().get(&block_id).cloned()
+    }
+
+    /// Oldest first
+    pub fn list(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.running.lock().unwrap().values().cloned().collect();
+        jobs.sort_by_key(|job| job.started_at);
+        jobs
+    }
+
+    fn save(&self, running: &HashMap<Uuid, Job>) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let saved = if running.is_empty() {
+            std::fs::remove_file(file).or_else(|e| if e.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(e) })
+        } else {
+            let jobs: Vec<&Job> = running.values().collect();
+            file.parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(file, serde_json::to_vec(&jobs).unwrap_or_default()))
+        };
+        if let Err(e) = saved {
+            log::debug!("Cannot record jobs in {}: {}", file.display(), e);
+        }
+    }
+}
+
+/// Jobs recorded by every window in `dir`, oldest first. Files left by
+/// windows that have quit are deleted.
+pub fn list(dir: &Path) -> Vec<Job> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut jobs = Vec::new();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let owner = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse::<u32>().ok());
+        let Some(owner) = owner else {
+            continue;
+        };
+        if !is_alive(owner) {
+            let _ = std::fs::remove_file(&path);
+            continue;
+        }
+        let recorded: Vec<Job> = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        jobs.extend(recorded.into_iter().filter(|job| is_alive(job.pid)));
+    }
+    jobs.sort_by_key(|job| job.started_at);
+    jobs
+}
+
+/// The job whose block id starts with `prefix`, if just one does
+pub fn find<'a>(jobs: &'a [Job], prefix: &str) -> Option<&'a Job> {
+    let mut matches = jobs.iter().filter(|job| job.block_id.to_string().starts_with(&prefix.to_lowercase()));
+    let found = matches.next()?;
+    matches.next().is_none().then_some(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(block_id: &str, pid: u32) -> Job {
+        Job { block_id: Uuid::parse_str(block_id).unwrap(), pid, command: "cargo build".to_string(), started_at: Utc::now() }
+    }
+
+    #[test]
+    fn test_parse_ps_and_summary() {
+        let usage = parse_ps("  3.5 40960\n").unwrap();
+        assert_eq!(usage, Usage { cpu_percent: 3.5, memory_bytes: 40960 * 1024 });
+        assert_eq!(parse_ps(""), None);
+
+        let job = Job { started_at: Utc::now() - chrono::Duration::seconds(65), ..job("a1b2c3d4-0000-0000-0000-000000000000", 4242) };
+        assert_eq!(job.summary(Some(usage), job.started_at + chrono::Duration::seconds(65)), "pid 4242 · 1m 05s · 3.5% CPU · 40.0 MB");
+    }
+
+    #[test]
+    fn test_find_by_unique_prefix() {
+        let jobs = [job("a1b2c3d4-0000-0000-0000-000000000000", 1), job("a1ffffff-0000-0000-0000-000000000000", 2)];
+        assert_eq!(find(&jobs, "A1B").map(|job| job.pid), Some(1));
+        assert_eq!(find(&jobs, "a1"), None);
+        assert_eq!(find(&jobs, "b"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_registry_file_is_listed_until_the_job_ends() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = JobRegistry::recorded_in(dir.path().to_path_buf());
+        let ours = job("a1b2c3d4-0000-0000-0000-000000000000", std::process::id());
+        registry.insert(ours.clone());
+        assert_eq!(list(dir.path()), vec![ours.clone()]);
+
+        registry.remove(ours.block_id);
+        assert!(list(dir.path()).is_empty());
+        assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+}
@@ -0,0 +1,456 @@
+//! Keys that run application actions. `keybindings.bindings` in the config
+//! maps an action id such as `palette` or `split-pane` to key sequences
+//! written like `Ctrl+Shift+P` or `Ctrl+K Ctrl+T`.
+//!
+//! `Keymap` follows key presses through those sequences: the first keys of
+//! a longer one wait for the rest, and any other key abandons it.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+use crate::config::KeyBindings;
+
+/// Keys the editor records for one binding
+pub const MAX_SEQUENCE: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Execute,
+    ToggleAgent,
+    ToggleAiSidebar,
+    Palette,
+    Settings,
+    HistorySearch,
+    Find,
+    Complete,
+    CompletePrevious,
+    SplitPane,
+    SplitPaneDown,
+    ClosePane,
+    FocusPaneLeft,
+    FocusPaneRight,
+    FocusPaneUp,
+    FocusPaneDown,
+    PreviousPrompt,
+    NextPrompt,
+    MoveBlockUp,
+    MoveBlockDown,
+    NewTab,
+    CloseTab,
+    /// 1-based
+    SelectTab(u8),
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+}
+
+/// Every action: its id in the config, what the editor calls it, and its
+/// default keys
+const ACTIONS: &[(Action, &str, &str, &[&str])] = &[
+    (Action::Execute, "execute", "Run the command", &[]),
+    (Action::ToggleAgent, "toggle-agent", "Toggle agent mode", &[]),
+    (Action::ToggleAiSidebar, "toggle-ai-sidebar", "Browse conversations", &[]),
+    (Action::Palette, "palette", "Open the command palette", &["Ctrl+Shift+P"]),
+    (Action::Settings, "settings", "Open settings", &["Ctrl+,"]),
+    (Action::HistorySearch, "history-search", "Search history", &["Ctrl+R"]),
+    (Action::Find, "find", "Search blocks", &["Ctrl+F"]),
+    (Action::Complete, "complete", "Complete the word", &["Tab"]),
+    (Action::CompletePrevious, "complete-previous", "Complete the word, backwards", &["Shift+Tab"]),
+    (Action::SplitPane, "split-pane", "Split pane right", &["Ctrl+Shift+D"]),
+    (Action::SplitPaneDown, "split-pane-down", "Split pane down", &["Ctrl+Shift+E"]),
+    (Action::ClosePane, "close-pane", "Close pane", &["Ctrl+Shift+W"]),
+    (Action::FocusPaneLeft, "focus-pane-left", "Focus the pane to the left", &["Ctrl+Shift+Left"]),
+    (Action::FocusPaneRight, "focus-pane-right", "Focus the pane to the right", &["Ctrl+Shift+Right"]),
+    (Action::FocusPaneUp, "focus-pane-up", "Focus the pane above", &["Ctrl+Shift+Up"]),
+    (Action::FocusPaneDown, "focus-pane-down", "Focus the pane below", &["Ctrl+Shift+Down"]),
+    (Action::PreviousPrompt, "previous-prompt", "Previous prompt", &["Ctrl+Up"]),
+    (Action::NextPrompt, "next-prompt", "Next prompt", &["Ctrl+Down"]),
+    (Action::MoveBlockUp, "move-block-up", "Move the block up", &["Alt+Up"]),
+    (Action::MoveBlockDown, "move-block-down", "Move the block down", &["Alt+Down"]),
+    (Action::NewTab, "new-tab", "New tab", &["Ctrl+T"]),
+    (Action::CloseTab, "close-tab", "Close tab", &["Ctrl+W"]),
+    (Action::SelectTab(1), "select-tab-1", "Go to tab 1", &["Ctrl+1"]),
+    (Action::SelectTab(2), "select-tab-2", "Go to tab 2", &["Ctrl+2"]),
+    (Action::SelectTab(3), "select-tab-3", "Go to tab 3", &["Ctrl+3"]),
+    (Action::SelectTab(4), "select-tab-4", "Go to tab 4", &["Ctrl+4"]),
+    (Action::SelectTab(5), "select-tab-5", "Go to tab 5", &["Ctrl+5"]),
+    (Action::SelectTab(6), "select-tab-6", "Go to tab 6", &["Ctrl+6"]),
+    (Action::SelectTab(7), "select-tab-7", "Go to tab 7", &["Ctrl+7"]),
+    (Action::SelectTab(8), "select-tab-8", "Go to tab 8", &["Ctrl+8"]),
+    (Action::SelectTab(9), "select-tab-9", "Go to tab 9", &["Ctrl+9"]),
+    // Shift+= gives + on most layouts, and the keypad has its own +
+    (Action::ZoomIn, "zoom-in", "Zoom in", &["Ctrl+=", "Ctrl++", "Ctrl+Shift++"]),
+    (Action::ZoomOut, "zoom-out", "Zoom out", &["Ctrl+-"]),
+    (Action::ZoomReset, "zoom-reset", "Reset zoom", &["Ctrl+0"]),
+];
+
+impl Action {
+    pub fn all() -> impl Iterator<Item = Action> {
+        ACTIONS.iter().map(|(action, ..)| *action)
+    }
+
+    pub fn from_id(id: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(_, action_id, ..)| *action_id == id).map(|(action, ..)| *action)
+    }
+
+    pub fn id(self) -> &'static str {
+        self.entry().1
+    }
+
+    pub fn label(self) -> &'static str {
+        self.entry().2
+    }
+
+    pub fn default_keys(self) -> Vec<KeySequence> {
+        self.entry().3.iter().map(|keys| keys.parse().expect("default keys parse")).collect()
+    }
+
+    fn entry(self) -> &'static (Action, &'static str, &'static str, &'static [&'static str]) {
+        ACTIONS.iter().find(|(action, ..)| *action == self).expect("every action is listed")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum KeyParseError {
+    #[error("no keys given")]
+    Empty,
+    #[error("\"{0}\" isn't a modifier")]
+    UnknownModifier(String),
+    #[error("\"{0}\" isn't a key")]
+    UnknownKey(String),
+}
+
+/// Keys with a name, as written in the config and as iced calls them
+const NAMED_KEYS: &[(&str, &str)] = &[
+    ("Tab", "Tab"),
+    ("Enter", "Enter"),
+    ("Escape", "Escape"),
+    ("Space", "Space"),
+    ("Backspace", "Backspace"),
+    ("Delete", "Delete"),
+    ("Insert", "Insert"),
+    ("Home", "Home"),
+    ("End", "End"),
+    ("PageUp", "PageUp"),
+    ("PageDown", "PageDown"),
+    ("Up", "ArrowUp"),
+    ("Down", "ArrowDown"),
+    ("Left", "ArrowLeft"),
+    ("Right", "ArrowRight"),
+];
+
+/// Keys that only modify others, and never finish a chord
+const MODIFIER_KEYS: &[&str] = &["Shift", "Control", "Alt", "AltGraph", "Super", "Meta", "Hyper", "CapsLock", "NumLock", "Fn"];
+
+/// One key with the modifiers held down for it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// Cmd on macOS, the Windows key elsewhere
+    pub logo: bool,
+    /// A lowercase character, or a name from `NAMED_KEYS`
+    pub key: String,
+}
+
+impl KeyChord {
+    /// The chord for a key press; `None` for modifier keys on their own
+    pub fn from_key(key: &iced::keyboard::Key, modifiers: iced::keyboard::Modifiers) -> Option<Self> {
+        use iced::keyboard::Key;
+        let key = match key.as_ref() {
+            Key::Character(c) => c.to_lowercase(),
+            Key::Named(named) => {
+                let name = format!("{:?}", named);
+                if MODIFIER_KEYS.contains(&name.as_str()) {
+                    return None;
+                }
+                NAMED_KEYS.iter().find(|(_, iced)| *iced == name).map_or(name, |(key, _)| key.to_string())
+            }
+            Key::Unidentified => return None,
+        };
+        Some(Self { ctrl: modifiers.control(), alt: modifiers.alt(), shift: modifiers.shift(), logo: modifiers.logo(), key })
+    }
+
+    /// Whether the key would type into a focused input, which then keeps it
+    pub fn types_text(&self) -> bool {
+        !self.ctrl && !self.alt && !self.logo && !is_function_key(&self.key)
+    }
+}
+
+fn is_function_key(key: &str) -> bool {
+    key.strip_prefix('F').is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [(self.ctrl, "Ctrl"), (self.alt, "Alt"), (self.shift, "Shift"), (self.logo, "Super")] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        if self.key.chars().count() == 1 {
+            write!(f, "{}", self.key.to_uppercase())
+        } else {
+            write!(f, "{}", self.key)
+        }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = KeyParseError;
+
+    fn from_str(chord: &str) -> Result<Self, Self::Err> {
+        // `Ctrl++` is Ctrl with the + key
+        let (modifiers, key) = match chord.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None if chord == "+" => ("", "+"),
+            None => chord.rsplit_once('+').unwrap_or(("", chord)),
+        };
+        let key = if key.chars().count() == 1 {
+            key.to_lowercase()
+        } else if let Some((name, _)) = NAMED_KEYS.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)) {
+            name.to_string()
+        } else if key.eq_ignore_ascii_case("esc") {
+            "Escape".to_string()
+        } else if is_function_key(&key.to_uppercase()) {
+            key.to_uppercase()
+        } else {
+            return Err(if key.is_empty() { KeyParseError::Empty } else { KeyParseError::UnknownKey(key.to_string()) });
+        };
+
+        let mut parsed = Self { ctrl: false, alt: false, shift: false, logo: false, key };
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => parsed.ctrl = true,
+                "alt" | "option" => parsed.alt = true,
+                "shift" => parsed.shift = true,
+                "super" | "cmd" | "win" | "meta" => parsed.logo = true,
+                _ => return Err(KeyParseError::UnknownModifier(modifier.to_string())),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Chords pressed one after another, written separated by spaces
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(pub Vec<KeyChord>);
+
+impl KeySequence {
+    /// Whether one sequence is the start of the other, so both can't be used
+    pub fn overlaps(&self, other: &KeySequence) -> bool {
+        self.0.starts_with(&other.0) || other.0.starts_with(&self.0)
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chords: Vec<String> = self.0.iter().map(KeyChord::to_string).collect();
+        write!(f, "{}", chords.join(" "))
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = KeyParseError;
+
+    fn from_str(keys: &str) -> Result<Self, Self::Err> {
+        let chords = keys.split_whitespace().map(str::parse).collect::<Result<Vec<KeyChord>, _>>()?;
+        if chords.is_empty() {
+            return Err(KeyParseError::Empty);
+        }
+        Ok(Self(chords))
+    }
+}
+
+impl Serialize for KeySequence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeySequence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// What a binding in the config file may hold
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BindingEntry {
+    One(String),
+    Many(Vec<String>),
+    Other(serde::de::IgnoredAny),
+}
+
+/// Read `keybindings.bindings`, leaving out unknown actions and keys that
+/// don't parse, so those fall back to their defaults instead of failing
+/// the whole config
+pub fn deserialize_bindings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, Vec<KeySequence>>, D::Error> {
+    let entries = BTreeMap::<String, BindingEntry>::deserialize(deserializer)?;
+    let mut bindings = BTreeMap::new();
+    for (id, entry) in entries {
+        if Action::from_id(&id).is_none() {
+            log::warn!("Ignoring key binding for unknown action \"{}\"", id);
+            continue;
+        }
+        let keys = match entry {
+            BindingEntry::One(keys) => vec![keys],
+            BindingEntry::Many(keys) => keys,
+            BindingEntry::Other(_) => {
+                log::warn!("Ignoring key binding for \"{}\": expected keys like \"Ctrl+T\"", id);
+                continue;
+            }
+        };
+        match keys.iter().map(|keys| keys.parse()).collect::<Result<Vec<KeySequence>, _>>() {
+            Ok(keys) => {
+                bindings.insert(id, keys);
+            }
+            Err(e) => log::warn!("Ignoring key binding for \"{}\": {}", id, e),
+        }
+    }
+    Ok(bindings)
+}
+
+/// The other action whose keys clash with `keys` bound to `action`
+pub fn conflict(bindings: &KeyBindings, action: Action, keys: &KeySequence) -> Option<Action> {
+    Action::all()
+        .filter(|other| *other != action)
+        .find(|other| bindings.keys(*other).iter().any(|bound| bound.overlaps(keys)))
+}
+
+/// Actions whose keys clash with another action's
+pub fn conflicting(bindings: &KeyBindings) -> HashSet<Action> {
+    Action::all()
+        .filter(|action| bindings.keys(*action).iter().any(|keys| conflict(bindings, *action, keys).is_some()))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Press {
+    Run(Action),
+    /// The keys so far start a sequence
+    Pending,
+    /// The key ended a sequence that isn't bound; it's used up
+    Abandoned(KeySequence),
+    /// Not bound; the key goes on to whatever else takes it
+    Unbound,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: Vec<(KeySequence, Action)>,
+    pending: Vec<KeyChord>,
+}
+
+impl Keymap {
+    pub fn new(bindings: &KeyBindings) -> Self {
+        let bindings = Action::all()
+            .flat_map(|action| bindings.keys(action).into_iter().map(move |keys| (keys, action)))
+            .collect();
+        Self { bindings, pending: Vec::new() }
+    }
+
+    /// Follow a key press. `typing` is whether a focused input took it, in
+    /// which case keys that type text are left to the input.
+    pub fn press(&mut self, chord: KeyChord, typing: bool) -> Press {
+        if self.pending.is_empty() && typing && chord.types_text() {
+            return Press::Unbound;
+        }
+        self.pending.push(chord);
+        let mut started = false;
+        for (keys, action) in &self.bindings {
+            if keys.0 == self.pending {
+                self.pending.clear();
+                return Press::Run(*action);
+            }
+            started |= keys.0.starts_with(&self.pending);
+        }
+        if started {
+            return Press::Pending;
+        }
+        let pressed = std::mem::take(&mut self.pending);
+        if pressed.len() > 1 {
+            Press::Abandoned(KeySequence(pressed))
+        } else {
+            Press::Unbound
+        }
+    }
+
+    /// Keys pressed so far of an unfinished sequence
+    pub fn pending(&self) -> KeySequence {
+        KeySequence(self.pending.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &str) -> KeySequence {
+        keys.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(keys("ctrl+shift+p").to_string(), "Ctrl+Shift+P");
+        assert_eq!(keys("Ctrl+K  Ctrl+T").to_string(), "Ctrl+K Ctrl+T");
+        assert_eq!(keys("Ctrl++").0[0].key, "+");
+        assert_eq!(keys("cmd+esc").to_string(), "Super+Escape");
+        assert_eq!(keys("f11").to_string(), "F11");
+        assert_eq!("Hyper+A".parse::<KeySequence>(), Err(KeyParseError::UnknownModifier("Hyper".to_string())));
+        assert_eq!("Ctrl+Nope".parse::<KeySequence>(), Err(KeyParseError::UnknownKey("Nope".to_string())));
+        assert_eq!("  ".parse::<KeySequence>(), Err(KeyParseError::Empty));
+        for action in Action::all() {
+            assert_eq!(Action::from_id(action.id()), Some(action));
+            action.default_keys();
+        }
+    }
+
+    #[test]
+    fn test_press_follows_sequences() {
+        let mut bindings = KeyBindings::default();
+        bindings.set(Action::ToggleAgent, vec![keys("Ctrl+K Ctrl+T")]);
+        let mut keymap = Keymap::new(&bindings);
+
+        assert_eq!(keymap.press(keys("Ctrl+T").0[0].clone(), true), Press::Run(Action::NewTab));
+        assert_eq!(keymap.press(keys("Ctrl+K").0[0].clone(), true), Press::Pending);
+        assert_eq!(keymap.pending().to_string(), "Ctrl+K");
+        assert_eq!(keymap.press(keys("Ctrl+T").0[0].clone(), true), Press::Run(Action::ToggleAgent));
+        assert_eq!(keymap.press(keys("Ctrl+K").0[0].clone(), false), Press::Pending);
+        assert_eq!(keymap.press(keys("X").0[0].clone(), false), Press::Abandoned(keys("Ctrl+K X")));
+        // Typing goes to the input; the same key outside it is free
+        assert_eq!(keymap.press(keys("Tab").0[0].clone(), true), Press::Unbound);
+        assert_eq!(keymap.press(keys("Tab").0[0].clone(), false), Press::Run(Action::Complete));
+    }
+
+    #[test]
+    fn test_conflicts_and_lenient_config() {
+        let mut bindings = KeyBindings::default();
+        assert!(conflicting(&bindings).is_empty());
+        assert_eq!(conflict(&bindings, Action::Palette, &keys("Ctrl+T")), Some(Action::NewTab));
+        // A sequence starting with another action's keys hides behind it
+        assert_eq!(conflict(&bindings, Action::Palette, &keys("Ctrl+W Ctrl+P")), Some(Action::CloseTab));
+        bindings.set(Action::Palette, vec![keys("Ctrl+F")]);
+        assert_eq!(conflicting(&bindings), HashSet::from([Action::Palette, Action::Find]));
+
+        let parsed: KeyBindings = toml::from_str(
+            r#"
+            [bindings]
+            palette = "Ctrl+K Ctrl+P"
+            new-tab = ["Ctrl+N", "Alt+T"]
+            close-tab = "Ctrl+Nope"
+            no-such-action = "Ctrl+X"
+            [bindings.copy]
+            key = "c"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(parsed.keys(Action::Palette), vec![keys("Ctrl+K Ctrl+P")]);
+        assert_eq!(parsed.keys(Action::NewTab), vec![keys("Ctrl+N"), keys("Alt+T")]);
+        assert_eq!(parsed.keys(Action::CloseTab), Action::CloseTab.default_keys());
+    }
+}
@@ -1,5 +1,202 @@
-// languages module stub
+//! Languages whose snippets NeoTerm can run outside the shell, and the
+//! interpreters that run them.
 
-pub fn init() {
-    println!("languages loaded");
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Language {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Code fence tags that name this language
+    pub aliases: &'static [&'static str],
+    pub extension: &'static str,
+    /// How to run a script file, which is appended as the last argument
+    pub run_command: &'static [&'static str],
+    /// Other interpreters worth suggesting when `run_command` isn't installed
+    pub alternatives: &'static [&'static [&'static str]],
+    pub install_hint: &'static str,
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        id: "python",
+        name: "Python",
+        aliases: &["python", "py", "python3"],
+        extension: "py",
+        run_command: &["python3"],
+        alternatives: &[&["python"], &["pypy3"]],
+        install_hint: "install Python 3 from https://www.python.org or your package manager",
+    },
+    Language {
+        id: "javascript",
+        name: "JavaScript",
+        aliases: &["javascript", "js", "node", "mjs"],
+        extension: "js",
+        run_command: &["node"],
+        alternatives: &[&["deno", "run"], &["bun", "run"]],
+        install_hint: "install Node.js from https://nodejs.org or your package manager",
+    },
+    Language {
+        id: "rust",
+        name: "Rust",
+        aliases: &["rust", "rs"],
+        extension: "rs",
+        run_command: &["cargo", "+nightly", "-Zscript"],
+        alternatives: &[&["rust-script"]],
+        install_hint: "install a nightly toolchain with `rustup toolchain install nightly`",
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum LanguageError {
+    #[error("No runner for '{0}' snippets")]
+    Unknown(String),
+    #[error("{program} is not installed or not on PATH. {suggestion}")]
+    MissingInterpreter { program: String, suggestion: String },
+}
+
+/// Finds languages by fence tag and resolves how to run them on this machine
+#[derive(Debug, Clone)]
+pub struct LanguageManager {
+    /// Search path for interpreters; the process PATH unless set
+    path: Option<OsString>,
+    /// Per-language command lines chosen in preferences, by language id
+    overrides: HashMap<String, String>,
+}
+
+impl LanguageManager {
+    pub fn new(overrides: HashMap<String, String>) -> Self {
+        Self { path: std::env::var_os("PATH"), overrides }
+    }
+
+    pub fn with_path(path: impl Into<OsString>, overrides: HashMap<String, String>) -> Self {
+        Self { path: Some(path.into()), overrides }
+    }
+
+    pub fn languages(&self) -> &'static [Language] {
+        LANGUAGES
+    }
+
+    /// The language a code fence tag names, e.g. `py` or `JavaScript`
+    pub fn by_tag(&self, tag: &str) -> Option<&'static Language> {
+        let tag = tag.trim().to_ascii_lowercase();
+        LANGUAGES.iter().find(|language| language.aliases.contains(&tag.as_str()))
+    }
+
+    pub fn by_id(&self, id: &str) -> Option<&'static Language> {
+        LANGUAGES.iter().find(|language| language.id == id)
+    }
+
+    /// The language of files with this extension, e.g. `rs`
+    pub fn by_extension(&self, extension: &str) -> Option<&'static Language> {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        LANGUAGES.iter().find(|language| language.extension == extension)
+    }
+
+    /// Full command line that runs `script`. When the interpreter is
+    /// missing, the error names the alternatives that are installed.
+    pub fn run_command(&self, language: &Language, script: &Path) -> Result<Vec<String>, LanguageError> {
+        let mut argv: Vec<String> = match self.overrides.get(language.id) {
+            Some(line) => line.split_whitespace().map(str::to_string).collect(),
+            None => language.run_command.iter().map(|arg| arg.to_string()).collect(),
+        };
+        let Some(program) = argv.first().cloned() else {
+            return Err(LanguageError::Unknown(language.id.to_string()));
+        };
+
+        if self.find_program(&program).is_none() {
+            let detected: Vec<String> = language
+                .alternatives
+                .iter()
+                .filter(|alternative| self.find_program(alternative[0]).is_some())
+                .map(|alternative| alternative.join(" "))
+                .collect();
+            let suggestion = if detected.is_empty() {
+                format!("To run {} snippets, {}.", language.name, language.install_hint)
+            } else {
+                format!(
+                    "Found {}; set scratch.interpreters.{} in preferences to use it.",
+                    detected.join(", "),
+                    language.id
+                )
+            };
+            return Err(LanguageError::MissingInterpreter { program, suggestion });
+        }
+
+        argv.push(script.to_string_lossy().to_string());
+        Ok(argv)
+    }
+
+    fn find_program(&self, program: &str) -> Option<PathBuf> {
+        if program.contains(std::path::MAIN_SEPARATOR) {
+            return Path::new(program).is_file().then(|| PathBuf::from(program));
+        }
+        let path = self.path.as_ref()?;
+        std::env::split_paths(path)
+            .flat_map(|dir| executable_names(program).into_iter().map(move |name| dir.join(name)))
+            .find(|candidate| candidate.is_file())
+    }
+}
+
+fn executable_names(program: &str) -> Vec<String> {
+    if cfg!(windows) {
+        vec![format!("{}.exe", program), format!("{}.cmd", program), program.to_string()]
+    } else {
+        vec![program.to_string()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn install(dir: &Path, program: &str) {
+        std::fs::write(dir.join(executable_names(program).remove(0)), "").unwrap();
+    }
+
+    #[test]
+    fn test_tags_resolve_to_languages() {
+        let manager = LanguageManager::with_path("", HashMap::new());
+        assert_eq!(manager.by_tag("py").map(|l| l.id), Some("python"));
+        assert_eq!(manager.by_tag("JavaScript").map(|l| l.id), Some("javascript"));
+        assert_eq!(manager.by_tag("rs").map(|l| l.id), Some("rust"));
+        assert!(manager.by_tag("bash").is_none());
+        assert_eq!(manager.by_extension("PY").map(|l| l.id), Some("python"));
+        assert!(manager.by_extension("toml").is_none());
+    }
+
+    #[test]
+    fn test_run_command_appends_the_script() {
+        let temp_dir = TempDir::new().unwrap();
+        install(temp_dir.path(), "node");
+        let manager = LanguageManager::with_path(temp_dir.path(), HashMap::new());
+        let argv = manager.run_command(manager.by_id("javascript").unwrap(), Path::new("/tmp/a.js")).unwrap();
+        assert_eq!(argv, vec!["node", "/tmp/a.js"]);
+    }
+
+    #[test]
+    fn test_missing_interpreter_names_installed_alternatives() {
+        let temp_dir = TempDir::new().unwrap();
+        install(temp_dir.path(), "python");
+        let manager = LanguageManager::with_path(temp_dir.path(), HashMap::new());
+        let python = manager.by_id("python").unwrap();
+
+        let err = manager.run_command(python, Path::new("a.py")).unwrap_err().to_string();
+        assert!(err.contains("python3 is not installed"), "{}", err);
+        assert!(err.contains("Found python;"), "{}", err);
+        assert!(err.contains("scratch.interpreters.python"), "{}", err);
+
+        let overridden = LanguageManager::with_path(
+            temp_dir.path(),
+            HashMap::from([("python".to_string(), "python -u".to_string())]),
+        );
+        assert_eq!(overridden.run_command(python, Path::new("a.py")).unwrap(), vec!["python", "-u", "a.py"]);
+
+        let bare = LanguageManager::with_path("", HashMap::new());
+        let err = bare.run_command(bare.by_id("rust").unwrap(), Path::new("a.rs")).unwrap_err().to_string();
+        assert!(err.contains("rustup toolchain install nightly"), "{}", err);
+    }
 }
@@ -0,0 +1,533 @@
+//! Width-driven layout rules. Below `COMPACT_COLUMNS` the toolbar folds into
+//! a menu, block headers take two lines and optional status segments are
+//! hidden; splits stack vertically once a pane would get too narrow.
+
+use std::cell::RefCell;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Widget;
+use serde::{Deserialize, Serialize};
+use crate::ansi;
+use crate::block::{Block, MarkdownSection};
+use crate::config::StatusLinePreferences;
+use crate::font::OutputFont;
+use crate::hints::{self, InteractableKind, InteractableRegistry};
+use crate::palette::CommandPalette;
+use crate::renderer::ScrollState;
+use crate::status_line::{StatusBar, StatusContext};
+
+/// Narrower than this and the compact rules apply
+pub const COMPACT_COLUMNS: usize = 80;
+/// Side-by-side panes never get fewer columns than this
+pub const MIN_PANE_COLUMNS: usize = 40;
+/// Approximate monospace advance at the 14px body font, with the output
+/// font at its default size; cells grow and shrink with the output font
+pub const CELL_WIDTH: f32 = 8.4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolbarLayout {
+    Full,
+    /// One menu button that opens the toolbar entries
+    Menu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderLayout {
+    /// Command and details on one row
+    SingleLine,
+    /// Command on the first row, details and actions on the second
+    TwoLine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponsiveLayout {
+    pub columns: usize,
+}
+
+impl ResponsiveLayout {
+    pub fn new(columns: usize) -> Self {
+        Self { columns }
+    }
+
+    /// Layout for a window `width` logical pixels wide
+    pub fn for_width(width: f32, font: &OutputFont) -> Self {
+        Self::new((width / (CELL_WIDTH * font.scale())).max(0.0) as usize)
+    }
+
+    pub fn is_compact(&self) -> bool {
+        self.columns < COMPACT_COLUMNS
+    }
+
+    pub fn toolbar(&self) -> ToolbarLayout {
+        if self.is_compact() { ToolbarLayout::Menu } else { ToolbarLayout::Full }
+    }
+
+    pub fn block_header(&self) -> HeaderLayout {
+        if self.is_compact() { HeaderLayout::TwoLine } else { HeaderLayout::SingleLine }
+    }
+
+    /// How to arrange `panes` side by side, keeping one separator column between them
+    pub fn split(&self, panes: usize) -> SplitDirection {
+        if panes <= 1 {
+            return SplitDirection::Horizontal;
+        }
+        let per_pane = self.columns.saturating_sub(panes - 1) / panes;
+        if per_pane >= MIN_PANE_COLUMNS { SplitDirection::Horizontal } else { SplitDirection::Vertical }
+    }
+
+    /// Status line preferences with the optional segments dropped when compact
+    pub fn status_line(&self, prefs: &StatusLinePreferences) -> StatusLinePreferences {
+        let mut prefs = prefs.clone();
+        if self.is_compact() {
+            prefs.show_env_profile = false;
+            prefs.show_activity = false;
+        }
+        prefs
+    }
+
+    /// The toolbar as one row of text
+    pub fn toolbar_line(&self, labels: &[&str]) -> String {
+        match self.toolbar() {
+            ToolbarLayout::Full => labels.iter().map(|label| format!("[{}]", label)).collect::<Vec<_>>().join(" "),
+            ToolbarLayout::Menu => "[Menu]".to_string(),
+        }
+    }
+}
+
+/// Cut to `max` characters, marking the cut with `…`
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let mut cut: String = text.chars().take(max - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// What a hint label on the text screen activates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScreenTarget {
+    Toolbar(usize),
+    /// The folded toolbar menu
+    Menu,
+    Link(String),
+    /// A template row in the palette
+    PaletteRow(String),
+}
+
+/// Text rendering of the main screen: toolbar on top, blocks below it, an
+/// open palette above the status line on the last row
+pub struct Screen<'a> {
+    pub toolbar: &'a [&'a str],
+    pub blocks: &'a [Block],
+    pub palette: Option<&'a CommandPalette>,
+    pub status: &'a StatusContext,
+    pub status_prefs: &'a StatusLinePreferences,
+    pub frame: usize,
+    /// In hint mode, filled with what's on screen and labelled in place
+    pub hints: Option<&'a RefCell<InteractableRegistry<ScreenTarget>>>,
+    /// Where the blocks are scrolled to, in rows; without it the top is shown
+    pub scroll: Option<&'a RefCell<ScrollState>>,
+}
+
+/// Register an element and draw its label over its first cells
+fn hint(buf: &mut Buffer, registry: &RefCell<InteractableRegistry<ScreenTarget>>, x: u16, y: u16, kind: InteractableKind, description: &str, target: ScreenTarget) {
+    if let Some(label) = registry.borrow_mut().register(kind, description, target) {
+        buf.set_string(x, y, label, Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD));
+    }
+}
+
+impl Widget for Screen<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height == 0 {
+            return;
+        }
+        let layout = ResponsiveLayout::new(area.width as usize);
+        let width = area.width as usize;
+        let bottom = area.y + area.height;
+
+        buf.set_stringn(area.x, area.y, layout.toolbar_line(self.toolbar), width, Style::default());
+        if let Some(registry) = self.hints {
+            registry.borrow_mut().clear();
+            match layout.toolbar() {
+                ToolbarLayout::Full => {
+                    let mut x = area.x;
+                    for (index, label) in self.toolbar.iter().enumerate() {
+                        if usize::from(x - area.x) + 3 > width {
+                            break;
+                        }
+                        hint(buf, registry, x + 1, area.y, InteractableKind::ToolbarButton, label, ScreenTarget::Toolbar(index));
+                        x += label.chars().count() as u16 + 3;
+                    }
+                }
+                ToolbarLayout::Menu => hint(buf, registry, area.x + 1, area.y, InteractableKind::ToolbarButton, "Menu", ScreenTarget::Menu),
+            }
+        }
+
+        let status_prefs = layout.status_line(self.status_prefs);
+        let status_rows = u16::from(status_prefs.visible).min(area.height - 1);
+        if status_rows > 0 {
+            let row = Rect::new(area.x, bottom - 1, area.width, 1);
+            StatusBar { context: self.status, prefs: &status_prefs, frame: self.frame }.render(row, buf);
+        }
+
+        let palette_lines = self.palette.map(|palette| palette.lines(width)).unwrap_or_default();
+        let palette_top = (bottom - status_rows).saturating_sub(palette_lines.len() as u16).max(area.y + 1);
+        for (y, line) in (palette_top..bottom - status_rows).zip(&palette_lines) {
+            buf.set_stringn(area.x, y, line, width, Style::default());
+        }
+
+        let mut lines: Vec<Line> = Vec::new();
+        for block in self.blocks {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            match block.header(false) {
+                Some(header) => lines.extend(header.lines(&layout).into_iter().map(Line::raw)),
+                None => lines.push(Line::raw(truncate(&block.title(), width))),
+            }
+            // Output keeps its colors; other escape sequences are dropped
+            let output = block.output_text();
+            if !output.is_empty() {
+                lines.extend(ansi::lines(output).into_iter().map(styled_line));
+            }
+            for section in block.markdown_sections() {
+                match section {
+                    MarkdownSection::Text(text) => lines.extend(text.into_iter().map(styled_line)),
+                    MarkdownSection::Code { index, language, lines: code } => {
+                        let label = format!("─ {} #{} ", if language.is_empty() { "code" } else { &language }, index + 1);
+                        lines.push(Line::styled(label, Style::default().add_modifier(Modifier::DIM)));
+                        lines.extend(code.into_iter().map(|spans| {
+                            let mut line = styled_line(spans);
+                            line.spans.insert(0, Span::raw("  "));
+                            line
+                        }));
+                    }
+                }
+            }
+        }
+        let rows = palette_top.saturating_sub(area.y + 1);
+        let first = match self.scroll {
+            Some(scroll) => {
+                let mut scroll = scroll.borrow_mut();
+                let offset = scroll.offset();
+                scroll.on_viewport(offset, f32::from(rows), lines.len() as f32);
+                scroll.offset() as usize
+            }
+            None => 0,
+        };
+        for (y, line) in (area.y + 1..palette_top).zip(lines.iter().skip(first)) {
+            buf.set_line(area.x, y, line, area.width);
+            if let Some(registry) = self.hints {
+                let plain: String = line.spans.iter().map(|span| span.content.as_ref()).collect();
+                for (column, url) in hints::find_links(&plain) {
+                    if column < width {
+                        hint(buf, registry, area.x + column as u16, y, InteractableKind::Link, &url, ScreenTarget::Link(url.clone()));
+                    }
+                }
+            }
+        }
+
+        // Anchored above the tail: the pill sits on the last row of blocks
+        if let Some(indicator) = self.scroll.and_then(|scroll| scroll.borrow().indicator()).filter(|_| rows > 0) {
+            let label = format!(" {} ", indicator);
+            let x = area.x + (width.saturating_sub(label.chars().count()) / 2) as u16;
+            buf.set_stringn(area.x, palette_top - 1, " ".repeat(width), width, Style::default());
+            buf.set_stringn(x, palette_top - 1, label, width, Style::default().add_modifier(Modifier::REVERSED));
+        }
+
+        // Template rows are indented under their section title
+        if let Some(registry) = self.hints {
+            for (y, line) in (palette_top..bottom - status_rows).zip(&palette_lines) {
+                if let Some(name) = line.strip_prefix("  ").and_then(|row| row.split_whitespace().next()) {
+                    hint(buf, registry, area.x, y, InteractableKind::PaletteRow, name, ScreenTarget::PaletteRow(name.to_string()));
+                }
+            }
+        }
+    }
+}
+
+fn styled_line(spans: Vec<ansi::Span>) -> Line<'static> {
+    Line::from(spans.into_iter().map(|span| Span::styled(span.text, span.style.to_ratatui())).collect::<Vec<_>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockContent;
+    use crate::palette::PaletteMessage;
+    use crate::resources::ResourceManager;
+    use crate::status_line::Mode;
+    use crate::workflows::Shell;
+
+    fn command(input: &str, output: &str, exit_code: i32) -> Block {
+        let mut block = Block::new_command(input.to_string());
+        if let BlockContent::Command { working_directory, .. } = &mut block.content {
+            *working_directory = "/srv/neoterm".to_string();
+        }
+        block.set_output(output.to_string(), exit_code);
+        block
+    }
+
+    fn screen(columns: u16) -> Vec<String> {
+        let blocks = [
+            command("cargo test --workspace --all-features", "test result: FAILED. 41 passed; 1 failed\n", 101),
+            command("git status --short --branch", "## main...origin/main\n M src/layout.rs\n", 0),
+        ];
+        let mut palette = CommandPalette::new(ResourceManager::bundled().unwrap(), Shell::Bash);
+        palette.update(PaletteMessage::QueryChanged("docker stop".to_string()));
+        let status = StatusContext {
+            mode: Mode::Normal,
+            cwd: "/srv/neoterm".to_string(),
+            git_branch: Some("main".to_string()),
+            env_profile: Some("staging".to_string()),
+            idle: true,
+            ..Default::default()
+        };
+
+        let area = Rect::new(0, 0, columns, 14);
+        let mut buffer = Buffer::empty(area);
+        Screen {
+            toolbar: &["Agent OFF", "Settings", "Find/Replace", "Templates"],
+            blocks: &blocks,
+            palette: Some(&palette),
+            status: &status,
+            status_prefs: &StatusLinePreferences::default(),
+            frame: 0,
+            hints: None,
+            scroll: None,
+        }
+        .render(area, &mut buffer);
+
+        (0..area.height)
+            .map(|y| {
+                let row: String = (0..area.width).map(|x| buffer.get(x, y).symbol().to_string()).collect();
+                row.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_screen_at_120_columns() {
+        assert_eq!(screen(120), vec![
+            "[Agent OFF] [Settings] [Find/Replace] [Templates]",
+            "$ cargo test --workspace --all-features                                                          /srv/neoterm · exit 101",
+            "test result: FAILED. 41 passed; 1 failed",
+            "",
+            "$ git status --short --branch                                                                      /srv/neoterm · exit 0",
+            "## main...origin/main",
+            " M src/layout.rs",
+            "",
+            "",
+            "> docker stop",
+            "Templates",
+            "  docker-cleanup-all  Remove stopped containers, unused networks, images and build cache",
+            "  docker-stop-all  Stop every running container",
+            "NORMAL │ /srv/neoterm (main) │ env:staging                                                                          idle",
+        ]);
+    }
+
+    #[test]
+    fn test_output_colors_reach_the_buffer() {
+        let blocks = [command("cargo build", "\x1b[1m\x1b[32m   Compiling\x1b[0m neoterm\x1b[K\n", 0)];
+        let area = Rect::new(0, 0, 100, 4);
+        let mut buffer = Buffer::empty(area);
+        Screen {
+            toolbar: &[],
+            blocks: &blocks,
+            palette: None,
+            status: &StatusContext::default(),
+            status_prefs: &StatusLinePreferences { visible: false, ..Default::default() },
+            frame: 0,
+            hints: None,
+            scroll: None,
+        }
+        .render(area, &mut buffer);
+
+        let row: String = (0..area.width).map(|x| buffer.get(x, 2).symbol().to_string()).collect();
+        assert_eq!(row.trim_end(), "   Compiling neoterm");
+        let compiling = buffer.get(3, 2);
+        assert_eq!(compiling.fg, ratatui::style::Color::Indexed(2));
+        assert!(compiling.modifier.contains(Modifier::BOLD));
+        assert_eq!(buffer.get(13, 2).fg, ratatui::style::Color::Reset);
+    }
+
+    #[test]
+    fn test_screen_follows_the_tail_until_scrolled_up() {
+        let output: Vec<String> = (1..=10).map(|n| n.to_string()).collect();
+        let blocks = [command("seq 10", &output.join("\n"), 0)];
+        let scroll = RefCell::new(ScrollState::new().with_follow_threshold(0.0));
+        let render = || {
+            let area = Rect::new(0, 0, 100, 5);
+            let mut buffer = Buffer::empty(area);
+            Screen {
+                toolbar: &[],
+                blocks: &blocks,
+                palette: None,
+                status: &StatusContext::default(),
+                status_prefs: &StatusLinePreferences { visible: false, ..Default::default() },
+                frame: 0,
+                hints: None,
+                scroll: Some(&scroll),
+            }
+            .render(area, &mut buffer);
+            (1..area.height)
+                .map(|y| (0..area.width).map(|x| buffer.get(x, y).symbol().to_string()).collect::<String>().trim().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(render(), ["7", "8", "9", "10"]);
+
+        scroll.borrow_mut().scroll_by(-3.0);
+        scroll.borrow_mut().on_output(2);
+        assert_eq!(render(), ["4", "5", "6", "2 new lines ↓"]);
+        scroll.borrow_mut().jump_to_bottom();
+        assert_eq!(render(), ["7", "8", "9", "10"]);
+    }
+
+    #[test]
+    fn test_assistant_replies_render_as_markdown() {
+        let mut reply = Block::new_agent_message(String::new());
+        for chunk in ["## Fix\nRun **this**", ":\n```sh\nls -la\n", "```\n"] {
+            reply.append_agent_text(chunk);
+        }
+        let blocks = [reply];
+        let area = Rect::new(0, 0, 60, 8);
+        let mut buffer = Buffer::empty(area);
+        Screen {
+            toolbar: &[],
+            blocks: &blocks,
+            palette: None,
+            status: &StatusContext::default(),
+            status_prefs: &StatusLinePreferences { visible: false, ..Default::default() },
+            frame: 0,
+            hints: None,
+            scroll: None,
+        }
+        .render(area, &mut buffer);
+
+        let row = |y: u16| (0..area.width).map(|x| buffer.get(x, y).symbol().to_string()).collect::<String>();
+        assert_eq!(row(2).trim_end(), "Fix");
+        assert!(buffer.get(0, 2).modifier.contains(Modifier::BOLD));
+        assert_eq!(row(3).trim_end(), "Run this:");
+        assert!(buffer.get(4, 3).modifier.contains(Modifier::BOLD));
+        assert_eq!(row(4).trim_end(), "─ sh #1");
+        assert_eq!(row(5).trim_end(), "  ls -la");
+    }
+
+    #[test]
+    fn test_screen_at_80_columns() {
+        assert_eq!(screen(80), vec![
+            "[Agent OFF] [Settings] [Find/Replace] [Templates]",
+            "$ cargo test --workspace --all-features                  /srv/neoterm · exit 101",
+            "test result: FAILED. 41 passed; 1 failed",
+            "",
+            "$ git status --short --branch                              /srv/neoterm · exit 0",
+            "## main...origin/main",
+            " M src/layout.rs",
+            "",
+            "",
+            "> docker stop",
+            "Templates",
+            "  docker-cleanup-all  Remove stopped containers, unused networks, images and bu…",
+            "  docker-stop-all  Stop every running container",
+            "NORMAL │ /srv/neoterm (main) │ env:staging                                  idle",
+        ]);
+    }
+
+    #[test]
+    fn test_screen_at_60_columns() {
+        assert_eq!(screen(60), vec![
+            "[Menu]",
+            "$ cargo test --workspace --all-features",
+            "  /srv/neoterm · exit 101",
+            "test result: FAILED. 41 passed; 1 failed",
+            "",
+            "$ git status --short --branch",
+            "  /srv/neoterm · exit 0",
+            "## main...origin/main",
+            " M src/layout.rs",
+            "> docker stop",
+            "Templates",
+            "  docker-cleanup-all  Remove stopped containers, unused net…",
+            "  docker-stop-all  Stop every running container",
+            "NORMAL │ /srv/neoterm (main)",
+        ]);
+    }
+
+    #[test]
+    fn test_hint_labels_dispatch_on_a_busy_screen() {
+        use crate::hints::{HintInput, HintMode};
+
+        let output: String = (0..30).map(|i| format!("see https://ci.example.com/runs/{}\n", i)).collect();
+        let blocks = [command("./ci-status --all", &output, 0)];
+        let mut palette = CommandPalette::new(ResourceManager::bundled().unwrap(), Shell::Bash);
+        palette.update(PaletteMessage::QueryChanged("docker".to_string()));
+        let status = StatusContext::default();
+        let registry = RefCell::new(InteractableRegistry::new());
+
+        let area = Rect::new(0, 0, 100, 48);
+        let mut buffer = Buffer::empty(area);
+        Screen {
+            toolbar: &["Agent OFF", "Settings", "Find/Replace", "Templates"],
+            blocks: &blocks,
+            palette: Some(&palette),
+            status: &status,
+            status_prefs: &StatusLinePreferences::default(),
+            frame: 0,
+            hints: Some(&registry),
+            scroll: None,
+        }
+        .render(area, &mut buffer);
+
+        let registry = registry.into_inner();
+        assert!(registry.len() > 30, "only {} interactables", registry.len());
+        let labels: std::collections::HashSet<&str> = registry.entries().iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(labels.len(), registry.len());
+
+        let cell = |x: u16, y: u16| buffer.get(x, y).symbol().to_string();
+        for entry in registry.entries() {
+            let target_row = (0..area.height).find(|&y| {
+                (0..area.width - 1).any(|x| cell(x, y) + &cell(x + 1, y) == entry.label)
+            });
+            assert!(target_row.is_some(), "label {} not drawn", entry.label);
+        }
+
+        let link = registry.entries().iter().find(|e| e.action == ScreenTarget::Link("https://ci.example.com/runs/17".to_string())).unwrap();
+        let mut mode = HintMode::new();
+        let mut chars = link.label.chars();
+        assert_eq!(mode.key(chars.next().unwrap(), &registry), HintInput::Pending);
+        assert_eq!(mode.key(chars.next().unwrap(), &registry), HintInput::Activate(link.action.clone()));
+
+        let settings = &registry.entries()[1];
+        assert_eq!(settings.action, ScreenTarget::Toolbar(1));
+        assert!(registry.entries().iter().any(|e| e.action == ScreenTarget::PaletteRow("docker-stop-all".to_string())));
+    }
+
+    #[test]
+    fn test_compact_rules_switch_at_threshold() {
+        assert_eq!(ResponsiveLayout::new(80).toolbar(), ToolbarLayout::Full);
+        assert_eq!(ResponsiveLayout::new(79).toolbar(), ToolbarLayout::Menu);
+        assert_eq!(ResponsiveLayout::new(79).block_header(), HeaderLayout::TwoLine);
+        assert_eq!(ResponsiveLayout::for_width(1008.0, &OutputFont::default()).columns, 120);
+    }
+
+    #[test]
+    fn test_splits_stack_when_panes_get_narrow() {
+        assert_eq!(ResponsiveLayout::new(81).split(2), SplitDirection::Horizontal);
+        assert_eq!(ResponsiveLayout::new(80).split(2), SplitDirection::Vertical);
+        assert_eq!(ResponsiveLayout::new(120).split(3), SplitDirection::Vertical);
+        assert_eq!(ResponsiveLayout::new(20).split(1), SplitDirection::Horizontal);
+    }
+}
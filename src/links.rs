@@ -0,0 +1,220 @@
+//! Links in command output: URLs, and paths to files that exist such as
+//! `src/ai/mod.rs:120:9`, with an optional line and column.
+//!
+//! `LinkIndex` finds them as output streams in. Complete lines keep the
+//! links found in them, so each chunk only scans the lines it finishes and
+//! the unfinished last line.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::ansi;
+
+/// Links kept per block; output past this many isn't scanned
+pub const MAX_LINKS: usize = 1_000;
+
+pub fn url_pattern() -> &'static Regex {
+    static URL: OnceLock<Regex> = OnceLock::new();
+    URL.get_or_init(|| Regex::new(r#"https?://[^\s<>"'`]+[^\s<>"'`.,;:!?)\]]"#).unwrap())
+}
+
+fn path_pattern() -> &'static Regex {
+    static PATH: OnceLock<Regex> = OnceLock::new();
+    PATH.get_or_init(|| Regex::new(r"[\w@+./~-]*[\w/](?::(\d+))?(?::(\d+))?").unwrap())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    Url(String),
+    /// An existing file or directory, resolved against the block's directory
+    Path { path: PathBuf, line: Option<usize>, column: Option<usize> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub line: usize,
+    /// Bytes of the line, with escape sequences removed
+    pub range: Range<usize>,
+    pub target: LinkTarget,
+}
+
+/// Links in one line of output, without escape sequences
+pub fn find(line: &str, cwd: &Path) -> Vec<(Range<usize>, LinkTarget)> {
+    let mut links: Vec<(Range<usize>, LinkTarget)> = url_pattern()
+        .find_iter(line)
+        .map(|found| (found.range(), LinkTarget::Url(found.as_str().to_string())))
+        .collect();
+    let urls: Vec<Range<usize>> = links.iter().map(|(range, _)| range.clone()).collect();
+
+    for captures in path_pattern().captures_iter(line) {
+        let found = captures.get(0).unwrap();
+        if urls.iter().any(|url| url.start < found.end() && found.start() < url.end) {
+            continue;
+        }
+        let number = |index| captures.get(index).and_then(|number| number.as_str().parse().ok());
+        // The path ends at the colon before the line number
+        let path_end = captures.get(1).map_or(found.end(), |number| number.start() - 1);
+        let candidate = &line[found.start()..path_end];
+        if !looks_like_path(candidate) {
+            continue;
+        }
+        if let Some(path) = resolve(candidate, cwd) {
+            links.push((found.range(), LinkTarget::Path { path, line: number(1), column: number(2) }));
+        }
+    }
+    links.sort_by_key(|(range, _)| range.start);
+    links
+}
+
+/// A slash or an extension, so plain words and version numbers aren't taken
+/// for paths
+fn looks_like_path(candidate: &str) -> bool {
+    if candidate.contains('/') {
+        return !candidate.trim_matches(['/', '.', '~']).is_empty();
+    }
+    Path::new(candidate)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.chars().any(|c| c.is_ascii_alphabetic()))
+}
+
+fn resolve(candidate: &str, cwd: &Path) -> Option<PathBuf> {
+    let path = match candidate.strip_prefix("~/") {
+        Some(rest) => directories::BaseDirs::new()?.home_dir().join(rest),
+        None => cwd.join(candidate),
+    };
+    path.exists().then_some(path)
+}
+
+/// Links found so far in a block's output
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkIndex {
+    /// Ordered by line; those past `complete` are on the unfinished line
+    links: Vec<Link>,
+    complete: usize,
+    /// Bytes of output scanned, up to the start of the unfinished line
+    scanned: usize,
+    /// Complete lines scanned
+    lines: usize,
+}
+
+impl LinkIndex {
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    /// Scan what `output` gained since the last call
+    pub fn update(&mut self, output: &str, cwd: &Path) {
+        if self.scanned > output.len() {
+            *self = Self::default();
+        }
+        self.links.truncate(self.complete);
+        let rest = &output[self.scanned..];
+        let finished = rest.rfind('\n').map_or(0, |end| end + 1);
+        for line in rest[..finished].split_terminator('\n') {
+            self.scan(line, cwd);
+            self.lines += 1;
+        }
+        self.scanned += finished;
+        self.complete = self.links.len();
+        self.scan(&output[self.scanned..], cwd);
+    }
+
+    fn scan(&mut self, line: &str, cwd: &Path) {
+        if self.links.len() >= MAX_LINKS || line.is_empty() {
+            return;
+        }
+        let plain: String = ansi::parse(line).into_iter().map(|span| span.text).collect();
+        let found = find(&plain, cwd).into_iter().map(|(range, target)| Link { line: self.lines, range, target });
+        self.links.extend(found.take(MAX_LINKS - self.links.len()));
+    }
+
+    /// The first `bytes` of output, `lines` whole lines, were dropped from memory
+    pub fn drop_front(&mut self, bytes: usize, lines: usize) {
+        if bytes > self.scanned {
+            *self = Self::default();
+            return;
+        }
+        self.links.retain(|link| link.line >= lines);
+        self.links.iter_mut().for_each(|link| link.line -= lines);
+        self.complete = self.links.iter().filter(|link| link.line < self.lines - lines).count();
+        self.scanned -= bytes;
+        self.lines -= lines;
+    }
+}
+
+/// The command line opening `path` at `line` in `editor`. Editors that
+/// take `+LINE` get that; the ones that want `path:line:column` get it.
+pub fn editor_command(editor: &str, path: &Path, line: Option<usize>, column: Option<usize>) -> String {
+    let file = path.to_string_lossy().into_owned();
+    let program = editor.split_whitespace().next().unwrap_or_default();
+    let name = Path::new(program).file_name().and_then(|name| name.to_str()).unwrap_or(program);
+    let args = match line {
+        None => vec![file],
+        Some(line) => {
+            let located = format!("{}:{}:{}", file, line, column.unwrap_or(1));
+            match name {
+                "code" | "codium" | "code-insiders" => vec!["--goto".to_string(), located],
+                "subl" | "zed" | "hx" | "helix" => vec![located],
+                _ => vec![format!("+{}", line), file],
+            }
+        }
+    };
+    format!("{} {}", editor, crate::scratch::command_line(&args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_urls_and_paths() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/ai")).unwrap();
+        std::fs::write(dir.path().join("src/ai/mod.rs"), "").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "").unwrap();
+
+        let line = "error: src/ai/mod.rs:120:9, see https://docs.rs/x/1.2 and notes.md. v1.2.3 missing.rs";
+        let found = find(line, dir.path());
+        assert_eq!(found, vec![
+            (7..26, LinkTarget::Path { path: dir.path().join("src/ai/mod.rs"), line: Some(120), column: Some(9) }),
+            (32..53, LinkTarget::Url("https://docs.rs/x/1.2".to_string())),
+            (58..66, LinkTarget::Path { path: dir.path().join("notes.md"), line: None, column: None }),
+        ]);
+    }
+
+    #[test]
+    fn test_index_scans_incrementally() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "").unwrap();
+
+        let mut index = LinkIndex::default();
+        let mut output = "see a.r".to_string();
+        index.update(&output, dir.path());
+        assert!(index.links().is_empty());
+
+        output.push_str("s:3\nhttp://x.io\nand \x1b[1ma.rs\x1b[0m");
+        index.update(&output, dir.path());
+        let lines: Vec<(usize, Range<usize>)> = index.links().iter().map(|link| (link.line, link.range.clone())).collect();
+        assert_eq!(lines, vec![(0, 4..10), (1, 0..11), (2, 4..8)]);
+
+        // The first line scrolls out of memory
+        let cut = output.find('\n').unwrap() + 1;
+        output.drain(..cut);
+        index.drop_front(cut, 1);
+        output.push('\n');
+        index.update(&output, dir.path());
+        let lines: Vec<usize> = index.links().iter().map(|link| link.line).collect();
+        assert_eq!(lines, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_editor_command() {
+        let path = Path::new("/src/main.rs");
+        assert_eq!(editor_command("vim", path, Some(42), Some(7)), "vim +42 /src/main.rs");
+        assert_eq!(editor_command("code --wait", path, Some(42), None), "code --wait --goto /src/main.rs:42:1");
+        assert_eq!(editor_command("nano", path, None, None), "nano /src/main.rs");
+    }
+}
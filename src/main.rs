@@ -1,11 +1,15 @@
 use iced::{executor, Application, Command, Element, Settings, Theme};
-use iced::widget::{column, container, scrollable, text_input, button, row, text};
+use futures::StreamExt;
+use iced::widget::{checkbox, column, container, scrollable, text_input, button, row, text, pick_list, tooltip};
 use std::path::PathBuf;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
 mod block;
+mod block_search;
+mod cli;
 mod shell;
+mod redaction;
 mod input;
 mod renderer;
 mod agent_mode_eval;
@@ -30,21 +34,108 @@ mod graphql;
 mod command;
 mod drive;
 mod fuzzy_match;
+mod find_replace;
+mod plugin_api;
+mod timeline;
+mod net;
+mod idle;
+mod share;
+mod status_line;
+mod palette;
+mod exec_events;
+mod layout;
+mod panes;
+mod tabs;
+mod crash_reports;
+mod diagnostics;
+mod maintenance;
+mod bell;
+mod notifications;
+mod pty;
+mod jobs;
+mod shell_integration;
+mod containers;
+mod git_status;
+mod system_appearance;
+mod dir_listing;
+mod file_preview;
+mod font;
+mod keymap;
+mod vim;
+mod hints;
+mod links;
+mod read_only;
+mod safety;
+mod scratch;
+mod clear;
+mod history;
+mod tick;
+mod tee;
+mod block_export;
+mod session;
+mod session_export;
+mod path_inspector;
+mod hooks;
+mod search;
+mod scrollback;
+mod ansi;
+mod completion;
+mod autosuggest;
+mod ai_sidebar;
+mod i18n;
 mod asset_macro;
 
-use block::{Block, BlockContent};
-use shell::ShellManager;
+use block::{AgentRole, Block, BlockContent, BlockMove};
+use shell::{CommandEvent, ShellManager};
 use input::EnhancedTextInput;
 use agent_mode_eval::{AgentMode, AgentConfig, AgentMessage};
-use config::AppConfig;
+use agent_mode_eval::availability::{self, AiGate, AiRequest, AiStatus, Gate};
+use agent_mode_eval::context;
+use agent_mode_eval::system_info::HostContext;
+use agent_mode_eval::tools::{Approval, ToolCall, ToolResult};
+use config::{AppConfig, EnvProfileManager};
+use redaction::Redactor;
+use renderer::ScrollState;
+use find_replace::FindReplaceMessage;
+use plugin_api::{PluginHost, PluginOutput};
+use idle::{IdleDetector, IdleState};
+use share::ShareRecord;
+use status_line::{StatusContext, StatusMessages};
+use palette::{ActionRegistry, CommandAction, CommandPalette, PaletteAction, PaletteMessage};
+use layout::{ResponsiveLayout, SplitDirection, ToolbarLayout};
+use panes::{FocusDirection, PaneId, PaneState, PaneTree, Panes};
+use tabs::{BlockOwner, TabState, Tabs};
 
 #[derive(Debug, Clone)]
 pub struct NeoTerm {
     blocks: Vec<Block>,
+    // Split layout and the panes that aren't focused; the focused pane's
+    // blocks, input and directory are the app's own fields
+    panes: Panes<PaneState>,
+    // Tabs besides the active one, whose state is the app's own fields;
+    // the tab being renamed, and whether closing this one awaits confirmation
+    tabs: Tabs<TabState>,
+    renaming_tab: Option<(usize, String)>,
+    pending_tab_close: bool,
     current_input: String,
-    input_history: Vec<String>,
-    history_index: Option<usize>,
+    history: history::CommandHistory,
+    /// Ctrl+R reverse search, while it's open
+    history_search: Option<history::HistorySearch>,
+    /// Ctrl+F search through the blocks, while its bar is open
+    block_search: Option<block_search::BlockSearch>,
+    /// Chips above the block list; hidden blocks stay in `blocks`
+    block_filter: block::BlockFilter,
+    // Earlier lines of a multi-line command; `current_input` is the last one
+    input_lines: Vec<String>,
+    // Held modifiers, so Enter can tell Shift+Enter and Alt+Enter apart
+    modifiers: iced::keyboard::Modifiers,
+    // Tab completion candidates for the word at the end of the input
+    completion: Option<completion::Completions>,
+    // Words of finished blocks for palette search; shared with an open palette
+    search_index: std::sync::Arc<search::BlockIndex>,
     shell_manager: ShellManager,
+    // Shared with the shell manager and the agent; while on, nothing is spawned
+    read_only: read_only::ReadOnly,
     input_state: text_input::State,
     suggestions: Vec<String>,
     active_suggestion: Option<usize>,
@@ -53,37 +144,779 @@ pub struct NeoTerm {
     agent_mode: Option<AgentMode>,
     agent_enabled: bool,
     agent_streaming: bool,
+    // Block receiving the reply currently being streamed
+    agent_reply_block: Option<Uuid>,
+    // Message id of the prompt being edited; the next submit replaces that turn
+    editing_prompt: Option<Uuid>,
+    // Conversation whose over-budget warning was dismissed
+    budget_warning_dismissed: Option<Uuid>,
+    // Tool calls of the reply being answered, the ones awaiting approval,
+    // and how many rounds of calls the current prompt has taken
+    tool_round: Option<agent_mode_eval::ToolRound>,
+    pending_tool_approvals: std::collections::VecDeque<ToolCall>,
+    tool_rounds_taken: usize,
     
     // Configuration
     config: AppConfig,
     settings_open: bool,
+    settings_view: settings::SettingsView,
+    // Tab shown the next time settings are opened
+    last_settings_tab: settings::SettingsTab,
+    // Reloads the config file when it's edited outside the app
+    config_manager: Option<config::ConfigManager>,
+    // Models recently listed by Ollama servers, and the one being asked now
+    model_cache: availability::ModelCache,
+    model_request: Option<String>,
+
+    // Masks secrets before output leaves the terminal
+    redactor: Redactor,
+
+    // Follow-tail vs anchored scrolling of the block list
+    scroll: ScrollState,
+
+    // Plugins enabled in the config, with their block renderers
+    plugins: PluginHost,
+
+    // Input inactivity; background work pauses while idle
+    idle: IdleDetector,
+    // Incognito session hidden after a long idle until a key is pressed
+    locked: bool,
+
+    // Open command palette, if any
+    palette: Option<CommandPalette>,
+    // What the palette lists as actions; plugins and workflows add to it
+    actions: ActionRegistry,
+    // Saved conversations panel, when open
+    ai_sidebar: Option<ai_sidebar::AiSidebar>,
+    // Where agent conversations are saved; `None` without a config directory
+    conversation_store: Option<agent_mode_eval::store::ConversationManager>,
+
+    // Block awaiting confirmation to share, with the exact text to upload
+    share_preview: Option<(Uuid, String)>,
+
+    // Data chosen for clearing, awaiting confirmation
+    pending_clear: Option<clear::ClearTarget>,
+
+    // Commands of a generated snippet awaiting confirmation, with the reply they came from
+    pending_code_run: Option<(Uuid, Vec<String>)>,
+
+    // Where command names resolve in the current PATH, to flag shadowed executables
+    path_resolver: path_inspector::PathResolver,
+
+    // Ghost text after the cursor, from history or the AI
+    autosuggest: autosuggest::Autosuggester,
+
+    // Sentence the input's command was written from, while it is unchanged
+    translated_command: Option<(String, String)>,
+    /// Fixes suggested in the last minute, reused when the same command fails again
+    fix_cache: agent_mode_eval::fix::FixCache,
+    /// Terminals commands run in, sized to the block list
+    pty: pty::PtyManager,
+    // Containers from the last listing, and the one new commands run in
+    containers: Vec<containers::Container>,
+    exec_container: Option<containers::Container>,
+    // Container stop or restart awaiting confirmation
+    pending_container_action: Option<(containers::Container, containers::ContainerAction)>,
+
+    // Files running blocks' output is mirrored to, and the dialog opening one
+    tees: std::collections::HashMap<Uuid, tee::Tee>,
+    tee_prompt: Option<tee::TeePrompt>,
+
+    // Block being exported, with the chosen format and path
+    export_prompt: Option<block_export::ExportPrompt>,
+    // Transcript of every block being exported
+    session_export_prompt: Option<session_export::SessionExportPrompt>,
+
+    // Block whose trimmed output awaits confirmation before it's sent to the AI
+    ai_context_preview: Option<(Uuid, context::BlockContext)>,
+
+    // Status line: transient notices, spinner frame, branch of the working directory
+    status_messages: StatusMessages,
+    status_frame: usize,
+    git_branch: Option<String>,
+    // Git directory the watcher follows, and the status shown by the prompt
+    git_dir: Option<PathBuf>,
+    git_status: Option<git_status::GitStatus>,
+    // System mode last seen with `appearance.follow_system`. The theme only
+    // switches when this changes, so one picked by hand stays until then.
+    system_appearance: Option<system_appearance::SystemAppearance>,
+
+    // Command from --run, executed once the first frame has been drawn
+    startup_command: Option<String>,
+    // Layout requested with --layout
+    layout: Option<String>,
+
+    // Whether the "AI isn't set up" notice was shown, and any local Ollama found
+    ai_gate: AiGate,
+
+    // Block that keyboard moves apply to, and the block being dragged
+    focused_block: Option<Uuid>,
+    dragging_block: Option<Uuid>,
+    /// Running block attached with Attach, when `terminal.require_attach` is on
+    stdin_attached: Option<Uuid>,
+
+    // Width-driven layout rules, and whether the folded toolbar menu is open
+    responsive: ResponsiveLayout,
+    toolbar_menu_open: bool,
+    // Font of command output, and the sizes its cell metrics were last worked out for
+    output_font: font::OutputFont,
+    window_width: Option<f32>,
+    blocks_viewport: Option<iced::Size>,
+    // Actions by their keys from `keybindings`, and the keys of a sequence pressed so far
+    keymap: keymap::Keymap,
+    // Modal editing of the prompt, when `editor.vim_mode` is on
+    vim: vim::Vim,
+
+    // Bell detection per running command, throttling, and the block whose border is flashing
+    bell_detectors: std::collections::HashMap<Uuid, bell::BellDetector>,
+    bell_limiter: bell::BellLimiter,
+    bell_flash: Option<(Uuid, std::time::Instant)>,
+    // Shell prompt marks per running command
+    prompt_trackers: std::collections::HashMap<Uuid, shell_integration::PromptTracker>,
+    window_focused: bool,
+
+    // Keyboard hints: letters typed so far, and what the last render labelled
+    hint_mode: Option<hints::HintMode>,
+    interactables: std::cell::RefCell<hints::InteractableRegistry<Message>>,
+
+    // Interpreters for running snippets from assistant replies
+    languages: languages::LanguageManager,
+
+    // When the AI request in flight was sent, recent response times, and
+    // the latest health report shared with the diagnostics endpoint
+    ai_request_started: Option<std::time::Instant>,
+    ai_latency: diagnostics::LatencyTracker,
+    diagnostics_report: diagnostics::SharedReport,
+
+    // Scripts in the hooks directory, run on terminal events
+    hooks: hooks::HookRunner,
+
+    // Workflows, for running the scheduled ones while the app is open
+    workflows: Option<workflows::WorkflowManager>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     InputChanged(String),
     ExecuteCommand,
+    /// Typing paused; ask the AI for a suggestion if the input is unchanged
+    AutosuggestDue(u64),
+    /// The AI's suggestion for this input, with how long it took
+    AutosuggestReply(u64, String, Result<(String, std::time::Duration), String>),
+    /// Right Arrow or End in the input: take the ghost text
+    AcceptAutosuggestion,
     CommandOutput(String, i32), // output, exit_code
-    KeyPressed(iced::keyboard::Key),
+    /// Output of a block's command, with the tab and pane the block is in
+    CommandEvent(BlockOwner, Uuid, CommandEvent),
+    /// A key press, and whether a widget such as the focused input took it
+    KeyPressed { key: iced::keyboard::Key, modifiers: iced::keyboard::Modifiers, captured: bool },
+    ModifiersChanged(iced::keyboard::Modifiers),
     HistoryUp,
     HistoryDown,
+    /// Ctrl+R: open the history search, or move to the next candidate
+    StartHistorySearch,
+    /// Ctrl+F: open the block search bar
+    OpenBlockSearch,
+    BlockSearchChanged(String),
+    BlockSearchRegexToggled(bool),
+    /// Enter or n in the search bar
+    NextBlockMatch,
+    /// N in the search bar
+    PreviousBlockMatch,
+    /// Esc; clears the highlights
+    CloseBlockSearch,
+    SetBlockFilter(block::BlockFilter),
+    HistorySearchChanged(String),
+    /// Put the nth candidate in the input without running it
+    HistorySearchPick(usize),
+    CancelHistorySearch,
+    /// Tab (forwards) or Shift+Tab (backwards): complete the last word
+    Complete { backwards: bool },
+    /// Put the nth completion in the input and close the list
+    CompletionPicked(usize),
     SuggestionSelected(usize),
     BlockAction(Uuid, BlockMessage),
+    BlocksScrolled(scrollable::Viewport),
+    /// Mouse down on a block: focuses it and starts a drag
+    BlockPressed(Uuid),
+    /// Mouse up over a block: drops the dragged block there
+    BlockReleased(Uuid),
+    /// Mouse up anywhere else
+    DragCancelled,
+    MoveFocusedBlock(BlockMove),
+    /// Ctrl+Shift+D splits side by side, Ctrl+Shift+E one above the other
+    SplitPane(SplitDirection),
+    /// Ctrl+Shift+W
+    ClosePane,
+    FocusPane(PaneId),
+    /// Ctrl+Shift+Arrow
+    MovePaneFocus(FocusDirection),
+    /// Ctrl+T
+    NewTab,
+    /// Ctrl+W; asks first if commands are running and `confirm_before_closing` is on
+    CloseTab,
+    ConfirmCloseTab,
+    CancelCloseTab,
+    /// Ctrl+1..9, or a click on the tab
+    SelectTab(usize),
+    /// Ctrl+=, Ctrl+- and Ctrl+0
+    Zoom(font::Zoom),
+    /// Right-click on a tab
+    StartTabRename(usize),
+    TabRenameChanged(String),
+    FinishTabRename,
+    SortBlocksByTime,
+    JumpToLatest,
+    OpenFindReplace,
+    OpenPalette,
+    Palette(PaletteMessage),
+    /// Open the palette on a workflow's parameter form
+    OpenWorkflow(String),
+    /// Start the scheduled workflows that are due
+    ScheduleTick,
+    /// A workflow run started by its schedule or by hand ended: its block,
+    /// the workflow's name, and its output and exit code
+    WorkflowRunFinished(Uuid, String, Result<(String, i32), String>),
+    /// Run a scheduled workflow now
+    TriggerWorkflow(String),
+    /// Pause (true) or resume a workflow's schedule
+    PauseSchedule(String, bool),
+    /// List scheduled workflows and their next runs in a block
+    ShowSchedules,
+    /// List running commands with their usage in a block
+    ShowJobs,
+    /// Scroll a running command's block into view
+    ShowJob(Uuid),
+    SignalJob(Uuid, jobs::JobSignal),
+    /// Put a command in the input and run it
+    RunCommandLine(String),
+    OpenSessionExport,
+    /// Use the named env profile for new commands, or none
+    SwitchEnvProfile(Option<String>),
+    /// Use the named config profile's preferences and theme
+    SwitchConfigProfile(String),
+    ToggleAiSidebar,
+    AiSidebar(ai_sidebar::SidebarMessage),
+    FindReplace(Uuid, FindReplaceMessage),
+    PluginOutput(Result<PluginOutput, String>),
+    PluginEvent(Uuid, String),
+    ConfirmShare,
+    CancelShare,
+    /// Answer to the crash report prompt
+    CrashReportConsent(bool),
+    /// Hide the token budget warning for the current conversation
+    DismissBudgetWarning,
+    /// Approve (true) or deny the agent's tool call with this id
+    ToolApproval(String, bool),
+    ToolFinished(ToolResult),
+    /// An explanation for the block's output arrived, or failed
+    ExplanationReady(Uuid, Result<String, String>),
+    /// A sentence typed at the prompt was turned into a command, or failed to be
+    CommandTranslated(String, Result<String, String>),
+    /// The oldest messages were summarized (or failed to be) before a turn
+    HistorySummarized(Result<agent_mode_eval::history::HistorySummary, String>),
+    Shared(Uuid, Result<ShareRecord, String>),
+    // Clearing saved state, confirmed first
+    RequestClear(clear::ClearTarget),
+    ConfirmClear,
+    CancelClear,
+    // Mirroring a running block's output to a file
+    TeePathChanged(String),
+    BrowseTeePath,
+    TeePathPicked(Option<PathBuf>),
+    TeeBackfillToggled(bool),
+    TeeStripAnsiToggled(bool),
+    ConfirmTee,
+    CancelTee,
+    // Saving a block to a file
+    ExportPathChanged(String),
+    ExportFormatSelected(block_export::ExportFormat),
+    ConfirmExport,
+    CancelExport,
+    // Saving every block as one document
+    SessionExportPathChanged(String),
+    SessionExportFormatSelected(session_export::SessionFormat),
+    ConfirmSessionExport,
+    CancelSessionExport,
+    /// Open a directory block for the working directory
+    BrowseDirectory,
+    Directory(Uuid, dir_listing::DirMessage),
+    /// Preview the `path:line` typed at the prompt
+    PreviewInput,
+    /// Preview a file, marking and scrolling to the line when there is one.
+    /// Relative paths are taken from the working directory.
+    PreviewFile(PathBuf, Option<usize>),
+    PreviewRead(Result<file_preview::FilePreview, String>),
+    EditFile(PathBuf),
+    /// A URL or file path clicked in command output
+    OpenOutputLink(links::LinkTarget),
+    // Git status of the working directory's repository
+    OpenGitStatus,
+    GitPanelRead(Result<git_status::GitStatus, String>),
+    /// Something under the watched git directory changed
+    GitChanged,
+    /// Status read for the directory, for the prompt and open git panels
+    GitStatusRead(PathBuf, Result<git_status::GitStatus, String>),
+    /// A git panel's per-file button, in the repository at the path
+    Git(PathBuf, git_status::GitAction),
+    GitActionDone(PathBuf, git_status::GitAction, Result<Option<String>, String>),
+    // Docker and Podman containers
+    ListContainers,
+    ContainersListed(Result<Vec<containers::Container>, String>),
+    /// Run new commands in the container, or on this machine again
+    ExecInContainer(Option<containers::Container>),
+    ContainerLogs(containers::Container),
+    RequestContainerAction(containers::Container, containers::ContainerAction),
+    ConfirmContainerAction,
+    CancelContainerAction,
+    ContainerActionFinished(String, containers::ContainerAction, Result<(), String>),
+    // A generated snippet about to run
+    ConfirmCodeRun,
+    CancelCodeRun,
+    // Block output about to be sent to the AI
+    ConfirmAiContext,
+    SummarizeAiContext,
+    CancelAiContext,
+    AiContextSummarized(Uuid, Result<String, String>),
+    Unshared(Uuid, Result<(), String>),
+    /// The submit hook ran (or there was none); the command runs unless it cancelled
+    SubmitHookFinished(String, Vec<(String, String)>, Option<hooks::HookOutcome>),
+    /// A hook that can't change anything ended; failures are already logged
+    HookFinished,
     Tick,
+    WindowResized(u32),
+    WindowFocusChanged(bool),
+    /// The assistant's corrected command for a failed block
+    FixSuggested(Uuid, Result<String, String>),
+    /// Keys typed into the interactive block, as the terminal sends them
+    StdinInput(Vec<u8>),
+    /// Focus the previous (-1) or next (1) shell prompt, or command block
+    /// where the block has no prompts
+    JumpToPrompt(isize),
+    /// The desktop notification for a finished command was shown, or couldn't be
+    CommandNotified(Uuid, Result<(), String>),
+    /// The window is closing; the session is saved first
+    CloseRequested,
+    OpenLink(String),
+    /// Run the nth code snippet of an assistant reply
+    RunSnippet(Uuid, usize),
+    ToggleToolbarMenu,
+    FirstFrame,
+    IdleCheck,
+    IdleStateChanged(IdleState),
     
     // Agent mode messages
     ToggleAgentMode,
     AgentMessage(AgentMessage),
-    AgentStreamingChunk(String),
-    AgentError(String),
+    OllamaDetected(Option<String>),
+    /// A background maintenance check ended; `None` when it wasn't due
+    MaintenanceFinished(Result<Option<maintenance::MaintenanceReport>, String>),
+    /// Maintenance requested from the diagnostics block ended
+    MaintenanceRan(Result<maintenance::MaintenanceReport, String>),
+    OpenDiagnostics,
+    RefreshDiagnostics,
+    DiagnosticAction(diagnostics::DiagnosticAction),
+    // Two steps to leave read-only mode
+    ArmReadOnlyExit,
+    ConfirmReadOnlyExit,
+    CancelReadOnlyExit,
+    SwitchBranch(Uuid),
     
     // Settings messages
     ToggleSettings,
     SettingsMessage(settings::SettingsMessage),
+    ModelsDiscovered(String, Result<Vec<String>, String>),
+    /// Plugins whose registry lists a newer version, with that version
+    PluginUpdatesChecked(Vec<(String, String)>),
     
+    /// Ask the system for light or dark mode again
+    CheckSystemAppearance,
+    SystemAppearanceDetected(Option<system_appearance::SystemAppearance>),
+
     // Configuration
     ConfigLoaded(AppConfig),
     ConfigSaved,
+    /// The config file was written, maybe by another program
+    ConfigFileChanged,
+    ConfigReloaded(std::sync::Arc<config::ConfigUpdate>),
+}
+
+/// How often the idle detector looks at the clock
+const IDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+/// How often workflow schedules are checked, and reloaded from disk
+const SCHEDULE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// Quiet time after a change to the git directory before the status is read again
+const GIT_WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(300);
+/// Quiet time after the config file is written before it's read again
+const CONFIG_WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(300);
+
+fn idle_detector(config: &AppConfig) -> IdleDetector {
+    let general = &config.preferences.general;
+    IdleDetector::new(
+        std::time::Duration::from_secs(general.idle_timeout_minutes * 60),
+        general.lock_after_idle_minutes.map(|minutes| std::time::Duration::from_secs(minutes * 60)),
+        std::time::Instant::now(),
+    )
+}
+
+/// Messages that mean someone is at the keyboard or mouse
+fn is_user_input(message: &Message) -> bool {
+    matches!(
+        message,
+        Message::InputChanged(_)
+            | Message::ExecuteCommand
+            | Message::KeyPressed { .. }
+            | Message::StdinInput(_)
+            | Message::ModifiersChanged(_)
+            | Message::HistoryUp
+            | Message::HistoryDown
+            | Message::StartHistorySearch
+            | Message::HistorySearchChanged(_)
+            | Message::HistorySearchPick(_)
+            | Message::CancelHistorySearch
+            | Message::Complete { .. }
+            | Message::CompletionPicked(_)
+            | Message::SuggestionSelected(_)
+            | Message::BlockAction(..)
+            | Message::BlocksScrolled(_)
+            | Message::BlockPressed(_)
+            | Message::BlockReleased(_)
+            | Message::MoveFocusedBlock(_)
+            | Message::SplitPane(_)
+            | Message::ClosePane
+            | Message::FocusPane(_)
+            | Message::MovePaneFocus(_)
+            | Message::NewTab
+            | Message::OpenBlockSearch
+            | Message::BlockSearchChanged(_)
+            | Message::BlockSearchRegexToggled(_)
+            | Message::NextBlockMatch
+            | Message::PreviousBlockMatch
+            | Message::CloseBlockSearch
+            | Message::SetBlockFilter(_)
+            | Message::CloseTab
+            | Message::ConfirmCloseTab
+            | Message::CancelCloseTab
+            | Message::SelectTab(_)
+            | Message::Zoom(_)
+            | Message::StartTabRename(_)
+            | Message::TabRenameChanged(_)
+            | Message::FinishTabRename
+            | Message::SortBlocksByTime
+            | Message::JumpToLatest
+            | Message::OpenFindReplace
+            | Message::OpenPalette
+            | Message::OpenWorkflow(_)
+            | Message::TriggerWorkflow(_)
+            | Message::PauseSchedule(..)
+            | Message::ShowSchedules
+            | Message::RunCommandLine(_)
+            | Message::OpenSessionExport
+            | Message::SwitchEnvProfile(_)
+            | Message::SwitchConfigProfile(_)
+            | Message::ToggleAiSidebar
+            | Message::AiSidebar(_)
+            | Message::ToggleToolbarMenu
+            | Message::OpenLink(_)
+            | Message::RunSnippet(..)
+            | Message::OpenDiagnostics
+            | Message::DiagnosticAction(_)
+            | Message::ArmReadOnlyExit
+            | Message::ConfirmReadOnlyExit
+            | Message::CancelReadOnlyExit
+            | Message::Palette(_)
+            | Message::FindReplace(..)
+            | Message::PluginEvent(..)
+            | Message::ConfirmShare
+            | Message::CancelShare
+            | Message::RequestClear(_)
+            | Message::ConfirmClear
+            | Message::CancelClear
+            | Message::TeePathChanged(_)
+            | Message::BrowseTeePath
+            | Message::TeeBackfillToggled(_)
+            | Message::TeeStripAnsiToggled(_)
+            | Message::ConfirmTee
+            | Message::CancelTee
+            | Message::ExportPathChanged(_)
+            | Message::ExportFormatSelected(_)
+            | Message::ConfirmExport
+            | Message::CancelExport
+            | Message::SessionExportPathChanged(_)
+            | Message::SessionExportFormatSelected(_)
+            | Message::ConfirmSessionExport
+            | Message::CancelSessionExport
+            | Message::BrowseDirectory
+            | Message::Directory(..)
+            | Message::PreviewInput
+            | Message::PreviewFile(..)
+            | Message::EditFile(_)
+            | Message::OpenOutputLink(_)
+            | Message::OpenGitStatus
+            | Message::Git(..)
+            | Message::ListContainers
+            | Message::ExecInContainer(_)
+            | Message::ContainerLogs(_)
+            | Message::RequestContainerAction(..)
+            | Message::ConfirmContainerAction
+            | Message::CancelContainerAction
+            | Message::ConfirmCodeRun
+            | Message::CancelCodeRun
+            | Message::ConfirmAiContext
+            | Message::SummarizeAiContext
+            | Message::CancelAiContext
+            | Message::CrashReportConsent(_)
+            | Message::DismissBudgetWarning
+            | Message::ToolApproval(..)
+            | Message::ToggleAgentMode
+            | Message::SwitchBranch(_)
+            | Message::ToggleSettings
+            | Message::SettingsMessage(_)
+    )
+}
+
+/// Markdown list of scheduled workflows, soonest first
+fn schedules_summary(runs: &[workflows::UpcomingRun]) -> String {
+    if runs.is_empty() {
+        return "No workflows have a schedule. Add a `schedule:` section with `every` or `cron` to run one on a timer."
+            .to_string();
+    }
+    let mut summary = "**Scheduled workflows**\n".to_string();
+    for run in runs {
+        let next = match (run.paused, run.next_run) {
+            (true, _) => "paused".to_string(),
+            (false, Some(next)) => format!("next at {}", next.format("%H:%M")),
+            (false, None) => "no further runs".to_string(),
+        };
+        let state = match (run.running, run.queued) {
+            (true, true) => " · running, one more queued",
+            (true, false) => " · running",
+            _ => "",
+        };
+        summary.push_str(&format!("\n- `{}` — {} · {}{}", run.name, run.schedule, next, state));
+    }
+    summary
+}
+
+/// Run maintenance after `delay`, if it is due by then
+fn schedule_maintenance(delay: std::time::Duration, prefs: config::MaintenancePreferences) -> Command<Message> {
+    Command::perform(
+        async move {
+            tokio::time::sleep(delay).await;
+            maintenance::run_if_due(prefs).await
+        },
+        Message::MaintenanceFinished,
+    )
+}
+
+fn detect_system_appearance() -> Command<Message> {
+    Command::perform(
+        async { tokio::task::spawn_blocking(system_appearance::detect).await.ok().flatten() },
+        Message::SystemAppearanceDetected,
+    )
+}
+
+/// `GitChanged` when the repository is first watched, then after each
+/// change under its git directory
+fn watch_git(git_dir: PathBuf) -> iced::Subscription<Message> {
+    iced::subscription::channel(git_dir.clone(), 1, move |mut output| async move {
+        use futures::SinkExt;
+        let _ = output.send(Message::GitChanged).await;
+        match watcher::Watcher::new(&git_dir, GIT_WATCH_DEBOUNCE) {
+            Ok((_watcher, mut changes)) => {
+                while changes.recv().await.is_some() {
+                    let _ = output.send(Message::GitChanged).await;
+                }
+            }
+            Err(e) => log::warn!("Cannot watch {} for git changes: {}", git_dir.display(), e),
+        }
+        futures::future::pending().await
+    })
+}
+
+/// `ConfigFileChanged` after each write to the config file, and
+/// `ConfigReloaded` for each update the manager sends
+fn watch_config(manager: config::ConfigManager) -> iced::Subscription<Message> {
+    iced::subscription::channel(manager.path().to_path_buf(), 1, move |mut output| async move {
+        use futures::SinkExt;
+        let mut updates = manager.subscribe_changes();
+        let watched = watcher::Watcher::file(manager.path(), CONFIG_WATCH_DEBOUNCE);
+        if let Err(e) = &watched {
+            log::warn!("Cannot watch {} for changes: {}", manager.path().display(), e);
+        }
+        let (_watcher, mut writes) = match watched {
+            Ok((watcher, writes)) => (Some(watcher), Some(writes)),
+            Err(_) => (None, None),
+        };
+        loop {
+            tokio::select! {
+                Some(()) = async { writes.as_mut()?.recv().await } => {
+                    let _ = output.send(Message::ConfigFileChanged).await;
+                }
+                update = updates.recv() => match update {
+                    Ok(update) => {
+                        let _ = output.send(Message::ConfigReloaded(update)).await;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                },
+            }
+        }
+        futures::future::pending().await
+    })
+}
+
+/// Layouts that can be requested with --layout
+const KNOWN_LAYOUTS: &[&str] = &["default"];
+
+/// A hint label, drawn in the theme's text and background colors swapped
+fn hint_badge<'a>(label: String) -> Element<'a, Message> {
+    container(text(label.to_uppercase()).font(iced::Font::MONOSPACE).size(12))
+        .padding([0, 4])
+        .style(|theme: &Theme| {
+            let palette = theme.palette();
+            container::Appearance {
+                text_color: Some(palette.background),
+                background: Some(iced::Background::Color(palette.text)),
+                border: iced::Border { radius: 3.0.into(), ..Default::default() },
+                ..Default::default()
+            }
+        })
+        .into()
+}
+
+fn blocks_scrollable_id() -> scrollable::Id {
+    scrollable::Id::new("blocks")
+}
+
+fn command_input_id() -> text_input::Id {
+    text_input::Id::new("command-input")
+}
+
+fn block_search_input_id() -> text_input::Id {
+    text_input::Id::new("block-search")
+}
+
+fn tab_rename_input_id() -> text_input::Id {
+    text_input::Id::new("tab-rename")
+}
+
+/// What a key binding's action does
+fn key_action_message(action: keymap::Action) -> Message {
+    use keymap::Action;
+    match action {
+        Action::Execute => Message::ExecuteCommand,
+        Action::ToggleAgent => Message::ToggleAgentMode,
+        Action::ToggleAiSidebar => Message::ToggleAiSidebar,
+        Action::Palette => Message::OpenPalette,
+        Action::Settings => Message::ToggleSettings,
+        Action::HistorySearch => Message::StartHistorySearch,
+        Action::Find => Message::OpenBlockSearch,
+        Action::Complete => Message::Complete { backwards: false },
+        Action::CompletePrevious => Message::Complete { backwards: true },
+        Action::SplitPane => Message::SplitPane(SplitDirection::Horizontal),
+        Action::SplitPaneDown => Message::SplitPane(SplitDirection::Vertical),
+        Action::ClosePane => Message::ClosePane,
+        Action::FocusPaneLeft => Message::MovePaneFocus(FocusDirection::Left),
+        Action::FocusPaneRight => Message::MovePaneFocus(FocusDirection::Right),
+        Action::FocusPaneUp => Message::MovePaneFocus(FocusDirection::Up),
+        Action::FocusPaneDown => Message::MovePaneFocus(FocusDirection::Down),
+        Action::PreviousPrompt => Message::JumpToPrompt(-1),
+        Action::NextPrompt => Message::JumpToPrompt(1),
+        Action::MoveBlockUp => Message::MoveFocusedBlock(BlockMove::Up),
+        Action::MoveBlockDown => Message::MoveFocusedBlock(BlockMove::Down),
+        Action::NewTab => Message::NewTab,
+        Action::CloseTab => Message::CloseTab,
+        Action::SelectTab(number) => Message::SelectTab(usize::from(number) - 1),
+        Action::ZoomIn => Message::Zoom(font::Zoom::In),
+        Action::ZoomOut => Message::Zoom(font::Zoom::Out),
+        Action::ZoomReset => Message::Zoom(font::Zoom::Reset),
+    }
+}
+
+/// Palette actions for things the application itself can do
+fn builtin_actions() -> ActionRegistry {
+    use clear::ClearTarget;
+
+    let mut actions = ActionRegistry::new();
+    actions.register(
+        CommandAction::new("agent.toggle", "Toggle agent mode", "Agent", || async { Message::ToggleAgentMode })
+            .with_description("Send input to the AI agent instead of the shell"),
+    );
+    actions.register(
+        CommandAction::new("history.search", "Search history", "General", || async { Message::StartHistorySearch })
+            .with_keybinding("Ctrl+R"),
+    );
+    actions.register(
+        CommandAction::new("agent.conversations", "Browse conversations", "Agent", || async { Message::ToggleAiSidebar })
+            .with_description("Continue, rename or delete saved agent conversations")
+            .with_keybinding("A"),
+    );
+    actions.register(CommandAction::new("settings.open", "Open settings", "General", || async { Message::ToggleSettings }));
+    actions.register(
+        CommandAction::new("pane.split_right", "Split pane right", "Panes", || async {
+            Message::SplitPane(SplitDirection::Horizontal)
+        })
+        .with_keybinding("Ctrl+Shift+D"),
+    );
+    actions.register(
+        CommandAction::new("pane.split_down", "Split pane down", "Panes", || async {
+            Message::SplitPane(SplitDirection::Vertical)
+        })
+        .with_keybinding("Ctrl+Shift+E"),
+    );
+    actions.register(
+        CommandAction::new("pane.close", "Close pane", "Panes", || async { Message::ClosePane })
+            .with_keybinding("Ctrl+Shift+W"),
+    );
+    actions.register(
+        CommandAction::new("blocks.search", "Search blocks", "General", || async { Message::OpenBlockSearch })
+            .with_keybinding("Ctrl+F"),
+    );
+    actions.register(
+        CommandAction::new("blocks.previous_prompt", "Previous prompt", "General", || async { Message::JumpToPrompt(-1) })
+            .with_keybinding("Ctrl+Up"),
+    );
+    actions.register(
+        CommandAction::new("blocks.next_prompt", "Next prompt", "General", || async { Message::JumpToPrompt(1) })
+            .with_keybinding("Ctrl+Down"),
+    );
+    actions.register(
+        CommandAction::new("directory.browse", "Browse directory", "General", || async { Message::BrowseDirectory })
+            .with_description("Entries of the working directory, to open, sort and page through"),
+    );
+    actions.register(
+        CommandAction::new("file.preview", "Preview file", "General", || async { Message::PreviewInput })
+            .with_description("Show the file typed at the prompt, such as src/main.rs:42, highlighted"),
+    );
+    actions.register(
+        CommandAction::new("git.status", "Git status", "General", || async { Message::OpenGitStatus })
+            .with_description("Staged, unstaged and untracked files, to stage, unstage or diff"),
+    );
+    actions.register(CommandAction::new("tab.new", "New tab", "Tabs", || async { Message::NewTab }).with_keybinding("Ctrl+T"));
+    actions.register(CommandAction::new("tab.close", "Close tab", "Tabs", || async { Message::CloseTab }).with_keybinding("Ctrl+W"));
+    actions.register(
+        CommandAction::new("diagnostics", "Diagnostics", "General", || async { Message::OpenDiagnostics })
+            .with_description("Health of AI, commands, caches and plugins"),
+    );
+    actions.register(
+        CommandAction::new("session.export", "Export session", "General", || async { Message::OpenSessionExport })
+            .with_description("Save every block as one Markdown or HTML document"),
+    );
+    for (target, name, description) in [
+        (ClearTarget::History, "Clear history", "Forget entered commands"),
+        (ClearTarget::Blocks, "Clear blocks", "Remove all blocks and saved sessions"),
+        (ClearTarget::Conversations, "Clear conversations", "Start over with the agent"),
+        (ClearTarget::Caches, "Clear caches", "Delete workflow caches and scratch files"),
+    ] {
+        actions.register(
+            CommandAction::new(format!("clear.{:?}", target).to_lowercase(), name, "Clear", move || async move {
+                Message::RequestClear(target)
+            })
+            .with_description(description),
+        );
+    }
+    actions
 }
 
 #[derive(Debug, Clone)]
@@ -92,46 +925,331 @@ pub enum BlockMessage {
     Rerun,
     Delete,
     Export,
+    /// Branch the agent conversation after this block's message
+    Fork,
+    /// Load the last prompt into the input to edit and resend it
+    Edit,
+    /// Show or hide the output timeline scrubber
+    ToggleScrubber,
+    /// Move the scrubber to an offset in milliseconds
+    Scrub(u64),
+    /// Preview the redacted markdown before uploading it
+    Share,
+    /// Delete a previous share
+    Unshare,
+    /// Show just a one-line preview of the block, or all of it again
+    ToggleCollapse,
+    /// Keep the block above the others, or stop
+    TogglePin,
+    MoveToTop,
+    MoveToBottom,
+    /// Preview the output as AI context, then ask the agent about it
+    AskAi,
+    /// Ask where to mirror a running command's output
+    StartTee,
+    /// Close the file the output is mirrored to
+    StopTee,
+    /// Open the file holding output no longer kept in memory
+    ShowFullOutput,
+    /// Put a reply's fenced code block on the clipboard
+    CopyCode(usize),
+    /// Run a reply's shell snippet as if typed at the prompt
+    RunCode(usize),
+    /// Put a reply's shell snippet in the input bar to edit first
+    InsertCode(usize),
+    /// Ask for a short explanation of the output, shown under the block
+    Explain,
+    ToggleAnnotation,
+    /// Add the explanation to the agent conversation
+    PinAnnotation,
+    DismissAnnotation,
+    /// Ask for a fix, whether or not that happens on every failure
+    SuggestFix,
+    /// Put the suggested fix in the input bar to edit first
+    UseFix,
+    /// Run the suggested fix in a new block linked to this one
+    ApplyFix,
+    DismissFix,
+    /// Send typed keys to the running command
+    AttachStdin,
+    DetachStdin,
 }
 
 impl Application for NeoTerm {
     type Message = Message;
     type Theme = Theme;
     type Executor = executor::Default;
-    type Flags = ();
+    type Flags = cli::StartupOptions;
+
+    fn new(startup: cli::StartupOptions) -> (Self, Command<Message>) {
+        let mut shell_manager = ShellManager::new();
+        let mut blocks = Vec::new();
+
+        let read_only = read_only::ReadOnly::new();
+        if startup.read_only {
+            read_only.enable(read_only::ReadOnlyReason::Requested);
+        }
+        shell_manager.set_read_only(read_only.clone());
+
+        // Commands and blocks pick up the process working directory
+        if let Some(cwd) = &startup.cwd {
+            if let Err(e) = std::env::set_current_dir(cwd) {
+                blocks.push(Block::new_error(format!("Cannot open {}: {}", cwd.display(), e)));
+            }
+        }
 
-    fn new(_flags: ()) -> (Self, Command<Message>) {
-        let shell_manager = ShellManager::new();
+        if let Some(layout) = startup.layout.as_deref().filter(|name| !KNOWN_LAYOUTS.contains(name)) {
+            blocks.push(Block::new_error(format!(
+                "Unknown layout '{}', using the default (available: {})",
+                layout,
+                KNOWN_LAYOUTS.join(", ")
+            )));
+        }
         
+        // Data left in an older location isn't read until it's migrated
+        if let Ok(paths) = config::ConfigPaths::resolve() {
+            for legacy in paths.legacy_data(|name| std::env::var(name).ok()) {
+                blocks.push(Block::new_info(format!(
+                    "Found NeoTerm data in {}, which is no longer read. Run `neoterm config migrate` to copy it to {}.",
+                    legacy.display(),
+                    paths.root().display()
+                )));
+            }
+        }
+
         // Load configuration
         let config = AppConfig::load().unwrap_or_default();
+        net::configure(&config.preferences.network);
+        shell_manager.set_default_shell(config.preferences.general.default_shell.as_deref());
+        if config.preferences.terminal.shell_integration {
+            if let Ok(paths) = config::ConfigPaths::resolve() {
+                if let Err(e) = shell_manager.enable_shell_integration(paths.shell_integration_dir()) {
+                    log::warn!("Shell integration is off, its scripts could not be written: {}", e);
+                }
+            }
+        }
+
+        // Without `--cwd`, the preference picks where the session starts
+        if startup.cwd.is_none() {
+            let target = match &config.preferences.general.working_directory {
+                config::WorkingDirectoryBehavior::Home => Some("~".to_string()),
+                config::WorkingDirectoryBehavior::LastUsed => config::ConfigPaths::resolve()
+                    .ok()
+                    .and_then(|paths| std::fs::read_to_string(paths.last_cwd_file()).ok())
+                    .map(|dir| dir.trim().to_string()),
+                config::WorkingDirectoryBehavior::Custom(dir) => Some(dir.clone()),
+            };
+            if let Some(target) = target.filter(|target| !target.is_empty()) {
+                let result = shell::resolve_dir(shell_manager.cwd(), &target)
+                    .map_err(|e| e.to_string())
+                    .and_then(|dir| std::env::set_current_dir(&dir).map_err(|e| e.to_string()));
+                if let Err(e) = result {
+                    blocks.push(Block::new_error(format!("Cannot start in {}: {}", target, e)));
+                }
+            }
+        }
+        shell_manager.set_cwd(std::env::current_dir().unwrap_or_default());
+
+        // Blocks from the last run go above this run's startup notices
+        let mut panes = Panes::default();
+        let (mut restored_tabs, mut active_tab) = (Vec::new(), 0);
+        if matches!(config.preferences.general.startup_behavior, config::StartupBehavior::RestoreLastSession) {
+            if let Ok(paths) = config::ConfigPaths::resolve() {
+                match session::load(&paths.last_session_file()) {
+                    Ok(restored) => {
+                        if restored.skipped > 0 {
+                            blocks.insert(0, Block::new_info(format!(
+                                "{} blocks from the last session were saved by a newer NeoTerm and were not restored",
+                                restored.skipped
+                            )));
+                        }
+                        blocks.splice(0..0, restored.blocks);
+                        panes = restored.panes;
+                        (restored_tabs, active_tab) = (restored.tabs, restored.active_tab);
+                    }
+                    Err(e) => blocks.insert(0, Block::new_error(format!("Could not restore the last session: {}", e))),
+                }
+            }
+        }
+
+        // Apply the active env profile to spawned commands
+        let mut redactor = Redactor::new();
+        if let Some(profile_name) = &config.active_env_profile {
+            if let Some(profile) = EnvProfileManager::new()
+                .ok()
+                .and_then(|manager| manager.get_profile(profile_name).cloned())
+            {
+                redactor.register_env(&profile.variables);
+                shell_manager.set_profile_env(profile.variables);
+            }
+        }
         
-        // Initialize agent mode if configured
-        let agent_mode = if let Some(api_key) = std::env::var("OPENAI_API_KEY").ok() {
-            let mut agent_config = AgentConfig::default();
-            agent_config.api_key = Some(api_key);
-            AgentMode::new(agent_config).ok()
+        // The agent always exists; without credentials it stays disabled
+        let mut agent_mode = AgentMode::new(AgentConfig::from_preferences(&config.preferences.ai, |name| std::env::var(name).ok())).ok();
+        if let Some(agent) = agent_mode.as_mut() {
+            agent.set_read_only(read_only.clone());
+            agent.set_host(HostContext {
+                env_profile: config.active_env_profile.clone(),
+                share_identity: config.preferences.privacy.share_host_identity,
+            });
+        }
+        let detect_ollama = match agent_mode.as_ref().map(AgentMode::status) {
+            Some(AiStatus::Ready { .. }) => Command::none(),
+            _ => {
+                let base_url = AgentConfig::ollama().base_url.unwrap_or_default();
+                Command::perform(
+                    async move { availability::detect_ollama(&base_url).await.then_some(base_url) },
+                    Message::OllamaDetected,
+                )
+            }
+        };
+        let diagnostics_report = diagnostics::SharedReport::default();
+        let serve_diagnostics = match config.preferences.diagnostics.api_port {
+            Some(port) => Command::perform(diagnostics::serve(port, diagnostics_report.clone()), |_| Message::RefreshDiagnostics),
+            None => Command::none(),
+        };
+        let follow_system = if config.preferences.appearance.follow_system {
+            detect_system_appearance()
         } else {
-            None
+            Command::none()
         };
-        
+        let (output_font, font_warning) = font::OutputFont::resolve(&config.preferences.ui);
+        let keymap = keymap::Keymap::new(&config.keybindings);
+        let languages = languages::LanguageManager::new(config.preferences.scratch.interpreters.clone());
+        let maintenance = schedule_maintenance(maintenance::STARTUP_DELAY, config.preferences.maintenance.clone());
+        let privacy = &config.preferences.privacy;
+        let mut history = history::CommandHistory::load(
+            config::ConfigPaths::resolve().ok().map(|paths| paths.history_file()),
+            privacy.history_limit,
+        );
+        history.set_persistent(privacy.history_enabled && !privacy.incognito_mode);
+        let hooks = hooks::HookRunner::from_prefs(
+            config::ConfigPaths::resolve().map(|paths| paths.hooks_dir()).unwrap_or_default(),
+            &config.preferences.hooks,
+        );
+
+        let mut app = Self {
+            blocks,
+            panes,
+            tabs: Tabs::default(),
+            renaming_tab: None,
+            pending_tab_close: false,
+            current_input: String::new(),
+            history,
+            history_search: None,
+            block_search: None,
+            block_filter: block::BlockFilter::All,
+            input_lines: Vec::new(),
+            modifiers: iced::keyboard::Modifiers::default(),
+            completion: None,
+            search_index: std::sync::Arc::new(search::BlockIndex::open(
+                config::ConfigPaths::resolve().ok().map(|paths| paths.search_index_file()),
+            )),
+            shell_manager,
+            read_only,
+            input_state: text_input::State::new(),
+            suggestions: Vec::new(),
+            active_suggestion: None,
+            agent_mode,
+            agent_enabled: false,
+            agent_streaming: false,
+            agent_reply_block: None,
+            editing_prompt: None,
+            budget_warning_dismissed: None,
+            tool_round: None,
+            pending_tool_approvals: std::collections::VecDeque::new(),
+            tool_rounds_taken: 0,
+            settings_view: settings::SettingsView::new(config.clone()),
+            last_settings_tab: settings::SettingsTab::General,
+            config_manager: AppConfig::config_path().ok().map(config::ConfigManager::new),
+            model_cache: availability::ModelCache::default(),
+            model_request: None,
+            config,
+            settings_open: false,
+            redactor,
+            scroll: ScrollState::new(),
+            plugins: PluginHost::with_builtins(&config.plugins.enabled_plugins),
+            idle: idle_detector(&config),
+            locked: false,
+            palette: None,
+            actions: builtin_actions(),
+            ai_sidebar: None,
+            conversation_store: config::ConfigPaths::resolve()
+                .ok()
+                .map(|paths| agent_mode_eval::store::ConversationManager::new(paths.conversations_dir())),
+            share_preview: None,
+            ai_context_preview: None,
+            pending_clear: None,
+            pending_code_run: None,
+            path_resolver: path_inspector::PathResolver::default(),
+            autosuggest: autosuggest::Autosuggester::new(),
+            translated_command: None,
+            fix_cache: agent_mode_eval::fix::FixCache::default(),
+            pty: pty::PtyManager::with_jobs(
+                config::ConfigPaths::resolve()
+                    .map(|paths| jobs::JobRegistry::recorded_in(paths.jobs_dir()))
+                    .unwrap_or_default(),
+            ),
+            containers: Vec::new(),
+            exec_container: None,
+            pending_container_action: None,
+            tees: std::collections::HashMap::new(),
+            tee_prompt: None,
+            export_prompt: None,
+            session_export_prompt: None,
+            status_messages: StatusMessages::default(),
+            status_frame: 0,
+            git_branch: std::env::current_dir().ok().and_then(|cwd| status_line::git_branch(&cwd)),
+            git_dir: std::env::current_dir().ok().and_then(|cwd| status_line::git_dir(&cwd)),
+            git_status: None,
+            system_appearance: None,
+            startup_command: startup.run,
+            layout: startup.layout,
+            ai_gate: AiGate::new(),
+            focused_block: None,
+            stdin_attached: None,
+            dragging_block: None,
+            responsive: ResponsiveLayout::new(layout::COMPACT_COLUMNS),
+            toolbar_menu_open: false,
+            output_font,
+            window_width: None,
+            blocks_viewport: None,
+            keymap,
+            vim: vim::Vim::default(),
+            bell_detectors: std::collections::HashMap::new(),
+            prompt_trackers: std::collections::HashMap::new(),
+            bell_limiter: bell::BellLimiter::new(),
+            bell_flash: None,
+            window_focused: true,
+            hint_mode: None,
+            interactables: std::cell::RefCell::new(hints::InteractableRegistry::new()),
+            languages,
+            ai_request_started: None,
+            ai_latency: diagnostics::LatencyTracker::new(),
+            diagnostics_report,
+            hooks,
+            workflows: workflows::WorkflowManager::new().ok(),
+        };
+        app.restore_tabs(restored_tabs, active_tab);
+        if let Some(warning) = font_warning {
+            app.status_messages.push(warning, std::time::Instant::now());
+        }
+        let session_start = app.fire_hook(
+            hooks::HookEvent::SessionStart,
+            serde_json::json!({
+                "event": hooks::HookEvent::SessionStart.script_name(),
+                "working_directory": std::env::current_dir().unwrap_or_default(),
+            }),
+        );
         (
-            Self {
-                blocks: Vec::new(),
-                current_input: String::new(),
-                input_history: Vec::new(),
-                history_index: None,
-                shell_manager,
-                input_state: text_input::State::new(),
-                suggestions: Vec::new(),
-                active_suggestion: None,
-                agent_mode,
-                agent_enabled: false,
-                agent_streaming: false,
-                config,
-                settings_open: false,
-            },
-            Command::none(),
+            app,
+            Command::batch([
+                detect_ollama,
+                maintenance,
+                serve_diagnostics,
+                session_start,
+                follow_system,
+            ]),
         )
     }
 
@@ -144,43 +1262,308 @@ impl Application for NeoTerm {
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
+        if is_user_input(&message) {
+            if let Some(state) = self.idle.record_activity(std::time::Instant::now()) {
+                self.set_idle_state(state);
+            }
+        }
+
+        // Choosing an entry from the folded toolbar closes its menu
+        if matches!(
+            message,
+            Message::ToggleAgentMode | Message::ToggleSettings | Message::OpenFindReplace | Message::OpenPalette
+        ) {
+            self.toolbar_menu_open = false;
+        }
+
         match message {
             Message::InputChanged(input) => {
                 self.current_input = input.clone();
                 self.suggestions = self.generate_suggestions(&input);
+                self.completion = None;
+                self.update_autosuggestion()
+            }
+            Message::AutosuggestDue(generation) => self.request_ai_suggestion(generation),
+            Message::AutosuggestReply(generation, input, result) => {
+                match result {
+                    Ok((reply, latency)) => self.autosuggest.ai_reply(generation, &input, &reply, latency),
+                    Err(e) => {
+                        log::debug!("No AI suggestion for `{}`: {}", input, e);
+                        self.autosuggest.ai_failed(generation);
+                    }
+                }
                 Command::none()
             }
+            Message::AcceptAutosuggestion => {
+                // Right and End keep their usual meaning everywhere else
+                if self.settings_open || self.palette.is_some() || self.history_search.is_some() {
+                    return Command::none();
+                }
+                let Some(text) = self.autosuggest.accept() else {
+                    return Command::none();
+                };
+                self.current_input = text;
+                self.suggestions.clear();
+                text_input::move_cursor_to_end(command_input_id())
+            }
             Message::ExecuteCommand => {
+                if let Err(e) = self.read_only.check() {
+                    // Keep the input so it can be submitted after leaving read-only mode
+                    self.status_messages.push(e.to_string(), std::time::Instant::now());
+                    return Command::none();
+                }
+                self.autosuggest.clear();
+                // Shift+Enter and Alt+Enter start a new line, as does Enter in
+                // the middle of a quote, after a backslash or inside a heredoc
+                let full_input = self.full_input();
+                let continues = !self.agent_enabled && input::needs_continuation(&full_input);
+                if self.modifiers.shift() || self.modifiers.alt() || continues {
+                    self.input_lines.push(std::mem::take(&mut self.current_input));
+                    self.suggestions.clear();
+                    return text_input::focus(command_input_id());
+                }
+                self.input_lines.clear();
+                self.current_input = full_input;
+                // Ctrl+Enter runs the input as typed, even if it reads like a sentence
+                let input = self.current_input.clone();
+                let translated = self.translated_command.take().is_some_and(|(_, command)| command == input);
+                if !translated && !self.modifiers.control() && !self.agent_enabled && self.reads_as_request(&input) {
+                    return self.translate_to_command();
+                }
                 if !self.current_input.trim().is_empty() {
                     let command = self.current_input.clone();
-                    self.input_history.push(command.clone());
-                    self.history_index = None;
+                    self.history.push(command.clone());
                     
-                    if self.agent_enabled && self.agent_mode.is_some() {
+                    let ai_args = command.trim().strip_prefix("/ai").filter(|rest| rest.is_empty() || rest.starts_with(' '));
+                    if let Some(args) = ai_args {
+                        self.current_input.clear();
+                        self.handle_ai_command(args.trim());
+                        self.follow_output(1)
+                    } else if self.agent_enabled && self.agent_mode.is_some() {
                         // Send to agent mode
+                        if !self.ai_allowed(AiRequest::AgentPrompt) {
+                            return Command::none();
+                        }
                         self.handle_agent_command(command)
                     } else {
-                        // Regular command execution
-                        let block = Block::new_command(command.clone());
-                        self.blocks.push(block);
+                        // Regular command execution; leading NAME=value pairs apply
+                        // to this invocation only. A multi-line command goes to
+                        // the shell exactly as typed.
+                        let (env_overrides, command) = if command.contains('\n') {
+                            (Vec::new(), command)
+                        } else {
+                            shell::parse_env_prefix(&command)
+                        };
+                        self.redactor.register_env(env_overrides.iter().map(|(k, v)| (k, v)));
+                        // The shell expands what it runs; lines handled here get the same treatment
+                        let expanded = if self.config.preferences.terminal.expand_variables {
+                            self.shell_manager.expand(&command, &env_overrides)
+                        } else {
+                            command.clone()
+                        };
+
+                        if let Some(plugin) = self.plugins.plugin_for_command(&expanded) {
+                            self.current_input.clear();
+                            let command = expanded;
+                            // Plugin commands may shell out; keep them off the UI thread
+                            return Command::perform(
+                                async move {
+                                    tokio::task::spawn_blocking(move || PluginHost::execute(plugin.as_ref(), &command))
+                                        .await
+                                        .map_err(|e| e.to_string())
+                                        .and_then(|result| result.map_err(|e| e.to_string()))
+                                },
+                                Message::PluginOutput,
+                            );
+                        }
+
                         self.current_input.clear();
-                        
-                        Command::perform(
-                            self.shell_manager.execute_command(command),
-                            |(output, exit_code)| Message::CommandOutput(output, exit_code)
-                        )
+                        let on_host = env_overrides.is_empty() && self.exec_container.is_none();
+                        if let Some(dir_command) = shell::parse_dir_command(&expanded).filter(|_| on_host) {
+                            return self.change_directory(command, dir_command);
+                        }
+                        if on_host && self.config.preferences.terminal.browse_on_ls && expanded.trim() == "ls" {
+                            return self.browse_directory(self.shell_manager.cwd().to_path_buf());
+                        }
+                        if self.hooks_allowed() && self.hooks.script(hooks::HookEvent::CommandSubmit).is_some() {
+                            let runner = self.hooks.clone();
+                            let payload = serde_json::json!({
+                                "event": hooks::HookEvent::CommandSubmit.script_name(),
+                                "command": command,
+                                "env": env_overrides.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+                                "working_directory": std::env::current_dir().unwrap_or_default(),
+                            });
+                            return Command::perform(
+                                async move { runner.run(hooks::HookEvent::CommandSubmit, &payload).await },
+                                move |outcome| Message::SubmitHookFinished(command, env_overrides, outcome),
+                            );
+                        }
+                        let mut block = Block::new_command_with_env(command.clone(), env_overrides.clone());
+                        let command = self.in_container(&mut block, command, &env_overrides);
+                        self.run_in_block(block, command, env_overrides.into_iter().collect())
                     }
                 } else {
                     Command::none()
                 }
             }
+            Message::SubmitHookFinished(command, env_overrides, outcome) => {
+                if let Some(outcome) = outcome.filter(hooks::HookOutcome::cancels) {
+                    let reason = outcome.stderr.trim();
+                    let message = if reason.is_empty() {
+                        format!("`{}` was cancelled by the on-command-submit hook", command)
+                    } else {
+                        format!("`{}` was cancelled by the on-command-submit hook: {}", command, reason)
+                    };
+                    self.blocks.push(Block::new_error(message));
+                    return self.follow_output(1);
+                }
+                let mut block = Block::new_command_with_env(command.clone(), env_overrides.clone());
+                let command = self.in_container(&mut block, command, &env_overrides);
+                self.run_in_block(block, command, env_overrides.into_iter().collect())
+            }
+            Message::HookFinished => Command::none(),
             Message::CommandOutput(output, exit_code) => {
+                let added_lines = output.lines().count();
                 if let Some(last_block) = self.blocks.last_mut() {
                     last_block.set_output(output, exit_code);
                 }
-                Command::none()
+                self.follow_output(added_lines)
+            }
+            Message::CommandEvent(owner, block_id, event) => {
+                let alert_patterns: Vec<regex::Regex> = self.config.preferences.terminal.alert_patterns
+                    .iter()
+                    .filter_map(|pattern| regex::Regex::new(pattern).ok())
+                    .collect();
+                // Output of a tab or pane in the background goes to its parked blocks
+                let in_active_tab = owner.tab == self.tabs.active_id();
+                let in_focus = in_active_tab && owner.pane == self.panes.focused();
+                let blocks = if !in_active_tab {
+                    match self.tabs.get_mut(owner.tab).and_then(|tab| tab.pane_mut(owner.pane)) {
+                        Some(state) => &mut state.blocks,
+                        None => return Command::none(),
+                    }
+                } else if in_focus {
+                    &mut self.blocks
+                } else {
+                    match self.panes.get_mut(owner.pane) {
+                        Some(state) => &mut state.blocks,
+                        None => return Command::none(),
+                    }
+                };
+                let Some(block) = blocks.iter_mut().find(|b| b.id == block_id) else {
+                    return Command::none();
+                };
+
+                let mut bells = 0;
+                let mut exited = None;
+                let added_lines = match event {
+                    CommandEvent::Chunk(chunk) => {
+                        let added_lines = chunk.text.matches('\n').count();
+                        bells = self.bell_detectors.entry(block_id).or_default().feed(chunk.text.as_bytes());
+                        block.ring_bell(bells);
+                        if let Some(tee) = self.tees.get_mut(&block_id) {
+                            match tee.write_chunk(&chunk) {
+                                Ok(()) => block.tee = Some(tee.status()),
+                                Err(e) => {
+                                    self.tees.remove(&block_id);
+                                    block.tee = None;
+                                    self.status_messages.push(
+                                        format!("Stopped writing output to file: {}", e),
+                                        std::time::Instant::now(),
+                                    );
+                                }
+                            }
+                        }
+                        let pieces = self.prompt_trackers.entry(block_id).or_default().feed(&chunk.text);
+                        for piece in pieces {
+                            match piece {
+                                shell_integration::Piece::Text(text) => block.append_chunk(timeline::OutputChunk {
+                                    offset_ms: chunk.offset_ms,
+                                    stream: chunk.stream,
+                                    text,
+                                }),,
+                                shell_integration::Piece::Prompt => block.mark_prompt(),
+                                shell_integration::Piece::Command(command) => block.set_prompt_command(command),
+                                shell_integration::Piece::Exited(code) => block.set_prompt_exit(code),
+                            }
+                        }
+                        added_lines
+                    }
+                    CommandEvent::Exited(exit_code) => {
+                        let elapsed = block
+                            .metadata()
+                            .map_or(0, |metadata| metadata.elapsed(chrono::Utc::now()).as_millis() as u64);
+                        block.finish_output(exit_code, elapsed, &alert_patterns);
+                        self.bell_detectors.remove(&block_id);
+                        self.prompt_trackers.remove(&block_id);
+                        // The command may have switched branches, or created a repository
+                        if let Ok(cwd) = std::env::current_dir() {
+                            self.sync_git(&cwd);
+                        }
+                        if let BlockContent::Command { input, working_directory, .. } = &block.content {
+                            exited = Some((input.clone(), working_directory.clone(), exit_code, elapsed));
+                        }
+                        0
+                    }
+                };
+                let mut hook_runs = Vec::new();
+                let exited_code = exited.as_ref().map(|(_, _, exit_code, _)| *exit_code);
+                if let Some((command, working_directory, exit_code, elapsed)) = exited {
+                    self.stop_tee(block_id);
+                    self.index_block(block_id);
+                    let long_running_ms = self.config.preferences.hooks.long_running_secs * 1000;
+                    let events = [
+                        (exit_code != 0, hooks::HookEvent::CommandFailed),
+                        (elapsed >= long_running_ms, hooks::HookEvent::CommandLongRunning),
+                    ];
+                    let duration = std::time::Duration::from_millis(elapsed);
+                    if notifications::should_notify(&self.config.preferences.terminal, duration, self.window_focused) {
+                        let finished = notifications::CommandFinished { command: self.redactor.redact(&command), exit_code, duration };
+                        hook_runs.push(Command::perform(notifications::send(finished), move |result| {
+                            Message::CommandNotified(block_id, result)
+                        }));
+                    }
+                    for (_, event) in events.into_iter().filter(|(applies, _)| *applies) {
+                        hook_runs.push(self.fire_hook(
+                            event,
+                            serde_json::json!({
+                                "event": event.script_name(),
+                                "command": command,
+                                "exit_code": exit_code,
+                                "duration_ms": elapsed,
+                                "working_directory": working_directory,
+                            }),
+                        ));
+                    }
+                }
+                let ring = if bells > 0 { self.ring_bell(block_id) } else { Command::none() };
+                // Suggestions are shown under the block, so only for the pane in view
+                // Edits to the working tree don't touch the git directory
+                let git = if exited_code.is_some() { self.refresh_git(self.shell_manager.cwd().to_path_buf()) } else { Command::none() };
+                let fix = match exited_code {
+                    Some(code) if code != 0 && in_focus && self.config.preferences.ai.auto_fix_on_failure => {
+                        self.auto_suggest_fix(block_id, code)
+                    }
+                    _ => Command::none(),
+                };
+                let follow = if in_focus {
+                    if let Some(search) = self.block_search.as_mut() {
+                        search.refresh(block::visible(&self.blocks, self.block_filter));
+                    }
+                    self.follow_output(added_lines)
+                } else {
+                    // Counted for the pill the pane shows once it has focus again
+                    if let Some(state) = self.parked_pane_mut(owner) {
+                        state.scroll.on_output(added_lines);
+                    }
+                    Command::none()
+                };
+                Command::batch([follow, ring, fix, git].into_iter().chain(hook_runs))
             }
             Message::ToggleAgentMode => {
+                if !self.ai_allowed(AiRequest::ToggleAgent) {
+                    return Command::none();
+                }
                 if let Some(ref mut agent) = self.agent_mode {
                     self.agent_enabled = agent.toggle();
                     if self.agent_enabled {
@@ -193,250 +1576,4896 @@ impl Application for NeoTerm {
                         let block = Block::new_agent_message("Agent mode deactivated.".to_string());
                         self.blocks.push(block);
                     }
-                } else {
-                    // Try to initialize agent mode
-                    if let Some(api_key) = std::env::var("OPENAI_API_KEY").ok() {
-                        let mut agent_config = AgentConfig::default();
-                        agent_config.api_key = Some(api_key);
-                        if let Ok(agent) = AgentMode::new(agent_config) {
-                            self.agent_mode = Some(agent);
-                            self.agent_enabled = true;
-                            let block = Block::new_agent_message("Agent mode activated. How can I help you?".to_string());
-                            self.blocks.push(block);
-                        } else {
-                            let block = Block::new_error("Failed to initialize agent mode. Check your API key.".to_string());
-                            self.blocks.push(block);
-                        }
-                    } else {
-                        let block = Block::new_error("Agent mode requires OPENAI_API_KEY environment variable.".to_string());
-                        self.blocks.push(block);
-                    }
                 }
                 Command::none()
             }
-            Message::AgentStreamingChunk(chunk) => {
-                if let Some(last_block) = self.blocks.last_mut() {
-                    if let BlockContent::AgentMessage { ref mut content, .. } = last_block.content {
-                        content.push_str(&chunk);
+            Message::OllamaDetected(base_url) => {
+                self.ai_gate.set_ollama(base_url);
+                Command::none()
+            }
+            Message::MaintenanceFinished(result) => {
+                match result {
+                    Ok(Some(report)) if report.is_notable() => {
+                        self.status_messages.push(report.summary(), std::time::Instant::now());
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Maintenance failed: {}", e),
+                }
+                // Check again once the next run could be due
+                let interval = std::time::Duration::from_secs(
+                    self.config.preferences.maintenance.interval_days.max(1) * 24 * 60 * 60,
+                );
+                schedule_maintenance(interval, self.config.preferences.maintenance.clone())
+            }
+            Message::MaintenanceRan(result) => {
+                let notice = match result {
+                    Ok(report) => report.summary(),
+                    Err(e) => format!("Maintenance failed: {}", e),
+                };
+                self.status_messages.push(notice, std::time::Instant::now());
+                self.update(Message::RefreshDiagnostics)
+            }
+            Message::OpenDiagnostics => {
+                let report = self.collect_diagnostics();
+                self.blocks.push(Block::new_diagnostics(report));
+                self.scroll.jump_to_bottom();
+                scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+            }
+            Message::RefreshDiagnostics => {
+                let report = self.collect_diagnostics();
+                for block in &mut self.blocks {
+                    if let BlockContent::Diagnostics(ref mut shown) = block.content {
+                        *shown = report.clone();
                     }
                 }
                 Command::none()
             }
-            Message::AgentError(error) => {
-                let block = Block::new_error(format!("Agent error: {}", error));
-                self.blocks.push(block);
-                self.agent_streaming = false;
+            Message::DiagnosticAction(action) => self.handle_diagnostic_action(action),
+            Message::ArmReadOnlyExit => {
+                if let Err(e) = self.read_only.arm_exit() {
+                    self.status_messages.push(e.to_string(), std::time::Instant::now());
+                }
                 Command::none()
             }
-            Message::ToggleSettings => {
-                self.settings_open = !self.settings_open;
+            Message::ConfirmReadOnlyExit => {
+                let notice = match self.read_only.confirm_exit() {
+                    Ok(()) => "Left read-only mode; commands run again".to_string(),
+                    Err(e) => e.to_string(),
+                };
+                self.status_messages.push(notice, std::time::Instant::now());
                 Command::none()
             }
-            Message::HistoryUp => {
-                if !self.input_history.is_empty() {
-                    let new_index = match self.history_index {
-                        None => Some(self.input_history.len() - 1),
-                        Some(i) if i > 0 => Some(i - 1),
-                        Some(i) => Some(i),
-                    };
-                    
-                    if let Some(index) = new_index {
-                        self.current_input = self.input_history[index].clone();
-                        self.history_index = new_index;
-                    }
-                }
+            Message::CancelReadOnlyExit => {
+                self.read_only.disarm_exit();
                 Command::none()
             }
-            Message::HistoryDown => {
-                match self.history_index {
-                    Some(i) if i < self.input_history.len() - 1 => {
-                        self.history_index = Some(i + 1);
-                        self.current_input = self.input_history[i + 1].clone();
-                    }
-                    Some(_) => {
-                        self.history_index = None;
-                        self.current_input.clear();
-                    }
+            Message::SwitchBranch(branch_id) => {
+                let switched = self.agent_mode
+                    .as_mut()
+                    .map(|agent| agent.switch_branch(branch_id));
+                match switched {
+                    Some(Ok(())) => self.show_active_branch(),
+                    Some(Err(e)) => self.blocks.push(Block::new_error(e.to_string())),
                     None => {}
                 }
                 Command::none()
             }
-            Message::BlockAction(block_id, action) => {
-                self.handle_block_action(block_id, action)
+            Message::AgentMessage(agent_message) => {
+                let added_lines = match &agent_message {
+                    AgentMessage::AssistantDelta(chunk) => chunk.matches('\n').count(),
+                    AgentMessage::UserMessage(_) | AgentMessage::Usage(_) | AgentMessage::Done => 0,
+                    _ => 1,
+                };
+                let command = self.handle_agent_message(agent_message);
+                Command::batch([command, self.follow_output(added_lines)])
             }
-            _ => Command::none(),
-        }
-    }
-
-    fn view(&self) -> Element<Message> {
-        if self.settings_open {
-            // Show settings view
-            let settings_view = settings::SettingsView::new(self.config.clone());
-            return settings_view.view().map(Message::SettingsMessage);
-        }
-
-        let blocks_view = scrollable(
-            column(
-                self.blocks
-                    .iter()
-                    .map(|block| block.view())
-                    .collect::<Vec<_>>()
-            )
-            .spacing(8)
-        )
-        .height(iced::Length::Fill);
-
-        let input_view = self.create_input_view();
-        let toolbar = self.create_toolbar();
-
-        column![toolbar, blocks_view, input_view]
-            .spacing(8)
-            .padding(16)
-            .into()
-    }
-}
-
-impl NeoTerm {
-    fn generate_suggestions(&self, input: &str) -> Vec<String> {
-        let mut suggestions = Vec::new();
-        
-        // Add command history matches
-        for cmd in &self.input_history {
-            if cmd.contains(input) && cmd != input {
-                suggestions.push(cmd.clone());
+            Message::ToolApproval(call_id, approved) => {
+                let Some(index) = self.pending_tool_approvals.iter().position(|call| call.id == call_id) else {
+                    return Command::none();
+                };
+                let call = self.pending_tool_approvals.remove(index).expect("index is in range");
+                let command = if approved {
+                    self.run_tool(call)
+                } else {
+                    self.finish_tool(ToolResult::error(&call, agent_mode_eval::tools::DENIED_BY_USER))
+                };
+                Command::batch([command, self.follow_output(1)])
             }
-        }
-        
-        // Add common commands
-        let common_commands = ["ls", "cd", "git", "npm", "cargo", "docker", "kubectl"];
-        for cmd in &common_commands {
-            if cmd.starts_with(input) && !input.is_empty() {
-                suggestions.push(cmd.to_string());
+            Message::ToolFinished(result) => {
+                let command = self.finish_tool(result);
+                Command::batch([command, self.follow_output(1)])
             }
-        }
-        
-        // Add agent mode suggestions
-        if self.agent_enabled {
-            let agent_suggestions = [
-                "explain this command:",
-                "help me with",
-                "what does this error mean:",
-                "how do I",
-                "show me how to",
-            ];
-            for suggestion in &agent_suggestions {
-                if suggestion.starts_with(input) && !input.is_empty() {
-                    suggestions.push(suggestion.to_string());
+            Message::FixSuggested(block_id, result) => {
+                // Dismissed while the answer was on its way
+                let Some(block) = self.blocks.iter().find(|b| b.id == block_id).filter(|b| b.fix.is_some()) else {
+                    return Command::none();
+                };
+                if let Ok(command) = &result {
+                    self.fix_cache.insert(block.title(), command.clone(), std::time::Instant::now());
                 }
+                self.show_fix(block_id, result);
+                Command::none()
             }
-        }
-        
-        suggestions.truncate(5);
-        suggestions
+            Message::ExplanationReady(block_id, result) => {
+                // Dismissed while the answer was on its way
+                if let Some(annotation) = self.blocks.iter_mut().find(|b| b.id == block_id).and_then(|b| b.annotation.as_mut()) {
+                    annotation.state = match result {
+                        Ok(explanation) => block::AnnotationState::Ready(explanation),
+                        Err(e) => block::AnnotationState::Failed(e),
+                    };
+                }
+                Command::none()
+            }
+            Message::CommandTranslated(request, result) => {
+                match result {
+                    // Prefilled for review; nothing runs until it is submitted
+                    Ok(command) if self.current_input.is_empty() && self.input_lines.is_empty() => {
+                        self.set_input(&command);
+                        self.translated_command = Some((request, command));
+                        text_input::focus(command_input_id())
+                    }
+                    // Something else was typed meanwhile; don't overwrite it
+                    Ok(_) => Command::none(),
+                    Err(e) => {
+                        self.blocks.push(Block::new_error(format!("Could not turn that into a command: {}", e)));
+                        if self.current_input.is_empty() {
+                            self.set_input(&request);
+                        }
+                        self.follow_output(1)
+                    }
+                }
+            }
+            Message::HistorySummarized(summary) => {
+                let Some(agent) = self.agent_mode.as_mut() else {
+                    return Command::none();
+                };
+                // Without a summary the oldest messages are left out instead
+                let applied = match summary {
+                    Ok(summary) => agent.apply_summary(summary),
+                    Err(e) => {
+                        log::warn!("Could not summarize the conversation: {}", e);
+                        Ok(())
+                    }
+                };
+                // Cleared while the summary was being written
+                if let Err(agent_mode_eval::AgentError::NoActiveConversation) = applied {
+                    return Command::none();
+                }
+                let turn = agent.prepare_turn();
+                self.save_conversation();
+                self.stream_agent_turn(turn)
+            }
+            Message::BlocksScrolled(viewport) => {
+                // Scrolling also reports the block list's new bounds after a resize
+                let bounds = viewport.bounds().size();
+                self.blocks_viewport = Some(bounds);
+                self.pty.resize(pty::TerminalSize::for_viewport(bounds.width, bounds.height, &self.output_font));
+                self.scroll.set_sensitivity(self.config.preferences.terminal.scroll_sensitivity);
+                let correction = self.scroll.on_viewport(
+                    viewport.absolute_offset().y,
+                    viewport.bounds().height,
+                    viewport.content_bounds().height,
+                );
+                match correction {
+                    Some(y) => scrollable::scroll_to(blocks_scrollable_id(), scrollable::AbsoluteOffset { x: 0.0, y }),
+                    None => Command::none(),
+                }
+            }
+            Message::JumpToLatest => {
+                self.scroll.jump_to_bottom();
+                scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+            }
+            Message::IdleCheck => {
+                if let Some(state) = self.idle.poll(std::time::Instant::now()) {
+                    self.set_idle_state(state);
+                }
+                Command::none()
+            }
+            Message::IdleStateChanged(state) => {
+                self.set_idle_state(state);
+                Command::none()
+            }
+            Message::FirstFrame => {
+                // Run --run only once the window is up so its output is visible from the start
+                match self.startup_command.take() {
+                    Some(command) => {
+                        self.current_input = command;
+                        self.update(Message::ExecuteCommand)
+                    }
+                    None => Command::none(),
+                }
+            }
+            Message::OpenFindReplace => {
+                let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+                self.blocks.push(Block::new_find_replace(root));
+                self.scroll.jump_to_bottom();
+                scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+            }
+            Message::FindReplace(block_id, message) => {
+                self.handle_find_replace(block_id, message)
+            }
+            Message::PluginOutput(result) => {
+                let block = match result {
+                    Ok(PluginOutput::Text(output)) => Block::new_agent_message(output),
+                    Ok(PluginOutput::Block { block_type, data }) => match self.plugins.create_block(block_type, data) {
+                        Ok(plugin_block) => Block::new_plugin(plugin_block),
+                        Err(e) => Block::new_error(e.to_string()),
+                    },
+                    Err(e) => Block::new_error(e),
+                };
+                self.blocks.push(block);
+                self.follow_output(1)
+            }
+            Message::PluginEvent(block_id, action) => {
+                if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                    if let BlockContent::Plugin(ref mut plugin_block) = block.content {
+                        self.plugins.handle_event(block_id, plugin_block, &action);
+                    }
+                }
+                Command::none()
+            }
+            Message::OpenPalette => {
+                let resources = resources::ResourceManager::load();
+                self.register_dynamic_actions(&resources);
+                self.palette = Some(
+                    CommandPalette::new(resources, cli::current_shell())
+                        .with_search(self.search_corpus())
+                        .with_actions(self.actions.clone()),
+                );
+                text_input::focus(palette::query_input_id())
+            }
+            Message::OpenWorkflow(name) => {
+                // The user's own workflows win over bundled templates of the same name
+                let workflow = self.workflows.as_ref().and_then(|manager| manager.get_workflow(&name)).cloned();
+                let command = self.update(Message::OpenPalette);
+                if let Some(palette) = self.palette.as_mut() {
+                    match workflow {
+                        Some(workflow) => palette.open_workflow(workflow),
+                        None => {
+                            palette.update(PaletteMessage::SelectTemplate(name));
+                        }
+                    }
+                }
+                command
+            }
+            Message::RunCommandLine(command) => {
+                self.set_input(&command);
+                self.update(Message::ExecuteCommand)
+            }
+            Message::ScheduleTick => {
+                let Some(manager) = self.workflows.as_mut() else {
+                    return Command::none();
+                };
+                if let Err(e) = manager.refresh_schedules() {
+                    self.status_messages.push(format!("Could not reload workflows: {}", e), std::time::Instant::now());
+                }
+                let due = manager.due_runs(chrono::Local::now());
+                let runs: Vec<_> = due.into_iter().map(|workflow| self.start_workflow_run(workflow, std::collections::HashMap::new())).collect();
+                Command::batch(runs)
+            }
+            Message::WorkflowRunFinished(block_id, name, result) => {
+                let (output, exit_code) = result.unwrap_or_else(|e| (format!("{}\n", e), 1));
+                let output = self.redactor.redact(&output);
+                if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                    block.set_output(output, exit_code);
+                }
+                self.index_block(block_id);
+                match self.workflows.as_mut().and_then(|manager| manager.run_finished(&name)) {
+                    Some(workflow) => self.start_workflow_run(workflow, std::collections::HashMap::new()),
+                    None => Command::none(),
+                }
+            }
+            Message::TriggerWorkflow(name) => {
+                let Some(manager) = self.workflows.as_mut() else {
+                    return Command::none();
+                };
+                let notice = match manager.trigger_now(&name) {
+                    Ok((workflows::Trigger::Start, workflow)) => return self.start_workflow_run(workflow, std::collections::HashMap::new()),
+                    Ok((workflows::Trigger::Queued, _)) => format!("'{}' is running; it will run again when it ends", name),
+                    Ok((workflows::Trigger::Skipped, _)) => format!("'{}' is already running", name),
+                    Err(e) => e.to_string(),
+                };
+                self.status_messages.push(notice, std::time::Instant::now());
+                Command::none()
+            }
+            Message::PauseSchedule(name, paused) => {
+                let Some(manager) = self.workflows.as_mut() else {
+                    return Command::none();
+                };
+                let notice = match manager.pause_schedule(&name, paused) {
+                    Ok(()) if paused => format!("Paused the schedule of '{}'", name),
+                    Ok(()) => format!("Resumed the schedule of '{}'", name),
+                    Err(e) => e.to_string(),
+                };
+                self.status_messages.push(notice, std::time::Instant::now());
+                Command::none()
+            }
+            Message::ShowSchedules => {
+                let runs = self.workflows.as_ref().map(|manager| manager.upcoming_runs()).unwrap_or_default();
+                self.blocks.push(Block::new_info(schedules_summary(&runs)));
+                self.scroll.jump_to_bottom();
+                scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+            }
+            Message::ShowJobs => {
+                let now = chrono::Utc::now();
+                let lines: Vec<String> = self
+                    .pty
+                    .jobs()
+                    .list()
+                    .into_iter()
+                    .map(|job| format!("{}  {}", job.command, job.summary(jobs::usage(job.pid), now)))
+                    .collect();
+                let summary = if lines.is_empty() { "No running commands".to_string() } else { lines.join("\n") };
+                self.blocks.push(Block::new_info(summary));
+                self.scroll.jump_to_bottom();
+                scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+            }
+            Message::ShowJob(block_id) => self.show_block(block_id),
+            Message::BrowseDirectory => self.browse_directory(self.shell_manager.cwd().to_path_buf()),
+            Message::Directory(block_id, message) => self.handle_directory(block_id, message),
+            Message::PreviewInput => {
+                let location = self.current_input.trim();
+                if location.is_empty() {
+                    self.status_messages.push("Type a file to preview, such as src/main.rs:42".to_string(), std::time::Instant::now());
+                    return Command::none();
+                }
+                let (path, line) = file_preview::parse_location(location);
+                let path = PathBuf::from(path);
+                self.current_input.clear();
+                self.update(Message::PreviewFile(path, line))
+            }
+            Message::PreviewFile(path, line) => {
+                let path = self.shell_manager.cwd().join(path);
+                Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            file_preview::FilePreview::read(&path, line).map_err(|e| format!("Cannot preview {}: {}", path.display(), e))
+                        })
+                        .await
+                        .map_err(|e| e.to_string())
+                        .and_then(|result| result)
+                    },
+                    Message::PreviewRead,
+                )
+            }
+            Message::PreviewRead(result) => match result {
+                Ok(preview) => {
+                    // Put the marked line a few rows from the top
+                    let offset = preview.line.map_or(0.0, |line| line.saturating_sub(4) as f32 * block::PREVIEW_LINE_HEIGHT);
+                    let block = Block::new_preview(preview);
+                    let scroll_id = block::preview_scrollable_id(block.id);
+                    self.blocks.push(block);
+                    self.scroll.jump_to_bottom();
+                    Command::batch([
+                        scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END),
+                        scrollable::scroll_to(scroll_id, scrollable::AbsoluteOffset { x: 0.0, y: offset }),
+                    ])
+                }
+                Err(e) => {
+                    self.blocks.push(Block::new_error(e));
+                    self.follow_output(1)
+                }
+            },
+            Message::EditFile(path) => self.edit_file(path, None, None),
+            Message::OpenOutputLink(target) => {
+                let behavior = self.config.preferences.terminal.hyperlink_behavior;
+                let held = self.modifiers.control() || self.modifiers.logo();
+                if behavior == config::HyperlinkBehavior::CtrlClick && !held {
+                    self.status_messages.push("Hold Ctrl and click to open links".to_string(), std::time::Instant::now());
+                    return Command::none();
+                }
+                match target {
+                    links::LinkTarget::Url(url) => self.update(Message::OpenLink(url)),
+                    links::LinkTarget::Path { path, line, column } => match self.config.preferences.terminal.open_paths_in {
+                        config::PathLinkAction::Preview => self.update(Message::PreviewFile(path, line)),
+                        config::PathLinkAction::Editor => self.edit_file(path, line, column),
+                    },
+                }
+            }
+            Message::OpenGitStatus => {
+                let dir = self.shell_manager.cwd().to_path_buf();
+                Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || git_status::read(&dir))
+                            .await
+                            .map_err(|e| e.to_string())
+                            .and_then(|result| result.map_err(|e| e.to_string()))
+                    },
+                    Message::GitPanelRead,
+                )
+            }
+            Message::GitPanelRead(result) => {
+                let block = match result {
+                    Ok(status) => Block::new_git_status(status),
+                    Err(e) => Block::new_error(e),
+                };
+                self.blocks.push(block);
+                self.scroll.jump_to_bottom();
+                scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+            }
+            Message::GitChanged => self.refresh_git(self.shell_manager.cwd().to_path_buf()),
+            Message::GitStatusRead(dir, result) => {
+                if dir == self.shell_manager.cwd() {
+                    self.git_status = result.as_ref().ok().cloned();
+                }
+                if let Ok(status) = result {
+                    for block in &mut self.blocks {
+                        if let BlockContent::GitStatus(ref mut shown) = block.content {
+                            if shown.root == status.root {
+                                *shown = status.clone();
+                            }
+                        }
+                    }
+                }
+                Command::none()
+            }
+            Message::Git(root, action) => {
+                if !matches!(action, git_status::GitAction::Diff { .. }) {
+                    if let Err(e) = self.read_only.check() {
+                        self.status_messages.push(e.to_string(), std::time::Instant::now());
+                        return Command::none();
+                    }
+                }
+                let (applied_in, applied) = (root.clone(), action.clone());
+                Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || git_status::apply(&applied_in, &applied))
+                            .await
+                            .map_err(|e| e.to_string())
+                            .and_then(|result| result.map_err(|e| e.to_string()))
+                    },
+                    move |result| Message::GitActionDone(root, action, result),
+                )
+            }
+            Message::GitActionDone(root, action, result) => match (action, result) {
+                (git_status::GitAction::Diff { path, staged }, Ok(patch)) => {
+                    let patch = patch.unwrap_or_default();
+                    let command = if staged { format!("git diff --cached -- {}", path) } else { format!("git diff -- {}", path) };
+                    let body = if patch.is_empty() { "No changes".to_string() } else { format!("```diff\n{}```", patch) };
+                    self.blocks.push(Block::new_info(format!("`{}`\n\n{}", command, body)));
+                    self.scroll.jump_to_bottom();
+                    scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+                }
+                (_, Ok(_)) => self.refresh_git(root),
+                (action, Err(e)) => {
+                    let failed = match action {
+                        git_status::GitAction::Stage(path) => format!("Cannot stage {}: {}", path, e),
+                        git_status::GitAction::Unstage(path) => format!("Cannot unstage {}: {}", path, e),
+                        git_status::GitAction::Diff { path, .. } => format!("Cannot diff {}: {}", path, e),
+                    };
+                    self.blocks.push(Block::new_error(failed));
+                    self.follow_output(1)
+                }
+            },
+            Message::ListContainers => Command::perform(
+                async {
+                    tokio::task::spawn_blocking(containers::list)
+                        .await
+                        .map_err(|e| e.to_string())
+                        .and_then(|result| result.map_err(|e| e.to_string()))
+                },
+                Message::ContainersListed,
+            ),
+            Message::ContainersListed(result) => {
+                let block = match &result {
+                    Ok(listed) if listed.is_empty() => Block::new_info("No running containers".to_string()),
+                    Ok(listed) => {
+                        let lines: Vec<String> = listed.iter().map(|container| container.summary()).collect();
+                        Block::new_info(format!(
+                            "{}\n\nPick one under Containers in the command palette to run commands in it.",
+                            lines.join("\n")
+                        ))
+                    }
+                    Err(e) => Block::new_error(format!("Cannot list containers: {}", e)),
+                };
+                self.containers = match result {
+                    Ok(listed) => listed,
+                    Err(_) => Vec::new(),
+                };
+                self.blocks.push(block);
+                self.scroll.jump_to_bottom();
+                scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+            }
+            Message::ExecInContainer(target) => {
+                let notice = match &target {
+                    Some(container) => format!("New commands run in container {}", container.name),
+                    None => "New commands run on this machine".to_string(),
+                };
+                self.exec_container = target;
+                self.status_messages.push(notice, std::time::Instant::now());
+                Command::none()
+            }
+            Message::ContainerLogs(container) => {
+                let command = container.logs_line();
+                let mut block = Block::new_command(command.clone());
+                block.container = Some(container.name.clone());
+                self.run_in_block(block, command, std::collections::HashMap::new())
+            }
+            Message::RequestContainerAction(container, action) => {
+                if let Err(e) = self.read_only.check() {
+                    self.status_messages.push(e.to_string(), std::time::Instant::now());
+                    return Command::none();
+                }
+                self.pending_container_action = Some((container, action));
+                Command::none()
+            }
+            Message::ConfirmContainerAction => {
+                let Some((container, action)) = self.pending_container_action.take() else {
+                    return Command::none();
+                };
+                let name = container.name.clone();
+                Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || action.run(&container))
+                            .await
+                            .map_err(|e| e.to_string())
+                            .and_then(|result| result.map_err(|e| e.to_string()))
+                    },
+                    move |result| Message::ContainerActionFinished(name, action, result),
+                )
+            }
+            Message::CancelContainerAction => {
+                self.pending_container_action = None;
+                Command::none()
+            }
+            Message::ContainerActionFinished(name, action, result) => {
+                match result {
+                    Ok(()) => {
+                        let stopped = action == containers::ContainerAction::Stop
+                            && self.exec_container.as_ref().is_some_and(|container| container.name == name);
+                        if stopped {
+                            self.exec_container = None;
+                        }
+                        let notice = match stopped {
+                            true => format!("{} {}; new commands run on this machine", action.done(), name),
+                            false => format!("{} {}", action.done(), name),
+                        };
+                        self.status_messages.push(notice, std::time::Instant::now());
+                        Command::none()
+                    }
+                    Err(e) => {
+                        self.blocks.push(Block::new_error(format!("Cannot {} {}: {}", action.verb().to_lowercase(), name, e)));
+                        self.follow_output(1)
+                    }
+                }
+            }
+            Message::JumpToPrompt(step) => self.jump_to_prompt(step),
+            Message::SignalJob(block_id, signal) => {
+                let Some(job) = self.pty.jobs().get(block_id) else {
+                    self.status_messages.push("That command has already exited", std::time::Instant::now());
+                    return Command::none();
+                };
+                let notice = match signal.send(job.pid) {
+                    Ok(()) => format!("{} sent to {}", signal.verb(), job.command),
+                    Err(e) => format!("Cannot signal {}: {}", job.command, e),
+                };
+                self.status_messages.push(notice, std::time::Instant::now());
+                Command::none()
+            }
+            Message::ToggleAiSidebar => {
+                self.ai_sidebar = match self.ai_sidebar {
+                    Some(_) => None,
+                    None => Some(ai_sidebar::AiSidebar::new(self.saved_conversations(), self.active_conversation_id())),
+                };
+                Command::none()
+            }
+            Message::AiSidebar(message) => {
+                let Some(sidebar) = self.ai_sidebar.as_mut() else {
+                    return Command::none();
+                };
+                match sidebar.update(message) {
+                    Some(ai_sidebar::SidebarAction::Open(id)) => self.open_conversation(id),
+                    Some(ai_sidebar::SidebarAction::Rename(id, title)) => {
+                        if let Some(store) = &self.conversation_store {
+                            if let Err(e) = store.rename(id, &title) {
+                                self.status_messages.push(format!("Could not rename the conversation: {}", e), std::time::Instant::now());
+                            }
+                        }
+                        // The open conversation keeps the title when it's saved again
+                        if self.active_conversation_id() == Some(id) {
+                            if let Some(tree) = self.agent_mode.as_mut().and_then(|agent| agent.conversations.as_mut()) {
+                                let title = title.trim();
+                                tree.root_mut().metadata.title = (!title.is_empty()).then(|| title.to_string());
+                            }
+                        }
+                        self.refresh_ai_sidebar();
+                        Command::none()
+                    }
+                    Some(ai_sidebar::SidebarAction::Delete(id)) => {
+                        if let Some(Err(e)) = self.conversation_store.as_ref().map(|store| store.delete(id)) {
+                            self.status_messages.push(format!("Could not delete the conversation: {}", e), std::time::Instant::now());
+                        }
+                        if self.active_conversation_id() == Some(id) {
+                            self.start_fresh_conversation();
+                        }
+                        self.refresh_ai_sidebar();
+                        self.follow_output(1)
+                    }
+                    Some(ai_sidebar::SidebarAction::NewConversation) => {
+                        if self.agent_enabled {
+                            self.start_fresh_conversation();
+                            self.refresh_ai_sidebar();
+                            self.follow_output(1)
+                        } else {
+                            self.update(Message::ToggleAgentMode)
+                        }
+                    }
+                    Some(ai_sidebar::SidebarAction::Close) => {
+                        self.ai_sidebar = None;
+                        Command::none()
+                    }
+                    None => Command::none(),
+                }
+            }
+            Message::OpenSessionExport => {
+                self.session_export_prompt = Some(session_export::SessionExportPrompt::new(
+                    &block_export::default_dir(),
+                    chrono::Local::now(),
+                ));
+                Command::none()
+            }
+            Message::SwitchEnvProfile(name) => {
+                if let Err(e) = self.apply_env_profile(name.clone()) {
+                    self.blocks.push(Block::new_error(e));
+                    return self.follow_output(1);
+                }
+                if let Err(e) = self.config.save() {
+                    log::warn!("Could not save the active env profile: {}", e);
+                }
+                let notice = match name {
+                    Some(name) => format!("Using env profile {}", name),
+                    None => "No env profile".to_string(),
+                };
+                self.status_messages.push(notice, std::time::Instant::now());
+                Command::none()
+            }
+            Message::SwitchConfigProfile(name) => {
+                let profile = config::ConfigProfileManager::new().and_then(|manager| manager.load(&name));
+                let config = match profile {
+                    Ok(profile) => profile.apply_to(&self.config, &name),
+                    Err(e) => {
+                        self.blocks.push(Block::new_error(format!("Cannot switch to config profile {}: {}", name, e)));
+                        return self.follow_output(1);
+                    }
+                };
+                if let Err(e) = config.save() {
+                    log::warn!("Could not save config profile {} as the config: {}", name, e);
+                }
+                // Blocks, tabs and panes stay; only preferences and theme change
+                let applied = self.apply_config(config);
+                self.sync_settings_view();
+                self.status_messages.push(format!("Using config profile {}", name), std::time::Instant::now());
+                applied
+            }
+            Message::Palette(message) => {
+                let Some(palette) = self.palette.as_mut() else {
+                    return Command::none();
+                };
+                match palette.update(message) {
+                    Some(PaletteAction::Run(command)) => {
+                        self.palette = None;
+                        self.current_input = command;
+                        self.update(Message::ExecuteCommand)
+                    }
+                    Some(PaletteAction::RunWorkflow(arguments)) => {
+                        match self.palette.take().and_then(|palette| palette.workflow().cloned()) {
+                            Some(workflow) => self.start_workflow_run(workflow, arguments),
+                            None => Command::none(),
+                        }
+                    }
+                    Some(PaletteAction::Offer(command)) => {
+                        self.palette = None;
+                        self.current_input = command;
+                        text_input::focus(command_input_id())
+                    }
+                    Some(PaletteAction::ShowBlock(block_id)) => {
+                        self.palette = None;
+                        self.show_block(block_id)
+                    }
+                    Some(PaletteAction::ShowMessage(message_id)) => {
+                        self.palette = None;
+                        self.show_message(message_id)
+                    }
+                    Some(PaletteAction::Action(id)) => {
+                        self.palette = None;
+                        match self.actions.get(&id) {
+                            Some(action) => Command::perform(action.run(), |message| message),
+                            None => Command::none(),
+                        }
+                    }
+                    Some(PaletteAction::Close) => {
+                        self.palette = None;
+                        Command::none()
+                    }
+                    None => Command::none(),
+                }
+            }
+            Message::BlockPressed(block_id) => {
+                self.focused_block = Some(block_id);
+                self.dragging_block = Some(block_id);
+                Command::none()
+            }
+            Message::BlockReleased(target) => match self.dragging_block.take() {
+                Some(dragged) if dragged != target => {
+                    let index = block::move_block_to(&mut self.blocks, dragged, target);
+                    self.scroll_to_moved_block(index)
+                }
+                _ => Command::none(),
+            },
+            Message::DragCancelled => {
+                self.dragging_block = None;
+                Command::none()
+            }
+            Message::MoveFocusedBlock(movement) => match self.focused_block {
+                Some(block_id) => {
+                    let index = block::move_block(&mut self.blocks, block_id, movement);
+                    self.scroll_to_moved_block(index)
+                }
+                None => Command::none(),
+            },
+            Message::SplitPane(direction) => {
+                let fresh = PaneState { cwd: self.shell_manager.cwd().to_path_buf(), ..Default::default() };
+                let mut current = self.take_pane_state();
+                self.panes.split(direction, &mut current, fresh);
+                self.load_pane_state(current)
+            }
+            Message::ClosePane => {
+                let mut current = self.take_pane_state();
+                let closed = self.panes.close(&mut current);
+                let command = self.load_pane_state(current);
+                let Some(closed) = closed else {
+                    self.status_messages.push("This is the only pane", std::time::Instant::now());
+                    return command;
+                };
+                // Its commands keep running, but nothing is left to show their output
+                for block in &closed.blocks {
+                    self.stop_tee(block.id);
+                    self.bell_detectors.remove(&block.id);
+                    self.prompt_trackers.remove(&block.id);
+                }
+                command
+            }
+            Message::FocusPane(pane) => {
+                let mut current = self.take_pane_state();
+                self.panes.focus(pane, &mut current);
+                self.load_pane_state(current)
+            }
+            Message::MovePaneFocus(direction) => match self.panes.neighbor(direction) {
+                Some(pane) => self.update(Message::FocusPane(pane)),
+                None => Command::none(),
+            },
+            Message::OpenBlockSearch => {
+                if self.block_search.is_none() {
+                    self.block_search = Some(block_search::BlockSearch::default());
+                }
+                Command::batch([
+                    text_input::focus(block_search_input_id()),
+                    text_input::select_all(block_search_input_id()),
+                ])
+            }
+            Message::BlockSearchChanged(query) => {
+                if let Some(search) = self.block_search.as_mut() {
+                    search.set_query(query, block::visible(&self.blocks, self.block_filter));
+                }
+                self.show_current_match()
+            }
+            Message::BlockSearchRegexToggled(regex) => {
+                if let Some(search) = self.block_search.as_mut() {
+                    search.set_regex(regex, block::visible(&self.blocks, self.block_filter));
+                }
+                self.show_current_match()
+            }
+            Message::NextBlockMatch => {
+                if let Some(search) = self.block_search.as_mut() {
+                    search.refresh(block::visible(&self.blocks, self.block_filter));
+                    search.next();
+                }
+                self.show_current_match()
+            }
+            Message::PreviousBlockMatch => {
+                if let Some(search) = self.block_search.as_mut() {
+                    search.refresh(block::visible(&self.blocks, self.block_filter));
+                    search.previous();
+                }
+                self.show_current_match()
+            }
+            Message::SetBlockFilter(filter) => {
+                self.block_filter = filter;
+                if let Some(search) = self.block_search.as_mut() {
+                    search.refresh(block::visible(&self.blocks, filter));
+                }
+                self.scroll.jump_to_bottom();
+                scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+            }
+            Message::CloseBlockSearch => {
+                self.block_search = None;
+                text_input::focus(command_input_id())
+            }
+            Message::NewTab => {
+                let fresh = TabState {
+                    pane: PaneState { cwd: self.shell_manager.cwd().to_path_buf(), ..Default::default() },
+                    env_profile: self.config.active_env_profile.clone(),
+                    ..Default::default()
+                };
+                let mut current = self.take_tab_state();
+                self.tabs.open(&mut current, fresh);
+                self.load_tab_state(current)
+            }
+            Message::SelectTab(index) => {
+                if index == self.tabs.active() || index >= self.tabs.count() {
+                    return Command::none();
+                }
+                let mut current = self.take_tab_state();
+                self.tabs.select(index, &mut current);
+                self.load_tab_state(current)
+            }
+            Message::CloseTab => {
+                let running = tabs::has_running(&self.blocks)
+                    || self.panes.parked().any(|(_, pane)| tabs::has_running(&pane.blocks));
+                if running && self.config.preferences.terminal.confirm_before_closing {
+                    self.pending_tab_close = true;
+                    return Command::none();
+                }
+                self.update(Message::ConfirmCloseTab)
+            }
+            Message::ConfirmCloseTab => {
+                self.pending_tab_close = false;
+                self.renaming_tab = None;
+                // Closing the only tab closes the window
+                if self.tabs.count() == 1 {
+                    return self.update(Message::CloseRequested);
+                }
+                let mut current = self.take_tab_state();
+                let closed = self.tabs.close(&mut current);
+                let command = self.load_tab_state(current);
+                if let Some(closed) = closed {
+                    let parked = closed.panes.parked().flat_map(|(_, pane)| pane.blocks.iter());
+                    for block_id in closed.pane.blocks.iter().chain(parked).map(|block| block.id) {
+                        self.stop_tee(block_id);
+                        self.bell_detectors.remove(&block_id);
+                        self.prompt_trackers.remove(&block_id);
+                    }
+                }
+                command
+            }
+            Message::CancelCloseTab => {
+                self.pending_tab_close = false;
+                Command::none()
+            }
+            Message::StartTabRename(index) => {
+                let Some(tab) = self.tabs.iter().nth(index) else {
+                    return Command::none();
+                };
+                let title = match tab.state() {
+                    Some(state) => tabs::title(tab.name(), &state.pane.blocks),
+                    None => tabs::title(tab.name(), &self.blocks),
+                };
+                self.renaming_tab = Some((index, title));
+                Command::batch([text_input::focus(tab_rename_input_id()), text_input::select_all(tab_rename_input_id())])
+            }
+            Message::TabRenameChanged(name) => {
+                if let Some((_, value)) = self.renaming_tab.as_mut() {
+                    *value = name;
+                }
+                Command::none()
+            }
+            Message::FinishTabRename => {
+                if let Some((index, name)) = self.renaming_tab.take() {
+                    self.tabs.rename(index, &name);
+                }
+                text_input::focus(command_input_id())
+            }
+            Message::SortBlocksByTime => {
+                block::sort_by_time(&mut self.blocks);
+                Command::none()
+            }
+            Message::WindowResized(width) => {
+                self.window_width = Some(width as f32);
+                self.relayout()
+            }
+            Message::Zoom(zoom) => {
+                let ui = &mut self.config.preferences.ui;
+                ui.zoom_level = zoom.apply(ui.zoom_level);
+                let zoom_level = ui.zoom_level;
+                // Open settings would otherwise save the old level back
+                self.settings_view.config.preferences.ui.zoom_level = zoom_level;
+                if let Err(e) = self.config.save() {
+                    log::warn!("Could not save the zoom level: {}", e);
+                }
+                self.status_messages.push(format!("Zoom {:.0}%", zoom_level * 100.0), std::time::Instant::now());
+                self.apply_output_font()
+            }
+            Message::OpenLink(url) => {
+                if let Err(e) = open::that_detached(&url) {
+                    self.blocks.push(Block::new_error(format!("Cannot open {}: {}", url, e)));
+                    return self.follow_output(1);
+                }
+                Command::none()
+            }
+            Message::RunSnippet(block_id, index) => self.run_snippet(block_id, index),
+            Message::WindowFocusChanged(focused) => {
+                self.window_focused = focused;
+                Command::none()
+            }
+            Message::CommandNotified(block_id, result) => match result {
+                Ok(()) => Command::none(),
+                // No notification daemon; the bell is the next best thing
+                Err(e) => {
+                    log::debug!("Command notification failed: {}", e);
+                    self.ring_bell(block_id)
+                }
+            },
+            Message::CloseRequested => {
+                self.save_session();
+                iced::window::close(iced::window::Id::MAIN)
+            }
+            Message::ToggleToolbarMenu => {
+                self.toolbar_menu_open = !self.toolbar_menu_open;
+                Command::none()
+            }
+            Message::Tick => {
+                self.status_frame = self.status_frame.wrapping_add(1);
+                self.status_messages.expire(std::time::Instant::now());
+                if self.bell_flash.is_some_and(|(_, until)| until <= std::time::Instant::now()) {
+                    self.bell_flash = None;
+                }
+                Command::none()
+            }
+            Message::ConfirmShare => {
+                let Some((block_id, content)) = self.share_preview.take() else {
+                    return Command::none();
+                };
+                let description = self.blocks
+                    .iter()
+                    .find(|b| b.id == block_id)
+                    .map(|b| self.redactor.redact(&b.title()))
+                    .unwrap_or_default();
+                let sharer = share::Sharer::new(self.config.preferences.share.clone());
+                Command::perform(
+                    async move { sharer.share(&content, &description).await.map_err(|e| e.to_string()) },
+                    move |result| Message::Shared(block_id, result)
+                )
+            }
+            Message::CancelShare => {
+                self.share_preview = None;
+                Command::none()
+            }
+            Message::TeePathChanged(path) => {
+                if let Some(prompt) = &mut self.tee_prompt {
+                    prompt.path = path;
+                }
+                Command::none()
+            }
+            Message::BrowseTeePath => {
+                let Some(prompt) = &self.tee_prompt else {
+                    return Command::none();
+                };
+                let path = PathBuf::from(&prompt.path);
+                let mut dialog = rfd::AsyncFileDialog::new();
+                if let Some(dir) = path.parent().filter(|dir| dir.is_dir()) {
+                    dialog = dialog.set_directory(dir);
+                }
+                if let Some(name) = path.file_name() {
+                    dialog = dialog.set_file_name(name.to_string_lossy());
+                }
+                Command::perform(
+                    async move { dialog.save_file().await.map(|file| file.path().to_path_buf()) },
+                    Message::TeePathPicked,
+                )
+            }
+            Message::TeePathPicked(path) => {
+                if let (Some(prompt), Some(path)) = (&mut self.tee_prompt, path) {
+                    prompt.path = path.to_string_lossy().to_string();
+                }
+                Command::none()
+            }
+            Message::TeeBackfillToggled(backfill) => {
+                if let Some(prompt) = &mut self.tee_prompt {
+                    prompt.options.backfill = backfill;
+                }
+                Command::none()
+            }
+            Message::TeeStripAnsiToggled(strip_ansi) => {
+                if let Some(prompt) = &mut self.tee_prompt {
+                    prompt.options.strip_ansi = strip_ansi;
+                }
+                Command::none()
+            }
+            Message::ConfirmTee => {
+                if let Some(prompt) = self.tee_prompt.take() {
+                    self.start_tee(prompt);
+                }
+                Command::none()
+            }
+            Message::CancelTee => {
+                self.tee_prompt = None;
+                Command::none()
+            }
+            Message::ExportPathChanged(path) => {
+                if let Some(prompt) = &mut self.export_prompt {
+                    prompt.path.set_value(path);
+                }
+                Command::none()
+            }
+            Message::ExportFormatSelected(format) => {
+                if let Some(prompt) = &mut self.export_prompt {
+                    prompt.set_format(format);
+                }
+                Command::none()
+            }
+            Message::ConfirmExport => {
+                if let Some(prompt) = self.export_prompt.take() {
+                    self.export_block(prompt);
+                }
+                Command::none()
+            }
+            Message::CancelExport => {
+                self.export_prompt = None;
+                Command::none()
+            }
+            Message::SessionExportPathChanged(path) => {
+                if let Some(prompt) = &mut self.session_export_prompt {
+                    prompt.path.set_value(path);
+                }
+                Command::none()
+            }
+            Message::SessionExportFormatSelected(format) => {
+                if let Some(prompt) = &mut self.session_export_prompt {
+                    prompt.set_format(format);
+                }
+                Command::none()
+            }
+            Message::ConfirmSessionExport => {
+                if let Some(prompt) = self.session_export_prompt.take() {
+                    let path = prompt.target();
+                    let notice = match session_export::export_to(&self.blocks, prompt.format, &path) {
+                        Ok(()) => Block::new_info(format!("Exported {} blocks to {}", self.blocks.len(), path.display())),
+                        Err(e) => Block::new_error(format!("Export to {} failed: {}", path.display(), e)),
+                    };
+                    self.blocks.push(notice);
+                }
+                Command::none()
+            }
+            Message::CancelSessionExport => {
+                self.session_export_prompt = None;
+                Command::none()
+            }
+            Message::RequestClear(target) => {
+                self.settings_open = false;
+                self.pending_clear = Some(target);
+                Command::none()
+            }
+            Message::ConfirmClear => {
+                let Some(target) = self.pending_clear.take() else {
+                    return Command::none();
+                };
+                let notice = match self.clear_state(target) {
+                    Ok(report) => report.summary(target),
+                    Err(e) => format!("Could not clear {}: {}", target.describe(), e),
+                };
+                self.status_messages.push(notice, std::time::Instant::now());
+                Command::none()
+            }
+            Message::CancelClear => {
+                self.pending_clear = None;
+                Command::none()
+            }
+            Message::ConfirmCodeRun => {
+                let Some((source, commands)) = self.pending_code_run.take() else {
+                    return Command::none();
+                };
+                self.start_code_run(source, commands)
+            }
+            Message::CancelCodeRun => {
+                self.pending_code_run = None;
+                Command::none()
+            }
+            Message::ConfirmAiContext => {
+                let Some((_, context)) = self.ai_context_preview.take() else {
+                    return Command::none();
+                };
+                if !self.ai_allowed(AiRequest::AgentPrompt) {
+                    return Command::none();
+                }
+                let prompt = self.redactor.redact(&context.prompt());
+                self.handle_agent_command(prompt)
+            }
+            Message::SummarizeAiContext => {
+                let Some((block_id, context)) = self.ai_context_preview.take() else {
+                    return Command::none();
+                };
+                let (Some(agent), Some(block)) = (&self.agent_mode, self.blocks.iter().find(|b| b.id == block_id)) else {
+                    return Command::none();
+                };
+                let client = agent.ai_client.clone();
+                let lines: Vec<_> = block
+                    .output_lines()
+                    .into_iter()
+                    .map(|line| context::OutputLine { text: self.redactor.redact(&line.text), ..line })
+                    .collect();
+                let limits = self.config.preferences.ai_context.clone();
+                self.status_messages.push(
+                    format!("Summarizing {} lines of output…", i18n::format_number(context.total_lines as u64)),
+                    std::time::Instant::now(),
+                );
+                let command = context.command;
+                Command::perform(
+                    async move {
+                        context::summarize(&client, &command, &lines, &limits)
+                            .await
+                            .map(|summary| context::summary_prompt(&command, lines.len(), &summary))
+                            .map_err(|e| e.to_string())
+                    },
+                    move |result| Message::AiContextSummarized(block_id, result),
+                )
+            }
+            Message::CancelAiContext => {
+                self.ai_context_preview = None;
+                Command::none()
+            }
+            Message::AiContextSummarized(_, result) => match result {
+                Ok(prompt) => {
+                    if !self.ai_allowed(AiRequest::AgentPrompt) {
+                        return Command::none();
+                    }
+                    self.handle_agent_command(prompt)
+                }
+                Err(e) => {
+                    self.blocks.push(Block::new_error(format!("Could not summarize the output: {}", e)));
+                    self.follow_output(1)
+                }
+            },
+            Message::CrashReportConsent(granted) => {
+                self.config.preferences.crash_reports.consent = if granted {
+                    config::CrashReportConsent::Granted
+                } else {
+                    config::CrashReportConsent::Denied
+                };
+                if let Err(e) = self.config.save() {
+                    self.blocks.push(Block::new_error(format!("Could not save crash report choice: {}", e)));
+                    return self.follow_output(1);
+                }
+                Command::none()
+            }
+            Message::DismissBudgetWarning => {
+                self.budget_warning_dismissed = self.active_conversation_id();
+                Command::none()
+            }
+            Message::Shared(block_id, result) => match result {
+                Ok(record) => {
+                    let url = record.url.clone();
+                    if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                        block.shared = Some(record);
+                    }
+                    self.status_messages.push("Share link copied to clipboard", std::time::Instant::now());
+                    iced::clipboard::write(url)
+                }
+                Err(e) => {
+                    self.blocks.push(Block::new_error(format!("Share failed: {}", e)));
+                    self.follow_output(1)
+                }
+            },
+            Message::Unshared(block_id, result) => match result {
+                Ok(()) => {
+                    if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                        block.shared = None;
+                    }
+                    Command::none()
+                }
+                Err(e) => {
+                    self.blocks.push(Block::new_error(format!("Unshare failed: {}", e)));
+                    self.follow_output(1)
+                }
+            },
+            Message::ToggleSettings => {
+                self.settings_open = !self.settings_open;
+                if self.settings_open {
+                    self.settings_view = settings::SettingsView::new(self.config.clone())
+                        .with_tab(self.last_settings_tab.clone());
+                    let manifests = self.settings_view.installed_plugins.clone();
+                    let check_updates = Command::perform(plugin_api::update::check_updates(manifests), |results| {
+                        Message::PluginUpdatesChecked(
+                            results
+                                .into_iter()
+                                .filter_map(|(_, result)| result.ok().flatten())
+                                .map(|update| (update.id, update.latest.version))
+                                .collect(),
+                        )
+                    });
+                    return Command::batch([self.discover_models(), check_updates]);
+                }
+                Command::none()
+            }
+            Message::SettingsMessage(settings::SettingsMessage::Clear(target)) => self.update(Message::RequestClear(target)),
+            Message::SettingsMessage(settings::SettingsMessage::RefreshModels) => {
+                if let Some(base_url) = self.ollama_base_url() {
+                    self.model_cache.invalidate(&base_url);
+                }
+                self.settings_view.discovered_models = None;
+                self.discover_models()
+            }
+            Message::PluginUpdatesChecked(updates) => {
+                self.settings_view.plugin_updates = updates.into_iter().collect();
+                Command::none()
+            }
+            Message::ModelsDiscovered(base_url, result) => {
+                self.model_request = None;
+                if let Ok(models) = &result {
+                    self.model_cache.insert(base_url.clone(), models.clone(), std::time::Instant::now());
+                }
+                if let Err(error) = &result {
+                    if self.settings_open {
+                        self.status_messages.push(error.clone(), std::time::Instant::now());
+                    } else {
+                        self.blocks.push(Block::new_error(error.clone()));
+                    }
+                }
+                if self.ollama_base_url().as_deref() == Some(base_url.as_str()) {
+                    self.settings_view.discovered_models = Some(result);
+                    Command::none()
+                } else {
+                    // The URL changed while the old one was being asked
+                    self.discover_models()
+                }
+            }
+            Message::SettingsMessage(settings_message) => {
+                let mut applied = Vec::new();
+                if let Some(config) = self.settings_view.update(settings_message) {
+                    applied.push(self.apply_config(config));
+                }
+                self.last_settings_tab = self.settings_view.active_tab.clone();
+                applied.push(self.discover_models());
+                Command::batch(applied)
+            }
+            Message::ConfigFileChanged => {
+                let Some(manager) = &self.config_manager else {
+                    return Command::none();
+                };
+                // A change comes back through `subscribe_changes` as `ConfigReloaded`
+                match manager.reload(&self.config) {
+                    Ok(_) => Command::none(),
+                    Err(e) => {
+                        self.blocks.push(Block::new_error(format!(
+                            "config.toml wasn't reloaded, the settings in use are unchanged: {}",
+                            e
+                        )));
+                        self.follow_output(1)
+                    }
+                }
+            }
+            Message::ConfigReloaded(update) => {
+                self.blocks.push(Block::new_info(format!(
+                    "Reloaded config.toml:\n\n```diff\n{}\n```",
+                    update.diff()
+                )));
+                let applied = self.apply_config(update.config.clone());
+                self.sync_settings_view();
+                let lines = update.changes.len() * 2 + 4;
+                Command::batch([applied, self.follow_output(lines)])
+            }
+            Message::CheckSystemAppearance => detect_system_appearance(),
+            Message::SystemAppearanceDetected(detected) => {
+                let appearance = &self.config.preferences.appearance;
+                if !appearance.follow_system || detected.is_none() || detected == self.system_appearance {
+                    return Command::none();
+                }
+                self.system_appearance = detected;
+                let name = match detected {
+                    Some(system_appearance::SystemAppearance::Light) => &appearance.light_theme,
+                    _ => &appearance.dark_theme,
+                };
+                if let Some(theme) = config::ThemeConfig::builtin_themes().into_iter().find(|t| &t.name == name) {
+                    // The open settings would otherwise save the previous theme back
+                    if self.settings_open {
+                        self.settings_view.config.theme = theme.clone();
+                    }
+                    self.config.theme = theme;
+                }
+                Command::none()
+            }
+            Message::KeyPressed { key, modifiers, captured } => {
+                let chord = keymap::KeyChord::from_key(&key, modifiers);
+                if let Some(chord) = chord.clone().filter(|_| self.settings_open && self.settings_view.keybinding_editor.is_recording()) {
+                    let recorded = settings::keybinding_editor::Message::KeyCaptured(chord);
+                    return self.update(Message::SettingsMessage(settings::SettingsMessage::KeyBindingEditor(recorded)));
+                }
+                // The unlocking key and hint labels aren't shortcuts
+                if let Some(chord) = chord.filter(|_| !self.locked && self.hint_mode.is_none()) {
+                    match self.keymap.press(chord, captured) {
+                        keymap::Press::Run(action) => return self.update(key_action_message(action)),
+                        keymap::Press::Pending => {
+                            let pending = format!("{} was pressed; waiting for the next key", self.keymap.pending());
+                            self.status_messages.push(pending, std::time::Instant::now());
+                            return Command::none();
+                        }
+                        keymap::Press::Abandoned(keys) => {
+                            self.status_messages.push(format!("{} isn't bound", keys), std::time::Instant::now());
+                            return Command::none();
+                        }
+                        keymap::Press::Unbound => {}
+                    }
+                }
+                // The input lets go of focus on Esc, and the prompt's normal mode takes over
+                let escape = matches!(key.as_ref(), iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape));
+                if escape && captured && modifiers.is_empty() && self.vim_prompt() && self.vim.mode() == vim::Mode::Insert {
+                    self.vim.escape(&self.current_input);
+                    return Command::none();
+                }
+                if captured {
+                    return Command::none();
+                }
+                self.handle_key_press(key)
+            }
+            Message::StdinInput(bytes) => {
+                // Sent straight to the PTY: typed input is never added to
+                // history or the block, only what the command echoes is
+                let Some(block_id) = self.stdin_target() else {
+                    return Command::none();
+                };
+                if let Err(e) = self.pty.write(block_id, &bytes) {
+                    self.status_messages.push(format!("Cannot send input: {}", e), std::time::Instant::now());
+                }
+                Command::none()
+            }
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                Command::none()
+            }
+            Message::HistoryUp => {
+                if let Some(entry) = self.history.previous().map(str::to_string) {
+                    self.set_input(&entry);
+                }
+                Command::none()
+            }
+            Message::HistoryDown => {
+                if let Some(entry) = self.history.next().map(str::to_string) {
+                    self.set_input(&entry);
+                }
+                Command::none()
+            }
+            // Ctrl+R belongs to the interactive command's own history search
+            Message::StartHistorySearch if self.stdin_target().is_some() => Command::none(),
+            Message::StartHistorySearch => {
+                match &mut self.history_search {
+                    Some(search) => {
+                        let count = self.history.search(&search.query, history::SEARCH_CANDIDATES).len();
+                        search.move_selection(1, count);
+                    }
+                    None => {
+                        self.history_search = Some(history::HistorySearch::new(self.current_input.clone()));
+                        self.suggestions.clear();
+                    }
+                }
+                text_input::focus(command_input_id())
+            }
+            Message::HistorySearchChanged(query) => {
+                if let Some(search) = &mut self.history_search {
+                    search.query = query;
+                    search.selected = 0;
+                }
+                Command::none()
+            }
+            Message::HistorySearchPick(index) => {
+                let Some(search) = self.history_search.take() else {
+                    return Command::none();
+                };
+                let entry = self
+                    .history
+                    .search(&search.query, history::SEARCH_CANDIDATES)
+                    .get(index)
+                    .map(|entry| entry.to_string())
+                    .unwrap_or(search.saved_input);
+                self.set_input(&entry);
+                Command::batch([text_input::focus(command_input_id()), text_input::move_cursor_to_end(command_input_id())])
+            }
+            Message::CancelHistorySearch => {
+                if let Some(search) = self.history_search.take() {
+                    self.current_input = search.saved_input;
+                }
+                Command::none()
+            }
+            Message::Complete { backwards } => {
+                if self.history_search.is_some() || self.stdin_target().is_some() {
+                    return Command::none();
+                }
+                // A candidate already in the input is cycled; anything else
+                // (including a completed directory) starts a new completion
+                let cycling = self.completion.as_ref().is_some_and(|completion| {
+                    completion.candidates.len() > 1
+                        && completion.selected.is_some_and(|i| completion.apply(i) == self.current_input)
+                });
+                if !cycling {
+                    let path_var = self
+                        .shell_manager
+                        .profile_env()
+                        .get("PATH")
+                        .map(std::ffi::OsString::from)
+                        .or_else(|| std::env::var_os("PATH"))
+                        .unwrap_or_default();
+                    self.completion = completion::complete(&self.current_input, self.shell_manager.cwd(), &path_var);
+                }
+                let Some(completion) = &mut self.completion else {
+                    return Command::none();
+                };
+                let index = completion.cycle(backwards);
+                self.current_input = completion.apply(index);
+                self.suggestions.clear();
+                // Nothing left to choose between
+                if completion.candidates.len() == 1 {
+                    self.completion = None;
+                }
+                Command::batch([text_input::focus(command_input_id()), text_input::move_cursor_to_end(command_input_id())])
+            }
+            Message::CompletionPicked(index) => {
+                let Some(completion) = self.completion.take() else {
+                    return Command::none();
+                };
+                if index < completion.candidates.len() {
+                    self.current_input = completion.apply(index);
+                }
+                Command::batch([text_input::focus(command_input_id()), text_input::move_cursor_to_end(command_input_id())])
+            }
+            Message::BlockAction(block_id, action) => {
+                self.handle_block_action(block_id, action)
+            }
+            _ => Command::none(),
+        }
+    }
+
+    fn view(&self) -> Element<Message> {
+        self.interactables.borrow_mut().clear();
+        if self.settings_open {
+            // Show settings view
+            return self.settings_view.view(self.responsive.is_compact()).map(Message::SettingsMessage);
+        }
+
+        if self.locked {
+            return container(
+                column![
+                    text("🔒 Locked after inactivity").size(20),
+                    text("Press any key to continue").size(14),
+                ]
+                .spacing(8)
+            )
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill)
+            .center_x()
+            .center_y()
+            .into();
+        }
+
+        let show_status_glyphs = self.config.preferences.ui.always_show_status_glyphs
+            || self.config.theme.vision.requires_status_glyphs();
+
+        let blocks_view = scrollable(
+            column(
+                block::visible(&self.blocks, self.block_filter)
+                    .into_iter()
+                    .map(|block| self.view_block(block, show_status_glyphs))
+                    .collect::<Vec<_>>()
+            )
+            .spacing(8)
+        )
+        .id(blocks_scrollable_id())
+        .on_scroll(Message::BlocksScrolled)
+        .height(iced::Length::Fill);
+
+        let blocks_view: Element<Message> = if self.panes.is_split() {
+            self.view_pane_tree(self.panes.tree(), &mut Some(blocks_view.into()), show_status_glyphs)
+        } else {
+            blocks_view.into()
+        };
+
+        let input_view = self.create_input_view();
+        let toolbar = self.create_toolbar();
+
+        let mut content = column![].spacing(8);
+        if let Some(tab_bar) = self.create_tab_bar() {
+            content = content.push(tab_bar);
+        }
+        content = content.push(toolbar);
+        if let Some(search) = &self.block_search {
+            content = content.push(self.create_block_search_bar(search));
+        }
+        content = content.push(self.create_filter_chips()).push(blocks_view);
+
+        // Anchored above the tail: offer a way back to the newest output
+        if let Some(indicator) = self.scroll.indicator() {
+            content = content.push(
+                container(
+                    button(text(indicator).size(12))
+                        .on_press(Message::JumpToLatest)
+                        .padding([4, 12])
+                )
+                .width(iced::Length::Fill)
+                .center_x()
+            );
+        }
+
+        if let Some(palette) = &self.palette {
+            let labels = self.register_palette_rows(palette);
+            content = content.push(container(palette.view(&labels).map(Message::Palette)).padding(12));
+        }
+
+        if let Some((_, preview)) = &self.share_preview {
+            content = content.push(self.create_share_preview(preview));
+        }
+
+        if let Some(target) = self.pending_clear {
+            content = content.push(self.create_clear_confirmation(target));
+        }
+
+        if self.pending_tab_close {
+            content = content.push(self.create_tab_close_confirmation());
+        }
+
+        if let Some((_, commands)) = &self.pending_code_run {
+            content = content.push(self.create_code_run_confirmation(commands));
+        }
+
+        if let Some((target, action)) = &self.pending_container_action {
+            content = content.push(self.create_container_action_confirmation(target, *action));
+        }
+
+        if let Some(call) = self.pending_tool_approvals.front() {
+            content = content.push(self.create_tool_approval(call));
+        }
+
+        if let Some(prompt) = &self.tee_prompt {
+            content = content.push(self.create_tee_prompt(prompt));
+        }
+
+        if let Some(prompt) = &self.export_prompt {
+            content = content.push(self.create_export_prompt(prompt));
+        }
+
+        if let Some(prompt) = &self.session_export_prompt {
+            content = content.push(self.create_session_export_prompt(prompt));
+        }
+
+        if let Some((_, context)) = &self.ai_context_preview {
+            content = content.push(self.create_ai_context_preview(context));
+        }
+
+        if self.config.preferences.crash_reports.consent == config::CrashReportConsent::NotAsked {
+            content = content.push(self.create_crash_report_prompt());
+        }
+
+        if let Some(warning) = self.create_budget_warning() {
+            content = content.push(warning);
+        }
+
+        content = content.push(input_view);
+
+        if self.config.preferences.ui.status_line.visible {
+            content = content.push(self.create_status_line());
+        }
+
+        match &self.ai_sidebar {
+            Some(sidebar) => row![
+                content.padding(16).width(iced::Length::Fill),
+                container(sidebar.view().map(Message::AiSidebar)).padding(16),
+            ]
+            .into(),
+            None => content.padding(16).into(),
+        }
+    }
+
+    fn subscription(&self) -> iced::Subscription<Message> {
+        let keys = iced::Subscription::batch([
+            // Every key press goes through the keymap, including those the
+            // focused input takes
+            iced::event::listen_with(|event, status| match event {
+                iced::Event::Keyboard(iced::keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                    Some(Message::KeyPressed { key, modifiers, captured: status == iced::event::Status::Captured })
+                }
+                _ => None,
+            }),
+            iced::event::listen_with(|event, _status| match event {
+                iced::Event::Window(_, iced::window::Event::Resized { width, .. }) => Some(Message::WindowResized(width)),
+                iced::Event::Window(_, iced::window::Event::Focused) => Some(Message::WindowFocusChanged(true)),
+                iced::Event::Window(_, iced::window::Event::Unfocused) => Some(Message::WindowFocusChanged(false)),
+                iced::Event::Window(_, iced::window::Event::CloseRequested) => Some(Message::CloseRequested),
+                iced::Event::Keyboard(iced::keyboard::Event::ModifiersChanged(modifiers)) => {
+                    Some(Message::ModifiersChanged(modifiers))
+                }
+                _ => None,
+            }),
+            // Right and End are taken by the focused input, so they're
+            // watched here rather than in on_key_press
+            iced::event::listen_with(|event, status| match (event, status) {
+                (
+                    iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                        key: iced::keyboard::Key::Named(
+                            iced::keyboard::key::Named::ArrowRight | iced::keyboard::key::Named::End,
+                        ),
+                        modifiers,
+                        ..
+                    }),
+                    iced::event::Status::Captured,
+                ) if modifiers.is_empty() => Some(Message::AcceptAutosuggestion),
+                _ => None,
+            }),
+            iced::time::every(IDLE_CHECK_INTERVAL).map(|_| Message::IdleCheck),
+        ]);
+
+        let mut subscriptions = vec![keys];
+        if self.stdin_target().is_some() {
+            subscriptions.push(iced::event::listen_with(|event, _status| match event {
+                iced::Event::Keyboard(iced::keyboard::Event::KeyPressed { key, modifiers, text, .. }) => {
+                    // Ctrl+Up and Ctrl+Down stay with prompt navigation
+                    let navigation = modifiers.control()
+                        && matches!(key, iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowUp | iced::keyboard::key::Named::ArrowDown));
+                    if navigation {
+                        return None;
+                    }
+                    pty::key_bytes(&key, modifiers, text.as_deref()).map(Message::StdinInput)
+                }
+                _ => None,
+            }));
+        }
+        // A release that no block picked up ends the drag without moving anything
+        if self.dragging_block.is_some() {
+            subscriptions.push(iced::event::listen_with(|event, status| match (event, status) {
+                (
+                    iced::Event::Mouse(iced::mouse::Event::ButtonReleased(iced::mouse::Button::Left)),
+                    iced::event::Status::Ignored,
+                ) => Some(Message::DragCancelled),
+                _ => None,
+            }));
+        }
+        if self.startup_command.is_some() {
+            subscriptions.push(iced::window::frames().map(|_| Message::FirstFrame));
+        }
+        if self.workflows.is_some() {
+            subscriptions.push(iced::time::every(SCHEDULE_CHECK_INTERVAL).map(|_| Message::ScheduleTick));
+        }
+        if self.config.preferences.appearance.follow_system {
+            subscriptions.push(iced::time::every(system_appearance::CHECK_INTERVAL).map(|_| Message::CheckSystemAppearance));
+        }
+        if let Some(git_dir) = &self.git_dir {
+            subscriptions.push(watch_git(git_dir.clone()));
+        }
+        if let Some(manager) = &self.config_manager {
+            subscriptions.push(watch_config(manager.clone()));
+        }
+        // Diagnostics refresh while a block shows them or the endpoint serves them
+        let diagnostics_open = self.blocks.iter().any(|b| matches!(b.content, BlockContent::Diagnostics(_)));
+        if diagnostics_open || self.config.preferences.diagnostics.api_port.is_some() {
+            subscriptions.push(iced::time::every(diagnostics::REFRESH_INTERVAL).map(|_| Message::RefreshDiagnostics));
+        }
+        // Output and input are event driven; the tick only runs for what changes with time
+        if let Some(interval) = self.tick_interest().interval() {
+            subscriptions.push(iced::time::every(interval).map(|_| Message::Tick));
+        }
+        iced::Subscription::batch(subscriptions)
+    }
+}
+
+impl NeoTerm {
+    /// `cd`, `pushd` or `popd`: commands run in the new directory from now on
+    fn change_directory(&mut self, command_line: String, dir_command: shell::DirCommand) -> Command<Message> {
+        match self.enter_directory(&dir_command) {
+            Ok(_) => {
+                let mut block = Block::new_command(command_line);
+                block.set_output(String::new(), 0);
+                self.blocks.push(block);
+            }
+            Err(e) => {
+                let name = command_line.split_whitespace().next().unwrap_or("cd");
+                self.blocks.push(Block::new_error(format!("{}: {}", name, e)));
+            }
+        }
+        self.follow_output(1)
+    }
+
+    /// Make the directory `dir_command` leads to the working directory
+    fn enter_directory(&mut self, dir_command: &shell::DirCommand) -> Result<PathBuf, shell::DirError> {
+        let dir = self.shell_manager.change_directory(dir_command)?;
+        // The process follows, so blocks, hooks and the status line see it too
+        std::env::set_current_dir(&dir).map_err(|_| shell::DirError::NotFound(dir.clone()))?;
+        self.sync_git(&dir);
+        Ok(dir)
+    }
+
+    /// Resolve the output font from the preferences again, and redo the
+    /// cell metrics that depend on it
+    /// Show a config changed outside settings in the open settings view,
+    /// unless that would throw away unsaved changes
+    fn sync_settings_view(&mut self) {
+        if !self.settings_open {
+            return;
+        }
+        if self.settings_view.unsaved_changes {
+            self.status_messages.push(
+                "config.toml changed on disk; saving settings will overwrite it".to_string(),
+                std::time::Instant::now(),
+            );
+        } else {
+            self.settings_view =
+                settings::SettingsView::new(self.config.clone()).with_tab(self.settings_view.active_tab.clone());
+        }
+    }
+
+    /// Put a new config into effect, from settings or the edited file
+    fn apply_config(&mut self, config: AppConfig) -> Command<Message> {
+        let mut applied = Vec::new();
+        net::configure(&config.preferences.network);
+        i18n::set_locale(config.preferences.general.locale());
+        self.shell_manager.set_default_shell(config.preferences.general.default_shell.as_deref());
+        let privacy = &config.preferences.privacy;
+        self.history.set_limit(privacy.history_limit);
+        self.history.set_persistent(privacy.history_enabled && !privacy.incognito_mode);
+        let ai_changed = config.preferences.ai != self.config.preferences.ai;
+        let appearance_changed = config.preferences.appearance != self.config.preferences.appearance;
+        let (ui, previous_ui) = (&config.preferences.ui, &self.config.preferences.ui);
+        let font_changed = ui.font_family != previous_ui.font_family
+            || ui.font_size != previous_ui.font_size
+            || ui.zoom_level != previous_ui.zoom_level;
+        if config.keybindings != self.config.keybindings {
+            self.keymap = keymap::Keymap::new(&config.keybindings);
+        }
+        if !config.preferences.editor.vim_mode {
+            self.vim.reset();
+        }
+        self.config = config;
+        self.sync_agent_host();
+        if ai_changed {
+            self.apply_ai_preferences();
+        }
+        if font_changed {
+            applied.push(self.apply_output_font());
+        }
+        if appearance_changed && self.config.preferences.appearance.follow_system {
+            // Apply the light or dark theme now rather than at the next system change
+            self.system_appearance = None;
+            applied.push(detect_system_appearance());
+        }
+        Command::batch(applied)
+    }
+
+    fn apply_output_font(&mut self) -> Command<Message> {
+        let (output_font, warning) = font::OutputFont::resolve(&self.config.preferences.ui);
+        if let Some(warning) = warning {
+            self.status_messages.push(warning, std::time::Instant::now());
+        }
+        self.output_font = output_font;
+        if let Some(bounds) = self.blocks_viewport {
+            self.pty.resize(pty::TerminalSize::for_viewport(bounds.width, bounds.height, &self.output_font));
+        }
+        self.relayout()
+    }
+
+    /// Apply the layout rules for the window's width in the current font
+    fn relayout(&mut self) -> Command<Message> {
+        let Some(width) = self.window_width else {
+            return Command::none();
+        };
+        let previous = self.responsive;
+        self.responsive = ResponsiveLayout::for_width(width, &self.output_font);
+        if previous.is_compact() == self.responsive.is_compact() {
+            return Command::none();
+        }
+        self.toolbar_menu_open = false;
+        self.restore_after_reflow()
+    }
+
+    /// List `dir` in a directory block, focused so it takes the arrow keys
+    fn browse_directory(&mut self, dir: PathBuf) -> Command<Message> {
+        match dir_listing::DirListing::read(&dir) {
+            Ok(listing) => {
+                let block = Block::new_directory(listing);
+                self.focused_block = Some(block.id);
+                self.blocks.push(block);
+            }
+            Err(e) => self.blocks.push(Block::new_error(format!("Cannot list {}: {}", dir.display(), e))),
+        }
+        self.scroll.jump_to_bottom();
+        scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+    }
+
+    fn handle_directory(&mut self, block_id: Uuid, message: dir_listing::DirMessage) -> Command<Message> {
+        self.focused_block = Some(block_id);
+        let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) else {
+            return Command::none();
+        };
+        let BlockContent::Directory(ref mut listing) = block.content else {
+            return Command::none();
+        };
+        let Some(effect) = listing.update(message) else {
+            return Command::none();
+        };
+
+        match effect {
+            dir_listing::DirEffect::Enter(dir) => {
+                let entered = self
+                    .enter_directory(&shell::DirCommand::Cd(Some(dir.to_string_lossy().into_owned())))
+                    .map_err(|e| e.to_string())
+                    .and_then(|dir| dir_listing::DirListing::read(&dir).map_err(|e| e.to_string()));
+                match entered {
+                    Ok(entered) => {
+                        if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                            block.content = BlockContent::Directory(entered);
+                        }
+                    }
+                    Err(e) => self.status_messages.push(format!("Cannot open {}: {}", dir.display(), e), std::time::Instant::now()),
+                }
+                Command::none()
+            }
+            dir_listing::DirEffect::Edit(path) => self.edit_file(path, None, None),
+            dir_listing::DirEffect::Preview(path) => self.update(Message::PreviewFile(path, None)),
+            dir_listing::DirEffect::CopyPath(path) => {
+                let path = path.to_string_lossy().into_owned();
+                self.status_messages.push(format!("Copied {}", path), std::time::Instant::now());
+                iced::clipboard::write(path)
+            }
+            dir_listing::DirEffect::Delete(path) => {
+                let deleted = self.read_only.check().map_err(|e| e.to_string()).and_then(|_| {
+                    dir_listing::delete(&path).map_err(|e| format!("Cannot delete {}: {}", path.display(), e))
+                });
+                let notice = match deleted {
+                    Ok(()) => format!("Deleted {}", path.display()),
+                    Err(e) => e,
+                };
+                self.status_messages.push(notice, std::time::Instant::now());
+                if let Some(BlockContent::Directory(listing)) = self.blocks.iter_mut().find(|b| b.id == block_id).map(|b| &mut b.content) {
+                    if let Err(e) = listing.reload() {
+                        self.status_messages.push(format!("Cannot list {}: {}", listing.dir.display(), e), std::time::Instant::now());
+                    }
+                }
+                Command::none()
+            }
+        }
+    }
+
+    /// Open `path` in $VISUAL or $EDITOR, at `line` when given, run in a
+    /// focused block so terminal editors can be typed into; without either,
+    /// in the desktop's default app
+    fn edit_file(&mut self, path: PathBuf, line: Option<usize>, column: Option<usize>) -> Command<Message> {
+        let editor = ["VISUAL", "EDITOR"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|editor| !editor.trim().is_empty());
+        let Some(editor) = editor else {
+            if let Err(e) = open::that_detached(&path) {
+                self.blocks.push(Block::new_error(format!("Cannot open {}: {}", path.display(), e)));
+                return self.follow_output(1);
+            }
+            return Command::none();
+        };
+        let command = links::editor_command(&editor, &path, line, column);
+        let block = Block::new_command(command.clone());
+        self.focused_block = Some(block.id);
+        self.run_in_block(block, command, std::collections::HashMap::new())
+    }
+
+    /// Follow the repository `dir` is in: its branch for the status line, and
+    /// its git directory for the watcher
+    fn sync_git(&mut self, dir: &std::path::Path) {
+        self.git_branch = status_line::git_branch(dir);
+        let git_dir = status_line::git_dir(dir);
+        if git_dir != self.git_dir {
+            self.git_dir = git_dir;
+            self.git_status = None;
+        }
+    }
+
+    /// Read the git status of `dir` off the UI thread
+    fn refresh_git(&self, dir: PathBuf) -> Command<Message> {
+        if self.git_dir.is_none() {
+            return Command::none();
+        }
+        Command::perform(
+            async move {
+                let read_from = dir.clone();
+                let result = tokio::task::spawn_blocking(move || git_status::read(&read_from))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|result| result.map_err(|e| e.to_string()));
+                (dir, result)
+            },
+            |(dir, result)| Message::GitStatusRead(dir, result),
+        )
+    }
+
+    /// Take the focused pane's blocks, input and directory out of the app, to park them
+    fn take_pane_state(&mut self) -> PaneState {
+        PaneState {
+            blocks: std::mem::take(&mut self.blocks),
+            input: std::mem::take(&mut self.current_input),
+            input_lines: std::mem::take(&mut self.input_lines),
+            cwd: self.shell_manager.cwd().to_path_buf(),
+            scroll: std::mem::take(&mut self.scroll),
+        }
+    }
+
+    /// Make `state` the focused pane's; commands run in its directory from now on
+    fn load_pane_state(&mut self, state: PaneState) -> Command<Message> {
+        self.blocks = state.blocks;
+        self.current_input = state.input;
+        self.input_lines = state.input_lines;
+        self.completion = None;
+        self.focused_block = None;
+        if state.cwd != self.shell_manager.cwd() {
+            match std::env::set_current_dir(&state.cwd) {
+                Ok(()) => self.shell_manager.set_cwd(state.cwd),
+                Err(e) => self.status_messages.push(
+                    format!("Cannot open {}: {}", state.cwd.display(), e),
+                    std::time::Instant::now(),
+                ),
+            }
+        }
+        let cwd = self.shell_manager.cwd().to_path_buf();
+        self.sync_git(&cwd);
+        // Each pane comes back scrolled where it was left
+        self.scroll = state.scroll;
+        if let Some(search) = self.block_search.as_mut() {
+            search.refresh(block::visible(&self.blocks, self.block_filter));
+        }
+        Command::batch([self.restore_scroll(), text_input::focus(command_input_id())])
+    }
+
+    /// A pane that isn't focused, in the active tab or another one
+    fn parked_pane_mut(&mut self, owner: BlockOwner) -> Option<&mut PaneState> {
+        if owner.tab == self.tabs.active_id() {
+            self.panes.get_mut(owner.pane)
+        } else {
+            self.tabs.get_mut(owner.tab)?.pane_mut(owner.pane)
+        }
+    }
+
+    /// Take the active tab's panes, env profile and conversation out of the app, to park them
+    fn take_tab_state(&mut self) -> TabState {
+        self.save_conversation();
+        let (conversation, agent_enabled) = match self.agent_mode.as_mut() {
+            Some(agent) => (agent.conversations.take(), agent.enabled),
+            None => (None, false),
+        };
+        TabState {
+            pane: self.take_pane_state(),
+            panes: std::mem::take(&mut self.panes),
+            env_profile: self.config.active_env_profile.clone(),
+            conversation,
+            agent_enabled,
+        }
+    }
+
+    /// Make `state` the active tab's
+    fn load_tab_state(&mut self, state: TabState) -> Command<Message> {
+        self.panes = state.panes;
+        self.renaming_tab = None;
+        if state.env_profile != self.config.active_env_profile {
+            if let Err(e) = self.apply_env_profile(state.env_profile) {
+                self.status_messages.push(e, std::time::Instant::now());
+            }
+        }
+        if let Some(agent) = self.agent_mode.as_mut() {
+            agent.conversations = state.conversation;
+            agent.enabled = state.agent_enabled;
+            self.agent_enabled = state.agent_enabled;
+        }
+        self.editing_prompt = None;
+        self.refresh_ai_sidebar();
+        self.load_pane_state(state.pane)
+    }
+
+    /// Park the saved tabs around the active one. A tab that had an agent
+    /// conversation open continues it.
+    fn restore_tabs(&mut self, saved: Vec<session::RestoredTab>, active: usize) {
+        if saved.len() < 2 {
+            return;
+        }
+        let mut tabs = Vec::new();
+        for (index, tab) in saved.into_iter().enumerate() {
+            let conversation = tab.conversation.and_then(|id| self.conversation_store.as_ref()?.load(id).ok());
+            if index == active {
+                if let (Some(agent), Some(tree)) = (self.agent_mode.as_mut(), conversation) {
+                    agent.load_conversation(tree);
+                    agent.enabled = true;
+                    self.agent_enabled = true;
+                }
+                tabs.push((tab.name, None));
+                continue;
+            }
+            let state = TabState {
+                pane: PaneState { blocks: tab.blocks, cwd: tab.cwd, ..Default::default() },
+                panes: tab.panes,
+                env_profile: tab.env_profile,
+                agent_enabled: conversation.is_some(),
+                conversation,
+            };
+            tabs.push((tab.name, Some(state)));
+        }
+        self.tabs = Tabs::restore(tabs, active);
+    }
+
+    /// Run commands with the variables of env profile `name`, or none
+    fn apply_env_profile(&mut self, name: Option<String>) -> Result<(), String> {
+        let variables = match &name {
+            Some(name) => EnvProfileManager::new()
+                .ok()
+                .and_then(|manager| manager.get_profile(name).cloned())
+                .map(|profile| profile.variables)
+                .ok_or_else(|| format!("No env profile named `{}`", name))?,
+            None => std::collections::HashMap::new(),
+        };
+        self.redactor.register_env(&variables);
+        self.shell_manager.set_profile_env(variables);
+        self.config.active_env_profile = name;
+        self.sync_agent_host();
+        Ok(())
+    }
+
+    /// Keep the blocks for the next start, or forget them if privacy settings say so
+    fn save_session(&mut self) {
+        let Ok(paths) = config::ConfigPaths::resolve() else { return };
+        let privacy = &self.config.preferences.privacy;
+        // For `working_directory: LastUsed`
+        if !privacy.incognito_mode {
+            let written = std::fs::create_dir_all(paths.sessions_dir())
+                .and_then(|_| std::fs::write(paths.last_cwd_file(), self.shell_manager.cwd().to_string_lossy().as_bytes()));
+            if let Err(e) = written {
+                log::warn!("Failed to save the working directory: {}", e);
+            }
+        }
+        let result = if privacy.clear_history_on_exit {
+            if let Err(e) = self.history.clear() {
+                log::warn!("Failed to clear command history: {}", e);
+            }
+            session::discard(&paths.last_session_file())
+        } else if privacy.incognito_mode {
+            Ok(())
+        } else {
+            let active = session::SavedTab {
+                name: self.tabs.active_name().map(str::to_string),
+                env_profile: self.config.active_env_profile.clone(),
+                conversation: self.active_conversation_id(),
+                cwd: self.shell_manager.cwd().to_path_buf(),
+                ..session::SavedTab::new(&self.blocks, &self.panes)
+            };
+            let tabs: Vec<session::SavedTab> = self.tabs
+                .iter()
+                .map(|tab| match tab.state() {
+                    Some(state) => session::SavedTab {
+                        name: tab.name().map(str::to_string),
+                        env_profile: state.env_profile.clone(),
+                        conversation: state.conversation.as_ref().map(|tree| tree.root().id),
+                        cwd: state.pane.cwd.clone(),
+                        ..session::SavedTab::new(&state.pane.blocks, &state.panes)
+                    },
+                    None => active.clone(),
+                })
+                .collect();
+            session::save(&tabs, self.tabs.active(), &paths.last_session_file(), privacy.history_limit)
+        };
+        if let Err(e) = result {
+            log::warn!("Failed to save session: {}", e);
+        }
+    }
+
+    /// What currently needs `Message::Tick`, and how often
+    fn tick_interest(&self) -> tick::TickController {
+        use tick::{Rate, TickSource};
+
+        tick::TickController::new()
+            .with(TickSource::Spinner, self.status_context().is_animating().then_some(Rate::Fast))
+            .with(TickSource::BellFlash, self.bell_flash.is_some().then_some(Rate::Fast))
+            .with(TickSource::StatusMessages, (!self.status_messages.is_empty()).then_some(Rate::Slow))
+            .with(TickSource::RunningCommand, tabs::has_running(&self.blocks).then_some(Rate::Slow))
+    }
+
+    /// Switching between the regular and compact layouts rebuilds parts of
+    /// the widget tree; put the scroll position and input focus back
+    fn restore_after_reflow(&self) -> Command<Message> {
+        let focus = if self.palette.is_none() && !self.settings_open {
+            text_input::focus(command_input_id())
+        } else {
+            Command::none()
+        };
+        Command::batch([self.restore_scroll(), focus])
+    }
+
+    /// Put the block list back at the tail, or where the user anchored it
+    fn restore_scroll(&self) -> Command<Message> {
+        if self.scroll.is_following() {
+            scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
+        } else {
+            scrollable::scroll_to(
+                blocks_scrollable_id(),
+                scrollable::AbsoluteOffset { x: 0.0, y: self.scroll.offset() },
+            )
+        }
+    }
+
+    /// Panes as laid out in `tree`. The focused one shows `focused`, the full
+    /// block list; a click on another focuses it.
+    fn view_pane_tree<'a>(
+        &'a self,
+        tree: &'a PaneTree,
+        focused: &mut Option<Element<'a, Message>>,
+        show_status_glyphs: bool,
+    ) -> Element<'a, Message> {
+        let frame = |content: Element<'a, Message>, is_focused: bool| {
+            container(content)
+                .padding(4)
+                .width(iced::Length::Fill)
+                .height(iced::Length::Fill)
+                .style(container::Appearance {
+                    border: iced::Border {
+                        color: if is_focused { iced::Color::from_rgb(0.3, 0.5, 0.9) } else { iced::Color::from_rgb(0.4, 0.4, 0.4) },
+                        width: 1.0,
+                        radius: 6.0.into(),
+                    },
+                    ..Default::default()
+                })
+        };
+        match tree {
+            PaneTree::Pane(pane) if *pane == self.panes.focused() => {
+                frame(focused.take().unwrap_or_else(|| column![].into()), true).into()
+            }
+            PaneTree::Pane(pane) => {
+                let blocks = self.panes.get(*pane).map(|state| state.blocks.as_slice()).unwrap_or_default();
+                let list = scrollable(
+                    column(
+                        blocks
+                            .iter()
+                            .map(|block| block.view(show_status_glyphs, &self.responsive, self.read_only.reason(), &[], self.output_links(block), self.output_font))
+                            .collect::<Vec<_>>()
+                    )
+                    .spacing(8)
+                )
+                .height(iced::Length::Fill);
+                iced::widget::mouse_area(frame(list.into(), false)).on_press(Message::FocusPane(*pane)).into()
+            }
+            PaneTree::Split { direction, children } => {
+                let mut views = Vec::new();
+                for child in children {
+                    views.push(self.view_pane_tree(child, focused, show_status_glyphs));
+                }
+                match direction {
+                    SplitDirection::Horizontal => row(views).spacing(8).height(iced::Length::Fill).into(),
+                    SplitDirection::Vertical => column(views).spacing(8).height(iced::Length::Fill).into(),
+                }
+            }
+        }
+    }
+
+    /// Links to draw clickable in a block's output, unless turned off
+    fn output_links<'a>(&self, block: &'a Block) -> &'a [links::Link] {
+        let terminal = &self.config.preferences.terminal;
+        if !terminal.url_detection || terminal.hyperlink_behavior == config::HyperlinkBehavior::Disabled {
+            return &[];
+        }
+        block.links()
+    }
+
+    /// A block with press/release handling for focus and drag-to-reorder
+    fn view_block<'a>(&'a self, block: &'a Block, show_status_glyphs: bool) -> Element<'a, Message> {
+        let highlighted = self.focused_block == Some(block.id) || self.dragging_block == Some(block.id);
+        let flashing = self.bell_flash.is_some_and(|(id, _)| id == block.id);
+        let search = self.block_search.as_ref();
+        let current_match = search.and_then(|search| search.current()).is_some_and(|found| found.block == block.id);
+        let highlights = search.map(|search| search.highlights(block.id)).unwrap_or_default();
+        let framed = container(block.view(show_status_glyphs, &self.responsive, self.read_only.reason(), &highlights, self.output_links(block), self.output_font))
+            .padding(2)
+            .style(container::Appearance {
+                border: iced::Border {
+                    color: if flashing {
+                        iced::Color::from_rgb(0.95, 0.75, 0.2)
+                    } else if current_match {
+                        iced::Color::from_rgb(1.0, 0.6, 0.1)
+                    } else if highlighted {
+                        iced::Color::from_rgb(0.3, 0.5, 0.9)
+                    } else {
+                        iced::Color::TRANSPARENT
+                    },
+                    width: 2.0,
+                    radius: 10.0.into(),
+                },
+                ..Default::default()
+            });
+
+        let block_view = iced::widget::mouse_area(framed)
+            .on_press(Message::BlockPressed(block.id))
+            .on_release(Message::BlockReleased(block.id));
+
+        let mut view = column![].spacing(2);
+        if let Some(hints) = self.view_block_hints(block) {
+            view = view.push(hints);
+        }
+        view = view.push(block_view);
+        if let Some(stdin) = self.view_stdin_state(block) {
+            view = view.push(stdin);
+        }
+        if let Some(offers) = self.view_snippet_offers(block) {
+            view = view.push(offers);
+        }
+        view.into()
+    }
+
+    /// Whether keys go to a running block's command, and the button to
+    /// attach or detach
+    fn view_stdin_state<'a>(&self, block: &'a Block) -> Option<Element<'a, Message>> {
+        if !self.pty.is_running(block.id) {
+            return None;
+        }
+        let require_attach = self.config.preferences.terminal.require_attach;
+        if self.stdin_target() != Some(block.id) {
+            return require_attach.then(|| {
+                let message = Message::BlockAction(block.id, BlockMessage::AttachStdin);
+                self.hinted(button(text("⌨ Attach").size(12)).on_press(message.clone()), hints::InteractableKind::BlockButton, "⌨ Attach", message)
+            });
+        }
+        let state = if self.pty.echo_off(block.id) {
+            "🔒 Interactive · input hidden"
+        } else {
+            "⌨ Interactive · keys go to this command"
+        };
+        let message = Message::BlockAction(block.id, BlockMessage::DetachStdin);
+        let detach = self.hinted(button(text("Detach").size(12)).on_press(message.clone()), hints::InteractableKind::BlockButton, "Detach", message);
+        Some(
+            row![text(state).size(12).style(iced::theme::Text::Color(iced::Color::from_rgb(0.3, 0.5, 0.9))), detach]
+                .spacing(8)
+                .align_items(iced::Alignment::Center)
+                .into(),
+        )
+    }
+
+    /// In hint mode, the labels for a block's buttons and the links in its
+    /// output, drawn as a row above the block
+    fn view_block_hints<'a>(&'a self, block: &'a Block) -> Option<Element<'a, Message>> {
+        self.hint_mode.as_ref()?;
+        let mut registry = self.interactables.borrow_mut();
+        let mut labels = row![].spacing(8);
+
+        let read_only = self.read_only.is_enabled();
+        for (description, action) in block.actions() {
+            if read_only && matches!(action, BlockMessage::Rerun) {
+                continue;
+            }
+            let message = Message::BlockAction(block.id, action);
+            if let Some(label) = registry.register(hints::InteractableKind::BlockButton, description, message) {
+                labels = labels.push(row![hint_badge(label), text(description).size(12)].spacing(4));
+            }
+        }
+        for (_, url) in block.output_text().lines().flat_map(hints::find_links) {
+            let description = format!("↗ {}", layout::truncate(&url, 40));
+            if let Some(label) = registry.register(hints::InteractableKind::Link, url.clone(), Message::OpenLink(url)) {
+                labels = labels.push(row![hint_badge(label), text(description).size(12)].spacing(4));
+            }
+        }
+        Some(labels.into())
+    }
+
+    /// Run buttons for the snippets of an assistant reply that may be run
+    fn view_snippet_offers<'a>(&self, block: &'a Block) -> Option<Element<'a, Message>> {
+        let BlockContent::AgentMessage { content, role: AgentRole::Assistant, superseded: false, .. } = &block.content else {
+            return None;
+        };
+        let offers = scratch::offers(content, &self.languages, &self.config.preferences.scratch);
+        if offers.is_empty() {
+            return None;
+        }
+        if let Some(reason) = self.read_only.reason() {
+            let buttons = offers.into_iter().map(|(index, language)| {
+                let label = text(format!("▶ Run {} #{}", language.name, index + 1)).size(12);
+                tooltip(button(label), text(reason.explanation()).size(12), tooltip::Position::Bottom).into()
+            });
+            return Some(iced::widget::Row::with_children(buttons).spacing(8).into());
+        }
+        let buttons = offers.into_iter().map(|(index, language)| {
+            let description = format!("▶ Run {} #{}", language.name, index + 1);
+            let message = Message::RunSnippet(block.id, index);
+            self.hinted(button(text(description.clone()).size(12)).on_press(message.clone()), hints::InteractableKind::BlockButton, &description, message)
+        });
+        Some(iced::widget::Row::with_children(buttons).spacing(8).into())
+    }
+
+    /// Register an element for hint mode and, while hints are shown, put its label in front of it
+    fn hinted<'a>(
+        &self,
+        element: impl Into<Element<'a, Message>>,
+        kind: hints::InteractableKind,
+        description: &str,
+        message: Message,
+    ) -> Element<'a, Message> {
+        if self.hint_mode.is_none() {
+            return element.into();
+        }
+        match self.interactables.borrow_mut().register(kind, description, message) {
+            Some(label) => row![hint_badge(label), element.into()].spacing(4).align_items(iced::Alignment::Center).into(),
+            None => element.into(),
+        }
+    }
+
+    /// Labels for the palette's rows, by template name or result title
+    fn register_palette_rows(&self, palette: &CommandPalette) -> std::collections::HashMap<String, String> {
+        if self.hint_mode.is_none() || palette.is_showing_form() {
+            return std::collections::HashMap::new();
+        }
+        let mut registry = self.interactables.borrow_mut();
+        let actions = palette.actions().into_iter().map(|action| {
+            (action.name.clone(), PaletteMessage::SelectAction(action.id.clone()))
+        });
+        let templates = palette.sections()
+            .into_iter()
+            .flat_map(|(_, templates)| templates)
+            .map(|template| (template.name.clone(), PaletteMessage::SelectTemplate(template.name.clone())));
+        let results = palette.results()
+            .iter()
+            .map(|hit| (hit.title.clone(), PaletteMessage::SelectResult(hit.target.clone())));
+        actions
+            .chain(templates)
+            .chain(results)
+            .filter_map(|(name, message)| {
+                let label = registry.register(hints::InteractableKind::PaletteRow, name.clone(), Message::Palette(message))?;
+                Some((name, label))
+            })
+            .collect()
+    }
+
+    /// Typed letters go to the hint labels; Esc or a miss leaves hint mode
+    fn handle_hint_key(&mut self, key: iced::keyboard::Key) -> Command<Message> {
+        use iced::keyboard::{key::Named, Key};
+
+        let Some(mode) = self.hint_mode.as_mut() else {
+            return Command::none();
+        };
+        let letter = match key.as_ref() {
+            Key::Character(c) => c.chars().next(),
+            Key::Named(Named::Escape) => None,
+            _ => return Command::none(),
+        };
+        let input = match letter {
+            Some(letter) => mode.key(letter, &self.interactables.borrow()),
+            None => hints::HintInput::NoMatch,
+        };
+        match input {
+            hints::HintInput::Pending => Command::none(),
+            hints::HintInput::Activate(message) => {
+                self.hint_mode = None;
+                self.update(message)
+            }
+            hints::HintInput::NoMatch => {
+                self.hint_mode = None;
+                Command::none()
+            }
+        }
+    }
+
+    /// Act on a bell from `block_id` per the bell preference, at most once a second
+    /// Health of every subsystem, also published to the diagnostics endpoint
+    fn collect_diagnostics(&self) -> diagnostics::DiagnosticsReport {
+        use diagnostics::*;
+
+        let running = self.blocks.iter().filter(|b| b.status() == Some(block::BlockStatus::Running)).count();
+        let commands = CommandsHealth { running, open_streams: self.bell_detectors.len() };
+
+        let ai_status = self.agent_mode.as_ref().map(AgentMode::status);
+        let ai = AiHealth { status: ai_status.as_ref(), streaming: self.agent_streaming, latency: &self.ai_latency };
+        let suggestions = SuggestionsHealth {
+            enabled: self.autosuggestions_enabled(),
+            ai_enabled: self.config.preferences.editor.ai_suggestions,
+            history_latency: &self.autosuggest.history_latency,
+            ai_latency: &self.autosuggest.ai_latency,
+        };
+
+        let cache = workflows::WorkflowCache::new().map(|cache| cache.entries()).unwrap_or_default();
+        let cache = CacheHealth {
+            entries: cache.len(),
+            bytes: cache.iter().map(|(_, entry)| entry.size_bytes).sum(),
+            limit_bytes: self.config.preferences.maintenance.cache_max_mb.saturating_mul(1024 * 1024),
+        };
+
+        let loaded = self.plugins.names();
+        let plugins = PluginsHealth {
+            loaded: loaded.len(),
+            failed: self.config.plugins.enabled_plugins
+                .iter()
+                .filter(|name| !loaded.contains(&name.as_str()))
+                .cloned()
+                .collect(),
+        };
+
+        let due = maintenance::Stores::resolve()
+            .map(|stores| {
+                maintenance::Maintenance::new(stores, self.config.preferences.maintenance.clone())
+                    .is_due(chrono::Utc::now())
+            })
+            .unwrap_or(false);
+        let upkeep = MaintenanceHealth { due };
+
+        let report = DiagnosticsReport::collect(&[&commands, &ai, &suggestions, &cache, &plugins, &upkeep], chrono::Utc::now());
+        if let Ok(mut shared) = self.diagnostics_report.write() {
+            *shared = Some(report.clone());
+        }
+        report
+    }
+
+    fn handle_diagnostic_action(&mut self, action: diagnostics::DiagnosticAction) -> Command<Message> {
+        match action {
+            diagnostics::DiagnosticAction::ClearAiQueue => {
+                if self.agent_streaming {
+                    self.agent_streaming = false;
+                    self.ai_request_started = None;
+                    self.drop_tool_round();
+                    self.status_messages.push("Stopped waiting for the AI reply", std::time::Instant::now());
+                }
+                self.update(Message::RefreshDiagnostics)
+            }
+            diagnostics::DiagnosticAction::RunMaintenance => Command::perform(
+                maintenance::run_now(self.config.preferences.maintenance.clone()),
+                Message::MaintenanceRan,
+            ),
+            diagnostics::DiagnosticAction::ReloadPlugins => {
+                self.plugins = PluginHost::with_builtins(&self.config.plugins.enabled_plugins);
+                self.update(Message::RefreshDiagnostics)
+            }
+        }
+    }
+
+    /// The running block that typed keys go to: the focused one, once
+    /// attached when `terminal.require_attach` is on
+    fn stdin_target(&self) -> Option<Uuid> {
+        let block_id = self.focused_block?;
+        if self.config.preferences.terminal.require_attach && self.stdin_attached != Some(block_id) {
+            return None;
+        }
+        let in_view = self.blocks.iter().any(|b| b.id == block_id);
+        (in_view && self.pty.is_running(block_id)).then_some(block_id)
+    }
+
+    /// Add a command block and stream `command`'s output into it
+    /// `command` as run in the container chosen for new commands, with the
+    /// block badged with it; unchanged without one. Env overrides are
+    /// passed through to the container.
+    fn in_container(&self, block: &mut Block, command: String, env_overrides: &[(String, String)]) -> String {
+        let Some(container) = &self.exec_container else {
+            return command;
+        };
+        block.container = Some(container.name.clone());
+        let names: Vec<String> = env_overrides.iter().map(|(name, _)| name.clone()).collect();
+        container.exec_line(&command, &names, self.config.preferences.terminal.use_pty)
+    }
+
+    fn run_in_block(
+        &mut self,
+        mut block: Block,
+        command: String,
+        invocation_env: std::collections::HashMap<String, String>,
+    ) -> Command<Message> {
+        // PATH as the command will see it; a different PATH from last time
+        // (a profile switch or a one-off override) drops cached lookups
+        let path_var = invocation_env
+            .get("PATH")
+            .or_else(|| self.shell_manager.profile_env().get("PATH"))
+            .map(std::ffi::OsString::from)
+            .or_else(|| std::env::var_os("PATH"))
+            .unwrap_or_default();
+        self.path_resolver.set_path(&path_var);
+        if let Some(program) = path_inspector::program_of(&command) {
+            let resolution = self.path_resolver.resolve(&program);
+            if resolution.is_shadowing() {
+                block.resolution = Some(resolution.clone());
+            }
+        }
+
+        block.set_scrollback_limit(self.config.preferences.terminal.scrollback_lines);
+        block.start(self.config.active_env_profile.clone());
+        if let Some(lines) = self.config.preferences.terminal.auto_collapse_lines {
+            block::auto_collapse(&mut self.blocks, lines);
+        }
+        let owner = BlockOwner { tab: self.tabs.active_id(), pane: self.panes.focused() };
+        let block_id = block.id;
+        self.blocks.push(block);
+        // Submitting a command always brings the newest block into view
+        self.scroll.jump_to_bottom();
+
+        // Stream output so each chunk is timestamped for the scrubber
+        let shell_manager = self.shell_manager.clone();
+        let pty = self.config.preferences.terminal.use_pty.then(|| self.pty.clone());
+        let events = futures::stream::once(async move {
+            match pty {
+                Some(pty) => shell_manager.execute_command_in_pty(&pty, block_id, command, invocation_env),
+                None => shell_manager.execute_command_streaming(command, invocation_env),
+            }
+        })
+        .flat_map(tokio_stream::wrappers::ReceiverStream::new);
+
+        Command::batch([
+            Command::run(events, move |event| Message::CommandEvent(owner, block_id, event)),
+            scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END),
+        ])
+    }
+
+    /// Tell the agent's tools about the active env profile and privacy choice
+    fn sync_agent_host(&mut self) {
+        let host = HostContext {
+            env_profile: self.config.active_env_profile.clone(),
+            share_identity: self.config.preferences.privacy.share_host_identity,
+        };
+        if let Some(agent) = self.agent_mode.as_mut() {
+            agent.set_host(host);
+        }
+    }
+
+    /// Rebuild the agent's client from the AI preferences. The conversation
+    /// carries over, so a reply can come from a different provider than the
+    /// turns before it.
+    fn apply_ai_preferences(&mut self) {
+        let agent_config = AgentConfig::from_preferences(&self.config.preferences.ai, |name| std::env::var(name).ok());
+        let provider = agent_config.provider.clone();
+        let model = agent_config.model.clone();
+        let switched = match self.agent_mode.as_mut() {
+            Some(agent) => agent.set_config(agent_config),
+            None => AgentMode::new(agent_config).map(|mut agent| {
+                agent.set_read_only(self.read_only.clone());
+                self.agent_mode = Some(agent);
+            }),
+        };
+        if let Err(e) = switched {
+            self.blocks.push(Block::new_error(format!("Could not switch the AI provider: {}", e)));
+            return;
+        }
+        self.sync_agent_host();
+        self.status_messages.push(format!("AI provider: {} ({})", provider, model), std::time::Instant::now());
+
+        let in_conversation = self.agent_mode
+            .as_ref()
+            .and_then(|agent| agent.conversations.as_ref())
+            .is_some_and(|tree| !tree.active().messages.is_empty());
+        if in_conversation && !provider.supports_tools() {
+            self.blocks.push(Block::new_info(format!(
+                "{} can't run tools. The conversation continues, but the agent can no longer run commands or read files.",
+                provider
+            )));
+        }
+    }
+
+    /// Server the AI settings tab would list models from, while it's open on
+    /// an Ollama provider
+    fn ollama_base_url(&self) -> Option<String> {
+        if !self.settings_open || self.settings_view.active_tab != settings::SettingsTab::Ai {
+            return None;
+        }
+        let effective = AgentConfig::from_preferences(&self.settings_view.config.preferences.ai, |name| std::env::var(name).ok());
+        (effective.provider == agent_mode_eval::ai_client::AiProvider::Ollama).then(|| {
+            effective
+                .base_url
+                .or_else(|| AgentConfig::get_default_base_url(&effective.provider).map(str::to_string))
+                .unwrap_or_default()
+        })
+    }
+
+    /// Fill the AI tab's model list from the cache, or ask the server when
+    /// nothing recent is cached
+    fn discover_models(&mut self) -> Command<Message> {
+        let Some(base_url) = self.ollama_base_url() else {
+            return Command::none();
+        };
+        if self.settings_view.discovered_models.is_some() || self.model_request.is_some() {
+            return Command::none();
+        }
+        if let Some(models) = self.model_cache.get(&base_url, std::time::Instant::now()) {
+            self.settings_view.discovered_models = Some(Ok(models.to_vec()));
+            return Command::none();
+        }
+        self.model_request = Some(base_url.clone());
+        Command::perform(
+            async move {
+                let result = availability::ollama_models(&base_url).await;
+                (base_url, result)
+            },
+            |(base_url, result)| Message::ModelsDiscovered(base_url, result),
+        )
+    }
+
+    /// Language tag and code of a reply's nth code block
+    fn code_block(&self, block_id: Uuid, index: usize) -> Option<(String, String)> {
+        let block = self.blocks.iter().find(|b| b.id == block_id)?;
+        block.code_blocks().get(index).map(|(language, code)| (language.to_string(), code.to_string()))
+    }
+
+    /// Commands of a reply's shell snippet
+    fn shell_snippet(&self, block_id: Uuid, index: usize) -> Option<Vec<String>> {
+        let (language, code) = self.code_block(block_id, index)?;
+        let commands = scratch::shell_commands(&code);
+        (scratch::is_shell_tag(&language) && !commands.is_empty()).then_some(commands)
+    }
+
+    /// Run a reply's shell snippet. Snippets of several commands are
+    /// confirmed first when the preference asks for it, and destructive
+    /// ones always are.
+    fn run_code_block(&mut self, source: Uuid, index: usize) -> Command<Message> {
+        if let Err(e) = self.read_only.check() {
+            self.status_messages.push(e.to_string(), std::time::Instant::now());
+            return Command::none();
+        }
+        let Some(commands) = self.shell_snippet(source, index) else {
+            return Command::none();
+        };
+        let several = commands.len() > 1 && self.config.preferences.ai.confirm_generated_commands;
+        if several || !safety::classify(&commands.join("\n")).is_safe() {
+            self.pending_code_run = Some((source, commands));
+            return Command::none();
+        }
+        self.start_code_run(source, commands)
+    }
+
+    /// Run snippet commands as one command line, in a block linked to the reply
+    fn start_code_run(&mut self, source: Uuid, commands: Vec<String>) -> Command<Message> {
+        let command = commands.join("\n");
+        let mut block = Block::new_command(command.clone());
+        block.source = Some(source);
+        let command = self.in_container(&mut block, command, &[]);
+        self.run_in_block(block, command, std::collections::HashMap::new())
+    }
+
+    /// Write a reply's snippet to the scratch directory and run it in a
+    /// command block linked to the reply
+    fn run_snippet(&mut self, source: Uuid, index: usize) -> Command<Message> {
+        if let Err(e) = self.read_only.check() {
+            self.status_messages.push(e.to_string(), std::time::Instant::now());
+            return Command::none();
+        }
+        let prefs = self.config.preferences.scratch.clone();
+        let Some(snippet) = self.blocks.iter().find(|b| b.id == source).and_then(|block| match &block.content {
+            BlockContent::AgentMessage { content, .. } => scratch::extract_snippets(content).into_iter().nth(index),
+            _ => None,
+        }) else {
+            return Command::none();
+        };
+        // The reply may have changed since the button was drawn
+        let Some(language) = scratch::offer(&snippet, &self.languages, &prefs) else {
+            return Command::none();
+        };
+
+        let prepared = scratch::ScratchDir::new(&prefs)
+            .map_err(|e| e.to_string())
+            .and_then(|dir| {
+                let removed = dir.clean(std::time::SystemTime::now());
+                if removed > 0 {
+                    log::debug!("Removed {} expired snippet files", removed);
+                }
+                dir.write(language, &snippet.code).map_err(|e| e.to_string())
+            })
+            .and_then(|path| self.languages.run_command(language, &path).map_err(|e| e.to_string()));
+
+        match prepared {
+            Ok(argv) => {
+                let command = scratch::command_line(&argv);
+                let mut block = Block::new_command(command.clone());
+                block.source = Some(source);
+                self.run_in_block(block, command, std::collections::HashMap::new())
+            }
+            Err(e) => {
+                self.blocks.push(Block::new_error(format!("Cannot run {} snippet: {}", language.name, e)));
+                self.follow_output(1)
+            }
+        }
+    }
+
+    fn ring_bell(&mut self, block_id: Uuid) -> Command<Message> {
+        let now = std::time::Instant::now();
+        if !self.bell_limiter.allow(now) {
+            return Command::none();
+        }
+        let mut commands = Vec::new();
+        for action in bell::actions(&self.config.preferences.terminal.bell_behavior, self.window_focused) {
+            match action {
+                bell::BellAction::Sound => bell::play_sound(),
+                bell::BellAction::Flash => self.bell_flash = Some((block_id, now + bell::FLASH_DURATION)),
+                bell::BellAction::Notify => {
+                    let command = self.blocks
+                        .iter()
+                        .find(|b| b.id == block_id)
+                        .map(|b| self.redactor.redact(&b.title()))
+                        .unwrap_or_default();
+                    bell::notify(&command);
+                }
+                bell::BellAction::Urgent => commands.push(iced::window::request_user_attention(
+                    iced::window::Id::MAIN,
+                    Some(iced::window::UserAttention::Informational),
+                )),
+            }
+        }
+        Command::batch(commands)
+    }
+
+    /// Run a workflow with `arguments` in the background, its output going
+    /// to a command block tagged with the workflow's name
+    fn start_workflow_run(&mut self, workflow: workflows::Workflow, arguments: std::collections::HashMap<String, String>) -> Command<Message> {
+        let block = Block::new_command(workflow.summary()).with_workflow(workflow.name.clone());
+        let (block_id, name) = (block.id, workflow.name.clone());
+        self.blocks.push(block);
+        let executor = workflows::WorkflowExecutor::new(cli::current_shell()).with_read_only(self.read_only.clone());
+        Command::perform(workflows::run_in_background(executor, workflow, arguments), move |result| {
+            Message::WorkflowRunFinished(block_id, name, result.map_err(|e| e.to_string()))
+        })
+    }
+
+    /// Add a finished command block to the search index. Nothing run in
+    /// incognito is indexed.
+    fn index_block(&mut self, block_id: Uuid) {
+        if self.config.preferences.privacy.incognito_mode {
+            return;
+        }
+        let Some(block) = self.blocks.iter().find(|b| b.id == block_id) else { return };
+        let (title, output, created_at) = (block.title(), self.redactor.redact(block.output_text()), block.created_at);
+        if let Err(e) = std::sync::Arc::make_mut(&mut self.search_index).add(block_id, &title, &output, created_at) {
+            self.status_messages.push(format!("Could not update the search index: {}", e), std::time::Instant::now());
+        }
+    }
+
+    /// Refresh the palette actions that come from workflows, env and config profiles,
+    /// running commands and plugin commands, which can change while the
+    /// terminal runs
+    fn register_dynamic_actions(&mut self, resources: &resources::ResourceManager) {
+        self.actions.remove_category("Workflows");
+        for template in resources.templates() {
+            let name = template.name.clone();
+            let action = CommandAction::new(format!("workflow.{}", name), format!("Run workflow: {}", name), "Workflows", move || {
+                let name = name.clone();
+                async move { Message::OpenWorkflow(name) }
+            });
+            self.actions.register(action.with_description(template.description.clone().unwrap_or_default()));
+        }
+        // The user's workflows, registered second so they replace templates of the same name
+        let shell = cli::current_shell();
+        let user_workflows = self.workflows.as_ref().map(|manager| manager.get_all_workflows(Some(&shell))).unwrap_or_default();
+        for result in user_workflows {
+            let name = result.workflow.name.clone();
+            let action = CommandAction::new(format!("workflow.{}", name), format!("Run workflow: {}", name), "Workflows", move || {
+                let name = name.clone();
+                async move { Message::OpenWorkflow(name) }
+            });
+            self.actions.register(action.with_description(result.workflow.description.unwrap_or_default()));
+        }
+
+        self.actions.remove_category("Schedules");
+        let upcoming = self.workflows.as_ref().map(|manager| manager.upcoming_runs()).unwrap_or_default();
+        if !upcoming.is_empty() {
+            self.actions.register(CommandAction::new("schedule.list", "Show scheduled workflows", "Schedules", || async {
+                Message::ShowSchedules
+            }));
+        }
+        for run in upcoming {
+            let name = run.name.clone();
+            self.actions.register(CommandAction::new(
+                format!("schedule.run.{}", run.name),
+                format!("Run scheduled workflow now: {}", run.name),
+                "Schedules",
+                move || {
+                    let name = name.clone();
+                    async move { Message::TriggerWorkflow(name) }
+                },
+            ));
+            let (name, pause) = (run.name.clone(), !run.paused);
+            self.actions.register(CommandAction::new(
+                format!("schedule.pause.{}", run.name),
+                format!("{} schedule: {}", if pause { "Pause" } else { "Resume" }, run.name),
+                "Schedules",
+                move || {
+                    let name = name.clone();
+                    async move { Message::PauseSchedule(name, pause) }
+                },
+            ));
+        }
+
+        self.actions.remove_category("Profiles");
+        let profiles = EnvProfileManager::new().map(|manager| manager.get_profile_names()).unwrap_or_default();
+        for name in profiles {
+            let profile = Some(name.clone());
+            self.actions.register(CommandAction::new(
+                format!("profile.{}", name),
+                format!("Switch env profile: {}", name),
+                "Profiles",
+                move || {
+                    let profile = profile.clone();
+                    async move { Message::SwitchEnvProfile(profile) }
+                },
+            ));
+        }
+        if self.config.active_env_profile.is_some() {
+            self.actions.register(CommandAction::new("profile.none", "Switch env profile: none", "Profiles", || async {
+                Message::SwitchEnvProfile(None)
+            }));
+        }
+        let config_profiles = config::ConfigProfileManager::new().map(|manager| manager.names()).unwrap_or_default();
+        for name in config_profiles {
+            if self.config.active_profile.as_ref() == Some(&name) {
+                continue;
+            }
+            let profile = name.clone();
+            self.actions.register(
+                CommandAction::new(
+                    format!("config_profile.{}", name),
+                    format!("Switch config profile: {}", name),
+                    "Profiles",
+                    move || {
+                        let profile = profile.clone();
+                        async move { Message::SwitchConfigProfile(profile) }
+                    },
+                )
+                .with_description("Preferences and theme; blocks and tabs stay"),
+            );
+        }
+
+        self.actions.remove_category("Jobs");
+        let running = self.pty.jobs().list();
+        if !running.is_empty() {
+            self.actions.register(CommandAction::new("jobs.list", "Show running commands", "Jobs", || async {
+                Message::ShowJobs
+            }));
+        }
+        let now = chrono::Utc::now();
+        for job in running {
+            let summary = job.summary(jobs::usage(job.pid), now);
+            let block_id = job.block_id;
+            let show = CommandAction::new(format!("jobs.show.{}", block_id), format!("Go to: {}", job.command), "Jobs", move || async move {
+                Message::ShowJob(block_id)
+            });
+            self.actions.register(show.with_description(summary.clone()));
+            for signal in [jobs::JobSignal::Kill, jobs::JobSignal::Stop, jobs::JobSignal::Continue] {
+                let action = CommandAction::new(
+                    format!("jobs.{}.{}", signal.verb().to_lowercase(), block_id),
+                    format!("{}: {}", signal.verb(), job.command),
+                    "Jobs",
+                    move || async move { Message::SignalJob(block_id, signal) },
+                );
+                self.actions.register(action.with_description(summary.clone()));
+            }
+        }
+
+        self.actions.remove_category("Containers");
+        self.actions.register(
+            CommandAction::new("containers.list", "List containers", "Containers", || async { Message::ListContainers })
+                .with_description("Running Docker and Podman containers, to run commands in"),
+        );
+        if let Some(current) = &self.exec_container {
+            self.actions.register(CommandAction::new(
+                "containers.leave",
+                format!("Leave container {}", current.name),
+                "Containers",
+                || async { Message::ExecInContainer(None) },
+            ));
+        }
+        for container in &self.containers {
+            let summary = container.summary();
+            let target = container.clone();
+            let exec = CommandAction::new(
+                format!("containers.exec.{}", container.id),
+                format!("Run commands in {}", container.name),
+                "Containers",
+                move || {
+                    let target = target.clone();
+                    async move { Message::ExecInContainer(Some(target)) }
+                },
+            );
+            self.actions.register(exec.with_description(summary.clone()));
+            let target = container.clone();
+            let logs = CommandAction::new(
+                format!("containers.logs.{}", container.id),
+                format!("Logs: {}", container.name),
+                "Containers",
+                move || {
+                    let target = target.clone();
+                    async move { Message::ContainerLogs(target) }
+                },
+            );
+            self.actions.register(logs.with_description(summary.clone()));
+            for action in [containers::ContainerAction::Stop, containers::ContainerAction::Restart] {
+                let target = container.clone();
+                let request = CommandAction::new(
+                    format!("containers.{}.{}", action.verb().to_lowercase(), container.id),
+                    format!("{}: {}", action.verb(), container.name),
+                    "Containers",
+                    move || {
+                        let target = target.clone();
+                        async move { Message::RequestContainerAction(target, action) }
+                    },
+                );
+                self.actions.register(request.with_description(summary.clone()));
+            }
+        }
+
+        let plugin_commands = self.plugins.commands();
+        for (plugin, _) in &plugin_commands {
+            self.actions.remove_category(plugin);
+        }
+        for (plugin, command) in plugin_commands {
+            let line = command.clone();
+            self.actions.register(CommandAction::new(format!("plugin.{}.{}", plugin, command), format!("Run {}", command), plugin, move || {
+                let line = line.clone();
+                async move { Message::RunCommandLine(line) }
+            }));
+        }
+    }
+
+    /// What palette search looks through: history, indexed blocks, and the
+    /// messages of every conversation branch
+    fn search_corpus(&self) -> search::SearchCorpus {
+        let mut messages = Vec::new();
+        if let Some(tree) = self.agent_mode.as_ref().and_then(|agent| agent.conversations.as_ref()) {
+            // Branches share the messages before their fork point
+            let mut seen = std::collections::HashSet::new();
+            for message in tree.branches().iter().flat_map(|branch| branch.messages.iter()) {
+                if !matches!(message.role, agent_mode_eval::conversation::MessageRole::System) && seen.insert(message.id) {
+                    messages.push(search::MessageEntry {
+                        id: message.id,
+                        content: message.content.clone(),
+                        timestamp: message.timestamp,
+                    });
+                }
+            }
+        }
+        search::SearchCorpus {
+            history: self.history.entries().to_vec(),
+            blocks: self.search_index.clone(),
+            messages,
+            workflows: Vec::new(),
+        }
+    }
+
+    /// Focus a block and scroll it into view
+    fn show_block(&mut self, block_id: Uuid) -> Command<Message> {
+        let Some(index) = self.blocks.iter().position(|b| b.id == block_id) else {
+            self.status_messages.push("That block is no longer open".to_string(), std::time::Instant::now());
+            return Command::none();
+        };
+        self.focused_block = Some(block_id);
+        self.scroll_to_moved_block(Some(index))
+    }
+
+    /// Move focus `step` stops through the visible command blocks, where
+    /// each shell prompt in a block is a stop of its own
+    fn jump_to_prompt(&mut self, step: isize) -> Command<Message> {
+        let visible = block::visible(&self.blocks, self.block_filter);
+        let stops: Vec<(usize, Uuid, Option<usize>)> = visible
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| matches!(b.content, BlockContent::Command { .. }))
+            .flat_map(|(index, b)| match b.prompts().len() {
+                0 => vec![(index, b.id, None)],
+                count => (0..count).map(|prompt| (index, b.id, Some(prompt))).collect(),
+            })
+            .collect();
+        if stops.is_empty() {
+            return Command::none();
+        }
+        let current = self.focused_block.and_then(|focused| {
+            let cursor = visible.iter().find(|b| b.id == focused).and_then(|b| b.prompt_cursor());
+            // Before any prompt is picked, the block's first stop stands in
+            stops.iter().position(|&(_, id, prompt)| id == focused && (cursor.is_none() || prompt == cursor))
+        });
+        let target = match current {
+            Some(current) => (current as isize + step).clamp(0, stops.len() as isize - 1) as usize,
+            None if step < 0 => stops.len() - 1,
+            None => 0,
+        };
+        let (index, block_id, prompt) = stops[target];
+        let count = visible.len();
+        for block in &mut self.blocks {
+            block.set_prompt_cursor(if block.id == block_id { prompt } else { None });
+        }
+        self.focused_block = Some(block_id);
+
+        let position = index as f32 / count.saturating_sub(1).max(1) as f32;
+        self.scroll.expect_jump();
+        scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset { x: 0.0, y: position })
+    }
+
+    /// Scroll the block with the current search match into view
+    fn show_current_match(&mut self) -> Command<Message> {
+        let Some(block_id) = self.block_search.as_ref().and_then(|search| search.current()).map(|found| found.block) else {
+            return Command::none();
+        };
+        let visible = block::visible(&self.blocks, self.block_filter);
+        let Some(index) = visible.iter().position(|b| b.id == block_id) else {
+            return Command::none();
+        };
+        let position = index as f32 / visible.len().saturating_sub(1).max(1) as f32;
+        self.scroll.expect_jump();
+        scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset { x: 0.0, y: position })
+    }
+
+    /// Show the block of a conversation message, switching to the branch
+    /// that holds it when it isn't on screen
+    fn show_message(&mut self, message_id: Uuid) -> Command<Message> {
+        let shown = |blocks: &[Block]| blocks.iter().rev().find(|b| b.message_id() == Some(message_id)).map(|b| b.id);
+        if let Some(block_id) = shown(&self.blocks) {
+            return self.show_block(block_id);
+        }
+        let branch = self.agent_mode
+            .as_ref()
+            .and_then(|agent| agent.conversations.as_ref())
+            .and_then(|tree| tree.branches().iter().find(|branch| branch.position_of(message_id).is_some()))
+            .map(|branch| branch.id);
+        let Some(branch) = branch else {
+            self.status_messages.push("That message is no longer open".to_string(), std::time::Instant::now());
+            return Command::none();
+        };
+        let switched = self.update(Message::SwitchBranch(branch));
+        match shown(&self.blocks) {
+            Some(block_id) => Command::batch([switched, self.show_block(block_id)]),
+            None => switched,
+        }
+    }
+
+    /// Keep a moved block in view. Moving to the end resumes following new
+    /// output; anywhere else anchors the view at the block's position.
+    fn scroll_to_moved_block(&mut self, index: Option<usize>) -> Command<Message> {
+        let Some(index) = index else {
+            return Command::none();
+        };
+        if index + 1 == self.blocks.len() {
+            self.scroll.jump_to_bottom();
+            return scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END);
+        }
+        let position = index as f32 / (self.blocks.len() - 1) as f32;
+        self.scroll.expect_jump();
+        scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset { x: 0.0, y: position })
+    }
+
+    fn status_context(&self) -> StatusContext {
+        let mode = if self.config.preferences.privacy.incognito_mode {
+            status_line::Mode::Incognito
+        } else if self.agent_enabled {
+            status_line::Mode::Agent
+        } else {
+            status_line::Mode::Normal
+        };
+        let running = self.blocks
+            .iter()
+            .filter(|b| matches!(b.content, BlockContent::Command { exit_code: None, .. }))
+            .count();
+
+        StatusContext {
+            mode,
+            cwd: std::env::current_dir().map(|cwd| status_line::display_path(&cwd)).unwrap_or_default(),
+            git_branch: self.git_branch.clone(),
+            env_profile: self.config.active_env_profile.clone(),
+            running,
+            queued: usize::from(self.startup_command.is_some()),
+            ai_busy: self.agent_streaming,
+            idle: self.idle.state().is_idle(),
+            read_only: self.read_only.is_enabled(),
+            message: self.status_messages.current(std::time::Instant::now()).map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    /// One monospace row, laid out for however many columns fit
+    fn create_status_line(&self) -> Element<Message> {
+        let context = self.status_context();
+        let prefs = self.responsive.status_line(&self.config.preferences.ui.status_line);
+        let frame = self.status_frame;
+
+        container(iced::widget::responsive(move |size| {
+            // Monospace advance is roughly 0.6em at the 12px status font
+            let columns = (size.width / 7.2) as usize;
+            text(status_line::render(&context, &prefs, columns, frame))
+                .font(iced::Font::MONOSPACE)
+                .size(12)
+                .into()
+        }))
+        .height(iced::Length::Fixed(18.0))
+        .into()
+    }
+
+    /// Exactly what will be uploaded, so nothing leaves without being seen
+    fn create_share_preview<'a>(&self, preview: &'a str) -> Element<'a, Message> {
+        let destination = match &self.config.preferences.share.target {
+            config::ShareTarget::Gist { public: true } => "a public GitHub gist".to_string(),
+            config::ShareTarget::Gist { public: false } => "a secret GitHub gist".to_string(),
+            config::ShareTarget::Post { url } => url.clone(),
+            config::ShareTarget::SelfHosted { base_url } => base_url.clone(),
+        };
+
+        container(
+            column![
+                text(format!("Share to {}?", destination)).size(14),
+                scrollable(text(preview).size(12)).height(iced::Length::Fixed(200.0)),
+                row![
+                    button("Share").on_press(Message::ConfirmShare),
+                    button("Cancel").on_press(Message::CancelShare),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    /// Destination and options for mirroring a running block's output
+    fn create_tee_prompt<'a>(&self, prompt: &'a tee::TeePrompt) -> Element<'a, Message> {
+        container(
+            column![
+                text("Also write this block's output to:").size(14),
+                row![
+                    text_input("Path...", &prompt.path)
+                        .on_input(Message::TeePathChanged)
+                        .on_submit(Message::ConfirmTee),
+                    button("Browse…").on_press(Message::BrowseTeePath),
+                ]
+                .spacing(8),
+                checkbox("Include the output so far", prompt.options.backfill).on_toggle(Message::TeeBackfillToggled),
+                checkbox("Strip colors and other escape sequences", prompt.options.strip_ansi)
+                    .on_toggle(Message::TeeStripAnsiToggled),
+                row![
+                    button("Start").on_press_maybe((!prompt.path.trim().is_empty()).then_some(Message::ConfirmTee)),
+                    button("Cancel").on_press(Message::CancelTee),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    /// Format and destination for saving a block
+    fn create_export_prompt<'a>(&self, prompt: &'a block_export::ExportPrompt) -> Element<'a, Message> {
+        container(
+            column![
+                text("Export this block to:").size(14),
+                prompt.path.view_with("Path...", Message::ExportPathChanged, Message::ConfirmExport),
+                pick_list(&block_export::ExportFormat::ALL[..], Some(prompt.format), Message::ExportFormatSelected),
+                row![
+                    button("Export").on_press_maybe((!prompt.path.value().trim().is_empty()).then_some(Message::ConfirmExport)),
+                    button("Cancel").on_press(Message::CancelExport),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    /// Format and destination for a transcript of the whole session
+    fn create_session_export_prompt<'a>(&self, prompt: &'a session_export::SessionExportPrompt) -> Element<'a, Message> {
+        container(
+            column![
+                text(format!("Export all {} blocks to:", self.blocks.len())).size(14),
+                prompt.path.view_with("Path...", Message::SessionExportPathChanged, Message::ConfirmSessionExport),
+                pick_list(&session_export::SessionFormat::ALL[..], Some(prompt.format), Message::SessionExportFormatSelected),
+                row![
+                    button("Export").on_press_maybe(
+                        (!prompt.path.value().trim().is_empty()).then_some(Message::ConfirmSessionExport)
+                    ),
+                    button("Cancel").on_press(Message::CancelSessionExport),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    /// Everything that will be deleted, listed before anything is
+    /// Tabs by title, with a button for a new one; right-click renames
+    fn create_tab_bar(&self) -> Option<Element<Message>> {
+        if !self.config.preferences.ui.show_tab_bar.shows(self.tabs.count()) {
+            return None;
+        }
+        let mut bar = row![].spacing(4);
+        for (index, tab) in self.tabs.iter().enumerate() {
+            if let Some((_, name)) = self.renaming_tab.as_ref().filter(|(renaming, _)| *renaming == index) {
+                bar = bar.push(
+                    text_input("Tab name", name)
+                        .id(tab_rename_input_id())
+                        .on_input(Message::TabRenameChanged)
+                        .on_submit(Message::FinishTabRename)
+                        .size(13)
+                        .width(iced::Length::Fixed(160.0))
+                );
+                continue;
+            }
+            let title = match tab.state() {
+                Some(state) => tabs::title(tab.name(), &state.pane.blocks),
+                None => tabs::title(tab.name(), &self.blocks),
+            };
+            let label = if index < 9 { format!("{} {}", index + 1, title) } else { title };
+            let tab_button = button(text(label).size(13))
+                .padding([4, 10])
+                .style(if index == self.tabs.active() { button::primary } else { button::secondary })
+                .on_press(Message::SelectTab(index));
+            bar = bar.push(iced::widget::mouse_area(tab_button).on_right_press(Message::StartTabRename(index)));
+        }
+        bar = bar.push(button(text("+").size(13)).padding([4, 10]).on_press(Message::NewTab));
+        Some(bar.into())
+    }
+
+    fn create_tab_close_confirmation(&self) -> Element<Message> {
+        container(
+            column![
+                text("Commands are still running in this tab. Close it anyway?").size(14),
+                row![
+                    button("Close tab").on_press(Message::ConfirmCloseTab),
+                    button("Cancel").on_press(Message::CancelCloseTab),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    fn create_clear_confirmation(&self, target: clear::ClearTarget) -> Element<Message> {
+        let plan = clear::Clearer::resolve(false).map(|clearer| clearer.plan(target)).unwrap_or_default();
+        let mut details = column![text(format!("Clear {}?", target.describe())).size(14)].spacing(4);
+        if matches!(target, clear::ClearTarget::Blocks | clear::ClearTarget::Conversations | clear::ClearTarget::All) {
+            details = details.push(text("Blocks on screen are removed too.").size(12));
+        }
+        for path in &plan {
+            details = details.push(text(format!("Deletes {}", path.display())).size(12));
+        }
+
+        container(
+            column![
+                details,
+                row![
+                    button("Clear").on_press(Message::ConfirmClear),
+                    button("Cancel").on_press(Message::CancelClear),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    fn create_container_action_confirmation(
+        &self,
+        target: &containers::Container,
+        action: containers::ContainerAction,
+    ) -> Element<Message> {
+        let mut details = column![text(format!("{} container {}?", action.verb(), target.name)).size(14)].spacing(4);
+        details = details.push(text(target.summary()).size(12));
+        if action == containers::ContainerAction::Stop {
+            details = details.push(text("Anything running in it is stopped too.").size(12));
+        }
+
+        container(
+            column![
+                details,
+                row![
+                    button(action.verb()).on_press(Message::ConfirmContainerAction),
+                    button("Cancel").on_press(Message::CancelContainerAction),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    /// The commands of a generated snippet, listed before any of them runs
+    fn create_code_run_confirmation(&self, commands: &[String]) -> Element<Message> {
+        let question = match commands.len() {
+            1 => "Run this command from the assistant?".to_string(),
+            n => format!("Run these {} commands from the assistant?", n),
+        };
+        let mut details = column![text(question).size(14)].spacing(4);
+        if let safety::Verdict::Destructive(reason) = safety::classify(&commands.join("\n")) {
+            details = details.push(text(format!("⚠ This snippet {}.", reason)).size(12));
+        }
+        for command in commands {
+            details = details.push(text(format!("$ {}", command)).size(12));
+        }
+
+        container(
+            column![
+                details,
+                row![
+                    button("Run").on_press(Message::ConfirmCodeRun),
+                    button("Cancel").on_press(Message::CancelCodeRun),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    /// The agent's next command or file write, waiting for a decision
+    fn create_tool_approval(&self, call: &ToolCall) -> Element<Message> {
+        let mut details = column![
+            text(format!("The assistant wants to use `{}`", call.name)).size(14),
+            text(self.redactor.redact(&call.describe())).font(iced::Font::MONOSPACE).size(12),
+        ]
+        .spacing(4);
+        if let Some(command) = call.arguments.get("command").and_then(|v| v.as_str()) {
+            if let safety::Verdict::Destructive(reason) = safety::classify(command) {
+                details = details.push(text(format!("⚠ This command {}.", reason)).size(12));
+            }
+        }
+        if self.pending_tool_approvals.len() > 1 {
+            details = details.push(text(format!("{} more waiting", self.pending_tool_approvals.len() - 1)).size(12));
+        }
+
+        container(
+            column![
+                details,
+                row![
+                    button("Approve").on_press(Message::ToolApproval(call.id.clone(), true)),
+                    button("Deny").on_press(Message::ToolApproval(call.id.clone(), false)),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    /// Clear saved state along with what's loaded of it
+    fn clear_state(&mut self, target: clear::ClearTarget) -> Result<clear::ClearReport, clear::ClearError> {
+        use clear::ClearTarget;
+
+        let report = clear::Clearer::resolve(false)?.clear(target, &mut self.history)?;
+        if matches!(target, ClearTarget::History | ClearTarget::All) {
+            self.suggestions.clear();
+            self.active_suggestion = None;
+        }
+        if matches!(target, ClearTarget::Blocks | ClearTarget::All) {
+            std::sync::Arc::make_mut(&mut self.search_index)
+                .clear()
+                .map_err(|e| clear::ClearError::IoError(e.to_string()))?;
+            self.blocks.clear();
+            self.agent_reply_block = None;
+            self.focused_block = None;
+            self.scroll.jump_to_bottom();
+        }
+        if matches!(target, ClearTarget::Conversations | ClearTarget::All) {
+            if let Some(agent) = self.agent_mode.as_mut() {
+                agent.clear_conversation();
+            }
+            self.blocks.retain(|b| !matches!(b.content, BlockContent::AgentMessage { .. } | BlockContent::UserMessage { .. }));
+            self.agent_reply_block = None;
+            self.agent_streaming = false;
+            self.editing_prompt = None;
+            self.drop_tool_round();
+        }
+        Ok(report)
+    }
+
+    /// The trimmed output and its size, before anything is sent to the AI
+    fn create_ai_context_preview<'a>(&self, context: &'a context::BlockContext) -> Element<'a, Message> {
+        let limits = &self.config.preferences.ai_context;
+        let mut size = format!("About {} tokens", i18n::format_number(context.estimated_tokens() as u64));
+        if context.omitted_lines() > 0 {
+            size.push_str(&format!(
+                "; {} of {} lines omitted",
+                i18n::format_number(context.omitted_lines() as u64),
+                i18n::format_number(context.total_lines as u64)
+            ));
+        }
+
+        let mut actions = row![button("Send").on_press(Message::ConfirmAiContext)].spacing(8);
+        if context.is_over(limits) {
+            size.push_str(&format!(", over the {} token limit", i18n::format_number(limits.max_tokens as u64)));
+            actions = actions.push(button("Summarize first").on_press(Message::SummarizeAiContext));
+        }
+        actions = actions.push(button("Cancel").on_press(Message::CancelAiContext));
+
+        container(
+            column![
+                text(format!("Ask the AI about `{}`?", context.command)).size(14),
+                text(size).size(12),
+                scrollable(text(context.render()).size(12)).height(iced::Length::Fixed(200.0)),
+                actions,
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    /// Asked once; the answer can be changed later under Settings > Privacy
+    fn create_crash_report_prompt(&self) -> Element<Message> {
+        container(
+            column![
+                text("Send crash reports to help fix NeoTerm?").size(14),
+                text("Reports are scrubbed of commands, environment values and API keys. They are always saved locally; see `neoterm crashes list`.").size(12),
+                row![
+                    button("Send reports").on_press(Message::CrashReportConsent(true)),
+                    button("Don't send").on_press(Message::CrashReportConsent(false)),
+                ]
+                .spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .width(iced::Length::Fill)
+        .into()
+    }
+
+    /// Shown while the loaded conversation is over the token budget, until dismissed
+    fn create_budget_warning(&self) -> Option<Element<Message>> {
+        let budget = self.config.preferences.ai.token_budget;
+        let tree = self.agent_mode.as_ref()?.conversations.as_ref()?;
+        let usage = tree.usage();
+        if !usage.exceeds(budget) || self.budget_warning_dismissed == Some(tree.root().id) {
+            return None;
+        }
+        Some(
+            container(
+                row![
+                    text(format!(
+                        "⚠ This conversation has used {}, over the budget of {} tokens. Start a new one to keep costs down.",
+                        usage,
+                        i18n::format_number(budget.unwrap_or_default()),
+                    ))
+                    .size(13)
+                    .width(iced::Length::Fill),
+                    button(text("Dismiss").size(12)).on_press(Message::DismissBudgetWarning),
+                ]
+                .spacing(8)
+                .align_items(iced::Alignment::Center)
+            )
+            .padding(12)
+            .width(iced::Length::Fill)
+            .into()
+        )
+    }
+
+    /// Everything typed, including the earlier lines of a multi-line command
+    fn full_input(&self) -> String {
+        let mut lines = self.input_lines.clone();
+        lines.push(self.current_input.clone());
+        lines.join("\n")
+    }
+
+    /// Replace what's typed; all but the last line of `text` go above the input
+    fn set_input(&mut self, text: &str) {
+        let mut lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+        self.current_input = lines.pop().unwrap_or_default();
+        self.input_lines = lines;
+        self.autosuggest.clear();
+    }
+
+    /// Whether ghost text is shown at all; never in incognito
+    fn autosuggestions_enabled(&self) -> bool {
+        self.config.preferences.editor.auto_suggestions && !self.config.preferences.privacy.incognito_mode
+    }
+
+    /// Suggest from history for the new input, and schedule an AI lookup
+    /// for when typing pauses if history has nothing
+    fn update_autosuggestion(&mut self) -> Command<Message> {
+        if !self.autosuggestions_enabled() || self.history_search.is_some() {
+            self.autosuggest.clear();
+            return Command::none();
+        }
+        let ai = self.config.preferences.editor.ai_suggestions && !self.agent_enabled && self.agent_mode.is_some();
+        let input = self.current_input.clone();
+        match self.autosuggest.input_changed(&input, self.history.entries(), ai) {
+            Some(generation) => Command::perform(tokio::time::sleep(autosuggest::AI_DEBOUNCE), move |_| {
+                Message::AutosuggestDue(generation)
+            }),
+            None => Command::none(),
+        }
+    }
+
+    /// Ask the AI to complete the input, unless it changed since
+    /// `generation` or a request went out too recently
+    fn request_ai_suggestion(&mut self, generation: u64) -> Command<Message> {
+        let now = std::time::Instant::now();
+        match self.autosuggest.due(generation, now) {
+            autosuggest::Due::Ignore => return Command::none(),
+            autosuggest::Due::Wait(delay) => {
+                return Command::perform(tokio::time::sleep(delay), move |_| Message::AutosuggestDue(generation));
+            }
+            autosuggest::Due::Request => {}
+        }
+        // Settings may have changed while waiting
+        if !self.autosuggestions_enabled() || !self.config.preferences.editor.ai_suggestions {
+            return Command::none();
+        }
+        if !self.ai_allowed(AiRequest::Autocomplete) {
+            return Command::none();
+        }
+        let Some(client) = self.agent_mode.as_ref().and_then(|agent| autosuggest::ai_client(&agent.ai_client.config)) else {
+            return Command::none();
+        };
+        let input = self.current_input.clone();
+        let entries = self.history.entries();
+        let recent: Vec<String> = entries[entries.len().saturating_sub(autosuggest::AI_HISTORY_LINES)..]
+            .iter()
+            .map(|entry| self.redactor.redact(entry))
+            .collect();
+        let prompt = autosuggest::ai_prompt(&self.redactor.redact(&input), self.shell_manager.shell_name(), &recent);
+        let registration = self.autosuggest.start_request(now);
+
+        let message = agent_mode_eval::ai_client::AiMessage { role: "user".to_string(), content: prompt, tool_calls: None, tool_call_id: None };
+        Command::perform(
+            async move {
+                let started = std::time::Instant::now();
+                // Aborted as soon as the input changes, dropping the connection
+                match futures::future::Abortable::new(client.complete(vec![message], None), registration).await {
+                    Ok(Ok(response)) => Ok((response.content, started.elapsed())),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(futures::future::Aborted) => Err("cancelled".to_string()),
+                }
+            },
+            move |result| Message::AutosuggestReply(generation, input, result),
+        )
+    }
+
+    fn generate_suggestions(&self, input: &str) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        
+        // Add command history matches
+        for cmd in self.history.entries() {
+            if cmd.contains(input) && cmd != input {
+                suggestions.push(cmd.clone());
+            }
+        }
+        
+        // Add common commands
+        let common_commands = ["ls", "cd", "git", "npm", "cargo", "docker", "kubectl"];
+        for cmd in &common_commands {
+            if cmd.starts_with(input) && !input.is_empty() {
+                suggestions.push(cmd.to_string());
+            }
+        }
+        
+        // Add agent mode suggestions
+        if self.agent_enabled {
+            let agent_suggestions = [
+                "explain this command:",
+                "help me with",
+                "what does this error mean:",
+                "how do I",
+                "show me how to",
+            ];
+            for suggestion in &agent_suggestions {
+                if suggestion.starts_with(input) && !input.is_empty() {
+                    suggestions.push(suggestion.to_string());
+                }
+            }
+        }
+        
+        suggestions.truncate(5);
+        suggestions
+    }
+
+    /// The prompt in vim's normal and visual mode, which the text input
+    /// can't show: a block cursor, or the selection highlighted
+    fn create_vim_line(&self) -> Element<Message> {
+        let chars: Vec<char> = self.current_input.chars().collect();
+        let marked = self.vim.selection().unwrap_or(self.vim.cursor()..self.vim.cursor() + 1);
+        let (start, end) = (marked.start.min(chars.len()), marked.end.min(chars.len()));
+        let mut under_cursor: String = chars[start..end].iter().collect();
+        if under_cursor.is_empty() {
+            under_cursor.push(' ');
+        }
+        container(row![
+            text(chars[..start].iter().collect::<String>()).size(16),
+            container(text(under_cursor).size(16)).style(|theme: &Theme| container::Appearance {
+                background: Some(theme.palette().primary.scale_alpha(0.5).into()),
+                ..Default::default()
+            }),
+            text(chars[end..].iter().collect::<String>()).size(16),
+        ])
+        .padding(12)
+        .width(iced::Length::Fill)
+        .style(|theme: &Theme| container::Appearance {
+            border: iced::Border { color: theme.palette().text.scale_alpha(0.3), width: 1.0, radius: 2.0.into() },
+            ..Default::default()
+        })
+        .into()
+    }
+
+    fn create_input_view(&self) -> Element<Message> {
+        if let Some(search) = &self.history_search {
+            return self.create_history_search_view(search);
+        }
+        let prompt_indicator = if self.agent_enabled {
+            "🤖 ".to_string()
+        } else {
+            let git = self.git_status.as_ref().map(|status| format!("({}) ", status.indicator())).unwrap_or_default();
+            format!("{} {}$ ", status_line::short_path(self.shell_manager.cwd()), git)
+        };
+
+        let interactive = self.stdin_target().is_some();
+        let placeholder = if let Some(reason) = self.read_only.reason() {
+            reason.explanation()
+        } else if interactive {
+            "Typing goes to the running command; click another block to stop..."
+        } else if self.editing_prompt.is_some() {
+            "Edit your prompt and press Enter to resend (Esc to cancel)..."
+        } else if self.agent_enabled {
+            "Ask me anything or enter a command..."
+        } else {
+            "Enter command..."
+        };
+
+        let mut input = text_input(placeholder, &self.current_input)
+            .id(command_input_id())
+            .padding(12)
+            .size(16);
+        // Disabled while keys go to a command, so they aren't typed twice
+        if !interactive {
+            input = input.on_input(Message::InputChanged);
+        }
+        // Typing still works in read-only mode; submitting doesn't
+        if !self.read_only.is_enabled() {
+            input = input.on_submit(Message::ExecuteCommand);
+        }
+        // Ghost text is laid over the input, after an invisible copy of
+        // what's typed so it starts at the cursor
+        let suggestion = self
+            .autosuggest
+            .suggestion()
+            .filter(|suggestion| suggestion.input == self.current_input && self.input_lines.is_empty());
+        let input: Element<Message> = match suggestion {
+            Some(suggestion) => iced::widget::stack![
+                input,
+                container(row![
+                    text(&suggestion.input).size(16).style(iced::theme::Text::Color(iced::Color::TRANSPARENT)),
+                    text(suggestion.remainder()).size(16).style(iced::theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5))),
+                ])
+                .padding(12),
+            ]
+            .into(),
+            None => input.into(),
+        };
+        let vim_mode = self.config.preferences.editor.vim_mode.then(|| self.vim.mode());
+        let input = match vim_mode {
+            Some(vim::Mode::Normal | vim::Mode::Visual) => self.create_vim_line(),
+            _ => input,
+        };
+
+        let mut input_with_prompt = row![
+            text(if self.read_only.is_enabled() {
+                "🔒 ".to_string()
+            } else if !self.input_lines.is_empty() {
+                "> ".to_string()
+            } else {
+                prompt_indicator.clone()
+            })
+            .size(16),
+        ].spacing(8);
+        if let Some(mode) = vim_mode {
+            let color = match mode {
+                vim::Mode::Insert => iced::Color::from_rgb8(0x3a, 0x7a, 0x4b),
+                vim::Mode::Normal => iced::Color::from_rgb8(0x3a, 0x5a, 0x9a),
+                vim::Mode::Visual => iced::Color::from_rgb8(0x9a, 0x5a, 0x2a),
+            };
+            input_with_prompt = input_with_prompt.push(
+                container(text(mode.label()).size(12)).padding([4, 8]).style(container::Appearance {
+                    background: Some(iced::Background::Color(color)),
+                    text_color: Some(iced::Color::WHITE),
+                    border: iced::Border { radius: 4.0.into(), ..Default::default() },
+                    ..Default::default()
+                }),
+            );
+        }
+        let input_with_prompt = input_with_prompt.push(input);
+        // Marks a command written from a sentence until it's edited
+        let input_with_prompt = match &self.translated_command {
+            Some((request, command)) if *command == self.full_input() => input_with_prompt.push(tooltip(
+                container(text("✨ AI translated").size(12)).padding([4, 8]).style(container::Appearance {
+                    background: Some(iced::Background::Color(iced::Color::from_rgb8(0x4b, 0x3a, 0x7a))),
+                    text_color: Some(iced::Color::WHITE),
+                    border: iced::Border { radius: 10.0.into(), ..Default::default() },
+                    ..Default::default()
+                }),
+                text(format!("Written from: {} (edit before running, or Ctrl+Enter to run as typed)", request)).size(12),
+                tooltip::Position::Top,
+            )),
+            _ => input_with_prompt,
+        };
+
+        let suggestions_view = if let Some(completion) = &self.completion {
+            self.create_completion_view(completion)
+        } else if !self.suggestions.is_empty() {
+            column(
+                self.suggestions
+                    .iter()
+                    .enumerate()
+                    .map(|(i, suggestion)| {
+                        self.hinted(
+                            button(text(suggestion))
+                                .on_press(Message::SuggestionSelected(i))
+                                .width(iced::Length::Fill),
+                            hints::InteractableKind::Suggestion,
+                            suggestion,
+                            Message::SuggestionSelected(i),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            )
+            .spacing(2)
+            .into()
+        } else {
+            column![].into()
+        };
+
+        if self.input_lines.is_empty() {
+            return column![input_with_prompt, suggestions_view].spacing(4).into();
+        }
+        // Earlier lines grow the input upwards, then scroll
+        const LINE_HEIGHT: f32 = 22.0;
+        let max_lines = self.config.preferences.ui.input_max_lines.max(1);
+        let earlier = column(
+            self.input_lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let prompt = if i == 0 { prompt_indicator.as_str() } else { "> " };
+                    text(format!("{}{}", prompt, line)).size(16).into()
+                })
+                .collect::<Vec<Element<Message>>>(),
+        );
+        let earlier = container(scrollable(earlier).width(iced::Length::Fill))
+            .max_height(LINE_HEIGHT * max_lines as f32)
+            .padding([0, 12]);
+        column![
+            earlier,
+            input_with_prompt,
+            text("Shift+Enter for a new line · Esc on an empty line goes back").size(12),
+            suggestions_view,
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    /// Ctrl+R: the query in place of the input, candidates under it
+    fn create_completion_view<'a>(&'a self, completion: &'a completion::Completions) -> Element<'a, Message> {
+        let mut list = column![].spacing(2);
+        for (i, candidate) in completion.candidates.iter().enumerate() {
+            let marker = if completion.selected == Some(i) { "▸ " } else { "  " };
+            list = list.push(
+                button(text(format!("{}{}", marker, candidate.label)).size(14))
+                    .on_press(Message::CompletionPicked(i))
+                    .width(iced::Length::Fill),
+            );
+        }
+        column![list, text("Tab / Shift+Tab to cycle · Esc to close").size(12)]
+            .spacing(4)
+            .into()
+    }
+
+    /// One chip per filter; the active one is highlighted
+    fn create_filter_chips(&self) -> Element<Message> {
+        let chips = block::BlockFilter::ALL.into_iter().map(|filter| {
+            let style = if filter == self.block_filter { button::primary } else { button::secondary };
+            button(text(filter.label()).size(12))
+                .padding([2, 10])
+                .style(style)
+                .on_press(Message::SetBlockFilter(filter))
+                .into()
+        });
+        iced::widget::Row::with_children(chips).spacing(4).into()
+    }
+
+    fn create_block_search_bar<'a>(&'a self, search: &'a block_search::BlockSearch) -> Element<'a, Message> {
+        let counter = match (search.error(), search.count()) {
+            (Some(error), _) => format!("Invalid pattern: {}", error),
+            _ if search.query.is_empty() => String::new(),
+            (None, 0) => "No matches".to_string(),
+            (None, count) => format!("{} of {}", search.position() + 1, count),
+        };
+        row![
+            text_input("Search blocks...", &search.query)
+                .id(block_search_input_id())
+                .on_input(Message::BlockSearchChanged)
+                .on_submit(Message::NextBlockMatch)
+                .size(14)
+                .width(iced::Length::FillPortion(3)),
+            checkbox("Regex", search.regex).on_toggle(Message::BlockSearchRegexToggled),
+            text(counter).size(12).width(iced::Length::FillPortion(1)),
+            button(text("↑").size(12)).on_press(Message::PreviousBlockMatch),
+            button(text("↓").size(12)).on_press(Message::NextBlockMatch),
+            button(text("✕").size(12)).on_press(Message::CloseBlockSearch),
+        ]
+        .spacing(8)
+        .align_items(iced::Alignment::Center)
+        .into()
+    }
+
+    fn create_history_search_view<'a>(&'a self, search: &'a history::HistorySearch) -> Element<'a, Message> {
+        let input = text_input("Search history...", &search.query)
+            .id(command_input_id())
+            .on_input(Message::HistorySearchChanged)
+            .on_submit(Message::HistorySearchPick(search.selected))
+            .padding(12)
+            .size(16);
+        let candidates = self.history.search(&search.query, history::SEARCH_CANDIDATES);
+        let mut list = column![].spacing(2);
+        if candidates.is_empty() {
+            list = list.push(text("No matching commands").size(12));
+        }
+        for (i, candidate) in candidates.into_iter().enumerate() {
+            let marker = if i == search.selected { "▸ " } else { "  " };
+            list = list.push(
+                button(text(format!("{}{}", marker, candidate)))
+                    .on_press(Message::HistorySearchPick(i))
+                    .width(iced::Length::Fill),
+            );
+        }
+
+        column![
+            row![text("(reverse-i-search)").size(16), input].spacing(8),
+            list,
+            text("Enter to use · ↑/↓ or Ctrl+R to move · Esc to cancel").size(12),
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    fn create_toolbar(&self) -> Element<Message> {
+        let ai_ready = self.agent_mode.as_ref().is_some_and(|agent| agent.status().is_ready());
+        let tool = |label: &'static str, message: Message| {
+            self.hinted(button(text(label)).on_press(message.clone()), hints::InteractableKind::ToolbarButton, label, message)
+        };
+        let agent_button = tool(
+            match (ai_ready, self.agent_enabled) {
+                (false, _) => "🤖 AI not set up",
+                (true, true) => "🤖 Agent ON",
+                (true, false) => "🤖 Agent OFF",
+            },
+            Message::ToggleAgentMode,
+        );
+
+        if self.responsive.toolbar() == ToolbarLayout::Menu {
+            let menu = tool("☰", Message::ToggleToolbarMenu);
+            if !self.toolbar_menu_open {
+                return menu;
+            }
+            let mut entries = column![
+                menu,
+                agent_button,
+                tool("⚙️ Settings", Message::ToggleSettings),
+                tool("🔎 Find/Replace", Message::OpenFindReplace),
+                tool("☰ Templates", Message::OpenPalette),
+            ]
+            .spacing(4);
+            if !block::is_chronological(&self.blocks) {
+                entries = entries.push(tool("⇅ Sort by time", Message::SortBlocksByTime));
+            }
+            if let Some(control) = self.view_read_only_control() {
+                entries = entries.push(control);
+            }
+            return entries.into();
+        }
+
+        let mut toolbar = row![
+            agent_button,
+            tool("⚙️ Settings", Message::ToggleSettings),
+            tool("🔎 Find/Replace", Message::OpenFindReplace),
+            tool("☰ Templates", Message::OpenPalette),
+        ]
+        .spacing(8);
+
+        // Offered once blocks have been rearranged, so the original order is always recoverable
+        if !block::is_chronological(&self.blocks) {
+            toolbar = toolbar.push(tool("⇅ Sort by time", Message::SortBlocksByTime));
+        }
+        if let Some(control) = self.view_read_only_control() {
+            toolbar = toolbar.push(control);
+        }
+
+        // Branch switcher, once the conversation has been forked
+        if let Some(tree) = self.agent_mode.as_ref().and_then(|agent| agent.conversations.as_ref()) {
+            if tree.branch_count() > 1 {
+                let branches = tree.summaries();
+                let active = branches.iter().find(|b| b.id == tree.active_id()).cloned();
+                toolbar = toolbar
+                    .push(text(format!("⎇ {} branches", branches.len())).size(14))
+                    .push(pick_list(branches, active, |branch| Message::SwitchBranch(branch.id)));
+            }
+        }
+
+        toolbar.into()
+    }
+
+    /// Leaving read-only mode takes two clicks; viewers only get the explanation
+    fn view_read_only_control(&self) -> Option<Element<Message>> {
+        let reason = self.read_only.reason()?;
+        let explanation = text(reason.explanation()).size(12);
+        if !reason.can_exit() {
+            return Some(tooltip(button(text("🔒 Read-only")), explanation, tooltip::Position::Bottom).into());
+        }
+        if self.read_only.is_exit_armed() {
+            return Some(
+                row![
+                    text("Allow running commands?").size(14),
+                    button(text("Leave read-only")).on_press(Message::ConfirmReadOnlyExit),
+                    button(text("Cancel")).on_press(Message::CancelReadOnlyExit),
+                ]
+                .spacing(8)
+                .align_items(iced::Alignment::Center)
+                .into(),
+            );
+        }
+        let label = "🔒 Read-only";
+        Some(tooltip(
+            self.hinted(button(text(label)).on_press(Message::ArmReadOnlyExit), hints::InteractableKind::ToolbarButton, label, Message::ArmReadOnlyExit),
+            explanation,
+            tooltip::Position::Bottom,
+        ).into())
+    }
+
+    /// Whether an AI feature may run. Without credentials the first explicit
+    /// request shows a notice; everything else quietly does nothing.
+    fn ai_allowed(&mut self, request: AiRequest) -> bool {
+        let status = match &self.agent_mode {
+            Some(agent) => agent.status(),
+            None => AiStatus::of(&AgentConfig::default()),
+        };
+        match self.ai_gate.check(&status, request) {
+            Gate::Proceed => true,
+            Gate::Notice(notice) => {
+                self.blocks.push(Block::new_info(notice));
+                false
+            }
+            Gate::Skip => false,
+        }
+    }
+
+    /// `/ai status` and `/ai use-ollama`
+    fn handle_ai_command(&mut self, args: &str) {
+        match args {
+            "status" | "" => {
+                let status = self.agent_mode.as_ref().map(AgentMode::status);
+                let mut report = match status {
+                    Some(status) => format!("AI: {}", status),
+                    None => "AI: unavailable".to_string(),
+                };
+                if let Some(base_url) = self.ai_gate.ollama() {
+                    report.push_str(&format!("\nLocal Ollama daemon: {}", base_url));
+                }
+                self.blocks.push(Block::new_info(report));
+            }
+            "use-ollama" => match self.ai_gate.ollama() {
+                Some(_) => match AgentMode::new(AgentConfig::ollama()) {
+                    Ok(mut agent) => {
+                        agent.set_read_only(self.read_only.clone());
+                        self.agent_mode = Some(agent);
+                        self.sync_agent_host();
+                        self.agent_enabled = false;
+                        self.blocks.push(Block::new_info(
+                            "Using the local Ollama daemon. Toggle agent mode to start.".to_string()
+                        ));
+                    }
+                    Err(e) => self.blocks.push(Block::new_error(e.to_string())),
+                },
+                None => {
+                    self.blocks.push(Block::new_info("No local Ollama daemon was found.".to_string()));
+                }
+            },
+            _ => {
+                if self.ai_allowed(AiRequest::AiCommand) {
+                    self.blocks.push(Block::new_info("Usage: /ai status | /ai use-ollama".to_string()));
+                }
+            }
+        }
+    }
+
+    fn handle_agent_command(&mut self, command: String) -> Command<Message> {
+        let editing = self.editing_prompt.take();
+
+        if let Some(ref mut agent) = self.agent_mode {
+            self.current_input.clear();
+
+            // Record the prompt on the active branch before streaming from a snapshot.
+            // An edit replaces (or forks away from) the last turn.
+            let recorded = match editing {
+                Some(_) => agent.edit_last_user_message(command.clone()),
+                None => agent.push_user_message(command.clone()),
+            };
+            let message_id = match recorded {
+                Ok(id) => id,
+                Err(e) => {
+                    self.blocks.push(Block::new_error(e.to_string()));
+                    return Command::none();
+                }
+            };
+            if let Some(previous_prompt) = editing {
+                self.supersede_from(previous_prompt);
+            }
+            
+            // Add user message block
+            let user_block = Block::new_user_message(command.clone()).with_message_id(message_id);
+            self.blocks.push(user_block);
+
+            self.drop_tool_round();
+            self.tool_rounds_taken = 0;
+            self.start_agent_turn()
+        } else {
+            Command::none()
+        }
+    }
+
+    /// Whether submitting `input` should write a command from it rather than
+    /// run it: the preference is on, it reads as a sentence, and its first
+    /// word is neither a shell builtin nor a program on PATH
+    fn reads_as_request(&mut self, input: &str) -> bool {
+        if !self.config.preferences.ai.natural_language_commands || self.agent_mode.is_none() || input.contains('\n') {
+            return false;
+        }
+        let path_var = self
+            .shell_manager
+            .profile_env()
+            .get("PATH")
+            .map(std::ffi::OsString::from)
+            .or_else(|| std::env::var_os("PATH"))
+            .unwrap_or_default();
+        self.path_resolver.set_path(&path_var);
+        let program = input.split_whitespace().next().unwrap_or_default();
+        let runnable = shell::is_builtin(program)
+            || program.contains('/')
+            || self.plugins.plugin_for_command(program).is_some()
+            || !self.path_resolver.resolve(program).matches.is_empty();
+        natural_language_detection::NaturalLanguageDetector::default().is_natural_language(input, |_| runnable)
+    }
+
+    /// Ask the assistant for a command doing what the input describes; it
+    /// replaces the input once written
+    fn translate_to_command(&mut self) -> Command<Message> {
+        if !self.ai_allowed(AiRequest::AgentPrompt) {
+            return Command::none();
+        }
+        let Some(agent) = self.agent_mode.as_ref() else {
+            return Command::none();
+        };
+        let request = std::mem::take(&mut self.current_input);
+        self.history.push(request.clone());
+        let translation = agent.generate_command(&self.redactor.redact(&request), self.shell_manager.shell_name());
+        self.suggestions.clear();
+        self.status_messages.push("Writing a command for that…", std::time::Instant::now());
+        Command::perform(
+            async move { translation.run().await.map_err(|e| e.to_string()) },
+            move |result| Message::CommandTranslated(request, result),
+        )
+    }
+
+    /// Ask the assistant what a command's output means, outside the
+    /// conversation; the answer is attached under the block
+    fn explain_block(&mut self, block_id: Uuid) -> Command<Message> {
+        let Some(block) = self.blocks.iter().find(|b| b.id == block_id) else {
+            return Command::none();
+        };
+        let BlockContent::Command { input, exit_code, .. } = &block.content else {
+            return Command::none();
+        };
+        let lines: Vec<_> = block
+            .output_lines()
+            .into_iter()
+            .map(|line| context::OutputLine { text: self.redactor.redact(&line.text), ..line })
+            .collect();
+        let prompt = context::explain_prompt(&self.redactor.redact(input), *exit_code, &lines);
+        if !self.ai_allowed(AiRequest::AgentPrompt) {
+            return Command::none();
+        }
+        let Some(client) = self.agent_mode.as_ref().map(|agent| agent.ai_client.clone()) else {
+            return Command::none();
+        };
+        if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+            block.annotation = Some(block::Annotation::new(prompt.clone()));
+        }
+
+        let message = agent_mode_eval::ai_client::AiMessage { role: "user".to_string(), content: prompt, tool_calls: None, tool_call_id: None };
+        Command::perform(
+            async move {
+                client
+                    .complete(vec![message], None)
+                    .await
+                    .map(|response| response.content.trim().to_string())
+                    .map_err(|e| e.to_string())
+            },
+            move |result| Message::ExplanationReady(block_id, result),
+        )
+    }
+
+    /// A fix for a command that just failed: none for benign exit codes,
+    /// the recent one if the same command failed a moment ago, otherwise
+    /// a new request
+    fn auto_suggest_fix(&mut self, block_id: Uuid, exit_code: i32) -> Command<Message> {
+        let Some(input) = self.blocks.iter().find(|b| b.id == block_id).map(Block::title) else {
+            return Command::none();
+        };
+        if agent_mode_eval::fix::is_benign(&input, exit_code, &self.config.preferences.ai.benign_exits) {
+            return Command::none();
+        }
+        if let Some(fix) = self.fix_cache.get(&input, std::time::Instant::now()) {
+            self.show_fix(block_id, Ok(fix.to_string()));
+            return Command::none();
+        }
+        self.suggest_fix(block_id, AiRequest::SuggestFix)
+    }
+
+    /// Put a fix, or why there is none, under the block
+    fn show_fix(&mut self, block_id: Uuid, result: Result<String, String>) {
+        let one_click = self.config.preferences.ai.one_click_fixes;
+        let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) else {
+            return;
+        };
+        block.fix = Some(match result {
+            Ok(command) => {
+                let run = match agent_mode_eval::fix::needs_review(&command) {
+                    Some(reason) => block::FixRun::Review(reason),
+                    None if one_click => block::FixRun::OneClick,
+                    None => block::FixRun::Manual,
+                };
+                block::FixSuggestion::Ready { command, run }
+            }
+            Err(e) => block::FixSuggestion::Failed(e),
+        });
+    }
+
+    /// Ask the assistant for a corrected version of a failed command,
+    /// offered under the block
+    fn suggest_fix(&mut self, block_id: Uuid, request: AiRequest) -> Command<Message> {
+        let Some(block) = self.blocks.iter().find(|b| b.id == block_id) else {
+            return Command::none();
+        };
+        let BlockContent::Command { input, exit_code: Some(exit_code), .. } = &block.content else {
+            return Command::none();
+        };
+        let input = input.clone();
+        let lines: Vec<_> = block
+            .output_lines()
+            .into_iter()
+            .map(|line| context::OutputLine { text: self.redactor.redact(&line.text), ..line })
+            .collect();
+        let prompt = agent_mode_eval::fix::fix_prompt(&self.redactor.redact(&input), *exit_code, &lines);
+        if !self.ai_allowed(request) {
+            return Command::none();
+        }
+        let Some(client) = self.agent_mode.as_ref().map(|agent| agent.ai_client.clone()) else {
+            return Command::none();
+        };
+        if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+            block.fix = Some(block::FixSuggestion::Loading);
+        }
+
+        let message = agent_mode_eval::ai_client::AiMessage { role: "user".to_string(), content: prompt, tool_calls: None, tool_call_id: None };
+        Command::perform(
+            async move {
+                let response = client.complete(vec![message], None).await.map_err(|e| e.to_string())?;
+                agent_mode_eval::fix::parse_fix(&response.content, &input).ok_or_else(|| "the assistant had no fix".to_string())
+            },
+            move |result| Message::FixSuggested(block_id, result),
+        )
+    }
+
+    /// A block's suggested fix, once there is one
+    fn ready_fix(&self, block_id: Uuid) -> Option<(String, block::FixRun)> {
+        match self.blocks.iter().find(|b| b.id == block_id)?.fix.as_ref()? {
+            block::FixSuggestion::Ready { command, run } => Some((command.clone(), *run)),
+            _ => None,
+        }
+    }
+
+    /// Run a suggested fix in a new block linked to the failed one. Checked
+    /// again here, since the preference may have changed since it arrived.
+    fn apply_fix(&mut self, block_id: Uuid) -> Command<Message> {
+        if let Err(e) = self.read_only.check() {
+            self.status_messages.push(e.to_string(), std::time::Instant::now());
+            return Command::none();
+        }
+        let Some((command, block::FixRun::OneClick)) = self.ready_fix(block_id) else {
+            return Command::none();
+        };
+        if !self.config.preferences.ai.one_click_fixes || agent_mode_eval::fix::needs_review(&command).is_some() {
+            return Command::none();
+        }
+        if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+            block.fix = None;
+        }
+        let mut block = Block::new_command(command.clone());
+        block.retry_of = Some(block_id);
+        let command = self.in_container(&mut block, command, &[]);
+        self.run_in_block(block, command, std::collections::HashMap::new())
+    }
+
+    /// Record an explanation in the conversation, as if it had been asked there
+    fn pin_annotation(&mut self, block_id: Uuid) {
+        // Would land in the middle of the turn being streamed
+        if self.agent_streaming {
+            return;
+        }
+        let Some(annotation) = self.blocks.iter_mut().find(|b| b.id == block_id).and_then(|b| b.annotation.as_mut()) else {
+            return;
+        };
+        let block::AnnotationState::Ready(explanation) = &annotation.state else {
+            return;
+        };
+        let Some(agent) = self.agent_mode.as_mut() else {
+            return;
+        };
+        if agent.conversations.is_none() && agent.start_conversation().is_err() {
+            return;
+        }
+        let recorded = agent
+            .push_user_message(annotation.prompt.clone())
+            .and_then(|_| agent.record_assistant_reply(explanation.clone()));
+        match recorded {
+            Ok(_) => {
+                annotation.pinned = true;
+                self.save_conversation();
+            }
+            Err(e) => self.blocks.push(Block::new_error(e.to_string())),
+        }
+    }
+
+    /// Ask for the next reply, first summarizing the oldest messages if the
+    /// conversation has outgrown the model's context
+    fn start_agent_turn(&mut self) -> Command<Message> {
+        let Some(agent) = self.agent_mode.as_ref() else {
+            return Command::none();
+        };
+        if let Some(job) = agent.summary_job() {
+            self.status_messages.push("Summarizing earlier messages to fit the model's context", std::time::Instant::now());
+            return Command::perform(
+                async move { job.run().await.map_err(|e| e.to_string()) },
+                Message::HistorySummarized,
+            );
+        }
+        // Streams from its own copy of the client and messages
+        let turn = agent.prepare_turn();
+        self.stream_agent_turn(turn)
+    }
+
+    /// Stream a reply into a new block and forward each event as it arrives
+    fn stream_agent_turn(&mut self, turn: Result<agent_mode_eval::Turn, agent_mode_eval::AgentError>) -> Command<Message> {
+        let agent_block = Block::new_agent_message(String::new());
+        self.agent_reply_block = Some(agent_block.id);
+        self.blocks.push(agent_block);
+        self.agent_streaming = true;
+        self.ai_request_started = Some(std::time::Instant::now());
+
+        let events = futures::stream::once(async move {
+            match turn {
+                Ok(turn) => Ok(turn.stream().await),
+                Err(e) => Err(e),
+            }
+        })
+        .flat_map(|result| match result {
+            Ok(rx) => tokio_stream::wrappers::ReceiverStream::new(rx).boxed(),
+            Err(e) => futures::stream::iter(vec![AgentMessage::from(e)]).boxed(),
+        });
+
+        Command::run(events, Message::AgentMessage)
+    }
+
+    /// Run, queue for approval, or refuse a tool call from the streaming reply
+    fn handle_tool_call(&mut self, call: ToolCall) -> Command<Message> {
+        let Some(agent) = self.agent_mode.as_ref() else {
+            return Command::none();
+        };
+        let approval = agent.approval(&call);
+        self.tool_round.get_or_insert_with(Default::default).push_call(call.clone());
+        match approval {
+            Approval::Run => self.run_tool(call),
+            Approval::Ask => {
+                self.pending_tool_approvals.push_back(call);
+                Command::none()
+            }
+            Approval::Deny(reason) => self.finish_tool(ToolResult::error(&call, &reason)),
+        }
+    }
+
+    fn run_tool(&mut self, call: ToolCall) -> Command<Message> {
+        let Some(agent) = self.agent_mode.as_ref() else {
+            return Command::none();
+        };
+        self.blocks.push(Block::new_agent_message(format!("🔧 Calling tool `{}`", call.name)));
+        let registry = agent.tool_registry.clone();
+        Command::perform(
+            async move {
+                match registry.execute_tool(call.clone()).await {
+                    Ok(result) => result,
+                    Err(e) => ToolResult::error(&call, &e.to_string()),
+                }
+            },
+            Message::ToolFinished,
+        )
+    }
+
+    /// Show a tool's result and keep it for the model
+    fn finish_tool(&mut self, result: ToolResult) -> Command<Message> {
+        let summary = match &result.error {
+            Some(error) => format!("Tool failed: {}", error),
+            None => result.output.clone(),
+        };
+        let Some(round) = self.tool_round.as_mut() else {
+            return Command::none();
+        };
+        if !round.resolve(result) {
+            return Command::none();
+        }
+        self.blocks.push(Block::new_agent_message(summary));
+        self.continue_tool_round()
     }
 
-    fn create_input_view(&self) -> Element<Message> {
-        let prompt_indicator = if self.agent_enabled {
-            "🤖 "
-        } else {
-            "$ "
+    /// Once the reply has finished and every call has a result, record the
+    /// round and let the model carry on from the results
+    fn continue_tool_round(&mut self) -> Command<Message> {
+        if self.agent_streaming || !self.tool_round.as_ref().is_some_and(agent_mode_eval::ToolRound::is_complete) {
+            return Command::none();
+        }
+        let Some(round) = self.tool_round.take() else {
+            return Command::none();
+        };
+        let Some(agent) = self.agent_mode.as_mut() else {
+            return Command::none();
         };
 
-        let placeholder = if self.agent_enabled {
-            "Ask me anything or enter a command..."
+        let block_id = self.agent_reply_block.take();
+        let content = block_id
+            .and_then(|id| self.blocks.iter().find(|b| b.id == id))
+            .and_then(|block| match &block.content {
+                BlockContent::AgentMessage { content, .. } => Some(content.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        match agent.record_tool_round(content, round) {
+            Ok(message_id) => {
+                if let Some(block) = block_id.and_then(|id| self.blocks.iter_mut().find(|b| b.id == id)) {
+                    block.set_message_id(message_id);
+                }
+            }
+            Err(e) => {
+                self.blocks.push(Block::new_error(e.to_string()));
+                return Command::none();
+            }
+        }
+        self.save_conversation();
+
+        if self.tool_rounds_taken >= agent_mode_eval::MAX_TOOL_ROUNDS {
+            self.blocks.push(Block::new_info(format!(
+                "Stopped after {} rounds of tool calls. Send another prompt to continue.",
+                agent_mode_eval::MAX_TOOL_ROUNDS
+            )));
+            return Command::none();
+        }
+        self.tool_rounds_taken += 1;
+        self.start_agent_turn()
+    }
+
+    /// Forget tool calls of a reply that is no longer being answered
+    fn drop_tool_round(&mut self) {
+        self.tool_round = None;
+        self.pending_tool_approvals.clear();
+    }
+
+    fn handle_agent_message(&mut self, agent_message: AgentMessage) -> Command<Message> {
+        match agent_message {
+            // The prompt block was already added when the command was submitted
+            AgentMessage::UserMessage(_) => {}
+            // A cleared request keeps streaming in the background; drop what arrives
+            AgentMessage::AssistantDelta(_) | AgentMessage::ToolCall(_) if !self.agent_streaming => {}
+            AgentMessage::AssistantDelta(chunk) => {
+                let reply = self.agent_reply_block;
+                if let Some(block) = self.blocks.iter_mut().rev().find(|b| Some(b.id) == reply) {
+                    block.append_agent_text(&chunk);
+                }
+            }
+            AgentMessage::ToolCall(call) => return self.handle_tool_call(call),
+            AgentMessage::ToolResult(result) => return self.finish_tool(result),
+            AgentMessage::SystemNotice(notice) => {
+                self.blocks.push(Block::new_agent_message(notice));
+            }
+            AgentMessage::Error(error) => {
+                self.blocks.push(Block::new_error(format!("Agent error: {}", error)));
+                self.agent_streaming = false;
+                self.ai_request_started = None;
+                self.drop_tool_round();
+            }
+            // Recorded even for a cleared request: the tokens were still spent
+            AgentMessage::Usage(usage) => {
+                if let Some(agent) = self.agent_mode.as_mut() {
+                    let _ = agent.record_usage(&usage, &self.config.preferences.ai.prices);
+                }
+            }
+            AgentMessage::Done if !self.agent_streaming => {}
+            AgentMessage::Done => {
+                self.agent_streaming = false;
+                if let Some(started) = self.ai_request_started.take() {
+                    self.ai_latency.record(started.elapsed());
+                }
+                // A reply that called tools is recorded with their results
+                if self.tool_round.is_some() {
+                    return self.continue_tool_round();
+                }
+                self.record_agent_reply();
+            }
+        }
+        Command::none()
+    }
+
+    /// Collapse the blocks of a replaced turn, starting at its prompt
+    fn supersede_from(&mut self, message_id: Uuid) {
+        if let Some(start) = self.blocks.iter().position(|b| b.message_id() == Some(message_id)) {
+            for block in &mut self.blocks[start..] {
+                block.mark_superseded();
+            }
+        }
+    }
+
+    /// Put a prompt back into the input for editing. Only the last prompt of
+    /// the active branch can be edited; earlier ones are branched from instead.
+    fn start_editing_prompt(&mut self, block_id: Uuid) {
+        let Some(block) = self.blocks.iter().find(|b| b.id == block_id) else { return };
+        let BlockContent::UserMessage { ref content, message_id: Some(message_id), .. } = block.content else { return };
+
+        let last_prompt = self.agent_mode
+            .as_ref()
+            .and_then(|agent| agent.get_conversation_history())
+            .and_then(|conversation| conversation.get_user_messages().last().map(|msg| msg.id));
+
+        if last_prompt == Some(message_id) {
+            self.current_input = content.clone();
+            self.editing_prompt = Some(message_id);
         } else {
-            "Enter command..."
+            self.blocks.push(Block::new_agent_message(
+                "Only the last prompt can be edited; use ⎇ to branch from an earlier message.".to_string()
+            ));
+        }
+    }
+
+    /// Store the finished reply on the active branch so it can be forked from
+    fn record_agent_reply(&mut self) {
+        let Some(block_id) = self.agent_reply_block.take() else { return };
+        let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) else { return };
+        let BlockContent::AgentMessage { ref content, .. } = block.content else { return };
+
+        if let Some(agent) = self.agent_mode.as_mut() {
+            if let Ok(message_id) = agent.record_assistant_reply(content.clone()) {
+                block.set_message_id(message_id);
+            }
+        }
+        self.save_conversation();
+    }
+
+    /// Id of the conversation loaded in agent mode, as saved
+    fn active_conversation_id(&self) -> Option<Uuid> {
+        self.agent_mode.as_ref()?.conversations.as_ref().map(|tree| tree.root().id)
+    }
+
+    fn saved_conversations(&self) -> Vec<agent_mode_eval::store::ConversationSummary> {
+        let Some(store) = &self.conversation_store else {
+            return Vec::new();
         };
+        store.list().unwrap_or_else(|e| {
+            log::warn!("Could not list saved conversations: {}", e);
+            Vec::new()
+        })
+    }
 
-        let input = text_input(placeholder, &self.current_input)
-            .on_input(Message::InputChanged)
-            .on_submit(Message::ExecuteCommand)
-            .padding(12)
-            .size(16);
+    fn refresh_ai_sidebar(&mut self) {
+        if self.ai_sidebar.is_some() {
+            let (conversations, active) = (self.saved_conversations(), self.active_conversation_id());
+            if let Some(sidebar) = self.ai_sidebar.as_mut() {
+                sidebar.refresh(conversations, active);
+            }
+        }
+    }
 
-        let input_with_prompt = row![
-            text(prompt_indicator).size(16),
-            input
-        ].spacing(8);
+    /// Write the current conversation to the store; nothing is kept in incognito
+    /// mode, and a conversation without messages isn't worth listing
+    fn save_conversation(&mut self) {
+        if self.config.preferences.privacy.incognito_mode {
+            return;
+        }
+        let Some(tree) = self.agent_mode.as_ref().and_then(|agent| agent.conversations.as_ref()) else {
+            return;
+        };
+        if tree.active().messages.is_empty() {
+            return;
+        }
+        if let Some(Err(e)) = self.conversation_store.as_ref().map(|store| store.save(tree)) {
+            log::warn!("Could not save the conversation: {}", e);
+        }
+        self.refresh_ai_sidebar();
+    }
 
-        let suggestions_view = if !self.suggestions.is_empty() {
-            column(
-                self.suggestions
-                    .iter()
-                    .enumerate()
-                    .map(|(i, suggestion)| {
-                        button(text(suggestion))
-                            .on_press(Message::SuggestionSelected(i))
-                            .width(iced::Length::Fill)
-                            .into()
-                    })
-                    .collect::<Vec<_>>()
-            )
-            .spacing(2)
-            .into()
-        } else {
-            column![].into()
+    /// Continue the saved conversation `id` in agent mode
+    fn open_conversation(&mut self, id: Uuid) -> Command<Message> {
+        let Some(store) = &self.conversation_store else {
+            return Command::none();
+        };
+        let tree = match store.load(id) {
+            Ok(tree) => tree,
+            Err(e) => {
+                self.blocks.push(Block::new_error(format!("Could not open the conversation: {}", e)));
+                return self.follow_output(1);
+            }
+        };
+        if !self.agent_enabled && !self.ai_allowed(AiRequest::ToggleAgent) {
+            return Command::none();
+        }
+        let Some(agent) = self.agent_mode.as_mut() else {
+            return Command::none();
         };
+        let title = agent_mode_eval::store::ConversationSummary::of(&tree).title;
+        agent.enabled = true;
+        agent.load_conversation(tree);
+        self.agent_enabled = true;
+        self.agent_reply_block = None;
+        self.editing_prompt = None;
+        self.drop_tool_round();
+        self.show_conversation(format!("Continuing \"{}\"", title));
+        self.refresh_ai_sidebar();
+        self.follow_output(1)
+    }
 
-        column![input_with_prompt, suggestions_view].spacing(4).into()
+    /// Replace the loaded conversation with an empty one
+    fn start_fresh_conversation(&mut self) {
+        let Some(agent) = self.agent_mode.as_mut() else { return };
+        if agent.start_conversation().is_ok() {
+            self.agent_reply_block = None;
+            self.editing_prompt = None;
+            self.drop_tool_round();
+            self.blocks.push(Block::new_agent_message("Started a new conversation.".to_string()));
+        }
     }
 
-    fn create_toolbar(&self) -> Element<Message> {
-        let agent_button = button(
-            text(if self.agent_enabled { "🤖 Agent ON" } else { "🤖 Agent OFF" })
-        )
-        .on_press(Message::ToggleAgentMode);
+    /// Append the active branch's messages as blocks after a fork or switch.
+    /// Earlier blocks stay in place so nothing from other branches is lost.
+    fn show_active_branch(&mut self) {
+        let Some(conversation) = self.agent_mode.as_ref().and_then(|agent| agent.get_conversation_history()) else {
+            return;
+        };
+        self.show_conversation(format!("⎇ Branch with {} messages", conversation.messages.len()));
+        self.save_conversation();
+    }
 
-        let settings_button = button(text("⚙️ Settings"))
-            .on_press(Message::ToggleSettings);
+    /// Append the active branch's messages as blocks under `header`
+    fn show_conversation(&mut self, header: String) {
+        let Some(conversation) = self.agent_mode.as_ref().and_then(|agent| agent.get_conversation_history()) else {
+            return;
+        };
 
-        row![agent_button, settings_button]
-            .spacing(8)
-            .into()
+        let mut blocks = vec![
+            Block::new_separator(),
+            Block::new_agent_message(header),
+        ];
+        for message in &conversation.messages {
+            let block = match message.role {
+                agent_mode_eval::conversation::MessageRole::User => Block::new_user_message(message.content.clone()),
+                _ => Block::new_agent_message(message.content.clone()),
+            };
+            blocks.push(block.with_message_id(message.id));
+        }
+
+        self.blocks.extend(blocks);
     }
 
-    fn handle_agent_command(&mut self, command: String) -> Command<Message> {
-        if let Some(ref mut agent) = self.agent_mode {
-            self.current_input.clear();
-            
-            // Add user message block
-            let user_block = Block::new_user_message(command.clone());
-            self.blocks.push(user_block);
-            
-            // Add streaming agent response block
-            let agent_block = Block::new_agent_message(String::new());
-            self.blocks.push(agent_block);
-            self.agent_streaming = true;
-            
-            // Send message to agent
-            let agent_clone = agent.clone();
-            Command::perform(
-                async move {
-                    match agent_clone.send_message(command).await {
-                        Ok(mut rx) => {
-                            let mut full_response = String::new();
-                            while let Some(chunk) = rx.recv().await {
-                                full_response.push_str(&chunk);
-                                // In a real implementation, you'd send streaming updates
-                            }
-                            Ok(full_response)
-                        }
-                        Err(e) => Err(e.to_string()),
-                    }
-                },
-                |result| match result {
-                    Ok(response) => Message::AgentStreamingChunk(response),
-                    Err(error) => Message::AgentError(error),
+    /// Whether the prompt is edited as vim would: `editor.vim_mode` is on and
+    /// nothing else has the keyboard
+    fn vim_prompt(&self) -> bool {
+        self.config.preferences.editor.vim_mode
+            && !self.settings_open
+            && !self.locked
+            && self.hint_mode.is_none()
+            && self.palette.is_none()
+            && self.history_search.is_none()
+            && self.block_search.is_none()
+            && self.renaming_tab.is_none()
+            && self.stdin_target().is_none()
+    }
+
+    fn handle_vim_key(&mut self, key: vim::Key) -> Command<Message> {
+        let mut line = self.current_input.clone();
+        let effect = self.vim.key(&mut line, key);
+        let edited = if line != self.current_input {
+            self.update(Message::InputChanged(line))
+        } else {
+            Command::none()
+        };
+        let followed = match effect {
+            vim::Effect::None => Command::none(),
+            vim::Effect::Insert(at) => {
+                Command::batch([text_input::focus(command_input_id()), text_input::move_cursor_to(command_input_id(), at)])
+            }
+            vim::Effect::HistoryPrevious => self.update(Message::HistoryUp),
+            vim::Effect::HistoryNext => self.update(Message::HistoryDown),
+            vim::Effect::Submit => Command::batch([self.update(Message::ExecuteCommand), text_input::focus(command_input_id())]),
+        };
+        Command::batch([edited, followed])
+    }
+
+    fn handle_key_press(&mut self, key: iced::keyboard::Key) -> Command<Message> {
+        use iced::keyboard::{key::Named, Key};
+
+        // The unlocking key press is consumed
+        if self.locked {
+            self.locked = false;
+            return Command::none();
+        }
+
+        if self.hint_mode.is_some() {
+            return self.handle_hint_key(key);
+        }
+
+        // Keys go to the interactive block's command instead
+        if self.stdin_target().is_some() {
+            return Command::none();
+        }
+
+        if self.settings_open {
+            let settings_message = match key {
+                Key::Named(Named::ArrowLeft) => Some(settings::SettingsMessage::PreviousTab),
+                Key::Named(Named::ArrowRight) => Some(settings::SettingsMessage::NextTab),
+                Key::Named(Named::Escape) => {
+                    self.settings_open = false;
+                    None
                 }
-            )
+                _ => None,
+            };
+            if let Some(settings_message) = settings_message {
+                return self.update(Message::SettingsMessage(settings_message));
+            }
+            return Command::none();
+        }
+
+        if self.palette.is_some() {
+            let palette_message = match key.as_ref() {
+                Key::Named(Named::ArrowUp) => PaletteMessage::MoveSelection(-1),
+                Key::Named(Named::ArrowDown) => PaletteMessage::MoveSelection(1),
+                Key::Named(Named::Escape) => PaletteMessage::Close,
+                _ => return Command::none(),
+            };
+            return self.update(Message::Palette(palette_message));
+        }
+
+        if let Some(search) = &mut self.history_search {
+            let count = self.history.search(&search.query, history::SEARCH_CANDIDATES).len();
+            match key.as_ref() {
+                Key::Named(Named::Escape) => return self.update(Message::CancelHistorySearch),
+                Key::Named(Named::ArrowUp) => search.move_selection(-1, count),
+                Key::Named(Named::ArrowDown) => search.move_selection(1, count),
+                _ => {}
+            }
+            return Command::none();
+        }
+
+        // The bar's input takes typing; n/N and Esc arrive once it's left
+        if self.block_search.is_some() {
+            match key.as_ref() {
+                Key::Named(Named::Escape) => return self.update(Message::CloseBlockSearch),
+                Key::Character("n") => return self.update(Message::NextBlockMatch),
+                Key::Character("N") => return self.update(Message::PreviousBlockMatch),
+                _ => {}
+            }
+        }
+
+        // Normal and visual mode keys edit the prompt
+        let vim_editing = self.vim_prompt() && self.vim.mode() != vim::Mode::Insert;
+        if vim_editing && !self.modifiers.control() && !self.modifiers.alt() {
+            let vim_key = match key.as_ref() {
+                Key::Character(c) if c.chars().count() == 1 => c.chars().next().map(vim::Key::Char),
+                Key::Named(Named::Space) => Some(vim::Key::Char(' ')),
+                Key::Named(Named::Escape) => Some(vim::Key::Escape),
+                Key::Named(Named::Enter) => Some(vim::Key::Enter),
+                Key::Named(Named::ArrowLeft | Named::Backspace) => Some(vim::Key::Left),
+                Key::Named(Named::ArrowRight) => Some(vim::Key::Right),
+                _ => None,
+            };
+            if let Some(vim_key) = vim_key {
+                return self.handle_vim_key(vim_key);
+            }
+        }
+
+        if self.completion.is_some() && matches!(key.as_ref(), Key::Named(Named::Escape)) {
+            self.completion = None;
+            return Command::none();
+        }
+
+        // Esc on an empty continuation line goes back to the line before
+        if self.current_input.is_empty() && matches!(key.as_ref(), Key::Named(Named::Escape)) {
+            if let Some(line) = self.input_lines.pop() {
+                self.current_input = line;
+                return text_input::move_cursor_to_end(command_input_id());
+            }
+        }
+
+        // A focused directory block takes the keys for moving around in it
+        let directory = self
+            .focused_block
+            .filter(|id| self.blocks.iter().any(|b| b.id == *id && matches!(b.content, BlockContent::Directory(_))));
+        if let Some(block_id) = directory {
+            let message = match key.as_ref() {
+                Key::Named(Named::Enter) => Some(dir_listing::DirMessage::OpenSelected),
+                Key::Named(Named::Backspace) => Some(dir_listing::DirMessage::Up),
+                Key::Named(Named::ArrowUp) => Some(dir_listing::DirMessage::MoveSelection(-1)),
+                Key::Named(Named::ArrowDown) => Some(dir_listing::DirMessage::MoveSelection(1)),
+                _ => None,
+            };
+            if let Some(message) = message {
+                return self.handle_directory(block_id, message);
+            }
+        }
+
+        // Key presses only reach us when the input doesn't capture them
+        match key.as_ref() {
+            Key::Character(c) if c == self.config.preferences.ui.hint_key => {
+                self.hint_mode = Some(hints::HintMode::new());
+                Command::none()
+            }
+            Key::Character("a") => self.update(Message::ToggleAiSidebar),
+            Key::Named(Named::End) | Key::Character("G") => self.update(Message::JumpToLatest),
+            Key::Named(named @ (Named::PageUp | Named::PageDown)) => {
+                let page = self.scroll.viewport_height() * 0.9;
+                let delta = if named == Named::PageUp { -page } else { page };
+                let y = self.scroll.scroll_by(delta);
+                scrollable::scroll_to(blocks_scrollable_id(), scrollable::AbsoluteOffset { x: 0.0, y })
+            }
+            // Chat-style recall of the last prompt for editing
+            Key::Named(Named::ArrowUp) if self.agent_enabled && self.current_input.is_empty() => {
+                let last_prompt = self.blocks
+                    .iter()
+                    .rev()
+                    .find(|b| matches!(b.content, BlockContent::UserMessage { message_id: Some(_), superseded: false, .. }))
+                    .map(|b| b.id);
+                if let Some(block_id) = last_prompt {
+                    self.start_editing_prompt(block_id);
+                }
+                Command::none()
+            }
+            Key::Named(Named::Escape) if self.editing_prompt.is_some() => {
+                self.editing_prompt = None;
+                self.current_input.clear();
+                Command::none()
+            }
+            _ => Command::none(),
+        }
+    }
+
+    fn handle_find_replace(&mut self, block_id: Uuid, message: FindReplaceMessage) -> Command<Message> {
+        let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) else {
+            return Command::none();
+        };
+        let BlockContent::FindReplace(ref mut state) = block.content else {
+            return Command::none();
+        };
+
+        if let FindReplaceMessage::OpenMatch(file, index) = message {
+            return match state.location(file, index) {
+                Some((path, line)) => self.update(Message::PreviewFile(path, Some(line))),
+                None => Command::none(),
+            };
+        }
+
+        let search = matches!(message, FindReplaceMessage::Search);
+        state.update(message);
+        if !search || state.pattern.is_empty() {
+            state.searching = false;
+            return Command::none();
+        }
+
+        // Walking a large tree can take a while; keep it off the UI thread
+        let (root, query) = (state.root.clone(), state.query());
+        Command::perform(
+            async move {
+                tokio::task::spawn_blocking(move || find_replace::search_project(&root, &query))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|result| result.map_err(|e| e.to_string()))
+            },
+            move |result| Message::FindReplace(block_id, FindReplaceMessage::SearchFinished(result)),
+        )
+    }
+
+    /// Apply an idle transition. Background features check `self.idle.state()`
+    /// before doing work; long idle locks incognito sessions if configured.
+    fn set_idle_state(&mut self, state: IdleState) {
+        let incognito = self.config.preferences.privacy.incognito_mode;
+        if state == IdleState::LongIdle && incognito {
+            self.locked = true;
+        }
+    }
+
+    /// Keep the newest output in view while following; otherwise count it
+    /// towards the "new lines" indicator and leave the viewport where it is.
+    fn follow_output(&mut self, added_lines: usize) -> Command<Message> {
+        if self.scroll.on_output(added_lines) {
+            scrollable::snap_to(blocks_scrollable_id(), scrollable::RelativeOffset::END)
         } else {
             Command::none()
         }
     }
 
+    /// Write a block to the file from a confirmed export prompt, reporting
+    /// the outcome in a block of its own
+    fn export_block(&mut self, prompt: block_export::ExportPrompt) {
+        let Some(block) = self.blocks.iter().find(|b| b.id == prompt.block_id) else {
+            return;
+        };
+        let path = prompt.target();
+        let notice = match block_export::export(block, prompt.format, &path) {
+            Ok(()) => Block::new_info(format!("Exported block to {}", path.display())),
+            Err(e) => Block::new_error(format!("Export to {} failed: {}", path.display(), e)),
+        };
+        self.blocks.push(notice);
+    }
+
+    /// Open the file from a confirmed "Also write to file…" prompt and start
+    /// mirroring the block's output into it
+    fn start_tee(&mut self, prompt: tee::TeePrompt) {
+        let Some(block) = self.blocks.iter_mut().find(|b| b.id == prompt.block_id) else {
+            return;
+        };
+        let BlockContent::Command { working_directory, timeline, .. } = &block.content else {
+            return;
+        };
+        if block.status() != Some(block::BlockStatus::Running) {
+            self.status_messages.push("The command finished before its output file was opened", std::time::Instant::now());
+            return;
+        }
+        let path = std::path::Path::new(working_directory).join(prompt.path.trim());
+        match tee::Tee::open(&path, prompt.options, timeline.chunks()) {
+            Ok(tee) => {
+                block.tee = Some(tee.status());
+                self.tees.insert(prompt.block_id, tee);
+            }
+            Err(e) => self.status_messages.push(format!("Couldn't write output to file: {}", e), std::time::Instant::now()),
+        }
+    }
+
+    /// Hooks are off in read-only and incognito sessions unless configured otherwise
+    fn hooks_allowed(&self) -> bool {
+        self.config.preferences.hooks.allows(self.read_only.is_enabled(), self.config.preferences.privacy.incognito_mode)
+    }
+
+    /// Run `event`'s hook in the background, if there is one
+    fn fire_hook(&self, event: hooks::HookEvent, payload: serde_json::Value) -> Command<Message> {
+        if !self.hooks_allowed() || self.hooks.script(event).is_none() {
+            return Command::none();
+        }
+        let runner = self.hooks.clone();
+        Command::perform(async move { runner.run(event, &payload).await }, |_| Message::HookFinished)
+    }
+
+    /// Close a block's output file, if it has one, and say where it went
+    fn stop_tee(&mut self, block_id: Uuid) {
+        let Some(tee) = self.tees.remove(&block_id) else {
+            return;
+        };
+        if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+            block.tee = None;
+        }
+        let notice = match tee.close() {
+            Ok(status) => format!(
+                "Wrote {} bytes to {}",
+                i18n::format_number(status.bytes_written),
+                status.path.display()
+            ),
+            Err(e) => format!("Couldn't finish writing output to file: {}", e),
+        };
+        self.status_messages.push(notice, std::time::Instant::now());
+    }
+
     fn handle_block_action(&mut self, block_id: Uuid, action: BlockMessage) -> Command<Message> {
         match action {
             BlockMessage::Rerun => {
+                if let Err(e) = self.read_only.check() {
+                    self.status_messages.push(e.to_string(), std::time::Instant::now());
+                    return Command::none();
+                }
                 if let Some(block) = self.blocks.iter().find(|b| b.id == block_id) {
                     match &block.content {
                         BlockContent::Command { input, .. } => {
@@ -452,16 +6481,197 @@ impl NeoTerm {
                     Command::none()
                 }
             }
+            BlockMessage::ToggleCollapse => {
+                if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                    block.toggle_collapse();
+                }
+                Command::none()
+            }
+            BlockMessage::TogglePin => {
+                if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                    block.pinned = !block.pinned;
+                }
+                Command::none()
+            }
+            BlockMessage::MoveToTop | BlockMessage::MoveToBottom => {
+                self.focused_block = Some(block_id);
+                let movement = if matches!(action, BlockMessage::MoveToTop) { BlockMove::Top } else { BlockMove::Bottom };
+                let index = block::move_block(&mut self.blocks, block_id, movement);
+                self.scroll_to_moved_block(index)
+            }
             BlockMessage::Delete => {
+                self.stop_tee(block_id);
+                if let Some(scrollback) = self.blocks.iter().find(|b| b.id == block_id).and_then(Block::scrollback) {
+                    scrollback.discard();
+                }
                 self.blocks.retain(|b| b.id != block_id);
                 Command::none()
             }
+            BlockMessage::CopyCode(index) => {
+                match self.code_block(block_id, index) {
+                    Some((_, code)) => iced::clipboard::write(code),
+                    None => Command::none(),
+                }
+            }
+            BlockMessage::RunCode(index) => self.run_code_block(block_id, index),
+            BlockMessage::InsertCode(index) => {
+                let Some(commands) = self.shell_snippet(block_id, index) else {
+                    return Command::none();
+                };
+                self.set_input(&commands.join("\n"));
+                self.completion = None;
+                self.suggestions.clear();
+                text_input::focus(command_input_id())
+            }
+            BlockMessage::ShowFullOutput => {
+                let Some(scrollback) = self.blocks.iter().find(|b| b.id == block_id).and_then(Block::scrollback) else {
+                    return Command::none();
+                };
+                let result = scrollback
+                    .full_output()
+                    .and_then(|path| open::that_detached(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e)));
+                if let Err(e) = result {
+                    self.status_messages.push(e, std::time::Instant::now());
+                }
+                Command::none()
+            }
+            BlockMessage::StartTee => {
+                if let Some(block) = self.blocks.iter().find(|b| b.id == block_id) {
+                    if let BlockContent::Command { input, working_directory, .. } = &block.content {
+                        let path = tee::default_path(std::path::Path::new(working_directory), input, chrono::Local::now());
+                        self.tee_prompt = Some(tee::TeePrompt {
+                            block_id,
+                            path: path.to_string_lossy().to_string(),
+                            options: tee::TeeOptions::default(),
+                        });
+                    }
+                }
+                Command::none()
+            }
+            BlockMessage::StopTee => {
+                self.stop_tee(block_id);
+                Command::none()
+            }
             BlockMessage::Copy => {
                 // TODO: Implement clipboard copy
                 Command::none()
             }
+            BlockMessage::Explain => self.explain_block(block_id),
+            BlockMessage::ToggleAnnotation => {
+                if let Some(annotation) = self.blocks.iter_mut().find(|b| b.id == block_id).and_then(|b| b.annotation.as_mut()) {
+                    annotation.collapsed = !annotation.collapsed;
+                }
+                Command::none()
+            }
+            BlockMessage::DismissAnnotation => {
+                if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                    block.annotation = None;
+                }
+                Command::none()
+            }
+            BlockMessage::UseFix => {
+                let Some(command) = self.ready_fix(block_id).map(|(command, _)| command) else {
+                    return Command::none();
+                };
+                self.set_input(&command);
+                self.completion = None;
+                self.suggestions.clear();
+                text_input::focus(command_input_id())
+            }
+            BlockMessage::SuggestFix => self.suggest_fix(block_id, AiRequest::AgentPrompt),
+            BlockMessage::ApplyFix => self.apply_fix(block_id),
+            BlockMessage::DismissFix => {
+                if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                    block.fix = None;
+                }
+                Command::none()
+            }
+            BlockMessage::AttachStdin => {
+                self.focused_block = Some(block_id);
+                self.stdin_attached = Some(block_id);
+                Command::none()
+            }
+            BlockMessage::DetachStdin => {
+                self.stdin_attached = None;
+                if !self.config.preferences.terminal.require_attach {
+                    self.focused_block = None;
+                }
+                Command::none()
+            }
+            BlockMessage::PinAnnotation => {
+                self.pin_annotation(block_id);
+                Command::none()
+            }
+            BlockMessage::AskAi => {
+                if let Some(block) = self.blocks.iter().find(|b| b.id == block_id) {
+                    if let BlockContent::Command { input, .. } = &block.content {
+                        let context = context::BlockContext::build(input, &block.output_lines(), &self.config.preferences.ai_context);
+                        self.ai_context_preview = Some((block_id, context));
+                    }
+                }
+                Command::none()
+            }
             BlockMessage::Export => {
-                // TODO: Implement export functionality
+                if self.blocks.iter().any(|b| b.id == block_id) {
+                    self.export_prompt = Some(block_export::ExportPrompt::new(
+                        block_id,
+                        &block_export::default_dir(),
+                        chrono::Local::now(),
+                    ));
+                }
+                Command::none()
+            }
+            BlockMessage::ToggleScrubber => {
+                if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                    if let BlockContent::Command { ref timeline, scrub_ms, .. } = block.content {
+                        // Open at the end so the first view matches the final output
+                        let position = match scrub_ms {
+                            Some(_) => None,
+                            None => Some(timeline.duration_ms),
+                        };
+                        block.scrub_to(position);
+                    }
+                }
+                Command::none()
+            }
+            BlockMessage::Scrub(offset_ms) => {
+                if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+                    block.scrub_to(Some(offset_ms));
+                }
+                Command::none()
+            }
+            BlockMessage::Share => {
+                if let Some(block) = self.blocks.iter().find(|b| b.id == block_id) {
+                    let content = share::prepare(&block.to_markdown(), &self.redactor);
+                    self.share_preview = Some((block_id, content));
+                }
+                Command::none()
+            }
+            BlockMessage::Unshare => {
+                let Some(record) = self.blocks.iter().find(|b| b.id == block_id).and_then(|b| b.shared.clone()) else {
+                    return Command::none();
+                };
+                let sharer = share::Sharer::new(self.config.preferences.share.clone());
+                Command::perform(
+                    async move { sharer.unshare(&record).await.map_err(|e| e.to_string()) },
+                    move |result| Message::Unshared(block_id, result)
+                )
+            }
+            BlockMessage::Edit => {
+                self.start_editing_prompt(block_id);
+                Command::none()
+            }
+            BlockMessage::Fork => {
+                let message_id = self.blocks
+                    .iter()
+                    .find(|b| b.id == block_id)
+                    .and_then(|b| b.message_id());
+                if let (Some(message_id), Some(agent)) = (message_id, self.agent_mode.as_mut()) {
+                    match agent.fork_at(message_id) {
+                        Ok(_) => self.show_active_branch(),
+                        Err(e) => self.blocks.push(Block::new_error(e.to_string())),
+                    }
+                }
                 Command::none()
             }
         }
@@ -469,8 +6679,24 @@ impl NeoTerm {
 }
 
 fn main() -> iced::Result {
+    let cli = cli::Cli::parse_localized();
+    // Before anything reads configuration
+    config::ConfigPaths::set_override(cli.config_dir.clone());
+    let preferences = config::AppConfig::load().unwrap_or_default().preferences;
+    i18n::set_locale(preferences.general.locale());
+    let _crash_guard = config::ConfigPaths::resolve()
+        .ok()
+        .and_then(|paths| crash_reports::install(&preferences.crash_reports, paths.crash_reports_dir()));
+    if let Some(command) = cli.command {
+        std::process::exit(cli::run(command));
+    }
+
     // Initialize modules
     agent_mode_eval::init();
     
-    NeoTerm::run(Settings::default())
+    // Closing goes through `Message::CloseRequested` so the session is saved
+    NeoTerm::run(Settings {
+        window: iced::window::Settings { exit_on_close_request: false, ..Default::default() },
+        ..Settings::with_flags(cli.startup_options())
+    })
 }
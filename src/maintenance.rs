@@ -0,0 +1,451 @@
+//! Background maintenance: enforces the retention limits of what NeoTerm
+//! keeps on disk and clears out what interrupted runs left behind.
+//!
+//! Deletions are ordered so that stopping at any point leaves every store
+//! readable. Cache metadata goes before the files it describes, and
+//! rewritten files are replaced with a rename. Each run re-plans from what
+//! is actually on disk, so the next one finishes anything a crash cut short.
+
+use crate::config::{ConfigPaths, MaintenancePreferences};
+use crate::workflows::{self, WorkflowCache, WorkflowRunRecord};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Wait this long after launch so maintenance doesn't compete with startup
+pub const STARTUP_DELAY: Duration = Duration::from_secs(120);
+/// Reclaiming at least this much is worth a notice in the status line
+pub const NOTABLE_BYTES: u64 = 100 * 1024 * 1024;
+
+const MIB: u64 = 1024 * 1024;
+const TEMP_SUFFIX: &str = "tmp";
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum MaintenanceError {
+    #[error("IO error: {0}")]
+    IoError(String),
+}
+
+impl From<std::io::Error> for MaintenanceError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IoError(e.to_string())
+    }
+}
+
+impl From<workflows::WorkflowError> for MaintenanceError {
+    fn from(e: workflows::WorkflowError) -> Self {
+        Self::IoError(e.to_string())
+    }
+}
+
+/// One thing a run removes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Deletion {
+    /// Older recorded runs of one workflow, beyond the per-workflow limit
+    RunRecords { workflow: String, count: usize, bytes: u64 },
+    /// A least-recently-used workflow cache entry
+    CacheEntry { dir: PathBuf, bytes: u64 },
+    CrashReport { path: PathBuf, bytes: u64 },
+    /// Left behind by an interrupted write or prune
+    Orphan { path: PathBuf, bytes: u64 },
+}
+
+impl Deletion {
+    pub fn bytes(&self) -> u64 {
+        match self {
+            Deletion::RunRecords { bytes, .. }
+            | Deletion::CacheEntry { bytes, .. }
+            | Deletion::CrashReport { bytes, .. }
+            | Deletion::Orphan { bytes, .. } => *bytes,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Deletion::RunRecords { workflow, count, .. } => {
+                format!("{} old run record(s) of '{}'", count, workflow)
+            }
+            Deletion::CacheEntry { dir, .. } => format!("workflow cache entry {}", dir.display()),
+            Deletion::CrashReport { path, .. } => format!("crash report {}", path.display()),
+            Deletion::Orphan { path, .. } => format!("leftover {}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaintenanceReport {
+    pub deletions: Vec<Deletion>,
+}
+
+impl MaintenanceReport {
+    pub fn freed_bytes(&self) -> u64 {
+        self.deletions.iter().map(Deletion::bytes).sum()
+    }
+
+    pub fn is_notable(&self) -> bool {
+        self.freed_bytes() >= NOTABLE_BYTES
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Maintenance removed {} item(s), reclaiming {:.1} MB",
+            self.deletions.len(),
+            self.freed_bytes() as f64 / MIB as f64
+        )
+    }
+}
+
+/// When maintenance last completed, kept next to the caches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct MaintenanceState {
+    last_run: Option<DateTime<Utc>>,
+}
+
+/// Where each store lives
+#[derive(Debug, Clone)]
+pub struct Stores {
+    pub run_history: PathBuf,
+    pub workflow_cache: PathBuf,
+    pub crash_reports: PathBuf,
+    pub state_file: PathBuf,
+}
+
+impl Stores {
+    pub fn resolve() -> Result<Self, MaintenanceError> {
+        let paths = ConfigPaths::resolve().map_err(|e| MaintenanceError::IoError(e.to_string()))?;
+        Ok(Self {
+            run_history: paths.workflow_runs_file(),
+            workflow_cache: paths.workflow_cache_dir(),
+            crash_reports: paths.crash_reports_dir(),
+            state_file: paths.maintenance_state_file(),
+        })
+    }
+}
+
+pub struct Maintenance {
+    stores: Stores,
+    prefs: MaintenancePreferences,
+}
+
+impl Maintenance {
+    pub fn new(stores: Stores, prefs: MaintenancePreferences) -> Self {
+        Self { stores, prefs }
+    }
+
+    /// Whether a full interval has passed since the last completed run
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        let interval = chrono::Duration::days(self.prefs.interval_days as i64);
+        match self.load_state().last_run {
+            Some(last) => now - last >= interval,
+            None => true,
+        }
+    }
+
+    /// Everything a run at `now` would remove, without touching anything
+    pub fn plan(&self, now: DateTime<Utc>) -> Vec<Deletion> {
+        let mut deletions = Vec::new();
+        deletions.extend(self.plan_run_history());
+        deletions.extend(self.plan_workflow_cache());
+        deletions.extend(self.plan_crash_reports(now));
+        deletions
+    }
+
+    /// Apply the plan, stopping at the first failure; whatever remains is
+    /// picked up by the next run. With `dry_run`, only plan.
+    pub fn run(&self, now: DateTime<Utc>, dry_run: bool) -> Result<MaintenanceReport, MaintenanceError> {
+        let deletions = self.plan(now);
+        if dry_run {
+            return Ok(MaintenanceReport { deletions });
+        }
+
+        if deletions.iter().any(|d| matches!(d, Deletion::RunRecords { .. })) {
+            self.rewrite_run_history()?;
+        }
+        for deletion in &deletions {
+            match deletion {
+                Deletion::RunRecords { .. } => {}
+                Deletion::CacheEntry { dir, .. } => workflows::remove_entry_dir(dir)?,
+                Deletion::CrashReport { path, .. } => remove_if_present(path)?,
+                Deletion::Orphan { path, .. } if path.is_dir() => workflows::remove_entry_dir(path)?,
+                Deletion::Orphan { path, .. } => remove_if_present(path)?,
+            }
+        }
+
+        self.save_state(&MaintenanceState { last_run: Some(now) })?;
+        let report = MaintenanceReport { deletions };
+        log::info!("{}", report.summary());
+        Ok(report)
+    }
+
+    fn plan_run_history(&self) -> Vec<Deletion> {
+        let mut deletions = Vec::new();
+        let temp = temp_path(&self.stores.run_history);
+        if temp.exists() {
+            deletions.push(Deletion::Orphan { bytes: file_size(&temp), path: temp });
+        }
+
+        let (_, dropped) = self.split_run_history();
+        let mut by_workflow: Vec<(String, usize, u64)> = Vec::new();
+        for (workflow, bytes) in dropped {
+            match by_workflow.iter_mut().find(|(name, ..)| *name == workflow) {
+                Some((_, count, total)) => {
+                    *count += 1;
+                    *total += bytes;
+                }
+                None => by_workflow.push((workflow, 1, bytes)),
+            }
+        }
+        deletions.extend(
+            by_workflow
+                .into_iter()
+                .map(|(workflow, count, bytes)| Deletion::RunRecords { workflow, count, bytes }),
+        );
+        deletions
+    }
+
+    /// Lines to keep, and the workflow and size of each line to drop. The
+    /// newest `run_history_keep` runs of every workflow are kept; lines that
+    /// don't parse are left alone.
+    fn split_run_history(&self) -> (Vec<String>, Vec<(String, u64)>) {
+        let content = std::fs::read_to_string(&self.stores.run_history).unwrap_or_default();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut kept = Vec::new();
+        let mut dropped = Vec::new();
+
+        for line in content.lines().rev() {
+            match serde_json::from_str::<WorkflowRunRecord>(line) {
+                Ok(record) => {
+                    let count = seen.entry(record.workflow_name.clone()).or_default();
+                    *count += 1;
+                    if *count > self.prefs.run_history_keep {
+                        dropped.push((record.workflow_name, line.len() as u64 + 1));
+                        continue;
+                    }
+                    kept.push(line.to_string());
+                }
+                Err(_) => kept.push(line.to_string()),
+            }
+        }
+        kept.reverse();
+        (kept, dropped)
+    }
+
+    /// Write the kept records beside the history, then rename over it
+    fn rewrite_run_history(&self) -> Result<(), MaintenanceError> {
+        let (kept, _) = self.split_run_history();
+        let temp = temp_path(&self.stores.run_history);
+        let mut content = kept.join("\n");
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        std::fs::write(&temp, content)?;
+        std::fs::rename(&temp, &self.stores.run_history)?;
+        Ok(())
+    }
+
+    fn plan_workflow_cache(&self) -> Vec<Deletion> {
+        let cache = WorkflowCache::with_dir(self.stores.workflow_cache.clone(), u64::MAX);
+        let orphans = cache
+            .orphans()
+            .into_iter()
+            .map(|dir| Deletion::Orphan { bytes: workflows::dir_size(&dir), path: dir });
+        let evicted = cache
+            .eviction_candidates(self.prefs.cache_max_mb.saturating_mul(MIB))
+            .into_iter()
+            .map(|(dir, entry)| Deletion::CacheEntry { dir, bytes: entry.size_bytes });
+        orphans.chain(evicted).collect()
+    }
+
+    fn plan_crash_reports(&self, now: DateTime<Utc>) -> Vec<Deletion> {
+        let cutoff = now - chrono::Duration::days(self.prefs.crash_report_max_age_days);
+        crate::crash_reports::list(&self.stores.crash_reports)
+            .into_iter()
+            .enumerate()
+            .filter(|(i, report)| {
+                let at: DateTime<Utc> = report.event.timestamp.into();
+                *i >= self.prefs.crash_reports_keep || at < cutoff
+            })
+            .map(|(_, report)| Deletion::CrashReport { bytes: file_size(&report.path), path: report.path })
+            .collect()
+    }
+
+    fn load_state(&self) -> MaintenanceState {
+        std::fs::read_to_string(&self.stores.state_file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_state(&self, state: &MaintenanceState) -> Result<(), MaintenanceError> {
+        if let Some(parent) = self.stores.state_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string(state).map_err(|e| MaintenanceError::IoError(e.to_string()))?;
+        std::fs::write(&self.stores.state_file, content)?;
+        Ok(())
+    }
+}
+
+/// Run maintenance if it is due; used by the background task
+pub async fn run_if_due(prefs: MaintenancePreferences) -> Result<Option<MaintenanceReport>, String> {
+    tokio::task::spawn_blocking(move || {
+        let maintenance = Maintenance::new(Stores::resolve()?, prefs);
+        let now = Utc::now();
+        if !maintenance.is_due(now) {
+            return Ok(None);
+        }
+        maintenance.run(now, false).map(Some)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e: MaintenanceError| e.to_string())
+}
+
+/// Run maintenance now, due or not
+pub async fn run_now(prefs: MaintenancePreferences) -> Result<MaintenanceReport, String> {
+    tokio::task::spawn_blocking(move || Maintenance::new(Stores::resolve()?, prefs).run(Utc::now(), false))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e: MaintenanceError| e.to_string())
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(TEMP_SUFFIX);
+    path.with_file_name(name)
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn remove_if_present(path: &Path) -> Result<(), MaintenanceError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflows::{CommandOutput, RunOutcome, RunHistory, StepCache};
+    use tempfile::TempDir;
+
+    fn stores(root: &Path) -> Stores {
+        Stores {
+            run_history: root.join("workflow-runs.jsonl"),
+            workflow_cache: root.join("cache/workflow-cache"),
+            crash_reports: root.join("crash-reports"),
+            state_file: root.join("cache/maintenance.json"),
+        }
+    }
+
+    fn prefs() -> MaintenancePreferences {
+        MaintenancePreferences {
+            run_history_keep: 2,
+            cache_max_mb: 0,
+            ..MaintenancePreferences::default()
+        }
+    }
+
+    fn record(workflow: &str, n: usize) -> WorkflowRunRecord {
+        WorkflowRunRecord {
+            workflow_name: workflow.to_string(),
+            started_at: Utc::now(),
+            commands: vec![format!("step {}", n)],
+            remediations: Vec::new(),
+            outcome: RunOutcome::Succeeded,
+        }
+    }
+
+    fn fill_cache(stores: &Stores, workdir: &Path, keys: &[&str]) -> WorkflowCache {
+        std::fs::create_dir_all(workdir.join("target")).unwrap();
+        std::fs::write(workdir.join("target/app"), "0123456789").unwrap();
+        let cache = WorkflowCache::with_dir(stores.workflow_cache.clone(), u64::MAX);
+        let step = StepCache { key: "k".to_string(), paths: vec![PathBuf::from("target")] };
+        let output = CommandOutput { stdout: String::new(), stderr: String::new(), exit_code: 0 };
+        for key in keys {
+            cache.store("build", key, &step, workdir, &output, Duration::ZERO).unwrap();
+        }
+        cache
+    }
+
+    #[test]
+    fn test_dry_run_reports_without_deleting() {
+        let temp_dir = TempDir::new().unwrap();
+        let stores = stores(temp_dir.path());
+        let history = RunHistory::with_path(stores.run_history.clone());
+        for n in 0..4 {
+            history.append(&record("deploy", n)).unwrap();
+        }
+        history.append(&record("test", 0)).unwrap();
+        fill_cache(&stores, &temp_dir.path().join("project"), &["a"]);
+
+        let maintenance = Maintenance::new(stores.clone(), prefs());
+        let report = maintenance.run(Utc::now(), true).unwrap();
+        assert!(report.deletions.iter().any(|d| matches!(
+            d,
+            Deletion::RunRecords { workflow, count: 2, .. } if workflow == "deploy"
+        )));
+        assert!(report.deletions.iter().any(|d| matches!(d, Deletion::CacheEntry { .. })));
+
+        assert_eq!(history.load().len(), 5);
+        assert_eq!(WorkflowCache::with_dir(stores.workflow_cache.clone(), u64::MAX).entries().len(), 1);
+        assert!(maintenance.is_due(Utc::now()));
+    }
+
+    #[test]
+    fn test_keeps_newest_runs_per_workflow() {
+        let temp_dir = TempDir::new().unwrap();
+        let stores = stores(temp_dir.path());
+        let history = RunHistory::with_path(stores.run_history.clone());
+        for n in 0..4 {
+            history.append(&record("deploy", n)).unwrap();
+            history.append(&record("test", n)).unwrap();
+        }
+
+        let maintenance = Maintenance::new(stores, prefs());
+        maintenance.run(Utc::now(), false).unwrap();
+        let commands: Vec<String> = history.load().into_iter().map(|r| r.commands[0].clone()).collect();
+        assert_eq!(commands, vec!["step 2", "step 2", "step 3", "step 3"]);
+        assert!(!maintenance.is_due(Utc::now()));
+    }
+
+    #[test]
+    fn test_interrupted_prune_is_finished_by_the_next_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let stores = stores(temp_dir.path());
+        let cache = fill_cache(&stores, &temp_dir.path().join("project"), &["a", "b"]);
+        let history = RunHistory::with_path(stores.run_history.clone());
+        for n in 0..3 {
+            history.append(&record("deploy", n)).unwrap();
+        }
+
+        // A run that stopped after deleting one entry's metadata, and while
+        // the rewritten history was still a temp file
+        let (dir, _) = cache.entries().into_iter().find(|(_, e)| e.key == "a").unwrap();
+        std::fs::remove_file(dir.join("entry.json")).unwrap();
+        std::fs::write(temp_path(&stores.run_history), "{\"partial\":").unwrap();
+
+        // Nothing reads the half-deleted state
+        assert!(cache.lookup("build", "a").is_none());
+        assert!(cache.lookup("build", "b").is_some());
+        assert_eq!(history.load().len(), 3);
+
+        let report = Maintenance::new(stores.clone(), prefs()).run(Utc::now(), false).unwrap();
+        assert_eq!(report.deletions.iter().filter(|d| matches!(d, Deletion::Orphan { .. })).count(), 2);
+        assert!(!dir.exists());
+        assert!(!temp_path(&stores.run_history).exists());
+        assert!(cache.entries().is_empty());
+        assert!(cache.orphans().is_empty());
+        assert_eq!(history.load().len(), 2);
+
+        // And a further run has nothing left to do
+        let again = Maintenance::new(stores, prefs()).run(Utc::now(), true).unwrap();
+        assert!(again.deletions.is_empty());
+    }
+}
@@ -1,5 +1,388 @@
-// markdown_parser module stub
+//! Markdown for the subset assistant replies use: headings, paragraphs,
+//! fenced code, bullet and numbered lists, block quotes, and inline code,
+//! bold, italics and links. Anything else is kept as text, so the output
+//! never contains markup the input didn't ask for. Replies are parsed into
+//! blocks for the terminal views, and rendered to HTML for exports.
 
-pub fn init() {
-    println!("markdown_parser loaded");
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownParser;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListMarker {
+    Bullet,
+    Number(u32),
+}
+
+/// Text with the inline markup that applies to it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InlineSpan {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub link: Option<String>,
+}
+
+impl InlineSpan {
+    fn same_markup(&self, other: &InlineSpan) -> bool {
+        (self.bold, self.italic, self.code, &self.link) == (other.bold, other.italic, other.code, &other.link)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownBlock {
+    Heading(usize, Vec<InlineSpan>),
+    Paragraph(Vec<InlineSpan>),
+    Quote(Vec<InlineSpan>),
+    ListItem(ListMarker, Vec<InlineSpan>),
+    Code {
+        language: String,
+        code: String,
+        /// False while a streamed reply hasn't closed the fence yet
+        closed: bool,
+    },
+}
+
+impl MarkdownParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn parse(&self, markdown: &str) -> Vec<MarkdownBlock> {
+        let mut blocks = Vec::new();
+        let mut paragraph: Vec<&str> = Vec::new();
+        let mut fence: Option<(String, Vec<&str>)> = None;
+
+        for line in markdown.lines() {
+            if let Some((language, code)) = &mut fence {
+                if line.trim_start().starts_with("```") {
+                    blocks.push(MarkdownBlock::Code {
+                        language: std::mem::take(language),
+                        code: code.join("\n"),
+                        closed: true,
+                    });
+                    fence = None;
+                } else {
+                    code.push(line);
+                }
+                continue;
+            }
+
+            let trimmed = line.trim();
+            let item = list_item(trimmed);
+            if trimmed.is_empty() || trimmed.starts_with("```") || heading(trimmed).is_some() || trimmed.starts_with('>') || item.is_some() {
+                flush_paragraph(&mut blocks, &mut paragraph);
+            }
+
+            if let Some(language) = trimmed.strip_prefix("```") {
+                fence = Some((language.trim().to_string(), Vec::new()));
+            } else if let Some((level, text)) = heading(trimmed) {
+                blocks.push(MarkdownBlock::Heading(level, inline(text)));
+            } else if let Some(quote) = trimmed.strip_prefix('>') {
+                blocks.push(MarkdownBlock::Quote(inline(quote.trim())));
+            } else if let Some((marker, text)) = item {
+                blocks.push(MarkdownBlock::ListItem(marker, inline(text)));
+            } else if !trimmed.is_empty() {
+                paragraph.push(trimmed);
+            }
+        }
+
+        // An unclosed fence runs to the end, as it does while a reply streams in
+        if let Some((language, code)) = fence {
+            blocks.push(MarkdownBlock::Code { language, code: code.join("\n"), closed: false });
+        }
+        flush_paragraph(&mut blocks, &mut paragraph);
+        blocks
+    }
+
+    pub fn to_html(&self, markdown: &str) -> String {
+        let mut html = String::new();
+        let mut list: Option<ListMarker> = None;
+        for block in self.parse(markdown) {
+            let item = match &block {
+                MarkdownBlock::ListItem(marker, _) => Some(*marker),
+                _ => None,
+            };
+            if list.is_some() && list.map(is_numbered) != item.map(is_numbered) {
+                close_list(&mut html, &mut list);
+            }
+            match block {
+                MarkdownBlock::Heading(level, spans) => {
+                    html.push_str(&format!("<h{0}>{1}</h{0}>\n", level, spans_to_html(&spans)));
+                }
+                MarkdownBlock::Paragraph(spans) => html.push_str(&format!("<p>{}</p>\n", spans_to_html(&spans))),
+                MarkdownBlock::Quote(spans) => {
+                    html.push_str(&format!("<blockquote>{}</blockquote>\n", spans_to_html(&spans)));
+                }
+                MarkdownBlock::ListItem(marker, spans) => {
+                    if list.is_none() {
+                        html.push_str(if is_numbered(marker) { "<ol>\n" } else { "<ul>\n" });
+                        list = Some(marker);
+                    }
+                    html.push_str(&format!("<li>{}</li>\n", spans_to_html(&spans)));
+                }
+                MarkdownBlock::Code { language, code, closed } => {
+                    let class = if language.is_empty() || !closed {
+                        String::new()
+                    } else {
+                        format!(" class=\"language-{}\"", escape(&language))
+                    };
+                    html.push_str(&format!("<pre><code{}>{}</code></pre>\n", class, escape(&code)));
+                }
+            }
+        }
+        close_list(&mut html, &mut list);
+        html
+    }
+}
+
+/// Parsed blocks of a reply that is still streaming in. Only the text after
+/// the last point where a new block must start is parsed again on each
+/// update, so long replies stay cheap and finished blocks never change.
+#[derive(Debug, Clone, Default)]
+pub struct StreamingMarkdown {
+    /// Bytes of the content whose blocks are final
+    stable_len: usize,
+    stable: Vec<MarkdownBlock>,
+    tail: Vec<MarkdownBlock>,
+}
+
+impl StreamingMarkdown {
+    pub fn new(content: &str) -> Self {
+        let mut markdown = Self::default();
+        markdown.update(content);
+        markdown
+    }
+
+    /// Catch up with `content`, which has only grown since the last update
+    pub fn update(&mut self, content: &str) {
+        if !content.is_char_boundary(self.stable_len) {
+            *self = Self::default();
+        }
+        let parser = MarkdownParser::new();
+        let boundary = stable_boundary(&content[self.stable_len..]);
+        if boundary > 0 {
+            self.stable.extend(parser.parse(&content[self.stable_len..self.stable_len + boundary]));
+            self.stable_len += boundary;
+        }
+        self.tail = parser.parse(&content[self.stable_len..]);
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &MarkdownBlock> {
+        self.stable.iter().chain(&self.tail)
+    }
+
+    /// Language tags and contents of the fenced code blocks, in order
+    pub fn code_blocks(&self) -> Vec<(&str, &str)> {
+        self.blocks()
+            .filter_map(|block| match block {
+                MarkdownBlock::Code { language, code, .. } => Some((language.as_str(), code.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// End of the last complete line in `text` after which parsing can start
+/// afresh: a blank line outside a code fence, or a closing fence
+fn stable_boundary(text: &str) -> usize {
+    let mut boundary = 0;
+    let mut offset = 0;
+    let mut in_fence = false;
+    for line in text.split_inclusive('\n') {
+        offset += line.len();
+        if !line.ends_with('\n') {
+            break;
+        }
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+            if !in_fence {
+                boundary = offset;
+            }
+        } else if trimmed.is_empty() && !in_fence {
+            boundary = offset;
+        }
+    }
+    boundary
+}
+
+fn is_numbered(marker: ListMarker) -> bool {
+    matches!(marker, ListMarker::Number(_))
+}
+
+fn flush_paragraph(blocks: &mut Vec<MarkdownBlock>, paragraph: &mut Vec<&str>) {
+    if !paragraph.is_empty() {
+        blocks.push(MarkdownBlock::Paragraph(inline(&paragraph.join(" "))));
+        paragraph.clear();
+    }
+}
+
+fn close_list(html: &mut String, list: &mut Option<ListMarker>) {
+    match list.take() {
+        Some(ListMarker::Bullet) => html.push_str("</ul>\n"),
+        Some(ListMarker::Number(_)) => html.push_str("</ol>\n"),
+        None => {}
+    }
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text.trim()))
+}
+
+fn list_item(line: &str) -> Option<(ListMarker, &str)> {
+    if let Some(text) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return Some((ListMarker::Bullet, text));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let text = line[digits..].strip_prefix(". ")?;
+    let number = line[..digits].parse().unwrap_or(u32::MAX);
+    (digits > 0).then_some((ListMarker::Number(number), text))
+}
+
+/// Escape text for HTML element content and attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Inline code, links, bold and italics within one block of text
+fn inline(text: &str) -> Vec<InlineSpan> {
+    let mut spans = Vec::new();
+    inline_into(text, &InlineSpan::default(), &mut spans);
+    spans
+}
+
+/// Spans of `text`, inside markup already described by `outer`
+fn inline_into(text: &str, outer: &InlineSpan, spans: &mut Vec<InlineSpan>) {
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('`') {
+            if let Some(end) = after.find('`') {
+                push_text(spans, &after[..end], &InlineSpan { code: true, ..outer.clone() });
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+        if let Some(after) = rest.strip_prefix('[') {
+            if let Some((label, url, remaining)) = link(after) {
+                inline_into(label, &InlineSpan { link: Some(url.to_string()), ..outer.clone() }, spans);
+                rest = remaining;
+                continue;
+            }
+        }
+        if let Some(marker) = ["**", "*"].into_iter().find(|marker| rest.starts_with(marker)) {
+            let after = &rest[marker.len()..];
+            if let Some(end) = after.find(marker).filter(|end| *end > 0) {
+                let markup = if marker == "**" {
+                    InlineSpan { bold: true, ..outer.clone() }
+                } else {
+                    InlineSpan { italic: true, ..outer.clone() }
+                };
+                inline_into(&after[..end], &markup, spans);
+                rest = &after[end + marker.len()..];
+                continue;
+            }
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        push_text(spans, &rest[..c.len_utf8()], outer);
+        rest = &rest[c.len_utf8()..];
+    }
+}
+
+/// Append `text` with `markup`, joining it to the last span when they match
+fn push_text(spans: &mut Vec<InlineSpan>, text: &str, markup: &InlineSpan) {
+    match spans.last_mut() {
+        Some(last) if last.same_markup(markup) => last.text.push_str(text),
+        _ => spans.push(InlineSpan { text: text.to_string(), ..markup.clone() }),
+    }
+}
+
+fn spans_to_html(spans: &[InlineSpan]) -> String {
+    let mut html = String::new();
+    for span in spans {
+        let mut piece = escape(&span.text);
+        if span.code {
+            piece = format!("<code>{}</code>", piece);
+        }
+        if span.italic {
+            piece = format!("<em>{}</em>", piece);
+        }
+        if span.bold {
+            piece = format!("<strong>{}</strong>", piece);
+        }
+        if let Some(url) = &span.link {
+            piece = format!("<a href=\"{}\">{}</a>", escape(url), piece);
+        }
+        html.push_str(&piece);
+    }
+    html
+}
+
+/// `label](url)` and what follows it; only http(s) and mailto links are kept
+fn link(text: &str) -> Option<(&str, &str, &str)> {
+    let (label, after) = text.split_once("](")?;
+    let (url, rest) = after.split_once(')')?;
+    ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+        .then_some((label, url, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renders_blocks_and_inline_markup() {
+        let html = MarkdownParser::new().to_html(
+            "## Fix\nRun **this** with `sudo`:\n\n```sh\nls <dir>\n```\n- one\n- [docs](https://example.com)\n1. first",
+        );
+        assert_eq!(
+            html,
+            "<h2>Fix</h2>\n\
+             <p>Run <strong>this</strong> with <code>sudo</code>:</p>\n\
+             <pre><code class=\"language-sh\">ls &lt;dir&gt;</code></pre>\n\
+             <ul>\n<li>one</li>\n<li><a href=\"https://example.com\">docs</a></li>\n</ul>\n\
+             <ol>\n<li>first</li>\n</ol>\n"
+        );
+    }
+
+    #[test]
+    fn test_raw_html_and_unsafe_links_stay_text() {
+        let html = MarkdownParser::new().to_html("<script>alert(1)</script> [x](javascript:alert(1))");
+        assert_eq!(html, "<p>&lt;script&gt;alert(1)&lt;/script&gt; [x](javascript:alert(1))</p>\n");
+    }
+
+    #[test]
+    fn test_streaming_matches_a_full_parse() {
+        let reply = "## Steps\n\nRun **this**:\n\n```sh\necho one\n\necho two\n```\n- done\n";
+        let mut streaming = StreamingMarkdown::default();
+        // Every prefix, as chunks arrive; an open fence is already a code block
+        for end in (0..=reply.len()).filter(|end| reply.is_char_boundary(*end)) {
+            streaming.update(&reply[..end]);
+            let expected = MarkdownParser::new().parse(&reply[..end]);
+            assert_eq!(streaming.blocks().cloned().collect::<Vec<_>>(), expected, "after {:?}", &reply[..end]);
+        }
+        assert_eq!(streaming.code_blocks(), vec![("sh", "echo one\n\necho two")]);
+        assert!(streaming.stable_len > 0);
+
+        streaming.update("```py\nprint(1)");
+        assert_eq!(
+            streaming.blocks().cloned().collect::<Vec<_>>(),
+            vec![MarkdownBlock::Code { language: "py".to_string(), code: "print(1)".to_string(), closed: false }]
+        );
+    }
 }
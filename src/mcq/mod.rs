@@ -1,5 +1,111 @@
-// mcq module stub
+//! Multiple-choice quizzes for the "learn" feature.
 
-pub fn init() {
-    println!("mcq loaded");
+use rand::seq::SliceRandom;
+use rand::Rng;
+use crate::workflows::Workflow;
+
+/// Wrong answers offered alongside the right one
+const DISTRACTORS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Question {
+    pub prompt: String,
+    pub choices: Vec<String>,
+    /// Index into `choices`
+    pub answer: usize,
+}
+
+impl Question {
+    pub fn is_correct(&self, choice: usize) -> bool {
+        choice == self.answer
+    }
+}
+
+/// "Which command does X?" questions from command templates. Distractors
+/// prefer templates sharing a tag, so the choices look alike.
+pub fn quiz_from_templates<'a, R: Rng>(
+    templates: impl IntoIterator<Item = &'a Workflow>,
+    count: usize,
+    rng: &mut R,
+) -> Vec<Question> {
+    let templates: Vec<&Workflow> = templates.into_iter().collect();
+    if templates.len() <= DISTRACTORS {
+        return Vec::new();
+    }
+
+    let mut order: Vec<&Workflow> = templates.clone();
+    order.shuffle(rng);
+
+    order
+        .into_iter()
+        .take(count)
+        .map(|template| {
+            let (mut related, mut others): (Vec<&Workflow>, Vec<&Workflow>) = templates
+                .iter()
+                .copied()
+                .filter(|other| other.name != template.name && other.command != template.command)
+                .partition(|other| other.tags.iter().any(|tag| template.tags.contains(tag)));
+            related.shuffle(rng);
+            others.shuffle(rng);
+
+            let mut choices: Vec<String> = related
+                .into_iter()
+                .chain(others)
+                .take(DISTRACTORS)
+                .map(|other| other.command.clone())
+                .collect();
+            let answer = rng.gen_range(0..=choices.len());
+            choices.insert(answer, template.command.clone());
+
+            Question {
+                prompt: format!(
+                    "Which command would you use to {}?",
+                    lowercase_first(template.description.as_deref().unwrap_or(&template.name))
+                ),
+                choices,
+                answer,
+            }
+        })
+        .collect()
+}
+
+fn lowercase_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::ResourceManager;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_template_quiz() {
+        let manager = ResourceManager::bundled().unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let questions = quiz_from_templates(manager.templates(), 10, &mut rng);
+
+        assert_eq!(questions.len(), 10);
+        for question in &questions {
+            assert_eq!(question.choices.len(), DISTRACTORS + 1);
+            assert!(question.prompt.starts_with("Which command would you use to "));
+
+            let correct = &question.choices[question.answer];
+            let template = manager.templates().find(|t| &t.command == correct).unwrap();
+            assert!(question.prompt.contains(&lowercase_first(template.description.as_deref().unwrap())));
+            assert!(question.is_correct(question.answer));
+        }
+    }
+
+    #[test]
+    fn test_too_few_templates_gives_no_questions() {
+        let manager = ResourceManager::bundled().unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!(quiz_from_templates(manager.templates().take(DISTRACTORS), 5, &mut rng).is_empty());
+    }
 }
@@ -1,5 +1,115 @@
-// natural_language_detection module stub
+//! Telling a sentence typed at the prompt apart from a shell command.
+//!
+//! The detector only scores the text; whether the first word is a program
+//! on PATH is for the caller to say, since that depends on the session's
+//! environment. A line starting with a known executable is never treated as
+//! natural language.
 
-pub fn init() {
-    println!("natural_language_detection loaded");
+/// Confidence above which input is taken as natural language
+pub const DEFAULT_THRESHOLD: f32 = 0.6;
+
+/// Words common in requests and rare as the start of a command line
+const REQUEST_STARTERS: &[&str] = &[
+    "show", "list", "how", "what", "which", "where", "why", "when", "who", "can", "could", "would", "please",
+    "give", "tell", "count", "delete", "remove", "create", "make", "get", "display", "print", "search", "check",
+    "undo", "rename", "compress", "convert", "is", "are", "do", "does", "i",
+];
+
+/// Function words that shell commands almost never contain
+const FUNCTION_WORDS: &[&str] = &[
+    "the", "a", "an", "me", "my", "this", "that", "these", "those", "of", "in", "into", "to", "for", "with", "from",
+    "by", "all", "every", "which", "than", "and", "are", "is", "it", "its", "i", "you", "there", "larger",
+    "largest", "biggest", "smallest", "recent", "last", "older", "newer",
+];
+
+#[derive(Debug, Clone)]
+pub struct NaturalLanguageDetector {
+    threshold: f32,
+}
+
+impl Default for NaturalLanguageDetector {
+    fn default() -> Self {
+        Self { threshold: DEFAULT_THRESHOLD }
+    }
+}
+
+impl NaturalLanguageDetector {
+    pub fn with_threshold(threshold: f32) -> Self {
+        Self { threshold: threshold.clamp(0.0, 1.0) }
+    }
+
+    /// How much `input` reads like a sentence, from 0 (a command) to 1
+    pub fn confidence(&self, input: &str) -> f32 {
+        let input = input.trim();
+        let words: Vec<String> = input
+            .split_whitespace()
+            .map(|word| word.trim_matches(|c: char| ",.?!'\"".contains(c)).to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        // Too short to tell; `make test` and `git status` look like this
+        if words.len() < 3 {
+            return 0.0;
+        }
+        // Pipes, redirection, variables, flags and paths are shell
+        let shell_syntax = input.contains(['|', '>', '<', '$', '`', ';', '&', '='])
+            || words.iter().any(|word| word.starts_with('-') || word.contains('/') || word.contains('*'));
+        if shell_syntax {
+            return 0.0;
+        }
+
+        let plain_words = words.iter().filter(|word| word.chars().all(|c| c.is_alphabetic())).count();
+        let function_words = words.iter().filter(|word| FUNCTION_WORDS.contains(&word.as_str())).count();
+
+        let mut score = 0.3 * plain_words as f32 / words.len() as f32;
+        score += (0.15 * function_words as f32).min(0.4);
+        if REQUEST_STARTERS.contains(&words[0].as_str()) {
+            score += 0.2;
+        }
+        if input.ends_with('?') {
+            score += 0.2;
+        }
+        if words.len() >= 5 {
+            score += 0.1;
+        }
+        score.min(1.0)
+    }
+
+    /// Whether `input` should go to the assistant instead of the shell.
+    /// `is_program` says whether a word names something the shell can run.
+    pub fn is_natural_language(&self, input: &str, is_program: impl Fn(&str) -> bool) -> bool {
+        let Some(first) = input.split_whitespace().next() else {
+            return false;
+        };
+        !is_program(first) && self.confidence(input) > self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentences_score_above_commands() {
+        let detector = NaturalLanguageDetector::default();
+        let no_programs = |_: &str| false;
+        for sentence in [
+            "show me the five largest files in this directory",
+            "how do I undo the last commit?",
+            "list all docker containers that are running",
+        ] {
+            assert!(detector.is_natural_language(sentence, no_programs), "{}", sentence);
+        }
+        for command in ["ls -la", "git status", "cat notes.txt | grep todo", "cargo build --release", "echo $HOME"] {
+            assert!(!detector.is_natural_language(command, no_programs), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_known_program_is_never_translated() {
+        let detector = NaturalLanguageDetector::default();
+        let sentence = "find all the files in this directory that are larger than a gigabyte";
+        assert!(detector.is_natural_language(sentence, |_| false));
+        assert!(!detector.is_natural_language(sentence, |program| program == "find"));
+        assert!(!NaturalLanguageDetector::with_threshold(1.0).is_natural_language(sentence, |_| false));
+    }
 }
@@ -0,0 +1,72 @@
+//! Desktop notifications for commands that finish while the window is in
+//! the background. notify-rust picks the platform's backend: D-Bus on
+//! Linux, Notification Center on macOS and toasts on Windows. Where none
+//! is available, the finish rings the terminal bell instead.
+
+use std::time::Duration;
+use crate::config::TerminalPreferences;
+use crate::i18n::{format_duration, tr, tr_args};
+
+/// A command worth telling the user about
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandFinished {
+    pub command: String,
+    pub exit_code: i32,
+    pub duration: Duration,
+}
+
+impl CommandFinished {
+    pub fn summary(&self) -> String {
+        match self.exit_code {
+            0 => tr("notify.command_succeeded").to_string(),
+            code => tr_args("notify.command_failed", &[("code", &code)]),
+        }
+    }
+
+    /// The command line and how long it ran
+    pub fn body(&self) -> String {
+        format!("{} · {}", crate::layout::truncate(&self.command, 80), format_duration(self.duration))
+    }
+}
+
+/// Whether a command that ran for `duration` gets a notification
+pub fn should_notify(prefs: &TerminalPreferences, duration: Duration, focused: bool) -> bool {
+    prefs.notifications_enabled && !focused && duration >= Duration::from_secs(prefs.notify_after_secs)
+}
+
+/// Show the notification off the UI thread; fails without a backend
+pub async fn send(finished: CommandFinished) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        notify_rust::Notification::new()
+            .summary(&finished.summary())
+            .body(&finished.body())
+            .show()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_long_commands_in_the_background_notify() {
+        let mut prefs = TerminalPreferences { notify_after_secs: 10, ..Default::default() };
+        assert!(should_notify(&prefs, Duration::from_secs(600), false));
+        assert!(!should_notify(&prefs, Duration::from_secs(600), true));
+        assert!(!should_notify(&prefs, Duration::from_secs(9), false));
+
+        prefs.notifications_enabled = false;
+        assert!(!should_notify(&prefs, Duration::from_secs(600), false));
+    }
+
+    #[test]
+    fn test_notification_text() {
+        let finished = CommandFinished { command: "cargo build --release".to_string(), exit_code: 101, duration: Duration::from_secs(602) };
+        assert_eq!(finished.summary(), "NeoTerm: command failed (exit 101)");
+        assert_eq!(finished.body(), "cargo build --release · 10m 02s");
+    }
+}
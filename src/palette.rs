@@ -0,0 +1,722 @@
+//! Command palette: searchable entries grouped into sections. Actions come
+//! from an `ActionRegistry` that plugins and workflows add to at runtime and
+//! are ranked by fuzzy match. Choosing a template or workflow asks for its
+//! parameters in a form and shows the exact command before it runs. A query
+//! starting with `?` searches history, blocks, conversations and workflows
+//! instead.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use uuid::Uuid;
+use iced::widget::{button, checkbox, column, pick_list, row, scrollable, text, text_input};
+use iced::Element;
+use crate::resources::ResourceManager;
+use crate::search::{self, SearchCorpus, SearchHit, SearchTarget};
+use crate::workflows::{ArgumentType, ParameterForm, Shell, Workflow, WorkflowExecutor};
+use crate::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteSection {
+    Actions,
+    Templates,
+    Results,
+}
+
+impl PaletteSection {
+    fn title(&self) -> &'static str {
+        match self {
+            PaletteSection::Actions => "Actions",
+            PaletteSection::Templates => "Templates",
+            PaletteSection::Results => "Results",
+        }
+    }
+}
+
+pub type ActionFuture = Pin<Box<dyn Future<Output = Message> + Send>>;
+
+/// Something the palette can run, listed above the templates. Running it
+/// produces the message the application then handles.
+#[derive(Clone)]
+pub struct CommandAction {
+    /// Stable across registrations; registering the same id replaces the action
+    pub id: String,
+    pub name: String,
+    /// "General", "Agent", "Profiles", "Workflows", or a plugin's name
+    pub category: String,
+    pub description: String,
+    /// Shown next to the name, e.g. "Ctrl+Shift+P"
+    pub keybinding: Option<String>,
+    run: Arc<dyn Fn() -> ActionFuture + Send + Sync>,
+}
+
+impl CommandAction {
+    pub fn new<F, Fut>(id: impl Into<String>, name: impl Into<String>, category: impl Into<String>, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Message> + Send + 'static,
+    {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            category: category.into(),
+            description: String::new(),
+            keybinding: None,
+            run: Arc::new(move || Box::pin(run())),
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn with_keybinding(mut self, keybinding: impl Into<String>) -> Self {
+        self.keybinding = Some(keybinding.into());
+        self
+    }
+
+    pub fn run(&self) -> ActionFuture {
+        (self.run)()
+    }
+}
+
+impl std::fmt::Debug for CommandAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandAction")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("category", &self.category)
+            .field("keybinding", &self.keybinding)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Every action the palette offers, in registration order
+#[derive(Debug, Clone, Default)]
+pub struct ActionRegistry {
+    actions: Vec<CommandAction>,
+}
+
+impl ActionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `action`, replacing any earlier one with the same id
+    pub fn register(&mut self, action: CommandAction) {
+        match self.actions.iter_mut().find(|existing| existing.id == action.id) {
+            Some(existing) => *existing = action,
+            None => self.actions.push(action),
+        }
+    }
+
+    /// Drop every action in `category`, before registering its current set
+    pub fn remove_category(&mut self, category: &str) {
+        self.actions.retain(|action| action.category != category);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CommandAction> {
+        self.actions.iter().find(|action| action.id == id)
+    }
+
+    /// Actions matching `query` by name or category, best match first; all
+    /// of them in registration order for an empty query
+    pub fn search(&self, query: &str) -> Vec<&CommandAction> {
+        let query = query.trim();
+        if query.is_empty() {
+            return self.actions.iter().collect();
+        }
+        let matcher = SkimMatcherV2::default();
+        let mut scored: Vec<(i64, &CommandAction)> = self
+            .actions
+            .iter()
+            .filter_map(|action| {
+                let by_name = matcher.fuzzy_match(&action.name, query);
+                let by_category = matcher.fuzzy_match(&format!("{} {}", action.category, action.name), query);
+                Some((by_name.max(by_category)?, action))
+            })
+            .collect();
+        // Stable, so equal scores keep registration order
+        scored.sort_by(|a, b| b.0.cmp(&a.0));
+        scored.into_iter().map(|(_, action)| action).collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PaletteMessage {
+    QueryChanged(String),
+    SelectTemplate(String),
+    /// By action id
+    SelectAction(String),
+    SelectResult(SearchTarget),
+    /// Arrow keys: move the highlight through every listed row
+    MoveSelection(isize),
+    /// Enter in the search box: choose the highlighted row
+    Submit,
+    ArgumentChanged(String, String),
+    /// Back from the placeholder form to the list
+    Back,
+    Run,
+    Close,
+}
+
+/// What the application should do after an update
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteAction {
+    /// Put this command in the input and run it
+    Run(String),
+    /// Put this command in the input without running it
+    Offer(String),
+    ShowBlock(Uuid),
+    ShowMessage(Uuid),
+    /// Run the registered action with this id
+    Action(String),
+    /// Run the open workflow's steps with these values; see `workflow`
+    RunWorkflow(HashMap<String, String>),
+    Close,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandPalette {
+    resources: ResourceManager,
+    shell: Shell,
+    query: String,
+    /// Template or workflow whose parameters are being filled
+    form: Option<ParameterForm>,
+    /// What `?` queries search, when the palette was opened with it
+    search: Option<SearchCorpus>,
+    results: Vec<SearchHit>,
+    actions: ActionRegistry,
+    /// Highlighted row of the list, counting actions, templates and results
+    highlighted: usize,
+}
+
+impl CommandPalette {
+    pub fn new(resources: ResourceManager, shell: Shell) -> Self {
+        Self {
+            resources,
+            shell,
+            query: String::new(),
+            form: None,
+            search: None,
+            results: Vec::new(),
+            actions: ActionRegistry::new(),
+            highlighted: 0,
+        }
+    }
+
+    /// List `actions` above the templates
+    pub fn with_actions(mut self, actions: ActionRegistry) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    /// Enable `?` search over `corpus`; workflow names come from the templates
+    pub fn with_search(mut self, mut corpus: SearchCorpus) -> Self {
+        corpus.workflows = self.resources
+            .templates()
+            .map(|template| (template.name.clone(), template.last_used))
+            .collect();
+        self.search = Some(corpus);
+        self
+    }
+
+    /// The query after `?`, when searching
+    pub fn search_query(&self) -> Option<&str> {
+        self.search.as_ref()?;
+        self.query.strip_prefix(search::SEARCH_PREFIX)
+    }
+
+    /// Search results for the current query, best first
+    pub fn results(&self) -> &[SearchHit] {
+        &self.results
+    }
+
+    /// Registered actions matching the query, best first
+    pub fn actions(&self) -> Vec<&CommandAction> {
+        if self.search_query().is_some() {
+            return Vec::new();
+        }
+        self.actions.search(&self.query)
+    }
+
+    /// What choosing each listed row sends, top to bottom
+    pub fn rows(&self) -> Vec<PaletteMessage> {
+        let actions = self.actions().into_iter().map(|action| PaletteMessage::SelectAction(action.id.clone()));
+        let templates = self
+            .sections()
+            .into_iter()
+            .flat_map(|(_, templates)| templates)
+            .map(|template| PaletteMessage::SelectTemplate(template.name.clone()));
+        let results = self.results.iter().map(|hit| PaletteMessage::SelectResult(hit.target.clone()));
+        actions.chain(templates).chain(results).collect()
+    }
+
+    pub fn highlighted(&self) -> usize {
+        self.highlighted
+    }
+
+    /// Templates matching the query, by section
+    pub fn sections(&self) -> Vec<(PaletteSection, Vec<&Workflow>)> {
+        if self.search_query().is_some() {
+            return Vec::new();
+        }
+        let templates = self.resources.search_templates(&self.query);
+        if templates.is_empty() {
+            Vec::new()
+        } else {
+            vec![(PaletteSection::Templates, templates)]
+        }
+    }
+
+    /// Open the parameter form for a workflow that isn't a bundled template
+    pub fn open_workflow(&mut self, workflow: Workflow) {
+        self.form = Some(ParameterForm::new(workflow));
+    }
+
+    /// The workflow whose form is open
+    pub fn workflow(&self) -> Option<&Workflow> {
+        self.form.as_ref().map(ParameterForm::workflow)
+    }
+
+    /// The command, or for a multi-step workflow its steps one per line,
+    /// that the open form would run, or why it can't yet
+    pub fn preview(&self) -> Option<Result<String, String>> {
+        let form = self.form.as_ref()?;
+        if let Some((name, error)) = form.errors().into_iter().next() {
+            return Some(Err(format!("{}: {}", name, error)));
+        }
+        let execution = WorkflowExecutor::new(self.shell.clone())
+            .prepare_execution(form.workflow(), form.arguments())
+            .map_err(|e| e.to_string());
+        Some(execution.map(|execution| {
+            if execution.steps.is_empty() {
+                execution.resolved_command
+            } else {
+                execution.steps.iter().enumerate().map(|(index, step)| step.label(index)).collect::<Vec<_>>().join("\n")
+            }
+        }))
+    }
+
+    /// Plain-text form of the palette for `columns`-wide text renderers
+    pub fn lines(&self, columns: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        match self.workflow() {
+            Some(template) => {
+                lines.push(format!("> {}", template.name));
+                match self.preview() {
+                    Some(Ok(command)) => lines.push(format!("$ {}", command)),
+                    Some(Err(e)) => lines.push(e),
+                    None => {}
+                }
+            }
+            None => {
+                lines.push(format!("> {}", self.query));
+                let actions = self.actions();
+                if !actions.is_empty() {
+                    lines.push(PaletteSection::Actions.title().to_string());
+                    for action in actions {
+                        lines.push(format!("  {}  {}", action.name, action.description));
+                    }
+                }
+                for (section, items) in self.sections() {
+                    lines.push(section.title().to_string());
+                    for template in items {
+                        lines.push(format!("  {}  {}", template.name, template.description.as_deref().unwrap_or("")));
+                    }
+                }
+                if !self.results.is_empty() {
+                    lines.push(PaletteSection::Results.title().to_string());
+                    for hit in &self.results {
+                        lines.push(format!("  [{}] {}  {}", hit.source.badge(), hit.title, when(hit)));
+                    }
+                }
+            }
+        }
+        lines.iter().map(|line| crate::layout::truncate(line.trim_end(), columns)).collect()
+    }
+
+    pub fn update(&mut self, message: PaletteMessage) -> Option<PaletteAction> {
+        match message {
+            PaletteMessage::QueryChanged(query) => {
+                self.query = query;
+                self.results = match (self.search_query(), &self.search) {
+                    (Some(query), Some(corpus)) => search::search(corpus, query, chrono::Utc::now()),
+                    _ => Vec::new(),
+                };
+                self.highlighted = 0;
+                None
+            }
+            PaletteMessage::MoveSelection(delta) => {
+                let count = self.rows().len();
+                if count > 0 {
+                    self.highlighted = (self.highlighted as isize + delta).rem_euclid(count as isize) as usize;
+                }
+                None
+            }
+            PaletteMessage::Submit => {
+                let row = self.rows().into_iter().nth(self.highlighted)?;
+                self.update(row)
+            }
+            PaletteMessage::SelectTemplate(name) => {
+                let template = self.resources.template(&name)?.clone();
+                self.open_workflow(template);
+                None
+            }
+            PaletteMessage::SelectAction(id) => Some(PaletteAction::Action(id)),
+            PaletteMessage::SelectResult(target) => match target {
+                SearchTarget::Command(command) => Some(PaletteAction::Offer(command)),
+                SearchTarget::Block(id) => Some(PaletteAction::ShowBlock(id)),
+                SearchTarget::Message(id) => Some(PaletteAction::ShowMessage(id)),
+                SearchTarget::Workflow(name) => self.update(PaletteMessage::SelectTemplate(name)),
+            },
+            PaletteMessage::ArgumentChanged(name, value) => {
+                if let Some(form) = self.form.as_mut() {
+                    form.set(name, value);
+                }
+                None
+            }
+            PaletteMessage::Back => {
+                self.form = None;
+                None
+            }
+            PaletteMessage::Run => {
+                let command = self.preview()?.ok()?;
+                let form = self.form.as_ref()?;
+                if form.workflow().steps.is_empty() {
+                    Some(PaletteAction::Run(command))
+                } else {
+                    Some(PaletteAction::RunWorkflow(form.arguments()))
+                }
+            }
+            PaletteMessage::Close => Some(PaletteAction::Close),
+        }
+    }
+
+    /// Whether a parameter form is open instead of the list
+    pub fn is_showing_form(&self) -> bool {
+        self.form.is_some()
+    }
+
+    /// `hints` maps template names to keyboard hint labels shown on their rows
+    pub fn view(&self, hints: &HashMap<String, String>) -> Element<PaletteMessage> {
+        match &self.form {
+            Some(form) => self.view_form(form),
+            None => self.view_list(hints),
+        }
+    }
+
+    fn view_list(&self, hints: &HashMap<String, String>) -> Element<PaletteMessage> {
+        let mut entries = column![].spacing(4);
+        // Row numbers follow `rows`, so the highlight matches what Enter picks
+        let mut row_index = 0;
+        let mut title = |name: &str| {
+            let marker = if row_index == self.highlighted { "▸ " } else { "" };
+            row_index += 1;
+            match hints.get(name) {
+                Some(label) => text(format!("{}[{}] {}", marker, label.to_uppercase(), name)).size(14),
+                None => text(format!("{}{}", marker, name)).size(14),
+            }
+        };
+
+        let actions = self.actions();
+        if !actions.is_empty() {
+            entries = entries.push(text(PaletteSection::Actions.title()).size(12));
+        }
+        for action in actions {
+            let mut detail = action.category.clone();
+            if !action.description.is_empty() {
+                detail = format!("{} · {}", detail, action.description);
+            }
+            if let Some(keybinding) = &action.keybinding {
+                detail = format!("{} · {}", detail, keybinding);
+            }
+            entries = entries.push(
+                button(column![title(&action.name), text(detail).size(12)])
+                    .on_press(PaletteMessage::SelectAction(action.id.clone()))
+                    .width(iced::Length::Fill)
+            );
+        }
+        for (section, items) in self.sections() {
+            entries = entries.push(text(section.title()).size(12));
+            for template in items {
+                entries = entries.push(
+                    button(
+                        column![
+                            title(&template.name),
+                            text(template.description.as_deref().unwrap_or("")).size(12),
+                        ]
+                    )
+                    .on_press(PaletteMessage::SelectTemplate(template.name.clone()))
+                    .width(iced::Length::Fill)
+                );
+            }
+        }
+
+        if !self.results.is_empty() {
+            entries = entries.push(text(PaletteSection::Results.title()).size(12));
+        }
+        for hit in &self.results {
+            let mut label = column![
+                title(&hit.title),
+                text(format!("{} · {}", hit.source.badge(), when(hit))).size(12),
+            ];
+            if let Some(detail) = &hit.detail {
+                label = label.push(text(detail).size(12).font(iced::Font::MONOSPACE));
+            }
+            entries = entries.push(
+                button(label)
+                    .on_press(PaletteMessage::SelectResult(hit.target.clone()))
+                    .width(iced::Length::Fill)
+            );
+        }
+
+        column![
+            row![
+                text_input("Search actions and templates, or ? to search everything…", &self.query)
+                    .id(query_input_id())
+                    .on_input(PaletteMessage::QueryChanged)
+                    .on_submit(PaletteMessage::Submit)
+                    .padding(8),
+                button("✕").on_press(PaletteMessage::Close),
+            ]
+            .spacing(8),
+            scrollable(entries).height(iced::Length::Fixed(300.0)),
+        ]
+        .spacing(8)
+        .into()
+    }
+
+    fn view_form<'a>(&'a self, parameters: &'a ParameterForm) -> Element<'a, PaletteMessage> {
+        let template = parameters.workflow();
+        let mut form = column![
+            row![
+                button("←").on_press(PaletteMessage::Back),
+                text(&template.name).size(16),
+            ]
+            .spacing(8),
+            text(template.description.as_deref().unwrap_or("")).size(12),
+        ]
+        .spacing(8);
+
+        let error_color = iced::Color::from_rgb(0.8, 0.3, 0.3);
+        for arg in &template.arguments {
+            let value = parameters.value(&arg.name);
+            let name = arg.name.clone();
+            let input: Element<'a, PaletteMessage> = match (&arg.arg_type, &arg.options) {
+                (ArgumentType::Enum, Some(options)) => pick_list(
+                    options.clone(),
+                    (!value.is_empty()).then(|| value.to_string()),
+                    move |value| PaletteMessage::ArgumentChanged(name.clone(), value),
+                )
+                .placeholder(arg.description.as_deref().unwrap_or("Select…"))
+                .into(),
+                (ArgumentType::Boolean, _) => checkbox(arg.description.as_deref().unwrap_or(""), parameters.is_checked(&arg.name))
+                    .on_toggle(move |checked| PaletteMessage::ArgumentChanged(name.clone(), checked.to_string()))
+                    .into(),
+                _ => text_input(arg.description.as_deref().unwrap_or(""), value)
+                    .on_input(move |value| PaletteMessage::ArgumentChanged(name.clone(), value))
+                    .on_submit(PaletteMessage::Run)
+                    .padding(6)
+                    .into(),
+            };
+            let label = if arg.required { format!("{} *", arg.name) } else { arg.name.clone() };
+            let mut field = row![text(label).size(12).width(iced::Length::Fixed(120.0)), input].spacing(8);
+            // Don't call an untouched required field wrong, the marker says it's needed
+            if let Some(error) = parameters.error(arg).filter(|_| !value.is_empty()) {
+                field = field.push(text(error).size(12).style(iced::theme::Text::Color(error_color)));
+            }
+            form = form.push(field);
+        }
+
+        let (preview, runnable) = match self.preview() {
+            Some(Ok(command)) => (text(format!("$ {}", command)).font(iced::Font::MONOSPACE), true),
+            Some(Err(e)) => (text(e).style(iced::theme::Text::Color(error_color)), false),
+            None => (text(""), false),
+        };
+        let mut run = button("Run");
+        if runnable {
+            run = run.on_press(PaletteMessage::Run);
+        }
+
+        form.push(preview).push(run).into()
+    }
+}
+
+/// The search box, focused when the palette opens
+pub fn query_input_id() -> text_input::Id {
+    text_input::Id::new("palette-query")
+}
+
+/// When a result happened, for its badge line; history has no timestamps
+fn when(hit: &SearchHit) -> String {
+    hit.timestamp
+        .map(|time| crate::i18n::format_datetime(&time.with_timezone(&chrono::Local)))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> CommandPalette {
+        CommandPalette::new(ResourceManager::bundled().unwrap(), Shell::Bash)
+    }
+
+    #[test]
+    fn test_templates_section_filters_by_query() {
+        let mut palette = palette();
+        palette.update(PaletteMessage::QueryChanged("docker".to_string()));
+
+        let sections = palette.sections();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].0, PaletteSection::Templates);
+        assert!(sections[0].1.iter().all(|t| t.name.starts_with("docker-")));
+    }
+
+    #[test]
+    fn test_filling_placeholders_runs_exact_command() {
+        let mut palette = palette();
+        palette.update(PaletteMessage::SelectTemplate("find-large-files".to_string()));
+
+        // Defaults are filled in, so the preview is runnable straight away
+        assert_eq!(
+            palette.preview(),
+            Some(Ok("find '.' -type f -size +'100M' -exec du -h {} + | sort -rh | head -n '20'".to_string()))
+        );
+
+        palette.update(PaletteMessage::ArgumentChanged("min_size".to_string(), "1G".to_string()));
+        let action = palette.update(PaletteMessage::Run);
+        assert_eq!(
+            action,
+            Some(PaletteAction::Run("find '.' -type f -size +'1G' -exec du -h {} + | sort -rh | head -n '20'".to_string()))
+        );
+    }
+
+    fn registry() -> ActionRegistry {
+        let mut registry = ActionRegistry::new();
+        registry.register(CommandAction::new("diagnostics", "Diagnostics", "General", || async { Message::OpenDiagnostics }));
+        registry.register(
+            CommandAction::new("settings", "Open settings", "General", || async { Message::ToggleSettings })
+                .with_keybinding("Ctrl+,"),
+        );
+        registry.register(CommandAction::new("agent", "Toggle agent mode", "Agent", || async { Message::ToggleAgentMode }));
+        registry
+    }
+
+    #[test]
+    fn test_actions_are_fuzzy_ranked() {
+        let mut palette = palette().with_actions(registry());
+        let names = |palette: &CommandPalette| palette.actions().iter().map(|a| a.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&palette), vec!["Diagnostics", "Open settings", "Toggle agent mode"]);
+
+        palette.update(PaletteMessage::QueryChanged("diag".to_string()));
+        assert_eq!(names(&palette), vec!["Diagnostics"]);
+        assert_eq!(
+            palette.update(PaletteMessage::SelectAction("diagnostics".to_string())),
+            Some(PaletteAction::Action("diagnostics".to_string()))
+        );
+
+        // Out of order letters still match, and so does the category
+        palette.update(PaletteMessage::QueryChanged("tglagt".to_string()));
+        assert_eq!(names(&palette), vec!["Toggle agent mode"]);
+        palette.update(PaletteMessage::QueryChanged("agent".to_string()));
+        assert_eq!(names(&palette)[0], "Toggle agent mode");
+
+        palette.update(PaletteMessage::QueryChanged("zzzz".to_string()));
+        assert!(palette.actions().is_empty());
+    }
+
+    #[test]
+    fn test_registering_an_id_again_replaces_it() {
+        let mut registry = registry();
+        registry.register(CommandAction::new("settings", "Preferences", "General", || async { Message::ToggleSettings }));
+        assert_eq!(registry.search("").len(), 3);
+        assert_eq!(registry.get("settings").unwrap().name, "Preferences");
+
+        registry.remove_category("General");
+        assert_eq!(registry.search("").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_arrows_and_enter_choose_a_row() {
+        let mut palette = palette().with_actions(registry());
+        palette.update(PaletteMessage::MoveSelection(1));
+        assert_eq!(palette.highlighted(), 1);
+        assert_eq!(palette.update(PaletteMessage::Submit), Some(PaletteAction::Action("settings".to_string())));
+
+        // Wraps around past the top
+        palette.update(PaletteMessage::MoveSelection(-2));
+        assert_eq!(palette.highlighted(), palette.rows().len() - 1);
+
+        let action = registry().get("agent").unwrap().clone();
+        assert!(matches!(action.run().await, Message::ToggleAgentMode));
+    }
+
+    #[test]
+    fn test_run_waits_for_required_values() {
+        let mut palette = palette();
+        palette.update(PaletteMessage::SelectTemplate("ssh-socks-proxy".to_string()));
+
+        assert!(matches!(palette.preview(), Some(Err(_))));
+        assert_eq!(palette.update(PaletteMessage::Run), None);
+    }
+
+    #[test]
+    fn test_step_workflow_form_runs_with_collected_values() {
+        let workflow = Workflow::from_yaml(
+            "name: rollout\nparameters:\n  - name: env\n    type: enum\n    options: [staging, production]\n    required: true\n  \
+             - name: wait\n    type: boolean\nsteps:\n  - type: command\n    run: ./rollout.sh {{env}}\n",
+        )
+        .unwrap();
+        let mut palette = palette();
+        palette.open_workflow(workflow);
+
+        assert_eq!(palette.update(PaletteMessage::Run), None);
+        palette.update(PaletteMessage::ArgumentChanged("env".to_string(), "production".to_string()));
+        palette.update(PaletteMessage::ArgumentChanged("wait".to_string(), "true".to_string()));
+        assert_eq!(palette.preview(), Some(Ok("Step 1: $ ./rollout.sh 'production'".to_string())));
+
+        let arguments = HashMap::from([
+            ("env".to_string(), "production".to_string()),
+            ("wait".to_string(), "true".to_string()),
+        ]);
+        assert_eq!(palette.update(PaletteMessage::Run), Some(PaletteAction::RunWorkflow(arguments)));
+        assert_eq!(palette.workflow().unwrap().name, "rollout");
+    }
+
+    #[test]
+    fn test_question_mark_searches_instead_of_listing() {
+        let mut index = search::BlockIndex::new(None);
+        let block = Uuid::new_v4();
+        index.add(block, "curl https://internal.example", "SSL certificate problem", chrono::Utc::now()).unwrap();
+        let corpus = SearchCorpus {
+            history: vec!["openssl s_client -connect host:443".to_string()],
+            blocks: Arc::new(index),
+            ..SearchCorpus::default()
+        };
+        let mut palette = palette().with_search(corpus);
+
+        palette.update(PaletteMessage::QueryChanged("?certificate".to_string()));
+        assert!(palette.actions().is_empty());
+        assert!(palette.sections().is_empty());
+        assert_eq!(palette.results()[0].target, SearchTarget::Block(block));
+        assert_eq!(
+            palette.update(PaletteMessage::SelectResult(SearchTarget::Block(block))),
+            Some(PaletteAction::ShowBlock(block))
+        );
+
+        palette.update(PaletteMessage::QueryChanged("?openssl".to_string()));
+        let command = palette.results()[0].target.clone();
+        assert_eq!(
+            palette.update(PaletteMessage::SelectResult(command)),
+            Some(PaletteAction::Offer("openssl s_client -connect host:443".to_string()))
+        );
+
+        palette.update(PaletteMessage::SelectResult(SearchTarget::Workflow("find-large-files".to_string())));
+        assert!(palette.is_showing_form());
+    }
+}
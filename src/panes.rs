@@ -0,0 +1,290 @@
+//! Split panes. Each pane has its own blocks, input and working directory;
+//! how they're arranged is a tree of splits with a pane at every leaf.
+//!
+//! Only the focused pane's state is live in the app. The others are parked
+//! here and swapped in when they get focus.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use serde::{Deserialize, Serialize};
+use crate::block::Block;
+use crate::layout::SplitDirection;
+use crate::renderer::ScrollState;
+
+pub type PaneId = u32;
+
+/// Side of the focused pane to move focus to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaneTree {
+    Pane(PaneId),
+    /// Children side by side (`Horizontal`) or stacked (`Vertical`), sharing the space equally
+    Split { direction: SplitDirection, children: Vec<PaneTree> },
+}
+
+impl PaneTree {
+    /// Pane ids from left to right, top to bottom
+    pub fn panes(&self) -> Vec<PaneId> {
+        match self {
+            PaneTree::Pane(id) => vec![*id],
+            PaneTree::Split { children, .. } => children.iter().flat_map(PaneTree::panes).collect(),
+        }
+    }
+
+    /// Put `new` after `target`. A split in the same direction takes it as
+    /// one more child rather than nesting.
+    fn split(&mut self, target: PaneId, new: PaneId, direction: SplitDirection) -> bool {
+        match self {
+            PaneTree::Pane(id) if *id == target => {
+                *self = PaneTree::Split { direction, children: vec![PaneTree::Pane(target), PaneTree::Pane(new)] };
+                true
+            }
+            PaneTree::Pane(_) => false,
+            PaneTree::Split { direction: split_direction, children } => {
+                let position = children.iter().position(|child| *child == PaneTree::Pane(target));
+                match position {
+                    Some(index) if *split_direction == direction => {
+                        children.insert(index + 1, PaneTree::Pane(new));
+                        true
+                    }
+                    _ => children.iter_mut().any(|child| child.split(target, new, direction)),
+                }
+            }
+        }
+    }
+
+    /// Take `target` out; a split left with one child becomes that child
+    fn remove(&mut self, target: PaneId) -> bool {
+        let PaneTree::Split { children, .. } = self else { return false };
+        let removed = match children.iter().position(|child| *child == PaneTree::Pane(target)) {
+            Some(index) => {
+                children.remove(index);
+                true
+            }
+            None => children.iter_mut().any(|child| child.remove(target)),
+        };
+        if children.len() == 1 {
+            let only = children.remove(0);
+            *self = only;
+        }
+        removed
+    }
+
+    /// Where each pane goes in `area`
+    pub fn areas(&self, area: Rect) -> Vec<(PaneId, Rect)> {
+        match self {
+            PaneTree::Pane(id) => vec![(*id, area)],
+            PaneTree::Split { direction, children } => {
+                let count = children.len() as u32;
+                let rects = Layout::default()
+                    .direction(match direction {
+                        SplitDirection::Horizontal => Direction::Horizontal,
+                        SplitDirection::Vertical => Direction::Vertical,
+                    })
+                    .constraints(children.iter().map(|_| Constraint::Ratio(1, count)))
+                    .split(area);
+                children.iter().zip(rects.iter()).flat_map(|(child, rect)| child.areas(*rect)).collect()
+            }
+        }
+    }
+}
+
+/// What each pane keeps of its own
+#[derive(Debug, Clone, Default)]
+pub struct PaneState {
+    pub blocks: Vec<Block>,
+    pub input: String,
+    pub input_lines: Vec<String>,
+    pub cwd: PathBuf,
+    pub scroll: ScrollState,
+}
+
+/// Size of the area focus moves are worked out in; only proportions matter
+const NAVIGATION_AREA: u16 = 1200;
+
+#[derive(Debug, Clone)]
+pub struct Panes<T> {
+    tree: PaneTree,
+    focused: PaneId,
+    parked: HashMap<PaneId, T>,
+}
+
+impl<T> Default for Panes<T> {
+    fn default() -> Self {
+        Self { tree: PaneTree::Pane(0), focused: 0, parked: HashMap::new() }
+    }
+}
+
+impl<T> Panes<T> {
+    /// A saved layout, or a single pane if it doesn't hold together: every
+    /// pane but the focused one needs its parked state
+    pub fn restore(tree: PaneTree, focused: PaneId, parked: HashMap<PaneId, T>) -> Self {
+        let mut ids = tree.panes();
+        let mut expected: Vec<PaneId> = parked.keys().copied().chain([focused]).collect();
+        ids.sort_unstable();
+        expected.sort_unstable();
+        if ids != expected || parked.contains_key(&focused) {
+            return Self::default();
+        }
+        Self { tree, focused, parked }
+    }
+
+    pub fn tree(&self) -> &PaneTree {
+        &self.tree
+    }
+
+    pub fn focused(&self) -> PaneId {
+        self.focused
+    }
+
+    pub fn is_split(&self) -> bool {
+        matches!(self.tree, PaneTree::Split { .. })
+    }
+
+    pub fn get(&self, id: PaneId) -> Option<&T> {
+        self.parked.get(&id)
+    }
+
+    /// A pane that isn't focused, e.g. to deliver output to it
+    pub fn get_mut(&mut self, id: PaneId) -> Option<&mut T> {
+        self.parked.get_mut(&id)
+    }
+
+    /// Panes other than the focused one
+    pub fn parked(&self) -> impl Iterator<Item = (PaneId, &T)> {
+        self.parked.iter().map(|(id, state)| (*id, state))
+    }
+
+    /// Add `fresh` after the focused pane and focus it
+    pub fn split(&mut self, direction: SplitDirection, current: &mut T, fresh: T) -> PaneId {
+        let new = self.tree.panes().into_iter().max().unwrap_or(0) + 1;
+        self.tree.split(self.focused, new, direction);
+        self.parked.insert(new, fresh);
+        self.focus(new, current);
+        new
+    }
+
+    /// Park `current` and swap in pane `id`'s state
+    pub fn focus(&mut self, id: PaneId, current: &mut T) -> bool {
+        if id == self.focused {
+            return false;
+        }
+        let Some(state) = self.parked.remove(&id) else { return false };
+        self.parked.insert(self.focused, std::mem::replace(current, state));
+        self.focused = id;
+        true
+    }
+
+    /// Close the focused pane and focus the one before it, returning the
+    /// closed pane's state. The last pane isn't closed.
+    pub fn close(&mut self, current: &mut T) -> Option<T> {
+        let order = self.tree.panes();
+        let position = order.iter().position(|id| *id == self.focused)?;
+        let next = *order.get(position.checked_sub(1).unwrap_or(position + 1))?;
+        let state = self.parked.remove(&next)?;
+        self.tree.remove(self.focused);
+        self.focused = next;
+        Some(std::mem::replace(current, state))
+    }
+
+    /// The nearest pane on `direction`'s side of the focused one, preferring
+    /// the one sharing the most of its edge
+    pub fn neighbor(&self, direction: FocusDirection) -> Option<PaneId> {
+        let areas = self.tree.areas(Rect::new(0, 0, NAVIGATION_AREA, NAVIGATION_AREA));
+        let from = areas.iter().find(|(id, _)| *id == self.focused)?.1;
+        let overlap = |start: u16, end: u16, other_start: u16, other_end: u16| end.min(other_end).saturating_sub(start.max(other_start));
+        areas
+            .iter()
+            .filter(|(id, _)| *id != self.focused)
+            .filter_map(|(id, rect)| {
+                let (gap, shared) = match direction {
+                    FocusDirection::Left => (from.x.checked_sub(rect.right())?, overlap(from.y, from.bottom(), rect.y, rect.bottom())),
+                    FocusDirection::Right => (rect.x.checked_sub(from.right())?, overlap(from.y, from.bottom(), rect.y, rect.bottom())),
+                    FocusDirection::Up => (from.y.checked_sub(rect.bottom())?, overlap(from.x, from.right(), rect.x, rect.right())),
+                    FocusDirection::Down => (rect.y.checked_sub(from.bottom())?, overlap(from.x, from.right(), rect.x, rect.right())),
+                };
+                (shared > 0).then_some((gap, Reverse(shared), *id))
+            })
+            .min()
+            .map(|(_, _, id)| id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_nest_and_close_back_to_one_pane() {
+        let mut panes: Panes<&str> = Panes::default();
+        let mut current = "first";
+        let right = panes.split(SplitDirection::Horizontal, &mut current, "right");
+        assert_eq!((panes.focused(), current), (right, "right"));
+        let below = panes.split(SplitDirection::Vertical, &mut current, "below");
+        // Splitting the first pane again sideways adds a column instead of nesting
+        panes.focus(0, &mut current);
+        let middle = panes.split(SplitDirection::Horizontal, &mut current, "middle");
+        assert_eq!(panes.tree(), &PaneTree::Split {
+            direction: SplitDirection::Horizontal,
+            children: vec![
+                PaneTree::Pane(0),
+                PaneTree::Pane(middle),
+                PaneTree::Split { direction: SplitDirection::Vertical, children: vec![PaneTree::Pane(right), PaneTree::Pane(below)] },
+            ],
+        });
+
+        assert_eq!(panes.close(&mut current), Some("middle"));
+        assert_eq!((panes.focused(), current), (0, "first"));
+        panes.focus(below, &mut current);
+        assert_eq!(panes.close(&mut current), Some("below"));
+        assert_eq!((panes.focused(), current), (right, "right"));
+        assert_eq!(panes.close(&mut current), Some("right"));
+        assert_eq!(panes.tree(), &PaneTree::Pane(0));
+        assert!(!panes.is_split());
+        assert_eq!(panes.close(&mut current), None);
+        assert_eq!(current, "first");
+    }
+
+    #[test]
+    fn test_focus_moves_to_the_adjacent_pane() {
+        // ┌───┬───┐
+        // │ 0 │ 1 │
+        // │   ├───┤
+        // │   │ 2 │
+        // └───┴───┘
+        let mut panes: Panes<()> = Panes::default();
+        let right = panes.split(SplitDirection::Horizontal, &mut (), ());
+        let below = panes.split(SplitDirection::Vertical, &mut (), ());
+        assert_eq!(panes.neighbor(FocusDirection::Up), Some(right));
+        assert_eq!(panes.neighbor(FocusDirection::Left), Some(0));
+        assert_eq!(panes.neighbor(FocusDirection::Right), None);
+
+        panes.focus(0, &mut ());
+        // Both share half the edge; the one split off first wins
+        assert_eq!(panes.neighbor(FocusDirection::Right), Some(right));
+        assert_eq!(panes.neighbor(FocusDirection::Down), None);
+        let areas = panes.tree().areas(Rect::new(0, 0, 80, 24));
+        assert_eq!(areas, vec![(0, Rect::new(0, 0, 40, 24)), (right, Rect::new(40, 0, 40, 12)), (below, Rect::new(40, 12, 40, 12))]);
+    }
+
+    #[test]
+    fn test_inconsistent_layout_restores_as_one_pane() {
+        let tree = PaneTree::Split { direction: SplitDirection::Vertical, children: vec![PaneTree::Pane(0), PaneTree::Pane(3)] };
+        let restored = Panes::restore(tree.clone(), 3, HashMap::from([(0, "top")]));
+        assert_eq!((restored.focused(), restored.get(0)), (3, Some(&"top")));
+
+        let missing = Panes::restore(tree, 3, HashMap::from([(1, "top")]));
+        assert_eq!(missing.tree(), &PaneTree::Pane(0));
+        assert_eq!(missing.parked().count(), 0);
+    }
+}
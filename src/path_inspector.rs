@@ -0,0 +1,281 @@
+//! PATH inspection: every executable a command name could resolve to, in
+//! PATH order, and hygiene problems with PATH itself.
+//!
+//! The first match is the one the shell runs. When a later match is a
+//! different file (not a symlink to the same one), the first shadows it;
+//! that's usually how "the wrong python" gets run.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Where a program name resolves across PATH
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    pub program: String,
+    /// Every executable match, in PATH order
+    pub matches: Vec<PathBuf>,
+}
+
+impl Resolution {
+    /// The executable that runs
+    pub fn resolved(&self) -> Option<&Path> {
+        self.matches.first().map(PathBuf::as_path)
+    }
+
+    /// Later matches that are different files from the one that runs
+    pub fn shadowed(&self) -> Vec<&Path> {
+        let Some(first) = self.resolved() else {
+            return Vec::new();
+        };
+        let target = canonical(first);
+        self.matches[1..]
+            .iter()
+            .filter(|path| canonical(path) != target)
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    pub fn is_shadowing(&self) -> bool {
+        !self.shadowed().is_empty()
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(unix)]
+pub(crate) fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Every executable called `program` in the directories of `path_var`.
+/// Names containing a slash aren't looked up in PATH and have no matches.
+pub fn resolve_all(program: &str, path_var: &OsStr) -> Resolution {
+    let mut matches = Vec::new();
+    if !program.is_empty() && !program.contains('/') {
+        let mut seen = Vec::new();
+        for dir in std::env::split_paths(path_var) {
+            let dir = if dir.as_os_str().is_empty() { PathBuf::from(".") } else { dir };
+            // A directory listed twice finds the same file twice
+            let key = canonical(&dir);
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+            let candidate = dir.join(program);
+            if is_executable(&candidate) {
+                matches.push(candidate);
+            }
+        }
+    }
+    Resolution { program: program.to_string(), matches }
+}
+
+/// The program a command line runs: the first word after any `NAME=value`
+/// assignments
+pub fn program_of(command: &str) -> Option<String> {
+    let (_, rest) = crate::shell::parse_env_prefix(command);
+    rest.split_whitespace().next().map(str::to_string)
+}
+
+/// Resolutions for one PATH value, recomputed when PATH changes
+#[derive(Debug, Default)]
+pub struct PathResolver {
+    path: std::ffi::OsString,
+    cache: HashMap<String, Resolution>,
+}
+
+impl PathResolver {
+    pub fn new(path_var: impl Into<std::ffi::OsString>) -> Self {
+        Self { path: path_var.into(), cache: HashMap::new() }
+    }
+
+    /// Use `path_var` from now on; cached results are dropped if it differs
+    pub fn set_path(&mut self, path_var: &OsStr) {
+        if self.path != path_var {
+            self.path = path_var.to_os_string();
+            self.cache.clear();
+        }
+    }
+
+    pub fn resolve(&mut self, program: &str) -> &Resolution {
+        let path = &self.path;
+        self.cache
+            .entry(program.to_string())
+            .or_insert_with(|| resolve_all(program, path))
+    }
+}
+
+/// Something wrong with a PATH entry; positions are 1-based
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathIssue {
+    /// Listed again at `position` after first appearing at `first`
+    Duplicate { dir: PathBuf, first: usize, position: usize },
+    Missing { dir: PathBuf, position: usize },
+    /// Anyone can add executables here, and they come before later entries
+    WorldWritable { dir: PathBuf, position: usize },
+}
+
+impl std::fmt::Display for PathIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathIssue::Duplicate { dir, first, position } => {
+                write!(f, "{} is listed twice (entries {} and {})", dir.display(), first, position)
+            }
+            PathIssue::Missing { dir, position } => {
+                write!(f, "{} (entry {}) doesn't exist", dir.display(), position)
+            }
+            PathIssue::WorldWritable { dir, position } => write!(
+                f,
+                "{} (entry {}) is world-writable; anyone can shadow commands in later entries",
+                dir.display(),
+                position
+            ),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_world_writable(dir: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    dir.metadata().is_ok_and(|meta| meta.permissions().mode() & 0o002 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_world_writable(_dir: &Path) -> bool {
+    false
+}
+
+/// Duplicate, nonexistent and world-writable entries of `path_var`, in PATH order
+pub fn hygiene(path_var: &OsStr) -> Vec<PathIssue> {
+    let dirs: Vec<PathBuf> = std::env::split_paths(path_var).collect();
+    let mut issues = Vec::new();
+    let mut seen: Vec<(PathBuf, usize)> = Vec::new();
+
+    for (index, dir) in dirs.iter().enumerate() {
+        let position = index + 1;
+        if !dir.is_dir() {
+            issues.push(PathIssue::Missing { dir: dir.clone(), position });
+            continue;
+        }
+        let key = canonical(dir);
+        if let Some((_, first)) = seen.iter().find(|(seen_dir, _)| *seen_dir == key) {
+            issues.push(PathIssue::Duplicate { dir: dir.clone(), first: *first, position });
+            continue;
+        }
+        seen.push((key, position));
+        // The last entry can't shadow anything
+        if position < dirs.len() && is_world_writable(dir) {
+            issues.push(PathIssue::WorldWritable { dir: dir.clone(), position });
+        }
+    }
+    issues
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    /// `<dir>/<name>` under `root`, executable
+    fn executable(root: &Path, dir: &str, name: &str) -> PathBuf {
+        let dir = root.join(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn path_var(root: &Path, dirs: &[&str]) -> std::ffi::OsString {
+        std::env::join_paths(dirs.iter().map(|dir| root.join(dir))).unwrap()
+    }
+
+    #[test]
+    fn test_shim_shadows_system_python() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let shim = executable(root, "shims", "python");
+        let system = executable(root, "usr/bin", "python");
+        // Not executable, so not a match
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/python"), "").unwrap();
+
+        let resolution = resolve_all("python", &path_var(root, &["shims", "docs", "usr/bin"]));
+        assert_eq!(resolution.matches, vec![shim.clone(), system.clone()]);
+        assert_eq!(resolution.resolved(), Some(shim.as_path()));
+        assert_eq!(resolution.shadowed(), vec![system.as_path()]);
+        assert!(resolve_all("python3", &path_var(root, &["shims", "usr/bin"])).matches.is_empty());
+        assert!(resolve_all("./python", &path_var(root, &["shims"])).matches.is_empty());
+    }
+
+    #[test]
+    fn test_symlink_to_the_same_file_is_not_shadowing() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let real = executable(root, "usr/bin", "node");
+        std::fs::create_dir_all(root.join("local/bin")).unwrap();
+        std::os::unix::fs::symlink(&real, root.join("local/bin/node")).unwrap();
+
+        let resolution = resolve_all("node", &path_var(root, &["local/bin", "usr/bin", "usr/bin"]));
+        assert_eq!(resolution.matches.len(), 2);
+        assert!(!resolution.is_shadowing());
+    }
+
+    #[test]
+    fn test_resolver_cache_follows_path_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let system = executable(root, "usr/bin", "python");
+
+        let mut resolver = PathResolver::new(path_var(root, &["usr/bin"]));
+        assert!(!resolver.resolve("python").is_shadowing());
+
+        // Cached until PATH changes, e.g. a profile switch adds a venv
+        let venv = executable(root, "venv/bin", "python");
+        assert_eq!(resolver.resolve("python").matches, vec![system.clone()]);
+        resolver.set_path(&path_var(root, &["usr/bin"]));
+        assert_eq!(resolver.resolve("python").matches, vec![system.clone()]);
+        resolver.set_path(&path_var(root, &["venv/bin", "usr/bin"]));
+        assert_eq!(resolver.resolve("python").matches, vec![venv, system]);
+    }
+
+    #[test]
+    fn test_hygiene() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for dir in ["bin", "usr/bin", "tmp", "last"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for dir in ["tmp", "last"] {
+            std::fs::set_permissions(root.join(dir), std::fs::Permissions::from_mode(0o777)).unwrap();
+        }
+
+        let issues = hygiene(&path_var(root, &["bin", "tmp", "gone", "usr/bin", "bin", "last"]));
+        assert_eq!(
+            issues,
+            vec![
+                PathIssue::WorldWritable { dir: root.join("tmp"), position: 2 },
+                PathIssue::Missing { dir: root.join("gone"), position: 3 },
+                PathIssue::Duplicate { dir: root.join("bin"), first: 1, position: 5 },
+            ]
+        );
+        assert!(hygiene(&path_var(root, &["bin", "usr/bin"])).is_empty());
+    }
+
+    #[test]
+    fn test_program_of() {
+        assert_eq!(program_of("RUST_LOG=debug cargo run").as_deref(), Some("cargo"));
+        assert_eq!(program_of("  python -V").as_deref(), Some("python"));
+        assert_eq!(program_of("   "), None);
+    }
+}
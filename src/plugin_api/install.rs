@@ -0,0 +1,284 @@
+//! Installing plugins into `<config>/plugins/<id>/`, from a directory or a
+//! `.tar.gz` archive holding one. Whether a plugin is enabled is kept in the
+//! config file's `enabled_plugins`, not here.
+
+use std::path::{Path, PathBuf};
+
+use super::manifest::{PluginManifest, MANIFEST_FILE};
+use super::PluginError;
+use crate::config::ConfigPaths;
+
+/// A plugin directory and what its manifest says, or why it can't be read
+#[derive(Debug, Clone)]
+pub struct InstalledPlugin {
+    pub dir: PathBuf,
+    pub manifest: Result<PluginManifest, PluginError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginManager {
+    plugins_dir: PathBuf,
+}
+
+impl PluginManager {
+    pub fn new() -> Result<Self, PluginError> {
+        let paths = ConfigPaths::resolve().map_err(|e| PluginError::Io(e.to_string()))?;
+        Ok(Self::with_dir(paths.plugins_dir()))
+    }
+
+    pub fn with_dir(plugins_dir: PathBuf) -> Self {
+        Self { plugins_dir }
+    }
+
+    /// Install from `source`, a plugin directory or a `.tar.gz` of one. The
+    /// manifest and entry point are checked before anything is kept, and an
+    /// id that's already installed is refused. `review` sees the manifest,
+    /// to ask about its permissions; returning false cancels the install.
+    pub fn install_plugin(
+        &self,
+        source: &str,
+        review: impl FnOnce(&PluginManifest) -> bool,
+    ) -> Result<PluginManifest, PluginError> {
+        let source = Path::new(source);
+        std::fs::create_dir_all(&self.plugins_dir).map_err(io_error)?;
+        // Unpacked next to the final location so the move is a rename
+        let staging = self.plugins_dir.join(format!(".installing-{}", uuid::Uuid::new_v4()));
+        let result = self.stage(source, &staging).and_then(|root| self.commit(&root, review));
+        let _ = std::fs::remove_dir_all(&staging);
+        result
+    }
+
+    fn stage(&self, source: &Path, staging: &Path) -> Result<PathBuf, PluginError> {
+        if source.is_dir() {
+            copy_dir(source, staging)?;
+            return Ok(staging.to_path_buf());
+        }
+        let name = source.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if !(name.ends_with(".tar.gz") || name.ends_with(".tgz")) {
+            return Err(PluginError::InvalidManifest(format!(
+                "{} is not a plugin directory or .tar.gz archive",
+                source.display()
+            )));
+        }
+        let file = std::fs::File::open(source).map_err(io_error)?;
+        // `unpack` refuses entries that would land outside `staging`
+        tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(staging).map_err(io_error)?;
+        plugin_root(staging)
+    }
+
+    fn commit(&self, root: &Path, review: impl FnOnce(&PluginManifest) -> bool) -> Result<PluginManifest, PluginError> {
+        let manifest = PluginManifest::load(root)?;
+        let target = self.plugin_dir(&manifest.id);
+        if target.exists() {
+            return Err(PluginError::AlreadyInstalled(manifest.id));
+        }
+        if !review(&manifest) {
+            return Err(PluginError::InstallCancelled(manifest.id));
+        }
+        std::fs::rename(root, &target).map_err(io_error)?;
+        Ok(manifest)
+    }
+
+    /// Replace the installed plugin `id` with the `.tar.gz` in `archive`,
+    /// which must hold the same plugin at a newer version. The old version
+    /// is moved aside and put back if the new one can't be moved in.
+    pub fn update_plugin(&self, id: &str, archive: &[u8]) -> Result<PluginManifest, PluginError> {
+        let installed = self.get(id)?;
+        let token = uuid::Uuid::new_v4();
+        let download = self.plugins_dir.join(format!(".download-{}.tar.gz", token));
+        let staging = self.plugins_dir.join(format!(".installing-{}", token));
+        let backup = self.plugins_dir.join(format!(".previous-{}-{}", id, token));
+
+        let result = std::fs::write(&download, archive)
+            .map_err(io_error)
+            .and_then(|()| self.stage(&download, &staging))
+            .and_then(|root| {
+                let manifest = PluginManifest::load(&root)?;
+                if manifest.id != id {
+                    return Err(PluginError::InvalidManifest(format!("the update is for '{}', not '{}'", manifest.id, id)));
+                }
+                let newer = semver::Version::parse(&manifest.version).ok() > semver::Version::parse(&installed.version).ok();
+                if !newer {
+                    return Err(PluginError::InvalidManifest(format!(
+                        "the update's version {} is not newer than {}",
+                        manifest.version, installed.version
+                    )));
+                }
+
+                let target = self.plugin_dir(id);
+                std::fs::rename(&target, &backup).map_err(io_error)?;
+                if let Err(e) = std::fs::rename(&root, &target) {
+                    std::fs::rename(&backup, &target).map_err(io_error)?;
+                    return Err(io_error(e));
+                }
+                Ok(manifest)
+            });
+
+        let _ = std::fs::remove_file(&download);
+        let _ = std::fs::remove_dir_all(&staging);
+        if result.is_ok() {
+            let _ = std::fs::remove_dir_all(&backup);
+        }
+        result
+    }
+
+    /// Where the plugin with `id` is or would be installed
+    pub fn plugin_dir(&self, id: &str) -> PathBuf {
+        self.plugins_dir.join(id)
+    }
+
+    /// Every installed plugin, by id
+    pub fn installed(&self) -> Result<Vec<InstalledPlugin>, PluginError> {
+        let entries = match std::fs::read_dir(&self.plugins_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(e)),
+        };
+        let mut plugins: Vec<InstalledPlugin> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|dir| dir.is_dir() && !dir.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')))
+            .map(|dir| InstalledPlugin { manifest: PluginManifest::load(&dir), dir })
+            .collect();
+        plugins.sort_by(|a, b| a.dir.cmp(&b.dir));
+        Ok(plugins)
+    }
+
+    pub fn get(&self, id: &str) -> Result<PluginManifest, PluginError> {
+        let dir = self.plugin_dir(id);
+        if !dir.is_dir() {
+            return Err(PluginError::NotInstalled(id.to_string()));
+        }
+        PluginManifest::load(&dir)
+    }
+}
+
+/// The directory holding `plugin.toml`: the archive's top level, or its
+/// only directory when everything was packed inside one
+fn plugin_root(unpacked: &Path) -> Result<PathBuf, PluginError> {
+    if unpacked.join(MANIFEST_FILE).is_file() {
+        return Ok(unpacked.to_path_buf());
+    }
+    let entries: Vec<PathBuf> = std::fs::read_dir(unpacked)
+        .map_err(io_error)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect();
+    match entries.as_slice() {
+        [only] if only.join(MANIFEST_FILE).is_file() => Ok(only.clone()),
+        _ => Err(PluginError::InvalidManifest(format!("no {} in the archive", MANIFEST_FILE))),
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), PluginError> {
+    for entry in walkdir::WalkDir::new(from) {
+        let entry = entry.map_err(|e| PluginError::Io(e.to_string()))?;
+        let target = to.join(entry.path().strip_prefix(from).expect("walkdir yields paths under its root"));
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target).map_err(io_error)?;
+        } else if entry.file_type().is_file() {
+            std::fs::copy(entry.path(), &target).map_err(io_error)?;
+        }
+    }
+    Ok(())
+}
+
+fn io_error(e: std::io::Error) -> PluginError {
+    PluginError::Io(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_plugin(dir: &Path, id: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(
+            dir.join(MANIFEST_FILE),
+            format!("id = \"{}\"\nname = \"Hello\"\nversion = \"1.0.0\"\nplugin_type = \"script\"\nentry_point = \"bin/hello\"\n", id),
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.join("bin")).unwrap();
+        std::fs::write(dir.join("bin/hello"), "#!/bin/sh\necho hello\n").unwrap();
+    }
+
+    #[test]
+    fn test_install_from_directory_rejects_duplicate_ids() {
+        let source = tempfile::tempdir().unwrap();
+        let config = tempfile::tempdir().unwrap();
+        write_plugin(source.path(), "hello");
+        let manager = PluginManager::with_dir(config.path().join("plugins"));
+
+        let manifest = manager.install_plugin(source.path().to_str().unwrap(), |_| true).unwrap();
+        assert_eq!(manifest.id, "hello");
+        assert!(manager.plugin_dir("hello").join("bin/hello").is_file());
+        assert!(matches!(
+            manager.install_plugin(source.path().to_str().unwrap(), |_| true),
+            Err(PluginError::AlreadyInstalled(id)) if id == "hello"
+        ));
+
+        let installed = manager.installed().unwrap();
+        assert_eq!(installed.len(), 1);
+        assert_eq!(installed[0].manifest.as_ref().unwrap().version, "1.0.0");
+    }
+
+    #[test]
+    fn test_install_from_archive_with_top_level_directory() {
+        let source = tempfile::tempdir().unwrap();
+        write_plugin(&source.path().join("hello-1.0.0"), "hello");
+        let archive = source.path().join("hello-1.0.0.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            std::fs::File::create(&archive).unwrap(),
+            flate2::Compression::default(),
+        ));
+        builder.append_dir_all("hello-1.0.0", source.path().join("hello-1.0.0")).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let config = tempfile::tempdir().unwrap();
+        let manager = PluginManager::with_dir(config.path().join("plugins"));
+        manager.install_plugin(archive.to_str().unwrap(), |_| true).unwrap();
+        assert!(manager.plugin_dir("hello").join(MANIFEST_FILE).is_file());
+        // Nothing is left behind from unpacking
+        assert_eq!(std::fs::read_dir(config.path().join("plugins")).unwrap().count(), 1);
+    }
+
+    fn archive_of(dir: &Path) -> Vec<u8> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        builder.append_dir_all(".", dir).unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_update_swaps_in_newer_version_only() {
+        let source = tempfile::tempdir().unwrap();
+        write_plugin(source.path(), "hello");
+        let config = tempfile::tempdir().unwrap();
+        let manager = PluginManager::with_dir(config.path().join("plugins"));
+        manager.install_plugin(source.path().to_str().unwrap(), |_| true).unwrap();
+
+        // Same version: refused, and the installed copy is untouched
+        let same = archive_of(source.path());
+        assert!(matches!(manager.update_plugin("hello", &same), Err(PluginError::InvalidManifest(_))));
+        assert_eq!(manager.get("hello").unwrap().version, "1.0.0");
+
+        let manifest = std::fs::read_to_string(source.path().join(MANIFEST_FILE)).unwrap();
+        std::fs::write(source.path().join(MANIFEST_FILE), manifest.replace("1.0.0", "1.1.0")).unwrap();
+        let updated = manager.update_plugin("hello", &archive_of(source.path())).unwrap();
+        assert_eq!(updated.version, "1.1.0");
+        assert_eq!(manager.get("hello").unwrap().version, "1.1.0");
+        // Only the plugin itself is left in the directory
+        assert_eq!(std::fs::read_dir(config.path().join("plugins")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_missing_entry_point_is_not_installed() {
+        let source = tempfile::tempdir().unwrap();
+        write_plugin(source.path(), "hello");
+        std::fs::remove_file(source.path().join("bin/hello")).unwrap();
+        let config = tempfile::tempdir().unwrap();
+        let manager = PluginManager::with_dir(config.path().join("plugins"));
+
+        assert!(matches!(manager.install_plugin(source.path().to_str().unwrap(), |_| true), Err(PluginError::InvalidManifest(_))));
+        assert!(manager.installed().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+//! Example plugin: `kpods [namespace]` shows a Kubernetes pod-status table.
+
+use serde_json::{json, Value};
+use uuid::Uuid;
+use super::{Plugin, PluginError, PluginOutput, RenderNode, Tone};
+
+pub const BLOCK_TYPE: &str = "k8s.pods";
+
+#[derive(Debug)]
+pub struct PodStatusPlugin;
+
+impl Plugin for PodStatusPlugin {
+    fn name(&self) -> &str {
+        "k8s-pods"
+    }
+
+    fn commands(&self) -> Vec<String> {
+        vec!["kpods".to_string()]
+    }
+
+    fn block_types(&self) -> Vec<String> {
+        vec![BLOCK_TYPE.to_string()]
+    }
+
+    fn execute(&self, _command: &str, args: &[String]) -> Result<PluginOutput, PluginError> {
+        let namespace = args.first().cloned().unwrap_or_else(|| "default".to_string());
+        Ok(PluginOutput::Block {
+            block_type: BLOCK_TYPE.to_string(),
+            data: json!({
+                "namespace": namespace,
+                "failing_only": false,
+                "pods": fetch_pods(&namespace)?,
+            }),
+        })
+    }
+
+    fn render(&self, _block_type: &str, data: &Value) -> Result<RenderNode, PluginError> {
+        let namespace = data["namespace"].as_str().unwrap_or("default");
+        let failing_only = data["failing_only"].as_bool().unwrap_or(false);
+        let pods = data["pods"].as_array().cloned().unwrap_or_default();
+
+        let ready = pods.iter().filter(|pod| is_healthy(pod)).count();
+        let rows = pods
+            .iter()
+            .filter(|pod| !failing_only || !is_healthy(pod))
+            .map(|pod| {
+                vec![
+                    pod["name"].as_str().unwrap_or("").to_string(),
+                    pod["ready"].as_str().unwrap_or("").to_string(),
+                    pod["status"].as_str().unwrap_or("").to_string(),
+                    pod["restarts"].as_u64().unwrap_or(0).to_string(),
+                ]
+            })
+            .collect();
+
+        let summary_tone = if ready == pods.len() { Tone::Success } else { Tone::Warning };
+
+        Ok(RenderNode::Column {
+            children: vec![
+                RenderNode::Row {
+                    children: vec![
+                        RenderNode::styled(format!("Pods in {}", namespace), true, Tone::Default),
+                        RenderNode::Button { label: "Refresh".to_string(), action: "refresh".to_string() },
+                        RenderNode::Button {
+                            label: if failing_only { "Show all" } else { "Show failing" }.to_string(),
+                            action: "toggle_failing".to_string(),
+                        },
+                    ],
+                },
+                RenderNode::Progress {
+                    value: if pods.is_empty() { 0.0 } else { ready as f32 / pods.len() as f32 },
+                    label: None,
+                },
+                RenderNode::styled(format!("{}/{} pods ready", ready, pods.len()), false, summary_tone),
+                RenderNode::Table {
+                    headers: ["NAME", "READY", "STATUS", "RESTARTS"].map(String::from).to_vec(),
+                    rows,
+                },
+            ],
+        })
+    }
+
+    fn handle_event(&self, _block_id: Uuid, action: &str, data: &Value) -> Result<Option<Value>, PluginError> {
+        let mut data = data.clone();
+        match action {
+            "toggle_failing" => {
+                let failing_only = data["failing_only"].as_bool().unwrap_or(false);
+                data["failing_only"] = json!(!failing_only);
+            }
+            "refresh" => {
+                let namespace = data["namespace"].as_str().unwrap_or("default").to_string();
+                data["pods"] = fetch_pods(&namespace)?;
+            }
+            _ => return Ok(None),
+        }
+        Ok(Some(data))
+    }
+}
+
+fn is_healthy(pod: &Value) -> bool {
+    let status = pod["status"].as_str().unwrap_or("");
+    status == "Succeeded" || (status == "Running" && pod["all_ready"].as_bool().unwrap_or(false))
+}
+
+fn fetch_pods(namespace: &str) -> Result<Value, PluginError> {
+    let output = std::process::Command::new("kubectl")
+        .args(["get", "pods", "-o", "json", "-n", namespace])
+        .output()
+        .map_err(|e| PluginError::Failed(format!("kubectl: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(PluginError::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+
+    let list: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| PluginError::Failed(e.to_string()))?;
+    Ok(Value::Array(summarize_pods(&list)))
+}
+
+/// Reduce `kubectl get pods -o json` to the fields the table shows
+pub fn summarize_pods(list: &Value) -> Vec<Value> {
+    list["items"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .map(|pod| {
+                    let containers = pod["status"]["containerStatuses"].as_array().cloned().unwrap_or_default();
+                    let ready = containers.iter().filter(|c| c["ready"].as_bool().unwrap_or(false)).count();
+                    let restarts: u64 = containers.iter().filter_map(|c| c["restartCount"].as_u64()).sum();
+                    // A waiting container's reason (CrashLoopBackOff, ...) is more useful than the phase
+                    let status = containers
+                        .iter()
+                        .find_map(|c| c["state"]["waiting"]["reason"].as_str())
+                        .or_else(|| pod["status"]["phase"].as_str())
+                        .unwrap_or("Unknown");
+
+                    json!({
+                        "name": pod["metadata"]["name"],
+                        "ready": format!("{}/{}", ready, containers.len()),
+                        "all_ready": ready == containers.len(),
+                        "status": status,
+                        "restarts": restarts,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data() -> Value {
+        let list = json!({
+            "items": [
+                {
+                    "metadata": { "name": "api-7d9f" },
+                    "status": {
+                        "phase": "Running",
+                        "containerStatuses": [{ "ready": true, "restartCount": 0, "state": { "running": {} } }]
+                    }
+                },
+                {
+                    "metadata": { "name": "worker-55c1" },
+                    "status": {
+                        "phase": "Running",
+                        "containerStatuses": [{
+                            "ready": false,
+                            "restartCount": 12,
+                            "state": { "waiting": { "reason": "CrashLoopBackOff" } }
+                        }]
+                    }
+                }
+            ]
+        });
+        json!({ "namespace": "prod", "failing_only": false, "pods": summarize_pods(&list) })
+    }
+
+    #[test]
+    fn test_pod_table_snapshot() {
+        let tree = PodStatusPlugin.render(BLOCK_TYPE, &sample_data()).unwrap();
+        tree.validate().unwrap();
+
+        assert_eq!(
+            tree.to_plain_text(),
+            "**Pods in prod**  [Refresh]  [Show failing]\n\
+             [##########----------]  50%\n\
+             1/2 pods ready\n\
+             NAME        | READY | STATUS           | RESTARTS\n\
+             ------------+-------+------------------+---------\n\
+             api-7d9f    | 1/1   | Running          | 0\n\
+             worker-55c1 | 0/1   | CrashLoopBackOff | 12"
+        );
+    }
+
+    #[test]
+    fn test_toggle_failing_filters_rows() {
+        let data = PodStatusPlugin
+            .handle_event(Uuid::new_v4(), "toggle_failing", &sample_data())
+            .unwrap()
+            .unwrap();
+
+        let tree = PodStatusPlugin.render(BLOCK_TYPE, &data).unwrap();
+        let text = tree.to_plain_text();
+        assert!(text.contains("[Show all]"));
+        assert!(text.contains("worker-55c1"));
+        assert!(!text.contains("api-7d9f"));
+    }
+}
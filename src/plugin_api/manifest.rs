@@ -0,0 +1,190 @@
+//! `plugin.toml`, the manifest every installed plugin ships at the top of
+//! its directory:
+//!
+//! ```toml
+//! id = "git-graph"
+//! name = "Git graph"
+//! version = "0.3.1"
+//! plugin_type = "wasm"
+//! entry_point = "git_graph.wasm"
+//! permissions = ["filesystem", "run_commands"]
+//!
+//! [config_schema]
+//! type = "object"
+//! properties.max_commits = { type = "integer", default = 200 }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+
+use super::PluginError;
+use crate::i18n::tr;
+
+pub const MANIFEST_FILE: &str = "plugin.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginType {
+    /// A WebAssembly module
+    Wasm,
+    /// An executable run with the plugin's directory as working directory
+    Script,
+}
+
+impl std::fmt::Display for PluginType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PluginType::Wasm => "wasm",
+            PluginType::Script => "script",
+        })
+    }
+}
+
+/// Something a plugin may do beyond drawing its own blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginPermission {
+    /// Read and write files outside its data directory
+    Filesystem,
+    /// Make network requests
+    Network,
+    /// Run shell commands
+    RunCommands,
+    /// Read the clipboard
+    Clipboard,
+    /// Read environment variables
+    Environment,
+}
+
+impl PluginPermission {
+    /// What granting it lets the plugin do, for the install review
+    pub fn description(&self) -> &'static str {
+        match self {
+            PluginPermission::Filesystem => tr("plugin.permission.filesystem"),
+            PluginPermission::Network => tr("plugin.permission.network"),
+            PluginPermission::RunCommands => tr("plugin.permission.run_commands"),
+            PluginPermission::Clipboard => tr("plugin.permission.clipboard"),
+            PluginPermission::Environment => tr("plugin.permission.environment"),
+        }
+    }
+}
+
+impl std::fmt::Display for PluginPermission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PluginPermission::Filesystem => "filesystem",
+            PluginPermission::Network => "network",
+            PluginPermission::RunCommands => "run_commands",
+            PluginPermission::Clipboard => "clipboard",
+            PluginPermission::Environment => "environment",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginManifest {
+    /// Unique, and the name of the directory it's installed in
+    pub id: String,
+    pub name: String,
+    /// Semantic version, e.g. "1.4.0"
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub plugin_type: PluginType,
+    /// Path of the module or executable, relative to the plugin's directory
+    pub entry_point: String,
+    #[serde(default)]
+    pub permissions: Vec<PluginPermission>,
+    /// JSON Schema for the plugin's settings, checked before they're passed in
+    #[serde(default)]
+    pub config_schema: Option<serde_json::Value>,
+    /// Index listing the plugin's releases, for `neoterm plugin update`
+    #[serde(default)]
+    pub registry_url: Option<String>,
+    /// Base URL whose `index.json` is the index, when there's no `registry_url`
+    #[serde(default)]
+    pub repository: Option<String>,
+}
+
+impl PluginManifest {
+    pub fn from_toml(content: &str) -> Result<Self, PluginError> {
+        toml::from_str(content).map_err(|e| PluginError::InvalidManifest(e.to_string()))
+    }
+
+    /// Read and check the manifest of the plugin in `dir`
+    pub fn load(dir: &Path) -> Result<Self, PluginError> {
+        let path = dir.join(MANIFEST_FILE);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| PluginError::InvalidManifest(format!("{}: {}", path.display(), e)))?;
+        let manifest = Self::from_toml(&content)?;
+        manifest.validate(dir)?;
+        Ok(manifest)
+    }
+
+    /// Check the fields, and that the entry point is a file inside `dir`
+    pub fn validate(&self, dir: &Path) -> Result<(), PluginError> {
+        let valid_id = !self.id.is_empty()
+            && self.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid_id {
+            return Err(PluginError::InvalidManifest(format!(
+                "id '{}' must be lowercase letters, digits, '-' or '_'",
+                self.id
+            )));
+        }
+        if self.name.trim().is_empty() {
+            return Err(PluginError::InvalidManifest("name is empty".to_string()));
+        }
+        semver::Version::parse(&self.version)
+            .map_err(|e| PluginError::InvalidManifest(format!("version '{}': {}", self.version, e)))?;
+
+        let entry_point = Path::new(&self.entry_point);
+        let inside = entry_point.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if self.entry_point.is_empty() || !inside {
+            return Err(PluginError::InvalidManifest(format!(
+                "entry_point '{}' must be a path inside the plugin",
+                self.entry_point
+            )));
+        }
+        if !dir.join(entry_point).is_file() {
+            return Err(PluginError::InvalidManifest(format!("entry_point '{}' does not exist", self.entry_point)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+id = "git-graph"
+name = "Git graph"
+version = "0.3.1"
+plugin_type = "wasm"
+entry_point = "git_graph.wasm"
+permissions = ["filesystem", "run_commands"]
+
+[config_schema]
+type = "object"
+properties.max_commits = { type = "integer", default = 200 }
+"#;
+
+    #[test]
+    fn test_manifest_parses_and_checks_entry_point() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = PluginManifest::from_toml(MANIFEST).unwrap();
+        assert_eq!(manifest.plugin_type, PluginType::Wasm);
+        assert_eq!(manifest.permissions, vec![PluginPermission::Filesystem, PluginPermission::RunCommands]);
+        assert_eq!(manifest.config_schema.as_ref().unwrap()["properties"]["max_commits"]["default"], 200);
+        assert!(matches!(manifest.validate(dir.path()), Err(PluginError::InvalidManifest(e)) if e.contains("does not exist")));
+
+        std::fs::write(dir.path().join("git_graph.wasm"), b"\0asm").unwrap();
+        manifest.validate(dir.path()).unwrap();
+
+        let escaping = PluginManifest { entry_point: "../git_graph.wasm".to_string(), ..manifest.clone() };
+        assert!(escaping.validate(dir.path()).is_err());
+        let bad_version = PluginManifest { version: "latest".to_string(), ..manifest };
+        assert!(bad_version.validate(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+pub mod render;
+pub mod k8s_pods;
+pub mod manifest;
+pub mod install;
+pub mod permissions;
+pub mod update;
+
+pub use render::{RenderError, RenderNode, TextStyle, Tone};
+pub use manifest::{PluginManifest, PluginPermission, PluginType};
+pub use install::{InstalledPlugin, PluginManager};
+pub use permissions::PluginGrants;
+pub use update::{AvailableUpdate, RegistryEntry, RegistryIndex};
+
+/// Time a plugin may spend producing a render tree. Rendering runs on the UI
+/// thread, so a slower plugin would drop frames; its output is discarded instead.
+pub const RENDER_BUDGET: Duration = Duration::from_millis(8);
+
+/// What a plugin command produces
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluginOutput {
+    Text(String),
+    /// Structured data to be drawn by one of the plugin's block types
+    Block { block_type: String, data: Value },
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum PluginError {
+    #[error("No plugin handles block type '{0}'")]
+    UnknownBlockType(String),
+    #[error("Block type '{0}' is already registered by plugin '{1}'")]
+    DuplicateBlockType(String, String),
+    #[error("Plugin '{0}' took {1:?} to render, over the {budget:?} budget", budget = RENDER_BUDGET)]
+    OverBudget(String, Duration),
+    #[error("Invalid render tree: {0}")]
+    InvalidTree(#[from] RenderError),
+    #[error("Plugin failed: {0}")]
+    Failed(String),
+    #[error("Invalid plugin manifest: {0}")]
+    InvalidManifest(String),
+    #[error("A plugin with id '{0}' is already installed")]
+    AlreadyInstalled(String),
+    #[error("No plugin with id '{0}' is installed")]
+    NotInstalled(String),
+    #[error("Plugin I/O error: {0}")]
+    Io(String),
+    #[error("Installing '{0}' was cancelled")]
+    InstallCancelled(String),
+    #[error("Download does not match its checksum: expected sha256 {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    /// A host call needed a permission the plugin wasn't granted or that was revoked
+    #[error("Plugin '{0}' does not have the {1} permission")]
+    PermissionDenied(String, PluginPermission),
+}
+
+/// In-process plugin. Plugins keep per-block state in the block's JSON data
+/// rather than in themselves, so one instance can serve many blocks.
+pub trait Plugin: Send + Sync + std::fmt::Debug {
+    fn name(&self) -> &str;
+
+    /// Commands typed at the prompt that this plugin handles
+    fn commands(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Block types this plugin can render
+    fn block_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn execute(&self, command: &str, args: &[String]) -> Result<PluginOutput, PluginError>;
+
+    /// Turn a block's data into a render tree
+    fn render(&self, _block_type: &str, _data: &Value) -> Result<RenderNode, PluginError> {
+        Err(PluginError::Failed(format!("{} does not render blocks", self.name())))
+    }
+
+    /// React to a button press in one of this plugin's blocks. Returning new
+    /// data re-renders the block.
+    fn handle_event(&self, _block_id: Uuid, _action: &str, _data: &Value) -> Result<Option<Value>, PluginError> {
+        Ok(None)
+    }
+}
+
+/// The plugins bundled with NeoTerm, enabled by name like installed ones
+pub fn builtins() -> Vec<Arc<dyn Plugin>> {
+    vec![Arc::new(k8s_pods::PodStatusPlugin)]
+}
+
+/// A block whose contents are drawn by a plugin
+#[derive(Debug, Clone)]
+pub struct PluginBlock {
+    pub plugin: String,
+    pub block_type: String,
+    pub data: Value,
+    /// Last successfully rendered tree, kept when a re-render fails
+    pub tree: Option<RenderNode>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PluginHost {
+    plugins: Vec<Arc<dyn Plugin>>,
+}
+
+impl PluginHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Host with the bundled plugins that are listed in `enabled`
+    pub fn with_builtins(enabled: &[String]) -> Self {
+        let mut host = Self::new();
+        for plugin in builtins() {
+            if enabled.iter().any(|name| name == plugin.name()) {
+                // Bundled plugins don't overlap, so registration can't fail
+                let _ = host.register(plugin);
+            }
+        }
+        host
+    }
+
+    pub fn register(&mut self, plugin: Arc<dyn Plugin>) -> Result<(), PluginError> {
+        for block_type in plugin.block_types() {
+            if let Some(owner) = self.owner_of(&block_type) {
+                return Err(PluginError::DuplicateBlockType(block_type, owner.name().to_string()));
+            }
+        }
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    /// Names of the registered plugins
+    pub fn names(&self) -> Vec<&str> {
+        self.plugins.iter().map(|p| p.name()).collect()
+    }
+
+    /// Every prompt command, with the name of the plugin handling it
+    pub fn commands(&self) -> Vec<(String, String)> {
+        self.plugins
+            .iter()
+            .flat_map(|p| p.commands().into_iter().map(|command| (p.name().to_string(), command)))
+            .collect()
+    }
+
+    fn owner_of(&self, block_type: &str) -> Option<&Arc<dyn Plugin>> {
+        self.plugins.iter().find(|p| p.block_types().iter().any(|t| t == block_type))
+    }
+
+    /// Plugin handling the first word of `input`, if any
+    pub fn plugin_for_command(&self, input: &str) -> Option<Arc<dyn Plugin>> {
+        let command = input.split_whitespace().next()?;
+        self.plugins
+            .iter()
+            .find(|p| p.commands().iter().any(|c| c == command))
+            .cloned()
+    }
+
+    /// Run a plugin command; block output is rendered straight away
+    pub fn execute(plugin: &dyn Plugin, input: &str) -> Result<PluginOutput, PluginError> {
+        let mut words = input.split_whitespace().map(str::to_string);
+        let command = words.next().unwrap_or_default();
+        let args: Vec<String> = words.collect();
+        plugin.execute(&command, &args)
+    }
+
+    pub fn create_block(&self, block_type: String, data: Value) -> Result<PluginBlock, PluginError> {
+        let plugin = self.owner_of(&block_type)
+            .ok_or_else(|| PluginError::UnknownBlockType(block_type.clone()))?;
+        let mut block = PluginBlock {
+            plugin: plugin.name().to_string(),
+            block_type,
+            data,
+            tree: None,
+            error: None,
+        };
+        self.render(&mut block);
+        Ok(block)
+    }
+
+    /// Re-render a block, enforcing the size limits and time budget
+    pub fn render(&self, block: &mut PluginBlock) {
+        match self.render_tree(&block.block_type, &block.data) {
+            Ok(tree) => {
+                block.tree = Some(tree);
+                block.error = None;
+            }
+            Err(e) => block.error = Some(e.to_string()),
+        }
+    }
+
+    fn render_tree(&self, block_type: &str, data: &Value) -> Result<RenderNode, PluginError> {
+        let plugin = self.owner_of(block_type)
+            .ok_or_else(|| PluginError::UnknownBlockType(block_type.to_string()))?;
+
+        let started = Instant::now();
+        let tree = plugin.render(block_type, data)?;
+        let elapsed = started.elapsed();
+        if elapsed > RENDER_BUDGET {
+            return Err(PluginError::OverBudget(plugin.name().to_string(), elapsed));
+        }
+
+        tree.validate()?;
+        Ok(tree)
+    }
+
+    /// Route a button press to the block's plugin and re-render on new data
+    pub fn handle_event(&self, block_id: Uuid, block: &mut PluginBlock, action: &str) {
+        let Some(plugin) = self.owner_of(&block.block_type).cloned() else {
+            block.error = Some(PluginError::UnknownBlockType(block.block_type.clone()).to_string());
+            return;
+        };
+
+        match plugin.handle_event(block_id, action, &block.data) {
+            Ok(Some(data)) => {
+                block.data = data;
+                self.render(block);
+            }
+            Ok(None) => {}
+            Err(e) => block.error = Some(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug)]
+    struct SlowPlugin;
+
+    impl Plugin for SlowPlugin {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn block_types(&self) -> Vec<String> {
+            vec!["slow".to_string()]
+        }
+
+        fn execute(&self, _command: &str, _args: &[String]) -> Result<PluginOutput, PluginError> {
+            Ok(PluginOutput::Text(String::new()))
+        }
+
+        fn render(&self, _block_type: &str, data: &Value) -> Result<RenderNode, PluginError> {
+            std::thread::sleep(RENDER_BUDGET * 2);
+            Ok(RenderNode::text(data.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_render_over_budget_is_rejected() {
+        let mut host = PluginHost::new();
+        host.register(Arc::new(SlowPlugin)).unwrap();
+
+        let block = host.create_block("slow".to_string(), json!(1)).unwrap();
+        assert!(block.tree.is_none());
+        assert!(block.error.unwrap().contains("budget"));
+    }
+
+    #[test]
+    fn test_duplicate_block_types_are_rejected() {
+        let mut host = PluginHost::new();
+        host.register(Arc::new(SlowPlugin)).unwrap();
+        assert!(matches!(
+            host.register(Arc::new(SlowPlugin)),
+            Err(PluginError::DuplicateBlockType(..))
+        ));
+    }
+
+    #[test]
+    fn test_oversized_trees_fail_validation() {
+        let wide = RenderNode::Column {
+            children: (0..=render::MAX_NODES).map(|i| RenderNode::text(i.to_string())).collect(),
+        };
+        assert_eq!(wide.validate(), Err(RenderError::TooManyNodes));
+
+        let mut deep = RenderNode::text("leaf");
+        for _ in 0..render::MAX_DEPTH {
+            deep = RenderNode::Row { children: vec![deep] };
+        }
+        assert_eq!(deep.validate(), Err(RenderError::TooDeep));
+    }
+
+    #[test]
+    fn test_render_tree_deserializes_from_json() {
+        let tree: RenderNode = serde_json::from_value(json!({
+            "type": "column",
+            "children": [
+                { "type": "text", "content": "Build", "style": { "bold": true, "tone": "success" } },
+                { "type": "progress", "value": 0.5 },
+                { "type": "button", "label": "Retry", "action": "retry" }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(tree.to_plain_text(), "**Build**\n[##########----------]  50%\n[Retry]");
+    }
+}
@@ -0,0 +1,71 @@
+//! Which of its declared permissions each plugin has been granted. The user
+//! agrees to every declared permission at install time and can revoke any of
+//! them later; host calls check here first and fail with
+//! `PluginError::PermissionDenied`, which plugins can match on.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{PluginError, PluginPermission};
+
+/// Kept in the config file, by plugin id
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PluginGrants {
+    grants: BTreeMap<String, BTreeSet<PluginPermission>>,
+}
+
+impl PluginGrants {
+    pub fn grant(&mut self, plugin: &str, permissions: impl IntoIterator<Item = PluginPermission>) {
+        self.grants.entry(plugin.to_string()).or_default().extend(permissions);
+    }
+
+    pub fn revoke(&mut self, plugin: &str, permission: PluginPermission) {
+        if let Some(granted) = self.grants.get_mut(plugin) {
+            granted.remove(&permission);
+        }
+    }
+
+    /// Drop everything granted to an uninstalled plugin
+    pub fn forget(&mut self, plugin: &str) {
+        self.grants.remove(plugin);
+    }
+
+    pub fn is_granted(&self, plugin: &str, permission: PluginPermission) -> bool {
+        self.grants.get(plugin).is_some_and(|granted| granted.contains(&permission))
+    }
+
+    /// Gate for a host call that needs `permission`
+    pub fn check(&self, plugin: &str, permission: PluginPermission) -> Result<(), PluginError> {
+        if self.is_granted(plugin, permission) {
+            Ok(())
+        } else {
+            Err(PluginError::PermissionDenied(plugin.to_string(), permission))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revoked_permission_is_denied() {
+        let mut grants = PluginGrants::default();
+        grants.grant("git-graph", [PluginPermission::Network, PluginPermission::RunCommands]);
+        grants.check("git-graph", PluginPermission::RunCommands).unwrap();
+
+        grants.revoke("git-graph", PluginPermission::RunCommands);
+        assert!(matches!(
+            grants.check("git-graph", PluginPermission::RunCommands),
+            Err(PluginError::PermissionDenied(plugin, PluginPermission::RunCommands)) if plugin == "git-graph"
+        ));
+        grants.check("git-graph", PluginPermission::Network).unwrap();
+        // Nothing declared, nothing granted
+        assert!(grants.check("other", PluginPermission::Network).is_err());
+
+        let saved = toml::to_string(&grants).unwrap();
+        assert_eq!(saved, "git-graph = [\"network\"]\n");
+        assert_eq!(toml::from_str::<PluginGrants>(&saved).unwrap(), grants);
+    }
+}
@@ -0,0 +1,256 @@
+use iced::{Element, widget::{button, column, progress_bar, row, text}};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Largest render tree a plugin may return, counted in nodes
+pub const MAX_NODES: usize = 2_000;
+/// Deepest nesting of rows and columns
+pub const MAX_DEPTH: usize = 16;
+/// Longest text, link label or table cell, in bytes
+pub const MAX_TEXT_LEN: usize = 16 * 1024;
+
+/// Declarative UI returned by a plugin for one of its block types.
+/// Only these primitives exist so every renderer can draw them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RenderNode {
+    Text {
+        content: String,
+        #[serde(default)]
+        style: TextStyle,
+    },
+    Row {
+        children: Vec<RenderNode>,
+    },
+    Column {
+        children: Vec<RenderNode>,
+    },
+    /// `value` is clamped to 0.0..=1.0
+    Progress {
+        value: f32,
+        #[serde(default)]
+        label: Option<String>,
+    },
+    Table {
+        headers: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    Link {
+        label: String,
+        url: String,
+    },
+    /// Pressing sends `action` to the plugin's `handle_event`
+    Button {
+        label: String,
+        action: String,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextStyle {
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub tone: Tone,
+}
+
+/// Semantic colors; renderers map them onto the active theme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tone {
+    #[default]
+    Default,
+    Muted,
+    Success,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RenderError {
+    #[error("Render tree has more than {MAX_NODES} nodes")]
+    TooManyNodes,
+    #[error("Render tree is nested deeper than {MAX_DEPTH} levels")]
+    TooDeep,
+    #[error("Text longer than {MAX_TEXT_LEN} bytes")]
+    TextTooLong,
+}
+
+impl RenderNode {
+    pub fn text(content: impl Into<String>) -> Self {
+        RenderNode::Text { content: content.into(), style: TextStyle::default() }
+    }
+
+    pub fn styled(content: impl Into<String>, bold: bool, tone: Tone) -> Self {
+        RenderNode::Text { content: content.into(), style: TextStyle { bold, tone } }
+    }
+
+    /// Check the tree against the size limits before it is drawn
+    pub fn validate(&self) -> Result<(), RenderError> {
+        let mut nodes = 0;
+        self.validate_inner(1, &mut nodes)
+    }
+
+    fn validate_inner(&self, depth: usize, nodes: &mut usize) -> Result<(), RenderError> {
+        if depth > MAX_DEPTH {
+            return Err(RenderError::TooDeep);
+        }
+        *nodes += 1;
+        if *nodes > MAX_NODES {
+            return Err(RenderError::TooManyNodes);
+        }
+
+        let check = |s: &str| if s.len() > MAX_TEXT_LEN { Err(RenderError::TextTooLong) } else { Ok(()) };
+
+        match self {
+            RenderNode::Text { content, .. } => check(content),
+            RenderNode::Row { children } | RenderNode::Column { children } => {
+                children.iter().try_for_each(|child| child.validate_inner(depth + 1, nodes))
+            }
+            RenderNode::Progress { label, .. } => label.as_deref().map_or(Ok(()), check),
+            RenderNode::Table { headers, rows } => {
+                // Each cell counts as a node so huge tables hit the same limit
+                *nodes += headers.len() + rows.iter().map(Vec::len).sum::<usize>();
+                if *nodes > MAX_NODES {
+                    return Err(RenderError::TooManyNodes);
+                }
+                headers.iter().chain(rows.iter().flatten()).try_for_each(|cell| check(cell))
+            }
+            RenderNode::Link { label, url } => check(label).and(check(url)),
+            RenderNode::Button { label, action } => check(label).and(check(action)),
+        }
+    }
+
+    /// Plain-text rendering for terminal output, exports and snapshots
+    pub fn to_plain_text(&self) -> String {
+        let mut out = String::new();
+        self.write_plain(&mut out);
+        out
+    }
+
+    fn write_plain(&self, out: &mut String) {
+        match self {
+            RenderNode::Text { content, style } => {
+                if style.bold {
+                    out.push_str(&format!("**{}**", content));
+                } else {
+                    out.push_str(content);
+                }
+            }
+            RenderNode::Row { children } => {
+                let cells: Vec<String> = children.iter().map(RenderNode::to_plain_text).collect();
+                out.push_str(&cells.join("  "));
+            }
+            RenderNode::Column { children } => {
+                let lines: Vec<String> = children.iter().map(RenderNode::to_plain_text).collect();
+                out.push_str(&lines.join("\n"));
+            }
+            RenderNode::Progress { value, label } => {
+                const WIDTH: usize = 20;
+                let value = value.clamp(0.0, 1.0);
+                let filled = (value * WIDTH as f32).round() as usize;
+                out.push_str(&format!(
+                    "[{}{}] {:>3}%",
+                    "#".repeat(filled),
+                    "-".repeat(WIDTH - filled),
+                    (value * 100.0).round() as u32
+                ));
+                if let Some(label) = label {
+                    out.push_str(&format!(" {}", label));
+                }
+            }
+            RenderNode::Table { headers, rows } => {
+                let columns = headers.len().max(rows.iter().map(Vec::len).max().unwrap_or(0));
+                let width = |i: usize| {
+                    headers.get(i).map_or(0, |h| h.chars().count()).max(
+                        rows.iter().filter_map(|r| r.get(i)).map(|c| c.chars().count()).max().unwrap_or(0)
+                    )
+                };
+                let widths: Vec<usize> = (0..columns).map(width).collect();
+                let line = |cells: &[String]| {
+                    (0..columns)
+                        .map(|i| format!("{:<w$}", cells.get(i).map(String::as_str).unwrap_or(""), w = widths[i]))
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                        .trim_end()
+                        .to_string()
+                };
+
+                let mut lines = vec![line(headers)];
+                lines.push(widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().join("-+-"));
+                lines.extend(rows.iter().map(|r| line(r)));
+                out.push_str(&lines.join("\n"));
+            }
+            RenderNode::Link { label, url } => out.push_str(&format!("{} <{}>", label, url)),
+            RenderNode::Button { label, .. } => out.push_str(&format!("[{}]", label)),
+        }
+    }
+
+    /// Draw the tree in the GUI. Button presses are routed back to the
+    /// owning plugin together with `block_id`.
+    pub fn view(&self, block_id: Uuid) -> Element<'_, crate::Message> {
+        match self {
+            RenderNode::Text { content, style } => {
+                let size = if style.bold { 14 } else { 13 };
+                match tone_color(style.tone) {
+                    Some(color) => text(content).size(size).style(iced::theme::Text::Color(color)).into(),
+                    None => text(content).size(size).into(),
+                }
+            }
+            RenderNode::Row { children } => {
+                row(children.iter().map(|child| child.view(block_id)).collect::<Vec<_>>())
+                    .spacing(8)
+                    .into()
+            }
+            RenderNode::Column { children } => {
+                column(children.iter().map(|child| child.view(block_id)).collect::<Vec<_>>())
+                    .spacing(4)
+                    .into()
+            }
+            RenderNode::Progress { value, label } => {
+                let bar = progress_bar(0.0..=1.0, value.clamp(0.0, 1.0)).height(8);
+                match label {
+                    Some(label) => column![text(label).size(12), bar].spacing(2).into(),
+                    None => bar.into(),
+                }
+            }
+            RenderNode::Table { headers, rows } => {
+                let cell = |value: &str, bold: bool| -> Element<'_, crate::Message> {
+                    text(value.to_string())
+                        .size(if bold { 13 } else { 12 })
+                        .width(iced::Length::FillPortion(1))
+                        .into()
+                };
+                let mut table = column![
+                    row(headers.iter().map(|h| cell(h, true)).collect::<Vec<_>>()).spacing(8)
+                ]
+                .spacing(2);
+                for r in rows {
+                    table = table.push(row(r.iter().map(|c| cell(c, false)).collect::<Vec<_>>()).spacing(8));
+                }
+                table.into()
+            }
+            RenderNode::Link { label, url } => {
+                text(format!("{} ↗ {}", label, url))
+                    .size(13)
+                    .style(iced::theme::Text::Color(iced::Color::from_rgb(0.2, 0.4, 0.9)))
+                    .into()
+            }
+            RenderNode::Button { label, action } => {
+                button(text(label).size(12))
+                    .on_press(crate::Message::PluginEvent(block_id, action.clone()))
+                    .into()
+            }
+        }
+    }
+}
+
+fn tone_color(tone: Tone) -> Option<iced::Color> {
+    match tone {
+        Tone::Default => None,
+        Tone::Muted => Some(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+        Tone::Success => Some(iced::Color::from_rgb(0.0, 0.6, 0.0)),
+        Tone::Warning => Some(iced::Color::from_rgb(0.8, 0.5, 0.0)),
+        Tone::Error => Some(iced::Color::from_rgb(0.8, 0.0, 0.0)),
+    }
+}
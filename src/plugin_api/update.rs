@@ -0,0 +1,160 @@
+//! Updating installed plugins from a registry index. A plugin names its
+//! index with `registry_url`, or with `repository`, whose `index.json` is
+//! used. An index is a small JSON file:
+//!
+//! ```json
+//! { "plugins": [
+//!     { "id": "git-graph", "version": "0.4.0",
+//!       "url": "https://example.com/git-graph-0.4.0.tar.gz",
+//!       "sha256": "9f86d081884c7d65…" }
+//! ] }
+//! ```
+//!
+//! Archives are checked against their `sha256` before they're unpacked.
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::{PluginError, PluginManifest};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RegistryIndex {
+    pub plugins: Vec<RegistryEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RegistryEntry {
+    pub id: String,
+    pub version: String,
+    /// A `.tar.gz` of the plugin
+    pub url: String,
+    /// Hex digest of the archive
+    pub sha256: String,
+}
+
+/// A newer version than the installed one
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvailableUpdate {
+    pub id: String,
+    pub installed: String,
+    pub latest: RegistryEntry,
+}
+
+impl RegistryIndex {
+    /// The highest version listed for `id`; unparseable versions are ignored
+    pub fn latest(&self, id: &str) -> Option<&RegistryEntry> {
+        self.plugins
+            .iter()
+            .filter(|entry| entry.id == id)
+            .filter_map(|entry| Some((semver::Version::parse(&entry.version).ok()?, entry)))
+            .max_by(|a, b| a.0.cmp(&b.0))
+            .map(|(_, entry)| entry)
+    }
+
+    /// The update for `manifest`, when the index has a newer version
+    pub fn update_for(&self, manifest: &PluginManifest) -> Option<AvailableUpdate> {
+        let installed = semver::Version::parse(&manifest.version).ok()?;
+        let latest = self.latest(&manifest.id)?;
+        (semver::Version::parse(&latest.version).ok()? > installed).then(|| AvailableUpdate {
+            id: manifest.id.clone(),
+            installed: manifest.version.clone(),
+            latest: latest.clone(),
+        })
+    }
+}
+
+/// Where the plugin's index is, if it says
+pub fn index_url(manifest: &PluginManifest) -> Option<String> {
+    manifest.registry_url.clone().or_else(|| {
+        let repository = manifest.repository.as_deref()?;
+        Some(format!("{}/index.json", repository.trim_end_matches('/')))
+    })
+}
+
+pub async fn fetch_index(url: &str) -> Result<RegistryIndex, PluginError> {
+    let client = crate::net::client(Some(std::time::Duration::from_secs(30))).map_err(|e| PluginError::Io(e.to_string()))?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| PluginError::Io(crate::net::describe_error(&e)))?;
+    response.json().await.map_err(|e| PluginError::Io(format!("{}: {}", url, e)))
+}
+
+/// Ask each plugin's index for a newer version. Plugins without an index
+/// are left out; an index that can't be read is reported for its plugin.
+pub async fn check_updates(manifests: Vec<PluginManifest>) -> Vec<(String, Result<Option<AvailableUpdate>, PluginError>)> {
+    let mut results = Vec::new();
+    for manifest in manifests {
+        let Some(url) = index_url(&manifest) else { continue };
+        let result = fetch_index(&url).await.map(|index| index.update_for(&manifest));
+        results.push((manifest.id, result));
+    }
+    results
+}
+
+/// Download an update's archive, refusing it unless it matches the index
+pub async fn download(update: &AvailableUpdate) -> Result<Vec<u8>, PluginError> {
+    let client = crate::net::client(None).map_err(|e| PluginError::Io(e.to_string()))?;
+    let bytes = client
+        .get(&update.latest.url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| PluginError::Io(crate::net::describe_error(&e)))?
+        .bytes()
+        .await
+        .map_err(|e| PluginError::Io(e.to_string()))?;
+    verify_sha256(&bytes, &update.latest.sha256)?;
+    Ok(bytes.to_vec())
+}
+
+pub fn verify_sha256(bytes: &[u8], expected: &str) -> Result<(), PluginError> {
+    let actual: String = Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect();
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(PluginError::ChecksumMismatch { expected: expected.trim().to_string(), actual })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(version: &str) -> RegistryEntry {
+        RegistryEntry {
+            id: "git-graph".to_string(),
+            version: version.to_string(),
+            url: format!("https://example.com/git-graph-{}.tar.gz", version),
+            sha256: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_newest_semver_version_is_offered() {
+        let manifest = PluginManifest::from_toml(
+            "id = \"git-graph\"\nname = \"Git graph\"\nversion = \"0.9.0\"\nplugin_type = \"wasm\"\n\
+             entry_point = \"git_graph.wasm\"\nrepository = \"https://example.com/git-graph/\"\n",
+        )
+        .unwrap();
+        assert_eq!(index_url(&manifest).as_deref(), Some("https://example.com/git-graph/index.json"));
+
+        // 0.10.0 sorts after 0.9.0, unlike the strings
+        let index = RegistryIndex { plugins: vec![entry("0.8.2"), entry("0.10.0"), entry("nightly"), entry("0.9.1")] };
+        let update = index.update_for(&manifest).unwrap();
+        assert_eq!(update.latest.version, "0.10.0");
+
+        let current = PluginManifest { version: "0.10.0".to_string(), ..manifest };
+        assert_eq!(index.update_for(&current), None);
+    }
+
+    #[test]
+    fn test_checksum_must_match() {
+        let sha_of_abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        verify_sha256(b"abc", sha_of_abc).unwrap();
+        verify_sha256(b"abc", &sha_of_abc.to_uppercase()).unwrap();
+        assert!(matches!(verify_sha256(b"abd", sha_of_abc), Err(PluginError::ChecksumMismatch { .. })));
+    }
+}
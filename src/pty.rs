@@ -0,0 +1,338 @@
+//! Commands run in a pseudo-terminal sized to the block list, so programs
+//! that ask for the terminal size (`tput cols`, `htop`, `vim`) lay out for
+//! the space a block has rather than whatever NeoTerm was started from.
+//! Resizing the block list resizes every running command's terminal, which
+//! the kernel passes on as SIGWINCH.
+//!
+//! A PTY has a single output stream, so stdout and stderr arrive together
+//! and all output is recorded as stdout.
+//!
+//! Keys typed into a running block are written to its PTY as a terminal
+//! would send them. What the command shows of them is whatever the PTY
+//! echoes back, so input to a program that turns echo off (a password
+//! prompt) never reaches the block's output.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use iced::keyboard::{key::Named, Key, Modifiers};
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use tokio::sync::mpsc::{Receiver, Sender};
+use uuid::Uuid;
+use crate::font::OutputFont;
+use crate::jobs::{Job, JobRegistry};
+use crate::shell::CommandEvent;
+use crate::timeline::{OutputChunk, OutputStream};
+
+/// Borders and padding between the block list's edge and a block's output
+const BLOCK_CHROME: f32 = 40.0;
+const MIN_COLS: u16 = 20;
+const MIN_ROWS: u16 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalSize {
+    pub cols: u16,
+    pub rows: u16,
+}
+
+impl Default for TerminalSize {
+    fn default() -> Self {
+        Self { cols: 80, rows: 24 }
+    }
+}
+
+impl TerminalSize {
+    /// Cells of a block's output in a block list `width` × `height` pixels,
+    /// drawn in `font`
+    pub fn for_viewport(width: f32, height: f32, font: &OutputFont) -> Self {
+        let cells = |pixels: f32, cell: f32, min: u16| {
+            ((pixels - BLOCK_CHROME).max(0.0) / cell).floor().clamp(min as f32, u16::MAX as f32) as u16
+        };
+        Self {
+            cols: cells(width, font.cell_width(), MIN_COLS),
+            rows: cells(height, font.line_height(), MIN_ROWS),
+        }
+    }
+
+    fn pty_size(self) -> PtySize {
+        PtySize { rows: self.rows, cols: self.cols, pixel_width: 0, pixel_height: 0 }
+    }
+}
+
+/// The terminal size commands get, and the PTYs of those still running
+#[derive(Clone, Default)]
+pub struct PtyManager {
+    size: Arc<Mutex<TerminalSize>>,
+    live: Arc<Mutex<HashMap<Uuid, Box<dyn MasterPty + Send>>>>,
+    /// Stdin of the running commands
+    writers: Arc<Mutex<HashMap<Uuid, Box<dyn Write + Send>>>>,
+    jobs: JobRegistry,
+}
+
+impl std::fmt::Debug for PtyManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PtyManager")
+            .field("size", &self.size())
+            .field("running", &self.live.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl PtyManager {
+    /// Keep the running commands in `jobs`
+    pub fn with_jobs(jobs: JobRegistry) -> Self {
+        Self { jobs, ..Default::default() }
+    }
+
+    pub fn jobs(&self) -> &JobRegistry {
+        &self.jobs
+    }
+
+    pub fn size(&self) -> TerminalSize {
+        *self.size.lock().unwrap()
+    }
+
+    /// Whether `block_id`'s command is running in a PTY and can take input
+    pub fn is_running(&self, block_id: Uuid) -> bool {
+        self.writers.lock().unwrap().contains_key(&block_id)
+    }
+
+    /// Send `bytes` to the stdin of `block_id`'s command
+    pub fn write(&self, block_id: Uuid, bytes: &[u8]) -> std::io::Result<()> {
+        let mut writers = self.writers.lock().unwrap();
+        let writer = writers
+            .get_mut(&block_id)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "the command has exited"))?;
+        writer.write_all(bytes)?;
+        writer.flush()
+    }
+
+    /// Whether `block_id`'s command has turned echo off, as password
+    /// prompts do
+    #[cfg(unix)]
+    pub fn echo_off(&self, block_id: Uuid) -> bool {
+        let live = self.live.lock().unwrap();
+        let Some(fd) = live.get(&block_id).and_then(|master| master.as_raw_fd()) else {
+            return false;
+        };
+        let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: `fd` is the open master of a PTY and tcgetattr only fills `termios`
+        if unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) } != 0 {
+            return false;
+        }
+        // SAFETY: tcgetattr succeeded, so `termios` is initialised
+        let termios = unsafe { termios.assume_init() };
+        termios.c_lflag & libc::ECHO == 0
+    }
+
+    #[cfg(not(unix))]
+    pub fn echo_off(&self, _block_id: Uuid) -> bool {
+        false
+    }
+
+    /// Size new commands' terminals to `size` and resize the running ones.
+    /// Returns whether the size changed.
+    pub fn resize(&self, size: TerminalSize) -> bool {
+        {
+            let mut current = self.size.lock().unwrap();
+            if *current == size {
+                return false;
+            }
+            *current = size;
+        }
+        for (block_id, master) in self.live.lock().unwrap().iter() {
+            if let Err(e) = master.resize(size.pty_size()) {
+                log::debug!("Cannot resize the terminal of block {}: {}", block_id, e);
+            }
+        }
+        true
+    }
+
+    /// Run `program` with `args` in a new terminal of the current size,
+    /// streaming its output and then its exit code. It's listed as a job
+    /// under `command` until it exits.
+    pub fn spawn(
+        &self,
+        block_id: Uuid,
+        command: &str,
+        program: &str,
+        args: &[&str],
+        cwd: &Path,
+        env: &HashMap<String, String>,
+    ) -> Receiver<CommandEvent> {
+        let (tx, rx) = tokio::sync::mpsc::channel(256);
+        let mut builder = CommandBuilder::new(program);
+        builder.args(args);
+        builder.cwd(cwd);
+        builder.env_clear();
+        for (key, value) in env {
+            builder.env(key, value);
+        }
+        if !env.contains_key("TERM") {
+            builder.env("TERM", "xterm-256color");
+        }
+
+        let spawned = native_pty_system().openpty(self.size().pty_size()).and_then(|pair| {
+            let child = pair.slave.spawn_command(builder)?;
+            // Only the child holds the slave, so reads end when it exits
+            drop(pair.slave);
+            let reader = pair.master.try_clone_reader()?;
+            let writer = pair.master.take_writer()?;
+            Ok((pair.master, child, reader, writer))
+        });
+        let (master, mut child, mut reader, writer) = match spawned {
+            Ok(spawned) => spawned,
+            Err(e) => {
+                fail(&tx, &e.to_string());
+                return rx;
+            }
+        };
+        self.live.lock().unwrap().insert(block_id, master);
+        self.writers.lock().unwrap().insert(block_id, writer);
+        if let Some(pid) = child.process_id() {
+            self.jobs.insert(Job { block_id, pid, command: command.to_string(), started_at: chrono::Utc::now() });
+        }
+
+        let live = self.live.clone();
+        let writers = self.writers.clone();
+        let jobs = self.jobs.clone();
+        std::thread::spawn(move || {
+            let started = std::time::Instant::now();
+            let mut pending = Vec::new();
+            let mut buffer = [0u8; 4096];
+            // Linux reports EIO rather than EOF once the child has gone
+            while let Ok(read) = reader.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+                pending.extend_from_slice(&buffer[..read]);
+                let text = decode(&mut pending);
+                if text.is_empty() {
+                    continue;
+                }
+                let chunk = OutputChunk { offset_ms: started.elapsed().as_millis() as u64, stream: OutputStream::Stdout, text };
+                if tx.blocking_send(CommandEvent::Chunk(chunk)).is_err() {
+                    break;
+                }
+            }
+            let exit_code = child.wait().map(|status| status.exit_code() as i32).unwrap_or(1);
+            jobs.remove(block_id);
+            writers.lock().unwrap().remove(&block_id);
+            live.lock().unwrap().remove(&block_id);
+            let _ = tx.blocking_send(CommandEvent::Exited(exit_code));
+        });
+        rx
+    }
+}
+
+/// The bytes a terminal sends for a key press; `text` is what the key types
+pub fn key_bytes(key: &Key, modifiers: Modifiers, text: Option<&str>) -> Option<Vec<u8>> {
+    let sequence: &[u8] = match key.as_ref() {
+        Key::Named(Named::Enter) => b"\r",
+        Key::Named(Named::Backspace) => b"\x7f",
+        Key::Named(Named::Tab) if modifiers.shift() => b"\x1b[Z",
+        Key::Named(Named::Tab) => b"\t",
+        Key::Named(Named::Escape) => b"\x1b",
+        Key::Named(Named::ArrowUp) => b"\x1b[A",
+        Key::Named(Named::ArrowDown) => b"\x1b[B",
+        Key::Named(Named::ArrowRight) => b"\x1b[C",
+        Key::Named(Named::ArrowLeft) => b"\x1b[D",
+        Key::Named(Named::Home) => b"\x1b[H",
+        Key::Named(Named::End) => b"\x1b[F",
+        Key::Named(Named::Delete) => b"\x1b[3~",
+        Key::Named(Named::PageUp) => b"\x1b[5~",
+        Key::Named(Named::PageDown) => b"\x1b[6~",
+        // Ctrl+C is 0x03, Ctrl+[ is Esc and so on
+        Key::Character(c) if modifiers.control() => {
+            let byte = c.bytes().next().filter(|_| c.len() == 1)?.to_ascii_uppercase();
+            return (b'@'..=b'_').contains(&byte).then(|| vec![byte & 0x1f]);
+        }
+        _ => {
+            let typed = text.filter(|text| !text.is_empty() && !text.chars().any(char::is_control))?;
+            let mut bytes = if modifiers.alt() { vec![0x1b] } else { Vec::new() };
+            bytes.extend_from_slice(typed.as_bytes());
+            return Some(bytes);
+        }
+    };
+    Some(sequence.to_vec())
+}
+
+/// The complete characters at the start of `pending`, with CRLF line ends
+/// made plain; an incomplete character is left for the next read
+fn decode(pending: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(pending) {
+        Ok(text) => text.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let text = String::from_utf8_lossy(&pending[..complete]).replace("\r\n", "\n");
+    pending.drain(..complete);
+    text
+}
+
+fn fail(tx: &Sender<CommandEvent>, error: &str) {
+    let chunk = OutputChunk { offset_ms: 0, stream: OutputStream::Stderr, text: format!("Failed to execute command: {}\n", error) };
+    let _ = tx.try_send(CommandEvent::Chunk(chunk));
+    let _ = tx.try_send(CommandEvent::Exited(1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_to_cells() {
+        let font = OutputFont::default();
+        assert_eq!(TerminalSize::for_viewport(760.0, 520.0, &font), TerminalSize { cols: 100, rows: 30 });
+        assert_eq!(TerminalSize::for_viewport(0.0, 0.0, &font), TerminalSize { cols: MIN_COLS, rows: MIN_ROWS });
+        let zoomed = OutputFont { size: font.size * 2.0, ..font };
+        assert_eq!(TerminalSize::for_viewport(760.0, 520.0, &zoomed), TerminalSize { cols: 50, rows: 15 });
+    }
+
+    #[test]
+    fn test_key_bytes() {
+        let none = Modifiers::empty();
+        assert_eq!(key_bytes(&Key::Named(Named::Enter), none, Some("\r")), Some(b"\r".to_vec()));
+        assert_eq!(key_bytes(&Key::Named(Named::ArrowUp), none, None), Some(b"\x1b[A".to_vec()));
+        assert_eq!(key_bytes(&Key::Character("c".into()), Modifiers::CTRL, Some("c")), Some(vec![3]));
+        assert_eq!(key_bytes(&Key::Character("é".into()), none, Some("é")), Some("é".as_bytes().to_vec()));
+        assert_eq!(key_bytes(&Key::Named(Named::Shift), Modifiers::SHIFT, None), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_input_typed_with_echo_off_stays_out_of_the_output() {
+        let pty = PtyManager::default();
+        let block_id = Uuid::new_v4();
+        let env: HashMap<String, String> = std::env::vars().filter(|(key, _)| key == "PATH").collect();
+        let script = "stty -echo; printf 'Password: '; read secret; echo; echo \"got ${#secret}\"";
+        let mut rx = pty.spawn(block_id, "read secret", "/bin/sh", &["-c", script], Path::new("/"), &env);
+
+        let mut output = String::new();
+        while !output.contains("Password:") {
+            match rx.recv().await {
+                Some(CommandEvent::Chunk(chunk)) => output.push_str(&chunk.text),
+                other => panic!("exited before prompting: {:?}", other),
+            }
+        }
+        assert!(pty.echo_off(block_id));
+        pty.write(block_id, b"hunter2\r").unwrap();
+        while let Some(CommandEvent::Chunk(chunk)) = rx.recv().await {
+            output.push_str(&chunk.text);
+        }
+        assert!(output.contains("got 7"), "{}", output);
+        assert!(!output.contains("hunter2"), "{}", output);
+        assert!(!pty.is_running(block_id));
+    }
+
+    #[test]
+    fn test_decode_waits_for_split_characters() {
+        let mut pending = "é\r\n".as_bytes().to_vec();
+        let last = pending.split_off(1);
+        assert_eq!(decode(&mut pending), "");
+        pending.extend(last);
+        assert_eq!(decode(&mut pending), "é\n");
+        assert!(pending.is_empty());
+    }
+}
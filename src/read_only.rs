@@ -0,0 +1,210 @@
+//! Read-only mode: while it is on, nothing may start a process. Every path
+//! that spawns (the shell manager, the workflow executor, the agent's
+//! `execute_command` tool) holds a clone of the same guard and checks it
+//! right before spawning, so no UI path can get around it.
+
+use std::sync::{Arc, RwLock};
+
+/// Why the session is read-only
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadOnlyReason {
+    /// Asked for with `--read-only`
+    Requested,
+    ImportedSession,
+    RecoveredSession,
+    /// Watching someone else's shared session
+    RemoteViewer,
+}
+
+impl ReadOnlyReason {
+    /// Tooltip text for disabled controls
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            ReadOnlyReason::Requested => "This session was opened read-only",
+            ReadOnlyReason::ImportedSession => "Imported sessions open read-only so nothing re-runs by accident",
+            ReadOnlyReason::RecoveredSession => "Recovered sessions open read-only until you choose to continue",
+            ReadOnlyReason::RemoteViewer => "You are viewing someone else's session",
+        }
+    }
+
+    /// Remote viewers can never run anything
+    pub fn can_exit(&self) -> bool {
+        !matches!(self, ReadOnlyReason::RemoteViewer)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ReadOnlyError {
+    #[error("Read-only: {0}")]
+    Blocked(&'static str),
+    #[error("A viewer session cannot leave read-only mode")]
+    CannotExit,
+    #[error("Confirm leaving read-only mode first")]
+    NotArmed,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    reason: Option<ReadOnlyReason>,
+    /// First step of leaving taken
+    exit_armed: bool,
+}
+
+/// Shared switch; clones see the same state
+#[derive(Debug, Clone, Default)]
+pub struct ReadOnly {
+    state: Arc<RwLock<State>>,
+}
+
+impl ReadOnly {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enable(&self, reason: ReadOnlyReason) {
+        if let Ok(mut state) = self.state.write() {
+            state.reason = Some(reason);
+            state.exit_armed = false;
+        }
+    }
+
+    pub fn reason(&self) -> Option<ReadOnlyReason> {
+        self.state.read().ok().and_then(|state| state.reason)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// `Err` when nothing may be spawned
+    pub fn check(&self) -> Result<(), ReadOnlyError> {
+        match self.reason() {
+            Some(reason) => Err(ReadOnlyError::Blocked(reason.explanation())),
+            None => Ok(()),
+        }
+    }
+
+    /// First step of leaving read-only mode
+    pub fn arm_exit(&self) -> Result<(), ReadOnlyError> {
+        let mut state = self.state.write().map_err(|_| ReadOnlyError::NotArmed)?;
+        match state.reason {
+            Some(reason) if !reason.can_exit() => Err(ReadOnlyError::CannotExit),
+            Some(_) => {
+                state.exit_armed = true;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_exit_armed(&self) -> bool {
+        self.state.read().is_ok_and(|state| state.exit_armed)
+    }
+
+    pub fn disarm_exit(&self) {
+        if let Ok(mut state) = self.state.write() {
+            state.exit_armed = false;
+        }
+    }
+
+    /// Second step: actually leave, only after `arm_exit`
+    pub fn confirm_exit(&self) -> Result<(), ReadOnlyError> {
+        let mut state = self.state.write().map_err(|_| ReadOnlyError::NotArmed)?;
+        match state.reason {
+            Some(reason) if !reason.can_exit() => Err(ReadOnlyError::CannotExit),
+            Some(_) if !state.exit_armed => Err(ReadOnlyError::NotArmed),
+            _ => {
+                state.reason = None;
+                state.exit_armed = false;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_mode_eval::tools::{ToolCall, ToolRegistry};
+    use crate::shell::{CommandEvent, ShellManager};
+    use crate::workflows::{Shell, Workflow, WorkflowExecutor};
+    use std::collections::HashMap;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn touch(marker: &Path) -> String {
+        format!("touch '{}'", marker.display())
+    }
+
+    #[tokio::test]
+    async fn test_no_entry_point_spawns_while_read_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let marker = temp_dir.path().join("spawned");
+        let guard = ReadOnly::new();
+        guard.enable(ReadOnlyReason::ImportedSession);
+
+        // Shell manager: one-shot, streaming and interactive
+        let mut shell = ShellManager::new();
+        shell.set_read_only(guard.clone());
+        let (output, code) = shell.execute_command(touch(&marker)).await;
+        assert_ne!(code, 0);
+        assert!(output.contains("Read-only"), "{}", output);
+
+        let mut events = shell.execute_command_streaming(touch(&marker), HashMap::new());
+        let mut exit = None;
+        while let Some(event) = events.recv().await {
+            if let CommandEvent::Exited(code) = event {
+                exit = Some(code);
+            }
+        }
+        assert_ne!(exit, Some(0));
+
+        let mut lines = shell.execute_interactive_command(touch(&marker)).await;
+        assert_eq!(lines.recv().await, None);
+
+        // Workflows
+        let workflow = Workflow::from_yaml(&format!("name: touch\ncommand: {}\n", touch(&marker))).unwrap();
+        let executor = WorkflowExecutor::new(Shell::Bash).with_read_only(guard.clone());
+        let execution = executor.prepare_execution(&workflow, HashMap::new()).unwrap();
+        assert!(executor.execute_workflow(&execution).await.is_err());
+
+        // The agent's execute_command tool
+        let registry = ToolRegistry::new().with_read_only(guard.clone());
+        let result = registry
+            .execute_tool(ToolCall {
+                id: "1".to_string(),
+                name: "execute_command".to_string(),
+                arguments: HashMap::from([("command".to_string(), serde_json::json!(touch(&marker)))]),
+            })
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Read-only"));
+
+        // Give anything that did spawn time to run
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(!marker.exists(), "a process was spawned in read-only mode");
+
+        // Once left, commands run again
+        guard.arm_exit().unwrap();
+        guard.confirm_exit().unwrap();
+        let (_, code) = shell.execute_command(touch(&marker)).await;
+        assert_eq!(code, 0);
+        assert!(marker.exists());
+    }
+
+    #[test]
+    fn test_leaving_takes_two_steps_and_never_for_viewers() {
+        let guard = ReadOnly::new();
+        guard.enable(ReadOnlyReason::RecoveredSession);
+        assert_eq!(guard.confirm_exit(), Err(ReadOnlyError::NotArmed));
+        guard.arm_exit().unwrap();
+        guard.confirm_exit().unwrap();
+        assert!(!guard.is_enabled());
+
+        guard.enable(ReadOnlyReason::RemoteViewer);
+        assert_eq!(guard.arm_exit(), Err(ReadOnlyError::CannotExit));
+        assert_eq!(guard.confirm_exit(), Err(ReadOnlyError::CannotExit));
+        assert!(guard.is_enabled());
+    }
+}
@@ -0,0 +1,151 @@
+use regex::Regex;
+use std::collections::HashSet;
+
+/// Replacement text used wherever a secret value is masked.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Minimum length for a registered value to be considered worth masking.
+/// Shorter values ("1", "on") would mangle unrelated output.
+const MIN_SECRET_LEN: usize = 6;
+
+/// Masks secret values in text before it leaves the terminal (exports,
+/// shares, AI context, crash reports).
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    secrets: HashSet<String>,
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    pub fn new() -> Self {
+        let patterns = [
+            // OpenAI / Anthropic style keys
+            r"sk-[A-Za-z0-9_\-]{16,}",
+            // GitHub tokens
+            r"gh[pousr]_[A-Za-z0-9]{20,}",
+            // AWS access key ids
+            r"AKIA[0-9A-Z]{16}",
+            // Bearer tokens in headers
+            r"(?i)bearer\s+[A-Za-z0-9\-_\.=]{16,}",
+        ]
+        .iter()
+        .filter_map(|p| Regex::new(p).ok())
+        .collect();
+
+        Self {
+            secrets: HashSet::new(),
+            patterns,
+        }
+    }
+
+    /// Register a concrete value that must never appear in redacted text.
+    pub fn register_secret(&mut self, value: &str) {
+        if value.len() >= MIN_SECRET_LEN {
+            self.secrets.insert(value.to_string());
+        }
+    }
+
+    /// Register every value of an environment map whose key or value looks secret.
+    pub fn register_env<'a, I>(&mut self, vars: I)
+    where
+        I: IntoIterator<Item = (&'a String, &'a String)>,
+    {
+        for (key, value) in vars {
+            if looks_like_secret(key, value) {
+                self.register_secret(value);
+            }
+        }
+    }
+
+    pub fn secret_count(&self) -> usize {
+        self.secrets.len()
+    }
+
+    /// Return `text` with registered secrets and well-known token shapes masked.
+    pub fn redact(&self, text: &str) -> String {
+        let mut result = text.to_string();
+
+        // Longest first so a secret containing another secret is masked whole
+        let mut secrets: Vec<&String> = self.secrets.iter().collect();
+        secrets.sort_by(|a, b| b.len().cmp(&a.len()));
+        for secret in secrets {
+            result = result.replace(secret.as_str(), REDACTED);
+        }
+
+        for pattern in &self.patterns {
+            result = pattern.replace_all(&result, REDACTED).into_owned();
+        }
+
+        result
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether an environment variable name suggests it holds a credential.
+pub fn is_secret_key(key: &str) -> bool {
+    let upper = key.to_uppercase();
+    ["TOKEN", "SECRET", "PASSWORD", "PASSWD", "API_KEY", "APIKEY", "PRIVATE_KEY", "CREDENTIAL", "AUTH"]
+        .iter()
+        .any(|marker| upper.contains(marker))
+}
+
+/// Whether a key/value pair should be treated as a secret.
+pub fn looks_like_secret(key: &str, value: &str) -> bool {
+    if is_secret_key(key) {
+        return !value.is_empty();
+    }
+    Redactor::new().patterns.iter().any(|p| p.is_match(value))
+}
+
+/// Render `KEY=value` for display, masking the value when it looks secret.
+pub fn display_env_pair(key: &str, value: &str) -> String {
+    if looks_like_secret(key, value) {
+        format!("{}={}", key, REDACTED)
+    } else {
+        format!("{}={}", key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_secret_is_masked() {
+        let mut redactor = Redactor::new();
+        redactor.register_secret("hunter2hunter2");
+        assert_eq!(
+            redactor.redact("password is hunter2hunter2!"),
+            format!("password is {}!", REDACTED)
+        );
+    }
+
+    #[test]
+    fn test_short_values_are_not_registered() {
+        let mut redactor = Redactor::new();
+        redactor.register_secret("1");
+        assert_eq!(redactor.secret_count(), 0);
+        assert_eq!(redactor.redact("exit 1"), "exit 1");
+    }
+
+    #[test]
+    fn test_known_token_shapes_are_masked() {
+        let redactor = Redactor::new();
+        let text = "key=sk-abcdefghijklmnopqrstuvwx";
+        assert_eq!(redactor.redact(text), format!("key={}", REDACTED));
+    }
+
+    #[test]
+    fn test_secret_key_detection() {
+        assert!(looks_like_secret("GITHUB_TOKEN", "abc"));
+        assert!(looks_like_secret("DB_PASSWORD", "pw"));
+        assert!(!looks_like_secret("RUST_LOG", "debug"));
+        assert_eq!(display_env_pair("API_KEY", "xyz"), format!("API_KEY={}", REDACTED));
+        assert_eq!(display_env_pair("FOO", "bar"), "FOO=bar");
+    }
+}
@@ -76,19 +76,38 @@ impl SyntaxHighlighter {
         }
     }
 
+    /// One highlighter for the whole app; loading syntaxes and themes is slow
+    pub fn shared() -> &'static SyntaxHighlighter {
+        static SHARED: std::sync::OnceLock<SyntaxHighlighter> = std::sync::OnceLock::new();
+        SHARED.get_or_init(SyntaxHighlighter::new)
+    }
+
+    /// Name of the syntax a fence tag, extension or file name picks, when
+    /// there's one for it
+    pub fn syntax_name(&self, token: &str) -> Option<&str> {
+        self.syntax_set.find_syntax_by_token(token).map(|syntax| syntax.name.as_str())
+    }
+
+    /// `text` with 24-bit color escapes. `language` is a fence tag such as
+    /// `rs` or `bash`; unknown languages and lines the highlighter can't
+    /// handle come back plain.
     pub fn highlight(&self, text: &str, language: &str) -> String {
         let syntax = self.syntax_set
-            .find_syntax_by_extension(language)
-            .or_else(|| self.syntax_set.find_syntax_by_name(language))
+            .find_syntax_by_token(language)
             .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
 
         let theme = &self.theme_set.themes["base16-ocean.dark"];
-        
+
         let mut highlighter = syntect::easy::HighlightLines::new(syntax, theme);
-        let ranges = highlighter.highlight_line(text, &self.syntax_set).unwrap();
-        
-        // Convert to styled text - in a real implementation, you'd convert to Iced's styled text
-        syntect::util::as_24_bit_terminal_escaped(&ranges[..], false)
+        let mut highlighted = String::with_capacity(text.len());
+        for line in syntect::util::LinesWithEndings::from(text) {
+            match highlighter.highlight_line(line, &self.syntax_set) {
+                Ok(ranges) => highlighted.push_str(&syntect::util::as_24_bit_terminal_escaped(&ranges[..], false)),
+                Err(_) => highlighted.push_str(line),
+            }
+        }
+        highlighted.push_str("\x1b[0m");
+        highlighted
     }
 }
 
@@ -132,6 +151,162 @@ impl VirtualScroller {
     }
 }
 
+/// Distance from the bottom, in the same units as the offsets, within which the
+/// view is considered "at the bottom" and keeps following new output.
+pub const FOLLOW_THRESHOLD: f32 = 24.0;
+
+/// Scroll semantics for a list that receives streaming output.
+///
+/// The view follows the tail only while it is already at (or near) the bottom.
+/// Once the user scrolls up it stays anchored at that offset, counting the lines
+/// that arrive below it until they jump back to the latest output.
+#[derive(Debug, Clone)]
+pub struct ScrollState {
+    offset: f32,
+    viewport_height: f32,
+    content_height: f32,
+    follow: bool,
+    unseen_lines: usize,
+    /// Scale applied to the distance the user scrolls
+    sensitivity: f32,
+    follow_threshold: f32,
+    /// The next offset change is a jump the app made, not the user scrolling
+    jumping: bool,
+}
+
+impl ScrollState {
+    pub fn new() -> Self {
+        Self {
+            offset: 0.0,
+            viewport_height: 0.0,
+            content_height: 0.0,
+            follow: true,
+            unseen_lines: 0,
+            sensitivity: 1.0,
+            follow_threshold: FOLLOW_THRESHOLD,
+            jumping: false,
+        }
+    }
+
+    /// For offsets in other units than pixels, such as rows of text
+    pub fn with_follow_threshold(mut self, threshold: f32) -> Self {
+        self.follow_threshold = threshold;
+        self
+    }
+
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity.max(0.0);
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.follow
+    }
+
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    /// Lines appended below the viewport while anchored
+    pub fn unseen_lines(&self) -> usize {
+        self.unseen_lines
+    }
+
+    fn max_offset(&self) -> f32 {
+        (self.content_height - self.viewport_height).max(0.0)
+    }
+
+    fn is_near_bottom(&self) -> bool {
+        self.max_offset() - self.offset <= self.follow_threshold
+    }
+
+    /// Record a viewport report from the renderer. A changed offset means the
+    /// user scrolled; an unchanged offset with different content size means
+    /// content was added, collapsed or expanded.
+    ///
+    /// Returns the offset to move the view to when the sensitivity makes the
+    /// user's scroll go further or less far than the renderer did.
+    pub fn on_viewport(&mut self, offset: f32, viewport_height: f32, content_height: f32) -> Option<f32> {
+        let user_scrolled = (offset - self.offset).abs() > f32::EPSILON;
+
+        self.viewport_height = viewport_height;
+        self.content_height = content_height;
+
+        let mut correction = None;
+        if user_scrolled {
+            let sensitivity = if std::mem::take(&mut self.jumping) { 1.0 } else { self.sensitivity };
+            let scaled = (self.offset + (offset - self.offset) * sensitivity).clamp(0.0, self.max_offset());
+            if (scaled - offset).abs() > f32::EPSILON {
+                correction = Some(scaled);
+            }
+            self.offset = scaled;
+            self.follow = self.is_near_bottom();
+        } else if self.follow {
+            self.offset = self.max_offset();
+        } else {
+            // Content shrank underneath us (e.g. a block collapsed)
+            self.offset = self.offset.min(self.max_offset());
+        }
+
+        if self.follow {
+            self.unseen_lines = 0;
+        }
+        correction
+    }
+
+    /// Take the next reported offset as it is: the app is moving the view
+    /// somewhere, and the sensitivity only applies to the user's scrolling
+    pub fn expect_jump(&mut self) {
+        self.jumping = true;
+    }
+
+    /// Scroll by `delta` from where the view is, e.g. a page at a time, and
+    /// return the new offset. Reaching the bottom resumes following.
+    pub fn scroll_by(&mut self, delta: f32) -> f32 {
+        self.offset = (self.offset + delta).clamp(0.0, self.max_offset());
+        self.follow = self.is_near_bottom();
+        if self.follow {
+            self.unseen_lines = 0;
+        }
+        self.offset
+    }
+
+    pub fn viewport_height(&self) -> f32 {
+        self.viewport_height
+    }
+
+    /// Record newly streamed output. Returns true when the renderer should
+    /// snap to the bottom.
+    pub fn on_output(&mut self, added_lines: usize) -> bool {
+        if self.follow {
+            true
+        } else {
+            self.unseen_lines += added_lines;
+            false
+        }
+    }
+
+    /// Label for the "jump to latest" pill, while output arrives out of view
+    pub fn indicator(&self) -> Option<String> {
+        (!self.follow && self.unseen_lines > 0).then(|| {
+            let noun = if self.unseen_lines == 1 { "line" } else { "lines" };
+            format!("{} new {} ↓", self.unseen_lines, noun)
+        })
+    }
+
+    /// Jump to the newest output and resume following it
+    pub fn jump_to_bottom(&mut self) {
+        self.follow = true;
+        self.unseen_lines = 0;
+        self.offset = self.max_offset();
+    }
+}
+
+impl Default for ScrollState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Performance monitoring and optimization
 pub struct PerformanceMonitor {
     frame_times: Vec<std::time::Duration>,
@@ -186,3 +361,87 @@ impl PerformanceMonitor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_follows_tail_when_at_bottom() {
+        let mut scroll = ScrollState::new();
+        scroll.on_viewport(0.0, 200.0, 1000.0);
+        assert!(scroll.is_following());
+        assert_eq!(scroll.offset(), 800.0);
+
+        assert!(scroll.on_output(10));
+        scroll.on_viewport(800.0, 200.0, 1200.0);
+        assert_eq!(scroll.offset(), 1000.0);
+        assert_eq!(scroll.unseen_lines(), 0);
+    }
+
+    #[test]
+    fn test_anchored_view_does_not_move_while_streaming() {
+        let mut scroll = ScrollState::new();
+        scroll.on_viewport(0.0, 200.0, 1000.0);
+
+        // User scrolls up to read history
+        scroll.on_viewport(300.0, 200.0, 1000.0);
+        assert!(!scroll.is_following());
+
+        for step in 1..=20 {
+            assert!(!scroll.on_output(5));
+            scroll.on_viewport(300.0, 200.0, 1000.0 + step as f32 * 100.0);
+            assert_eq!(scroll.offset(), 300.0);
+        }
+        assert_eq!(scroll.unseen_lines(), 100);
+
+        scroll.jump_to_bottom();
+        assert!(scroll.is_following());
+        assert_eq!(scroll.unseen_lines(), 0);
+        assert_eq!(scroll.offset(), 2800.0);
+    }
+
+    #[test]
+    fn test_sensitivity_scales_user_scrolls() {
+        let mut scroll = ScrollState::new();
+        scroll.set_sensitivity(2.0);
+        scroll.on_viewport(0.0, 200.0, 1000.0);
+
+        // The renderer moved 50 up; the view should go 100 up
+        assert_eq!(scroll.on_viewport(750.0, 200.0, 1000.0), Some(700.0));
+        assert!(!scroll.is_following());
+        assert_eq!(scroll.on_viewport(700.0, 200.0, 1000.0), None);
+
+        // Paging back down to the bottom resumes following
+        assert_eq!(scroll.scroll_by(scroll.viewport_height()), 800.0);
+        assert!(scroll.is_following());
+    }
+
+    #[test]
+    fn test_scrolling_back_near_bottom_resumes_follow() {
+        let mut scroll = ScrollState::new();
+        scroll.on_viewport(0.0, 200.0, 1000.0);
+        scroll.on_viewport(100.0, 200.0, 1000.0);
+        scroll.on_output(3);
+
+        scroll.on_viewport(790.0, 200.0, 1000.0);
+        assert!(scroll.is_following());
+        assert_eq!(scroll.unseen_lines(), 0);
+    }
+
+    #[test]
+    fn test_collapse_clamps_anchored_offset() {
+        let mut scroll = ScrollState::new();
+        scroll.on_viewport(0.0, 200.0, 1000.0);
+        scroll.on_viewport(500.0, 200.0, 1000.0);
+
+        // A block above collapses and the content shrinks
+        scroll.on_viewport(500.0, 200.0, 600.0);
+        assert_eq!(scroll.offset(), 400.0);
+
+        // Expanding again keeps the anchored offset
+        scroll.on_viewport(400.0, 200.0, 1000.0);
+        assert_eq!(scroll.offset(), 400.0);
+        assert!(!scroll.is_following());
+    }
+}
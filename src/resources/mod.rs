@@ -1,5 +1,176 @@
-// resources module stub
+//! Data files shipped with NeoTerm. User files in the config directory
+//! override bundled ones of the same name.
 
-pub fn init() {
-    println!("resources loaded");
+pub mod templates;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::workflows::Workflow;
+
+pub use templates::{fill, BUNDLED_TEMPLATES};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResourceError {
+    #[error("Invalid template {0}: {1}")]
+    InvalidTemplate(String, String),
+    #[error("Duplicate template name '{0}'")]
+    DuplicateTemplate(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResourceManager {
+    /// Keyed by name so listing is stable and overrides replace in place
+    templates: BTreeMap<String, Workflow>,
+}
+
+impl ResourceManager {
+    /// Bundled resources plus overrides from `~/.config/neoterm/templates`.
+    /// A broken override is logged and skipped rather than hiding the rest.
+    pub fn load() -> Self {
+        let mut manager = Self::bundled().expect("bundled templates are checked by tests");
+        if let Some(dir) = Self::user_templates_dir() {
+            for error in manager.load_overrides(&dir) {
+                log::warn!("{}", error);
+            }
+        }
+        manager
+    }
+
+    pub fn user_templates_dir() -> Option<PathBuf> {
+        crate::config::ConfigPaths::resolve().ok().map(|paths| paths.templates_dir())
+    }
+
+    /// Only the templates compiled into the binary
+    pub fn bundled() -> Result<Self, ResourceError> {
+        let mut templates = BTreeMap::new();
+        for (file, yaml) in BUNDLED_TEMPLATES {
+            let template = Workflow::from_yaml(yaml)
+                .map_err(|e| ResourceError::InvalidTemplate(file.to_string(), e.to_string()))?;
+            if templates.contains_key(&template.name) {
+                return Err(ResourceError::DuplicateTemplate(template.name));
+            }
+            templates.insert(template.name.clone(), template);
+        }
+        Ok(Self { templates })
+    }
+
+    /// Add or replace templates from `*.yaml` files in `dir`; returns the files that failed
+    pub fn load_overrides(&mut self, dir: &Path) -> Vec<ResourceError> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml")))
+            .collect();
+        paths.sort();
+
+        let mut errors = Vec::new();
+        for path in paths {
+            match Workflow::from_file(&path) {
+                Ok(template) => {
+                    self.templates.insert(template.name.clone(), template);
+                }
+                Err(e) => errors.push(ResourceError::InvalidTemplate(path.display().to_string(), e.to_string())),
+            }
+        }
+        errors
+    }
+
+    pub fn templates(&self) -> impl Iterator<Item = &Workflow> {
+        self.templates.values()
+    }
+
+    pub fn template(&self, name: &str) -> Option<&Workflow> {
+        self.templates.get(name)
+    }
+
+    /// Templates whose name, description or tags contain every word of `query`
+    pub fn search_templates(&self, query: &str) -> Vec<&Workflow> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        self.templates
+            .values()
+            .filter(|template| {
+                let haystack = format!(
+                    "{} {} {}",
+                    template.name,
+                    template.description.as_deref().unwrap_or(""),
+                    template.tags.join(" ")
+                )
+                .to_lowercase();
+                words.iter().all(|word| haystack.contains(word))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflows::Shell;
+    use std::collections::{HashMap, HashSet};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bundled_templates_parse_with_unique_names() {
+        let mut names = HashSet::new();
+        for (file, yaml) in BUNDLED_TEMPLATES {
+            let template = Workflow::from_yaml(yaml).unwrap_or_else(|e| panic!("{}: {}", file, e));
+            assert_eq!(format!("{}.yaml", template.name), *file);
+            assert!(
+                template.description.as_deref().is_some_and(|d| !d.is_empty() && !d.contains('\n')),
+                "{} needs a one-line description", file
+            );
+            assert!(names.insert(template.name), "duplicate name in {}", file);
+        }
+        assert!(names.len() >= 20);
+        assert_eq!(ResourceManager::bundled().unwrap().templates().count(), names.len());
+    }
+
+    #[test]
+    fn test_fill_quotes_values_and_uses_defaults() {
+        let manager = ResourceManager::bundled().unwrap();
+        let template = manager.template("ssh-tunnel-local").unwrap();
+
+        let arguments = HashMap::from([("server".to_string(), "deploy@bastion".to_string())]);
+        let command = fill(template, arguments, Shell::Bash).unwrap();
+
+        assert_eq!(command, "ssh -N -L '8080':'localhost':'80' 'deploy@bastion'");
+    }
+
+    #[test]
+    fn test_fill_reports_missing_values() {
+        let manager = ResourceManager::bundled().unwrap();
+        let template = manager.template("archive-extract-tar").unwrap();
+
+        assert!(fill(template, HashMap::new(), Shell::Bash).is_err());
+    }
+
+    #[test]
+    fn test_user_templates_override_bundled() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("docker-disk-usage.yaml"),
+            "name: docker-disk-usage\ndescription: Summary only\ncommand: docker system df\n",
+        )
+        .unwrap();
+        std::fs::write(temp_dir.path().join("broken.yaml"), "name: [").unwrap();
+
+        let mut manager = ResourceManager::bundled().unwrap();
+        let bundled_count = manager.templates().count();
+        let errors = manager.load_overrides(temp_dir.path());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(manager.templates().count(), bundled_count);
+        assert_eq!(manager.template("docker-disk-usage").unwrap().command, "docker system df");
+    }
+
+    #[test]
+    fn test_search_matches_all_words() {
+        let manager = ResourceManager::bundled().unwrap();
+        let names: Vec<&str> = manager.search_templates("ssh tunnel").iter().map(|t| t.name.as_str()).collect();
+
+        assert_eq!(names, vec!["ssh-tunnel-local", "ssh-tunnel-remote"]);
+    }
 }
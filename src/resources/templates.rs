@@ -0,0 +1,43 @@
+//! Parameterized command templates for common operations tasks.
+//!
+//! Templates use the workflow format, so `{{name}}` placeholders are filled
+//! and shell-quoted by the same code that runs workflows.
+
+use std::collections::HashMap;
+use crate::workflows::{Shell, Workflow, WorkflowError, WorkflowExecutor};
+
+/// Templates compiled into the binary, as (file name, YAML)
+pub const BUNDLED_TEMPLATES: &[(&str, &str)] = &[
+    ("archive-create-tar-gz.yaml", include_str!("../../resources/templates/archive-create-tar-gz.yaml")),
+    ("archive-create-tar-zst.yaml", include_str!("../../resources/templates/archive-create-tar-zst.yaml")),
+    ("archive-create-zip.yaml", include_str!("../../resources/templates/archive-create-zip.yaml")),
+    ("archive-extract-tar.yaml", include_str!("../../resources/templates/archive-extract-tar.yaml")),
+    ("archive-extract-zip.yaml", include_str!("../../resources/templates/archive-extract-zip.yaml")),
+    ("archive-list-tar.yaml", include_str!("../../resources/templates/archive-list-tar.yaml")),
+    ("bulk-rename-extension.yaml", include_str!("../../resources/templates/bulk-rename-extension.yaml")),
+    ("bulk-rename-lowercase.yaml", include_str!("../../resources/templates/bulk-rename-lowercase.yaml")),
+    ("checksum-directory.yaml", include_str!("../../resources/templates/checksum-directory.yaml")),
+    ("disk-usage-top.yaml", include_str!("../../resources/templates/disk-usage-top.yaml")),
+    ("docker-cleanup-all.yaml", include_str!("../../resources/templates/docker-cleanup-all.yaml")),
+    ("docker-disk-usage.yaml", include_str!("../../resources/templates/docker-disk-usage.yaml")),
+    ("docker-remove-dangling-images.yaml", include_str!("../../resources/templates/docker-remove-dangling-images.yaml")),
+    ("docker-remove-volumes.yaml", include_str!("../../resources/templates/docker-remove-volumes.yaml")),
+    ("docker-stop-all.yaml", include_str!("../../resources/templates/docker-stop-all.yaml")),
+    ("find-large-files.yaml", include_str!("../../resources/templates/find-large-files.yaml")),
+    ("find-old-files.yaml", include_str!("../../resources/templates/find-old-files.yaml")),
+    ("port-who-listens.yaml", include_str!("../../resources/templates/port-who-listens.yaml")),
+    ("rsync-mirror.yaml", include_str!("../../resources/templates/rsync-mirror.yaml")),
+    ("serve-directory.yaml", include_str!("../../resources/templates/serve-directory.yaml")),
+    ("split-large-file.yaml", include_str!("../../resources/templates/split-large-file.yaml")),
+    ("ssh-copy-key.yaml", include_str!("../../resources/templates/ssh-copy-key.yaml")),
+    ("ssh-socks-proxy.yaml", include_str!("../../resources/templates/ssh-socks-proxy.yaml")),
+    ("ssh-tunnel-local.yaml", include_str!("../../resources/templates/ssh-tunnel-local.yaml")),
+    ("ssh-tunnel-remote.yaml", include_str!("../../resources/templates/ssh-tunnel-remote.yaml")),
+];
+
+/// The exact command a template runs with `arguments`, defaults filling the rest
+pub fn fill(template: &Workflow, arguments: HashMap<String, String>, shell: Shell) -> Result<String, WorkflowError> {
+    WorkflowExecutor::new(shell)
+        .prepare_execution(template, arguments)
+        .map(|execution| execution.resolved_command)
+}
@@ -0,0 +1,87 @@
+//! Screens code for obviously destructive operations before NeoTerm offers
+//! to run it. This is a tripwire for the blatant cases, such as wiping a
+//! home directory or formatting a disk. It is not a sandbox.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Safe,
+    /// Why the code looks destructive
+    Destructive(&'static str),
+}
+
+impl Verdict {
+    pub fn is_safe(&self) -> bool {
+        matches!(self, Verdict::Safe)
+    }
+}
+
+/// Roots and home directories, as they appear in shell, Python and JS code
+const BROAD_PATH: &str = r#"(?:/|~|\$HOME|/\*|~/\*|\$HOME/\*|os\.path\.expanduser\(\s*['"]~['"]\s*\)|os\.homedir\(\)|Path\.home\(\))"#;
+
+fn rules() -> &'static [(Regex, &'static str)] {
+    static RULES: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        let broad = |pattern: &str| pattern.replace("{BROAD}", BROAD_PATH);
+        [
+            (broad(r#"rm\s+(?:-[a-zA-Z]*[rR][a-zA-Z]*\s+|--recursive\s+)+(?:-\S+\s+)*['"]?{BROAD}['"]?(?:\s|$|;|'|")"#), "recursively deletes / or the home directory"),
+            (broad(r#"(?:shutil\.rmtree|rmtree)\(\s*['"]?{BROAD}['"]?\s*[,)]"#), "recursively deletes / or the home directory"),
+            (broad(r#"(?:fs\.)?(?:rmSync|rm|rmdirSync)\(\s*['"]?{BROAD}['"]?\s*,[^)]*recursive\s*:\s*true"#), "recursively deletes / or the home directory"),
+            (broad(r#"remove_dir_all\(\s*"?{BROAD}"?\s*\)"#), "recursively deletes / or the home directory"),
+            (r"\bmkfs(?:\.\w+)?\s".to_string(), "formats a filesystem"),
+            (r"\bdd\b[^\n]*\bof=/dev/(?:sd|nvme|hd|disk|mmcblk)".to_string(), "overwrites a disk device"),
+            (r#">\s*/dev/(?:sd|nvme|hd|disk|mmcblk)"#.to_string(), "overwrites a disk device"),
+            (r":\(\)\s*\{\s*:\s*\|\s*:\s*&\s*\}\s*;\s*:".to_string(), "is a fork bomb"),
+            (r"(?i)\b(?:drop\s+(?:database|schema)|truncate\s+table)\b".to_string(), "drops database contents"),
+            (r"chmod\s+-R\s+0?777\s+/(?:\s|$)".to_string(), "opens permissions on the whole filesystem"),
+            (r"\b(?:shutdown|reboot|halt|poweroff)\b(?:\s+-\w+)*(?:\s+now)?\s*(?:$|;|\n)".to_string(), "shuts down or reboots the machine"),
+        ]
+        .into_iter()
+        .map(|(pattern, reason)| (Regex::new(&pattern).unwrap(), reason))
+        .collect()
+    })
+}
+
+pub fn classify(code: &str) -> Verdict {
+    rules()
+        .iter()
+        .find(|(pattern, _)| pattern.is_match(code))
+        .map_or(Verdict::Safe, |(_, reason)| Verdict::Destructive(reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_destructive_snippets() {
+        for code in [
+            "import shutil\nshutil.rmtree(os.path.expanduser('~'))",
+            "subprocess.run('rm -rf / --no-preserve-root', shell=True)",
+            "require('fs').rmSync(os.homedir(), { recursive: true, force: true })",
+            "std::fs::remove_dir_all(\"/\").unwrap();",
+            "os.system('mkfs.ext4 /dev/sda1')",
+            "dd if=/dev/zero of=/dev/sda bs=1M",
+            ":(){ :|:& };:",
+            "cur.execute('DROP DATABASE prod')",
+        ] {
+            assert!(!classify(code).is_safe(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_everyday_snippets_are_safe() {
+        for code in [
+            "print(sum(range(10)))",
+            "import shutil\nshutil.rmtree('build')",
+            "fs.rmSync('./dist', { recursive: true })",
+            "rm -rf ./target",
+            "console.log('shutdown sequence complete:', status)",
+            "std::fs::remove_dir_all(tmp.path())?;",
+        ] {
+            assert_eq!(classify(code), Verdict::Safe, "{}", code);
+        }
+    }
+}
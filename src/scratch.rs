@@ -0,0 +1,243 @@
+//! Running code snippets from assistant replies. Each snippet is written to
+//! its own file in a scratch directory and run with its language's
+//! interpreter; old files are cleared out according to the retention setting.
+
+use crate::config::ScratchPreferences;
+use crate::languages::{Language, LanguageManager};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ScratchError {
+    #[error("IO error: {0}")]
+    IoError(String),
+}
+
+/// A fenced code block from a markdown reply
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    /// The fence's info string, e.g. `python`
+    pub tag: String,
+    pub code: String,
+}
+
+/// Fenced code blocks in `markdown`, in order. An unterminated fence runs
+/// to the end, as it does while a reply is still streaming.
+pub fn extract_snippets(markdown: &str) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    let mut open: Option<(String, String, Vec<&str>)> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        match open.take() {
+            None => {
+                if let Some(fence) = fence_marker(trimmed) {
+                    let tag = trimmed[fence.len()..].split_whitespace().next().unwrap_or("").to_string();
+                    open = Some((fence, tag, Vec::new()));
+                }
+            }
+            Some((fence, tag, lines)) if trimmed.starts_with(&fence) && trimmed.trim_end() == fence => {
+                snippets.push(Snippet { tag, code: lines.join("\n") });
+            }
+            Some((fence, tag, mut lines)) => {
+                lines.push(line);
+                open = Some((fence, tag, lines));
+            }
+        }
+    }
+    if let Some((_, tag, lines)) = open {
+        snippets.push(Snippet { tag, code: lines.join("\n") });
+    }
+    snippets
+}
+
+fn fence_marker(line: &str) -> Option<String> {
+    let ch = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.chars().take_while(|c| *c == ch).count();
+    (len >= 3).then(|| ch.to_string().repeat(len))
+}
+
+/// The language to offer a run button for, if any: the snippet must be in
+/// a language the preferences allow, and pass the safety check
+pub fn offer(snippet: &Snippet, languages: &LanguageManager, prefs: &ScratchPreferences) -> Option<&'static Language> {
+    let language = languages.by_tag(&snippet.tag)?;
+    if !prefs.run_languages.iter().any(|id| id == language.id) {
+        return None;
+    }
+    crate::safety::classify(&snippet.code).is_safe().then_some(language)
+}
+
+/// Snippets of a reply that get a run button, with their index among all snippets
+pub fn offers<'a>(
+    markdown: &str,
+    languages: &LanguageManager,
+    prefs: &ScratchPreferences,
+) -> Vec<(usize, &'static Language)> {
+    extract_snippets(markdown)
+        .iter()
+        .enumerate()
+        .filter_map(|(i, snippet)| offer(snippet, languages, prefs).map(|language| (i, language)))
+        .collect()
+}
+
+/// Directory holding snippet files
+#[derive(Debug, Clone)]
+pub struct ScratchDir {
+    dir: PathBuf,
+    retention: Duration,
+}
+
+impl ScratchDir {
+    pub fn new(prefs: &ScratchPreferences) -> Result<Self, ScratchError> {
+        let dir = crate::config::ConfigPaths::resolve()
+            .map(|paths| paths.scratch_dir())
+            .map_err(|e| ScratchError::IoError(e.to_string()))?;
+        Ok(Self::with_dir(dir, Duration::from_secs(prefs.retention_hours * 60 * 60)))
+    }
+
+    pub fn with_dir(dir: PathBuf, retention: Duration) -> Self {
+        Self { dir, retention }
+    }
+
+    /// Save `code` to a fresh file named for its language
+    pub fn write(&self, language: &Language, code: &str) -> Result<PathBuf, ScratchError> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| ScratchError::IoError(e.to_string()))?;
+        let path = self.dir.join(format!("snippet-{}.{}", uuid::Uuid::new_v4().simple(), language.extension));
+        let mut content = code.to_string();
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        std::fs::write(&path, content).map_err(|e| ScratchError::IoError(e.to_string()))?;
+        Ok(path)
+    }
+
+    /// Delete snippet files older than the retention period; returns how many went
+    pub fn clean(&self, now: SystemTime) -> usize {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return 0;
+        };
+        entries
+            .filter_map(Result::ok)
+            .filter(|entry| is_expired(&entry.path(), now, self.retention))
+            .filter(|entry| std::fs::remove_file(entry.path()).is_ok())
+            .count()
+    }
+}
+
+fn is_expired(path: &Path, now: SystemTime, retention: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| now.duration_since(modified).ok())
+        .is_some_and(|age| age >= retention)
+}
+
+/// Fence tags of snippets meant for the shell itself, which run as typed
+/// rather than through a scratch file
+const SHELL_TAGS: &[&str] = &["bash", "sh", "zsh", "fish", "shell"];
+
+pub fn is_shell_tag(tag: &str) -> bool {
+    let tag = tag.split_whitespace().next().unwrap_or_default();
+    SHELL_TAGS.iter().any(|shell| tag.eq_ignore_ascii_case(shell))
+}
+
+/// The commands of a shell snippet, as they'd be typed at the prompt:
+/// blank lines and comments are dropped, a leading `$ ` prompt is removed,
+/// and continued lines stay with the command they continue
+pub fn shell_commands(code: &str) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+    let mut open = false;
+    for line in code.lines() {
+        if open {
+            let command = commands.last_mut().expect("an open command");
+            command.push('\n');
+            command.push_str(line);
+            open = crate::input::needs_continuation(command);
+            continue;
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let command = trimmed.strip_prefix("$ ").unwrap_or(trimmed).to_string();
+        open = crate::input::needs_continuation(&command);
+        commands.push(command);
+    }
+    commands
+}
+
+/// `argv` as a shell command line
+pub fn command_line(argv: &[String]) -> String {
+    argv.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" ")
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./+=:,@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    const REPLY: &str = "Try this:\n\n```python\nimport shutil\nprint('hi')\n```\n\nOr in node:\n\n```js\nconsole.log(1)\n```\n\n```python\nimport shutil, os\nshutil.rmtree(os.path.expanduser('~'))\n```\n\n````rust\nfn main() {}\n````\n";
+
+    #[test]
+    fn test_extracts_fenced_snippets() {
+        let snippets = extract_snippets(REPLY);
+        assert_eq!(snippets.len(), 4);
+        assert_eq!(snippets[0], Snippet { tag: "python".to_string(), code: "import shutil\nprint('hi')".to_string() });
+        assert_eq!(snippets[3].tag, "rust");
+        assert_eq!(extract_snippets("```sh\nls\n")[0].code, "ls");
+    }
+
+    #[test]
+    fn test_offers_only_allowed_and_safe_snippets() {
+        let languages = LanguageManager::with_path("", HashMap::new());
+        let prefs = ScratchPreferences::default();
+        let offered: Vec<(usize, &str)> = offers(REPLY, &languages, &prefs)
+            .into_iter()
+            .map(|(i, language)| (i, language.id))
+            .collect();
+        // Rust isn't allowed by default and the third snippet deletes the home directory
+        assert_eq!(offered, vec![(0, "python"), (1, "javascript")]);
+    }
+
+    #[test]
+    fn test_write_and_clean_by_retention() {
+        let temp_dir = TempDir::new().unwrap();
+        let scratch = ScratchDir::with_dir(temp_dir.path().join("scratch"), Duration::from_secs(3600));
+        let languages = LanguageManager::with_path("", HashMap::new());
+        let path = scratch.write(languages.by_id("python").unwrap(), "print('hi')").unwrap();
+        assert_eq!(path.extension().unwrap(), "py");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "print('hi')\n");
+
+        assert_eq!(scratch.clean(SystemTime::now()), 0);
+        assert_eq!(scratch.clean(SystemTime::now() + Duration::from_secs(7200)), 1);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_shell_commands_as_typed() {
+        assert!(is_shell_tag("Bash"));
+        assert!(is_shell_tag("sh title=setup"));
+        assert!(!is_shell_tag("python"));
+
+        let code = "# list first\n$ ls -la\n\necho 'one\ntwo'\ncargo build \\\n  --release\n";
+        assert_eq!(shell_commands(code), vec!["ls -la", "echo 'one\ntwo'", "cargo build \\\n  --release"]);
+        assert!(shell_commands("# nothing\n").is_empty());
+    }
+
+    #[test]
+    fn test_command_line_quotes_arguments() {
+        let argv = vec!["python3".to_string(), "/tmp/my dir/it's.py".to_string()];
+        assert_eq!(command_line(&argv), r"python3 '/tmp/my dir/it'\''s.py'");
+    }
+}
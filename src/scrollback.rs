@@ -0,0 +1,141 @@
+//! Bounding how much of a command's output stays in memory.
+//!
+//! Once a block holds more than `limit` lines, the oldest ones are dropped
+//! from memory so rendering stays fast. Before the first drop the whole
+//! output so far is written to a file in the temp directory, and every later
+//! chunk is appended to it, so the file always holds the complete output and
+//! "Show full output" can simply open it.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scrollback {
+    /// Lines kept in memory; 0 keeps everything
+    pub limit: usize,
+    /// Complete lines currently in memory
+    lines: usize,
+    /// Lines dropped from memory
+    spilled_lines: usize,
+    /// Holds the complete output once anything has been dropped
+    spill_path: Option<PathBuf>,
+    /// Why the spill file stopped being written; the lines after it are lost
+    spill_error: Option<String>,
+}
+
+impl Scrollback {
+    pub fn new(limit: usize) -> Self {
+        Self { limit, ..Self::default() }
+    }
+
+    /// Account for the text appended to `output` from byte `start` on, and
+    /// return how many bytes the caller should drop from the front of `output`
+    pub fn appended(&mut self, block_id: Uuid, output: &str, start: usize) -> usize {
+        let text = &output[start..];
+        self.lines += text.matches('\n').count();
+        if self.spill_path.is_some() {
+            self.write(text, true);
+        }
+        if self.limit == 0 || self.lines <= self.limit {
+            return 0;
+        }
+
+        let excess = self.lines - self.limit;
+        let Some(cut) = output.match_indices('\n').nth(excess - 1).map(|(i, _)| i + 1) else {
+            return 0;
+        };
+        if self.spill_path.is_none() {
+            self.spill_path = Some(std::env::temp_dir().join(format!("neoterm-output-{}.log", block_id)));
+            self.write(output, false);
+        }
+        self.lines -= excess;
+        self.spilled_lines += excess;
+        cut
+    }
+
+    fn write(&mut self, text: &str, append: bool) {
+        let Some(path) = &self.spill_path else { return };
+        if self.spill_error.is_some() {
+            return;
+        }
+        let result = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .and_then(|mut file| file.write_all(text.as_bytes()));
+        if let Err(e) = result {
+            self.spill_error = Some(e.to_string());
+        }
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.spilled_lines > 0
+    }
+
+    /// Complete lines printed so far, including those no longer in memory
+    pub fn total_lines(&self) -> usize {
+        self.spilled_lines + self.lines
+    }
+
+    /// Complete lines still in memory
+    pub fn shown_lines(&self) -> usize {
+        self.lines
+    }
+
+    /// The file holding the complete output, if any was dropped
+    pub fn full_output(&self) -> Result<&PathBuf, String> {
+        match (&self.spill_path, &self.spill_error) {
+            (_, Some(e)) => Err(format!("The full output could not be saved: {}", e)),
+            (Some(path), None) => Ok(path),
+            (None, None) => Err("All of the output is already shown".to_string()),
+        }
+    }
+
+    /// Delete the spill file, once its block is gone
+    pub fn discard(&self) {
+        if let Some(path) = &self.spill_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_last_lines_and_spills_everything() {
+        let id = Uuid::new_v4();
+        let mut scrollback = Scrollback::new(3);
+        let mut output = String::new();
+        for text in ["1\n2\n", "3\n", "4\n5\n6", "\n7\n"] {
+            let start = output.len();
+            output.push_str(text);
+            let cut = scrollback.appended(id, &output, start);
+            output.drain(..cut);
+        }
+
+        assert_eq!(output, "5\n6\n7\n");
+        assert!(scrollback.is_truncated());
+        assert_eq!(scrollback.total_lines(), 7);
+        assert_eq!(scrollback.shown_lines(), 3);
+        let path = scrollback.full_output().unwrap().clone();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1\n2\n3\n4\n5\n6\n7\n");
+
+        scrollback.discard();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_unlimited_keeps_everything() {
+        let mut scrollback = Scrollback::new(0);
+        let output = "a\n".repeat(1000);
+        assert_eq!(scrollback.appended(Uuid::new_v4(), &output, 0), 0);
+        assert!(!scrollback.is_truncated());
+        assert!(scrollback.full_output().is_err());
+    }
+}
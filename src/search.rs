@@ -0,0 +1,548 @@
+//! Palette search across everything kept between commands: history, block
+//! titles and output, conversation messages and workflow names. Typing `?`
+//! in the palette switches to it. Block output is found through a small
+//! inverted index that grows as blocks finish, appended to a file next to
+//! the saved sessions; blocks run in incognito never enter it.
+
+use chrono::{DateTime, Utc};
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Typed first in the palette query to search instead of listing actions
+pub const SEARCH_PREFIX: char = '?';
+/// Oldest blocks drop out of the index beyond this many
+pub const MAX_INDEXED_BLOCKS: usize = 2_000;
+/// Distinct terms kept per block; long output is indexed by its first ones
+pub const MAX_TERMS_PER_BLOCK: usize = 500;
+const MAX_TERM_CHARS: usize = 40;
+const MAX_RESULTS: usize = 50;
+/// Score of a block whose output contains every query word
+const FULL_TERM_MATCH_SCORE: f64 = 80.0;
+/// Added to a match made right now, halving every `RECENCY_HALF_LIFE_DAYS`
+const RECENCY_WEIGHT: f64 = 40.0;
+const RECENCY_HALF_LIFE_DAYS: f64 = 14.0;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SearchError {
+    #[error("IO error: {0}")]
+    IoError(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchSource {
+    History,
+    Block,
+    Conversation,
+    Workflow,
+}
+
+impl SearchSource {
+    pub fn badge(&self) -> &'static str {
+        match self {
+            SearchSource::History => "history",
+            SearchSource::Block => "block",
+            SearchSource::Conversation => "chat",
+            SearchSource::Workflow => "workflow",
+        }
+    }
+}
+
+/// Where choosing a result leads
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchTarget {
+    /// Put back in the input to run again
+    Command(String),
+    Block(Uuid),
+    Message(Uuid),
+    Workflow(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub source: SearchSource,
+    pub title: String,
+    /// Line of a message that matched, shown under the title
+    pub detail: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub score: f64,
+    pub target: SearchTarget,
+}
+
+/// A conversation message, on whichever branch it was written
+#[derive(Debug, Clone)]
+pub struct MessageEntry {
+    pub id: Uuid,
+    pub content: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Everything the palette searches, gathered when it opens
+#[derive(Debug, Clone, Default)]
+pub struct SearchCorpus {
+    /// Oldest first, as entered
+    pub history: Vec<String>,
+    pub blocks: Arc<BlockIndex>,
+    pub messages: Vec<MessageEntry>,
+    pub workflows: Vec<(String, Option<DateTime<Utc>>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedBlock {
+    pub title: String,
+    pub timestamp: DateTime<Utc>,
+    /// Kept so the block's postings can be dropped again
+    terms: Vec<String>,
+}
+
+/// One line of the index file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexRecord {
+    id: Uuid,
+    #[serde(flatten)]
+    block: IndexedBlock,
+}
+
+/// Finished blocks by the words of their title and output
+#[derive(Debug, Clone, Default)]
+pub struct BlockIndex {
+    blocks: HashMap<Uuid, IndexedBlock>,
+    /// Oldest first, for eviction
+    order: VecDeque<Uuid>,
+    postings: BTreeMap<String, BTreeSet<Uuid>>,
+    store: Option<PathBuf>,
+}
+
+impl BlockIndex {
+    pub fn new(store: Option<PathBuf>) -> Self {
+        Self { store, ..Self::default() }
+    }
+
+    /// Replay the index file. A missing or damaged file gives an empty
+    /// index; it is only ever a shortcut to blocks saved elsewhere.
+    pub fn open(store: Option<PathBuf>) -> Self {
+        let mut index = Self::new(None);
+        let contents = store.as_ref().and_then(|path| std::fs::read_to_string(path).ok()).unwrap_or_default();
+        let mut records = 0;
+        for line in contents.lines() {
+            if let Ok(record) = serde_json::from_str::<IndexRecord>(line) {
+                index.insert(record.id, record.block);
+                records += 1;
+            }
+        }
+        index.store = store;
+        // Re-runs and evictions leave stale lines behind; rewrite once they dominate
+        if records > 2 * index.len().max(MAX_INDEXED_BLOCKS / 4) {
+            let _ = index.compact();
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&IndexedBlock> {
+        self.blocks.get(&id)
+    }
+
+    /// Index a finished block and append it to the store. Indexing the same
+    /// block again replaces its earlier terms.
+    pub fn add(&mut self, id: Uuid, title: &str, output: &str, timestamp: DateTime<Utc>) -> Result<(), SearchError> {
+        let mut terms = Vec::new();
+        let mut seen = HashSet::new();
+        for term in tokenize(title).chain(tokenize(output)) {
+            if terms.len() == MAX_TERMS_PER_BLOCK {
+                break;
+            }
+            if seen.insert(term.clone()) {
+                terms.push(term);
+            }
+        }
+        let block = IndexedBlock { title: title.to_string(), timestamp, terms };
+        let record = IndexRecord { id, block: block.clone() };
+        self.insert(id, block);
+
+        let Some(path) = &self.store else { return Ok(()) };
+        let line = serde_json::to_string(&record).map_err(|e| SearchError::IoError(e.to_string()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| SearchError::IoError(e.to_string()))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| SearchError::IoError(e.to_string()))?;
+        writeln!(file, "{}", line).map_err(|e| SearchError::IoError(e.to_string()))
+    }
+
+    /// Forget every block, in memory and on disk
+    pub fn clear(&mut self) -> Result<(), SearchError> {
+        self.blocks.clear();
+        self.order.clear();
+        self.postings.clear();
+        match &self.store {
+            Some(path) => match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(SearchError::IoError(e.to_string())),
+                _ => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Blocks containing a word starting with any of `words`, with how many
+    /// of the words they contain
+    pub fn lookup(&self, words: &[String]) -> Vec<(Uuid, usize)> {
+        let mut counts: HashMap<Uuid, usize> = HashMap::new();
+        for word in words {
+            let mut matched = HashSet::new();
+            for (_, ids) in self.postings.range(word.clone()..).take_while(|(term, _)| term.starts_with(word.as_str())) {
+                matched.extend(ids.iter().copied());
+            }
+            for id in matched {
+                *counts.entry(id).or_default() += 1;
+            }
+        }
+        counts.into_iter().collect()
+    }
+
+    fn insert(&mut self, id: Uuid, block: IndexedBlock) {
+        self.remove(id);
+        for term in &block.terms {
+            self.postings.entry(term.clone()).or_default().insert(id);
+        }
+        self.blocks.insert(id, block);
+        self.order.push_back(id);
+        while self.order.len() > MAX_INDEXED_BLOCKS {
+            if let Some(oldest) = self.order.front().copied() {
+                self.remove(oldest);
+            }
+        }
+    }
+
+    fn remove(&mut self, id: Uuid) {
+        let Some(block) = self.blocks.remove(&id) else { return };
+        self.order.retain(|other| *other != id);
+        for term in &block.terms {
+            if let Some(ids) = self.postings.get_mut(term) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+    }
+
+    /// Rewrite the store with only the blocks still indexed
+    fn compact(&self) -> Result<(), SearchError> {
+        let Some(path) = &self.store else { return Ok(()) };
+        let mut contents = String::new();
+        for id in &self.order {
+            let record = IndexRecord { id: *id, block: self.blocks[id].clone() };
+            contents.push_str(&serde_json::to_string(&record).map_err(|e| SearchError::IoError(e.to_string()))?);
+            contents.push('\n');
+        }
+        std::fs::write(path, contents).map_err(|e| SearchError::IoError(e.to_string()))
+    }
+}
+
+/// Lowercased words of two or more characters
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| (2..=MAX_TERM_CHARS).contains(&word.chars().count()))
+        .map(str::to_lowercase)
+}
+
+/// Query words for the index and substring matching
+fn query_words(query: &str) -> Vec<String> {
+    query.split_whitespace().flat_map(tokenize).collect()
+}
+
+/// Bonus for how recently something happened
+fn recency(timestamp: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let age_days = (now - timestamp).num_seconds().max(0) as f64 / 86_400.0;
+    RECENCY_WEIGHT * 0.5f64.powf(age_days / RECENCY_HALF_LIFE_DAYS)
+}
+
+/// Search every source at once, best matches first
+pub fn search(corpus: &SearchCorpus, query: &str, now: DateTime<Utc>) -> Vec<SearchHit> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut hits = std::thread::scope(|scope| {
+        let sources = [
+            scope.spawn(|| search_history(&corpus.history, query)),
+            scope.spawn(|| search_blocks(&corpus.blocks, query, now)),
+            scope.spawn(|| search_messages(&corpus.messages, query, now)),
+            scope.spawn(|| search_workflows(&corpus.workflows, query, now)),
+        ];
+        sources
+            .into_iter()
+            .flat_map(|source| source.join().unwrap_or_default())
+            .collect::<Vec<_>>()
+    });
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(MAX_RESULTS);
+    hits
+}
+
+/// History has no timestamps; position stands in for age, and repeated
+/// commands are listed once
+fn search_history(history: &[String], query: &str) -> Vec<SearchHit> {
+    let matcher = SkimMatcherV2::default();
+    let mut seen = HashSet::new();
+    history
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|&(_, command)| seen.insert(command.as_str()))
+        .filter_map(|(i, command)| {
+            let score = matcher.fuzzy_match(command, query)? as f64;
+            Some(SearchHit {
+                source: SearchSource::History,
+                title: command.clone(),
+                detail: None,
+                timestamp: None,
+                score: score + RECENCY_WEIGHT * (i + 1) as f64 / history.len() as f64,
+                target: SearchTarget::Command(command.clone()),
+            })
+        })
+        .collect()
+}
+
+fn search_blocks(index: &BlockIndex, query: &str, now: DateTime<Utc>) -> Vec<SearchHit> {
+    let matcher = SkimMatcherV2::default();
+    let words = query_words(query);
+    let mut scores: HashMap<Uuid, f64> = HashMap::new();
+    if !words.is_empty() {
+        for (id, matched) in index.lookup(&words) {
+            scores.insert(id, FULL_TERM_MATCH_SCORE * matched as f64 / words.len() as f64);
+        }
+    }
+    for (id, block) in &index.blocks {
+        if let Some(score) = matcher.fuzzy_match(&block.title, query) {
+            let best = scores.entry(*id).or_default();
+            *best = best.max(score as f64);
+        }
+    }
+    scores
+        .into_iter()
+        .map(|(id, score)| {
+            let block = &index.blocks[&id];
+            SearchHit {
+                source: SearchSource::Block,
+                title: block.title.clone(),
+                detail: None,
+                timestamp: Some(block.timestamp),
+                score: score + recency(block.timestamp, now),
+                target: SearchTarget::Block(id),
+            }
+        })
+        .collect()
+}
+
+/// Messages are long, so every query word has to appear; the line holding
+/// the first one is fuzzy-scored and shown
+fn search_messages(messages: &[MessageEntry], query: &str, now: DateTime<Utc>) -> Vec<SearchHit> {
+    let matcher = SkimMatcherV2::default();
+    let words = query_words(query);
+    let Some(first) = words.first() else { return Vec::new() };
+    messages
+        .iter()
+        .filter_map(|message| {
+            let content = message.content.to_lowercase();
+            if !words.iter().all(|word| content.contains(word.as_str())) {
+                return None;
+            }
+            let line = message.content.lines().find(|line| line.to_lowercase().contains(first.as_str()))?;
+            let score = matcher.fuzzy_match(line, query).unwrap_or(0) as f64;
+            Some(SearchHit {
+                source: SearchSource::Conversation,
+                title: message.content.lines().next().unwrap_or_default().to_string(),
+                detail: Some(line.trim().to_string()),
+                timestamp: Some(message.timestamp),
+                score: score + recency(message.timestamp, now),
+                target: SearchTarget::Message(message.id),
+            })
+        })
+        .collect()
+}
+
+fn search_workflows(workflows: &[(String, Option<DateTime<Utc>>)], query: &str, now: DateTime<Utc>) -> Vec<SearchHit> {
+    let matcher = SkimMatcherV2::default();
+    workflows
+        .iter()
+        .filter_map(|(name, last_used)| {
+            let score = matcher.fuzzy_match(name, query)? as f64;
+            Some(SearchHit {
+                source: SearchSource::Workflow,
+                title: name.clone(),
+                detail: None,
+                timestamp: *last_used,
+                score: score + last_used.map(|time| recency(time, now)).unwrap_or(0.0),
+                target: SearchTarget::Workflow(name.clone()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn corpus(now: DateTime<Utc>) -> (SearchCorpus, Uuid, Uuid) {
+        let mut index = BlockIndex::new(None);
+        let tls_block = Uuid::new_v4();
+        index
+            .add(
+                tls_block,
+                "curl https://internal.example",
+                "curl: (60) SSL certificate problem: unable to get local issuer certificate",
+                now - Duration::days(30),
+            )
+            .unwrap();
+        index.add(Uuid::new_v4(), "ls", "Cargo.toml src", now).unwrap();
+
+        let message = Uuid::new_v4();
+        let corpus = SearchCorpus {
+            history: vec!["cargo build".to_string(), "openssl s_client -connect host:443".to_string()],
+            blocks: Arc::new(index),
+            messages: vec![MessageEntry {
+                id: message,
+                content: "Why does curl fail?\nThe issuer certificate is missing from the bundle.".to_string(),
+                timestamp: now - Duration::days(2),
+            }],
+            workflows: vec![("docker-logs".to_string(), None)],
+        };
+        (corpus, tls_block, message)
+    }
+
+    #[test]
+    fn test_finds_matches_in_every_source() {
+        let now = Utc::now();
+        let (corpus, tls_block, message) = corpus(now);
+
+        let hits = search(&corpus, "issuer certificate", now);
+        let targets: Vec<_> = hits.iter().map(|hit| hit.target.clone()).collect();
+        assert!(targets.contains(&SearchTarget::Block(tls_block)));
+        assert!(targets.contains(&SearchTarget::Message(message)));
+        let chat = hits.iter().find(|hit| hit.source == SearchSource::Conversation).unwrap();
+        assert_eq!(chat.detail.as_deref(), Some("The issuer certificate is missing from the bundle."));
+
+        let hits = search(&corpus, "openssl", now);
+        assert_eq!(hits[0].target, SearchTarget::Command("openssl s_client -connect host:443".to_string()));
+
+        let hits = search(&corpus, "dockerlogs", now);
+        assert_eq!(hits[0].target, SearchTarget::Workflow("docker-logs".to_string()));
+        assert!(search(&corpus, "   ", now).is_empty());
+    }
+
+    #[test]
+    fn test_recent_matches_rank_higher() {
+        let now = Utc::now();
+        let mut index = BlockIndex::new(None);
+        let old = Uuid::new_v4();
+        let new = Uuid::new_v4();
+        index.add(old, "make test", "handshake failed", now - Duration::days(60)).unwrap();
+        index.add(new, "make test", "handshake failed", now - Duration::hours(1)).unwrap();
+        let corpus = SearchCorpus { blocks: Arc::new(index), ..SearchCorpus::default() };
+
+        let hits = search(&corpus, "handshake", now);
+        assert_eq!(hits.iter().map(|hit| hit.target.clone()).collect::<Vec<_>>(), vec![
+            SearchTarget::Block(new),
+            SearchTarget::Block(old),
+        ]);
+    }
+
+    #[test]
+    fn test_index_is_capped_and_reindexing_replaces_terms() {
+        let now = Utc::now();
+        let mut index = BlockIndex::new(None);
+        let first = Uuid::new_v4();
+        index.add(first, "echo", "alpha", now).unwrap();
+        index.add(first, "echo", "beta", now).unwrap();
+        assert!(index.lookup(&["alpha".to_string()]).is_empty());
+        assert_eq!(index.lookup(&["bet".to_string()]), vec![(first, 1)]);
+
+        for _ in 0..MAX_INDEXED_BLOCKS {
+            index.add(Uuid::new_v4(), "true", "", now).unwrap();
+        }
+        assert_eq!(index.len(), MAX_INDEXED_BLOCKS);
+        assert!(index.get(first).is_none());
+        assert!(index.lookup(&["beta".to_string()]).is_empty());
+
+        let long_output: String = (0..MAX_TERMS_PER_BLOCK * 2).map(|n| format!("word{} ", n)).collect();
+        let long = Uuid::new_v4();
+        index.add(long, "seq", &long_output, now).unwrap();
+        assert_eq!(index.get(long).unwrap().terms.len(), MAX_TERMS_PER_BLOCK);
+    }
+
+    #[test]
+    fn test_index_survives_reopening_and_clear_removes_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("sessions").join("search-index.jsonl");
+        let now = Utc::now();
+        let id = Uuid::new_v4();
+
+        let mut index = BlockIndex::open(Some(store.clone()));
+        index.add(id, "kubectl get pods", "CrashLoopBackOff", now).unwrap();
+
+        let mut reopened = BlockIndex::open(Some(store.clone()));
+        assert_eq!(reopened.get(id).map(|block| block.title.as_str()), Some("kubectl get pods"));
+        assert_eq!(reopened.lookup(&["crashloop".to_string()]), vec![(id, 1)]);
+
+        reopened.clear().unwrap();
+        assert!(reopened.is_empty());
+        assert!(!store.exists());
+    }
+
+    /// Latency with stores of a typical size. Run with
+    /// `cargo test --release search::tests::bench -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_typical_stores_under_50ms() {
+        let now = Utc::now();
+        let mut index = BlockIndex::new(None);
+        for n in 0..MAX_INDEXED_BLOCKS {
+            let output: String = (0..200).map(|word| format!("token{}x{} ", word, n % 97)).collect();
+            index.add(Uuid::new_v4(), &format!("make target-{}", n), &output, now - Duration::minutes(n as i64)).unwrap();
+        }
+        let corpus = SearchCorpus {
+            history: (0..10_000).map(|n| format!("git commit -m 'change {}'", n)).collect(),
+            blocks: Arc::new(index),
+            messages: (0..2_000)
+                .map(|n| MessageEntry {
+                    id: Uuid::new_v4(),
+                    content: format!("Question {} about the TLS handshake\n{}", n, "details ".repeat(40)),
+                    timestamp: now - Duration::hours(n),
+                })
+                .collect(),
+            workflows: (0..200).map(|n| (format!("workflow-{}", n), None)).collect(),
+        };
+
+        let mut timings: Vec<_> = ["tls", "token42", "commit 99", "make target"]
+            .iter()
+            .cycle()
+            .take(20)
+            .map(|query| {
+                let started = std::time::Instant::now();
+                search(&corpus, query, now);
+                started.elapsed()
+            })
+            .collect();
+        timings.sort();
+        let median = timings[timings.len() / 2];
+        println!("median {:?}, worst {:?}", median, timings[timings.len() - 1]);
+        assert!(median < std::time::Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,382 @@
+//! The block list saved when NeoTerm quits and restored on the next start.
+//!
+//! The file is versioned and each block is read on its own, so a session
+//! written by a newer build loads whatever blocks this build understands
+//! instead of failing as a whole. Files from before the version field (a
+//! bare array of blocks) still load.
+//!
+//! `blocks` are the active tab's focused pane's. The split layout, the other
+//! panes and the other tabs are saved next to them; builds without panes or
+//! tabs ignore those and restore just the blocks.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use crate::block::{Block, BlockContent, BlockStatus};
+use crate::block_export::{ExportError, ExportedBlock};
+use crate::panes::{PaneId, PaneState, PaneTree, Panes};
+
+/// Bumped when the file layout changes incompatibly
+pub const SESSION_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionFile {
+    version: u32,
+    saved_at: DateTime<Utc>,
+    /// Kept as raw values so one unreadable block doesn't lose the others
+    blocks: Vec<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    panes: Option<StoredPanes>,
+    /// Every tab in order; the active one's blocks and panes are the ones above
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tabs: Vec<StoredTab>,
+    #[serde(default)]
+    active_tab: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredPanes {
+    layout: PaneTree,
+    focused: PaneId,
+    /// Every pane but the focused one
+    others: Vec<StoredPane>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredPane {
+    id: PaneId,
+    cwd: PathBuf,
+    blocks: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredTab {
+    name: Option<String>,
+    env_profile: Option<String>,
+    conversation: Option<Uuid>,
+    cwd: PathBuf,
+    #[serde(default)]
+    blocks: Vec<serde_json::Value>,
+    #[serde(default)]
+    panes: Option<StoredPanes>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum StoredSession {
+    Versioned(SessionFile),
+    Unversioned(Vec<serde_json::Value>),
+}
+
+/// A tab to save
+#[derive(Debug, Clone)]
+pub struct SavedTab<'a> {
+    pub name: Option<String>,
+    pub env_profile: Option<String>,
+    /// Id of its agent conversation in the conversation store
+    pub conversation: Option<Uuid>,
+    /// The focused pane's directory and blocks
+    pub cwd: PathBuf,
+    pub blocks: &'a [Block],
+    pub panes: &'a Panes<PaneState>,
+}
+
+impl<'a> SavedTab<'a> {
+    pub fn new(blocks: &'a [Block], panes: &'a Panes<PaneState>) -> Self {
+        Self { name: None, env_profile: None, conversation: None, cwd: PathBuf::new(), blocks, panes }
+    }
+}
+
+/// What a restore found
+#[derive(Debug, Default)]
+pub struct RestoredSession {
+    /// The active tab's focused pane's
+    pub blocks: Vec<Block>,
+    /// Blocks this build couldn't read
+    pub skipped: usize,
+    /// The active tab's split layout, with its other panes' blocks parked in it
+    pub panes: Panes<PaneState>,
+    /// Every tab in order, when there were tabs; the active one's blocks and
+    /// panes are the ones above
+    pub tabs: Vec<RestoredTab>,
+    pub active_tab: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct RestoredTab {
+    pub name: Option<String>,
+    pub env_profile: Option<String>,
+    pub conversation: Option<Uuid>,
+    pub cwd: PathBuf,
+    pub blocks: Vec<Block>,
+    pub panes: Panes<PaneState>,
+}
+
+/// Whether a block is worth keeping: live views (find-and-replace, plugins,
+/// diagnostics, git status, directories) and commands still running don't survive a restart
+fn persists(block: &Block) -> bool {
+    match &block.content {
+        BlockContent::FindReplace(_)
+        | BlockContent::Plugin(_)
+        | BlockContent::Diagnostics(_)
+        | BlockContent::GitStatus(_)
+        | BlockContent::Directory(_)
+        | BlockContent::Preview(_) => false,
+        BlockContent::Command { .. } => block.status() != Some(BlockStatus::Running),
+        _ => true,
+    }
+}
+
+/// The newest `limit` blocks that persist
+fn stored_blocks(blocks: &[Block], limit: usize) -> Result<Vec<serde_json::Value>, ExportError> {
+    let kept: Vec<&Block> = blocks.iter().filter(|block| persists(block)).collect();
+    kept[kept.len().saturating_sub(limit)..]
+        .iter()
+        .map(|block| serde_json::to_value(ExportedBlock::from_block(block)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ExportError::SerializationError(e.to_string()))
+}
+
+/// The layout and the panes besides the focused one, if it's split
+fn stored_panes(panes: &Panes<PaneState>, limit: usize) -> Result<Option<StoredPanes>, ExportError> {
+    if !panes.is_split() {
+        return Ok(None);
+    }
+    let others = panes
+        .parked()
+        .map(|(id, pane)| Ok(StoredPane { id, cwd: pane.cwd.clone(), blocks: stored_blocks(&pane.blocks, limit)? }))
+        .collect::<Result<Vec<_>, ExportError>>()?;
+    Ok(Some(StoredPanes { layout: panes.tree().clone(), focused: panes.focused(), others }))
+}
+
+/// Write `tabs` to `path`, keeping the newest `limit` blocks of each pane
+/// that persist
+pub fn save(tabs: &[SavedTab], active: usize, path: &Path, limit: usize) -> Result<(), ExportError> {
+    let Some(active_tab) = tabs.get(active) else {
+        return Err(ExportError::SerializationError(format!("no tab {} to save", active)));
+    };
+    let stored_tabs = if tabs.len() > 1 {
+        tabs.iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                let (blocks, panes) = if index == active {
+                    (Vec::new(), None)
+                } else {
+                    (stored_blocks(tab.blocks, limit)?, stored_panes(tab.panes, limit)?)
+                };
+                Ok(StoredTab {
+                    name: tab.name.clone(),
+                    env_profile: tab.env_profile.clone(),
+                    conversation: tab.conversation,
+                    cwd: tab.cwd.clone(),
+                    blocks,
+                    panes,
+                })
+            })
+            .collect::<Result<Vec<_>, ExportError>>()?
+    } else {
+        Vec::new()
+    };
+    let file = SessionFile {
+        version: SESSION_VERSION,
+        saved_at: Utc::now(),
+        blocks: stored_blocks(active_tab.blocks, limit)?,
+        panes: stored_panes(active_tab.panes, limit)?,
+        tabs: stored_tabs,
+        active_tab: active,
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|e| ExportError::SerializationError(e.to_string()))?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| ExportError::IoError(e.to_string()))?;
+    }
+    // Written aside and renamed, so quitting mid-write keeps the previous session
+    let partial = path.with_extension("json.partial");
+    std::fs::write(&partial, json).map_err(|e| ExportError::IoError(e.to_string()))?;
+    std::fs::rename(&partial, path).map_err(|e| ExportError::IoError(e.to_string()))
+}
+
+/// Read a saved session. A missing file is an empty session.
+pub fn load(path: &Path) -> Result<RestoredSession, ExportError> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(RestoredSession::default()),
+        Err(e) => return Err(ExportError::IoError(e.to_string())),
+    };
+    let file = match serde_json::from_str(&json).map_err(|e| ExportError::SerializationError(e.to_string()))? {
+        StoredSession::Versioned(file) => file,
+        StoredSession::Unversioned(blocks) => SessionFile {
+            version: 0,
+            saved_at: Utc::now(),
+            blocks,
+            panes: None,
+            tabs: Vec::new(),
+            active_tab: 0,
+        },
+    };
+    let mut skipped = 0;
+    let blocks = read_blocks(file.blocks, &mut skipped);
+    let panes = read_panes(file.panes, &mut skipped);
+    let tabs = file
+        .tabs
+        .into_iter()
+        .map(|tab| RestoredTab {
+            name: tab.name,
+            env_profile: tab.env_profile,
+            conversation: tab.conversation,
+            cwd: tab.cwd,
+            blocks: read_blocks(tab.blocks, &mut skipped),
+            panes: read_panes(tab.panes, &mut skipped),
+        })
+        .collect();
+    Ok(RestoredSession { blocks, skipped, panes, tabs, active_tab: file.active_tab })
+}
+
+fn read_panes(stored: Option<StoredPanes>, skipped: &mut usize) -> Panes<PaneState> {
+    let Some(stored) = stored else { return Panes::default() };
+    let parked: HashMap<PaneId, PaneState> = stored
+        .others
+        .into_iter()
+        .map(|pane| {
+            let blocks = read_blocks(pane.blocks, skipped);
+            (pane.id, PaneState { blocks, cwd: pane.cwd, ..Default::default() })
+        })
+        .collect();
+    Panes::restore(stored.layout, stored.focused, parked)
+}
+
+/// The blocks this build can read, counting the others in `skipped`
+fn read_blocks(values: Vec<serde_json::Value>, skipped: &mut usize) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    for value in values {
+        match serde_json::from_value::<ExportedBlock>(value) {
+            Ok(block) => blocks.push(block.into_block()),
+            Err(_) => *skipped += 1,
+        }
+    }
+    blocks
+}
+
+/// Delete the saved session, for `clear_history_on_exit`
+pub fn discard(path: &Path) -> Result<(), ExportError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(ExportError::IoError(e.to_string())),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn finished(command: &str) -> Block {
+        let mut block = Block::new_command(command.to_string());
+        block.set_output(format!("{} done\n", command), 0);
+        block
+    }
+
+    #[test]
+    fn test_saves_newest_finished_blocks_up_to_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions").join("last.json");
+        let blocks = vec![
+            finished("one"),
+            finished("two"),
+            Block::new_command("still running".to_string()),
+            Block::new_find_replace(PathBuf::from(".")),
+            finished("three"),
+        ];
+
+        save(&[SavedTab::new(&blocks, &Panes::default())], 0, &path, 2).unwrap();
+        let restored = load(&path).unwrap();
+        assert_eq!(restored.skipped, 0);
+        let titles: Vec<_> = restored.blocks.iter().map(Block::title).collect();
+        assert_eq!(titles, vec!["two", "three"]);
+        assert_eq!(restored.blocks[1].id, blocks[4].id);
+        assert_eq!(restored.blocks[1].output_text(), "three done\n");
+    }
+
+    #[test]
+    fn test_unknown_blocks_and_old_files_still_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("last.json");
+        let known = serde_json::to_value(ExportedBlock::from_block(&finished("ls"))).unwrap();
+
+        let newer = serde_json::json!({
+            "version": SESSION_VERSION + 1,
+            "saved_at": Utc::now(),
+            "blocks": [known, { "kind": "hologram", "id": "not even a uuid" }],
+        });
+        std::fs::write(&path, newer.to_string()).unwrap();
+        let restored = load(&path).unwrap();
+        assert_eq!(restored.blocks.len(), 1);
+        assert_eq!(restored.skipped, 1);
+
+        std::fs::write(&path, serde_json::json!([known]).to_string()).unwrap();
+        assert_eq!(load(&path).unwrap().blocks.len(), 1);
+
+        std::fs::write(&path, "{ truncated").unwrap();
+        assert!(load(&path).is_err());
+    }
+
+    #[test]
+    fn test_split_panes_restore_with_their_own_blocks() {
+        use crate::layout::SplitDirection;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("last.json");
+        let mut panes = Panes::default();
+        let mut current = PaneState { blocks: vec![finished("make")], cwd: PathBuf::from("/src"), ..Default::default() };
+        let logs = PaneState { blocks: vec![finished("tail log")], cwd: PathBuf::from("/var/log"), ..Default::default() };
+        let right = panes.split(SplitDirection::Horizontal, &mut current, logs);
+        panes.focus(0, &mut current);
+
+        save(&[SavedTab::new(&current.blocks, &panes)], 0, &path, 10).unwrap();
+        let restored = load(&path).unwrap();
+        assert_eq!(restored.blocks[0].title(), "make");
+        assert_eq!(restored.panes.tree(), panes.tree());
+        assert_eq!(restored.panes.focused(), 0);
+        let other = restored.panes.get(right).unwrap();
+        assert_eq!(other.cwd, PathBuf::from("/var/log"));
+        assert_eq!(other.blocks[0].title(), "tail log");
+    }
+
+    #[test]
+    fn test_every_tab_is_saved_with_its_profile_and_conversation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("last.json");
+        let (build, logs) = ([finished("make")], [finished("tail log")]);
+        let panes = Panes::default();
+        let conversation = Uuid::new_v4();
+        let tabs = [
+            SavedTab { name: Some("logs".to_string()), env_profile: Some("staging".to_string()), cwd: PathBuf::from("/var/log"), ..SavedTab::new(&logs, &panes) },
+            SavedTab { conversation: Some(conversation), ..SavedTab::new(&build, &panes) },
+        ];
+
+        save(&tabs, 1, &path, 10).unwrap();
+        let restored = load(&path).unwrap();
+        assert_eq!(restored.blocks[0].title(), "make");
+        assert_eq!((restored.tabs.len(), restored.active_tab), (2, 1));
+        let parked = &restored.tabs[0];
+        assert_eq!((parked.name.as_deref(), parked.env_profile.as_deref()), (Some("logs"), Some("staging")));
+        assert_eq!(parked.cwd, PathBuf::from("/var/log"));
+        assert_eq!(parked.blocks[0].title(), "tail log");
+        assert_eq!(restored.tabs[1].conversation, Some(conversation));
+        assert!(restored.tabs[1].blocks.is_empty());
+    }
+
+    #[test]
+    fn test_missing_file_is_empty_and_discard_removes_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path: PathBuf = dir.path().join("last.json");
+        assert!(load(&path).unwrap().blocks.is_empty());
+
+        save(&[SavedTab::new(&[finished("ls")], &Panes::default())], 0, &path, 10).unwrap();
+        discard(&path).unwrap();
+        assert!(!path.exists());
+        discard(&path).unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+//! Transcripts of a whole session: every block, in order, as one Markdown
+//! or HTML document. Escape sequences are dropped from command output in
+//! Markdown and turned into colored spans in HTML; assistant replies are
+//! rendered through the Markdown parser. Superseded turns stay collapsed.
+//!
+//! `neoterm export` reads sessions saved by `session`.
+
+use chrono::{DateTime, Local};
+use std::path::{Path, PathBuf};
+use crate::block::{AgentRole, Block, BlockContent};
+use crate::block_export::{self, ExportError};
+use crate::input::EnhancedTextInput;
+use crate::markdown_parser::{escape, MarkdownParser};
+use crate::tee::AnsiStripper;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SessionFormat {
+    #[value(name = "md")]
+    Markdown,
+    Html,
+}
+
+impl SessionFormat {
+    pub const ALL: [SessionFormat; 2] = [SessionFormat::Markdown, SessionFormat::Html];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SessionFormat::Markdown => "md",
+            SessionFormat::Html => "html",
+        }
+    }
+}
+
+impl std::fmt::Display for SessionFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionFormat::Markdown => write!(f, "Markdown"),
+            SessionFormat::Html => write!(f, "HTML"),
+        }
+    }
+}
+
+/// "Export session…" being filled in
+#[derive(Debug, Clone)]
+pub struct SessionExportPrompt {
+    pub format: SessionFormat,
+    pub path: EnhancedTextInput,
+}
+
+impl SessionExportPrompt {
+    pub fn new(dir: &Path, now: DateTime<Local>) -> Self {
+        let format = SessionFormat::Markdown;
+        let mut path = EnhancedTextInput::new();
+        path.set_value(default_path(dir, format, now).to_string_lossy().to_string());
+        Self { format, path }
+    }
+
+    /// Switch format, keeping the file extension in step unless it was changed by hand
+    pub fn set_format(&mut self, format: SessionFormat) {
+        let path = PathBuf::from(self.path.value());
+        if path.extension().and_then(|ext| ext.to_str()) == Some(self.format.extension()) {
+            self.path.set_value(path.with_extension(format.extension()).to_string_lossy().to_string());
+        }
+        self.format = format;
+    }
+
+    pub fn target(&self) -> PathBuf {
+        block_export::expand_home(self.path.value())
+    }
+}
+
+/// `neoterm-session-<timestamp>.<ext>` in `dir`
+pub fn default_path(dir: &Path, format: SessionFormat, now: DateTime<Local>) -> PathBuf {
+    dir.join(format!("neoterm-session-{}.{}", now.format("%Y%m%d-%H%M%S"), format.extension()))
+}
+
+pub fn export(blocks: &[Block], format: SessionFormat) -> String {
+    match format {
+        SessionFormat::Markdown => export_markdown(blocks),
+        SessionFormat::Html => export_html(blocks),
+    }
+}
+
+/// Write a transcript to `path`, creating missing directories
+pub fn export_to(blocks: &[Block], format: SessionFormat, path: &Path) -> Result<(), ExportError> {
+    write(path, export(blocks, format))
+}
+
+pub fn export_markdown(blocks: &[Block]) -> String {
+    let mut markdown = String::from("# NeoTerm session\n");
+    for block in blocks {
+        let body = match &block.content {
+            BlockContent::Separator => "---\n".to_string(),
+            BlockContent::AgentMessage { content, superseded: true, .. }
+            | BlockContent::UserMessage { content, superseded: true, .. } => {
+                format!("<details><summary>Superseded</summary>\n\n{}\n</details>\n", content)
+            }
+            _ => AnsiStripper::new().strip(&block.to_markdown()),
+        };
+        if !body.is_empty() {
+            markdown.push('\n');
+            markdown.push_str(&body);
+        }
+    }
+    markdown
+}
+
+pub fn export_html(blocks: &[Block]) -> String {
+    let parser = MarkdownParser::new();
+    let mut html = String::from(HTML_HEAD);
+    for block in blocks {
+        let body = match &block.content {
+            BlockContent::Command { input, output, exit_code, working_directory, .. } => {
+                let outcome = match exit_code {
+                    Some(0) => ("ok", "exit 0".to_string()),
+                    Some(code) => ("failed", format!("exit {}", code)),
+                    None => ("running", "running".to_string()),
+                };
+                format!(
+                    "<section class=\"block command {}\">\n<pre class=\"input\">$ {}</pre>\n<pre class=\"output\">{}</pre>\n<p class=\"meta\">{} · {}</p>\n</section>\n",
+                    outcome.0,
+                    escape(input),
+                    ansi_to_html(output.as_deref().unwrap_or("")),
+                    escape(working_directory),
+                    outcome.1,
+                )
+            }
+            BlockContent::AgentMessage { content, role, superseded, .. } => {
+                let class = match role {
+                    AgentRole::Assistant => "assistant",
+                    AgentRole::User => "user",
+                    AgentRole::System => "system",
+                };
+                message_html(class, &parser.to_html(content), *superseded)
+            }
+            BlockContent::UserMessage { content, superseded, .. } => message_html("user", &parser.to_html(content), *superseded),
+            BlockContent::Error { message } => format!("<section class=\"block error\"><p>{}</p></section>\n", escape(message)),
+            BlockContent::Separator => "<hr>\n".to_string(),
+            _ => match block.to_markdown() {
+                markdown if markdown.is_empty() => String::new(),
+                markdown => format!("<section class=\"block\">{}</section>\n", parser.to_html(&AnsiStripper::new().strip(&markdown))),
+            },
+        };
+        html.push_str(&body);
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn message_html(class: &str, body: &str, superseded: bool) -> String {
+    if superseded {
+        format!("<details class=\"block message {} superseded\"><summary>Superseded</summary>\n{}</details>\n", class, body)
+    } else {
+        format!("<section class=\"block message {}\">\n{}</section>\n", class, body)
+    }
+}
+
+const HTML_HEAD: &str = "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>NeoTerm session</title>\n<style>\n\
+body { font-family: sans-serif; max-width: 960px; margin: 2em auto; }\n\
+.block { border: 1px solid #ddd; border-radius: 8px; padding: 0.5em 1em; margin: 1em 0; }\n\
+.command pre { font-family: monospace; white-space: pre-wrap; margin: 0.3em 0; }\n\
+.command.failed { border-color: #d33; }\n\
+.meta { color: #777; font-size: 0.85em; }\n\
+.assistant { background: #f3f8ff; }\n\
+.user { background: #f8fff3; }\n\
+.system { background: #fffaf3; }\n\
+.error { border-color: #d33; color: #d33; }\n\
+.superseded { color: #999; }\n\
+</style>\n</head>\n<body>\n<h1>NeoTerm session</h1>\n";
+
+/// Escaped output with SGR styles as spans; other escape sequences dropped
+pub fn ansi_to_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for span in crate::ansi::parse(text) {
+        match span.style.to_css() {
+            Some(css) => html.push_str(&format!("<span style=\"{}\">{}</span>", css, escape(&span.text))),
+            None => html.push_str(&escape(&span.text)),
+        }
+    }
+    html
+}
+
+/// Blocks of a saved session, as written by `session::save`
+pub fn load(path: &Path) -> Result<Vec<Block>, ExportError> {
+    if !path.exists() {
+        return Err(ExportError::IoError(format!("{} not found", path.display())));
+    }
+    crate::session::load(path).map(|restored| restored.blocks)
+}
+
+/// Most recently written session in `dir`
+pub fn latest(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+}
+
+fn write(path: &Path, contents: String) -> Result<(), ExportError> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| ExportError::IoError(e.to_string()))?;
+    }
+    std::fs::write(path, contents).map_err(|e| ExportError::IoError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::{OutputChunk, OutputStream};
+
+    fn session() -> Vec<Block> {
+        let mut command = Block::new_command("cargo test".to_string());
+        command.append_chunk(OutputChunk {
+            offset_ms: 0,
+            stream: OutputStream::Stdout,
+            text: "\x1b[1;31mFAILED\x1b[0m <tests>\n".to_string(),
+        });
+        command.finish_output(101, 10, &[]);
+        let mut old_prompt = Block::new_user_message("why?".to_string());
+        old_prompt.mark_superseded();
+        vec![
+            command,
+            old_prompt,
+            Block::new_user_message("Why did it fail?".to_string()),
+            Block::new_agent_message("Run `cargo test -- --nocapture`".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_markdown_keeps_order_and_strips_escapes() {
+        let markdown = export_markdown(&session());
+        assert!(markdown.contains("$ cargo test\nFAILED <tests>\n"));
+        assert!(markdown.contains(", exit 101_"));
+        assert!(markdown.contains("<details><summary>Superseded</summary>\n\nwhy?\n</details>"));
+        let prompt = markdown.find("Why did it fail?").unwrap();
+        let reply = markdown.find("cargo test -- --nocapture").unwrap();
+        assert!(markdown.find("$ cargo test").unwrap() < prompt && prompt < reply);
+        assert!(!markdown.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_html_colors_output_and_renders_replies() {
+        let html = export_html(&session());
+        assert!(html.contains(
+            "<pre class=\"output\"><span style=\"color: #cd3131; font-weight: bold\">FAILED</span> &lt;tests&gt;\n</pre>"
+        ));
+        assert!(html.contains("<section class=\"block command failed\">"));
+        assert!(html.contains("<p>Run <code>cargo test -- --nocapture</code></p>"));
+        assert!(html.contains("<details class=\"block message user superseded\">"));
+    }
+
+    #[test]
+    fn test_ansi_to_html_drops_other_sequences() {
+        assert_eq!(ansi_to_html("\x1b]0;title\x07a\x1b[2Kb\x1b[32mc\x1b[39md"), "ab<span style=\"color: #0dbc79\">c</span>d");
+    }
+
+    #[test]
+    fn test_saved_session_loads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        let blocks = session();
+        crate::session::save(&[crate::session::SavedTab::new(&blocks, &crate::panes::Panes::default())], 0, &path, usize::MAX).unwrap();
+
+        assert_eq!(latest(dir.path()), Some(path.clone()));
+        let loaded = load(&path).unwrap();
+        assert_eq!(export_markdown(&loaded), export_markdown(&blocks));
+    }
+}
@@ -1,70 +1,102 @@
 use iced::{Element, widget::{column, row, text, button, text_input, scrollable}};
-use std::collections::HashMap;
-use crate::config::{KeyBindings, KeyBinding, Action, Modifier};
+use std::collections::HashSet;
+use crate::config::KeyBindings;
+use crate::keymap::{self, Action, KeyChord, KeySequence};
 
 #[derive(Debug, Clone)]
 pub struct KeyBindingEditor {
     keybindings: KeyBindings,
-    editing_binding: Option<String>,
-    new_binding_name: String,
+    /// The action whose keys are being recorded, and the keys so far
+    recording: Option<(Action, Vec<KeyChord>)>,
+    /// Keys refused for an action, and the action that already has them
+    refused: Option<(Action, KeySequence, Action)>,
     search_query: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    EditBinding(String),
-    UpdateBinding(String, KeyBinding),
-    DeleteBinding(String),
-    AddBinding,
-    NewBindingNameChanged(String),
+    Record(Action),
+    /// A key pressed while recording, passed on by the application
+    KeyCaptured(KeyChord),
+    SaveRecording,
+    CancelRecording,
+    Unbind(Action),
+    Reset(Action),
     SearchChanged(String),
     ResetToDefaults,
-    CancelEdit,
 }
 
 impl KeyBindingEditor {
     pub fn new(keybindings: KeyBindings) -> Self {
         Self {
             keybindings,
-            editing_binding: None,
-            new_binding_name: String::new(),
+            recording: None,
+            refused: None,
             search_query: String::new(),
         }
     }
 
+    /// Whether key presses should come here instead of running actions
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
     pub fn update(&mut self, message: Message) -> Option<KeyBindings> {
         match message {
-            Message::EditBinding(name) => {
-                self.editing_binding = Some(name);
+            Message::Record(action) => {
+                self.recording = Some((action, Vec::new()));
+                self.refused = None;
                 None
             }
-            Message::UpdateBinding(name, binding) => {
-                self.keybindings.bindings.insert(name, binding);
-                self.editing_binding = None;
+            Message::KeyCaptured(chord) => {
+                let (_, keys) = self.recording.as_mut()?;
+                let plain_escape = chord.key == "Escape" && !(chord.ctrl || chord.alt || chord.shift || chord.logo);
+                if plain_escape {
+                    self.recording = None;
+                    return None;
+                }
+                // Past the longest sequence, the next key starts over
+                if keys.len() >= keymap::MAX_SEQUENCE {
+                    keys.clear();
+                }
+                keys.push(chord);
+                None
+            }
+            Message::SaveRecording => {
+                let (action, keys) = self.recording.take()?;
+                if keys.is_empty() {
+                    return None;
+                }
+                let keys = KeySequence(keys);
+                if let Some(other) = keymap::conflict(&self.keybindings, action, &keys) {
+                    self.refused = Some((action, keys, other));
+                    self.recording = Some((action, Vec::new()));
+                    return None;
+                }
+                self.refused = None;
+                self.keybindings.set(action, vec![keys]);
                 Some(self.keybindings.clone())
             }
-            Message::DeleteBinding(name) => {
-                self.keybindings.bindings.remove(&name);
+            Message::CancelRecording => {
+                self.recording = None;
+                self.refused = None;
+                None
+            }
+            Message::Unbind(action) => {
+                self.keybindings.set(action, Vec::new());
                 Some(self.keybindings.clone())
             }
-            Message::AddBinding => {
-                if !self.new_binding_name.is_empty() {
-                    let binding = KeyBinding {
-                        key: "".to_string(),
-                        modifiers: vec![],
-                        action: Action::Command("".to_string()),
-                        when: None,
-                    };
-                    self.keybindings.bindings.insert(self.new_binding_name.clone(), binding);
-                    self.new_binding_name.clear();
-                    Some(self.keybindings.clone())
-                } else {
-                    None
+            Message::Reset(action) => {
+                let clash = action
+                    .default_keys()
+                    .into_iter()
+                    .find_map(|keys| keymap::conflict(&self.keybindings, action, &keys).map(|other| (keys, other)));
+                if let Some((keys, other)) = clash {
+                    self.refused = Some((action, keys, other));
+                    return None;
                 }
-            }
-            Message::NewBindingNameChanged(name) => {
-                self.new_binding_name = name;
-                None
+                self.keybindings.reset(action);
+                Some(self.keybindings.clone())
             }
             Message::SearchChanged(query) => {
                 self.search_query = query;
@@ -72,42 +104,34 @@ impl KeyBindingEditor {
             }
             Message::ResetToDefaults => {
                 self.keybindings = KeyBindings::default();
+                self.recording = None;
+                self.refused = None;
                 Some(self.keybindings.clone())
             }
-            Message::CancelEdit => {
-                self.editing_binding = None;
-                None
-            }
         }
     }
 
     pub fn view(&self) -> Element<Message> {
+        let conflicting = keymap::conflicting(&self.keybindings);
         column![
-            // Search and add new binding
-            row![
-                text_input("Search bindings...", &self.search_query)
-                    .on_input(Message::SearchChanged)
-                    .width(iced::Length::Fill),
-                text_input("New binding name...", &self.new_binding_name)
-                    .on_input(Message::NewBindingNameChanged),
-                button("Add")
-                    .on_press(Message::AddBinding)
-            ].spacing(8),
-            
+            text_input("Search bindings...", &self.search_query)
+                .on_input(Message::SearchChanged)
+                .width(iced::Length::Fill),
+
             // Key bindings list
             scrollable(
                 column(
-                    self.filtered_bindings()
+                    self.filtered_actions()
                         .into_iter()
-                        .map(|(name, binding)| self.create_binding_row(name, binding))
+                        .map(|action| self.create_binding_row(action, &conflicting))
                         .collect::<Vec<_>>()
                 )
                 .spacing(8)
             ).height(iced::Length::Fixed(400.0)),
-            
+
             // Actions
             row![
-                button("Reset to Defaults")
+                button("Reset All to Defaults")
                     .on_press(Message::ResetToDefaults),
             ].spacing(8),
         ]
@@ -115,135 +139,87 @@ impl KeyBindingEditor {
         .into()
     }
 
-    fn filtered_bindings(&self) -> Vec<(String, KeyBinding)> {
-        self.keybindings
-            .bindings
-            .iter()
-            .filter(|(name, _)| {
-                if self.search_query.is_empty() {
-                    true
-                } else {
-                    name.to_lowercase().contains(&self.search_query.to_lowercase())
-                }
+    fn filtered_actions(&self) -> Vec<Action> {
+        let query = self.search_query.to_lowercase();
+        Action::all()
+            .filter(|action| {
+                query.is_empty()
+                    || action.label().to_lowercase().contains(&query)
+                    || action.id().contains(&query)
+                    || self.format_keys(*action).to_lowercase().contains(&query)
             })
-            .map(|(name, binding)| (name.clone(), binding.clone()))
             .collect()
     }
 
-    fn create_binding_row(&self, name: String, binding: KeyBinding) -> Element<Message> {
-        let is_editing = self.editing_binding.as_ref() == Some(&name);
-        
-        if is_editing {
-            self.create_editing_row(name, binding)
-        } else {
-            self.create_display_row(name, binding)
-        }
-    }
+    fn create_binding_row(&self, action: Action, conflicting: &HashSet<Action>) -> Element<Message> {
+        let recorded = self.recording.as_ref().filter(|(recording, _)| *recording == action).map(|(_, keys)| keys);
+        let clashes = conflicting.contains(&action)
+            || self.refused.as_ref().is_some_and(|(refused, _, other)| *refused == action || *other == action);
 
-    fn create_display_row(&self, name: String, binding: KeyBinding) -> Element<Message> {
-        let key_combo = self.format_key_combination(&binding);
-        let action_desc = self.format_action(&binding.action);
-        
-        iced::widget::container(
-            row![
-                text(&name).width(iced::Length::Fixed(150.0)),
-                text(key_combo).width(iced::Length::Fixed(150.0)),
-                text(action_desc).width(iced::Length::Fill),
-                button("Edit")
-                    .on_press(Message::EditBinding(name.clone())),
-                button("Delete")
-                    .on_press(Message::DeleteBinding(name.clone()))
-                    .style(button::danger),
-            ]
-            .spacing(8)
-            .align_items(iced::Alignment::Center)
-        )
-        .padding(8)
-        .style(|theme| iced::widget::container::Appearance {
-            background: Some(theme.palette().background.into()),
-            border: iced::Border {
-                color: theme.palette().text.scale_alpha(0.1),
-                width: 1.0,
-                radius: 4.0.into(),
-            },
-            ..Default::default()
-        })
-        .into()
-    }
-
-    fn create_editing_row(&self, name: String, binding: KeyBinding) -> Element<Message> {
-        iced::widget::container(
-            column![
-                row![
-                    text("Name:").width(iced::Length::Fixed(80.0)),
-                    text(&name)
-                ].spacing(8),
-                
-                row![
-                    text("Key:").width(iced::Length::Fixed(80.0)),
-                    text_input("Key...", &binding.key)
-                ].spacing(8),
-                
-                row![
-                    text("Action:").width(iced::Length::Fixed(80.0)),
-                    text(self.format_action(&binding.action))
-                ].spacing(8),
-                
+        let controls = match recorded {
+            Some(keys) => {
+                let pressed = if keys.is_empty() {
+                    "Press keys… (Esc cancels)".to_string()
+                } else {
+                    KeySequence(keys.clone()).to_string()
+                };
                 row![
+                    text(pressed).width(iced::Length::Fill),
                     button("Save")
-                        .on_press(Message::UpdateBinding(name.clone(), binding)),
+                        .on_press_maybe((!keys.is_empty()).then_some(Message::SaveRecording)),
                     button("Cancel")
-                        .on_press(Message::CancelEdit),
-                ].spacing(8),
+                        .on_press(Message::CancelRecording),
+                ]
+            }
+            None => row![
+                text(self.format_keys(action)).width(iced::Length::Fill),
+                button("Record")
+                    .on_press(Message::Record(action)),
+                button("Reset")
+                    .on_press_maybe((!self.keybindings.is_default(action)).then_some(Message::Reset(action))),
+                button("Unbind")
+                    .on_press_maybe((!self.keybindings.keys(action).is_empty()).then_some(Message::Unbind(action)))
+                    .style(button::danger),
+            ],
+        };
+
+        let mut content = column![
+            row![
+                column![
+                    text(action.label()),
+                    text(action.id()).size(11).style(iced::theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5))),
+                ]
+                .width(iced::Length::Fixed(220.0)),
+                controls.spacing(8).align_items(iced::Alignment::Center),
             ]
             .spacing(8)
-        )
-        .padding(12)
-        .style(|theme| iced::widget::container::Appearance {
-            background: Some(theme.palette().primary.scale_alpha(0.1).into()),
-            border: iced::Border {
-                color: theme.palette().primary,
-                width: 2.0,
-                radius: 4.0.into(),
-            },
-            ..Default::default()
-        })
-        .into()
-    }
-
-    fn format_key_combination(&self, binding: &KeyBinding) -> String {
-        let mut parts = Vec::new();
-        
-        for modifier in &binding.modifiers {
-            match modifier {
-                Modifier::Ctrl => parts.push("Ctrl"),
-                Modifier::Alt => parts.push("Alt"),
-                Modifier::Shift => parts.push("Shift"),
-                Modifier::Super => parts.push("Super"),
-            }
-        }
-        
-        if !binding.key.is_empty() {
-            parts.push(&binding.key);
+            .align_items(iced::Alignment::Center)
+        ]
+        .spacing(4);
+        if let Some((_, keys, other)) = self.refused.as_ref().filter(|(refused, ..)| *refused == action) {
+            let message = format!("{} is already bound to \"{}\"", keys, other.label());
+            content = content.push(text(message).size(12).style(iced::theme::Text::Color(iced::Color::from_rgb(0.8, 0.0, 0.0))));
         }
-        
-        parts.join(" + ")
+
+        iced::widget::container(content)
+            .padding(8)
+            .style(move |theme: &iced::Theme| iced::widget::container::Appearance {
+                background: Some(theme.palette().background.into()),
+                border: iced::Border {
+                    color: if clashes { theme.palette().danger } else { theme.palette().text.scale_alpha(0.1) },
+                    width: if clashes { 2.0 } else { 1.0 },
+                    radius: 4.0.into(),
+                },
+                ..Default::default()
+            })
+            .into()
     }
 
-    fn format_action(&self, action: &Action) -> String {
-        match action {
-            Action::NewTab => "New Tab".to_string(),
-            Action::CloseTab => "Close Tab".to_string(),
-            Action::NextTab => "Next Tab".to_string(),
-            Action::PreviousTab => "Previous Tab".to_string(),
-            Action::Copy => "Copy".to_string(),
-            Action::Paste => "Paste".to_string(),
-            Action::Find => "Find".to_string(),
-            Action::ToggleFullscreen => "Toggle Fullscreen".to_string(),
-            Action::ToggleSettings => "Toggle Settings".to_string(),
-            Action::Quit => "Quit".to_string(),
-            Action::Command(cmd) => format!("Command: {}", cmd),
-            _ => "Unknown".to_string(),
+    fn format_keys(&self, action: Action) -> String {
+        let keys = self.keybindings.keys(action);
+        if keys.is_empty() {
+            return "Unbound".to_string();
         }
+        keys.iter().map(KeySequence::to_string).collect::<Vec<_>>().join(", ")
     }
 }
@@ -1,5 +1,10 @@
 use iced::{Element, widget::{column, row, text, button, container, scrollable, pick_list, slider, checkbox, text_input}};
 use crate::{Message, config::*};
+use crate::agent_mode_eval::{AgentConfig, ai_client::AiProvider, availability, tools::ApprovalMode};
+use crate::i18n::{tr, Locale};
+use crate::plugin_api::{PluginManager, PluginManifest, PluginPermission};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
 
 pub mod theme_editor;
 pub mod keybinding_editor;
@@ -14,6 +19,22 @@ pub struct SettingsView {
     pub theme_editor: ThemeEditor,
     pub keybinding_editor: KeyBindingEditor,
     pub unsaved_changes: bool,
+    /// Sections ticked in the reset confirmation, while it is open
+    pub reset_dialog: Option<BTreeSet<ConfigSection>>,
+    /// A reset is pending, so the next Save backs up the file it replaces
+    pub backup_before_save: bool,
+    /// Backups listed by "Restore from backup…", once asked for
+    pub backups: Option<Vec<ConfigBackup>>,
+    /// File picked for import, while asking how to combine it
+    pub import_dialog: Option<PathBuf>,
+    /// Why the picked file couldn't be imported
+    pub import_error: Option<String>,
+    /// Models an Ollama server reported for the AI tab, or why it couldn't
+    pub discovered_models: Option<Result<Vec<String>, String>>,
+    /// Installed plugins with a readable manifest, for the Plugins tab
+    pub installed_plugins: Vec<PluginManifest>,
+    /// Newer versions their registries list, by plugin id, once checked
+    pub plugin_updates: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -25,23 +46,79 @@ pub enum SettingsTab {
     KeyBindings,
     Performance,
     Privacy,
+    Ai,
     Plugins,
 }
 
+impl SettingsTab {
+    /// All tabs in display order
+    pub const ALL: [SettingsTab; 9] = [
+        SettingsTab::General,
+        SettingsTab::Appearance,
+        SettingsTab::Terminal,
+        SettingsTab::Editor,
+        SettingsTab::KeyBindings,
+        SettingsTab::Performance,
+        SettingsTab::Privacy,
+        SettingsTab::Ai,
+        SettingsTab::Plugins,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingsTab::General => tr("settings.tab.general"),
+            SettingsTab::Appearance => tr("settings.tab.appearance"),
+            SettingsTab::Terminal => tr("settings.tab.terminal"),
+            SettingsTab::Editor => tr("settings.tab.editor"),
+            SettingsTab::KeyBindings => tr("settings.tab.keybindings"),
+            SettingsTab::Performance => tr("settings.tab.performance"),
+            SettingsTab::Privacy => tr("settings.tab.privacy"),
+            SettingsTab::Ai => tr("settings.tab.ai"),
+            SettingsTab::Plugins => tr("settings.tab.plugins"),
+        }
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|tab| tab == self).unwrap_or(0)
+    }
+
+    /// The tab to the right, wrapping around
+    pub fn next(&self) -> SettingsTab {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()].clone()
+    }
+
+    /// The tab to the left, wrapping around
+    pub fn previous(&self) -> SettingsTab {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()].clone()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum SettingsMessage {
     TabChanged(SettingsTab),
+    NextTab,
+    PreviousTab,
     ConfigChanged(ConfigChange),
     ThemeChanged(String),
     CustomThemeCreated(String),
-    KeyBindingChanged(String, KeyBinding),
     ResetToDefaults,
+    ResetSectionToggled(ConfigSection, bool),
+    ConfirmReset,
+    CancelReset,
+    ShowBackups,
+    RestoreBackup(PathBuf),
     ImportConfig,
+    ConfirmImport(ImportStrategy),
+    CancelImport,
     ExportConfig,
     Save,
     Cancel,
     ThemeEditor(theme_editor::Message),
     KeyBindingEditor(keybinding_editor::Message),
+    /// Handled by the application, which asks for confirmation first
+    Clear(crate::clear::ClearTarget),
+    /// Handled by the application, which asks the model server again
+    RefreshModels,
 }
 
 #[derive(Debug, Clone)]
@@ -52,14 +129,25 @@ pub enum ConfigChange {
     WorkingDirectory(WorkingDirectoryBehavior),
     AutoUpdate(bool),
     TelemetryEnabled(bool),
+    Language(Option<Locale>),
     
     // Terminal
     ScrollbackLines(usize),
+    AutoCollapseLines(Option<usize>),
+    NotificationsEnabled(bool),
+    NotifyAfterSecs(u64),
+    UsePty(bool),
+    RequireAttach(bool),
+    ShellIntegration(bool),
+    BrowseOnLs(bool),
+    HyperlinkBehavior(HyperlinkBehavior),
+    OpenPathsIn(PathLinkAction),
     ScrollSensitivity(f32),
     MouseReporting(bool),
     CopyOnSelect(bool),
     PasteOnRightClick(bool),
     ConfirmBeforeClosing(bool),
+    ExpandVariables(bool),
     BellBehavior(BellBehavior),
     CursorStyle(CursorStyle),
     CursorBlink(bool),
@@ -67,6 +155,7 @@ pub enum ConfigChange {
     // Editor
     VimMode(bool),
     AutoSuggestions(bool),
+    AiSuggestions(bool),
     SyntaxHighlighting(bool),
     AutoCompletion(bool),
     IndentSize(usize),
@@ -80,7 +169,15 @@ pub enum ConfigChange {
     Transparency(f32),
     BlurBackground(bool),
     AnimationsEnabled(bool),
+    AlwaysShowStatusGlyphs(bool),
     ZoomLevel(f32),
+    FontFamily(String),
+    FontSize(f32),
+
+    // Appearance
+    FollowSystemAppearance(bool),
+    LightTheme(String),
+    DarkTheme(String),
     
     // Performance
     GpuAcceleration(bool),
@@ -93,7 +190,34 @@ pub enum ConfigChange {
     HistoryLimit(usize),
     ClearHistoryOnExit(bool),
     IncognitoMode(bool),
+    ShareHostIdentity(bool),
     LogLevel(LogLevel),
+    CrashReportConsent(bool),
+    CrashReportDsn(String),
+
+    // Network
+    HttpProxy(String),
+    HttpsProxy(String),
+    NoProxy(String),
+    CaBundlePath(String),
+    VerifyTls(bool),
+
+    // AI
+    AiProvider(Option<AiProvider>),
+    AiModel(String),
+    AiBaseUrl(String),
+    AiApiKey(String),
+    AiTemperature(f32),
+    ConfirmGeneratedCommands(bool),
+    NaturalLanguageCommands(bool),
+    OneClickFixes(bool),
+    AutoFixOnFailure(bool),
+    ToolApproval(ApprovalMode),
+
+    // Plugins
+    PluginEnabled(String, bool),
+    /// Grant (true) or revoke one of a plugin's declared permissions
+    PluginPermission(String, PluginPermission, bool),
 }
 
 impl SettingsView {
@@ -104,15 +228,40 @@ impl SettingsView {
             keybinding_editor: KeyBindingEditor::new(config.keybindings.clone()),
             config,
             unsaved_changes: false,
+            reset_dialog: None,
+            backup_before_save: false,
+            backups: None,
+            import_dialog: None,
+            import_error: None,
+            discovered_models: None,
+            installed_plugins: PluginManager::new()
+                .and_then(|manager| manager.installed())
+                .map(|plugins| plugins.into_iter().filter_map(|plugin| plugin.manifest.ok()).collect())
+                .unwrap_or_default(),
+            plugin_updates: HashMap::new(),
         }
     }
 
+    /// Open the settings view on a specific tab (e.g. the one last used)
+    pub fn with_tab(mut self, tab: SettingsTab) -> Self {
+        self.active_tab = tab;
+        self
+    }
+
     pub fn update(&mut self, message: SettingsMessage) -> Option<AppConfig> {
         match message {
             SettingsMessage::TabChanged(tab) => {
                 self.active_tab = tab;
                 None
             }
+            SettingsMessage::NextTab => {
+                self.active_tab = self.active_tab.next();
+                None
+            }
+            SettingsMessage::PreviousTab => {
+                self.active_tab = self.active_tab.previous();
+                None
+            }
             SettingsMessage::ConfigChanged(change) => {
                 self.apply_config_change(change);
                 self.unsaved_changes = true;
@@ -129,6 +278,15 @@ impl SettingsView {
                 None
             }
             SettingsMessage::Save => {
+                if self.backup_before_save {
+                    let backup = ConfigPaths::resolve().and_then(|paths| backup_config(&paths, chrono::Local::now()));
+                    if let Err(e) = backup {
+                        // Keep the file rather than overwrite it without a copy
+                        eprintln!("Failed to back up config, not saving: {}", e);
+                        return None;
+                    }
+                    self.backup_before_save = false;
+                }
                 if let Err(e) = self.config.save() {
                     eprintln!("Failed to save config: {}", e);
                 }
@@ -146,8 +304,89 @@ impl SettingsView {
                 }
             }
             SettingsMessage::ResetToDefaults => {
-                self.config = AppConfig::default();
-                self.unsaved_changes = true;
+                self.reset_dialog = Some(changed_sections(&self.config).into_iter().collect());
+                None
+            }
+            SettingsMessage::ResetSectionToggled(section, selected) => {
+                if let Some(sections) = &mut self.reset_dialog {
+                    if selected {
+                        sections.insert(section);
+                    } else {
+                        sections.remove(&section);
+                    }
+                }
+                None
+            }
+            SettingsMessage::ConfirmReset => {
+                let sections: Vec<ConfigSection> = self.reset_dialog.take().into_iter().flatten().collect();
+                if !sections.is_empty() {
+                    self.replace_config(reset_sections(&self.config, &sections));
+                    self.backup_before_save = true;
+                    self.unsaved_changes = true;
+                }
+                None
+            }
+            SettingsMessage::CancelReset => {
+                self.reset_dialog = None;
+                None
+            }
+            SettingsMessage::ShowBackups => {
+                self.backups = Some(ConfigPaths::resolve().map(|paths| list_backups(&paths)).unwrap_or_default());
+                None
+            }
+            SettingsMessage::RestoreBackup(path) => {
+                let restored = ConfigPaths::resolve().and_then(|paths| restore_backup(&paths, &path));
+                match restored {
+                    Ok(config) => {
+                        self.replace_config(config.clone());
+                        self.backups = None;
+                        self.backup_before_save = false;
+                        self.unsaved_changes = false;
+                        Some(config)
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to restore config backup: {}", e);
+                        None
+                    }
+                }
+            }
+            SettingsMessage::ImportConfig => {
+                self.import_dialog = rfd::FileDialog::new().add_filter("YAML", &["yaml", "yml"]).pick_file();
+                self.import_error = None;
+                None
+            }
+            SettingsMessage::ConfirmImport(strategy) => {
+                let path = self.import_dialog.as_ref()?;
+                let imported = std::fs::read_to_string(path)
+                    .map_err(|e| ConfigError::IoError(e.to_string()))
+                    .and_then(|content| import_yaml(&content, &self.config, strategy));
+                match imported {
+                    Ok(config) => {
+                        self.replace_config(config);
+                        self.import_dialog = None;
+                        // Like a reset, the file it replaces is backed up on Save
+                        self.backup_before_save = true;
+                        self.unsaved_changes = true;
+                    }
+                    Err(e) => self.import_error = Some(e.to_string()),
+                }
+                None
+            }
+            SettingsMessage::CancelImport => {
+                self.import_dialog = None;
+                self.import_error = None;
+                None
+            }
+            SettingsMessage::ExportConfig => {
+                let path = rfd::FileDialog::new()
+                    .add_filter("YAML", &["yaml", "yml"])
+                    .set_file_name("neoterm-config.yaml")
+                    .save_file()?;
+                let exported = export_yaml(&self.config, false)
+                    .and_then(|content| std::fs::write(&path, content).map_err(|e| ConfigError::IoError(e.to_string())));
+                if let Err(e) = exported {
+                    eprintln!("Failed to export config: {}", e);
+                }
                 None
             }
             SettingsMessage::ThemeEditor(msg) => {
@@ -168,6 +407,22 @@ impl SettingsView {
         }
     }
 
+    /// Models the AI tab offers for `provider`: what the server reported when
+    /// it was asked, otherwise the built-in list
+    fn model_choices(&self, provider: &AiProvider) -> Vec<String> {
+        match &self.discovered_models {
+            Some(Ok(models)) if *provider == AiProvider::Ollama => models.clone(),
+            _ => AgentConfig::get_available_models(provider).into_iter().map(str::to_string).collect(),
+        }
+    }
+
+    /// Swap in a whole new config, keeping the editors in step with it
+    fn replace_config(&mut self, config: AppConfig) {
+        self.theme_editor = ThemeEditor::new(config.theme.clone());
+        self.keybinding_editor = KeyBindingEditor::new(config.keybindings.clone());
+        self.config = config;
+    }
+
     fn apply_config_change(&mut self, change: ConfigChange) {
         match change {
             ConfigChange::StartupBehavior(behavior) => {
@@ -179,6 +434,36 @@ impl SettingsView {
             ConfigChange::AutoUpdate(enabled) => {
                 self.config.preferences.general.auto_update = enabled;
             }
+            ConfigChange::Language(language) => {
+                self.config.preferences.general.language = language;
+            }
+            ConfigChange::AutoCollapseLines(lines) => {
+                self.config.preferences.terminal.auto_collapse_lines = lines;
+            }
+            ConfigChange::NotificationsEnabled(enabled) => {
+                self.config.preferences.terminal.notifications_enabled = enabled;
+            }
+            ConfigChange::NotifyAfterSecs(secs) => {
+                self.config.preferences.terminal.notify_after_secs = secs;
+            }
+            ConfigChange::UsePty(enabled) => {
+                self.config.preferences.terminal.use_pty = enabled;
+            }
+            ConfigChange::RequireAttach(enabled) => {
+                self.config.preferences.terminal.require_attach = enabled;
+            }
+            ConfigChange::ShellIntegration(enabled) => {
+                self.config.preferences.terminal.shell_integration = enabled;
+            }
+            ConfigChange::BrowseOnLs(enabled) => {
+                self.config.preferences.terminal.browse_on_ls = enabled;
+            }
+            ConfigChange::HyperlinkBehavior(behavior) => {
+                self.config.preferences.terminal.hyperlink_behavior = behavior;
+            }
+            ConfigChange::OpenPathsIn(action) => {
+                self.config.preferences.terminal.open_paths_in = action;
+            }
             ConfigChange::ScrollbackLines(lines) => {
                 self.config.preferences.terminal.scrollback_lines = lines;
             }
@@ -188,27 +473,155 @@ impl SettingsView {
             ConfigChange::CopyOnSelect(enabled) => {
                 self.config.preferences.terminal.copy_on_select = enabled;
             }
+            ConfigChange::ExpandVariables(enabled) => {
+                self.config.preferences.terminal.expand_variables = enabled;
+            }
             ConfigChange::VimMode(enabled) => {
                 self.config.preferences.editor.vim_mode = enabled;
             }
             ConfigChange::AutoSuggestions(enabled) => {
                 self.config.preferences.editor.auto_suggestions = enabled;
             }
+            ConfigChange::AiSuggestions(enabled) => {
+                self.config.preferences.editor.ai_suggestions = enabled;
+            }
             ConfigChange::Transparency(value) => {
                 self.config.preferences.ui.transparency = value;
             }
+            ConfigChange::AlwaysShowStatusGlyphs(enabled) => {
+                self.config.preferences.ui.always_show_status_glyphs = enabled;
+            }
+            ConfigChange::FontFamily(family) => {
+                self.config.preferences.ui.font_family = family;
+            }
+            ConfigChange::FontSize(size) => {
+                self.config.preferences.ui.font_size = size;
+            }
+            ConfigChange::FollowSystemAppearance(enabled) => {
+                self.config.preferences.appearance.follow_system = enabled;
+            }
+            ConfigChange::LightTheme(name) => {
+                self.config.preferences.appearance.light_theme = name;
+            }
+            ConfigChange::DarkTheme(name) => {
+                self.config.preferences.appearance.dark_theme = name;
+            }
             ConfigChange::GpuAcceleration(enabled) => {
                 self.config.preferences.performance.gpu_acceleration = enabled;
             }
+            ConfigChange::CrashReportConsent(granted) => {
+                self.config.preferences.crash_reports.consent = if granted {
+                    crate::config::CrashReportConsent::Granted
+                } else {
+                    crate::config::CrashReportConsent::Denied
+                };
+            }
+            ConfigChange::CrashReportDsn(dsn) => {
+                self.config.preferences.crash_reports.dsn = non_empty(dsn);
+            }
+            ConfigChange::HttpProxy(url) => {
+                self.config.preferences.network.http_proxy = non_empty(url);
+            }
+            ConfigChange::HttpsProxy(url) => {
+                self.config.preferences.network.https_proxy = non_empty(url);
+            }
+            ConfigChange::NoProxy(list) => {
+                self.config.preferences.network.no_proxy = list
+                    .split(',')
+                    .map(|host| host.trim().to_string())
+                    .filter(|host| !host.is_empty())
+                    .collect();
+            }
+            ConfigChange::CaBundlePath(path) => {
+                self.config.preferences.network.ca_bundle_path = non_empty(path).map(std::path::PathBuf::from);
+            }
+            ConfigChange::VerifyTls(enabled) => {
+                self.config.preferences.network.verify_tls = enabled;
+            }
+            ConfigChange::AiProvider(provider) => {
+                let ai = &mut self.config.preferences.ai;
+                if ai.provider != provider {
+                    // Models, endpoints and keys belong to one provider; a key
+                    // is never sent to another
+                    ai.model = None;
+                    ai.base_url = None;
+                    ai.api_key = None;
+                    ai.provider = provider;
+                    self.discovered_models = None;
+                }
+            }
+            ConfigChange::AiModel(model) => {
+                self.config.preferences.ai.model = non_empty(model);
+            }
+            ConfigChange::AiBaseUrl(url) => {
+                self.config.preferences.ai.base_url = non_empty(url);
+                self.discovered_models = None;
+            }
+            ConfigChange::AiApiKey(key) => {
+                self.config.preferences.ai.api_key = non_empty(key);
+            }
+            ConfigChange::AiTemperature(value) => {
+                self.config.preferences.ai.temperature = (value * 10.0).round() / 10.0;
+            }
+            ConfigChange::ConfirmGeneratedCommands(enabled) => {
+                self.config.preferences.ai.confirm_generated_commands = enabled;
+            }
+            ConfigChange::NaturalLanguageCommands(enabled) => {
+                self.config.preferences.ai.natural_language_commands = enabled;
+            }
+            ConfigChange::OneClickFixes(enabled) => {
+                self.config.preferences.ai.one_click_fixes = enabled;
+            }
+            ConfigChange::AutoFixOnFailure(enabled) => {
+                self.config.preferences.ai.auto_fix_on_failure = enabled;
+            }
+            ConfigChange::ToolApproval(mode) => {
+                self.config.preferences.ai.tool_approval = mode;
+            }
+            ConfigChange::PluginEnabled(id, enabled) => {
+                self.config.plugins.enabled_plugins.retain(|plugin| plugin != &id);
+                if enabled {
+                    self.config.plugins.enabled_plugins.push(id);
+                }
+            }
+            ConfigChange::PluginPermission(id, permission, true) => {
+                self.config.plugins.granted_permissions.grant(&id, [permission]);
+            }
+            ConfigChange::PluginPermission(id, permission, false) => {
+                self.config.plugins.granted_permissions.revoke(&id, permission);
+            }
+            ConfigChange::ShareHostIdentity(enabled) => {
+                self.config.preferences.privacy.share_host_identity = enabled;
+            }
+            ConfigChange::ShowTabBar(visibility) => {
+                self.config.preferences.ui.show_tab_bar = visibility;
+            }
+            ConfigChange::ConfirmBeforeClosing(enabled) => {
+                self.config.preferences.terminal.confirm_before_closing = enabled;
+            }
             // Add other config changes...
             _ => {}
         }
     }
 
-    pub fn view(&self) -> Element<SettingsMessage> {
-        let tabs = self.create_tabs();
-        let content = self.create_content();
-        let actions = self.create_actions();
+    /// `compact` swaps the tab row for a dropdown and wraps the actions,
+    /// for windows too narrow to fit them on one line
+    pub fn view(&self, compact: bool) -> Element<SettingsMessage> {
+        let tabs = if compact {
+            pick_list(&SettingsTab::ALL[..], Some(self.active_tab.clone()), SettingsMessage::TabChanged).into()
+        } else {
+            self.create_tabs()
+        };
+        let content = match (&self.reset_dialog, &self.import_dialog) {
+            (Some(selected), _) => self.create_reset_dialog(selected),
+            (None, Some(path)) => self.create_import_dialog(path),
+            (None, None) => self.create_content(),
+        };
+        let actions = if compact {
+            self.create_compact_actions()
+        } else {
+            self.create_actions()
+        };
 
         container(
             column![
@@ -216,30 +629,20 @@ impl SettingsView {
                 scrollable(content).height(iced::Length::Fill),
                 actions
             ]
-            .spacing(16)
+            .spacing(if compact { 8 } else { 16 })
         )
-        .padding(24)
+        .padding(if compact { 8 } else { 24 })
         .into()
     }
 
     fn create_tabs(&self) -> Element<SettingsMessage> {
-        let tabs = vec![
-            ("General", SettingsTab::General),
-            ("Appearance", SettingsTab::Appearance),
-            ("Terminal", SettingsTab::Terminal),
-            ("Editor", SettingsTab::Editor),
-            ("Key Bindings", SettingsTab::KeyBindings),
-            ("Performance", SettingsTab::Performance),
-            ("Privacy", SettingsTab::Privacy),
-            ("Plugins", SettingsTab::Plugins),
-        ];
-
         row(
-            tabs.into_iter()
-                .map(|(label, tab)| {
-                    button(text(label))
+            SettingsTab::ALL
+                .iter()
+                .map(|tab| {
+                    button(text(tab.label()))
                         .on_press(SettingsMessage::TabChanged(tab.clone()))
-                        .style(if self.active_tab == tab {
+                        .style(if self.active_tab == *tab {
                             button::primary
                         } else {
                             button::secondary
@@ -261,16 +664,17 @@ impl SettingsView {
             SettingsTab::KeyBindings => self.create_keybinding_settings(),
             SettingsTab::Performance => self.create_performance_settings(),
             SettingsTab::Privacy => self.create_privacy_settings(),
+            SettingsTab::Ai => self.create_ai_settings(),
             SettingsTab::Plugins => self.create_plugin_settings(),
         }
     }
 
     fn create_general_settings(&self) -> Element<SettingsMessage> {
         column![
-            text("General Settings").size(20),
+            text(tr("settings.general.title")).size(20),
             
             row![
-                text("Startup Behavior:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.general.startup")).width(iced::Length::Fixed(150.0)),
                 pick_list(
                     vec![
                         StartupBehavior::NewSession,
@@ -282,135 +686,393 @@ impl SettingsView {
             ].spacing(8),
             
             row![
-                text("Default Shell:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.general.shell")).width(iced::Length::Fixed(150.0)),
                 text_input(
-                    "Shell path...",
+                    tr("settings.general.shell_placeholder"),
                     self.config.preferences.general.default_shell.as_deref().unwrap_or("")
                 )
                 .on_input(|shell| SettingsMessage::ConfigChanged(ConfigChange::DefaultShell(shell)))
             ].spacing(8),
+
+            row![
+                text(tr("settings.general.language")).width(iced::Length::Fixed(150.0)),
+                pick_list(
+                    LanguageChoice::all(),
+                    Some(LanguageChoice(self.config.preferences.general.language)),
+                    |choice| SettingsMessage::ConfigChanged(ConfigChange::Language(choice.0))
+                )
+            ].spacing(8),
             
             row![
                 checkbox(
-                    "Auto Update",
+                    tr("settings.general.auto_update"),
                     self.config.preferences.general.auto_update,
                     |enabled| SettingsMessage::ConfigChanged(ConfigChange::AutoUpdate(enabled))
                 ),
-                text("Automatically check for and install updates")
+                text(tr("settings.general.auto_update_help"))
             ].spacing(8),
             
             row![
                 checkbox(
-                    "Telemetry",
+                    tr("settings.general.telemetry"),
                     self.config.preferences.general.telemetry_enabled,
                     |enabled| SettingsMessage::ConfigChanged(ConfigChange::TelemetryEnabled(enabled))
                 ),
-                text("Help improve NeoTerm by sharing anonymous usage data")
+                text(tr("settings.general.telemetry_help"))
             ].spacing(8),
+
+            self.create_backup_list(),
+        ]
+        .spacing(16)
+        .into()
+    }
+
+    fn create_backup_list(&self) -> Element<SettingsMessage> {
+        let Some(backups) = &self.backups else {
+            return button(tr("settings.backups.open")).on_press(SettingsMessage::ShowBackups).into();
+        };
+        if backups.is_empty() {
+            return text(tr("settings.backups.none")).into();
+        }
+        column(
+            std::iter::once(text(tr("settings.backups.title")).size(16).into())
+                .chain(backups.iter().map(|backup| {
+                    row![
+                        text(backup.label()).width(iced::Length::Fixed(200.0)),
+                        button(tr("settings.backups.restore")).on_press(SettingsMessage::RestoreBackup(backup.path.clone())),
+                    ]
+                    .spacing(8)
+                    .into()
+                }))
+                .collect::<Vec<_>>()
+        )
+        .spacing(8)
+        .into()
+    }
+
+    fn create_reset_dialog(&self, selected: &BTreeSet<ConfigSection>) -> Element<SettingsMessage> {
+        let changed = changed_sections(&self.config);
+        if changed.is_empty() {
+            return column![
+                text(tr("settings.reset.title")).size(20),
+                text(tr("settings.reset.nothing")),
+                button(tr("settings.reset.close")).on_press(SettingsMessage::CancelReset),
+            ]
+            .spacing(16)
+            .into();
+        }
+        let sections = column(
+            changed
+                .into_iter()
+                .map(|section| {
+                    checkbox(section.title(), selected.contains(&section), move |on| {
+                        SettingsMessage::ResetSectionToggled(section, on)
+                    })
+                    .into()
+                })
+                .collect::<Vec<_>>()
+        )
+        .spacing(8);
+        column![
+            text(tr("settings.reset.title")).size(20),
+            text(tr("settings.reset.explanation")),
+            sections,
+            row![
+                button(tr("settings.actions.cancel")).on_press(SettingsMessage::CancelReset),
+                button(tr("settings.reset.confirm"))
+                    .on_press_maybe((!selected.is_empty()).then_some(SettingsMessage::ConfirmReset))
+                    .style(button::danger),
+            ]
+            .spacing(8),
         ]
         .spacing(16)
         .into()
     }
 
+    fn create_import_dialog(&self, path: &std::path::Path) -> Element<SettingsMessage> {
+        let mut dialog = column![
+            text(tr("settings.import.title")).size(20),
+            text(path.display().to_string()).size(12),
+            text(tr("settings.import.explanation")),
+        ]
+        .spacing(16);
+        if let Some(error) = &self.import_error {
+            dialog = dialog.push(text(error).style(iced::theme::Text::Color(iced::Color::from_rgb(0.8, 0.0, 0.0))));
+        }
+        dialog
+            .push(
+                row![
+                    button(tr("settings.actions.cancel")).on_press(SettingsMessage::CancelImport),
+                    button(tr("settings.import.merge")).on_press(SettingsMessage::ConfirmImport(ImportStrategy::Merge)),
+                    button(tr("settings.import.replace"))
+                        .on_press(SettingsMessage::ConfirmImport(ImportStrategy::Replace))
+                        .style(button::danger),
+                ]
+                .spacing(8),
+            )
+            .into()
+    }
+
     fn create_appearance_settings(&self) -> Element<SettingsMessage> {
         let theme_names: Vec<String> = ThemeConfig::builtin_themes()
             .into_iter()
             .map(|t| t.name)
             .collect();
+        let appearance = &self.config.preferences.appearance;
 
-        column![
-            text("Appearance Settings").size(20),
-            
+        let mut theme_section = column![
             row![
-                text("Theme:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.appearance.theme")).width(iced::Length::Fixed(150.0)),
                 pick_list(
-                    theme_names,
+                    theme_names.clone(),
                     Some(self.config.theme.name.clone()),
                     SettingsMessage::ThemeChanged
                 )
             ].spacing(8),
+            checkbox(
+                tr("settings.appearance.follow_system"),
+                appearance.follow_system,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::FollowSystemAppearance(enabled))
+            ),
+        ]
+        .spacing(16);
+        if appearance.follow_system {
+            theme_section = theme_section
+                .push(row![
+                    text(tr("settings.appearance.light_theme")).width(iced::Length::Fixed(150.0)),
+                    pick_list(
+                        theme_names.clone(),
+                        Some(appearance.light_theme.clone()),
+                        |name| SettingsMessage::ConfigChanged(ConfigChange::LightTheme(name))
+                    )
+                ].spacing(8))
+                .push(row![
+                    text(tr("settings.appearance.dark_theme")).width(iced::Length::Fixed(150.0)),
+                    pick_list(
+                        theme_names,
+                        Some(appearance.dark_theme.clone()),
+                        |name| SettingsMessage::ConfigChanged(ConfigChange::DarkTheme(name))
+                    )
+                ].spacing(8));
+        }
+
+        column![
+            text(tr("settings.appearance.title")).size(20),
+            
+            theme_section,
             
             row![
-                text("Font Family:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.appearance.font_family")).width(iced::Length::Fixed(150.0)),
                 text_input(
-                    "Font name...",
-                    &self.config.theme.typography.font_family
+                    tr("settings.appearance.font_placeholder"),
+                    &self.config.preferences.ui.font_family
                 )
+                .on_input(|family| SettingsMessage::ConfigChanged(ConfigChange::FontFamily(family)))
             ].spacing(8),
             
             row![
-                text("Font Size:").width(iced::Length::Fixed(150.0)),
-                slider(8.0..=24.0, self.config.theme.typography.font_size, |size| {
-                    // This would need to be handled differently in a real implementation
-                    SettingsMessage::ConfigChanged(ConfigChange::AutoUpdate(true))
-                })
+                text(tr("settings.appearance.font_size")).width(iced::Length::Fixed(150.0)),
+                slider(
+                    crate::font::MIN_SIZE..=crate::font::MAX_SIZE,
+                    self.config.preferences.ui.font_size,
+                    |size| SettingsMessage::ConfigChanged(ConfigChange::FontSize(size))
+                ),
+                text(format!("{:.0}", self.config.preferences.ui.font_size)).width(iced::Length::Fixed(30.0)),
             ].spacing(8),
+
+            self.create_font_preview(),
             
             row![
-                text("Transparency:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.appearance.transparency")).width(iced::Length::Fixed(150.0)),
                 slider(0.0..=1.0, self.config.preferences.ui.transparency, |value| {
                     SettingsMessage::ConfigChanged(ConfigChange::Transparency(value))
                 })
             ].spacing(8),
             
+            row![
+                text(tr("settings.appearance.tab_bar")).width(iced::Length::Fixed(150.0)),
+                pick_list(
+                    &TabBarVisibility::ALL[..],
+                    Some(self.config.preferences.ui.show_tab_bar),
+                    |visibility| SettingsMessage::ConfigChanged(ConfigChange::ShowTabBar(visibility))
+                )
+            ].spacing(8),
+            
             checkbox(
-                "Blur Background",
+                tr("settings.appearance.blur"),
                 self.config.preferences.ui.blur_background,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::BlurBackground(enabled))
             ),
             
             checkbox(
-                "Enable Animations",
+                tr("settings.appearance.animations"),
                 self.config.preferences.ui.animations_enabled,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::AnimationsEnabled(enabled))
             ),
+
+            checkbox(
+                tr("settings.appearance.status_glyphs"),
+                self.config.preferences.ui.always_show_status_glyphs,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::AlwaysShowStatusGlyphs(enabled))
+            ),
             
             // Theme editor section
-            text("Custom Theme Editor").size(16),
+            text(tr("settings.appearance.theme_editor")).size(16),
             self.theme_editor.view().map(SettingsMessage::ThemeEditor),
         ]
         .spacing(16)
         .into()
     }
 
+    /// Sample output in the chosen font, zoom included, and why it isn't
+    /// used if the family isn't installed
+    fn create_font_preview(&self) -> Element<SettingsMessage> {
+        let (font, warning) = crate::font::OutputFont::resolve(&self.config.preferences.ui);
+        let mut preview = column![
+            // Drawn like a block's output
+            container(
+                text(tr("settings.appearance.font_sample"))
+                    .font(font.font)
+                    .size(font.size)
+                    .style(iced::theme::Text::Color(iced::Color::from_rgb(0.85, 0.85, 0.85)))
+            )
+            .padding(8)
+            .width(iced::Length::Fill)
+            .style(container::Appearance {
+                background: Some(iced::Background::Color(iced::Color::from_rgb(0.05, 0.05, 0.05))),
+                border: iced::Border {
+                    color: iced::Color::from_rgb(0.2, 0.2, 0.2),
+                    width: 1.0,
+                    radius: 4.0.into(),
+                },
+                ..Default::default()
+            }),
+        ]
+        .spacing(4);
+        if let Some(warning) = warning {
+            preview = preview.push(text(warning).size(12).style(iced::theme::Text::Color(iced::Color::from_rgb(0.8, 0.5, 0.0))));
+        }
+        preview.into()
+    }
+
     fn create_terminal_settings(&self) -> Element<SettingsMessage> {
         column![
-            text("Terminal Settings").size(20),
+            text(tr("settings.terminal.title")).size(20),
             
             row![
-                text("Scrollback Lines:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.terminal.scrollback")).width(iced::Length::Fixed(150.0)),
                 slider(1000.0..=50000.0, self.config.preferences.terminal.scrollback_lines as f32, |lines| {
                     SettingsMessage::ConfigChanged(ConfigChange::ScrollbackLines(lines as usize))
                 })
             ].spacing(8),
             
             row![
-                text("Scroll Sensitivity:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.terminal.scroll_sensitivity")).width(iced::Length::Fixed(150.0)),
                 slider(0.1..=5.0, self.config.preferences.terminal.scroll_sensitivity, |sensitivity| {
                     SettingsMessage::ConfigChanged(ConfigChange::ScrollSensitivity(sensitivity))
                 })
             ].spacing(8),
             
             checkbox(
-                "Copy on Select",
+                tr("settings.terminal.copy_on_select"),
                 self.config.preferences.terminal.copy_on_select,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::CopyOnSelect(enabled))
             ),
             
             checkbox(
-                "Paste on Right Click",
+                tr("settings.terminal.paste_on_right_click"),
                 self.config.preferences.terminal.paste_on_right_click,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::PasteOnRightClick(enabled))
             ),
             
             checkbox(
-                "Confirm Before Closing",
+                tr("settings.terminal.confirm_close"),
                 self.config.preferences.terminal.confirm_before_closing,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::ConfirmBeforeClosing(enabled))
             ),
             
+            checkbox(
+                tr("settings.terminal.auto_collapse"),
+                self.config.preferences.terminal.auto_collapse_lines.is_some(),
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::AutoCollapseLines(enabled.then_some(DEFAULT_AUTO_COLLAPSE_LINES)))
+            ),
+
+            row![
+                text(tr("settings.terminal.auto_collapse_lines")).width(iced::Length::Fixed(150.0)),
+                slider(
+                    10.0..=1000.0,
+                    self.config.preferences.terminal.auto_collapse_lines.unwrap_or(DEFAULT_AUTO_COLLAPSE_LINES) as f32,
+                    |lines| SettingsMessage::ConfigChanged(ConfigChange::AutoCollapseLines(Some(lines as usize)))
+                ),
+                text(self.config.preferences.terminal.auto_collapse_lines.map(|lines| lines.to_string()).unwrap_or_default()),
+            ].spacing(8),
+
+            checkbox(
+                tr("settings.terminal.notifications"),
+                self.config.preferences.terminal.notifications_enabled,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::NotificationsEnabled(enabled))
+            ),
+
+            row![
+                text(tr("settings.terminal.notify_after")).width(iced::Length::Fixed(150.0)),
+                slider(
+                    1.0..=600.0,
+                    self.config.preferences.terminal.notify_after_secs as f32,
+                    |secs| SettingsMessage::ConfigChanged(ConfigChange::NotifyAfterSecs(secs as u64))
+                ),
+                text(self.config.preferences.terminal.notify_after_secs.to_string()),
+            ].spacing(8),
+
+            checkbox(
+                tr("settings.terminal.use_pty"),
+                self.config.preferences.terminal.use_pty,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::UsePty(enabled))
+            ),
+
+            checkbox(
+                tr("settings.terminal.require_attach"),
+                self.config.preferences.terminal.require_attach,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::RequireAttach(enabled))
+            ),
+
+            checkbox(
+                tr("settings.terminal.shell_integration"),
+                self.config.preferences.terminal.shell_integration,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::ShellIntegration(enabled))
+            ),
+
+            checkbox(
+                tr("settings.terminal.browse_on_ls"),
+                self.config.preferences.terminal.browse_on_ls,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::BrowseOnLs(enabled))
+            ),
+
+            row![
+                text(tr("settings.terminal.hyperlinks")).width(iced::Length::Fixed(150.0)),
+                pick_list(
+                    &HyperlinkBehavior::ALL[..],
+                    Some(self.config.preferences.terminal.hyperlink_behavior),
+                    |behavior| SettingsMessage::ConfigChanged(ConfigChange::HyperlinkBehavior(behavior))
+                )
+            ].spacing(8),
+
+            row![
+                text(tr("settings.terminal.open_paths_in")).width(iced::Length::Fixed(150.0)),
+                pick_list(
+                    &PathLinkAction::ALL[..],
+                    Some(self.config.preferences.terminal.open_paths_in),
+                    |action| SettingsMessage::ConfigChanged(ConfigChange::OpenPathsIn(action))
+                )
+            ].spacing(8),
+
+            checkbox(
+                tr("settings.terminal.expand_variables"),
+                self.config.preferences.terminal.expand_variables,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::ExpandVariables(enabled))
+            ),
+            
             row![
-                text("Cursor Style:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.terminal.cursor_style")).width(iced::Length::Fixed(150.0)),
                 pick_list(
                     vec![CursorStyle::Block, CursorStyle::Underline, CursorStyle::Bar],
                     Some(self.config.preferences.terminal.cursor_style.clone()),
@@ -419,7 +1081,7 @@ impl SettingsView {
             ].spacing(8),
             
             checkbox(
-                "Cursor Blink",
+                tr("settings.terminal.cursor_blink"),
                 self.config.preferences.terminal.cursor_blink,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::CursorBlink(enabled))
             ),
@@ -430,48 +1092,54 @@ impl SettingsView {
 
     fn create_editor_settings(&self) -> Element<SettingsMessage> {
         column![
-            text("Editor Settings").size(20),
+            text(tr("settings.editor.title")).size(20),
             
             checkbox(
-                "Vim Mode",
+                tr("settings.editor.vim_mode"),
                 self.config.preferences.editor.vim_mode,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::VimMode(enabled))
             ),
             
             checkbox(
-                "Auto Suggestions",
+                tr("settings.editor.auto_suggestions"),
                 self.config.preferences.editor.auto_suggestions,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::AutoSuggestions(enabled))
             ),
             
             checkbox(
-                "Syntax Highlighting",
+                tr("settings.editor.ai_suggestions"),
+                self.config.preferences.editor.ai_suggestions,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::AiSuggestions(enabled))
+            ),
+            
+            checkbox(
+                tr("settings.editor.syntax_highlighting"),
                 self.config.preferences.editor.syntax_highlighting,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::SyntaxHighlighting(enabled))
             ),
             
             checkbox(
-                "Auto Completion",
+                tr("settings.editor.auto_completion"),
                 self.config.preferences.editor.auto_completion,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::AutoCompletion(enabled))
             ),
             
             row![
-                text("Indent Size:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.editor.indent_size")).width(iced::Length::Fixed(150.0)),
                 slider(1.0..=8.0, self.config.preferences.editor.indent_size as f32, |size| {
                     SettingsMessage::ConfigChanged(ConfigChange::IndentSize(size as usize))
                 })
             ].spacing(8),
             
             row![
-                text("Tab Width:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.editor.tab_width")).width(iced::Length::Fixed(150.0)),
                 slider(1.0..=8.0, self.config.preferences.editor.tab_width as f32, |width| {
                     SettingsMessage::ConfigChanged(ConfigChange::TabWidth(width as usize))
                 })
             ].spacing(8),
             
             checkbox(
-                "Insert Spaces",
+                tr("settings.editor.insert_spaces"),
                 self.config.preferences.editor.insert_spaces,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::InsertSpaces(enabled))
             ),
@@ -482,7 +1150,7 @@ impl SettingsView {
 
     fn create_keybinding_settings(&self) -> Element<SettingsMessage> {
         column![
-            text("Key Bindings").size(20),
+            text(tr("settings.keybindings.title")).size(20),
             self.keybinding_editor.view().map(SettingsMessage::KeyBindingEditor),
         ]
         .spacing(16)
@@ -491,29 +1159,29 @@ impl SettingsView {
 
     fn create_performance_settings(&self) -> Element<SettingsMessage> {
         column![
-            text("Performance Settings").size(20),
+            text(tr("settings.performance.title")).size(20),
             
             checkbox(
-                "GPU Acceleration",
+                tr("settings.performance.gpu"),
                 self.config.preferences.performance.gpu_acceleration,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::GpuAcceleration(enabled))
             ),
             
             checkbox(
-                "VSync",
+                tr("settings.performance.vsync"),
                 self.config.preferences.performance.vsync,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::Vsync(enabled))
             ),
             
             row![
-                text("Max FPS:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.performance.max_fps")).width(iced::Length::Fixed(150.0)),
                 slider(30.0..=144.0, self.config.preferences.performance.max_fps.unwrap_or(60) as f32, |fps| {
                     SettingsMessage::ConfigChanged(ConfigChange::MaxFps(Some(fps as u32)))
                 })
             ].spacing(8),
             
             row![
-                text("Memory Limit (MB):").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.performance.memory_limit")).width(iced::Length::Fixed(150.0)),
                 slider(256.0..=4096.0, self.config.preferences.performance.memory_limit.unwrap_or(1024) as f32, |mb| {
                     SettingsMessage::ConfigChanged(ConfigChange::MemoryLimit(Some(mb as usize)))
                 })
@@ -525,59 +1193,283 @@ impl SettingsView {
 
     fn create_privacy_settings(&self) -> Element<SettingsMessage> {
         column![
-            text("Privacy Settings").size(20),
+            text(tr("settings.privacy.title")).size(20),
             
             checkbox(
-                "Enable History",
+                tr("settings.privacy.history"),
                 self.config.preferences.privacy.history_enabled,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::HistoryEnabled(enabled))
             ),
             
             row![
-                text("History Limit:").width(iced::Length::Fixed(150.0)),
+                text(tr("settings.privacy.history_limit")).width(iced::Length::Fixed(150.0)),
                 slider(100.0..=50000.0, self.config.preferences.privacy.history_limit as f32, |limit| {
                     SettingsMessage::ConfigChanged(ConfigChange::HistoryLimit(limit as usize))
                 })
             ].spacing(8),
             
             checkbox(
-                "Clear History on Exit",
+                tr("settings.privacy.clear_on_exit"),
                 self.config.preferences.privacy.clear_history_on_exit,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::ClearHistoryOnExit(enabled))
             ),
             
             checkbox(
-                "Incognito Mode",
+                tr("settings.privacy.incognito"),
                 self.config.preferences.privacy.incognito_mode,
                 |enabled| SettingsMessage::ConfigChanged(ConfigChange::IncognitoMode(enabled))
             ),
+
+            checkbox(
+                tr("settings.privacy.share_host_identity"),
+                self.config.preferences.privacy.share_host_identity,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::ShareHostIdentity(enabled))
+            ),
+
+            self.create_clear_data_settings(),
+
+            self.create_crash_report_settings(),
+
+            self.create_network_settings(),
         ]
         .spacing(16)
         .into()
     }
 
-    fn create_plugin_settings(&self) -> Element<SettingsMessage> {
+    fn create_clear_data_settings(&self) -> Element<SettingsMessage> {
+        use crate::clear::ClearTarget;
+
         column![
-            text("Plugin Settings").size(20),
-            text("Plugin management coming soon..."),
+            text(tr("settings.clear.title")).size(16),
+            row![
+                button(tr("settings.clear.history")).on_press(SettingsMessage::Clear(ClearTarget::History)),
+                button(tr("settings.clear.blocks")).on_press(SettingsMessage::Clear(ClearTarget::Blocks)),
+                button(tr("settings.clear.conversations")).on_press(SettingsMessage::Clear(ClearTarget::Conversations)),
+                button(tr("settings.clear.caches")).on_press(SettingsMessage::Clear(ClearTarget::Caches)),
+                button(tr("settings.clear.plugins_data")).on_press(SettingsMessage::Clear(ClearTarget::PluginsData)),
+                button(tr("settings.clear.all")).on_press(SettingsMessage::Clear(ClearTarget::All)),
+            ]
+            .spacing(8),
+            text(tr("settings.clear.help")).size(12),
         ]
-        .spacing(16)
+        .spacing(8)
+        .into()
+    }
+
+    fn create_crash_report_settings(&self) -> Element<SettingsMessage> {
+        let crash_reports = &self.config.preferences.crash_reports;
+        column![
+            text(tr("settings.crash_reports.title")).size(16),
+            checkbox(
+                tr("settings.crash_reports.send"),
+                crash_reports.consent == crate::config::CrashReportConsent::Granted,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::CrashReportConsent(enabled))
+            ),
+            row![
+                text(tr("settings.crash_reports.dsn")).width(iced::Length::Fixed(150.0)),
+                text_input("https://key@sentry.example.com/1", crash_reports.dsn.as_deref().unwrap_or_default())
+                    .on_input(|dsn| SettingsMessage::ConfigChanged(ConfigChange::CrashReportDsn(dsn)))
+            ].spacing(8),
+            text(tr("settings.crash_reports.help")).size(12),
+        ]
+        .spacing(8)
         .into()
     }
 
+    fn create_network_settings(&self) -> Element<SettingsMessage> {
+        let network = &self.config.preferences.network;
+        let field = |label: &str, placeholder: &str, value: String, change: fn(String) -> ConfigChange| {
+            row![
+                text(label.to_string()).width(iced::Length::Fixed(150.0)),
+                text_input(placeholder, &value)
+                    .on_input(move |value| SettingsMessage::ConfigChanged(change(value)))
+            ]
+            .spacing(8)
+        };
+
+        let mut section = column![
+            text(tr("settings.network.title")).size(16),
+            text(tr("settings.network.help")).size(12),
+            field(tr("settings.network.http_proxy"), "http://proxy:3128", network.http_proxy.clone().unwrap_or_default(), ConfigChange::HttpProxy),
+            field(tr("settings.network.https_proxy"), "http://proxy:3128", network.https_proxy.clone().unwrap_or_default(), ConfigChange::HttpsProxy),
+            field(tr("settings.network.no_proxy"), "localhost, .corp.example", network.no_proxy.join(", "), ConfigChange::NoProxy),
+            field(
+                tr("settings.network.ca_bundle"),
+                "/etc/ssl/corp-ca.pem",
+                network.ca_bundle_path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(),
+                ConfigChange::CaBundlePath,
+            ),
+            checkbox(
+                tr("settings.network.verify_tls"),
+                network.verify_tls,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::VerifyTls(enabled))
+            ),
+        ]
+        .spacing(12);
+
+        if !network.verify_tls {
+            section = section.push(
+                text(tr("settings.network.tls_off"))
+                    .size(14)
+                    .style(iced::theme::Text::Color(iced::Color::from_rgb(0.8, 0.0, 0.0)))
+            );
+        }
+
+        section.into()
+    }
+
+    fn create_ai_settings(&self) -> Element<SettingsMessage> {
+        let ai = &self.config.preferences.ai;
+        // What the agent would use with these settings, filling in what's unset
+        let effective = AgentConfig::from_preferences(ai, |name| std::env::var(name).ok());
+        let models = self.model_choices(&effective.provider);
+
+        let mut section = column![
+            text(tr("settings.ai.title")).size(20),
+            row![
+                text(tr("settings.ai.provider")).width(iced::Length::Fixed(150.0)),
+                pick_list(
+                    ProviderChoice::all(),
+                    Some(ProviderChoice(ai.provider.clone())),
+                    |choice| SettingsMessage::ConfigChanged(ConfigChange::AiProvider(choice.0))
+                )
+            ].spacing(8),
+            row![
+                text(tr("settings.ai.model")).width(iced::Length::Fixed(150.0)),
+                pick_list(
+                    models,
+                    Some(effective.model.clone()),
+                    |model| SettingsMessage::ConfigChanged(ConfigChange::AiModel(model))
+                ),
+                button(tr("settings.ai.refresh_models")).on_press(SettingsMessage::RefreshModels),
+            ].spacing(8),
+            row![
+                text(tr("settings.ai.base_url")).width(iced::Length::Fixed(150.0)),
+                text_input(
+                    AgentConfig::get_default_base_url(&effective.provider).unwrap_or_default(),
+                    ai.base_url.as_deref().unwrap_or_default()
+                )
+                .on_input(|url| SettingsMessage::ConfigChanged(ConfigChange::AiBaseUrl(url)))
+            ].spacing(8),
+        ]
+        .spacing(16);
+
+        if let Some(Err(error)) = &self.discovered_models {
+            section = section.push(text(error).size(12).style(iced::theme::Text::Color(iced::Color::from_rgb(0.8, 0.0, 0.0))));
+        }
+
+        if let Some(variable) = availability::key_variable(&effective.provider) {
+            section = section.push(
+                column![
+                    row![
+                        text(tr("settings.ai.api_key")).width(iced::Length::Fixed(150.0)),
+                        text_input(variable, ai.api_key.as_deref().unwrap_or_default())
+                            .secure(true)
+                            .on_input(|key| SettingsMessage::ConfigChanged(ConfigChange::AiApiKey(key)))
+                    ].spacing(8),
+                    text(tr("settings.ai.api_key_help")).size(12),
+                ]
+                .spacing(4)
+            );
+        }
+
+        section = section
+            .push(row![
+                text(tr("settings.ai.temperature")).width(iced::Length::Fixed(150.0)),
+                slider(0.0..=2.0, ai.temperature, |value| {
+                    SettingsMessage::ConfigChanged(ConfigChange::AiTemperature(value))
+                })
+                .step(0.1),
+                text(format!("{:.1}", ai.temperature)).width(iced::Length::Fixed(40.0)),
+            ].spacing(8))
+            .push(checkbox(
+                tr("settings.ai.confirm_generated_commands"),
+                ai.confirm_generated_commands,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::ConfirmGeneratedCommands(enabled))
+            ))
+            .push(checkbox(
+                tr("settings.ai.natural_language_commands"),
+                ai.natural_language_commands,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::NaturalLanguageCommands(enabled))
+            ))
+            .push(checkbox(
+                tr("settings.ai.auto_fix_on_failure"),
+                ai.auto_fix_on_failure,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::AutoFixOnFailure(enabled))
+            ))
+            .push(checkbox(
+                tr("settings.ai.one_click_fixes"),
+                ai.one_click_fixes,
+                |enabled| SettingsMessage::ConfigChanged(ConfigChange::OneClickFixes(enabled))
+            ))
+            .push(row![
+                text(tr("settings.ai.tool_approval")).width(iced::Length::Fixed(150.0)),
+                pick_list(
+                    &ApprovalMode::ALL[..],
+                    Some(ai.tool_approval),
+                    |mode| SettingsMessage::ConfigChanged(ConfigChange::ToolApproval(mode))
+                ),
+            ].spacing(8));
+
+        if !effective.provider.supports_tools() {
+            section = section.push(text(tr("settings.ai.no_tools")).size(12));
+        }
+
+        section.into()
+    }
+
+    fn create_plugin_settings(&self) -> Element<SettingsMessage> {
+        let mut section = column![text(tr("settings.plugins.title")).size(20)].spacing(16);
+        if self.installed_plugins.is_empty() {
+            section = section.push(text(tr("settings.plugins.none")));
+        }
+
+        let plugins = &self.config.plugins;
+        for manifest in &self.installed_plugins {
+            let id = manifest.id.clone();
+            let mut title = row![
+                checkbox(
+                    format!("{} {}", manifest.name, manifest.version),
+                    plugins.enabled_plugins.contains(&manifest.id),
+                    move |enabled| SettingsMessage::ConfigChanged(ConfigChange::PluginEnabled(id.clone(), enabled))
+                ),
+            ]
+            .spacing(8);
+            if let Some(version) = self.plugin_updates.get(&manifest.id) {
+                title = title.push(
+                    container(text(format!("{} {}", tr("settings.plugins.update_available"), version)).size(12)).padding([2, 6]),
+                );
+            }
+            let mut plugin = column![title].spacing(6);
+            for &permission in &manifest.permissions {
+                let granted = plugins.granted_permissions.is_granted(&manifest.id, permission);
+                let mut line = row![text(permission.description()).size(12).width(iced::Length::Fill)].spacing(8);
+                if !granted {
+                    line = line.push(text(tr("settings.plugins.revoked")).size(12));
+                }
+                let label = if granted { tr("settings.plugins.revoke") } else { tr("settings.plugins.allow") };
+                let change = ConfigChange::PluginPermission(manifest.id.clone(), permission, !granted);
+                plugin = plugin.push(line.push(button(text(label).size(12)).on_press(SettingsMessage::ConfigChanged(change))));
+            }
+            section = section.push(plugin);
+        }
+
+        section.into()
+    }
+
     fn create_actions(&self) -> Element<SettingsMessage> {
         row![
-            button("Reset to Defaults")
+            button(tr("settings.actions.reset"))
                 .on_press(SettingsMessage::ResetToDefaults),
-            button("Import Config")
+            button(tr("settings.actions.import"))
                 .on_press(SettingsMessage::ImportConfig),
-            button("Export Config")
+            button(tr("settings.actions.export"))
                 .on_press(SettingsMessage::ExportConfig),
             // Spacer
             iced::widget::horizontal_space(iced::Length::Fill),
-            button("Cancel")
+            button(tr("settings.actions.cancel"))
                 .on_press(SettingsMessage::Cancel),
-            button("Save")
+            button(tr("settings.actions.save"))
                 .on_press(SettingsMessage::Save)
                 .style(if self.unsaved_changes {
                     button::primary
@@ -588,4 +1480,295 @@ impl SettingsView {
         .spacing(8)
         .into()
     }
+
+    fn create_compact_actions(&self) -> Element<SettingsMessage> {
+        column![
+            row![
+                button(tr("settings.actions.cancel")).on_press(SettingsMessage::Cancel),
+                button(tr("settings.actions.save"))
+                    .on_press(SettingsMessage::Save)
+                    .style(if self.unsaved_changes {
+                        button::primary
+                    } else {
+                        button::secondary
+                    }),
+            ]
+            .spacing(8),
+            row![
+                button(tr("settings.actions.reset_short")).on_press(SettingsMessage::ResetToDefaults),
+                button(tr("settings.actions.import_short")).on_press(SettingsMessage::ImportConfig),
+                button(tr("settings.actions.export_short")).on_press(SettingsMessage::ExportConfig),
+            ]
+            .spacing(8),
+        ]
+        .spacing(8)
+        .into()
+    }
+}
+
+impl std::fmt::Display for SettingsTab {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// An entry in the language picker; `None` follows the system locale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LanguageChoice(pub Option<Locale>);
+
+impl LanguageChoice {
+    fn all() -> Vec<LanguageChoice> {
+        std::iter::once(LanguageChoice(None))
+            .chain(Locale::ALL.iter().map(|locale| LanguageChoice(Some(*locale))))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for LanguageChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            None => f.write_str(tr("settings.general.language_system")),
+            Some(locale) => f.write_str(locale.native_name()),
+        }
+    }
+}
+
+/// An entry in the provider picker; `None` picks one from the environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderChoice(pub Option<AiProvider>);
+
+impl ProviderChoice {
+    fn all() -> Vec<ProviderChoice> {
+        std::iter::once(ProviderChoice(None))
+            .chain(AiProvider::ALL.iter().map(|provider| ProviderChoice(Some(provider.clone()))))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for ProviderChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            None => f.write_str(tr("settings.ai.provider_auto")),
+            Some(provider) => write!(f, "{}", provider),
+        }
+    }
+}
+
+impl std::fmt::Display for ApprovalMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(tr(match self {
+            ApprovalMode::Auto => "settings.ai.tool_approval.auto",
+            ApprovalMode::Ask => "settings.ai.tool_approval.ask",
+            ApprovalMode::DenyShell => "settings.ai.tool_approval.deny_shell",
+        }))
+    }
+}
+
+impl std::fmt::Display for TabBarVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(tr(match self {
+            TabBarVisibility::Always => "settings.appearance.tab_bar.always",
+            TabBarVisibility::Auto => "settings.appearance.tab_bar.auto",
+            TabBarVisibility::Never => "settings.appearance.tab_bar.never",
+        }))
+    }
+}
+
+impl std::fmt::Display for HyperlinkBehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(tr(match self {
+            HyperlinkBehavior::Click => "settings.terminal.hyperlinks.click",
+            HyperlinkBehavior::CtrlClick => "settings.terminal.hyperlinks.ctrl_click",
+            HyperlinkBehavior::Disabled => "settings.terminal.hyperlinks.disabled",
+        }))
+    }
+}
+
+impl std::fmt::Display for PathLinkAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(tr(match self {
+            PathLinkAction::Preview => "settings.terminal.open_paths_in.preview",
+            PathLinkAction::Editor => "settings.terminal.open_paths_in.editor",
+        }))
+    }
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_tab_renders() {
+        for tab in SettingsTab::ALL {
+            let view = SettingsView::new(AppConfig::default()).with_tab(tab.clone());
+            assert_eq!(view.active_tab, tab);
+            let _ = view.view(false);
+        }
+    }
+
+    #[test]
+    fn test_tab_navigation_wraps() {
+        assert_eq!(SettingsTab::General.previous(), SettingsTab::Plugins);
+        assert_eq!(SettingsTab::Plugins.next(), SettingsTab::General);
+
+        let mut view = SettingsView::new(AppConfig::default());
+        for expected in SettingsTab::ALL.iter().skip(1) {
+            view.update(SettingsMessage::NextTab);
+            assert_eq!(&view.active_tab, expected);
+        }
+        view.update(SettingsMessage::PreviousTab);
+        assert_eq!(view.active_tab, SettingsTab::Ai);
+    }
+
+    #[test]
+    fn test_revoking_a_plugin_permission() {
+        let mut config = AppConfig::default();
+        config.plugins.granted_permissions.grant("git-graph", [PluginPermission::Network, PluginPermission::RunCommands]);
+        let mut view = SettingsView::new(config);
+
+        let revoke = ConfigChange::PluginPermission("git-graph".to_string(), PluginPermission::RunCommands, false);
+        view.update(SettingsMessage::ConfigChanged(revoke));
+        let grants = &view.config.plugins.granted_permissions;
+        assert!(grants.check("git-graph", PluginPermission::Network).is_ok());
+        assert!(matches!(
+            grants.check("git-graph", PluginPermission::RunCommands),
+            Err(crate::plugin_api::PluginError::PermissionDenied(..))
+        ));
+        assert!(view.unsaved_changes);
+    }
+
+    #[test]
+    fn test_switching_provider_drops_its_model_and_key() {
+        let mut view = SettingsView::new(AppConfig::default()).with_tab(SettingsTab::Ai);
+        view.update(SettingsMessage::ConfigChanged(ConfigChange::AiProvider(Some(AiProvider::OpenAI))));
+        view.update(SettingsMessage::ConfigChanged(ConfigChange::AiModel("gpt-4".to_string())));
+        view.update(SettingsMessage::ConfigChanged(ConfigChange::AiApiKey("sk-test".to_string())));
+        view.update(SettingsMessage::ConfigChanged(ConfigChange::AiTemperature(0.34)));
+        let _ = view.view(false);
+
+        view.update(SettingsMessage::ConfigChanged(ConfigChange::AiProvider(Some(AiProvider::Groq))));
+        let ai = &view.config.preferences.ai;
+        assert_eq!(ai.provider, Some(AiProvider::Groq));
+        assert_eq!((ai.model.as_deref(), ai.api_key.as_deref()), (None, None));
+        assert_eq!(ai.temperature, 0.3);
+        assert!(view.unsaved_changes);
+    }
+
+    #[test]
+    fn test_discovered_ollama_models_replace_the_builtin_list() {
+        let mut view = SettingsView::new(AppConfig::default()).with_tab(SettingsTab::Ai);
+        view.update(SettingsMessage::ConfigChanged(ConfigChange::AiProvider(Some(AiProvider::Ollama))));
+        view.discovered_models = Some(Ok(vec!["llama3:8b".to_string()]));
+        assert_eq!(view.model_choices(&AiProvider::Ollama), vec!["llama3:8b"]);
+        assert!(view.model_choices(&AiProvider::OpenAI).contains(&"gpt-4o".to_string()));
+
+        view.update(SettingsMessage::ConfigChanged(ConfigChange::AiBaseUrl("http://gpu:11434".to_string())));
+        assert!(view.discovered_models.is_none());
+    }
+
+    #[test]
+    fn test_reset_only_selected_sections() {
+        let mut config = AppConfig::default();
+        config.keybindings.bindings.clear();
+        config.preferences.terminal.scrollback_lines = 123;
+        config.active_env_profile = Some("staging".to_string());
+        let mut view = SettingsView::new(config);
+
+        view.update(SettingsMessage::ResetToDefaults);
+        let selected = view.reset_dialog.clone().unwrap();
+        assert_eq!(
+            selected.into_iter().collect::<Vec<_>>(),
+            vec![ConfigSection::KeyBindings, ConfigSection::EnvProfile, ConfigSection::Terminal]
+        );
+        // Nothing changes until confirmed
+        assert!(!view.unsaved_changes);
+        let _ = view.view(false);
+
+        view.update(SettingsMessage::ResetSectionToggled(ConfigSection::EnvProfile, false));
+        view.update(SettingsMessage::ResetSectionToggled(ConfigSection::Terminal, false));
+        view.update(SettingsMessage::ConfirmReset);
+
+        assert!(view.reset_dialog.is_none());
+        assert!(view.unsaved_changes);
+        assert!(view.backup_before_save);
+        assert_eq!(changed_sections(&view.config), vec![ConfigSection::EnvProfile, ConfigSection::Terminal]);
+        assert_eq!(view.config.preferences.terminal.scrollback_lines, 123);
+        assert_eq!(view.config.active_env_profile.as_deref(), Some("staging"));
+    }
+
+    #[test]
+    fn test_cancel_reset_keeps_config() {
+        let mut config = AppConfig::default();
+        config.keybindings.bindings.clear();
+        let mut view = SettingsView::new(config);
+
+        view.update(SettingsMessage::ResetToDefaults);
+        view.update(SettingsMessage::CancelReset);
+        assert!(view.reset_dialog.is_none());
+        assert_eq!(changed_sections(&view.config), vec![ConfigSection::KeyBindings]);
+        assert!(!view.backup_before_save);
+    }
+
+    /// Literals that are examples or technical values, not prose
+    const UNTRANSLATED: &[&str] = &[
+        "https://key@sentry.example.com/1",
+        "http://proxy:3128",
+        "localhost, .corp.example",
+        "/etc/ssl/corp-ca.pem",
+    ];
+
+    /// String literals in `source`, each with the code just before it
+    fn string_literals(source: &str) -> Vec<(&str, &str)> {
+        let mut literals = Vec::new();
+        let mut rest = source;
+        while let Some(open) = rest.find('"') {
+            let body = &rest[open + 1..];
+            let mut close = 0;
+            let mut escaped = false;
+            for (i, c) in body.char_indices() {
+                match c {
+                    '\\' if !escaped => escaped = true,
+                    '"' if !escaped => {
+                        close = i;
+                        break;
+                    }
+                    _ => escaped = false,
+                }
+            }
+            literals.push((&rest[..open], &body[..close]));
+            rest = &body[close + 1..];
+        }
+        literals
+    }
+
+    #[test]
+    fn test_settings_view_strings_come_from_the_catalog() {
+        let source: &'static str = include_str!("mod.rs");
+        let start = source.find("    pub fn view(&self, compact: bool)").unwrap();
+        let end = source.find("impl std::fmt::Display for SettingsTab").unwrap();
+
+        let untranslated: Vec<&str> = string_literals(&source[start..end])
+            .into_iter()
+            .filter(|(before, literal)| {
+                let is_key = before.ends_with("tr(") || before.ends_with("tr_args(");
+                let is_prose = literal.chars().any(char::is_alphabetic);
+                !is_key && is_prose && !UNTRANSLATED.contains(literal)
+            })
+            .map(|(_, literal)| literal)
+            .collect();
+        assert!(untranslated.is_empty(), "not routed through the catalog: {:?}", untranslated);
+
+        // And every key used resolves in every language
+        for (before, key) in string_literals(&source[start..end]) {
+            if before.ends_with("tr(") {
+                for locale in Locale::ALL {
+                    assert_ne!(locale.tr(key), key, "{} has no {} string", key, locale.code());
+                }
+            }
+        }
+    }
 }
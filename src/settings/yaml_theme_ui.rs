@@ -12,6 +12,8 @@ pub struct YamlThemeUI {
     show_import_dialog: bool,
     show_export_dialog: bool,
     import_error: Option<String>,
+    /// What the last scheme import couldn't carry over
+    import_summary: Option<String>,
     search_query: String,
 }
 
@@ -46,6 +48,7 @@ impl YamlThemeUI {
             show_import_dialog: false,
             show_export_dialog: false,
             import_error: None,
+            import_summary: None,
             search_query: String::new(),
         })
     }
@@ -75,6 +78,35 @@ impl YamlThemeUI {
                     }
                 }
             }
+            Message::ImportFromFile => {
+                let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Color schemes", &["itermcolors", "toml", "yml", "yaml"])
+                    .pick_file()
+                else {
+                    return None;
+                };
+                match self.theme_manager.import_scheme_file(&path) {
+                    Ok(imported) => {
+                        let mut summary = Vec::new();
+                        if !imported.defaulted.is_empty() {
+                            summary.push(format!("Kept the default theme's colors for: {}", imported.defaulted.join(", ")));
+                        }
+                        if !imported.skipped.is_empty() {
+                            summary.push(format!("Skipped: {}", imported.skipped.join(", ")));
+                        }
+                        self.import_summary = (!summary.is_empty()).then(|| summary.join("\n"));
+                        self.import_error = None;
+                        self.refresh_metadata();
+                        let theme_name = imported.theme.name.unwrap_or_default();
+                        self.selected_theme = Some(theme_name.clone());
+                        self.theme_manager.get_theme(&theme_name)
+                    }
+                    Err(e) => {
+                        self.import_error = Some(format!("Import failed: {}", e));
+                        None
+                    }
+                }
+            }
             Message::ExportTheme(theme) => {
                 match self.theme_manager.export_theme_to_string(&theme) {
                     Ok(yaml_str) => {
@@ -128,6 +160,7 @@ impl YamlThemeUI {
             }
             Message::ClearError => {
                 self.import_error = None;
+                self.import_summary = None;
                 None
             }
             _ => None,
@@ -141,6 +174,7 @@ impl YamlThemeUI {
     pub fn view(&self) -> Element<Message> {
         let main_content = column![
             self.create_header(),
+            self.create_import_summary(),
             self.create_theme_list(),
             self.create_actions(),
         ]
@@ -167,12 +201,30 @@ impl YamlThemeUI {
                 .on_press(Message::RefreshThemes),
             button("Import")
                 .on_press(Message::ShowImportDialog(true)),
+            button("Import…")
+                .on_press(Message::ImportFromFile),
         ]
         .spacing(8)
         .align_items(iced::Alignment::Center)
         .into()
     }
 
+    fn create_import_summary(&self) -> Element<Message> {
+        match &self.import_summary {
+            Some(summary) => row![
+                text(summary).size(12),
+                iced::widget::horizontal_space(iced::Length::Fill),
+                button("Dismiss")
+                    .on_press(Message::ClearError)
+                    .style(button::secondary),
+            ]
+            .spacing(8)
+            .align_items(iced::Alignment::Center)
+            .into(),
+            None => iced::widget::Space::new(0, 0).into(),
+        }
+    }
+
     fn create_theme_list(&self) -> Element<Message> {
         let filtered_themes: Vec<_> = self.theme_metadata
             .iter()
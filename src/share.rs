@@ -0,0 +1,295 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::config::{SharePreferences, ShareTarget};
+use crate::redaction::Redactor;
+
+/// File name used for the uploaded markdown
+const SHARE_FILE_NAME: &str = "neoterm-block.md";
+
+/// Where a block was shared to, kept on the block so it can be unshared
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareRecord {
+    pub url: String,
+    /// Id used to delete the share, when the service supports it
+    pub remote_id: Option<String>,
+    pub target: ShareTarget,
+    pub shared_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ShareError {
+    #[error("No GitHub token: set {0} to a token with the 'gist' scope")]
+    MissingToken(String),
+    #[error("GitHub rejected the token ({0}); check that it is valid and has the 'gist' scope")]
+    Unauthorized(u16),
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Share service returned {0}: {1}")]
+    Server(u16, String),
+    #[error("Unexpected response from share service: {0}")]
+    InvalidResponse(String),
+    #[error("This share target doesn't support deleting shares")]
+    UnshareUnsupported,
+}
+
+/// The exact text that will be uploaded: the block's markdown with secrets masked
+pub fn prepare(markdown: &str, redactor: &Redactor) -> String {
+    redactor.redact(markdown)
+}
+
+/// Request body for `POST /gists`
+pub fn gist_payload(content: &str, description: &str, public: bool) -> Value {
+    json!({
+        "description": description,
+        "public": public,
+        "files": {
+            SHARE_FILE_NAME: { "content": content }
+        }
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct Sharer {
+    preferences: SharePreferences,
+    gist_token: Option<String>,
+}
+
+impl Sharer {
+    pub fn new(preferences: SharePreferences) -> Self {
+        let gist_token = std::env::var(&preferences.gist_token_env).ok().filter(|t| !t.is_empty());
+        Self { preferences, gist_token }
+    }
+
+    pub fn with_gist_token(mut self, token: Option<String>) -> Self {
+        self.gist_token = token;
+        self
+    }
+
+    pub fn target(&self) -> &ShareTarget {
+        &self.preferences.target
+    }
+
+    /// Upload already-prepared content and return where it can be found
+    pub async fn share(&self, content: &str, description: &str) -> Result<ShareRecord, ShareError> {
+        let client = crate::net::client(Some(std::time::Duration::from_secs(30)))
+            .map_err(|e| ShareError::Network(e.to_string()))?;
+
+        let (url, remote_id) = match &self.preferences.target {
+            ShareTarget::Gist { public } => {
+                let token = self.token()?;
+                let response = client
+                    .post(format!("{}/gists", self.api_url()))
+                    .bearer_auth(token)
+                    .header("Accept", "application/vnd.github+json")
+                    .header("User-Agent", "neoterm")
+                    .json(&gist_payload(content, description, *public))
+                    .send()
+                    .await
+                    .map_err(network_error)?;
+                let body = checked_json(response).await?;
+                (string_field(&body, "html_url")?, Some(string_field(&body, "id")?))
+            }
+            ShareTarget::Post { url } => {
+                let response = client
+                    .post(url)
+                    .header("Content-Type", "text/markdown; charset=utf-8")
+                    .body(content.to_string())
+                    .send()
+                    .await
+                    .map_err(network_error)?;
+                let body = checked_text(response).await?;
+                // Accept either a bare URL or JSON with a `url` field
+                let url = serde_json::from_str::<Value>(&body)
+                    .ok()
+                    .and_then(|v| v["url"].as_str().map(str::to_string))
+                    .unwrap_or_else(|| body.trim().to_string());
+                if !url.starts_with("http") {
+                    return Err(ShareError::InvalidResponse(body));
+                }
+                (url, None)
+            }
+            ShareTarget::SelfHosted { base_url } => {
+                let response = client
+                    .post(format!("{}/shares", base_url.trim_end_matches('/')))
+                    .json(&json!({ "description": description, "content": content }))
+                    .send()
+                    .await
+                    .map_err(network_error)?;
+                let body = checked_json(response).await?;
+                (string_field(&body, "url")?, Some(string_field(&body, "id")?))
+            }
+        };
+
+        Ok(ShareRecord {
+            url,
+            remote_id,
+            target: self.preferences.target.clone(),
+            shared_at: Utc::now(),
+        })
+    }
+
+    /// Delete a share made with `share`
+    pub async fn unshare(&self, record: &ShareRecord) -> Result<(), ShareError> {
+        let remote_id = record.remote_id.as_ref().ok_or(ShareError::UnshareUnsupported)?;
+        let client = crate::net::client(Some(std::time::Duration::from_secs(30)))
+            .map_err(|e| ShareError::Network(e.to_string()))?;
+
+        let request = match &record.target {
+            ShareTarget::Gist { .. } => client
+                .delete(format!("{}/gists/{}", self.api_url(), remote_id))
+                .bearer_auth(self.token()?)
+                .header("Accept", "application/vnd.github+json")
+                .header("User-Agent", "neoterm"),
+            ShareTarget::SelfHosted { base_url } => {
+                client.delete(format!("{}/shares/{}", base_url.trim_end_matches('/'), remote_id))
+            }
+            ShareTarget::Post { .. } => return Err(ShareError::UnshareUnsupported),
+        };
+
+        checked_text(request.send().await.map_err(network_error)?).await.map(|_| ())
+    }
+
+    fn api_url(&self) -> &str {
+        self.preferences.github_api_url.trim_end_matches('/')
+    }
+
+    fn token(&self) -> Result<&str, ShareError> {
+        self.gist_token
+            .as_deref()
+            .ok_or_else(|| ShareError::MissingToken(self.preferences.gist_token_env.clone()))
+    }
+}
+
+fn network_error(error: reqwest::Error) -> ShareError {
+    ShareError::Network(crate::net::describe_error(&error))
+}
+
+async fn checked_text(response: reqwest::Response) -> Result<String, ShareError> {
+    let status = response.status().as_u16();
+    let body = response.text().await.map_err(network_error)?;
+    match status {
+        200..=299 => Ok(body),
+        401 | 403 => Err(ShareError::Unauthorized(status)),
+        _ => Err(ShareError::Server(status, body.chars().take(200).collect())),
+    }
+}
+
+async fn checked_json(response: reqwest::Response) -> Result<Value, ShareError> {
+    let body = checked_text(response).await?;
+    serde_json::from_str(&body).map_err(|_| ShareError::InvalidResponse(body))
+}
+
+fn string_field(body: &Value, field: &str) -> Result<String, ShareError> {
+    body[field]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| ShareError::InvalidResponse(format!("missing '{}'", field)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Fake GitHub API: answers one request and returns it (headers and body)
+    fn mock_github(status: &'static str, body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let length = text[..header_end]
+                        .lines()
+                        .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + length || read == 0 {
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status, body.len(), body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+        (url, handle)
+    }
+
+    fn gist_sharer(api_url: String) -> Sharer {
+        Sharer::new(SharePreferences {
+            target: ShareTarget::Gist { public: false },
+            gist_token_env: "NEOTERM_TEST_UNSET_TOKEN".to_string(),
+            github_api_url: api_url,
+        })
+        .with_gist_token(Some("ghp_testtoken".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_gist_upload_is_redacted_and_well_formed() {
+        let (api, server) = mock_github("201 Created", r#"{"id":"abc123","html_url":"https://gist.github.com/abc123"}"#);
+
+        let mut redactor = Redactor::new();
+        redactor.register_secret("hunter2-password");
+        let content = prepare("```sh\n$ login --password hunter2-password\nok\n```\n", &redactor);
+
+        let record = gist_sharer(api).share(&content, "login").await.unwrap();
+        assert_eq!(record.url, "https://gist.github.com/abc123");
+        assert_eq!(record.remote_id.as_deref(), Some("abc123"));
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /gists HTTP/1.1"));
+        assert!(request.to_lowercase().contains("authorization: bearer ghp_testtoken"));
+        assert!(!request.contains("hunter2-password"));
+
+        let body: Value = serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body, json!({
+            "description": "login",
+            "public": false,
+            "files": {
+                "neoterm-block.md": { "content": "```sh\n$ login --password [REDACTED]\nok\n```\n" }
+            }
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_bad_token_is_actionable() {
+        let (api, server) = mock_github("401 Unauthorized", r#"{"message":"Bad credentials"}"#);
+
+        let error = gist_sharer(api).share("hi", "x").await.unwrap_err();
+        server.join().unwrap();
+
+        assert!(matches!(error, ShareError::Unauthorized(401)));
+        assert!(error.to_string().contains("'gist' scope"));
+    }
+
+    #[tokio::test]
+    async fn test_unshare_deletes_the_gist() {
+        let (api, server) = mock_github("204 No Content", "");
+        let record = ShareRecord {
+            url: "https://gist.github.com/abc123".to_string(),
+            remote_id: Some("abc123".to_string()),
+            target: ShareTarget::Gist { public: false },
+            shared_at: Utc::now(),
+        };
+
+        gist_sharer(api).unshare(&record).await.unwrap();
+        assert!(server.join().unwrap().starts_with("DELETE /gists/abc123 HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_token_is_reported() {
+        let sharer = gist_sharer("http://127.0.0.1:9".to_string()).with_gist_token(None);
+        let error = sharer.share("hi", "x").await.unwrap_err();
+        assert!(error.to_string().contains("NEOTERM_TEST_UNSET_TOKEN"));
+    }
+}
@@ -2,12 +2,34 @@ use std::process::Stdio;
 use tokio::process::Command;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
 use uuid::Uuid;
+use crate::pty::PtyManager;
+use crate::read_only::ReadOnly;
+use crate::timeline::{OutputChunk, OutputStream};
 
 #[derive(Debug, Clone)]
 pub struct ShellManager {
     active_sessions: HashMap<Uuid, ShellSession>,
     default_shell: String,
+    profile_env: HashMap<String, String>,
+    read_only: ReadOnly,
+    /// Where commands run; `cd` and friends change it
+    cwd: PathBuf,
+    /// For `cd -`
+    previous_dir: Option<PathBuf>,
+    /// `pushd` and `popd`
+    dir_stack: Vec<PathBuf>,
+    /// Where the shell integration scripts are, once installed
+    integration_dir: Option<PathBuf>,
+}
+
+/// Progress of a streamed command
+#[derive(Debug, Clone)]
+pub enum CommandEvent {
+    Chunk(OutputChunk),
+    Exited(i32),
 }
 
 #[derive(Debug, Clone)]
@@ -22,13 +44,138 @@ impl ShellManager {
         Self {
             active_sessions: HashMap::new(),
             default_shell: Self::detect_shell(),
+            profile_env: HashMap::new(),
+            read_only: ReadOnly::new(),
+            cwd: std::env::current_dir().unwrap_or_default(),
+            previous_dir: None,
+            dir_stack: Vec::new(),
+            integration_dir: None,
         }
     }
 
-    pub async fn execute_command(&self, command: String) -> (String, i32) {
+    /// Share the read-only switch; while it is on nothing is spawned
+    pub fn set_read_only(&mut self, read_only: ReadOnly) {
+        self.read_only = read_only;
+    }
+
+    /// Set the variables of the active env profile (empty to clear)
+    pub fn set_profile_env(&mut self, variables: HashMap<String, String>) {
+        self.profile_env = variables;
+    }
+
+    /// Have interactive bash, zsh and fish shells started in a PTY mark
+    /// their prompts, using scripts written to `dir`
+    pub fn enable_shell_integration(&mut self, dir: PathBuf) -> std::io::Result<()> {
+        crate::shell_integration::install(&dir)?;
+        self.integration_dir = Some(dir);
+        Ok(())
+    }
+
+    pub fn profile_env(&self) -> &HashMap<String, String> {
+        &self.profile_env
+    }
+
+    /// Run commands with `shell` (`preferences.general.default_shell`), or
+    /// `$SHELL` when it isn't set
+    pub fn set_default_shell(&mut self, shell: Option<&str>) {
+        self.default_shell = shell
+            .map(str::trim)
+            .filter(|shell| !shell.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(Self::detect_shell);
+    }
+
+    /// The whole line goes to the shell, so quoting, pipes, redirects, globs
+    /// and `&&` chains behave as they would at a prompt
+    fn shell_command(&self, command: &str) -> Command {
         let mut cmd = Command::new(&self.default_shell);
-        cmd.arg("-c")
-           .arg(&command)
+        cmd.arg(command_flag(&self.default_shell)).arg(command).current_dir(&self.cwd);
+        cmd
+    }
+
+    /// `line` with variables expanded against the environment a command
+    /// would get: inherited, then the active profile, then `invocation_env`
+    pub fn expand(&self, line: &str, invocation_env: &[(String, String)]) -> String {
+        let env = EnvLayers {
+            inherited: std::env::vars().collect(),
+            profile: self.profile_env.clone(),
+            step: HashMap::new(),
+            invocation: invocation_env.iter().cloned().collect(),
+        }
+        .resolve();
+        expand_variables(line, |name| env.get(name).cloned())
+    }
+
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Name of the shell commands run in, e.g. `zsh`
+    pub fn shell_name(&self) -> &str {
+        Path::new(&self.default_shell)
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.default_shell)
+    }
+
+    /// Start from `dir`, e.g. the configured startup directory
+    pub fn set_cwd(&mut self, dir: PathBuf) {
+        self.cwd = dir;
+    }
+
+    /// Run `cd`, `pushd` or `popd` and return the new directory
+    pub fn change_directory(&mut self, command: &DirCommand) -> Result<PathBuf, DirError> {
+        let target = match command {
+            DirCommand::Cd(None) => home_dir().ok_or(DirError::NoHome)?,
+            DirCommand::Cd(Some(dir)) if dir == "-" => self.previous_dir.clone().ok_or(DirError::NoPreviousDirectory)?,
+            DirCommand::Cd(Some(dir)) => resolve_dir(&self.cwd, dir)?,
+            DirCommand::Pushd(Some(dir)) => {
+                let target = resolve_dir(&self.cwd, dir)?;
+                self.dir_stack.push(self.cwd.clone());
+                target
+            }
+            // Without an argument, swap with the top of the stack
+            DirCommand::Pushd(None) => {
+                let top = self.dir_stack.pop().ok_or(DirError::EmptyStack)?;
+                self.dir_stack.push(self.cwd.clone());
+                top
+            }
+            DirCommand::Popd => self.dir_stack.pop().ok_or(DirError::EmptyStack)?,
+        };
+        self.previous_dir = Some(std::mem::replace(&mut self.cwd, target.clone()));
+        Ok(target)
+    }
+
+    pub async fn execute_command(&self, command: String) -> (String, i32) {
+        self.execute_command_with_env(command, HashMap::new()).await
+    }
+
+    /// Execute a command with per-invocation environment overrides layered on
+    /// top of the active env profile and the inherited environment.
+    pub async fn execute_command_with_env(
+        &self,
+        command: String,
+        invocation_env: HashMap<String, String>,
+    ) -> (String, i32) {
+        if let Err(e) = self.read_only.check() {
+            return (e.to_string(), 126);
+        }
+        if command.trim().is_empty() {
+            return (String::new(), 0);
+        }
+
+        let env = EnvLayers {
+            inherited: std::env::vars().collect(),
+            profile: self.profile_env.clone(),
+            step: HashMap::new(),
+            invocation: invocation_env,
+        }
+        .resolve();
+
+        let mut cmd = self.shell_command(&command);
+        cmd.env_clear()
+           .envs(&env)
+           .env("PWD", &self.cwd)
            .stdout(Stdio::piped())
            .stderr(Stdio::piped());
 
@@ -77,15 +224,128 @@ impl ShellManager {
         }
     }
 
+    /// Execute a command, sending each line of stdout and stderr as it arrives
+    /// with its offset from the start, followed by the exit code.
+    pub fn execute_command_streaming(
+        &self,
+        command: String,
+        invocation_env: HashMap<String, String>,
+    ) -> tokio::sync::mpsc::Receiver<CommandEvent> {
+        if let Some(rx) = self.refuse(&command) {
+            return rx;
+        }
+        let (tx, rx) = tokio::sync::mpsc::channel(256);
+
+        let mut cmd = self.shell_command(&command);
+        cmd.env_clear()
+           .envs(self.command_env(invocation_env))
+           .stdout(Stdio::piped())
+           .stderr(Stdio::piped());
+
+        tokio::spawn(async move {
+            let started = std::time::Instant::now();
+            let mut child = match cmd.spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    let _ = tx.send(CommandEvent::Chunk(OutputChunk {
+                        offset_ms: 0,
+                        stream: OutputStream::Stderr,
+                        text: format!("Failed to execute command: {}\n", e),
+                    })).await;
+                    let _ = tx.send(CommandEvent::Exited(1)).await;
+                    return;
+                }
+            };
+
+            let forward = |reader: Box<dyn tokio::io::AsyncRead + Unpin + Send>, stream: OutputStream| {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let mut lines = BufReader::new(reader).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let chunk = OutputChunk {
+                            offset_ms: started.elapsed().as_millis() as u64,
+                            stream,
+                            text: format!("{}\n", line),
+                        };
+                        if tx.send(CommandEvent::Chunk(chunk)).await.is_err() {
+                            break;
+                        }
+                    }
+                })
+            };
+
+            let stdout = forward(Box::new(child.stdout.take().unwrap()), OutputStream::Stdout);
+            let stderr = forward(Box::new(child.stderr.take().unwrap()), OutputStream::Stderr);
+            let _ = tokio::join!(stdout, stderr);
+
+            let exit_code = child.wait().await.ok().and_then(|status| status.code()).unwrap_or(1);
+            let _ = tx.send(CommandEvent::Exited(exit_code)).await;
+        });
+
+        rx
+    }
+
+    /// Like `execute_command_streaming`, but in a terminal from `pty`, so the
+    /// command sees a TTY of the block list's size and follows its resizes
+    pub fn execute_command_in_pty(
+        &self,
+        pty: &PtyManager,
+        block_id: Uuid,
+        command: String,
+        invocation_env: HashMap<String, String>,
+    ) -> tokio::sync::mpsc::Receiver<CommandEvent> {
+        if let Some(rx) = self.refuse(&command) {
+            return rx;
+        }
+        let mut env = self.command_env(invocation_env);
+        if let Some(dir) = &self.integration_dir {
+            let integration = crate::shell_integration::environment(dir, &env);
+            env.extend(integration);
+        }
+        pty.spawn(block_id, &command, &self.default_shell, &[command_flag(&self.default_shell), &command], &self.cwd, &env)
+    }
+
+    /// The events for a command that doesn't run: read-only mode or a blank line
+    fn refuse(&self, command: &str) -> Option<tokio::sync::mpsc::Receiver<CommandEvent>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(2);
+        if let Err(e) = self.read_only.check() {
+            let _ = tx.try_send(CommandEvent::Chunk(OutputChunk {
+                offset_ms: 0,
+                stream: OutputStream::Stderr,
+                text: format!("{}\n", e),
+            }));
+            let _ = tx.try_send(CommandEvent::Exited(126));
+            return Some(rx);
+        }
+        if command.trim().is_empty() {
+            let _ = tx.try_send(CommandEvent::Exited(0));
+            return Some(rx);
+        }
+        None
+    }
+
+    /// The environment a streamed command runs with
+    fn command_env(&self, invocation_env: HashMap<String, String>) -> HashMap<String, String> {
+        let mut env = EnvLayers {
+            inherited: std::env::vars().collect(),
+            profile: self.profile_env.clone(),
+            step: HashMap::new(),
+            invocation: invocation_env,
+        }
+        .resolve();
+        env.insert("PWD".to_string(), self.cwd.to_string_lossy().into_owned());
+        env
+    }
+
     pub async fn execute_interactive_command(&mut self, command: String) -> tokio::sync::mpsc::Receiver<String> {
         let (tx, rx) = tokio::sync::mpsc::channel(100);
+        if self.read_only.check().is_err() {
+            return rx;
+        }
         
-        let shell = self.default_shell.clone();
+        let mut cmd = self.shell_command(&command);
         tokio::spawn(async move {
-            let mut cmd = Command::new(shell);
-            cmd.arg("-c")
-               .arg(command)
-               .stdout(Stdio::piped())
+            cmd.stdout(Stdio::piped())
                .stderr(Stdio::piped());
 
             if let Ok(mut child) = cmd.spawn() {
@@ -148,3 +408,440 @@ impl ShellSession {
         &self.working_dir
     }
 }
+
+#[derive(Error, Debug, PartialEq)]
+pub enum DirError {
+    #[error("no such directory: {0}")]
+    NotFound(PathBuf),
+    #[error("not a directory: {0}")]
+    NotADirectory(PathBuf),
+    #[error("no home directory")]
+    NoHome,
+    #[error("no previous directory")]
+    NoPreviousDirectory,
+    #[error("directory stack empty")]
+    EmptyStack,
+}
+
+/// `cd`, `pushd` and `popd`, which must change NeoTerm's own directory
+/// rather than a short-lived child's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirCommand {
+    /// `cd` alone goes home, `cd -` back to the previous directory
+    Cd(Option<String>),
+    Pushd(Option<String>),
+    Popd,
+}
+
+/// Whether `name` is run by the shell itself rather than looked up on PATH
+pub fn is_builtin(name: &str) -> bool {
+    const BUILTINS: &[&str] = &[
+        "cd", "pushd", "popd", "dirs", "export", "unset", "set", "alias", "unalias", "source", ".", "exec", "exit",
+        "eval", "type", "command", "builtin", "jobs", "fg", "bg", "wait", "read", "history", "ulimit", "umask",
+    ];
+    BUILTINS.contains(&name)
+}
+
+/// The directory command a line consists of, if it is one. Lines that also
+/// do other things (`cd src && make`) go to the shell as they are.
+pub fn parse_dir_command(line: &str) -> Option<DirCommand> {
+    let line = line.trim();
+    if line.contains([';', '&', '|', '<', '>', '$', '`', '(', ')', '\n']) {
+        return None;
+    }
+    let (name, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let argument = unquote(argument.trim());
+    let argument = (!argument.is_empty()).then_some(argument);
+    match name {
+        "cd" => Some(DirCommand::Cd(argument)),
+        "pushd" => Some(DirCommand::Pushd(argument)),
+        "popd" if argument.is_none() => Some(DirCommand::Popd),
+        _ => None,
+    }
+}
+
+/// One argument as the shell would read it: quotes removed, `\ ` unescaped
+fn unquote(argument: &str) -> String {
+    for quote in ['"', '\''] {
+        if let Some(inner) = argument.strip_prefix(quote).and_then(|rest| rest.strip_suffix(quote)) {
+            return inner.to_string();
+        }
+    }
+    argument.replace("\\ ", " ")
+}
+
+fn home_dir() -> Option<PathBuf> {
+    directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf())
+}
+
+/// Expand `$NAME`, `${NAME}` and `${NAME:-default}` the way a shell would
+/// for lines NeoTerm handles without one. Unset names expand to nothing,
+/// `$$` and `\$` give a literal `$`, and single-quoted text is left alone.
+pub fn expand_variables(line: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut expanded = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut single_quoted = false;
+
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '\'' => {
+                single_quoted = !single_quoted;
+                expanded.push(c);
+            }
+            '\\' if !single_quoted && rest.starts_with('$') => {
+                expanded.push('$');
+                rest = &rest[1..];
+            }
+            '$' if !single_quoted => {
+                if let Some(after) = rest.strip_prefix('$') {
+                    expanded.push('$');
+                    rest = after;
+                } else if let Some((inner, after)) = rest.strip_prefix('{').and_then(|braced| braced.split_once('}')) {
+                    let (name, default) = match inner.split_once(":-") {
+                        Some((name, default)) => (name, Some(default)),
+                        None => (inner, None),
+                    };
+                    if name.is_empty() || !name.chars().all(is_name) {
+                        // Not a form we know; keep it for whoever reads it next
+                        expanded.push_str(&format!("${{{}}}", inner));
+                    } else {
+                        let value = lookup(name).filter(|value| !value.is_empty() || default.is_none());
+                        expanded.push_str(&value.unwrap_or_else(|| default.unwrap_or_default().to_string()));
+                    }
+                    rest = after;
+                } else {
+                    let starts_name = rest.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+                    let len = if starts_name { rest.find(|c: char| !is_name(c)).unwrap_or(rest.len()) } else { 0 };
+                    if len == 0 {
+                        expanded.push('$');
+                    } else {
+                        expanded.push_str(&lookup(&rest[..len]).unwrap_or_default());
+                        rest = &rest[len..];
+                    }
+                }
+            }
+            c => expanded.push(c),
+        }
+    }
+    expanded
+}
+
+/// `dir` made absolute against `cwd`, with `~` expanded and `..` resolved
+pub fn resolve_dir(cwd: &Path, dir: &str) -> Result<PathBuf, DirError> {
+    let path = match dir.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            home_dir().ok_or(DirError::NoHome)?.join(rest.trim_start_matches('/'))
+        }
+        _ => cwd.join(dir),
+    };
+    let resolved = path.canonicalize().map_err(|_| DirError::NotFound(path.clone()))?;
+    if !resolved.is_dir() {
+        return Err(DirError::NotADirectory(path));
+    }
+    Ok(resolved)
+}
+
+/// The flag that makes `shell` run a command line: `/C` for cmd, `-Command`
+/// for PowerShell, `-c` for everything else
+fn command_flag(shell: &str) -> &'static str {
+    let name = std::path::Path::new(shell)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match name.as_str() {
+        "cmd" => "/C",
+        "powershell" | "pwsh" => "-Command",
+        _ => "-c",
+    }
+}
+
+/// The environment sources for a single command invocation.
+///
+/// Precedence, highest first:
+/// 1. `invocation` - `FOO=bar cmd` assignments typed in the input bar
+/// 2. `step` - the `env:` map of the workflow step being run
+/// 3. `profile` - the active env profile (or the one a workflow names)
+/// 4. `inherited` - the environment NeoTerm itself was started with
+#[derive(Debug, Clone, Default)]
+pub struct EnvLayers {
+    pub inherited: HashMap<String, String>,
+    pub profile: HashMap<String, String>,
+    pub step: HashMap<String, String>,
+    pub invocation: HashMap<String, String>,
+}
+
+impl EnvLayers {
+    /// Merge all layers into the final environment for the child process
+    pub fn resolve(&self) -> HashMap<String, String> {
+        let mut env = self.inherited.clone();
+        for layer in [&self.profile, &self.step, &self.invocation] {
+            for (key, value) in layer {
+                env.insert(key.clone(), value.clone());
+            }
+        }
+        env
+    }
+}
+
+/// Split leading `NAME=value` assignments off a command line.
+///
+/// Returns the assignments in order and the remaining command text. Values may
+/// be single- or double-quoted. A line consisting only of assignments is
+/// returned untouched so the shell can treat it as variable definitions.
+pub fn parse_env_prefix(input: &str) -> (Vec<(String, String)>, String) {
+    let mut assignments = Vec::new();
+    let mut rest = input.trim_start();
+
+    loop {
+        let Some((assignment, remaining)) = take_assignment(rest) else {
+            break;
+        };
+        let remaining = remaining.trim_start();
+        if remaining.is_empty() {
+            // Assignments with no command: let the shell handle the whole line
+            return (Vec::new(), input.to_string());
+        }
+        assignments.push(assignment);
+        rest = remaining;
+    }
+
+    (assignments, rest.to_string())
+}
+
+fn take_assignment(input: &str) -> Option<((String, String), &str)> {
+    let eq = input.find('=')?;
+    let name = &input[..eq];
+    let mut name_chars = name.chars();
+    let first = name_chars.next()?;
+    if !(first.is_ascii_alphabetic() || first == '_')
+        || !name_chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+
+    let mut value = String::new();
+    let mut chars = input[eq + 1..].char_indices();
+    let mut quote: Option<char> = None;
+    let mut end = input.len() - eq - 1;
+
+    while let Some((i, ch)) = chars.next() {
+        match (quote, ch) {
+            (None, '\'') | (None, '"') => quote = Some(ch),
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                if let Some((_, escaped)) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            (None, c) if c.is_whitespace() => {
+                end = i;
+                break;
+            }
+            (_, c) => value.push(c),
+        }
+    }
+
+    if quote.is_some() {
+        // Unterminated quote: not a well-formed assignment
+        return None;
+    }
+
+    Some(((name.to_string(), value), &input[eq + 1 + end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_env_precedence() {
+        let layers = EnvLayers {
+            inherited: map(&[("A", "inherited"), ("B", "inherited"), ("C", "inherited"), ("D", "inherited")]),
+            profile: map(&[("A", "profile"), ("B", "profile"), ("C", "profile")]),
+            step: map(&[("A", "step"), ("B", "step")]),
+            invocation: map(&[("A", "invocation")]),
+        };
+
+        let env = layers.resolve();
+        assert_eq!(env["A"], "invocation");
+        assert_eq!(env["B"], "step");
+        assert_eq!(env["C"], "profile");
+        assert_eq!(env["D"], "inherited");
+    }
+
+    #[test]
+    fn test_command_flag_per_shell() {
+        assert_eq!(command_flag("/bin/bash"), "-c");
+        assert_eq!(command_flag("/usr/local/bin/fish"), "-c");
+        assert_eq!(command_flag("C:\\Windows\\System32\\cmd.exe"), "/C");
+        assert_eq!(command_flag("pwsh"), "-Command");
+    }
+
+    #[cfg(unix)]
+    fn sh() -> ShellManager {
+        let mut manager = ShellManager::new();
+        manager.set_default_shell(Some("/bin/sh"));
+        manager
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_quoting_and_pipelines_reach_the_shell() {
+        let (output, code) = sh().execute_command("echo \"hello world\" | wc -w".to_string()).await;
+        assert_eq!((output.trim(), code), ("2", 0));
+
+        let (output, _) = sh().execute_command("printf '%s|' \"a b\" 'c  d' e\\ f".to_string()).await;
+        assert_eq!(output.trim(), "a b|c  d|e f|");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_env_expansion_and_chains() {
+        let env = map(&[("GREETING", "hi there")]);
+        let (output, _) = sh().execute_command_with_env("echo \"$GREETING\" && echo ${GREETING%% *}".to_string(), env).await;
+        assert_eq!(output, "hi there\nhi\n");
+
+        let (output, code) = sh().execute_command("false && echo skipped; true || echo skipped".to_string()).await;
+        assert_eq!((output.as_str(), code), ("", 0));
+        let (_, code) = sh().execute_command("true && false".to_string()).await;
+        assert_eq!(code, 1);
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        let env = map(&[("PROJECT", "neoterm"), ("EMPTY", "")]);
+        let expand = |line: &str| expand_variables(line, |name| env.get(name).cloned());
+
+        assert_eq!(expand("cd ~/src/$PROJECT/docs"), "cd ~/src/neoterm/docs");
+        assert_eq!(expand("echo ${PROJECT}_v2 $MISSING."), "echo neoterm_v2 .");
+        assert_eq!(expand("${MISSING:-fallback} ${EMPTY:-fallback} ${PROJECT:-fallback}"), "fallback fallback neoterm");
+        assert_eq!(expand("costs $$5 or \\$6, '$PROJECT' stays"), "costs $5 or $6, '$PROJECT' stays");
+        assert_eq!(expand("$ 1 $1 ${not valid} ${unterminated"), "$ 1 $1 ${not valid} ${unterminated");
+    }
+
+    #[test]
+    fn test_profile_variables_expand() {
+        let mut shell = ShellManager::new();
+        shell.set_profile_env(map(&[("NEOTERM_TEST_PROFILE_VAR", "staging")]));
+        assert_eq!(shell.expand("deploy $NEOTERM_TEST_PROFILE_VAR", &[]), "deploy staging");
+        let invocation = vec![("NEOTERM_TEST_PROFILE_VAR".to_string(), "prod".to_string())];
+        assert_eq!(shell.expand("deploy $NEOTERM_TEST_PROFILE_VAR", &invocation), "deploy prod");
+    }
+
+    #[test]
+    fn test_parse_dir_command() {
+        assert_eq!(parse_dir_command("cd"), Some(DirCommand::Cd(None)));
+        assert_eq!(parse_dir_command("  cd \"My Projects\" "), Some(DirCommand::Cd(Some("My Projects".to_string()))));
+        assert_eq!(parse_dir_command("cd My\\ Projects"), Some(DirCommand::Cd(Some("My Projects".to_string()))));
+        assert_eq!(parse_dir_command("pushd /tmp"), Some(DirCommand::Pushd(Some("/tmp".to_string()))));
+        assert_eq!(parse_dir_command("popd"), Some(DirCommand::Popd));
+        assert_eq!(parse_dir_command("cd src && make"), None);
+        assert_eq!(parse_dir_command("cdk deploy"), None);
+        assert_eq!(parse_dir_command("cd $PROJECT"), None);
+    }
+
+    #[test]
+    fn test_change_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let root_path = root.path().canonicalize().unwrap();
+        std::fs::create_dir(root_path.join("src")).unwrap();
+        std::fs::write(root_path.join("README.md"), "").unwrap();
+        let mut shell = ShellManager::new();
+        shell.set_cwd(root_path.clone());
+
+        let cd = |dir: &str| DirCommand::Cd(Some(dir.to_string()));
+        assert_eq!(shell.change_directory(&cd("src")), Ok(root_path.join("src")));
+        assert_eq!(shell.change_directory(&cd("..")), Ok(root_path.clone()));
+        assert_eq!(shell.change_directory(&cd("-")), Ok(root_path.join("src")));
+        assert_eq!(shell.change_directory(&cd("missing")), Err(DirError::NotFound(root_path.join("src/missing"))));
+        assert_eq!(
+            shell.change_directory(&cd("../README.md")),
+            Err(DirError::NotADirectory(root_path.join("src/../README.md")))
+        );
+        assert_eq!(shell.cwd(), root_path.join("src"));
+
+        assert_eq!(shell.change_directory(&DirCommand::Pushd(Some("..".to_string()))), Ok(root_path.clone()));
+        assert_eq!(shell.change_directory(&DirCommand::Popd), Ok(root_path.join("src")));
+        assert_eq!(shell.change_directory(&DirCommand::Popd), Err(DirError::EmptyStack));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_commands_run_in_the_tracked_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let mut shell = sh();
+        shell.set_cwd(root.path().canonicalize().unwrap());
+        let (output, _) = shell.execute_command("pwd -P".to_string()).await;
+        assert_eq!(output.trim(), root.path().canonicalize().unwrap().to_string_lossy());
+    }
+
+    #[cfg(unix)]
+    async fn pty_output(pty: &PtyManager, command: &str, resize_to: Option<crate::pty::TerminalSize>) -> String {
+        let mut rx = sh().execute_command_in_pty(pty, Uuid::new_v4(), command.to_string(), HashMap::new());
+        if let Some(size) = resize_to {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            pty.resize(size);
+        }
+        let mut output = String::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Chunk(chunk) => output.push_str(&chunk.text),
+                CommandEvent::Exited(_) => break,
+            }
+        }
+        output
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_commands_see_the_pty_size_and_its_resizes() {
+        use crate::pty::TerminalSize;
+        let pty = PtyManager::default();
+        pty.resize(TerminalSize { cols: 100, rows: 30 });
+        assert_eq!(pty_output(&pty, "tput cols; tput lines", None).await.split_whitespace().collect::<Vec<_>>(), ["100", "30"]);
+
+        let resized = pty_output(&pty, "sleep 0.5; tput cols", Some(TerminalSize { cols: 120, rows: 30 })).await;
+        assert_eq!(resized.trim(), "120");
+    }
+
+    #[tokio::test]
+    async fn test_blank_input_runs_nothing() {
+        assert_eq!(ShellManager::new().execute_command("   ".to_string()).await, (String::new(), 0));
+    }
+
+    #[test]
+    fn test_parse_env_prefix() {
+        let (env, command) = parse_env_prefix("FOO=bar BAZ=1 cargo test");
+        assert_eq!(env, vec![
+            ("FOO".to_string(), "bar".to_string()),
+            ("BAZ".to_string(), "1".to_string()),
+        ]);
+        assert_eq!(command, "cargo test");
+    }
+
+    #[test]
+    fn test_parse_env_prefix_quoted_values() {
+        let (env, command) = parse_env_prefix("MSG=\"hello world\" NAME='a b' echo $MSG");
+        assert_eq!(env[0].1, "hello world");
+        assert_eq!(env[1].1, "a b");
+        assert_eq!(command, "echo $MSG");
+    }
+
+    #[test]
+    fn test_parse_env_prefix_without_assignments() {
+        let (env, command) = parse_env_prefix("git commit -m \"FOO=bar\"");
+        assert!(env.is_empty());
+        assert_eq!(command, "git commit -m \"FOO=bar\"");
+
+        // Pure assignment lines are left to the shell
+        let (env, command) = parse_env_prefix("FOO=bar");
+        assert!(env.is_empty());
+        assert_eq!(command, "FOO=bar");
+    }
+}
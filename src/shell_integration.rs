@@ -0,0 +1,266 @@
+//! Shell integration: an interactive shell run inside a block marks its
+//! prompts with OSC 133 sequences, so the block can tell where each command
+//! it runs starts and ends.
+//!
+//! - `A`: a prompt starts
+//! - `B`: the prompt ends, and the typed command starts
+//! - `C`: the command is submitted, and its output starts
+//! - `D;<code>`: the command finished with that exit code
+//!
+//! The marks come from small scripts written to the cache directory, which
+//! bash, zsh and fish pick up through their environment: bash sources its
+//! script from `PROMPT_COMMAND`, zsh from a `ZDOTDIR` whose startup files
+//! load the user's own first, and fish from a `vendor_conf.d` directory on
+//! `XDG_DATA_DIRS`. A bashrc that replaces `PROMPT_COMMAND` outright turns
+//! the bash marks off. fish has no prompt variable to end with `B`, so its
+//! commands are listed without their text.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+const OSC_133: &str = "\x1b]133;";
+/// Longer than any mark; an unterminated "mark" past this is just output
+const MAX_MARK_LEN: usize = 32;
+
+const BASH: &str = r#"# NeoTerm shell integration, sourced from PROMPT_COMMAND before each prompt
+__neoterm_status=$?
+if [ -z "$__neoterm_integrated" ]; then
+    __neoterm_integrated=1
+    PS1="$PS1"'\[\e]133;B\a\]'
+    PS0='\[\e]133;C\a\]'"$PS0"
+else
+    printf '\033]133;D;%s\007' "$__neoterm_status"
+fi
+printf '\033]133;A\007'
+"#;
+
+const ZSHENV: &str = r#"# NeoTerm shell integration: load the user's .zshenv, keeping ZDOTDIR
+# pointed here until .zshrc
+__neoterm_zdotdir=$ZDOTDIR
+ZDOTDIR=${NEOTERM_USER_ZDOTDIR:-$HOME}
+[ -f "$ZDOTDIR/.zshenv" ] && . "$ZDOTDIR/.zshenv"
+ZDOTDIR=$__neoterm_zdotdir
+"#;
+
+const ZSHRC: &str = r#"# NeoTerm shell integration: load the user's .zshrc, then mark prompts
+ZDOTDIR=${NEOTERM_USER_ZDOTDIR:-$HOME}
+unset NEOTERM_USER_ZDOTDIR
+[ -f "$ZDOTDIR/.zshrc" ] && . "$ZDOTDIR/.zshrc"
+
+__neoterm_precmd() {
+    local neoterm_status=$?
+    [ -n "$__neoterm_ran" ] && printf '\033]133;D;%s\007' "$neoterm_status"
+    __neoterm_ran=
+    printf '\033]133;A\007'
+}
+__neoterm_preexec() {
+    __neoterm_ran=1
+    printf '\033]133;C\007'
+}
+autoload -Uz add-zsh-hook
+add-zsh-hook precmd __neoterm_precmd
+add-zsh-hook preexec __neoterm_preexec
+PS1="$PS1"$'%{\e]133;B\a%}'
+"#;
+
+const FISH: &str = r#"# NeoTerm shell integration
+function __neoterm_prompt --on-event fish_prompt
+    printf '\e]133;A\a'
+end
+function __neoterm_preexec --on-event fish_preexec
+    printf '\e]133;C\a'
+end
+function __neoterm_postexec --on-event fish_postexec
+    printf '\e]133;D;%s\a' $status
+end
+"#;
+
+/// Write the integration scripts into `dir`
+pub fn install(dir: &Path) -> std::io::Result<()> {
+    let files = [
+        ("neoterm.bash", BASH),
+        ("zsh/.zshenv", ZSHENV),
+        ("zsh/.zshrc", ZSHRC),
+        ("fish/vendor_conf.d/neoterm.fish", FISH),
+    ];
+    for (name, script) in files {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, script)?;
+    }
+    Ok(())
+}
+
+/// Variables that make shells started with `env` load the scripts in `dir`
+pub fn environment(dir: &Path, env: &HashMap<String, String>) -> Vec<(String, String)> {
+    let source_bash = format!(". \"{}\"", dir.join("neoterm.bash").display());
+    let prompt_command = match env.get("PROMPT_COMMAND").filter(|command| !command.is_empty()) {
+        Some(existing) => format!("{}; {}", source_bash, existing),
+        None => source_bash,
+    };
+    let data_dirs = env
+        .get("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .cloned()
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    let mut variables = vec![
+        ("NEOTERM_SHELL_INTEGRATION".to_string(), "1".to_string()),
+        ("PROMPT_COMMAND".to_string(), prompt_command),
+        ("ZDOTDIR".to_string(), dir.join("zsh").display().to_string()),
+        ("XDG_DATA_DIRS".to_string(), format!("{}:{}", dir.display(), data_dirs)),
+    ];
+    if let Some(user_zdotdir) = env.get("ZDOTDIR") {
+        variables.push(("NEOTERM_USER_ZDOTDIR".to_string(), user_zdotdir.clone()));
+    }
+    variables
+}
+
+/// Output with the marks taken out, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece {
+    Text(String),
+    /// A prompt starts on the current line
+    Prompt,
+    /// The command line typed at the last prompt
+    Command(String),
+    Exited(Option<i32>),
+}
+
+/// Finds the marks in a command's output as it streams in. A mark split
+/// across chunks is held back until the rest of it arrives.
+#[derive(Debug, Clone, Default)]
+pub struct PromptTracker {
+    pending: String,
+    /// What the shell has echoed since the prompt ended
+    typed: Option<String>,
+}
+
+impl PromptTracker {
+    pub fn feed(&mut self, text: &str) -> Vec<Piece> {
+        let input = std::mem::take(&mut self.pending) + text;
+        let mut pieces = Vec::new();
+        let mut rest = input.as_str();
+        loop {
+            let Some(start) = rest.find(OSC_133) else {
+                let keep = (1..OSC_133.len()).rev().find(|&len| rest.ends_with(&OSC_133[..len])).unwrap_or(0);
+                self.text(&rest[..rest.len() - keep], &mut pieces);
+                self.pending = rest[rest.len() - keep..].to_string();
+                break;
+            };
+            self.text(&rest[..start], &mut pieces);
+            let body = &rest[start + OSC_133.len()..];
+            let Some((end, terminator)) = find_terminator(body) else {
+                if rest.len() - start > MAX_MARK_LEN {
+                    self.text(&rest[start..], &mut pieces);
+                } else {
+                    self.pending = rest[start..].to_string();
+                }
+                break;
+            };
+            self.mark(&body[..end], &mut pieces);
+            rest = &body[end + terminator..];
+        }
+        pieces
+    }
+
+    fn text(&mut self, text: &str, pieces: &mut Vec<Piece>) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(typed) = &mut self.typed {
+            typed.push_str(text);
+        }
+        match pieces.last_mut() {
+            Some(Piece::Text(last)) => last.push_str(text),
+            _ => pieces.push(Piece::Text(text.to_string())),
+        }
+    }
+
+    fn mark(&mut self, body: &str, pieces: &mut Vec<Piece>) {
+        let mut fields = body.split(';');
+        match fields.next() {
+            Some("A") => {
+                self.typed = None;
+                pieces.push(Piece::Prompt);
+            }
+            Some("B") => self.typed = Some(String::new()),
+            Some("C") => {
+                if let Some(typed) = self.typed.take() {
+                    pieces.push(Piece::Command(command_line(&typed)));
+                }
+            }
+            Some("D") => pieces.push(Piece::Exited(fields.next().and_then(|code| code.parse().ok()))),
+            _ => {}
+        }
+    }
+}
+
+/// Index and length of the BEL or `ESC \` ending a mark
+fn find_terminator(body: &str) -> Option<(usize, usize)> {
+    let bel = body.find('\x07').map(|index| (index, 1));
+    let st = body.find("\x1b\\").map(|index| (index, 2));
+    match (bel, st) {
+        (Some(bel), Some(st)) => Some(bel.min(st)),
+        (found, None) | (None, found) => found,
+    }
+}
+
+/// The command as the shell echoed it, with styling, carriage returns and
+/// erased characters taken out
+fn command_line(echoed: &str) -> String {
+    let plain: String = crate::ansi::parse(echoed).into_iter().map(|span| span.text).collect();
+    let mut line = String::new();
+    for c in plain.chars() {
+        match c {
+            '\x08' => {
+                line.pop();
+            }
+            c if c.is_control() => {}
+            c => line.push(c),
+        }
+    }
+    line.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks_split_across_chunks() {
+        let mut tracker = PromptTracker::default();
+        let mut pieces = tracker.feed("done\n\x1b]133;D;2\x07\x1b]13");
+        pieces.extend(tracker.feed("3;A\x07$ \x1b]133;B\x1b\\ls -l\x08a\r\n\x1b]133;C\x07total 0\n"));
+        assert_eq!(pieces, vec![
+            Piece::Text("done\n".to_string()),
+            Piece::Exited(Some(2)),
+            Piece::Prompt,
+            Piece::Text("$ ls -l\x08a\r\n".to_string()),
+            Piece::Command("ls -a".to_string()),
+            Piece::Text("total 0\n".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_other_escapes_pass_through() {
+        let mut tracker = PromptTracker::default();
+        let text = "\x1b]0;title\x07\x1b[1mbold\x1b[0m \x1b]1337;x\x07";
+        assert_eq!(tracker.feed(text), vec![Piece::Text(text.to_string())]);
+    }
+
+    #[test]
+    fn test_environment_keeps_the_users_settings() {
+        let user = HashMap::from([
+            ("PROMPT_COMMAND".to_string(), "history -a".to_string()),
+            ("ZDOTDIR".to_string(), "/home/me/.config/zsh".to_string()),
+        ]);
+        let variables: HashMap<String, String> = environment(Path::new("/cache/shell"), &user).into_iter().collect();
+        assert_eq!(variables["PROMPT_COMMAND"], ". \"/cache/shell/neoterm.bash\"; history -a");
+        assert_eq!(variables["ZDOTDIR"], "/cache/shell/zsh");
+        assert_eq!(variables["NEOTERM_USER_ZDOTDIR"], "/home/me/.config/zsh");
+        assert_eq!(variables["XDG_DATA_DIRS"], "/cache/shell:/usr/local/share:/usr/share");
+    }
+}
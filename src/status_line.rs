@@ -0,0 +1,465 @@
+//! Single-row status bar: mode, location, environment, jobs and activity.
+//!
+//! The layout is plain text of an exact width so it can be drawn by any
+//! renderer and snapshot tested. Segments that don't fit are first shortened
+//! (ellipsized in the middle, keeping both ends of paths) and then dropped,
+//! lowest priority first.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::Widget;
+use crate::config::StatusLinePreferences;
+use crate::i18n::{format_number, tr, tr_args};
+
+const SEPARATOR: &str = " │ ";
+/// Shrinkable segments are not cut below this many characters; they are dropped instead
+const MIN_SEGMENT_WIDTH: usize = 8;
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+/// How long a transient message stays visible
+pub const MESSAGE_TTL: Duration = Duration::from_secs(5);
+/// Older messages are discarded beyond this many
+const MAX_MESSAGES: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Agent,
+    Incognito,
+    /// Input is sent to every pane
+    Broadcast,
+}
+
+impl Mode {
+    fn label(&self) -> &'static str {
+        match self {
+            Mode::Normal => tr("status.mode.normal"),
+            Mode::Agent => tr("status.mode.agent"),
+            Mode::Incognito => tr("status.mode.incognito"),
+            Mode::Broadcast => tr("status.mode.broadcast"),
+        }
+    }
+}
+
+/// Everything the status line shows, gathered by the application each frame
+#[derive(Debug, Clone, Default)]
+pub struct StatusContext {
+    pub mode: Mode,
+    pub cwd: String,
+    pub git_branch: Option<String>,
+    pub env_profile: Option<String>,
+    pub running: usize,
+    pub queued: usize,
+    pub syncing: bool,
+    pub ai_busy: bool,
+    pub idle: bool,
+    pub read_only: bool,
+    pub message: Option<String>,
+}
+
+impl StatusContext {
+    /// Whether a spinner is showing, so the caller knows to keep animating
+    pub fn is_animating(&self) -> bool {
+        self.syncing || self.ai_busy
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    text: String,
+    /// Higher survives longer when space runs out
+    priority: u8,
+    shrinkable: bool,
+    /// Columns allotted; below `text`'s length means ellipsized
+    width: usize,
+}
+
+impl Segment {
+    fn new(text: String, priority: u8, shrinkable: bool) -> Self {
+        let width = text.chars().count();
+        Self { text, priority, shrinkable, width }
+    }
+
+    fn display(&self) -> String {
+        ellipsize_middle(&self.text, self.width)
+    }
+}
+
+/// Lay the status line out in exactly `width` columns. `frame` advances the spinners.
+pub fn render(context: &StatusContext, prefs: &StatusLinePreferences, width: usize, frame: usize) -> String {
+    let (mut left, mut right) = segments(context, prefs, frame);
+    fit(&mut left, &mut right, width);
+
+    let left = join(&left);
+    let right = join(&right);
+    let gap = width.saturating_sub(left.chars().count() + right.chars().count());
+
+    let line: String = format!("{}{}{}", left, " ".repeat(gap), right).chars().take(width).collect();
+    let padding = width - line.chars().count();
+    line + &" ".repeat(padding)
+}
+
+fn segments(context: &StatusContext, prefs: &StatusLinePreferences, frame: usize) -> (Vec<Segment>, Vec<Segment>) {
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+
+    if prefs.show_mode {
+        left.push(Segment::new(context.mode.label().to_string(), 100, false));
+    }
+    if context.read_only {
+        left.push(Segment::new(tr("status.read_only").to_string(), 95, false));
+    }
+    if prefs.show_context && !context.cwd.is_empty() {
+        let location = match &context.git_branch {
+            Some(branch) => format!("{} ({})", context.cwd, branch),
+            None => context.cwd.clone(),
+        };
+        left.push(Segment::new(location, 60, true));
+    }
+    if prefs.show_env_profile {
+        if let Some(profile) = &context.env_profile {
+            left.push(Segment::new(format!("env:{}", profile), 40, true));
+        }
+    }
+    if prefs.show_messages {
+        if let Some(message) = &context.message {
+            left.push(Segment::new(message.clone(), 20, true));
+        }
+    }
+
+    if prefs.show_activity {
+        let spinner = SPINNER[frame % SPINNER.len()];
+        let mut activity = Vec::new();
+        if context.syncing {
+            activity.push(tr_args("status.sync", &[("spinner", &spinner)]));
+        }
+        if context.ai_busy {
+            activity.push(tr_args("status.ai", &[("spinner", &spinner)]));
+        }
+        if context.idle {
+            activity.push(tr("status.idle").to_string());
+        }
+        if !activity.is_empty() {
+            right.push(Segment::new(activity.join(" "), 50, false));
+        }
+    }
+    if prefs.show_tasks && (context.running > 0 || context.queued > 0) {
+        let mut tasks = tr_args("status.running", &[("count", &format_number(context.running as u64))]);
+        if context.queued > 0 {
+            tasks.push_str(&tr_args("status.queued", &[("count", &format_number(context.queued as u64))]));
+        }
+        right.push(Segment::new(tasks, 80, false));
+    }
+
+    (left, right)
+}
+
+fn joined_width(segments: &[Segment]) -> usize {
+    let separators = segments.len().saturating_sub(1) * SEPARATOR.chars().count();
+    segments.iter().map(|s| s.width).sum::<usize>() + separators
+}
+
+/// Columns used, keeping at least one space between the two sides
+fn total_width(left: &[Segment], right: &[Segment]) -> usize {
+    let gap = usize::from(!left.is_empty() && !right.is_empty());
+    joined_width(left) + joined_width(right) + gap
+}
+
+fn fit(left: &mut Vec<Segment>, right: &mut Vec<Segment>, width: usize) {
+    while total_width(left, right) > width {
+        // Take a column from the widest shrinkable segment, so long paths
+        // give way before short labels are cut
+        let widest = left
+            .iter_mut()
+            .chain(right.iter_mut())
+            .filter(|s| s.shrinkable && s.width > MIN_SEGMENT_WIDTH)
+            .max_by_key(|s| (s.width, std::cmp::Reverse(s.priority)));
+        if let Some(segment) = widest {
+            segment.width -= 1;
+            continue;
+        }
+
+        // Nothing left to shorten: drop the least important segment and
+        // give the rest their full width back
+        if left.len() + right.len() <= 1 {
+            break;
+        }
+        let Some(lowest) = left.iter().chain(right.iter()).map(|s| s.priority).min() else {
+            break;
+        };
+        left.retain(|s| s.priority != lowest);
+        right.retain(|s| s.priority != lowest);
+        for segment in left.iter_mut().chain(right.iter_mut()) {
+            segment.width = segment.text.chars().count();
+        }
+    }
+}
+
+fn join(segments: &[Segment]) -> String {
+    segments.iter().map(Segment::display).collect::<Vec<_>>().join(SEPARATOR)
+}
+
+/// Shorten to `max` characters by replacing the middle with `…`
+pub fn ellipsize_middle(text: &str, max: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let head = (max - 1) / 2;
+    let tail = max - 1 - head;
+    let mut shortened: String = chars[..head].iter().collect();
+    shortened.push('…');
+    shortened.extend(&chars[chars.len() - tail..]);
+    shortened
+}
+
+/// Ratatui widget drawing the status line on the first row of its area
+pub struct StatusBar<'a> {
+    pub context: &'a StatusContext,
+    pub prefs: &'a StatusLinePreferences,
+    pub frame: usize,
+}
+
+impl Widget for StatusBar<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height == 0 || !self.prefs.visible {
+            return;
+        }
+        let line = render(self.context, self.prefs, area.width as usize, self.frame);
+        buf.set_stringn(area.x, area.y, line, area.width as usize, Style::default().add_modifier(Modifier::REVERSED));
+    }
+}
+
+/// Short-lived notices shown in the status line, newest first
+#[derive(Debug, Clone)]
+pub struct StatusMessages {
+    messages: VecDeque<(String, Instant)>,
+    ttl: Duration,
+}
+
+impl StatusMessages {
+    pub fn new(ttl: Duration) -> Self {
+        Self { messages: VecDeque::new(), ttl }
+    }
+
+    pub fn push(&mut self, text: impl Into<String>, now: Instant) {
+        self.messages.push_front((text.into(), now + self.ttl));
+        self.messages.truncate(MAX_MESSAGES);
+    }
+
+    /// Newest message that hasn't timed out
+    pub fn current(&self, now: Instant) -> Option<&str> {
+        self.messages
+            .iter()
+            .find(|(_, expires)| *expires > now)
+            .map(|(text, _)| text.as_str())
+    }
+
+    /// Forget timed-out messages. Returns true if any were removed.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = self.messages.len();
+        self.messages.retain(|(_, expires)| *expires > now);
+        self.messages.len() != before
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+impl Default for StatusMessages {
+    fn default() -> Self {
+        Self::new(MESSAGE_TTL)
+    }
+}
+
+/// Git directory of the repository containing `dir`
+pub fn git_dir(dir: &Path) -> Option<std::path::PathBuf> {
+    for ancestor in dir.ancestors() {
+        let dot_git = ancestor.join(".git");
+        if dot_git.is_dir() {
+            return Some(dot_git);
+        } else if dot_git.is_file() {
+            // Worktrees and submodules point at the real git directory
+            let pointer = std::fs::read_to_string(&dot_git).ok()?;
+            return Some(ancestor.join(pointer.trim().strip_prefix("gitdir:")?.trim()));
+        }
+    }
+    None
+}
+
+/// Branch checked out in the repository containing `dir`, or the short
+/// commit id when detached. Reads `.git/HEAD` directly; no git process.
+pub fn git_branch(dir: &Path) -> Option<String> {
+    let head = std::fs::read_to_string(git_dir(dir)?.join("HEAD")).ok()?;
+    let head = head.trim();
+    Some(match head.strip_prefix("ref: ") {
+        Some(reference) => reference.strip_prefix("refs/heads/").unwrap_or(reference).to_string(),
+        None => head.chars().take(7).collect(),
+    })
+}
+
+/// `cwd` with the home directory shown as `~`
+pub fn display_path(path: &Path) -> String {
+    let home = std::env::var_os("HOME").map(std::path::PathBuf::from);
+    match home.and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+        Some(relative) => format!("~/{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// `display_path` with every directory but the last cut to its first
+/// letter, as shells show it in prompts: `~/p/neoterm`
+pub fn short_path(path: &Path) -> String {
+    let full = display_path(path);
+    let Some((parents, last)) = full.rsplit_once('/') else {
+        return full;
+    };
+    let mut short: Vec<String> = parents
+        .split('/')
+        .map(|part| {
+            // Hidden directories keep the letter after the dot
+            let take = if part.starts_with('.') { 2 } else { 1 };
+            part.chars().take(take).collect()
+        })
+        .collect();
+    short.push(last.to_string());
+    short.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_short_path() {
+        assert_eq!(short_path(Path::new("/usr/local/bin")), "/u/l/bin");
+        assert_eq!(short_path(Path::new("/srv/.config/neoterm")), "/s/.c/neoterm");
+        assert_eq!(short_path(Path::new("/")), "/");
+        if let Some(home) = std::env::var_os("HOME").map(std::path::PathBuf::from) {
+            assert_eq!(short_path(&home.join("projects").join("neoterm")), "~/p/neoterm");
+            assert_eq!(short_path(&home), "~");
+        }
+    }
+
+    fn busy_context() -> StatusContext {
+        StatusContext {
+            mode: Mode::Agent,
+            cwd: "~/projects/neoterm/crates/terminal-core".to_string(),
+            git_branch: Some("feature/status-line".to_string()),
+            env_profile: Some("staging".to_string()),
+            running: 2,
+            queued: 1,
+            ai_busy: true,
+            message: Some("Share link copied to clipboard".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_snapshots_at_narrowing_widths() {
+        let prefs = StatusLinePreferences::default();
+        let context = busy_context();
+
+        assert_eq!(
+            render(&context, &prefs, 120, 0),
+            "AGENT │ ~/projects/neoterm…eature/status-line) │ env:staging │ Share link copied to clipboard ⠋ AI │ 2 running, 1 queued"
+        );
+        assert_eq!(
+            render(&context, &prefs, 80, 0),
+            "AGENT │ ~/proj…s-line) │ env:staging │ Share …ipboard ⠋ AI │ 2 running, 1 queued"
+        );
+        assert_eq!(
+            render(&context, &prefs, 60, 0),
+            "AGENT │ ~/pro…line) │ env:staging ⠋ AI │ 2 running, 1 queued"
+        );
+    }
+
+    #[test]
+    fn test_always_exactly_one_row_of_the_requested_width() {
+        let prefs = StatusLinePreferences::default();
+        let context = busy_context();
+
+        for width in 0..200 {
+            let line = render(&context, &prefs, width, 3);
+            assert_eq!(line.chars().count(), width, "width {}: {:?}", width, line);
+            assert!(!line.contains('\n'));
+        }
+    }
+
+    #[test]
+    fn test_segments_can_be_turned_off() {
+        let prefs = StatusLinePreferences {
+            show_context: false,
+            show_tasks: false,
+            show_messages: false,
+            ..Default::default()
+        };
+        let context = StatusContext { idle: true, ..busy_context() };
+
+        assert_eq!(
+            render(&context, &prefs, 60, 1),
+            format!("AGENT │ env:staging{}⠙ AI idle", " ".repeat(32))
+        );
+    }
+
+    #[test]
+    fn test_widget_fills_one_row() {
+        let prefs = StatusLinePreferences::default();
+        let context = busy_context();
+        let area = Rect::new(0, 0, 60, 3);
+        let mut buffer = Buffer::empty(area);
+
+        StatusBar { context: &context, prefs: &prefs, frame: 0 }.render(area, &mut buffer);
+
+        let first_row: String = (0..60).map(|x| buffer.get(x, 0).symbol().to_string()).collect();
+        assert_eq!(first_row, render(&context, &prefs, 60, 0));
+        assert_eq!(buffer.get(0, 1).symbol(), " ");
+    }
+
+    #[test]
+    fn test_read_only_outlasts_other_segments() {
+        let prefs = StatusLinePreferences::default();
+        let context = StatusContext { read_only: true, ..busy_context() };
+
+        assert!(render(&context, &prefs, 120, 0).starts_with("AGENT │ 🔒 read-only │ ~/projects"));
+        assert!(render(&context, &prefs, 40, 0).starts_with("AGENT │ 🔒 read-only"));
+    }
+
+    #[test]
+    fn test_messages_time_out() {
+        let start = Instant::now();
+        let mut messages = StatusMessages::new(Duration::from_secs(5));
+        messages.push("first", start);
+        messages.push("second", start + Duration::from_secs(3));
+
+        assert_eq!(messages.current(start + Duration::from_secs(4)), Some("second"));
+        assert!(messages.expire(start + Duration::from_secs(6)));
+        assert_eq!(messages.current(start + Duration::from_secs(6)), Some("second"));
+        assert_eq!(messages.current(start + Duration::from_secs(9)), None);
+    }
+
+    #[test]
+    fn test_git_branch_from_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let git_dir = temp_dir.path().join(".git");
+        std::fs::create_dir(&git_dir).unwrap();
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        let nested = temp_dir.path().join("src/bin");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(git_branch(&nested).as_deref(), Some("main"));
+
+        std::fs::write(git_dir.join("HEAD"), "0123456789abcdef\n").unwrap();
+        assert_eq!(git_branch(temp_dir.path()).as_deref(), Some("0123456"));
+    }
+}
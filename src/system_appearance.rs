@@ -0,0 +1,123 @@
+//! Whether the operating system is in light or dark mode, for
+//! `appearance.follow_system`.
+//!
+//! Each platform is asked through its own tool: the XDG desktop portal (with
+//! GNOME's setting as a fallback) on Linux, the global `AppleInterfaceStyle`
+//! default on macOS and the `AppsUseLightTheme` registry value on Windows.
+//! The app asks again every `CHECK_INTERVAL` while the preference is on.
+
+use std::process::Command;
+use std::time::Duration;
+
+/// How often the mode is read again, so the theme follows changes made
+/// while the app runs
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemAppearance {
+    Light,
+    Dark,
+}
+
+/// The current mode, or `None` when the system doesn't say or has no
+/// preference
+pub fn detect() -> Option<SystemAppearance> {
+    if cfg!(target_os = "macos") {
+        // The key only exists in dark mode
+        let output = Command::new("defaults").args(["read", "-g", "AppleInterfaceStyle"]).output().ok()?;
+        return Some(parse_macos(&String::from_utf8_lossy(&output.stdout)));
+    }
+    if cfg!(target_os = "windows") {
+        let output = Command::new("reg")
+            .args([
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ])
+            .output()
+            .ok()?;
+        return parse_windows(&String::from_utf8_lossy(&output.stdout));
+    }
+    let portal = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.portal.Desktop",
+            "--object-path",
+            "/org/freedesktop/portal/desktop",
+            "--method",
+            "org.freedesktop.portal.Settings.Read",
+            "org.freedesktop.appearance",
+            "color-scheme",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| parse_portal(&String::from_utf8_lossy(&output.stdout)));
+    portal.or_else(|| {
+        let output = Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+            .output()
+            .ok()?;
+        parse_gsettings(&String::from_utf8_lossy(&output.stdout))
+    })
+}
+
+fn parse_macos(output: &str) -> SystemAppearance {
+    if output.trim().eq_ignore_ascii_case("dark") {
+        SystemAppearance::Dark
+    } else {
+        SystemAppearance::Light
+    }
+}
+
+/// `AppsUseLightTheme    REG_DWORD    0x0`
+fn parse_windows(output: &str) -> Option<SystemAppearance> {
+    let line = output.lines().find(|line| line.contains("AppsUseLightTheme"))?;
+    let value = line.split_whitespace().last()?.trim_start_matches("0x");
+    match u32::from_str_radix(value, 16).ok()? {
+        0 => Some(SystemAppearance::Dark),
+        _ => Some(SystemAppearance::Light),
+    }
+}
+
+/// `(<<uint32 1>>,)`: 1 is dark, 2 light and 0 no preference
+fn parse_portal(output: &str) -> Option<SystemAppearance> {
+    let value = output.split("uint32").nth(1)?;
+    let digits: String = value.trim_start().chars().take_while(char::is_ascii_digit).collect();
+    match digits.parse::<u32>().ok()? {
+        1 => Some(SystemAppearance::Dark),
+        2 => Some(SystemAppearance::Light),
+        _ => None,
+    }
+}
+
+/// `'prefer-dark'`, `'prefer-light'` or `'default'`
+fn parse_gsettings(output: &str) -> Option<SystemAppearance> {
+    match output.trim().trim_matches('\'') {
+        "prefer-dark" => Some(SystemAppearance::Dark),
+        "prefer-light" => Some(SystemAppearance::Light),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_platform_output() {
+        assert_eq!(parse_portal("(<<uint32 1>>,)\n"), Some(SystemAppearance::Dark));
+        assert_eq!(parse_portal("(<uint32 2>,)\n"), Some(SystemAppearance::Light));
+        assert_eq!(parse_portal("(<<uint32 0>>,)\n"), None);
+        assert_eq!(parse_gsettings("'prefer-dark'\n"), Some(SystemAppearance::Dark));
+        assert_eq!(parse_gsettings("'default'\n"), None);
+        assert_eq!(parse_macos("Dark\n"), SystemAppearance::Dark);
+        assert_eq!(parse_macos(""), SystemAppearance::Light);
+        let reg = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize\r\n    AppsUseLightTheme    REG_DWORD    0x0\r\n";
+        assert_eq!(parse_windows(reg), Some(SystemAppearance::Dark));
+        assert_eq!(parse_windows(&reg.replace("0x0", "0x1")), Some(SystemAppearance::Light));
+    }
+}
@@ -0,0 +1,236 @@
+//! Tabs. Each owns its panes (with their blocks, input and directory), its
+//! env profile and its agent conversation.
+//!
+//! As with panes, the active tab's state is live in the app and the others
+//! are parked here until they're switched to.
+
+use crate::agent_mode_eval::conversation::ConversationTree;
+use crate::block::{Block, BlockContent, BlockStatus};
+use crate::layout::truncate;
+use crate::panes::{PaneId, PaneState, Panes};
+
+pub type TabId = u32;
+
+/// Longest title derived from a command
+const TITLE_CHARS: usize = 24;
+
+/// Where a block lives, so its command's output finds it after focus moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockOwner {
+    pub tab: TabId,
+    pub pane: PaneId,
+}
+
+/// What a tab keeps while another one is active
+#[derive(Debug, Clone, Default)]
+pub struct TabState {
+    pub panes: Panes<PaneState>,
+    /// The focused pane's
+    pub pane: PaneState,
+    pub env_profile: Option<String>,
+    pub conversation: Option<ConversationTree>,
+    pub agent_enabled: bool,
+}
+
+impl TabState {
+    /// One of the tab's panes, focused or not
+    pub fn pane_mut(&mut self, pane: PaneId) -> Option<&mut PaneState> {
+        if pane == self.panes.focused() {
+            Some(&mut self.pane)
+        } else {
+            self.panes.get_mut(pane)
+        }
+    }
+}
+
+/// Whether any of `blocks` is still running its command
+pub fn has_running(blocks: &[Block]) -> bool {
+    blocks.iter().any(|block| block.status() == Some(BlockStatus::Running))
+}
+
+/// A tab's name, or the last command run in it
+pub fn title(name: Option<&str>, blocks: &[Block]) -> String {
+    if let Some(name) = name {
+        return name.to_string();
+    }
+    blocks
+        .iter()
+        .rev()
+        .find_map(|block| match &block.content {
+            BlockContent::Command { input, .. } => Some(truncate(input.trim(), TITLE_CHARS)),
+            _ => None,
+        })
+        .unwrap_or_else(|| "New tab".to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct Tab<T> {
+    id: TabId,
+    /// Set by renaming; otherwise the title follows the last command
+    name: Option<String>,
+    /// `None` while it's the active tab
+    state: Option<T>,
+}
+
+impl<T> Tab<T> {
+    pub fn id(&self) -> TabId {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn state(&self) -> Option<&T> {
+        self.state.as_ref()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Tabs<T> {
+    tabs: Vec<Tab<T>>,
+    active: usize,
+    next_id: TabId,
+}
+
+impl<T> Default for Tabs<T> {
+    fn default() -> Self {
+        Self { tabs: vec![Tab { id: 0, name: None, state: None }], active: 0, next_id: 1 }
+    }
+}
+
+impl<T> Tabs<T> {
+    /// Saved tabs in order, each with its name. Only the active one has no
+    /// state; if that doesn't hold there's a single tab.
+    pub fn restore(tabs: Vec<(Option<String>, Option<T>)>, active: usize) -> Self {
+        let live = tabs.iter().filter(|(_, state)| state.is_none()).count();
+        if live != 1 || !tabs.get(active).is_some_and(|(_, state)| state.is_none()) {
+            return Self::default();
+        }
+        let next_id = tabs.len() as TabId;
+        let tabs = tabs
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, state))| Tab { id: index as TabId, name, state })
+            .collect();
+        Self { tabs, active, next_id }
+    }
+
+    pub fn count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Tab<T>> {
+        self.tabs.iter()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn active_id(&self) -> TabId {
+        self.tabs[self.active].id
+    }
+
+    pub fn active_name(&self) -> Option<&str> {
+        self.tabs[self.active].name()
+    }
+
+    /// A parked tab's state
+    pub fn get_mut(&mut self, id: TabId) -> Option<&mut T> {
+        self.tabs.iter_mut().find(|tab| tab.id == id)?.state.as_mut()
+    }
+
+    /// Add `fresh` after the active tab and switch to it
+    pub fn open(&mut self, current: &mut T, fresh: T) -> TabId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.tabs.insert(self.active + 1, Tab { id, name: None, state: Some(fresh) });
+        self.select(self.active + 1, current);
+        id
+    }
+
+    /// Park `current` and swap in the state of the tab at `index`
+    pub fn select(&mut self, index: usize, current: &mut T) -> bool {
+        if index == self.active {
+            return false;
+        }
+        let Some(state) = self.tabs.get_mut(index).and_then(|tab| tab.state.take()) else { return false };
+        self.tabs[self.active].state = Some(std::mem::replace(current, state));
+        self.active = index;
+        true
+    }
+
+    /// Close the active tab and switch to the one after it (or before, for
+    /// the last), returning the closed tab's state. The only tab isn't closed.
+    pub fn close(&mut self, current: &mut T) -> Option<T> {
+        if self.tabs.len() < 2 {
+            return None;
+        }
+        let next = if self.active + 1 < self.tabs.len() { self.active + 1 } else { self.active - 1 };
+        let state = self.tabs[next].state.take()?;
+        self.tabs.remove(self.active);
+        self.active = if next > self.active { next - 1 } else { next };
+        Some(std::mem::replace(current, state))
+    }
+
+    /// Name the tab at `index`; an empty name goes back to the derived title
+    pub fn rename(&mut self, index: usize, name: &str) {
+        if let Some(tab) = self.tabs.get_mut(index) {
+            tab.name = Some(name.trim().to_string()).filter(|name| !name.is_empty());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tabs_open_switch_and_close() {
+        let mut tabs: Tabs<&str> = Tabs::default();
+        let mut current = "first";
+        tabs.open(&mut current, "second");
+        tabs.open(&mut current, "third");
+        assert_eq!((tabs.active(), current), (2, "third"));
+
+        assert!(tabs.select(0, &mut current));
+        assert_eq!(current, "first");
+        assert!(!tabs.select(7, &mut current));
+        let inserted = tabs.open(&mut current, "after first");
+        assert_eq!((tabs.active(), tabs.active_id()), (1, inserted));
+
+        // Closing moves to the tab on the right, or the left at the end
+        assert_eq!(tabs.close(&mut current), Some("after first"));
+        assert_eq!((tabs.active(), current), (1, "second"));
+        tabs.select(2, &mut current);
+        assert_eq!(tabs.close(&mut current), Some("third"));
+        assert_eq!((tabs.active(), current), (1, "second"));
+        assert_eq!(tabs.close(&mut current), Some("second"));
+        assert_eq!(tabs.close(&mut current), None);
+        assert_eq!((tabs.count(), current), (1, "first"));
+    }
+
+    #[test]
+    fn test_title_is_the_rename_or_last_command() {
+        let mut tabs: Tabs<()> = Tabs::default();
+        assert_eq!(title(tabs.active_name(), &[]), "New tab");
+        let blocks = [Block::new_command("cargo build".to_string()), Block::new_command("  tail -f /var/log/syslog --lines 100  ".to_string())];
+        assert_eq!(title(None, &blocks), "tail -f /var/log/syslog…");
+
+        tabs.rename(0, " logs ");
+        assert_eq!(title(tabs.active_name(), &blocks), "logs");
+        tabs.rename(0, "");
+        assert_eq!(tabs.active_name(), None);
+    }
+
+    #[test]
+    fn test_restore_needs_exactly_the_active_tab_live() {
+        let restored = Tabs::restore(vec![(Some("logs".to_string()), Some("logs")), (None, None)], 1);
+        assert_eq!((restored.count(), restored.active()), (2, 1));
+        assert_eq!(restored.iter().next().unwrap().name(), Some("logs"));
+
+        assert_eq!(Tabs::restore(vec![(None, Some("a")), (None, None)], 0).count(), 1);
+        assert_eq!(Tabs::<&str>::restore(vec![(None, None), (None, None)], 0).count(), 1);
+    }
+}
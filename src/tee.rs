@@ -0,0 +1,260 @@
+//! Mirroring a block's output to a file while the command runs.
+//!
+//! A tee starts partway through a command: from then on every chunk is
+//! appended to the file, optionally preceded by the output that arrived
+//! before it was opened. ANSI escape sequences can be dropped on the way so
+//! the file reads as plain text; the stripper keeps its state between
+//! chunks, so a sequence split across reads is still removed whole.
+
+use chrono::{DateTime, Local};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use uuid::Uuid;
+use crate::timeline::OutputChunk;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+#[derive(Error, Debug)]
+pub enum TeeError {
+    #[error("IO error: {0}")]
+    IoError(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TeeOptions {
+    /// Drop escape sequences (colors, cursor movement, titles)
+    pub strip_ansi: bool,
+    /// Write the output that arrived before the tee was opened first
+    pub backfill: bool,
+}
+
+impl Default for TeeOptions {
+    fn default() -> Self {
+        Self { strip_ansi: true, backfill: true }
+    }
+}
+
+/// "Also write to file…" being filled in for a running block
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeePrompt {
+    pub block_id: Uuid,
+    /// As typed; relative paths are taken from the block's working directory
+    pub path: String,
+    pub options: TeeOptions,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum State {
+    #[default]
+    Ground,
+    /// After ESC
+    Escape,
+    /// Inside `ESC [`, up to a final byte in `@`..=`~`
+    Csi,
+    /// Inside `ESC ]`, up to BEL or `ESC \`
+    Osc,
+    /// ESC inside an OSC, possibly the start of `ESC \`
+    OscEscape,
+    /// After `ESC (` or `ESC )`, which take one more character
+    Charset,
+}
+
+/// Removes escape sequences from a stream of text
+#[derive(Debug, Clone, Default)]
+pub struct AnsiStripper {
+    state: State,
+}
+
+impl AnsiStripper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `text` without escape sequences, continuing from the previous chunk
+    pub fn strip(&mut self, text: &str) -> String {
+        let mut plain = String::with_capacity(text.len());
+        for c in text.chars() {
+            self.state = match (self.state, c) {
+                (State::Ground, ESC) => State::Escape,
+                (State::Ground, c) => {
+                    plain.push(c);
+                    State::Ground
+                }
+                (State::Escape, '[') => State::Csi,
+                (State::Escape, ']') => State::Osc,
+                (State::Escape, '(' | ')') => State::Charset,
+                (State::Escape, ESC) => State::Escape,
+                (State::Escape, _) => State::Ground,
+                (State::Csi, '@'..='~') => State::Ground,
+                (State::Csi, _) => State::Csi,
+                (State::Osc, BEL) => State::Ground,
+                (State::Osc, ESC) => State::OscEscape,
+                (State::Osc, _) => State::Osc,
+                (State::OscEscape, '\\' | BEL) => State::Ground,
+                (State::OscEscape, ESC) => State::OscEscape,
+                (State::OscEscape, _) => State::Osc,
+                (State::Charset, _) => State::Ground,
+            };
+        }
+        plain
+    }
+}
+
+/// Where a tee writes and how much it has written, for the block footer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TeeStatus {
+    pub path: PathBuf,
+    pub bytes_written: u64,
+}
+
+/// An open output file for one running block
+#[derive(Debug)]
+pub struct Tee {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    stripper: Option<AnsiStripper>,
+    bytes_written: u64,
+}
+
+impl Tee {
+    /// Create `path`, replacing any file there, and write `earlier` first
+    /// when `options.backfill` is set
+    pub fn open(path: &Path, options: TeeOptions, earlier: &[OutputChunk]) -> Result<Self, TeeError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| TeeError::IoError(e.to_string()))?;
+        }
+        let file = File::create(path).map_err(|e| TeeError::IoError(format!("{}: {}", path.display(), e)))?;
+        let mut tee = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            stripper: options.strip_ansi.then(AnsiStripper::new),
+            bytes_written: 0,
+        };
+        if options.backfill {
+            for chunk in earlier {
+                tee.write_chunk(chunk)?;
+            }
+        }
+        Ok(tee)
+    }
+
+    pub fn write_chunk(&mut self, chunk: &OutputChunk) -> Result<(), TeeError> {
+        self.write_text(&chunk.text)
+    }
+
+    pub fn write_text(&mut self, text: &str) -> Result<(), TeeError> {
+        let stripped;
+        let text = match &mut self.stripper {
+            Some(stripper) => {
+                stripped = stripper.strip(text);
+                stripped.as_str()
+            }
+            None => text,
+        };
+        self.writer.write_all(text.as_bytes()).map_err(|e| TeeError::IoError(e.to_string()))?;
+        self.bytes_written += text.len() as u64;
+        Ok(())
+    }
+
+    pub fn status(&self) -> TeeStatus {
+        TeeStatus { path: self.path.clone(), bytes_written: self.bytes_written }
+    }
+
+    /// Flush and close the file
+    pub fn close(mut self) -> Result<TeeStatus, TeeError> {
+        self.writer.flush().map_err(|e| TeeError::IoError(e.to_string()))?;
+        self.writer.get_ref().sync_all().map_err(|e| TeeError::IoError(e.to_string()))?;
+        Ok(self.status())
+    }
+}
+
+/// `<command>-YYYYmmdd-HHMMSS.log` in `dir`, named after the program run
+pub fn default_path(dir: &Path, command: &str, now: DateTime<Local>) -> PathBuf {
+    let program = command
+        .split_whitespace()
+        .find(|word| !word.contains('='))
+        .and_then(|word| word.rsplit('/').next())
+        .unwrap_or("");
+    let slug: String = program
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let slug = slug.trim_matches('-');
+    let slug = if slug.is_empty() { "output" } else { slug };
+    dir.join(format!("{}-{}.log", slug, now.format("%Y%m%d-%H%M%S")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::OutputStream;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn chunk(offset_ms: u64, text: &str) -> OutputChunk {
+        OutputChunk { offset_ms, stream: OutputStream::Stdout, text: text.to_string() }
+    }
+
+    /// Five chunks; the tee is opened after the first two have arrived
+    fn stream(options: TeeOptions) -> (String, TeeStatus) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("logs").join("build.log");
+        let chunks = [
+            chunk(0, "\x1b[1mCompiling\x1b[0m a\n"),
+            chunk(10, "Compiling b\n"),
+            chunk(20, "\x1b[32mFinish"),
+            chunk(30, "ed\x1b["),
+            chunk(40, "0m in 1s\n"),
+        ];
+
+        let mut tee = Tee::open(&path, options, &chunks[..2]).unwrap();
+        for chunk in &chunks[2..] {
+            tee.write_chunk(chunk).unwrap();
+        }
+        let status = tee.close().unwrap();
+        (std::fs::read_to_string(&path).unwrap(), status)
+    }
+
+    #[test]
+    fn test_tee_with_backfill() {
+        let (contents, status) = stream(TeeOptions { strip_ansi: true, backfill: true });
+        assert_eq!(contents, "Compiling a\nCompiling b\nFinished in 1s\n");
+        assert_eq!(status.bytes_written, contents.len() as u64);
+        assert!(status.path.ends_with("logs/build.log"));
+    }
+
+    #[test]
+    fn test_tee_without_backfill() {
+        let (contents, status) = stream(TeeOptions { strip_ansi: true, backfill: false });
+        assert_eq!(contents, "Finished in 1s\n");
+        assert_eq!(status.bytes_written, contents.len() as u64);
+    }
+
+    #[test]
+    fn test_tee_keeps_ansi_when_asked() {
+        let (contents, _) = stream(TeeOptions { strip_ansi: false, backfill: false });
+        assert_eq!(contents, "\x1b[32mFinished\x1b[0m in 1s\n");
+    }
+
+    #[test]
+    fn test_strip_sequences_split_across_chunks() {
+        let mut stripper = AnsiStripper::new();
+        let pieces = ["a\x1b]0;ti", "tle\x1b", "\\b\x1b(", "Bc\x1b", "[2Kd\x07e"];
+        let plain: String = pieces.iter().map(|piece| stripper.strip(piece)).collect();
+        assert_eq!(plain, "abcd\x07e");
+    }
+
+    #[test]
+    fn test_default_path() {
+        let now = Local.with_ymd_and_hms(2026, 3, 14, 15, 9, 26).unwrap();
+        let dir = Path::new("/work");
+        assert_eq!(
+            default_path(dir, "RUST_LOG=debug /usr/bin/cargo build", now),
+            dir.join("cargo-20260314-150926.log")
+        );
+        assert_eq!(default_path(dir, "  ", now), dir.join("output-20260314-150926.log"));
+    }
+}
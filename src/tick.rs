@@ -0,0 +1,136 @@
+//! Decides how often `Message::Tick` fires. Output, window and key events
+//! already arrive on their own; the tick is only for things that change
+//! with time alone. Each of those registers its interest, and the tick
+//! runs at the fastest rate any of them asks for, or not at all.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Tick interval while something is animating
+pub const FAST: Duration = Duration::from_millis(100);
+/// Tick interval while something only needs to time out
+pub const SLOW: Duration = Duration::from_secs(1);
+
+/// Things that change with time alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TickSource {
+    /// Status line spinners for sync and AI requests
+    Spinner,
+    /// A block's border flashing for the terminal bell
+    BellFlash,
+    /// Status line notices waiting to time out
+    StatusMessages,
+    /// Elapsed time of commands running in the focused pane
+    RunningCommand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rate {
+    Slow,
+    Fast,
+}
+
+impl Rate {
+    pub fn interval(&self) -> Duration {
+        match self {
+            Rate::Slow => SLOW,
+            Rate::Fast => FAST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TickController {
+    interests: BTreeMap<TickSource, Rate>,
+}
+
+impl TickController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask for ticks at `rate`, or withdraw with `None`
+    pub fn set(&mut self, source: TickSource, rate: Option<Rate>) {
+        match rate {
+            Some(rate) => self.interests.insert(source, rate),
+            None => self.interests.remove(&source),
+        };
+    }
+
+    /// Builder form of `set`
+    pub fn with(mut self, source: TickSource, rate: Option<Rate>) -> Self {
+        self.set(source, rate);
+        self
+    }
+
+    pub fn rate(&self) -> Option<Rate> {
+        self.interests.values().max().copied()
+    }
+
+    /// How often to tick; `None` when nothing needs it
+    pub fn interval(&self) -> Option<Duration> {
+        self.rate().map(|rate| rate.interval())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Timer wakeups in one minute of paused time, with the tick at
+    /// `controller`'s interval alongside the app's other timers
+    async fn wakeups_per_minute(controller: &TickController, other_timers: &[Duration]) -> usize {
+        let intervals: Vec<Duration> = controller.interval().into_iter().chain(other_timers.iter().copied()).collect();
+        let counted = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let tasks: Vec<_> = intervals
+            .into_iter()
+            .map(|period| {
+                let counted = counted.clone();
+                tokio::spawn(async move {
+                    let start = tokio::time::Instant::now() + period;
+                    let mut timer = tokio::time::interval_at(start, period);
+                    loop {
+                        timer.tick().await;
+                        counted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        for task in tasks {
+            task.abort();
+        }
+        counted.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_app_barely_wakes_up() {
+        // The idle check is the only other periodic timer
+        let idle_check = [Duration::from_secs(15)];
+
+        let idle = TickController::new();
+        assert_eq!(idle.interval(), None);
+        assert!(wakeups_per_minute(&idle, &idle_check).await <= 4);
+
+        let notice = TickController::new().with(TickSource::StatusMessages, Some(Rate::Slow));
+        assert!(wakeups_per_minute(&notice, &idle_check).await <= 64);
+
+        let streaming = notice.clone().with(TickSource::Spinner, Some(Rate::Fast));
+        assert!(wakeups_per_minute(&streaming, &idle_check).await >= 600);
+    }
+
+    #[test]
+    fn test_fastest_interest_wins_and_can_be_withdrawn() {
+        let mut controller = TickController::new()
+            .with(TickSource::StatusMessages, Some(Rate::Slow))
+            .with(TickSource::BellFlash, Some(Rate::Fast));
+        assert_eq!(controller.interval(), Some(FAST));
+
+        controller.set(TickSource::BellFlash, None);
+        assert_eq!(controller.interval(), Some(SLOW));
+
+        controller.set(TickSource::StatusMessages, None);
+        assert_eq!(controller.rate(), None);
+    }
+}
@@ -0,0 +1,202 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// A piece of command output as it arrived
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputChunk {
+    /// Milliseconds since the command started
+    pub offset_ms: u64,
+    pub stream: OutputStream,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkerKind {
+    Stderr,
+    /// An alert pattern matched; holds the pattern
+    Alert(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineMarker {
+    pub offset_ms: u64,
+    pub kind: MarkerKind,
+}
+
+/// Arrival times of a block's output, indexed so the output can be cut back
+/// to any moment without re-reading it.
+///
+/// Block output is the concatenation of the chunks in arrival order, so the
+/// output at time `t` is a prefix of it ending where the last chunk at or
+/// before `t` ends.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredTimeline")]
+pub struct OutputTimeline {
+    chunks: Vec<OutputChunk>,
+    /// Byte length of the output after each chunk
+    #[serde(skip)]
+    ends: Vec<usize>,
+    pub duration_ms: u64,
+}
+
+/// Serialized form; the byte index is rebuilt on load
+#[derive(Deserialize)]
+struct StoredTimeline {
+    chunks: Vec<OutputChunk>,
+    duration_ms: u64,
+}
+
+impl From<StoredTimeline> for OutputTimeline {
+    fn from(stored: StoredTimeline) -> Self {
+        Self::from_chunks(stored.chunks, stored.duration_ms)
+    }
+}
+
+impl OutputTimeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild the index from recorded chunks, e.g. from an exported session
+    pub fn from_chunks(chunks: Vec<OutputChunk>, duration_ms: u64) -> Self {
+        let mut timeline = Self::new();
+        for chunk in chunks {
+            timeline.push(chunk);
+        }
+        timeline.duration_ms = timeline.duration_ms.max(duration_ms);
+        timeline
+    }
+
+    pub fn push(&mut self, chunk: OutputChunk) {
+        let end = self.ends.last().copied().unwrap_or(0) + chunk.text.len();
+        self.duration_ms = self.duration_ms.max(chunk.offset_ms);
+        self.ends.push(end);
+        self.chunks.push(chunk);
+    }
+
+    /// Forget the first `bytes` of output, once the block no longer keeps them.
+    /// `bytes` must fall on a character boundary of the output.
+    pub fn drop_front(&mut self, bytes: usize) {
+        let whole = self.ends.partition_point(|end| *end <= bytes);
+        self.chunks.drain(..whole);
+        self.ends.drain(..whole);
+        if let (Some(first), Some(end)) = (self.chunks.first_mut(), self.ends.first()) {
+            let start = end - first.text.len();
+            first.text.drain(..bytes.saturating_sub(start));
+        }
+        for end in &mut self.ends {
+            *end = end.saturating_sub(bytes);
+        }
+    }
+
+    pub fn chunks(&self) -> &[OutputChunk] {
+        &self.chunks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Number of chunks that had arrived by `offset_ms`
+    pub fn chunks_at(&self, offset_ms: u64) -> usize {
+        // Offsets are non-decreasing, so this is a binary search
+        self.chunks.partition_point(|chunk| chunk.offset_ms <= offset_ms)
+    }
+
+    /// The part of `output` that had been printed by `offset_ms`.
+    /// `output` must be the concatenation of this timeline's chunks.
+    pub fn output_at<'a>(&self, output: &'a str, offset_ms: u64) -> &'a str {
+        let count = self.chunks_at(offset_ms);
+        let end = if count == 0 { 0 } else { self.ends[count - 1] };
+        &output[..end.min(output.len())]
+    }
+
+    /// Places worth jumping to: stderr output and alert pattern matches
+    pub fn markers(&self, alert_patterns: &[Regex]) -> Vec<TimelineMarker> {
+        let mut markers = Vec::new();
+        for chunk in &self.chunks {
+            if chunk.stream == OutputStream::Stderr {
+                markers.push(TimelineMarker { offset_ms: chunk.offset_ms, kind: MarkerKind::Stderr });
+            }
+            for pattern in alert_patterns.iter().filter(|p| p.is_match(&chunk.text)) {
+                markers.push(TimelineMarker {
+                    offset_ms: chunk.offset_ms,
+                    kind: MarkerKind::Alert(pattern.as_str().to_string()),
+                });
+            }
+        }
+        markers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(offset_ms: u64, stream: OutputStream, text: &str) -> OutputChunk {
+        OutputChunk { offset_ms, stream, text: text.to_string() }
+    }
+
+    fn sample() -> (OutputTimeline, String) {
+        let chunks = vec![
+            chunk(0, OutputStream::Stdout, "starting\n"),
+            chunk(12_000, OutputStream::Stdout, "step 1 ok\n"),
+            chunk(30_000, OutputStream::Stderr, "warning: retrying\n"),
+            chunk(45_000, OutputStream::Stdout, "FAILED step 2\n"),
+        ];
+        let output = chunks.iter().map(|c| c.text.as_str()).collect();
+        (OutputTimeline::from_chunks(chunks, 46_000), output)
+    }
+
+    #[test]
+    fn test_output_at_reconstructs_prefix() {
+        let (timeline, output) = sample();
+
+        assert_eq!(timeline.output_at(&output, 11_999), "starting\n");
+        assert_eq!(timeline.output_at(&output, 12_000), "starting\nstep 1 ok\n");
+        assert_eq!(timeline.output_at(&output, 44_000), "starting\nstep 1 ok\nwarning: retrying\n");
+        assert_eq!(timeline.output_at(&output, timeline.duration_ms), output);
+        assert_eq!(timeline.duration_ms, 46_000);
+    }
+
+    #[test]
+    fn test_markers_for_stderr_and_alerts() {
+        let (timeline, _) = sample();
+        let markers = timeline.markers(&[Regex::new("FAILED").unwrap()]);
+
+        assert_eq!(markers, vec![
+            TimelineMarker { offset_ms: 30_000, kind: MarkerKind::Stderr },
+            TimelineMarker { offset_ms: 45_000, kind: MarkerKind::Alert("FAILED".to_string()) },
+        ]);
+    }
+
+    #[test]
+    fn test_drop_front_keeps_offsets_consistent() {
+        let (mut timeline, output) = sample();
+        // Into the middle of the second chunk
+        let dropped = "starting\nstep ".len();
+        timeline.drop_front(dropped);
+        let output = &output[dropped..];
+
+        assert_eq!(timeline.chunks()[0].text, "1 ok\n");
+        assert_eq!(timeline.output_at(output, 12_000), "1 ok\n");
+        assert_eq!(timeline.output_at(output, 30_000), "1 ok\nwarning: retrying\n");
+        assert_eq!(timeline.output_at(output, timeline.duration_ms), output);
+    }
+
+    #[test]
+    fn test_round_trip_keeps_index() {
+        let (timeline, output) = sample();
+        let json = serde_json::to_string(&timeline).unwrap();
+        let restored: OutputTimeline = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.output_at(&output, 12_000), "starting\nstep 1 ok\n");
+    }
+}
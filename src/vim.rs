@@ -0,0 +1,536 @@
+//! Vim-style modal editing of the input bar, for `editor.vim_mode`.
+//!
+//! The text input does the typing in insert mode. In normal and visual mode
+//! keys come here instead: motions move `cursor`, the d, c and y operators
+//! edit the line, and what they take goes to registers for p and P.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// The register used when none is named with `"x`
+const UNNAMED: char = '"';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Insert,
+    Normal,
+    Visual,
+}
+
+impl Mode {
+    pub fn label(self) -> &'static str {
+        match self {
+            Mode::Insert => "INSERT",
+            Mode::Normal => "NORMAL",
+            Mode::Visual => "VISUAL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Escape,
+    Enter,
+    Left,
+    Right,
+}
+
+/// What the application does after a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    None,
+    /// Insert mode, with the text input's cursor at this character
+    Insert(usize),
+    HistoryPrevious,
+    HistoryNext,
+    Submit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+/// f, t, F and T
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Find {
+    To,
+    Till,
+    BackTo,
+    BackTill,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Pending {
+    #[default]
+    None,
+    Register,
+    Operator(Operator),
+    Find(Option<Operator>, Find),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Vim {
+    mode: Mode,
+    /// Character index into the line
+    cursor: usize,
+    /// Where the visual selection started
+    anchor: usize,
+    pending: Pending,
+    /// Named with `"x` for the next operator or paste
+    register: Option<char>,
+    registers: HashMap<char, String>,
+}
+
+impl Vim {
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Characters selected in visual mode
+    pub fn selection(&self) -> Option<Range<usize>> {
+        (self.mode == Mode::Visual).then(|| self.anchor.min(self.cursor)..self.anchor.max(self.cursor) + 1)
+    }
+
+    /// Esc in insert mode. The text input doesn't say where its cursor
+    /// was, so normal mode starts on the last character.
+    pub fn escape(&mut self, line: &str) {
+        self.mode = Mode::Normal;
+        self.pending = Pending::None;
+        self.cursor = line.chars().count().saturating_sub(1);
+    }
+
+    /// Back to insert mode, as a new prompt starts
+    pub fn reset(&mut self) {
+        self.mode = Mode::Insert;
+        self.pending = Pending::None;
+        self.register = None;
+    }
+
+    /// A key in normal or visual mode
+    pub fn key(&mut self, line: &mut String, key: Key) -> Effect {
+        let mut chars: Vec<char> = line.chars().collect();
+        // The line may have changed since, through history or a completion
+        self.clamp(chars.len());
+        let effect = match key {
+            Key::Escape => {
+                if self.pending == Pending::None {
+                    self.mode = Mode::Normal;
+                }
+                self.pending = Pending::None;
+                self.register = None;
+                Effect::None
+            }
+            Key::Enter => {
+                self.reset();
+                Effect::Submit
+            }
+            Key::Left => self.char_key(&mut chars, 'h'),
+            Key::Right => self.char_key(&mut chars, 'l'),
+            Key::Char(c) => self.char_key(&mut chars, c),
+        };
+        if self.mode != Mode::Insert {
+            self.clamp(chars.len());
+        }
+        *line = chars.into_iter().collect();
+        effect
+    }
+
+    fn char_key(&mut self, chars: &mut Vec<char>, c: char) -> Effect {
+        match std::mem::take(&mut self.pending) {
+            Pending::Register => {
+                if c.is_ascii_lowercase() || c == UNNAMED {
+                    self.register = Some(c);
+                }
+                Effect::None
+            }
+            Pending::Find(operator, find) => match (find_char(chars, self.cursor, find, c), operator) {
+                (Some((target, inclusive)), Some(operator)) => self.operate(chars, operator, target, inclusive),
+                (Some((target, _)), None) => {
+                    self.cursor = target;
+                    Effect::None
+                }
+                (None, _) => Effect::None,
+            },
+            Pending::Operator(operator) => self.operator_key(chars, operator, c),
+            Pending::None if self.mode == Mode::Visual => self.visual_key(chars, c),
+            Pending::None => self.normal_key(chars, c),
+        }
+    }
+
+    fn normal_key(&mut self, chars: &mut Vec<char>, c: char) -> Effect {
+        let len = chars.len();
+        match c {
+            'i' => self.insert(self.cursor),
+            'a' => self.insert((self.cursor + 1).min(len)),
+            'I' => self.insert(0),
+            'A' => self.insert(len),
+            'v' => {
+                self.mode = Mode::Visual;
+                self.anchor = self.cursor;
+                Effect::None
+            }
+            'd' => self.start_operator(Operator::Delete),
+            'c' => self.start_operator(Operator::Change),
+            'y' => self.start_operator(Operator::Yank),
+            'D' => self.apply(chars, Operator::Delete, self.cursor..len),
+            'C' => self.apply(chars, Operator::Change, self.cursor..len),
+            'Y' => self.apply(chars, Operator::Yank, 0..len),
+            'x' if len > 0 => self.apply(chars, Operator::Delete, self.cursor..self.cursor + 1),
+            's' => self.apply(chars, Operator::Change, self.cursor..(self.cursor + 1).min(len)),
+            'p' => self.paste(chars, true),
+            'P' => self.paste(chars, false),
+            '"' => {
+                self.pending = Pending::Register;
+                Effect::None
+            }
+            'k' => Effect::HistoryPrevious,
+            'j' => Effect::HistoryNext,
+            _ => {
+                self.find_or_move(chars, None, c);
+                Effect::None
+            }
+        }
+    }
+
+    fn visual_key(&mut self, chars: &mut Vec<char>, c: char) -> Effect {
+        let selection = self.selection().map(|range| range.start..range.end.min(chars.len())).unwrap_or_default();
+        match c {
+            'v' => {
+                self.mode = Mode::Normal;
+                Effect::None
+            }
+            'd' | 'x' => self.apply(chars, Operator::Delete, selection),
+            'c' | 's' => self.apply(chars, Operator::Change, selection),
+            'y' => self.apply(chars, Operator::Yank, selection),
+            '"' => {
+                self.pending = Pending::Register;
+                Effect::None
+            }
+            _ => {
+                self.find_or_move(chars, None, c);
+                Effect::None
+            }
+        }
+    }
+
+    /// The key after d, c or y
+    fn operator_key(&mut self, chars: &mut Vec<char>, operator: Operator, c: char) -> Effect {
+        let doubled = matches!((operator, c), (Operator::Delete, 'd') | (Operator::Change, 'c') | (Operator::Yank, 'y'));
+        if doubled {
+            return self.apply(chars, operator, 0..chars.len());
+        }
+        // cw changes to the end of the word, leaving the space after it
+        if operator == Operator::Change && c == 'w' && chars.get(self.cursor).is_some_and(|c| !c.is_whitespace()) {
+            return self.operate(chars, operator, word_end(chars, self.cursor), true);
+        }
+        match motion(chars, self.cursor, c) {
+            Some((target, inclusive)) => self.operate(chars, operator, target, inclusive),
+            None => {
+                self.find_or_move(chars, Some(operator), c);
+                Effect::None
+            }
+        }
+    }
+
+    /// A motion moves the cursor; f, t, F and T wait for their character
+    fn find_or_move(&mut self, chars: &[char], operator: Option<Operator>, c: char) {
+        let find = match c {
+            'f' => Find::To,
+            't' => Find::Till,
+            'F' => Find::BackTo,
+            'T' => Find::BackTill,
+            _ => {
+                if let Some((target, _)) = motion(chars, self.cursor, c).filter(|_| operator.is_none()) {
+                    self.cursor = target;
+                }
+                return;
+            }
+        };
+        self.pending = Pending::Find(operator, find);
+    }
+
+    fn start_operator(&mut self, operator: Operator) -> Effect {
+        self.pending = Pending::Operator(operator);
+        Effect::None
+    }
+
+    fn insert(&mut self, at: usize) -> Effect {
+        self.reset();
+        self.cursor = at;
+        Effect::Insert(at)
+    }
+
+    /// Apply an operator from the cursor to where a motion lands
+    fn operate(&mut self, chars: &mut Vec<char>, operator: Operator, target: usize, inclusive: bool) -> Effect {
+        let (start, end) = (self.cursor.min(target), self.cursor.max(target));
+        let end = if inclusive { (end + 1).min(chars.len()) } else { end };
+        self.apply(chars, operator, start..end)
+    }
+
+    fn apply(&mut self, chars: &mut Vec<char>, operator: Operator, range: Range<usize>) -> Effect {
+        let taken: String = chars[range.clone()].iter().collect();
+        self.store(taken);
+        self.cursor = range.start;
+        match operator {
+            Operator::Yank => {
+                self.mode = Mode::Normal;
+                Effect::None
+            }
+            Operator::Delete => {
+                chars.drain(range);
+                self.mode = Mode::Normal;
+                Effect::None
+            }
+            Operator::Change => {
+                chars.drain(range.clone());
+                self.insert(range.start)
+            }
+        }
+    }
+
+    fn store(&mut self, text: String) {
+        if let Some(register) = self.register.take().filter(|register| *register != UNNAMED) {
+            self.registers.insert(register, text.clone());
+        }
+        self.registers.insert(UNNAMED, text);
+    }
+
+    /// p pastes after the cursor, P before it
+    fn paste(&mut self, chars: &mut Vec<char>, after: bool) -> Effect {
+        let register = self.register.take().unwrap_or(UNNAMED);
+        let Some(text) = self.registers.get(&register).filter(|text| !text.is_empty()) else {
+            return Effect::None;
+        };
+        let at = if after && !chars.is_empty() { self.cursor + 1 } else { self.cursor };
+        let pasted: Vec<char> = text.chars().collect();
+        self.cursor = at + pasted.len() - 1;
+        chars.splice(at..at, pasted);
+        Effect::None
+    }
+
+    /// Normal mode's cursor sits on a character, not after the last one
+    fn clamp(&mut self, len: usize) {
+        self.cursor = self.cursor.min(len.saturating_sub(1));
+        self.anchor = self.anchor.min(len.saturating_sub(1));
+    }
+}
+
+/// Whitespace, word characters, and other punctuation: w, b and e stop
+/// where the class changes
+fn class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// Where a motion key moves the cursor, and whether an operator takes the
+/// character it lands on
+fn motion(chars: &[char], cursor: usize, c: char) -> Option<(usize, bool)> {
+    let len = chars.len();
+    match c {
+        'h' => Some((cursor.saturating_sub(1), false)),
+        'l' | ' ' => Some(((cursor + 1).min(len), false)),
+        '0' => Some((0, false)),
+        '^' => Some((chars.iter().position(|c| !c.is_whitespace()).unwrap_or(0), false)),
+        '$' => Some((len.saturating_sub(1), len > 0)),
+        'w' => Some((next_word(chars, cursor), false)),
+        'b' => Some((previous_word(chars, cursor), false)),
+        'e' => Some((word_end(chars, cursor), true)),
+        _ => None,
+    }
+}
+
+fn next_word(chars: &[char], from: usize) -> usize {
+    let mut i = from;
+    if let Some(start) = chars.get(i).map(|c| class(*c)).filter(|class| *class != 0) {
+        while i < chars.len() && class(chars[i]) == start {
+            i += 1;
+        }
+    }
+    while i < chars.len() && class(chars[i]) == 0 {
+        i += 1;
+    }
+    i
+}
+
+fn previous_word(chars: &[char], from: usize) -> usize {
+    let mut i = from;
+    while i > 0 && class(chars[i - 1]) == 0 {
+        i -= 1;
+    }
+    if let Some(word) = i.checked_sub(1).map(|before| class(chars[before])) {
+        while i > 0 && class(chars[i - 1]) == word {
+            i -= 1;
+        }
+    }
+    i
+}
+
+fn word_end(chars: &[char], from: usize) -> usize {
+    let mut i = from + 1;
+    while i < chars.len() && class(chars[i]) == 0 {
+        i += 1;
+    }
+    if i >= chars.len() {
+        return chars.len().saturating_sub(1);
+    }
+    let word = class(chars[i]);
+    while i + 1 < chars.len() && class(chars[i + 1]) == word {
+        i += 1;
+    }
+    i
+}
+
+fn find_char(chars: &[char], cursor: usize, find: Find, target: char) -> Option<(usize, bool)> {
+    let after = || (cursor + 1..chars.len()).find(|i| chars[*i] == target);
+    let before = || (0..cursor).rev().find(|i| chars[*i] == target);
+    match find {
+        Find::To => after().map(|i| (i, true)),
+        Find::Till => after().map(|i| (i - 1, true)),
+        Find::BackTo => before().map(|i| (i, false)),
+        Find::BackTill => before().map(|i| (i + 1, false)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Start in normal mode on `cursor`, type `keys`, and return the line
+    /// and cursor
+    fn run(line: &str, cursor: usize, keys: &str) -> (String, usize, Vim) {
+        let mut vim = Vim { mode: Mode::Normal, cursor, ..Vim::default() };
+        let mut line = line.to_string();
+        for c in keys.chars() {
+            vim.key(&mut line, Key::Char(c));
+        }
+        let cursor = vim.cursor();
+        (line, cursor, vim)
+    }
+
+    #[test]
+    fn test_motions() {
+        let line = "git commit -m 'fix: typo' && ls";
+        let cases = [
+            (0, "w", 4),
+            (4, "w", 11),
+            (11, "ww", 14),
+            (15, "b", 14),
+            (20, "b", 18),
+            (20, "bb", 15),
+            (0, "e", 2),
+            (4, "e", 9),
+            (9, "e", 11),
+            (10, "0", 0),
+            (0, "$", 30),
+            (0, "fm", 6),
+            (0, "tm", 5),
+            (20, "Fi", 16),
+            (20, "Ti", 17),
+            (0, "fz", 0),
+            (30, "l", 30),
+            (0, "h", 0),
+        ];
+        for (start, keys, expected) in cases {
+            let (after, cursor, _) = run(line, start, keys);
+            assert_eq!(after, line);
+            assert_eq!(cursor, expected, "{} from {}", keys, start);
+        }
+    }
+
+    #[test]
+    fn test_operators_with_motions() {
+        let line = "cargo build --release foo";
+        let cases = [
+            (0, "dw", "build --release foo", 0),
+            (6, "de", "cargo  --release foo", 6),
+            (12, "db", "cargo --release foo", 6),
+            (6, "d$", "cargo ", 5),
+            (6, "d0", "build --release foo", 0),
+            (0, "dfb", "uild --release foo", 0),
+            (0, "dtb", "build --release foo", 0),
+            (12, "dFb", "cargo --release foo", 6),
+            (12, "dTb", "cargo b--release foo", 7),
+            (6, "dd", "", 0),
+            (6, "x", "cargo uild --release foo", 6),
+            (6, "D", "cargo ", 5),
+            (0, "dz", line, 0),
+        ];
+        for (start, keys, expected, cursor) in cases {
+            let (after, at, vim) = run(line, start, keys);
+            assert_eq!((after.as_str(), at), (expected, cursor), "{} from {}", keys, start);
+            assert_eq!(vim.mode(), Mode::Normal);
+        }
+
+        // Change leaves insert mode where the text went
+        let cases = [
+            (6, "cw", "cargo  --release foo", 6),
+            (6, "ce", "cargo  --release foo", 6),
+            (12, "cb", "cargo --release foo", 6),
+            (6, "c$", "cargo ", 6),
+            (6, "ct ", "cargo  --release foo", 6),
+            (6, "cc", "", 0),
+            (6, "C", "cargo ", 6),
+        ];
+        for (start, keys, expected, cursor) in cases {
+            let mut vim = Vim { mode: Mode::Normal, cursor: start, ..Vim::default() };
+            let mut after = line.to_string();
+            let mut effect = Effect::None;
+            for c in keys.chars() {
+                effect = vim.key(&mut after, Key::Char(c));
+            }
+            assert_eq!((after.as_str(), effect), (expected, Effect::Insert(cursor)), "{} from {}", keys, start);
+            assert_eq!(vim.mode(), Mode::Insert);
+        }
+
+        // Yank leaves the line alone and fills the register p pastes from
+        let (after, cursor, _) = run(line, 6, "yw$p");
+        assert_eq!((after.as_str(), cursor), ("cargo build --release foobuild ", 30));
+        let (after, _, _) = run(line, 6, "yeP");
+        assert_eq!(after, "cargo buildbuild --release foo");
+        let (after, cursor, _) = run(line, 12, "yb");
+        assert_eq!((after.as_str(), cursor), (line, 6));
+    }
+
+    #[test]
+    fn test_registers_visual_mode_and_history() {
+        // A named register keeps its text while the unnamed one moves on
+        let (after, _, vim) = run("one two three", 0, "\"adwdw0\"aP");
+        assert_eq!(after, "one three");
+        assert_eq!(vim.registers.get(&UNNAMED).map(String::as_str), Some("two "));
+
+        let (after, cursor, vim) = run("echo hello world", 5, "vey$p");
+        assert_eq!((after.as_str(), cursor), ("echo hello worldhello", 20));
+        assert_eq!(vim.mode(), Mode::Normal);
+        let (after, _, _) = run("echo hello world", 5, "vwd");
+        assert_eq!(after, "echo orld");
+        let mut vim = Vim { mode: Mode::Normal, ..Vim::default() };
+        let mut line = "echo hi".to_string();
+        assert_eq!(vim.key(&mut line, Key::Char('v')), Effect::None);
+        vim.key(&mut line, Key::Char('l'));
+        assert_eq!(vim.selection(), Some(0..2));
+        vim.key(&mut line, Key::Escape);
+        assert_eq!(vim.mode(), Mode::Normal);
+
+        assert_eq!(vim.key(&mut line, Key::Char('k')), Effect::HistoryPrevious);
+        assert_eq!(vim.key(&mut line, Key::Char('j')), Effect::HistoryNext);
+        assert_eq!(vim.key(&mut line, Key::Char('A')), Effect::Insert(7));
+        vim.escape(&line);
+        assert_eq!((vim.mode(), vim.cursor()), (Mode::Normal, 6));
+        assert_eq!(vim.key(&mut line, Key::Enter), Effect::Submit);
+        assert_eq!(vim.mode(), Mode::Insert);
+    }
+}
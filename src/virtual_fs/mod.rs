@@ -1,5 +1,210 @@
-// virtual_fs module stub
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-pub fn init() {
-    println!("virtual_fs loaded");
+/// In-memory layer of pending file edits over the real file system.
+///
+/// Edits are staged with the original contents captured as pre-images, can be
+/// previewed as one consolidated diff, and are only written on `commit`. Each
+/// commit is kept on an undo stack so it can be rolled back from the pre-images.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualFileSystem {
+    staged: BTreeMap<PathBuf, StagedFile>,
+    history: Vec<Vec<StagedFile>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagedFile {
+    pub path: PathBuf,
+    /// Contents before the edit; `None` if the file did not exist
+    pub original: Option<String>,
+    pub modified: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VfsError {
+    #[error("IO error on {0}: {1}")]
+    Io(PathBuf, String),
+    #[error("Nothing to undo")]
+    NothingToUndo,
+    #[error("{0} changed on disk since it was staged")]
+    Conflict(PathBuf),
+}
+
+impl VirtualFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Contents as they would be after commit: staged if present, else on disk
+    pub fn read(&self, path: &Path) -> Result<String, VfsError> {
+        match self.staged.get(path) {
+            Some(file) => Ok(file.modified.clone()),
+            None => std::fs::read_to_string(path)
+                .map_err(|e| VfsError::Io(path.to_path_buf(), e.to_string())),
+        }
+    }
+
+    /// Stage new contents for `path`, capturing the on-disk pre-image the first time
+    pub fn write(&mut self, path: &Path, contents: String) -> Result<(), VfsError> {
+        if let Some(file) = self.staged.get_mut(path) {
+            file.modified = contents;
+            return Ok(());
+        }
+
+        let original = match std::fs::read_to_string(path) {
+            Ok(text) => Some(text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(VfsError::Io(path.to_path_buf(), e.to_string())),
+        };
+
+        self.staged.insert(path.to_path_buf(), StagedFile {
+            path: path.to_path_buf(),
+            original,
+            modified: contents,
+        });
+        Ok(())
+    }
+
+    pub fn staged_files(&self) -> impl Iterator<Item = &StagedFile> {
+        self.staged.values()
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.staged.values().any(|file| file.original.as_deref() != Some(file.modified.as_str()))
+    }
+
+    pub fn discard(&mut self) {
+        self.staged.clear();
+    }
+
+    /// Unified-style diff of every staged file
+    pub fn diff(&self) -> String {
+        self.staged.values().map(file_diff).collect()
+    }
+
+    /// Write all staged files. Fails without writing anything if a file was
+    /// modified on disk after being staged.
+    pub fn commit(&mut self) -> Result<usize, VfsError> {
+        for file in self.staged.values() {
+            let current = std::fs::read_to_string(&file.path).ok();
+            if current != file.original {
+                return Err(VfsError::Conflict(file.path.clone()));
+            }
+        }
+
+        let files: Vec<StagedFile> = std::mem::take(&mut self.staged).into_values().collect();
+        for file in &files {
+            std::fs::write(&file.path, &file.modified)
+                .map_err(|e| VfsError::Io(file.path.clone(), e.to_string()))?;
+        }
+
+        let count = files.len();
+        self.history.push(files);
+        Ok(count)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    /// Restore the files of the last commit from their pre-images
+    pub fn undo(&mut self) -> Result<usize, VfsError> {
+        let files = self.history.pop().ok_or(VfsError::NothingToUndo)?;
+
+        for file in &files {
+            let result = match &file.original {
+                Some(original) => std::fs::write(&file.path, original),
+                None => std::fs::remove_file(&file.path),
+            };
+            result.map_err(|e| VfsError::Io(file.path.clone(), e.to_string()))?;
+        }
+
+        Ok(files.len())
+    }
+}
+
+/// Diff of one file. Lines are compared after trimming the common prefix and
+/// suffix; equal-length middles are compared line by line so in-place edits
+/// (the common case for replacements) produce small hunks.
+fn file_diff(file: &StagedFile) -> String {
+    let original = file.original.as_deref().unwrap_or("");
+    let old: Vec<&str> = original.lines().collect();
+    let new: Vec<&str> = file.modified.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    if old_mid.is_empty() && new_mid.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- a/{}\n+++ b/{}\n", file.path.display(), file.path.display());
+
+    if old_mid.len() == new_mid.len() {
+        for (offset, (a, b)) in old_mid.iter().zip(new_mid).enumerate() {
+            if a != b {
+                out.push_str(&format!("@@ -{0},1 +{0},1 @@\n-{1}\n+{2}\n", prefix + offset + 1, a, b));
+            }
+        }
+    } else {
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            prefix + 1, old_mid.len(), prefix + 1, new_mid.len()
+        ));
+        for line in old_mid {
+            out.push_str(&format!("-{}\n", line));
+        }
+        for line in new_mid {
+            out.push_str(&format!("+{}\n", line));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_commit_and_undo_restore_pre_images() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("main.rs");
+        std::fs::write(&path, "fn old() {}\nfn keep() {}\n").unwrap();
+
+        let mut vfs = VirtualFileSystem::new();
+        vfs.write(&path, "fn new() {}\nfn keep() {}\n".to_string()).unwrap();
+
+        // Nothing touches disk before commit
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn old() {}\nfn keep() {}\n");
+        assert_eq!(vfs.read(&path).unwrap(), "fn new() {}\nfn keep() {}\n");
+        assert!(vfs.diff().contains("@@ -1,1 +1,1 @@\n-fn old() {}\n+fn new() {}\n"));
+
+        assert_eq!(vfs.commit().unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn new() {}\nfn keep() {}\n");
+
+        vfs.undo().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn old() {}\nfn keep() {}\n");
+        assert!(matches!(vfs.undo(), Err(VfsError::NothingToUndo)));
+    }
+
+    #[test]
+    fn test_commit_refuses_files_changed_on_disk() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.txt");
+        std::fs::write(&path, "a\n").unwrap();
+
+        let mut vfs = VirtualFileSystem::new();
+        vfs.write(&path, "b\n".to_string()).unwrap();
+        std::fs::write(&path, "edited elsewhere\n").unwrap();
+
+        assert!(matches!(vfs.commit(), Err(VfsError::Conflict(_))));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "edited elsewhere\n");
+    }
 }
@@ -1,5 +1,51 @@
-// watcher module stub
+//! Filesystem watching. Events are debounced, so a burst of writes (a commit
+//! touching the index, refs and logs) is reported once.
 
-pub fn init() {
-    println!("watcher loaded");
+use std::path::Path;
+use std::time::Duration;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use tokio::sync::mpsc;
+
+/// Watches one directory tree, or one file, until dropped
+pub struct Watcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+impl Watcher {
+    /// Watch everything under `dir`. The receiver gets one message per quiet
+    /// period of `debounce` after changes; changes made while one is waiting
+    /// to be read are folded into it.
+    pub fn new(dir: &Path, debounce: Duration) -> notify_debouncer_mini::notify::Result<(Self, mpsc::Receiver<()>)> {
+        Self::watch(dir, RecursiveMode::Recursive, debounce, |_| true)
+    }
+
+    /// Watch a single file, as `new` does for a directory. The directory
+    /// holding it is watched instead, so editors that save by replacing
+    /// the file are still noticed.
+    pub fn file(path: &Path, debounce: Duration) -> notify_debouncer_mini::notify::Result<(Self, mpsc::Receiver<()>)> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let name = path.file_name().map(|name| name.to_os_string());
+        Self::watch(dir, RecursiveMode::NonRecursive, debounce, move |changed| {
+            changed.file_name().map(|changed| changed.to_os_string()) == name
+        })
+    }
+
+    fn watch(
+        dir: &Path,
+        mode: RecursiveMode,
+        debounce: Duration,
+        wanted: impl Fn(&Path) -> bool + Send + 'static,
+    ) -> notify_debouncer_mini::notify::Result<(Self, mpsc::Receiver<()>)> {
+        let (sender, receiver) = mpsc::channel(1);
+        let mut debouncer = new_debouncer(debounce, move |result: DebounceEventResult| match result {
+            Ok(events) if events.iter().any(|event| wanted(&event.path)) => {
+                let _ = sender.try_send(());
+            }
+            Ok(_) => {}
+            Err(e) => log::debug!("File watching failed: {}", e),
+        })?;
+        debouncer.watcher().watch(dir, mode)?;
+        Ok((Self { _debouncer: debouncer }, receiver))
+    }
 }
@@ -0,0 +1,444 @@
+use super::{CommandOutput, WorkflowError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// Default upper bound for the on-disk cache before LRU eviction kicks in (1 GiB).
+pub const DEFAULT_MAX_CACHE_BYTES: u64 = 1024 * 1024 * 1024;
+
+const ENTRY_FILE: &str = "entry.json";
+const FILES_DIR: &str = "files";
+
+/// Optional `cache:` section of a workflow step.
+///
+/// ```yaml
+/// cache:
+///   key: "build-{{profile}}-{{hash(files='Cargo.lock')}}"
+///   paths: [target/]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StepCache {
+    /// Key template. `{{hash(files='a,b')}}` expands to a digest of the listed
+    /// files (directories are walked), `{{name}}` to an argument value.
+    pub key: String,
+
+    /// Paths, relative to the working directory, saved on a miss and restored on a hit.
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+}
+
+/// Metadata stored next to the cached files of one step run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub workflow_name: String,
+    pub key: String,
+    pub output: CommandOutput,
+    /// How long the step took when it actually ran
+    pub duration_ms: u64,
+    pub created_at: DateTime<Utc>,
+    pub last_accessed: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+impl CacheEntry {
+    pub fn original_duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PruneReport {
+    pub removed: usize,
+    pub freed_bytes: u64,
+}
+
+/// Local store for cached workflow step results, laid out as
+/// `<root>/<workflow>/<key digest>/{entry.json, files/}`.
+#[derive(Debug, Clone)]
+pub struct WorkflowCache {
+    root: PathBuf,
+    max_bytes: u64,
+}
+
+impl WorkflowCache {
+    pub fn new() -> Result<Self, WorkflowError> {
+        Ok(Self::with_dir(Self::get_cache_dir()?, DEFAULT_MAX_CACHE_BYTES))
+    }
+
+    pub fn with_dir(root: PathBuf, max_bytes: u64) -> Self {
+        Self { root, max_bytes }
+    }
+
+    /// Get the workflow cache directory path
+    pub fn get_cache_dir() -> Result<PathBuf, WorkflowError> {
+        crate::config::ConfigPaths::resolve()
+            .map(|paths| paths.workflow_cache_dir())
+            .map_err(|e| WorkflowError::IoError(e.to_string()))
+    }
+
+    fn entry_dir(&self, workflow_name: &str, key: &str) -> PathBuf {
+        self.root
+            .join(super::manager::sanitize_filename(workflow_name))
+            .join(format!("{:016x}", fnv1a(key.as_bytes(), FNV_OFFSET)))
+    }
+
+    /// Find a stored result for this workflow and rendered key
+    pub fn lookup(&self, workflow_name: &str, key: &str) -> Option<CacheEntry> {
+        let content = std::fs::read_to_string(self.entry_dir(workflow_name, key).join(ENTRY_FILE)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+        // Guard against digest collisions
+        (entry.key == key).then_some(entry)
+    }
+
+    /// Copy the cached paths back into `workdir` and mark the entry as recently used
+    pub fn restore(&self, entry: &CacheEntry, workdir: &Path) -> Result<(), WorkflowError> {
+        let entry_dir = self.entry_dir(&entry.workflow_name, &entry.key);
+        copy_tree(&entry_dir.join(FILES_DIR), workdir)?;
+
+        let mut touched = entry.clone();
+        touched.last_accessed = Utc::now();
+        write_entry(&entry_dir, &touched)
+    }
+
+    /// Save the declared paths and output of a successful run, then evict
+    /// least-recently-used entries until the cache fits its size limit.
+    pub fn store(
+        &self,
+        workflow_name: &str,
+        key: &str,
+        cache: &StepCache,
+        workdir: &Path,
+        output: &CommandOutput,
+        duration: Duration,
+    ) -> Result<CacheEntry, WorkflowError> {
+        let entry_dir = self.entry_dir(workflow_name, key);
+        if entry_dir.exists() {
+            std::fs::remove_dir_all(&entry_dir)
+                .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        }
+        let files_dir = entry_dir.join(FILES_DIR);
+        std::fs::create_dir_all(&files_dir)
+            .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+
+        for path in &cache.paths {
+            let relative = relative_cache_path(path)?;
+            let source = workdir.join(&relative);
+            if source.exists() {
+                copy_tree(&source, &files_dir.join(&relative))?;
+            }
+        }
+
+        let now = Utc::now();
+        let entry = CacheEntry {
+            workflow_name: workflow_name.to_string(),
+            key: key.to_string(),
+            output: output.clone(),
+            duration_ms: duration.as_millis() as u64,
+            created_at: now,
+            last_accessed: now,
+            size_bytes: dir_size(&files_dir),
+        };
+        write_entry(&entry_dir, &entry)?;
+
+        self.prune(self.max_bytes)?;
+        Ok(entry)
+    }
+
+    /// All readable entries with their directories
+    pub fn entries(&self) -> Vec<(PathBuf, CacheEntry)> {
+        walkdir::WalkDir::new(&self.root)
+            .min_depth(3)
+            .max_depth(3)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name() == ENTRY_FILE)
+            .filter_map(|e| {
+                let content = std::fs::read_to_string(e.path()).ok()?;
+                let entry = serde_json::from_str(&content).ok()?;
+                Some((e.path().parent()?.to_path_buf(), entry))
+            })
+            .collect()
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.entries().iter().map(|(_, entry)| entry.size_bytes).sum()
+    }
+
+    /// Least-recently-used entries that must go for the cache to hold at most `max_bytes`
+    pub fn eviction_candidates(&self, max_bytes: u64) -> Vec<(PathBuf, CacheEntry)> {
+        let mut entries = self.entries();
+        entries.sort_by_key(|(_, entry)| entry.last_accessed);
+
+        let mut total: u64 = entries.iter().map(|(_, entry)| entry.size_bytes).sum();
+        entries
+            .into_iter()
+            .take_while(|(_, entry)| {
+                let over = total > max_bytes;
+                total = total.saturating_sub(entry.size_bytes);
+                over
+            })
+            .collect()
+    }
+
+    /// Entry directories without a readable `entry.json`, left behind by an
+    /// interrupted store or prune. Lookups already treat them as misses.
+    pub fn orphans(&self) -> Vec<PathBuf> {
+        walkdir::WalkDir::new(&self.root)
+            .min_depth(2)
+            .max_depth(2)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+            .map(|e| e.into_path())
+            .filter(|dir| {
+                std::fs::read_to_string(dir.join(ENTRY_FILE))
+                    .ok()
+                    .and_then(|content| serde_json::from_str::<CacheEntry>(&content).ok())
+                    .is_none()
+            })
+            .collect()
+    }
+
+    /// Remove least-recently-used entries until the cache holds at most `max_bytes`
+    pub fn prune(&self, max_bytes: u64) -> Result<PruneReport, WorkflowError> {
+        let mut report = PruneReport::default();
+        for (dir, entry) in self.eviction_candidates(max_bytes) {
+            remove_entry_dir(&dir)?;
+            report.removed += 1;
+            report.freed_bytes += entry.size_bytes;
+        }
+        Ok(report)
+    }
+}
+
+/// Delete an entry's metadata before its files, so an interruption leaves an
+/// orphan that reads as a miss rather than an entry pointing at missing files.
+/// Already-deleted parts are fine, which lets a later run finish the job.
+pub fn remove_entry_dir(dir: &Path) -> Result<(), WorkflowError> {
+    ignore_not_found(std::fs::remove_file(dir.join(ENTRY_FILE)))?;
+    ignore_not_found(std::fs::remove_dir_all(dir))
+}
+
+fn ignore_not_found(result: std::io::Result<()>) -> Result<(), WorkflowError> {
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(WorkflowError::IoError(e.to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// Render a cache key template against the arguments and the files under `workdir`
+pub fn compute_cache_key(
+    template: &str,
+    arguments: &HashMap<String, String>,
+    workdir: &Path,
+) -> Result<String, WorkflowError> {
+    let placeholder = regex::Regex::new(r"\{\{\s*([^}]+?)\s*\}\}").unwrap();
+    let hash_call = regex::Regex::new(r#"^hash\(\s*files\s*=\s*['"]([^'"]*)['"]\s*\)$"#).unwrap();
+
+    let mut key = String::with_capacity(template.len());
+    let mut last = 0;
+
+    for caps in placeholder.captures_iter(template) {
+        let whole = caps.get(0).unwrap();
+        key.push_str(&template[last..whole.start()]);
+        last = whole.end();
+
+        let expr = &caps[1];
+        if let Some(hash) = hash_call.captures(expr) {
+            let files: Vec<&str> = hash[1].split(',').map(str::trim).filter(|f| !f.is_empty()).collect();
+            key.push_str(&format!("{:016x}", hash_files(&files, workdir)?));
+        } else if let Some(value) = arguments.get(expr) {
+            key.push_str(value);
+        } else {
+            return Err(WorkflowError::ValidationError(
+                format!("Unknown cache key expression '{}'", expr)
+            ));
+        }
+    }
+    key.push_str(&template[last..]);
+
+    Ok(key)
+}
+
+fn hash_files(files: &[&str], workdir: &Path) -> Result<u64, WorkflowError> {
+    let mut hash = FNV_OFFSET;
+
+    for file in files {
+        let root = workdir.join(file);
+        hash = fnv1a(file.as_bytes(), hash);
+
+        if !root.exists() {
+            // A missing input is part of the key, so creating it later is a miss
+            hash = fnv1a(b"\0missing", hash);
+            continue;
+        }
+
+        for entry in walkdir::WalkDir::new(&root).sort_by_file_name() {
+            let entry = entry.map_err(|e| WorkflowError::IoError(e.to_string()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            hash = fnv1a(relative.to_string_lossy().as_bytes(), hash);
+            let content = std::fs::read(entry.path())
+                .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+            hash = fnv1a(&content, hash);
+        }
+    }
+
+    Ok(hash)
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// FNV-1a; stable across builds, unlike `DefaultHasher`, so keys survive upgrades.
+fn fnv1a(bytes: &[u8], mut hash: u64) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Cached paths must stay inside the working directory
+fn relative_cache_path(path: &Path) -> Result<PathBuf, WorkflowError> {
+    let escapes = path.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes {
+        return Err(WorkflowError::ValidationError(
+            format!("Cache path '{}' must be relative to the working directory", path.display())
+        ));
+    }
+    Ok(path.to_path_buf())
+}
+
+fn copy_tree(source: &Path, destination: &Path) -> Result<(), WorkflowError> {
+    if !source.exists() {
+        return Ok(());
+    }
+
+    for entry in walkdir::WalkDir::new(source) {
+        let entry = entry.map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        let relative = entry.path().strip_prefix(source).unwrap_or(entry.path());
+        let target = destination.join(relative);
+
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)
+                .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        } else {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+            }
+            std::fs::copy(entry.path(), &target)
+                .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+fn write_entry(entry_dir: &Path, entry: &CacheEntry) -> Result<(), WorkflowError> {
+    let content = serde_json::to_string_pretty(entry)
+        .map_err(|e| WorkflowError::ParseError(e.to_string()))?;
+    std::fs::write(entry_dir.join(ENTRY_FILE), content)
+        .map_err(|e| WorkflowError::IoError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn output(stdout: &str) -> CommandOutput {
+        CommandOutput {
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            exit_code: 0,
+        }
+    }
+
+    fn step_cache() -> StepCache {
+        StepCache {
+            key: "build-{{profile}}-{{hash(files='Cargo.lock')}}".to_string(),
+            paths: vec![PathBuf::from("target")],
+        }
+    }
+
+    #[test]
+    fn test_hit_until_input_file_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let workdir = temp_dir.path().join("project");
+        std::fs::create_dir_all(workdir.join("target")).unwrap();
+        std::fs::write(workdir.join("Cargo.lock"), "serde 1.0.0").unwrap();
+        std::fs::write(workdir.join("target/app"), "binary v1").unwrap();
+
+        let cache = WorkflowCache::with_dir(temp_dir.path().join("cache"), DEFAULT_MAX_CACHE_BYTES);
+        let args = HashMap::from([("profile".to_string(), "release".to_string())]);
+        let step = step_cache();
+
+        let key = compute_cache_key(&step.key, &args, &workdir).unwrap();
+        assert!(cache.lookup("build", &key).is_none());
+
+        cache.store("build", &key, &step, &workdir, &output("built"), Duration::from_secs(42)).unwrap();
+
+        // Same inputs: hit, restoring the declared paths
+        std::fs::remove_dir_all(workdir.join("target")).unwrap();
+        let key_again = compute_cache_key(&step.key, &args, &workdir).unwrap();
+        let entry = cache.lookup("build", &key_again).expect("cache hit");
+        assert_eq!(entry.output.stdout, "built");
+        assert_eq!(entry.original_duration(), Duration::from_secs(42));
+        cache.restore(&entry, &workdir).unwrap();
+        assert_eq!(std::fs::read_to_string(workdir.join("target/app")).unwrap(), "binary v1");
+
+        // Changed input file: miss
+        std::fs::write(workdir.join("Cargo.lock"), "serde 1.0.1").unwrap();
+        let changed = compute_cache_key(&step.key, &args, &workdir).unwrap();
+        assert_ne!(changed, key);
+        assert!(cache.lookup("build", &changed).is_none());
+
+        // Changed parameter value: miss
+        let debug = HashMap::from([("profile".to_string(), "debug".to_string())]);
+        std::fs::write(workdir.join("Cargo.lock"), "serde 1.0.0").unwrap();
+        assert!(cache.lookup("build", &compute_cache_key(&step.key, &debug, &workdir).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_prune_evicts_least_recently_used() {
+        let temp_dir = TempDir::new().unwrap();
+        let workdir = temp_dir.path().join("project");
+        std::fs::create_dir_all(workdir.join("target")).unwrap();
+        std::fs::write(workdir.join("target/app"), "0123456789").unwrap();
+
+        let cache = WorkflowCache::with_dir(temp_dir.path().join("cache"), DEFAULT_MAX_CACHE_BYTES);
+        let step = step_cache();
+        let old = cache.store("build", "old", &step, &workdir, &output(""), Duration::ZERO).unwrap();
+        let new = cache.store("build", "new", &step, &workdir, &output(""), Duration::ZERO).unwrap();
+        cache.restore(&new, &workdir).unwrap();
+
+        let report = cache.prune(old.size_bytes).unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(cache.lookup("build", "old").is_none());
+        assert!(cache.lookup("build", "new").is_some());
+    }
+
+    #[test]
+    fn test_unknown_key_expression_and_escaping_paths_are_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(compute_cache_key("{{missing}}", &HashMap::new(), temp_dir.path()).is_err());
+        assert!(relative_cache_path(Path::new("../outside")).is_err());
+        assert!(relative_cache_path(Path::new("/etc")).is_err());
+    }
+}
@@ -1,11 +1,24 @@
-use super::{Workflow, WorkflowExecution, WorkflowError, Shell, ArgumentType};
+use super::{Workflow, WorkflowExecution, WorkflowError, Shell, ArgumentType, WorkflowCache, compute_cache_key};
+use super::steps::{self, StepEvent, StepKind, StepsReport, WaitCondition, WorkflowStep};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::process::{Command, Stdio};
 use regex::Regex;
+use tokio::sync::mpsc;
+use crate::agent_mode_eval::ai_client::{AiClient, AiMessage};
+use crate::shell::EnvLayers;
+use crate::read_only::ReadOnly;
+use crate::tee::{Tee, TeeOptions};
 
 pub struct WorkflowExecutor {
     current_shell: Shell,
     environment: HashMap<String, String>,
+    env_profiles: HashMap<String, HashMap<String, String>>,
+    active_profile: Option<String>,
+    cache: Option<WorkflowCache>,
+    read_only: ReadOnly,
+    allow_undeclared: bool,
+    ai: Option<AiClient>,
 }
 
 impl WorkflowExecutor {
@@ -13,9 +26,53 @@ impl WorkflowExecutor {
         Self {
             current_shell: shell,
             environment: std::env::vars().collect(),
+            env_profiles: HashMap::new(),
+            active_profile: None,
+            cache: None,
+            read_only: ReadOnly::new(),
+            allow_undeclared: false,
+            ai: None,
         }
     }
 
+    /// Answer `ai_prompt` steps with `client`; without one they fail
+    pub fn with_ai(mut self, client: AiClient) -> Self {
+        self.ai = Some(client);
+        self
+    }
+
+    /// Refuse to run anything while `read_only` is on
+    pub fn with_read_only(mut self, read_only: ReadOnly) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Run imported workflows beyond their declared permissions. Only for a
+    /// single run the user has explicitly confirmed.
+    pub fn allow_undeclared(mut self) -> Self {
+        self.allow_undeclared = true;
+        self
+    }
+
+    /// Enable step caching for workflows that declare a `cache:` section.
+    /// Leaving this unset (e.g. `--no-cache`) always runs the command.
+    pub fn with_cache(mut self, cache: WorkflowCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Make env profiles available to workflows, with `active` used when a
+    /// workflow does not name its own profile
+    pub fn with_env_profiles(
+        mut self,
+        profiles: HashMap<String, HashMap<String, String>>,
+        active: Option<String>,
+    ) -> Self {
+        self.env_profiles = profiles;
+        self.active_profile = active;
+        self
+    }
+
     /// Prepare workflow for execution by resolving arguments
     pub fn prepare_execution(
         &self,
@@ -32,39 +89,255 @@ impl WorkflowExecutor {
         
         // Substitute arguments in command
         let resolved_command = self.substitute_arguments(&workflow.command, &resolved_args)?;
+        let steps = workflow
+            .steps
+            .iter()
+            .map(|step| step.render(|text, command| self.fill_template(text, &resolved_args, command)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (injected_env, env) = self.resolve_environment(workflow, &resolved_args)?;
 
         Ok(WorkflowExecution {
             workflow: workflow.clone(),
             arguments: resolved_args,
             resolved_command,
             shell: self.current_shell.clone(),
+            injected_env,
+            env,
+            steps,
         })
     }
 
+    /// Build the environment for a workflow run. See `EnvLayers` for precedence;
+    /// the workflow's `env:` map is the step layer.
+    fn resolve_environment(
+        &self,
+        workflow: &Workflow,
+        arguments: &HashMap<String, String>,
+    ) -> Result<(HashMap<String, String>, HashMap<String, String>), WorkflowError> {
+        let profile = match workflow.env_profile.as_ref().or(self.active_profile.as_ref()) {
+            Some(name) => self.env_profiles
+                .get(name)
+                .cloned()
+                .ok_or_else(|| WorkflowError::EnvProfileNotFound(name.clone()))?,
+            None => HashMap::new(),
+        };
+
+        let step: HashMap<String, String> = workflow.env
+            .iter()
+            .map(|(key, value)| (key.clone(), interpolate_env_value(value, arguments)))
+            .collect();
+
+        let mut injected = profile.clone();
+        injected.extend(step.clone());
+
+        let env = EnvLayers {
+            inherited: self.environment.clone(),
+            profile,
+            step,
+            invocation: HashMap::new(),
+        }
+        .resolve();
+
+        Ok((injected, env))
+    }
+
     /// Execute a workflow
     pub async fn execute_workflow(
         &self,
         execution: &WorkflowExecution,
     ) -> Result<WorkflowExecutionResult, WorkflowError> {
+        self.read_only.check().map_err(|e| WorkflowError::ReadOnly(e.to_string()))?;
+        if !self.allow_undeclared {
+            let workdir = std::env::current_dir().map_err(|e| WorkflowError::IoError(e.to_string()))?;
+            execution.workflow.check_permissions(&execution.resolved_command, &workdir)?;
+        }
         let start_time = std::time::Instant::now();
 
-        let output = match self.current_shell {
-            Shell::Bash => self.execute_bash(&execution.resolved_command).await?,
-            Shell::Zsh => self.execute_zsh(&execution.resolved_command).await?,
-            Shell::Fish => self.execute_fish(&execution.resolved_command).await?,
+        let cached_step = match (&self.cache, &execution.workflow.cache) {
+            (Some(cache), Some(step)) => {
+                let workdir = std::env::current_dir()
+                    .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+                let key = compute_cache_key(&step.key, &execution.arguments, &workdir)?;
+                Some((cache, step, workdir, key))
+            }
+            _ => None,
         };
 
+        if let Some((cache, _, workdir, key)) = &cached_step {
+            if let Some(entry) = cache.lookup(&execution.workflow.name, key) {
+                cache.restore(&entry, workdir)?;
+                tee_output(&execution.workflow, &entry.output)?;
+                return Ok(WorkflowExecutionResult {
+                    workflow_name: execution.workflow.name.clone(),
+                    command: execution.resolved_command.clone(),
+                    success: entry.output.exit_code == 0,
+                    output: entry.output.clone(),
+                    execution_time: start_time.elapsed(),
+                    cached: true,
+                    original_duration: Some(entry.original_duration()),
+                });
+            }
+        }
+
+        let output = self.run_shell(&execution.resolved_command, &execution.env).await?;
+
         let execution_time = start_time.elapsed();
+        tee_output(&execution.workflow, &output)?;
+
+        // Only successful runs are worth replaying
+        if let Some((cache, step, workdir, key)) = &cached_step {
+            if output.exit_code == 0 {
+                if let Err(e) = cache.store(&execution.workflow.name, key, step, workdir, &output, execution_time) {
+                    eprintln!("Failed to cache workflow step '{}': {}", execution.workflow.name, e);
+                }
+            }
+        }
 
         Ok(WorkflowExecutionResult {
             workflow_name: execution.workflow.name.clone(),
             command: execution.resolved_command.clone(),
+            success: output.exit_code == 0,
             output,
             execution_time,
-            success: true, // This would be determined by the actual execution
+            cached: false,
+            original_duration: None,
         })
     }
 
+    /// Run a workflow's steps in order, reporting progress on `events`.
+    /// Everything the steps would do is checked against the workflow's
+    /// permissions before the first one starts.
+    pub async fn run_steps(
+        &self,
+        execution: &WorkflowExecution,
+        events: &mpsc::Sender<StepEvent>,
+    ) -> Result<StepsReport, WorkflowError> {
+        self.read_only.check().map_err(|e| WorkflowError::ReadOnly(e.to_string()))?;
+        let workdir = std::env::current_dir().map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        if !self.allow_undeclared {
+            for step in &execution.steps {
+                self.check_step_permissions(&execution.workflow, step, &workdir)?;
+            }
+        }
+
+        let mut report = StepsReport::default();
+        for (index, step) in execution.steps.iter().enumerate() {
+            let _ = events.send(StepEvent::Started { index, name: step.label(index) }).await;
+            let started = std::time::Instant::now();
+            let result = self.run_step(step, &execution.env, &workdir).await;
+            report.completed += 1;
+
+            let (success, error) = match result {
+                Ok(output) => {
+                    if !output.is_empty() {
+                        let _ = events.send(StepEvent::Output { index, text: output }).await;
+                    }
+                    (true, None)
+                }
+                Err(e) => (false, Some(e.to_string())),
+            };
+            let duration_ms = started.elapsed().as_millis() as u64;
+            let _ = events.send(StepEvent::Finished { index, success, duration_ms, error }).await;
+            if success {
+                continue;
+            }
+            report.failed.push(index);
+            if !step.continue_on_error {
+                report.stopped_at = Some(index);
+                break;
+            }
+            let _ = events.send(StepEvent::Continuing { index }).await;
+        }
+        Ok(report)
+    }
+
+    /// Refuse a step an imported workflow didn't declare the permissions for
+    fn check_step_permissions(&self, workflow: &Workflow, step: &WorkflowStep, workdir: &std::path::Path) -> Result<(), WorkflowError> {
+        let denied = |reason: String| {
+            WorkflowError::PermissionDenied(format!(
+                "workflow '{}' {}; rerun with --allow-undeclared to grant it full access",
+                workflow.name, reason
+            ))
+        };
+        match &step.kind {
+            StepKind::Command { run } => workflow.check_permissions(run, workdir),
+            StepKind::WaitFor { command: Some(command), .. } => workflow.check_permissions(command, workdir),
+            StepKind::WaitFor { .. } => Ok(()),
+            StepKind::FileWrite { .. } | StepKind::AiPrompt { .. } if workflow.trust.is_full() => Ok(()),
+            StepKind::FileWrite { path, .. } => workflow.permissions.check_write(path, workdir).map_err(denied),
+            StepKind::AiPrompt { .. } if workflow.permissions.ai => Ok(()),
+            StepKind::AiPrompt { .. } => Err(denied("asks the assistant, which it doesn't declare".to_string())),
+        }
+    }
+
+    /// Run one step; its output, or why it failed
+    async fn run_step(
+        &self,
+        step: &WorkflowStep,
+        env: &HashMap<String, String>,
+        workdir: &std::path::Path,
+    ) -> Result<String, WorkflowError> {
+        match &step.kind {
+            StepKind::Command { run } => {
+                let output = self.run_shell(run, env).await?;
+                let text = format!("{}{}", output.stdout, output.stderr);
+                if output.exit_code == 0 {
+                    Ok(text)
+                } else {
+                    Err(WorkflowError::StepFailed(format!("exited with {}\n{}", output.exit_code, text.trim_end())))
+                }
+            }
+            StepKind::AiPrompt { prompt } => {
+                let client = self
+                    .ai
+                    .as_ref()
+                    .ok_or_else(|| WorkflowError::StepFailed("no AI provider is configured".to_string()))?;
+                let message = AiMessage { role: "user".to_string(), content: prompt.clone(), tool_calls: None, tool_call_id: None };
+                let response = client
+                    .complete(vec![message], None)
+                    .await
+                    .map_err(|e| WorkflowError::StepFailed(e.to_string()))?;
+                Ok(response.content)
+            }
+            StepKind::FileWrite { path, content, append } => {
+                let written = steps::write_file(workdir, path, content, *append)?;
+                Ok(format!("Wrote {} bytes to {}", content.len(), written.display()))
+            }
+            StepKind::WaitFor { timeout_secs, interval_secs, .. } => {
+                let condition = step.kind.wait_condition()?;
+                let interval = std::time::Duration::from_secs(*interval_secs);
+                let deadline = std::time::Instant::now() + std::time::Duration::from_secs(*timeout_secs);
+                loop {
+                    if self.condition_holds(&condition, env, workdir).await {
+                        return Ok(String::new());
+                    }
+                    if std::time::Instant::now() + interval > deadline {
+                        return Err(WorkflowError::StepFailed(format!("still waiting after {}s", timeout_secs)));
+                    }
+                    tokio::time::sleep(interval).await;
+                }
+            }
+        }
+    }
+
+    async fn condition_holds(&self, condition: &WaitCondition, env: &HashMap<String, String>, workdir: &std::path::Path) -> bool {
+        match condition {
+            WaitCondition::Command(command) => self.run_shell(command, env).await.is_ok_and(|output| output.exit_code == 0),
+            WaitCondition::File(path) => workdir.join(path).exists(),
+            WaitCondition::Port(port) => tokio::net::TcpStream::connect(("127.0.0.1", *port)).await.is_ok(),
+        }
+    }
+
+    /// Run `command` with the executor's shell
+    async fn run_shell(&self, command: &str, env: &HashMap<String, String>) -> Result<CommandOutput, WorkflowError> {
+        match self.current_shell {
+            Shell::Bash => self.execute_bash(command, env).await,
+            Shell::Zsh => self.execute_zsh(command, env).await,
+            Shell::Fish => self.execute_fish(command, env).await,
+        }
+    }
+
     /// Execute workflow in dry-run mode (show what would be executed)
     pub fn dry_run(&self, execution: &WorkflowExecution) -> WorkflowDryRun {
         WorkflowDryRun {
@@ -74,6 +347,7 @@ impl WorkflowExecutor {
             arguments: execution.arguments.clone(),
             shell: execution.shell.clone(),
             environment_vars: self.get_relevant_env_vars(&execution.resolved_command),
+            injected_env: execution.injected_env.clone(),
         }
     }
 
@@ -96,7 +370,7 @@ impl WorkflowExecutor {
             };
 
             // Validate argument value
-            self.validate_argument_value(arg_def, &value)?;
+            Self::validate_argument_value(arg_def, &value)?;
             resolved.insert(arg_def.name.clone(), value);
         }
 
@@ -111,8 +385,8 @@ impl WorkflowExecutor {
         Ok(resolved)
     }
 
-    fn validate_argument_value(
-        &self,
+    /// Check a value against its argument's type and options
+    pub fn validate_argument_value(
         arg_def: &super::WorkflowArgument,
         value: &str,
     ) -> Result<(), WorkflowError> {
@@ -189,14 +463,25 @@ impl WorkflowExecutor {
         command: &str,
         arguments: &HashMap<String, String>,
     ) -> Result<String, WorkflowError> {
-        let mut result = command.to_string();
+        self.fill_template(command, arguments, true)
+    }
+
+    /// Replace `{{name}}` placeholders, shell-quoting the values if `text`
+    /// is a command
+    fn fill_template(
+        &self,
+        text: &str,
+        arguments: &HashMap<String, String>,
+        command: bool,
+    ) -> Result<String, WorkflowError> {
+        let mut result = text.to_string();
 
         for (name, value) in arguments {
             let placeholder = format!("{{{{{}}}}}", name);
             
             // Escape shell special characters in the value
-            let escaped_value = self.escape_shell_value(value);
-            result = result.replace(&placeholder, &escaped_value);
+            let value = if command { self.escape_shell_value(value) } else { value.clone() };
+            result = result.replace(&placeholder, &value);
         }
 
         // Check for any remaining unresolved placeholders
@@ -228,10 +513,12 @@ impl WorkflowExecutor {
         }
     }
 
-    async fn execute_bash(&self, command: &str) -> Result<CommandOutput, WorkflowError> {
+    async fn execute_bash(&self, command: &str, env: &HashMap<String, String>) -> Result<CommandOutput, WorkflowError> {
         let output = Command::new("bash")
             .arg("-c")
             .arg(command)
+            .env_clear()
+            .envs(env)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .output()
@@ -244,10 +531,12 @@ impl WorkflowExecutor {
         })
     }
 
-    async fn execute_zsh(&self, command: &str) -> Result<CommandOutput, WorkflowError> {
+    async fn execute_zsh(&self, command: &str, env: &HashMap<String, String>) -> Result<CommandOutput, WorkflowError> {
         let output = Command::new("zsh")
             .arg("-c")
             .arg(command)
+            .env_clear()
+            .envs(env)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .output()
@@ -260,10 +549,12 @@ impl WorkflowExecutor {
         })
     }
 
-    async fn execute_fish(&self, command: &str) -> Result<CommandOutput, WorkflowError> {
+    async fn execute_fish(&self, command: &str, env: &HashMap<String, String>) -> Result<CommandOutput, WorkflowError> {
         let output = Command::new("fish")
             .arg("-c")
             .arg(command)
+            .env_clear()
+            .envs(env)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .output()
@@ -313,9 +604,13 @@ pub struct WorkflowExecutionResult {
     pub output: CommandOutput,
     pub execution_time: std::time::Duration,
     pub success: bool,
+    /// Result was restored from the step cache instead of running the command
+    pub cached: bool,
+    /// Duration of the run that populated the cache, when `cached`
+    pub original_duration: Option<std::time::Duration>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandOutput {
     pub stdout: String,
     pub stderr: String,
@@ -330,4 +625,125 @@ pub struct WorkflowDryRun {
     pub arguments: HashMap<String, String>,
     pub shell: Shell,
     pub environment_vars: HashMap<String, String>,
+    pub injected_env: HashMap<String, String>,
+}
+
+/// Replace `${name}` references to workflow arguments. References to names
+/// that are not arguments are left for the shell to expand.
+pub fn interpolate_env_value(value: &str, arguments: &HashMap<String, String>) -> String {
+    let reference = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+    reference
+        .replace_all(value, |caps: &regex::Captures| {
+            arguments
+                .get(&caps[1])
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Copy a step's output to its `tee:` file: stdout, then stderr
+fn tee_output(workflow: &Workflow, output: &CommandOutput) -> Result<(), WorkflowError> {
+    let Some(path) = &workflow.tee else {
+        return Ok(());
+    };
+    let to_error = |e: crate::tee::TeeError| WorkflowError::IoError(e.to_string());
+    let options = TeeOptions { strip_ansi: true, backfill: false };
+    let mut tee = Tee::open(path, options, &[]).map_err(to_error)?;
+    tee.write_text(&output.stdout).map_err(to_error)?;
+    tee.write_text(&output.stderr).map_err(to_error)?;
+    tee.close().map_err(to_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workflow_with_env(env: &[(&str, &str)], profile: Option<&str>) -> Workflow {
+        let mut workflow = Workflow::from_yaml(
+            "name: deploy\ncommand: ./deploy.sh {{target}}\narguments:\n  - name: target\n    default_value: staging\n",
+        ).unwrap();
+        workflow.env = env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        workflow.env_profile = profile.map(|p| p.to_string());
+        workflow
+    }
+
+    #[test]
+    fn test_env_interpolates_arguments() {
+        let executor = WorkflowExecutor::new(Shell::Bash);
+        let workflow = workflow_with_env(&[("DEPLOY_TARGET", "${target}"), ("OTHER", "${HOME}")], None);
+
+        let execution = executor.prepare_execution(&workflow, HashMap::new()).unwrap();
+        assert_eq!(execution.env["DEPLOY_TARGET"], "staging");
+        assert_eq!(execution.injected_env["OTHER"], "${HOME}");
+    }
+
+    #[test]
+    fn test_step_env_overrides_profile() {
+        let mut profiles = HashMap::new();
+        profiles.insert("prod".to_string(), HashMap::from([
+            ("REGION".to_string(), "eu-west-1".to_string()),
+            ("STAGE".to_string(), "prod".to_string()),
+        ]));
+        let executor = WorkflowExecutor::new(Shell::Bash).with_env_profiles(profiles, None);
+        let workflow = workflow_with_env(&[("STAGE", "canary")], Some("prod"));
+
+        let execution = executor.prepare_execution(&workflow, HashMap::new()).unwrap();
+        assert_eq!(execution.env["REGION"], "eu-west-1");
+        assert_eq!(execution.env["STAGE"], "canary");
+    }
+
+    #[tokio::test]
+    async fn test_step_output_is_teed_to_a_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let log = temp_dir.path().join("step.log");
+        let mut workflow = Workflow::from_yaml("name: greet\ncommand: printf '\\033[1mhello\\033[0m\\n'\n").unwrap();
+        workflow.tee = Some(log.clone());
+
+        let executor = WorkflowExecutor::new(Shell::Bash);
+        let execution = executor.prepare_execution(&workflow, HashMap::new()).unwrap();
+        let result = executor.execute_workflow(&execution).await.unwrap();
+
+        assert_eq!(result.output.stdout, "\x1b[1mhello\x1b[0m\n");
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "hello\n");
+    }
+
+    #[tokio::test]
+    async fn test_steps_stop_at_first_failure_unless_allowed() {
+        let workflow = Workflow::from_yaml(
+            "name: steps\nparameters:\n  - name: who\n    default: it's me\nsteps:\n  \
+             - type: command\n    run: echo {{who}}\n  \
+             - type: command\n    run: exit 3\n    continue_on_error: true\n  \
+             - type: command\n    run: exit 1\n  \
+             - type: command\n    run: echo unreachable\n",
+        ).unwrap();
+        let executor = WorkflowExecutor::new(Shell::Bash);
+        let execution = executor.prepare_execution(&workflow, HashMap::new()).unwrap();
+        assert_eq!(execution.steps[0].kind, StepKind::Command { run: "echo 'it'\"'\"'s me'".to_string() });
+
+        let (tx, mut rx) = mpsc::channel(32);
+        let report = executor.run_steps(&execution, &tx).await.unwrap();
+        drop(tx);
+        assert_eq!(report, StepsReport { completed: 3, failed: vec![1, 2], stopped_at: Some(2) });
+
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        assert_eq!(events[1], StepEvent::Output { index: 0, text: "it's me\n".to_string() });
+        assert!(events.contains(&StepEvent::Continuing { index: 1 }));
+        assert!(!events.iter().any(|event| matches!(event, StepEvent::Started { index: 3, .. })));
+    }
+
+    #[test]
+    fn test_unknown_profile_is_an_error() {
+        let executor = WorkflowExecutor::new(Shell::Bash);
+        let workflow = workflow_with_env(&[], Some("missing"));
+
+        assert!(matches!(
+            executor.prepare_execution(&workflow, HashMap::new()),
+            Err(WorkflowError::EnvProfileNotFound(_))
+        ));
+    }
 }
@@ -0,0 +1,125 @@
+//! Collecting a workflow's parameters before it runs.
+//!
+//! Fields start at the parameter's default, booleans at `false`, and are
+//! checked with the rules the executor applies, so the palette form and the
+//! CLI prompt can point at a bad value before anything runs.
+
+use std::collections::HashMap;
+
+use super::{ArgumentType, Workflow, WorkflowArgument, WorkflowExecutor};
+
+#[derive(Debug, Clone)]
+pub struct ParameterForm {
+    workflow: Workflow,
+    values: HashMap<String, String>,
+}
+
+impl ParameterForm {
+    pub fn new(workflow: Workflow) -> Self {
+        let values = workflow
+            .arguments
+            .iter()
+            .map(|argument| (argument.name.clone(), initial_value(argument)))
+            .collect();
+        Self { workflow, values }
+    }
+
+    pub fn workflow(&self) -> &Workflow {
+        &self.workflow
+    }
+
+    pub fn value(&self, name: &str) -> &str {
+        self.values.get(name).map(String::as_str).unwrap_or("")
+    }
+
+    /// Whether a boolean field is on
+    pub fn is_checked(&self, name: &str) -> bool {
+        matches!(self.value(name).to_lowercase().as_str(), "true" | "1" | "yes")
+    }
+
+    pub fn set(&mut self, name: String, value: String) {
+        self.values.insert(name, value);
+    }
+
+    /// What's wrong with a field's value, if anything
+    pub fn error(&self, argument: &WorkflowArgument) -> Option<String> {
+        let value = self.value(&argument.name);
+        if argument.required && value.trim().is_empty() {
+            return Some("Required".to_string());
+        }
+        WorkflowExecutor::validate_argument_value(argument, value).err().map(|e| e.to_string())
+    }
+
+    /// Each field with a problem, in order, with the problem
+    pub fn errors(&self) -> Vec<(String, String)> {
+        self.workflow
+            .arguments
+            .iter()
+            .filter_map(|argument| self.error(argument).map(|error| (argument.name.clone(), error)))
+            .collect()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors().is_empty()
+    }
+
+    /// The values to run with; fields left empty are not passed
+    pub fn arguments(&self) -> HashMap<String, String> {
+        self.values
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+}
+
+fn initial_value(argument: &WorkflowArgument) -> String {
+    match (&argument.default_value, &argument.arg_type) {
+        (Some(default), _) => default.clone(),
+        (None, ArgumentType::Boolean) => "false".to_string(),
+        (None, _) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflows::Shell;
+
+    const DEPLOY: &str = r#"
+name: deploy
+command: ./deploy.sh --env {{env}} --replicas {{replicas}} --dry-run={{dry_run}} {{tag}}
+arguments:
+  - name: env
+    arg_type: enum
+    options: [staging, production]
+    default_value: staging
+  - name: replicas
+    arg_type: number
+    default_value: "2"
+  - name: dry_run
+    arg_type: boolean
+  - name: tag
+    required: true
+"#;
+
+    #[test]
+    fn test_defaults_prefill_and_required_fields_block() {
+        let mut form = ParameterForm::new(Workflow::from_yaml(DEPLOY).unwrap());
+        assert_eq!(form.value("env"), "staging");
+        assert_eq!(form.value("dry_run"), "false");
+        assert_eq!(form.errors(), vec![("tag".to_string(), "Required".to_string())]);
+
+        form.set("tag".to_string(), "v1.2".to_string());
+        form.set("replicas".to_string(), "two".to_string());
+        assert_eq!(form.errors().len(), 1);
+        assert_eq!(form.errors()[0].0, "replicas");
+
+        form.set("replicas".to_string(), "3".to_string());
+        assert!(form.is_valid());
+        let execution = WorkflowExecutor::new(Shell::Bash)
+            .prepare_execution(form.workflow(), form.arguments())
+            .unwrap();
+        assert_eq!(execution.resolved_command, "./deploy.sh --env 'staging' --replicas '3' --dry-run='false' 'v1.2'");
+    }
+}
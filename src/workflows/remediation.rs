@@ -0,0 +1,418 @@
+//! What to do when a workflow step fails. The failing command, the tail of
+//! its stderr and the workflow context are offered to the assistant, which
+//! may suggest up to `MAX_SUGGESTIONS` remediations; without AI the user gets
+//! plain retry / skip / abort. Nothing runs until the user picks an option,
+//! and every pick is kept in the run's record.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use super::{CommandOutput, WorkflowError, WorkflowExecution, WorkflowExecutionResult, WorkflowExecutor};
+use crate::agent_mode_eval::ai_client::{AiClient, AiClientError, AiMessage};
+use crate::redaction::Redactor;
+
+/// Options the assistant may propose; Abort is always offered on top
+pub const MAX_SUGGESTIONS: usize = 3;
+/// Lines of stderr sent along with the failure
+const STDERR_TAIL_LINES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Remediation {
+    Retry,
+    /// Run a different command in place of the failed one
+    RetryWith { command: String },
+    /// Leave the step failed and carry on with the workflow
+    Skip,
+    Abort,
+}
+
+impl Remediation {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Remediation::Retry => "Retry step",
+            Remediation::RetryWith { .. } => "Retry with changes",
+            Remediation::Skip => "Skip and continue",
+            Remediation::Abort => "Abort workflow",
+        }
+    }
+
+    /// Line diff from the failed command, for `RetryWith`
+    pub fn diff(&self, original: &str) -> Option<String> {
+        let Remediation::RetryWith { command } = self else {
+            return None;
+        };
+        let old: Vec<&str> = original.lines().collect();
+        let new: Vec<&str> = command.lines().collect();
+        let removed = old.iter().filter(|line| !new.contains(line)).map(|line| format!("- {}", line));
+        let kept_or_added = new.iter().map(|line| {
+            if old.contains(line) { format!("  {}", line) } else { format!("+ {}", line) }
+        });
+        Some(removed.chain(kept_or_added).collect::<Vec<_>>().join("\n"))
+    }
+}
+
+/// Plain options offered when AI is off, unconfigured or unhelpful
+pub fn fallback_options() -> Vec<Remediation> {
+    vec![Remediation::Retry, Remediation::Skip, Remediation::Abort]
+}
+
+/// Everything the assistant is told about a failed step
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepFailure {
+    pub workflow_name: String,
+    pub description: Option<String>,
+    pub command: String,
+    pub arguments: HashMap<String, String>,
+    pub exit_code: i32,
+    pub stderr_tail: String,
+}
+
+impl StepFailure {
+    pub fn new(execution: &WorkflowExecution, output: &CommandOutput) -> Self {
+        Self {
+            workflow_name: execution.workflow.name.clone(),
+            description: execution.workflow.description.clone(),
+            command: execution.resolved_command.clone(),
+            arguments: execution.arguments.clone(),
+            exit_code: output.exit_code,
+            stderr_tail: tail(&output.stderr, STDERR_TAIL_LINES),
+        }
+    }
+
+    fn prompt(&self, redactor: &Redactor) -> String {
+        let mut arguments: Vec<String> = self.arguments.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        arguments.sort();
+        let prompt = format!(
+            "Workflow: {}\nDescription: {}\nArguments: {}\nFailed command: {}\nExit code: {}\nLast lines of stderr:\n{}",
+            self.workflow_name,
+            self.description.as_deref().unwrap_or("(none)"),
+            if arguments.is_empty() { "(none)".to_string() } else { arguments.join(", ") },
+            self.command,
+            self.exit_code,
+            self.stderr_tail,
+        );
+        redactor.redact(&prompt)
+    }
+}
+
+fn tail(text: &str, lines: usize) -> String {
+    let all: Vec<&str> = text.lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+const SYSTEM_PROMPT: &str = "A step of a terminal workflow failed. Suggest at most three ways to recover. \
+Reply with only a JSON array whose items are {\"action\":\"retry\"}, {\"action\":\"retry_with\",\"command\":\"<replacement command>\"} \
+or {\"action\":\"skip\"}, best first.";
+
+/// Ask the assistant for remediations. The result always ends with Abort;
+/// an answer with nothing usable gives the fallback options.
+pub async fn suggest(client: &AiClient, failure: &StepFailure, redactor: &Redactor) -> Result<Vec<Remediation>, AiClientError> {
+    let messages = vec![
+        AiMessage { role: "system".to_string(), content: SYSTEM_PROMPT.to_string(), tool_calls: None, tool_call_id: None },
+        AiMessage { role: "user".to_string(), content: failure.prompt(redactor), tool_calls: None, tool_call_id: None },
+    ];
+    let response = client.complete(messages, None).await?;
+    Ok(parse_suggestions(&response.content, &failure.command))
+}
+
+/// Read the assistant's JSON array, tolerating prose or code fences around it
+pub fn parse_suggestions(reply: &str, failed_command: &str) -> Vec<Remediation> {
+    let parsed: Vec<serde_json::Value> = match (reply.find('['), reply.rfind(']')) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&reply[start..=end]).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    let mut options = Vec::new();
+    for remediation in parsed.into_iter().filter_map(|item| serde_json::from_value::<Remediation>(item).ok()) {
+        let remediation = match remediation {
+            Remediation::RetryWith { command } if command.trim().is_empty() => continue,
+            Remediation::RetryWith { command } if command.trim() == failed_command.trim() => Remediation::Retry,
+            Remediation::Abort => continue,
+            other => other,
+        };
+        if !options.contains(&remediation) {
+            options.push(remediation);
+        }
+    }
+    if options.is_empty() {
+        return fallback_options();
+    }
+    options.truncate(MAX_SUGGESTIONS);
+    options.push(Remediation::Abort);
+    options
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Succeeded,
+    Skipped,
+    Aborted,
+    /// Failed with no remediation chosen
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemediationChoice {
+    /// Command that failed
+    pub command: String,
+    pub exit_code: i32,
+    pub remediation: Remediation,
+    pub suggested_by_ai: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowRunRecord {
+    pub workflow_name: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    /// Every command that ran, in order
+    pub commands: Vec<String>,
+    pub remediations: Vec<RemediationChoice>,
+    pub outcome: RunOutcome,
+}
+
+pub enum StepOutcome {
+    Succeeded(WorkflowExecutionResult),
+    Failed(StepFailure, WorkflowExecutionResult),
+}
+
+/// A workflow run that stops at a failed step until a remediation is chosen
+pub struct WorkflowRun {
+    execution: WorkflowExecution,
+    record: WorkflowRunRecord,
+    failure: Option<StepFailure>,
+}
+
+impl WorkflowRun {
+    pub fn new(execution: WorkflowExecution) -> Self {
+        let record = WorkflowRunRecord {
+            workflow_name: execution.workflow.name.clone(),
+            started_at: chrono::Utc::now(),
+            commands: Vec::new(),
+            remediations: Vec::new(),
+            outcome: RunOutcome::Failed,
+        };
+        Self { execution, record, failure: None }
+    }
+
+    pub fn execution(&self) -> &WorkflowExecution {
+        &self.execution
+    }
+
+    pub async fn run_step(&mut self, executor: &WorkflowExecutor) -> Result<StepOutcome, WorkflowError> {
+        self.record.commands.push(self.execution.resolved_command.clone());
+        let result = executor.execute_workflow(&self.execution).await?;
+        if result.success {
+            self.failure = None;
+            self.record.outcome = RunOutcome::Succeeded;
+            return Ok(StepOutcome::Succeeded(result));
+        }
+        let failure = StepFailure::new(&self.execution, &result.output);
+        self.failure = Some(failure.clone());
+        self.record.outcome = RunOutcome::Failed;
+        Ok(StepOutcome::Failed(failure, result))
+    }
+
+    /// Apply the user's pick for the last failure. Returns whether the step
+    /// should run again.
+    pub fn choose(&mut self, remediation: Remediation, suggested_by_ai: bool) -> bool {
+        let Some(failure) = self.failure.take() else {
+            return false;
+        };
+        self.record.remediations.push(RemediationChoice {
+            command: failure.command,
+            exit_code: failure.exit_code,
+            remediation: remediation.clone(),
+            suggested_by_ai,
+        });
+
+        match remediation {
+            Remediation::Retry => true,
+            Remediation::RetryWith { command } => {
+                self.execution.resolved_command = command;
+                true
+            }
+            Remediation::Skip => {
+                self.record.outcome = RunOutcome::Skipped;
+                false
+            }
+            Remediation::Abort => {
+                self.record.outcome = RunOutcome::Aborted;
+                false
+            }
+        }
+    }
+
+    pub fn record(&self) -> &WorkflowRunRecord {
+        &self.record
+    }
+
+    pub fn finish(self) -> WorkflowRunRecord {
+        self.record
+    }
+}
+
+/// Append-only log of workflow runs
+pub struct RunHistory {
+    path: PathBuf,
+}
+
+impl RunHistory {
+    pub fn new() -> Result<Self, WorkflowError> {
+        let path = crate::config::ConfigPaths::resolve()
+            .map(|paths| paths.workflow_runs_file())
+            .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        Ok(Self { path })
+    }
+
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn append(&self, record: &WorkflowRunRecord) -> Result<(), WorkflowError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        }
+        let line = serde_json::to_string(record).map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| WorkflowError::IoError(e.to_string()))?;
+        writeln!(file, "{}", line).map_err(|e| WorkflowError::IoError(e.to_string()))
+    }
+
+    /// Recorded runs, oldest first; unreadable lines are skipped
+    pub fn load(&self) -> Vec<WorkflowRunRecord> {
+        std::fs::read_to_string(&self.path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use tempfile::TempDir;
+    use crate::agent_mode_eval::ai_client::AiProvider;
+    use crate::agent_mode_eval::AgentConfig;
+    use crate::workflows::{Shell, Workflow};
+
+    /// OpenAI-style endpoint that answers one request with `reply`
+    fn mock_provider(reply: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let length = text[..header_end]
+                        .lines()
+                        .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + length || read == 0 {
+                        break;
+                    }
+                }
+            }
+            let body = serde_json::json!({
+                "choices": [{ "message": { "role": "assistant", "content": reply } }]
+            })
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(), body
+            );
+            let _ = std::io::Write::write_all(&mut stream, response.as_bytes());
+        });
+        url
+    }
+
+    fn failing_run() -> (WorkflowExecutor, WorkflowRun) {
+        let executor = WorkflowExecutor::new(Shell::Bash);
+        let workflow = Workflow::from_yaml("name: migrate\ncommand: echo 'relation missing' >&2; exit 3\n").unwrap();
+        let execution = executor.prepare_execution(&workflow, HashMap::new()).unwrap();
+        (executor, WorkflowRun::new(execution))
+    }
+
+    #[tokio::test]
+    async fn test_run_record_captures_chosen_ai_remediation() {
+        let client = AiClient::new(AgentConfig {
+            provider: AiProvider::OpenAI,
+            api_key: Some("sk-test".to_string()),
+            base_url: Some(mock_provider(
+                r#"```json
+[{"action":"retry"},{"action":"retry_with","command":"echo migrated"},{"action":"skip"},{"action":"retry"}]
+```"#,
+            )),
+            ..AgentConfig::default()
+        })
+        .unwrap();
+        let (executor, mut run) = failing_run();
+
+        let StepOutcome::Failed(failure, _) = run.run_step(&executor).await.unwrap() else {
+            panic!("step should fail");
+        };
+        assert_eq!(failure.exit_code, 3);
+        assert_eq!(failure.stderr_tail, "relation missing");
+
+        let options = suggest(&client, &failure, &Redactor::new()).await.unwrap();
+        assert_eq!(options, vec![
+            Remediation::Retry,
+            Remediation::RetryWith { command: "echo migrated".to_string() },
+            Remediation::Skip,
+            Remediation::Abort,
+        ]);
+        assert_eq!(options[1].diff(&failure.command).unwrap(), "- echo 'relation missing' >&2; exit 3\n+ echo migrated");
+
+        assert!(run.choose(options[1].clone(), true));
+        assert!(matches!(run.run_step(&executor).await.unwrap(), StepOutcome::Succeeded(_)));
+
+        let record = run.finish();
+        assert_eq!(record.outcome, RunOutcome::Succeeded);
+        assert_eq!(record.commands, vec!["echo 'relation missing' >&2; exit 3", "echo migrated"]);
+        assert_eq!(record.remediations, vec![RemediationChoice {
+            command: "echo 'relation missing' >&2; exit 3".to_string(),
+            exit_code: 3,
+            remediation: Remediation::RetryWith { command: "echo migrated".to_string() },
+            suggested_by_ai: true,
+        }]);
+
+        let temp_dir = TempDir::new().unwrap();
+        let history = RunHistory::with_path(temp_dir.path().join("workflow-runs.jsonl"));
+        history.append(&record).unwrap();
+        assert_eq!(history.load(), vec![record]);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_skip_is_recorded() {
+        let (executor, mut run) = failing_run();
+        assert!(matches!(run.run_step(&executor).await.unwrap(), StepOutcome::Failed(..)));
+
+        assert!(!run.choose(Remediation::Skip, false));
+        let record = run.finish();
+        assert_eq!(record.outcome, RunOutcome::Skipped);
+        assert_eq!(record.remediations[0].remediation, Remediation::Skip);
+        assert!(!record.remediations[0].suggested_by_ai);
+    }
+
+    #[test]
+    fn test_unusable_reply_falls_back() {
+        assert_eq!(parse_suggestions("I'm not sure what went wrong.", "make"), fallback_options());
+        assert_eq!(
+            parse_suggestions(r#"[{"action":"retry_with","command":"make"},{"action":"abort"}]"#, "make"),
+            vec![Remediation::Retry, Remediation::Abort]
+        );
+    }
+}
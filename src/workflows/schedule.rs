@@ -0,0 +1,494 @@
+//! Running workflows on a schedule while the app is open.
+//!
+//! A workflow with a `schedule:` section runs every `every` (`30s`, `15m`,
+//! `2h`, `1d`) or whenever its five-field `cron` expression matches, in
+//! local time. Runs use the arguments' defaults. If the previous run is
+//! still going when the next one comes due, `overlap` says whether to
+//! `skip` it or `queue` one more run after it.
+//!
+//! ```yaml
+//! name: Fetch and summarize
+//! command: git fetch --all --quiet && git status --short --branch
+//! schedule:
+//!   every: 15m
+//!   overlap: skip
+//! ```
+//!
+//! Paused schedules are kept next to the workflows so that pausing from the
+//! CLI reaches a running app.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tokio::sync::mpsc;
+
+use super::{StepEvent, Workflow, WorkflowError, WorkflowExecutor};
+
+/// Shortest interval accepted for `every`
+pub const MIN_INTERVAL_SECS: i64 = 30;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowSchedule {
+    /// A number and a unit: `s`, `m`, `h` or `d`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every: Option<String>,
+    /// minute hour day-of-month month day-of-week
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+}
+
+/// What to do with a run that comes due while the last one is going
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlapPolicy {
+    #[default]
+    Skip,
+    /// Run once more when the current run ends; further runs are dropped
+    Queue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cadence {
+    Every(Duration),
+    Cron(CronExpr),
+}
+
+impl WorkflowSchedule {
+    pub fn cadence(&self) -> Result<Cadence, WorkflowError> {
+        match (&self.every, &self.cron) {
+            (Some(every), None) => {
+                let interval = parse_interval(every).ok_or_else(|| {
+                    WorkflowError::ValidationError(format!("Schedule interval '{}' is not like `15m`", every))
+                })?;
+                if interval < Duration::seconds(MIN_INTERVAL_SECS) {
+                    return Err(WorkflowError::ValidationError(format!(
+                        "Schedule interval must be at least {}s",
+                        MIN_INTERVAL_SECS
+                    )));
+                }
+                Ok(Cadence::Every(interval))
+            }
+            (None, Some(cron)) => CronExpr::parse(cron)
+                .map(Cadence::Cron)
+                .ok_or_else(|| WorkflowError::ValidationError(format!("'{}' is not a cron expression", cron))),
+            _ => Err(WorkflowError::ValidationError("Schedule needs exactly one of `every` or `cron`".to_string())),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match (&self.every, &self.cron) {
+            (Some(every), _) => format!("every {}", every),
+            (_, Some(cron)) => format!("cron {}", cron),
+            _ => String::new(),
+        }
+    }
+}
+
+impl Cadence {
+    /// The first run after `after`
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Cadence::Every(interval) => Some(after + *interval),
+            Cadence::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+/// `90s`, `15m`, `2h` or `1d`
+pub fn parse_interval(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let amount: i64 = text[..split].parse().ok()?;
+    match text[split..].trim() {
+        "s" => Some(Duration::seconds(amount)),
+        "m" => Some(Duration::minutes(amount)),
+        "h" => Some(Duration::hours(amount)),
+        "d" => Some(Duration::days(amount)),
+        _ => None,
+    }
+}
+
+/// A standard five-field cron expression. Each field takes `*`, numbers,
+/// ranges, lists and `/step`; day-of-week runs 0-7 with Sunday as 0 or 7.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronExpr {
+    pub fn parse(text: &str) -> Option<Self> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return None;
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Some(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    /// As cron does, a day matches either restricted day field
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// The first matching minute after `after`, within four years
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(4 * 366);
+        let mut time = start;
+        while time < limit {
+            let date = time.date();
+            if self.months & (1 << date.month()) == 0 {
+                let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(date) {
+                time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = start_of_hour(time) + Duration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+            } else if let Some(local) = Local.from_local_datetime(&time).earliest() {
+                return Some(local);
+            } else {
+                // Skipped by a daylight saving change
+                time += Duration::minutes(1);
+            }
+        }
+        None
+    }
+}
+
+fn start_of_hour(time: NaiveDateTime) -> NaiveDateTime {
+    time.date().and_hms_opt(time.hour(), 0, 0).unwrap_or(time)
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Option<u64> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<usize>().ok().filter(|step| *step > 0)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+            // `5/15` runs from 5 to the end of the range
+            None if part.contains('/') => (range.parse().ok()?, max),
+            None => {
+                let value = range.parse().ok()?;
+                (value, value)
+            }
+        };
+        if start < min || end > max || start > end {
+            return None;
+        }
+        for value in (start..=end).step_by(step) {
+            bits |= 1 << value;
+        }
+    }
+    Some(bits)
+}
+
+/// What became of a run that was asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Start,
+    /// Starts when the current run ends
+    Queued,
+    /// The workflow is running and skips overlapping runs
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpcomingRun {
+    pub name: String,
+    pub schedule: String,
+    pub next_run: Option<DateTime<Local>>,
+    pub paused: bool,
+    pub running: bool,
+    pub queued: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ScheduledEntry {
+    schedule: WorkflowSchedule,
+    cadence: Cadence,
+    next_run: Option<DateTime<Local>>,
+    running: bool,
+    queued: bool,
+}
+
+/// When each scheduled workflow runs next, and which are running
+#[derive(Debug, Default)]
+pub struct WorkflowScheduler {
+    entries: BTreeMap<String, ScheduledEntry>,
+    paused: BTreeSet<String>,
+}
+
+impl WorkflowScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track the schedules of `workflows`, keeping the state of those that
+    /// haven't changed. A new schedule first runs at its next time after `now`.
+    pub fn sync<'a>(&mut self, workflows: impl IntoIterator<Item = &'a Workflow>, now: DateTime<Local>) {
+        let mut seen = BTreeSet::new();
+        for workflow in workflows {
+            let Some(schedule) = &workflow.schedule else { continue };
+            let Ok(cadence) = schedule.cadence() else { continue };
+            seen.insert(workflow.name.clone());
+            match self.entries.get_mut(&workflow.name) {
+                Some(entry) if entry.cadence == cadence => entry.schedule = schedule.clone(),
+                Some(entry) => {
+                    entry.next_run = cadence.next_after(now);
+                    entry.cadence = cadence;
+                    entry.schedule = schedule.clone();
+                }
+                None => {
+                    self.entries.insert(workflow.name.clone(), ScheduledEntry {
+                        schedule: schedule.clone(),
+                        next_run: cadence.next_after(now),
+                        cadence,
+                        running: false,
+                        queued: false,
+                    });
+                }
+            }
+        }
+        self.entries.retain(|name, _| seen.contains(name));
+    }
+
+    pub fn is_scheduled(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub fn paused(&self) -> &BTreeSet<String> {
+        &self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: BTreeSet<String>) {
+        self.paused = paused;
+    }
+
+    /// Pause or resume a schedule. Paused runs are not made up on resume.
+    pub fn pause(&mut self, name: &str, paused: bool) {
+        if paused {
+            self.paused.insert(name.to_string());
+        } else {
+            self.paused.remove(name);
+        }
+    }
+
+    /// Workflows to start now. Their next run is set, whether or not this
+    /// one was skipped or queued.
+    pub fn due(&mut self, now: DateTime<Local>) -> Vec<String> {
+        let mut due = Vec::new();
+        for (name, entry) in &mut self.entries {
+            if !entry.next_run.is_some_and(|next| next <= now) {
+                continue;
+            }
+            entry.next_run = entry.cadence.next_after(now);
+            if !self.paused.contains(name) && Self::request(entry) == Trigger::Start {
+                due.push(name.clone());
+            }
+        }
+        due
+    }
+
+    /// Run a scheduled workflow now, outside its schedule
+    pub fn trigger(&mut self, name: &str) -> Option<Trigger> {
+        self.entries.get_mut(name).map(Self::request)
+    }
+
+    fn request(entry: &mut ScheduledEntry) -> Trigger {
+        if !entry.running {
+            entry.running = true;
+            return Trigger::Start;
+        }
+        match entry.schedule.overlap {
+            OverlapPolicy::Skip => Trigger::Skipped,
+            OverlapPolicy::Queue => {
+                entry.queued = true;
+                Trigger::Queued
+            }
+        }
+    }
+
+    /// A run ended; true if a queued run should start now
+    pub fn finished(&mut self, name: &str) -> bool {
+        let Some(entry) = self.entries.get_mut(name) else { return false };
+        entry.running = std::mem::take(&mut entry.queued);
+        entry.running
+    }
+
+    /// Every schedule, soonest first and paused ones last
+    pub fn upcoming(&self) -> Vec<UpcomingRun> {
+        let mut runs: Vec<UpcomingRun> = self
+            .entries
+            .iter()
+            .map(|(name, entry)| UpcomingRun {
+                name: name.clone(),
+                schedule: entry.schedule.describe(),
+                next_run: entry.next_run,
+                paused: self.paused.contains(name),
+                running: entry.running,
+                queued: entry.queued,
+            })
+            .collect();
+        runs.sort_by_key(|run| (run.paused, run.next_run.is_none(), run.next_run));
+        runs
+    }
+}
+
+/// Paused schedules, as stored next to the workflows
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScheduleState {
+    #[serde(default)]
+    pub paused: BTreeSet<String>,
+}
+
+/// Run `workflow` with `arguments`, defaults filling the rest, for a schedule
+/// or the palette. Returns the output of the command or every step, and the
+/// exit code.
+pub async fn run_in_background(
+    executor: WorkflowExecutor,
+    workflow: Workflow,
+    arguments: HashMap<String, String>,
+) -> Result<(String, i32), WorkflowError> {
+    let execution = executor.prepare_execution(&workflow, arguments)?;
+    if execution.steps.is_empty() {
+        let result = executor.execute_workflow(&execution).await?;
+        return Ok((format!("{}{}", result.output.stdout, result.output.stderr), result.output.exit_code));
+    }
+
+    let (tx, mut rx) = mpsc::channel(32);
+    let collect = async move {
+        let mut output = String::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                StepEvent::Started { name, .. } => output.push_str(&format!("▶ {}\n", name)),
+                StepEvent::Output { text, .. } => output.push_str(&text),
+                StepEvent::Finished { error: Some(error), .. } => output.push_str(&format!("✗ {}\n", error)),
+                StepEvent::Finished { .. } | StepEvent::Continuing { .. } => {}
+            }
+        }
+        output
+    };
+    let run = async move {
+        let report = executor.run_steps(&execution, &tx).await;
+        drop(tx);
+        report
+    };
+    let (report, output) = tokio::join!(run, collect);
+    Ok((output, if report?.success() { 0 } else { 1 }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Local> {
+        // A Wednesday
+        Local.with_ymd_and_hms(2025, 6, 11, hour, minute, 20).unwrap()
+    }
+
+    fn scheduled(name: &str, schedule: &str) -> Workflow {
+        Workflow::from_yaml(&format!("name: {}\ncommand: git fetch --all\nschedule:\n{}", name, schedule)).unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_run() {
+        let every_quarter = CronExpr::parse("*/15 * * * *").unwrap();
+        assert_eq!(every_quarter.next_after(at(9, 7)), Some(at(9, 15).with_second(0).unwrap()));
+        assert_eq!(every_quarter.next_after(at(9, 45)), Some(at(10, 0).with_second(0).unwrap()));
+
+        // Weekdays at 9:30; from Wednesday evening that's Thursday
+        let weekdays = CronExpr::parse("30 9 * * 1-5").unwrap();
+        let next = weekdays.next_after(at(18, 0)).unwrap();
+        assert_eq!((next.day(), next.hour(), next.minute()), (12, 9, 30));
+
+        // Sundays, written as 7
+        let sunday = CronExpr::parse("0 0 * * 7").unwrap().next_after(at(18, 0)).unwrap();
+        assert_eq!(sunday.weekday(), chrono::Weekday::Sun);
+
+        for invalid in ["* * * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *", "@hourly"] {
+            assert_eq!(CronExpr::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_schedule_validation() {
+        assert_eq!(parse_interval("15m"), Some(Duration::minutes(15)));
+        assert_eq!(parse_interval("15 minutes"), None);
+        assert!(Workflow::from_yaml("name: x\ncommand: ls\nschedule:\n  every: 10s\n").is_err());
+        assert!(Workflow::from_yaml("name: x\ncommand: ls\nschedule:\n  every: 1h\n  cron: '* * * * *'\n").is_err());
+
+        let workflow = scheduled("fetch", "  every: 15m\n  overlap: queue\n");
+        let reparsed = Workflow::from_yaml(&workflow.to_yaml().unwrap()).unwrap();
+        assert_eq!(reparsed.schedule, workflow.schedule);
+    }
+
+    #[test]
+    fn test_overlapping_runs_follow_policy() {
+        let skip = scheduled("skip", "  every: 15m\n");
+        let queue = scheduled("queue", "  every: 15m\n  overlap: queue\n");
+        let mut scheduler = WorkflowScheduler::new();
+        scheduler.sync([&skip, &queue], at(9, 0));
+        assert!(scheduler.due(at(9, 10)).is_empty());
+
+        assert_eq!(scheduler.due(at(9, 15)), vec!["queue", "skip"]);
+        // Both are still running at the next run
+        assert!(scheduler.due(at(9, 30)).is_empty());
+        assert!(!scheduler.finished("skip"));
+        assert!(scheduler.finished("queue"));
+        assert!(!scheduler.finished("queue"));
+
+        assert_eq!(scheduler.trigger("skip"), Some(Trigger::Start));
+        assert_eq!(scheduler.trigger("skip"), Some(Trigger::Skipped));
+        assert_eq!(scheduler.trigger("missing"), None);
+    }
+
+    #[test]
+    fn test_paused_schedules_do_not_run() {
+        let workflow = scheduled("fetch", "  every: 15m\n");
+        let mut scheduler = WorkflowScheduler::new();
+        scheduler.sync([&workflow], at(9, 0));
+        scheduler.pause("fetch", true);
+        assert!(scheduler.due(at(9, 15)).is_empty());
+        assert!(scheduler.upcoming()[0].paused);
+
+        // Missed runs are not made up
+        scheduler.pause("fetch", false);
+        assert!(scheduler.due(at(9, 20)).is_empty());
+        assert_eq!(scheduler.due(at(9, 31)), vec!["fetch"]);
+
+        scheduler.sync(&Vec::<Workflow>::new(), at(9, 31));
+        assert!(scheduler.upcoming().is_empty());
+    }
+}
@@ -0,0 +1,324 @@
+//! Workflows made of several steps.
+//!
+//! Instead of a single `command`, a workflow may list `steps`, run in order:
+//! shell commands, prompts to the assistant, files to write and conditions
+//! to wait for. Any text in a step may use `{{param}}` templates; values are
+//! shell-quoted in commands and inserted as they are everywhere else. A
+//! failing step stops the run unless it sets `continue_on_error`.
+//!
+//! ```yaml
+//! name: Start and check the dev server
+//! parameters:
+//!   - name: port
+//!     type: number
+//!     default: "8080"
+//! steps:
+//!   - type: command
+//!     run: docker compose up -d
+//!   - type: wait_for
+//!     port: "{{port}}"
+//!     timeout_secs: 60
+//!   - type: command
+//!     run: curl -sf http://localhost:{{port}}/health
+//!     continue_on_error: true
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::WorkflowError;
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+fn default_interval_secs() -> u64 {
+    2
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    /// Shown while the step runs; defaults to a description of what it does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub kind: StepKind,
+    /// Carry on with the next step if this one fails
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub continue_on_error: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepKind {
+    /// Run a shell command; fails on a non-zero exit code
+    Command { run: String },
+    /// Ask the assistant; the reply is the step's output
+    AiPrompt { prompt: String },
+    /// Write `content` to `path`, relative to the working directory
+    FileWrite {
+        path: String,
+        content: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        append: bool,
+    },
+    /// Check every `interval_secs` until exactly one of `command` (exits
+    /// with 0), `file` (exists) or `port` (accepts connections on
+    /// localhost) holds; fails after `timeout_secs`
+    WaitFor {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        command: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+        /// A string so it can be templated
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        port: Option<String>,
+        #[serde(default = "default_timeout_secs")]
+        timeout_secs: u64,
+        #[serde(default = "default_interval_secs")]
+        interval_secs: u64,
+    },
+}
+
+/// What a `wait_for` step waits for
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaitCondition {
+    Command(String),
+    File(PathBuf),
+    Port(u16),
+}
+
+impl WorkflowStep {
+    pub fn label(&self, index: usize) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let what = match &self.kind {
+            StepKind::Command { run } => format!("$ {}", run.lines().next().unwrap_or_default()),
+            StepKind::AiPrompt { .. } => "Ask the assistant".to_string(),
+            StepKind::FileWrite { path, .. } => format!("Write {}", path),
+            StepKind::WaitFor { .. } => match self.kind.wait_condition() {
+                Ok(WaitCondition::Command(command)) => format!("Wait for `{}`", command),
+                Ok(WaitCondition::File(path)) => format!("Wait for {}", path.display()),
+                Ok(WaitCondition::Port(port)) => format!("Wait for port {}", port),
+                Err(_) => "Wait".to_string(),
+            },
+        };
+        format!("Step {}: {}", index + 1, what)
+    }
+
+    /// The text fields of the step, which may hold templates
+    pub fn templates(&self) -> Vec<&str> {
+        let mut templates: Vec<&str> = self.name.iter().map(String::as_str).collect();
+        match &self.kind {
+            StepKind::Command { run } => templates.push(run),
+            StepKind::AiPrompt { prompt } => templates.push(prompt),
+            StepKind::FileWrite { path, content, .. } => templates.extend([path.as_str(), content.as_str()]),
+            StepKind::WaitFor { command, file, port, .. } => {
+                templates.extend([command, file, port].into_iter().flatten().map(String::as_str))
+            }
+        }
+        templates
+    }
+
+    /// The step with every template filled in by `fill`, which is told
+    /// whether the text is a shell command
+    pub fn render(
+        &self,
+        fill: impl Fn(&str, bool) -> Result<String, WorkflowError>,
+    ) -> Result<WorkflowStep, WorkflowError> {
+        let text = |value: &str| fill(value, false);
+        let kind = match &self.kind {
+            StepKind::Command { run } => StepKind::Command { run: fill(run, true)? },
+            StepKind::AiPrompt { prompt } => StepKind::AiPrompt { prompt: text(prompt)? },
+            StepKind::FileWrite { path, content, append } => StepKind::FileWrite {
+                path: text(path)?,
+                content: text(content)?,
+                append: *append,
+            },
+            StepKind::WaitFor { command, file, port, timeout_secs, interval_secs } => StepKind::WaitFor {
+                command: command.as_deref().map(|command| fill(command, true)).transpose()?,
+                file: file.as_deref().map(text).transpose()?,
+                port: port.as_deref().map(text).transpose()?,
+                timeout_secs: *timeout_secs,
+                interval_secs: *interval_secs,
+            },
+        };
+        Ok(WorkflowStep {
+            name: self.name.as_deref().map(text).transpose()?,
+            kind,
+            continue_on_error: self.continue_on_error,
+        })
+    }
+
+    pub fn validate(&self, index: usize) -> Result<(), WorkflowError> {
+        let invalid = |reason: &str| Err(WorkflowError::ValidationError(format!("Step {}: {}", index + 1, reason)));
+        match &self.kind {
+            StepKind::Command { run } if run.trim().is_empty() => invalid("command steps need `run`"),
+            StepKind::AiPrompt { prompt } if prompt.trim().is_empty() => invalid("ai_prompt steps need `prompt`"),
+            StepKind::FileWrite { path, .. } if path.trim().is_empty() => invalid("file_write steps need `path`"),
+            StepKind::WaitFor { command, file, port, interval_secs, .. } => {
+                let conditions = [command.is_some(), file.is_some(), port.is_some()].iter().filter(|set| **set).count();
+                if conditions != 1 {
+                    return invalid("wait_for steps need exactly one of `command`, `file` or `port`");
+                }
+                if *interval_secs == 0 {
+                    return invalid("`interval_secs` must be at least 1");
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl StepKind {
+    /// What a `wait_for` step waits for, once its templates are filled
+    pub fn wait_condition(&self) -> Result<WaitCondition, WorkflowError> {
+        let StepKind::WaitFor { command, file, port, .. } = self else {
+            return Err(WorkflowError::ValidationError("not a wait_for step".to_string()));
+        };
+        match (command, file, port) {
+            (Some(command), None, None) => Ok(WaitCondition::Command(command.clone())),
+            (None, Some(file), None) => Ok(WaitCondition::File(PathBuf::from(file))),
+            (None, None, Some(port)) => port
+                .trim()
+                .parse()
+                .map(WaitCondition::Port)
+                .map_err(|_| WorkflowError::InvalidArgumentValue(format!("'{}' is not a port number", port))),
+            _ => Err(WorkflowError::ValidationError(
+                "wait_for steps need exactly one of `command`, `file` or `port`".to_string(),
+            )),
+        }
+    }
+}
+
+/// Progress of a multi-step run, for the UI and CLI to show
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StepEvent {
+    Started { index: usize, name: String },
+    Output { index: usize, text: String },
+    Finished {
+        index: usize,
+        success: bool,
+        duration_ms: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// A failed step that sets `continue_on_error`
+    Continuing { index: usize },
+}
+
+/// How a multi-step run ended
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StepsReport {
+    /// Steps that ran, in order
+    pub completed: usize,
+    /// Steps that failed, including those allowed to
+    pub failed: Vec<usize>,
+    /// The failed step the run stopped at
+    pub stopped_at: Option<usize>,
+}
+
+impl StepsReport {
+    pub fn success(&self) -> bool {
+        self.stopped_at.is_none()
+    }
+}
+
+/// Write or append to `path` under `workdir`, creating its directory
+pub fn write_file(workdir: &Path, path: &str, content: &str, append: bool) -> Result<PathBuf, WorkflowError> {
+    use std::io::Write;
+
+    let path = workdir.join(path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| WorkflowError::IoError(e.to_string()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .map_err(|e| WorkflowError::IoError(format!("{}: {}", path.display(), e)))?;
+    file.write_all(content.as_bytes()).map_err(|e| WorkflowError::IoError(e.to_string()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflows::Workflow;
+
+    const SERVER_CHECK: &str = r#"
+name: Check server
+parameters:
+  - name: port
+    type: number
+    default: "8080"
+    required: true
+steps:
+  - type: command
+    run: ./serve --port {{port}}
+  - name: Wait for the server
+    type: wait_for
+    port: "{{port}}"
+    timeout_secs: 30
+  - type: ai_prompt
+    prompt: Summarize the health of the server on port {{port}}
+    continue_on_error: true
+  - type: file_write
+    path: logs/last-port
+    content: "{{port}}"
+    append: true
+"#;
+
+    #[test]
+    fn test_steps_round_trip_through_yaml() {
+        let workflow = Workflow::from_yaml(SERVER_CHECK).unwrap();
+        assert_eq!(workflow.steps.len(), 4);
+        assert_eq!(workflow.arguments[0].default_value.as_deref(), Some("8080"));
+        assert_eq!(workflow.steps[1].kind, StepKind::WaitFor {
+            command: None,
+            file: None,
+            port: Some("{{port}}".to_string()),
+            timeout_secs: 30,
+            interval_secs: 2,
+        });
+        assert!(workflow.steps[2].continue_on_error);
+
+        let yaml = workflow.to_yaml().unwrap();
+        assert!(!yaml.contains("command: ''"), "{}", yaml);
+        let reparsed = Workflow::from_yaml(&yaml).unwrap();
+        assert_eq!(reparsed.steps, workflow.steps);
+        assert_eq!(reparsed.arguments[0].arg_type, workflow.arguments[0].arg_type);
+    }
+
+    #[test]
+    fn test_invalid_steps_are_rejected() {
+        let no_condition = "name: x\nsteps:\n  - type: wait_for\n    timeout_secs: 5\n";
+        assert!(matches!(Workflow::from_yaml(no_condition), Err(WorkflowError::ValidationError(_))));
+
+        let both = "name: x\ncommand: ls\nsteps:\n  - type: command\n    run: ls\n";
+        assert!(matches!(Workflow::from_yaml(both), Err(WorkflowError::ValidationError(_))));
+
+        let unknown_param = "name: x\nsteps:\n  - type: command\n    run: echo {{missing}}\n";
+        assert!(matches!(Workflow::from_yaml(unknown_param), Err(WorkflowError::ValidationError(_))));
+
+        let unknown_type = "name: x\nsteps:\n  - type: teleport\n";
+        assert!(matches!(Workflow::from_yaml(unknown_type), Err(WorkflowError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_bundled_examples_parse() {
+        for yaml in [
+            include_str!("../../workflows/release-notes.yaml"),
+            include_str!("../../workflows/wait-for-service.yaml"),
+        ] {
+            let workflow = Workflow::from_yaml(yaml).unwrap();
+            assert!(!workflow.steps.is_empty(), "{}", workflow.name);
+            assert_eq!(Workflow::from_yaml(&workflow.to_yaml().unwrap()).unwrap().steps, workflow.steps);
+        }
+    }
+}
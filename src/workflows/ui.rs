@@ -1,5 +1,6 @@
 use iced::{Element, widget::{column, row, text, button, text_input, scrollable, container, pick_list}};
-use crate::workflows::{WorkflowManager, Workflow, WorkflowSearchResult, WorkflowCategory, Shell, WorkflowArgument, ArgumentType};
+use crate::workflows::{WorkflowManager, Workflow, WorkflowSearchResult, WorkflowCategory, Shell, WorkflowArgument, ArgumentType, WorkflowPermissions, Trust};
+use crate::workflows::remediation::{Remediation, StepFailure};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
@@ -14,6 +15,16 @@ pub struct WorkflowUI {
     show_workflow_details: bool,
     show_create_workflow: bool,
     new_workflow: Workflow,
+    failure_assist: Option<FailureAssist>,
+    chosen_remediation: Option<(Remediation, bool)>,
+}
+
+/// A failed step waiting for the user to pick a remediation
+#[derive(Debug, Clone)]
+pub struct FailureAssist {
+    pub failure: StepFailure,
+    pub options: Vec<Remediation>,
+    pub suggested_by_ai: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +44,7 @@ pub enum Message {
     ImportWorkflow(String),
     ExportWorkflow(String),
     RefreshWorkflows,
+    RemediationChosen(usize),
 }
 
 impl WorkflowUI {
@@ -60,10 +72,20 @@ impl WorkflowUI {
                 author_url: None,
                 shells: None,
                 arguments: Vec::new(),
+                steps: Vec::new(),
+                env: HashMap::new(),
+                env_profile: None,
+                cache: None,
+                schedule: None,
+                tee: None,
+                permissions: WorkflowPermissions::default(),
+                trust: Trust::Full,
                 file_path: None,
                 last_used: None,
                 usage_count: 0,
             },
+            failure_assist: None,
+            chosen_remediation: None,
         })
     }
 
@@ -133,10 +155,27 @@ impl WorkflowUI {
                 self.update_search_results();
                 None
             }
+            Message::RemediationChosen(index) => {
+                if let Some(assist) = self.failure_assist.take() {
+                    self.chosen_remediation = assist.options.get(index).cloned().map(|option| (option, assist.suggested_by_ai));
+                }
+                None
+            }
             _ => None,
         }
     }
 
+    /// Stop at a failed step until one of `assist.options` is picked
+    pub fn show_failure(&mut self, assist: FailureAssist) {
+        self.failure_assist = Some(assist);
+        self.chosen_remediation = None;
+    }
+
+    /// The remediation the user picked, and whether the assistant suggested it
+    pub fn take_remediation(&mut self) -> Option<(Remediation, bool)> {
+        self.chosen_remediation.take()
+    }
+
     fn update_search_results(&mut self) {
         self.search_results = if self.search_query.is_empty() {
             if let Some(category) = &self.selected_category {
@@ -172,6 +211,8 @@ impl WorkflowUI {
 
         if self.show_create_workflow {
             self.create_workflow_dialog()
+        } else if let Some(assist) = &self.failure_assist {
+            column![self.create_failure_assist(assist), scrollable(main_content)].spacing(8).into()
         } else {
             scrollable(main_content).into()
         }
@@ -372,7 +413,7 @@ impl WorkflowUI {
                     column![
                         text("Command:").size(14),
                         container(
-                            text(&workflow.command)
+                            text(workflow.summary())
                                 .style(|theme| iced::widget::text::Appearance {
                                     color: Some(theme.palette().text.scale_alpha(0.9)),
                                 })
@@ -428,6 +469,44 @@ impl WorkflowUI {
         }
     }
 
+    fn create_failure_assist<'a>(&self, assist: &'a FailureAssist) -> Element<'a, Message> {
+        let failure = &assist.failure;
+        let options = assist.options.iter().enumerate().map(|(index, option)| {
+            let choice = button(text(option.label())).on_press(Message::RemediationChosen(index));
+            match option.diff(&failure.command) {
+                Some(diff) => column![
+                    choice,
+                    text(diff).font(iced::Font::MONOSPACE).size(12),
+                ]
+                .spacing(4)
+                .into(),
+                None => choice.into(),
+            }
+        });
+
+        container(
+            column![
+                text(format!("Step failed with exit code {}", failure.exit_code)).size(16),
+                text(&failure.command).font(iced::Font::MONOSPACE).size(12),
+                text(&failure.stderr_tail)
+                    .font(iced::Font::MONOSPACE)
+                    .size(12)
+                    .style(|theme| iced::widget::text::Appearance {
+                        color: Some(theme.palette().danger),
+                    }),
+                if assist.suggested_by_ai {
+                    text("Suggested by the assistant. Nothing runs until you choose.").size(12)
+                } else {
+                    text("Choose how to continue.").size(12)
+                },
+                column(options.collect::<Vec<_>>()).spacing(8),
+            ]
+            .spacing(8)
+        )
+        .padding(12)
+        .into()
+    }
+
     fn create_argument_input(&self, arg: &WorkflowArgument) -> Element<Message> {
         let current_value = self.argument_values
             .get(&arg.name)